@@ -0,0 +1,45 @@
+//! The shared body of the programs' `migrate_account` instructions.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::{plan, Layout, MigrationError};
+
+/// Applies the migration `plan` finds for `account`, growing it first when
+/// the new layout needs the space, with `payer` covering the extra rent.
+/// Returns the versions it migrated from and to, for the program's
+/// `AccountMigrated` event. `E` is the program's error type, which names
+/// the `MigrationError`s its callers can see.
+pub fn migrate<'info, E>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    layouts: &[Layout],
+) -> Result<(u8, u8)>
+where
+    E: From<MigrationError>,
+    Error: From<E>,
+{
+    let plan = plan(layouts, &account.try_borrow_data()?).map_err(E::from)?;
+    if plan.grow > 0 {
+        let len = account.data_len() + plan.grow;
+        let rent = Rent::get()?
+            .minimum_balance(len)
+            .saturating_sub(account.lamports());
+        if rent > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    system_program::Transfer {
+                        from: payer.clone(),
+                        to: account.clone(),
+                    },
+                ),
+                rent,
+            )?;
+        }
+        account.realloc(len, true)?;
+    }
+    plan.apply(&mut account.try_borrow_mut_data()?);
+    Ok((plan.from, plan.to))
+}
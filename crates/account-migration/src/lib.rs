@@ -0,0 +1,225 @@
+//! Versioned account layouts and in-place migrations.
+//!
+//! Every program account stores a `layout_version` byte right after its
+//! 8-byte discriminator, ahead of all other fields, so the version can be
+//! read without knowing the rest of the layout. When a layout changes, the
+//! account type bumps `Versioned::LAYOUT_VERSION` and adds a `Step` that
+//! rewrites data from the previous version. Each program's
+//! `migrate_account` instruction finds the account's `Layout` by
+//! discriminator, grows the account by what the steps need and applies them
+//! in order, so existing PDAs keep their address across layout changes.
+//!
+//! Accounts written before their type had a `layout_version` are version 0:
+//! their first field sits where the version byte now is, so the byte can't
+//! tell them apart. Such types start `MIGRATIONS` with `ADD_LAYOUT_VERSION`
+//! and say how to recognise their old accounts, usually by `FIXED_SPACE`,
+//! since a version 0 account is exactly the steps' growth short of it.
+//!
+//! With the `anchor` feature, `migrate` is the body of those instructions.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(feature = "anchor")]
+mod anchor;
+
+use core::fmt;
+
+#[cfg(feature = "anchor")]
+pub use anchor::migrate;
+
+/// Bytes before `layout_version`: the account discriminator.
+pub const VERSION_OFFSET: usize = 8;
+
+/// Upgrade from one layout version to the next.
+pub struct Step {
+    /// Version this step upgrades from; it leaves the account at `from + 1`.
+    pub from: u8,
+    /// Bytes the new layout adds.
+    pub grow: usize,
+    /// Rewrites the account data in place. The last `grow` bytes are zeroed
+    /// new space; `layout_version` is set by the caller afterwards.
+    pub upgrade: fn(&mut [u8]),
+}
+
+impl fmt::Debug for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Step")
+            .field("from", &self.from)
+            .field("grow", &self.grow)
+            .finish()
+    }
+}
+
+/// The first step of types whose accounts predate `layout_version`: makes
+/// room for the byte by moving every field after the discriminator along.
+pub const ADD_LAYOUT_VERSION: Step = Step {
+    from: 0,
+    grow: 1,
+    upgrade: insert_layout_version,
+};
+
+fn insert_layout_version(data: &mut [u8]) {
+    let len = data.len();
+    data.copy_within(VERSION_OFFSET..len - 1, VERSION_OFFSET + 1);
+}
+
+/// An account type with a versioned layout.
+pub trait Versioned {
+    const LAYOUT_VERSION: u8;
+    /// Steps from older versions, ordered by `Step::from`.
+    const MIGRATIONS: &'static [Step] = &[];
+    /// Bytes every account of the type takes on the current layout, for
+    /// types whose size doesn't depend on their content.
+    const FIXED_SPACE: Option<usize> = None;
+
+    /// Whether `data` is an account from before the type had a
+    /// `layout_version`. By default, one of a fixed-size type that starts
+    /// its steps with `ADD_LAYOUT_VERSION` and is exactly their total growth
+    /// short of `FIXED_SPACE`; accounts only grow, so no later version can
+    /// have that length.
+    fn is_unversioned(data: &[u8]) -> bool {
+        match (Self::MIGRATIONS.first(), Self::FIXED_SPACE) {
+            (Some(first), Some(space)) if first.from == 0 => {
+                data.len() + growth(Self::MIGRATIONS) == space
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Bytes `steps` add between them.
+pub const fn growth(steps: &[Step]) -> usize {
+    let mut total = 0;
+    let mut i = 0;
+    while i < steps.len() {
+        total += steps[i].grow;
+        i += 1;
+    }
+    total
+}
+
+/// Migration table entry for one account type.
+pub struct Layout {
+    pub discriminator: [u8; 8],
+    pub version: u8,
+    pub steps: &'static [Step],
+    /// `Versioned::is_unversioned` of the type.
+    pub unversioned: fn(&[u8]) -> bool,
+}
+
+impl Layout {
+    pub fn new<T: Versioned>(discriminator: [u8; 8]) -> Self {
+        Layout {
+            discriminator,
+            version: T::LAYOUT_VERSION,
+            steps: T::MIGRATIONS,
+            unversioned: T::is_unversioned,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MigrationError {
+    /// The discriminator matches none of the layouts.
+    UnknownAccount,
+    /// The data ends before `layout_version`.
+    TooShort,
+    /// The account is already on the current layout.
+    UpToDate,
+    /// The account claims a version newer than the program knows.
+    NewerVersion(u8),
+    /// No step upgrades from this version.
+    MissingStep(u8),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::UnknownAccount => write!(f, "unknown account type"),
+            MigrationError::TooShort => write!(f, "account data too short"),
+            MigrationError::UpToDate => write!(f, "account already on the current layout"),
+            MigrationError::NewerVersion(v) => {
+                write!(f, "layout version {} is newer than the program's", v)
+            }
+            MigrationError::MissingStep(v) => write!(f, "no migration from layout version {}", v),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MigrationError {}
+
+/// The steps taking one account to its current layout.
+#[derive(Debug)]
+pub struct Plan<'a> {
+    pub from: u8,
+    pub to: u8,
+    /// Bytes the account must grow by before `apply`.
+    pub grow: usize,
+    steps: &'a [Step],
+}
+
+/// Reads the layout version of an account.
+pub fn layout_version(data: &[u8]) -> Result<u8, MigrationError> {
+    data.get(VERSION_OFFSET)
+        .copied()
+        .ok_or(MigrationError::TooShort)
+}
+
+/// Works out how to bring `data` up to its type's current layout.
+pub fn plan<'a>(layouts: &'a [Layout], data: &[u8]) -> Result<Plan<'a>, MigrationError> {
+    let version = layout_version(data)?;
+    let layout = layouts
+        .iter()
+        .find(|layout| data[..VERSION_OFFSET] == layout.discriminator)
+        .ok_or(MigrationError::UnknownAccount)?;
+    let version = if (layout.unversioned)(data) {
+        0
+    } else {
+        version
+    };
+    if version == layout.version {
+        return Err(MigrationError::UpToDate);
+    }
+    if version > layout.version {
+        return Err(MigrationError::NewerVersion(version));
+    }
+
+    let first = layout
+        .steps
+        .iter()
+        .position(|step| step.from == version)
+        .ok_or(MigrationError::MissingStep(version))?;
+    let count = (layout.version - version) as usize;
+    let steps = layout
+        .steps
+        .get(first..first + count)
+        .ok_or(MigrationError::MissingStep(version))?;
+    let mut grow = 0;
+    for (expected, step) in (version..).zip(steps) {
+        if step.from != expected {
+            return Err(MigrationError::MissingStep(expected));
+        }
+        grow += step.grow;
+    }
+
+    Ok(Plan {
+        from: version,
+        to: layout.version,
+        grow,
+        steps,
+    })
+}
+
+impl Plan<'_> {
+    /// Runs the steps over `data`, which must already have grown by
+    /// `self.grow` zeroed bytes, and stamps the new version.
+    pub fn apply(&self, data: &mut [u8]) {
+        let mut len = data.len() - self.grow;
+        for step in self.steps {
+            len += step.grow;
+            (step.upgrade)(&mut data[..len]);
+            data[VERSION_OFFSET] = step.from + 1;
+        }
+    }
+}
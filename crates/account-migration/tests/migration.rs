@@ -0,0 +1,191 @@
+use account_migration::{
+    layout_version, plan, Layout, MigrationError, Step, Versioned, ADD_LAYOUT_VERSION,
+};
+
+const DISCRIMINATOR: [u8; 8] = *b"testacct";
+
+/// Version 1: `layout_version`, `count: u32`.
+/// Version 2 inserts `flags: u8` before `count`.
+/// Version 3 appends `limit: u64`, defaulting to 100.
+struct Counter;
+
+fn insert_flags(data: &mut [u8]) {
+    data.copy_within(9..13, 10);
+    data[9] = 0;
+}
+
+fn append_limit(data: &mut [u8]) {
+    let at = data.len() - 8;
+    data[at..].copy_from_slice(&100u64.to_le_bytes());
+}
+
+impl Versioned for Counter {
+    const LAYOUT_VERSION: u8 = 3;
+    const MIGRATIONS: &'static [Step] = &[
+        Step {
+            from: 1,
+            grow: 1,
+            upgrade: insert_flags,
+        },
+        Step {
+            from: 2,
+            grow: 8,
+            upgrade: append_limit,
+        },
+    ];
+}
+
+/// No migrations yet.
+struct Fresh;
+
+impl Versioned for Fresh {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+const LEGACY: [u8; 8] = *b"legacyac";
+
+/// Predates layout versions. Version 0: `count: u32`, `owner: [u8; 4]`.
+/// Version 1 adds `layout_version`, and version 2 appends `limit: u64`.
+struct Legacy;
+
+impl Legacy {
+    const SPACE: usize = 8 + 1 + 4 + 4 + 8;
+}
+
+impl Versioned for Legacy {
+    const LAYOUT_VERSION: u8 = 2;
+    const MIGRATIONS: &'static [Step] = &[
+        ADD_LAYOUT_VERSION,
+        Step {
+            from: 1,
+            grow: 8,
+            upgrade: append_limit,
+        },
+    ];
+    const FIXED_SPACE: Option<usize> = Some(Legacy::SPACE);
+}
+
+fn layouts() -> [Layout; 3] {
+    [
+        Layout::new::<Fresh>(*b"freshacc"),
+        Layout::new::<Counter>(DISCRIMINATOR),
+        Layout::new::<Legacy>(LEGACY),
+    ]
+}
+
+fn account(version: u8, body: &[u8]) -> Vec<u8> {
+    let mut data = DISCRIMINATOR.to_vec();
+    data.push(version);
+    data.extend_from_slice(body);
+    data
+}
+
+fn migrate(layouts: &[Layout], data: &mut Vec<u8>) -> Result<(u8, u8), MigrationError> {
+    let plan = plan(layouts, data)?;
+    data.resize(data.len() + plan.grow, 0);
+    plan.apply(data);
+    Ok((plan.from, plan.to))
+}
+
+#[test]
+fn runs_every_step_from_the_oldest_version() {
+    let layouts = layouts();
+    let mut data = account(1, &7u32.to_le_bytes());
+    assert_eq!(migrate(&layouts, &mut data), Ok((1, 3)));
+
+    let mut expected = account(3, &[0]);
+    expected.extend_from_slice(&7u32.to_le_bytes());
+    expected.extend_from_slice(&100u64.to_le_bytes());
+    assert_eq!(data, expected);
+    assert_eq!(layout_version(&data), Ok(3));
+}
+
+#[test]
+fn starts_from_the_stored_version() {
+    let layouts = layouts();
+    let mut body = vec![5];
+    body.extend_from_slice(&7u32.to_le_bytes());
+    let mut data = account(2, &body);
+    let plan = plan(&layouts, &data).unwrap();
+    assert_eq!((plan.from, plan.grow), (2, 8));
+
+    data.resize(data.len() + plan.grow, 0);
+    plan.apply(&mut data);
+    assert_eq!(data[9], 5);
+    assert_eq!(&data[14..], &100u64.to_le_bytes());
+}
+
+#[test]
+fn a_migrated_account_is_up_to_date() {
+    let layouts = layouts();
+    let mut data = account(1, &[0; 4]);
+    migrate(&layouts, &mut data).unwrap();
+    assert_eq!(
+        migrate(&layouts, &mut data).unwrap_err(),
+        MigrationError::UpToDate
+    );
+
+    let mut fresh = b"freshacc".to_vec();
+    fresh.push(1);
+    assert_eq!(
+        plan(&layouts, &fresh).unwrap_err(),
+        MigrationError::UpToDate
+    );
+}
+
+#[test]
+fn rejects_accounts_it_cannot_migrate() {
+    let layouts = layouts();
+    assert_eq!(
+        plan(&layouts, &DISCRIMINATOR).unwrap_err(),
+        MigrationError::TooShort
+    );
+
+    let mut other = b"otheracc".to_vec();
+    other.push(1);
+    assert_eq!(
+        plan(&layouts, &other).unwrap_err(),
+        MigrationError::UnknownAccount
+    );
+    assert_eq!(
+        plan(&layouts, &account(4, &[])).unwrap_err(),
+        MigrationError::NewerVersion(4)
+    );
+    assert_eq!(
+        plan(&layouts, &account(0, &[])).unwrap_err(),
+        MigrationError::MissingStep(0)
+    );
+}
+
+#[test]
+fn moves_the_fields_of_unversioned_accounts_after_the_version() {
+    let layouts = layouts();
+    let mut data = LEGACY.to_vec();
+    data.extend_from_slice(&7u32.to_le_bytes());
+    data.extend_from_slice(b"ownr");
+    assert_eq!(data.len(), Legacy::SPACE - 9);
+    assert_eq!(migrate(&layouts, &mut data), Ok((0, 2)));
+
+    let mut expected = LEGACY.to_vec();
+    expected.push(2);
+    expected.extend_from_slice(&7u32.to_le_bytes());
+    expected.extend_from_slice(b"ownr");
+    expected.extend_from_slice(&100u64.to_le_bytes());
+    assert_eq!(data, expected);
+    assert_eq!(
+        migrate(&layouts, &mut data).unwrap_err(),
+        MigrationError::UpToDate
+    );
+}
+
+#[test]
+fn only_the_unversioned_length_is_version_0() {
+    let layouts = layouts();
+    // A version 1 account whose first byte after the discriminator happens
+    // to be 0, as an unversioned account's could be.
+    let mut data = LEGACY.to_vec();
+    data.push(1);
+    data.extend_from_slice(&[0; 8]);
+    let plan = plan(&layouts, &data).unwrap();
+    assert_eq!((plan.from, plan.grow), (1, 8));
+}
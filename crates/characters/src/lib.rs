@@ -0,0 +1,489 @@
+//! Binary character definition format.
+//!
+//! Layout (all integers little-endian, `Fx32` values as their raw `i32`):
+//!
+//! ```text
+//! header  magic        [u8; 4]  "DFGC"
+//!         version      u16      format version, currently 3
+//! body    id           u8       character id
+//!         revision     u16      balance revision, bumped on every change
+//!         name_len     u8
+//!         name         [u8; name_len]  UTF-8
+//!         max_health   i32
+//!         walk_speed, jump_force, gravity, max_fall_speed,
+//!         ground_friction, air_friction    Fx32 each
+//!         hurtbox      rect
+//!         move_count   u8
+//!         moves        move_count x move, ascending by id
+//!         cancel_count u8
+//!         cancels      cancel_count x (from u8, to u8), ascending
+//!
+//! rect    offset_x, offset_y, width, height  Fx32 each
+//! move    id, startup, active, recovery      u8 each
+//!         damage, chip_damage                i32 each
+//!         hitstun, blockstun                 u16 each
+//!         pushback, launch                   Fx32 each
+//!         juggle_cost                        u8
+//!         hitbox                             rect
+//!         priority                           u8
+//!         has_hurtbox u8 (0 or 1), then rect when set
+//!         has_projectile u8 (0 or 1), then speed Fx32, lifetime u16,
+//!                                          hitbox rect when set
+//!         meter_on_hit, meter_on_block, meter_on_whiff, meter_cost  u16 each
+//!         super_freeze                       u8
+//! ```
+//!
+//! Decoding only accepts the canonical encoding of a definition: entries in
+//! order, flags of 0 or 1, no trailing bytes. Every definition therefore
+//! has exactly one byte form, and its SHA-256 is the hash anchored on-chain
+//! by `move_registry::set_definition_hash`, so clients and verifiers can
+//! check they simulate the same character version.
+
+use core::fmt;
+
+use game_core::{Cancel, CharacterDef, Fx32, MoveDef, MoveMeter, ProjectileDef, Rect};
+use sha2::{Digest, Sha256};
+
+pub const MAGIC: [u8; 4] = *b"DFGC";
+pub const FORMAT_VERSION: u16 = 3;
+pub const MAX_NAME_LEN: usize = u8::MAX as usize;
+pub const MAX_MOVES: usize = u8::MAX as usize;
+pub const MAX_CANCELS: usize = u8::MAX as usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DefinitionError {
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// Bytes left over after the last cancel.
+    TrailingBytes,
+    InvalidName,
+    NameTooLong,
+    TooManyMoves,
+    TooManyCancels,
+    DuplicateMove(u8),
+    /// A move has no active frames.
+    InvalidMove(u8),
+    /// A cancel refers to a move the character does not have.
+    UnknownMove(u8),
+    /// Moves or cancels are not in canonical order.
+    Unsorted,
+    /// An option flag other than 0 or 1.
+    InvalidFlag(u8),
+}
+
+impl fmt::Display for DefinitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DefinitionError::Truncated => write!(f, "character definition truncated"),
+            DefinitionError::BadMagic => write!(f, "not a character definition"),
+            DefinitionError::UnsupportedVersion(v) => {
+                write!(f, "unsupported definition version {}", v)
+            }
+            DefinitionError::TrailingBytes => write!(f, "trailing bytes after definition"),
+            DefinitionError::InvalidName => write!(f, "character name is not valid UTF-8"),
+            DefinitionError::NameTooLong => write!(f, "character name too long"),
+            DefinitionError::TooManyMoves => write!(f, "character has too many moves"),
+            DefinitionError::TooManyCancels => write!(f, "character has too many cancels"),
+            DefinitionError::DuplicateMove(id) => write!(f, "move {} defined twice", id),
+            DefinitionError::InvalidMove(id) => write!(f, "move {} has no active frames", id),
+            DefinitionError::UnknownMove(id) => write!(f, "cancel refers to unknown move {}", id),
+            DefinitionError::Unsorted => write!(f, "entries not in canonical order"),
+            DefinitionError::InvalidFlag(flag) => write!(f, "invalid option flag {}", flag),
+        }
+    }
+}
+
+impl std::error::Error for DefinitionError {}
+
+/// Owned, validated character definition. Moves are kept sorted by id and
+/// cancels sorted by `(from, to)`, so encoding is canonical.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CharacterData {
+    revision: u16,
+    id: u8,
+    name: String,
+    max_health: i32,
+    walk_speed: Fx32,
+    jump_force: Fx32,
+    gravity: Fx32,
+    max_fall_speed: Fx32,
+    ground_friction: Fx32,
+    air_friction: Fx32,
+    hurtbox: Rect,
+    moves: Vec<MoveDef>,
+    cancels: Vec<Cancel>,
+}
+
+impl CharacterData {
+    /// Copies and validates a definition, putting its moves and cancels in
+    /// canonical order. Repeated cancels are collapsed.
+    pub fn new(def: &CharacterDef, revision: u16) -> Result<Self, DefinitionError> {
+        let mut moves = def.moves.to_vec();
+        moves.sort_by_key(|m| m.id);
+        let mut cancels = def.cancels.to_vec();
+        cancels.sort_by_key(|c| (c.from, c.to));
+        cancels.dedup();
+
+        if let Some(pair) = moves.windows(2).find(|pair| pair[0].id == pair[1].id) {
+            return Err(DefinitionError::DuplicateMove(pair[0].id));
+        }
+        let data = CharacterData {
+            revision,
+            id: def.id,
+            name: def.name.to_string(),
+            max_health: def.max_health,
+            walk_speed: def.walk_speed,
+            jump_force: def.jump_force,
+            gravity: def.gravity,
+            max_fall_speed: def.max_fall_speed,
+            ground_friction: def.ground_friction,
+            air_friction: def.air_friction,
+            hurtbox: def.hurtbox,
+            moves,
+            cancels,
+        };
+        data.validate()?;
+        Ok(data)
+    }
+
+    /// Borrows the definition in the form the simulation takes.
+    pub fn as_def(&self) -> CharacterDef<'_> {
+        CharacterDef {
+            id: self.id,
+            name: &self.name,
+            max_health: self.max_health,
+            walk_speed: self.walk_speed,
+            jump_force: self.jump_force,
+            gravity: self.gravity,
+            max_fall_speed: self.max_fall_speed,
+            ground_friction: self.ground_friction,
+            air_friction: self.air_friction,
+            hurtbox: self.hurtbox,
+            moves: &self.moves,
+            cancels: &self.cancels,
+        }
+    }
+
+    pub fn revision(&self) -> u16 {
+        self.revision
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Writer::default();
+        out.bytes(&MAGIC);
+        out.u16(FORMAT_VERSION);
+        out.u8(self.id);
+        out.u16(self.revision);
+        out.u8(self.name.len() as u8);
+        out.bytes(self.name.as_bytes());
+        out.i32(self.max_health);
+        for value in [
+            self.walk_speed,
+            self.jump_force,
+            self.gravity,
+            self.max_fall_speed,
+            self.ground_friction,
+            self.air_friction,
+        ] {
+            out.fx(value);
+        }
+        out.rect(&self.hurtbox);
+
+        out.u8(self.moves.len() as u8);
+        for m in &self.moves {
+            out.bytes(&[m.id, m.startup, m.active, m.recovery]);
+            out.i32(m.damage);
+            out.i32(m.chip_damage);
+            out.u16(m.hitstun);
+            out.u16(m.blockstun);
+            out.fx(m.pushback);
+            out.fx(m.launch);
+            out.u8(m.juggle_cost);
+            out.rect(&m.hitbox);
+            out.u8(m.priority);
+            out.u8(m.hurtbox.is_some() as u8);
+            if let Some(hurtbox) = &m.hurtbox {
+                out.rect(hurtbox);
+            }
+            out.u8(m.projectile.is_some() as u8);
+            if let Some(projectile) = &m.projectile {
+                out.fx(projectile.speed);
+                out.u16(projectile.lifetime);
+                out.rect(&projectile.hitbox);
+            }
+            for value in [
+                m.meter.on_hit,
+                m.meter.on_block,
+                m.meter.on_whiff,
+                m.meter.cost,
+            ] {
+                out.u16(value);
+            }
+            out.u8(m.meter.super_freeze);
+        }
+
+        out.u8(self.cancels.len() as u8);
+        for cancel in &self.cancels {
+            out.bytes(&[cancel.from, cancel.to]);
+        }
+        out.0
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, DefinitionError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take::<4>()? != MAGIC {
+            return Err(DefinitionError::BadMagic);
+        }
+        let version = reader.u16()?;
+        if version != FORMAT_VERSION {
+            return Err(DefinitionError::UnsupportedVersion(version));
+        }
+        let id = reader.u8()?;
+        let revision = reader.u16()?;
+        let name_len = reader.u8()? as usize;
+        let name = core::str::from_utf8(reader.slice(name_len)?)
+            .map_err(|_| DefinitionError::InvalidName)?
+            .to_string();
+        let max_health = reader.i32()?;
+        let walk_speed = reader.fx()?;
+        let jump_force = reader.fx()?;
+        let gravity = reader.fx()?;
+        let max_fall_speed = reader.fx()?;
+        let ground_friction = reader.fx()?;
+        let air_friction = reader.fx()?;
+        let hurtbox = reader.rect()?;
+
+        let move_count = reader.u8()?;
+        let moves = (0..move_count)
+            .map(|_| reader.move_def())
+            .collect::<Result<Vec<_>, _>>()?;
+        let cancel_count = reader.u8()?;
+        let cancels = (0..cancel_count)
+            .map(|_| {
+                let [from, to] = reader.take()?;
+                Ok(Cancel { from, to })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if reader.pos != bytes.len() {
+            return Err(DefinitionError::TrailingBytes);
+        }
+
+        if let Some(pair) = moves.windows(2).find(|pair| pair[0].id >= pair[1].id) {
+            return Err(if pair[0].id == pair[1].id {
+                DefinitionError::DuplicateMove(pair[0].id)
+            } else {
+                DefinitionError::Unsorted
+            });
+        }
+        if cancels
+            .windows(2)
+            .any(|pair| (pair[0].from, pair[0].to) >= (pair[1].from, pair[1].to))
+        {
+            return Err(DefinitionError::Unsorted);
+        }
+
+        let data = CharacterData {
+            revision,
+            id,
+            name,
+            max_health,
+            walk_speed,
+            jump_force,
+            gravity,
+            max_fall_speed,
+            ground_friction,
+            air_friction,
+            hurtbox,
+            moves,
+            cancels,
+        };
+        data.validate()?;
+        Ok(data)
+    }
+
+    /// SHA-256 of the canonical encoding, as anchored on-chain.
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::digest(self.encode()).into()
+    }
+
+    fn validate(&self) -> Result<(), DefinitionError> {
+        if self.name.len() > MAX_NAME_LEN {
+            return Err(DefinitionError::NameTooLong);
+        }
+        if self.moves.len() > MAX_MOVES {
+            return Err(DefinitionError::TooManyMoves);
+        }
+        if self.cancels.len() > MAX_CANCELS {
+            return Err(DefinitionError::TooManyCancels);
+        }
+        if let Some(m) = self.moves.iter().find(|m| m.active == 0) {
+            return Err(DefinitionError::InvalidMove(m.id));
+        }
+        let def = self.as_def();
+        for cancel in &self.cancels {
+            for id in [cancel.from, cancel.to] {
+                if def.find_move(id).is_none() {
+                    return Err(DefinitionError::UnknownMove(id));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A decoded definition together with its on-chain hash.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadedCharacter {
+    pub data: CharacterData,
+    pub hash: [u8; 32],
+}
+
+/// Decodes a definition file and hashes it. Since only canonical encodings
+/// decode, the hash of the file equals the hash of the definition.
+pub fn load(bytes: &[u8]) -> Result<LoadedCharacter, DefinitionError> {
+    let data = CharacterData::decode(bytes)?;
+    Ok(LoadedCharacter {
+        data,
+        hash: Sha256::digest(bytes).into(),
+    })
+}
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn fx(&mut self, value: Fx32) {
+        self.i32(value.raw());
+    }
+
+    fn rect(&mut self, rect: &Rect) {
+        for value in [rect.offset_x, rect.offset_y, rect.width, rect.height] {
+            self.fx(value);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn slice(&mut self, len: usize) -> Result<&'a [u8], DefinitionError> {
+        let end = self.pos + len;
+        let slice = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(DefinitionError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DefinitionError> {
+        let mut out = [0; N];
+        out.copy_from_slice(self.slice(N)?);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, DefinitionError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DefinitionError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn i32(&mut self) -> Result<i32, DefinitionError> {
+        Ok(i32::from_le_bytes(self.take()?))
+    }
+
+    fn fx(&mut self) -> Result<Fx32, DefinitionError> {
+        Ok(Fx32::from_raw(self.i32()?))
+    }
+
+    fn flag(&mut self) -> Result<bool, DefinitionError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            flag => Err(DefinitionError::InvalidFlag(flag)),
+        }
+    }
+
+    fn rect(&mut self) -> Result<Rect, DefinitionError> {
+        Ok(Rect {
+            offset_x: self.fx()?,
+            offset_y: self.fx()?,
+            width: self.fx()?,
+            height: self.fx()?,
+        })
+    }
+
+    fn move_def(&mut self) -> Result<MoveDef, DefinitionError> {
+        let [id, startup, active, recovery] = self.take()?;
+        let damage = self.i32()?;
+        let chip_damage = self.i32()?;
+        let hitstun = self.u16()?;
+        let blockstun = self.u16()?;
+        let pushback = self.fx()?;
+        let launch = self.fx()?;
+        let juggle_cost = self.u8()?;
+        let hitbox = self.rect()?;
+        let priority = self.u8()?;
+        let hurtbox = if self.flag()? {
+            Some(self.rect()?)
+        } else {
+            None
+        };
+        let projectile = if self.flag()? {
+            Some(ProjectileDef {
+                speed: self.fx()?,
+                lifetime: self.u16()?,
+                hitbox: self.rect()?,
+            })
+        } else {
+            None
+        };
+        let meter = MoveMeter {
+            on_hit: self.u16()?,
+            on_block: self.u16()?,
+            on_whiff: self.u16()?,
+            cost: self.u16()?,
+            super_freeze: self.u8()?,
+        };
+        Ok(MoveDef {
+            id,
+            startup,
+            active,
+            recovery,
+            damage,
+            chip_damage,
+            hitstun,
+            blockstun,
+            pushback,
+            launch,
+            juggle_cost,
+            hitbox,
+            priority,
+            hurtbox,
+            projectile,
+            meter,
+        })
+    }
+}
@@ -0,0 +1,119 @@
+use characters::{load, CharacterData, DefinitionError};
+use game_core::character::{MOVE_LIGHT, MOVE_SPECIAL, ROSTER};
+use game_core::{step, Cancel, CharacterDef, GameState, Input};
+
+#[test]
+fn roster_round_trips() {
+    for def in &ROSTER {
+        let data = CharacterData::new(def, 1).unwrap();
+        let bytes = data.encode();
+        let loaded = load(&bytes).unwrap();
+        assert_eq!(loaded.data, data);
+        assert_eq!(loaded.data.as_def(), *def);
+        assert_eq!(loaded.hash, data.hash());
+    }
+}
+
+#[test]
+fn hash_is_independent_of_authoring_order() {
+    let mut moves = ROSTER[0].moves.to_vec();
+    moves.reverse();
+    let cancels = [
+        Cancel {
+            from: MOVE_LIGHT,
+            to: MOVE_SPECIAL,
+        },
+        Cancel {
+            from: MOVE_LIGHT,
+            to: MOVE_SPECIAL,
+        },
+    ];
+    let def = CharacterDef {
+        moves: &moves,
+        cancels: &cancels,
+        ..ROSTER[0]
+    };
+    let sorted = CharacterDef {
+        cancels: &cancels[..1],
+        ..ROSTER[0]
+    };
+    let a = CharacterData::new(&def, 3).unwrap();
+    let b = CharacterData::new(&sorted, 3).unwrap();
+    assert_eq!(a.hash(), b.hash());
+    assert_ne!(a.hash(), CharacterData::new(&sorted, 4).unwrap().hash());
+}
+
+#[test]
+fn rejects_non_canonical_bytes() {
+    let bytes = CharacterData::new(&ROSTER[0], 1).unwrap().encode();
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(load(&trailing), Err(DefinitionError::TrailingBytes));
+    assert_eq!(
+        load(&bytes[..bytes.len() - 1]),
+        Err(DefinitionError::Truncated)
+    );
+
+    let mut version = bytes.clone();
+    version[4] = 2;
+    assert_eq!(load(&version), Err(DefinitionError::UnsupportedVersion(2)));
+
+    // The first move's hurtbox flag sits after its 42 bytes of frame data.
+    let name_len = bytes[9] as usize;
+    let first_move = 10 + name_len + 4 + 6 * 4 + 16 + 1;
+    let mut flag = bytes.clone();
+    flag[first_move + 42] = 2;
+    assert_eq!(load(&flag), Err(DefinitionError::InvalidFlag(2)));
+
+    let mut duplicate = bytes.clone();
+    duplicate[first_move] = MOVE_SPECIAL;
+    assert_eq!(
+        load(&duplicate),
+        Err(DefinitionError::DuplicateMove(MOVE_SPECIAL))
+    );
+}
+
+#[test]
+fn rejects_cancels_into_unknown_moves() {
+    let cancels = [Cancel {
+        from: MOVE_LIGHT,
+        to: 9,
+    }];
+    let def = CharacterDef {
+        cancels: &cancels,
+        ..ROSTER[0]
+    };
+    assert_eq!(
+        CharacterData::new(&def, 1),
+        Err(DefinitionError::UnknownMove(9))
+    );
+}
+
+#[test]
+fn loaded_cancels_apply_in_simulation() {
+    let cancels = [Cancel {
+        from: MOVE_LIGHT,
+        to: MOVE_SPECIAL,
+    }];
+    let def = CharacterDef {
+        cancels: &cancels,
+        ..ROSTER[0]
+    };
+    let loaded = load(&CharacterData::new(&def, 1).unwrap().encode()).unwrap();
+    let ronin = loaded.data.as_def();
+    let defs = [&ronin, &ROSTER[0]];
+
+    let mut state = GameState::new(defs[0], defs[1]);
+    // Walk in until the light attack reaches.
+    for _ in 0..55 {
+        state = step(&state, [Input::RIGHT, Input::NONE], defs);
+    }
+    state = step(&state, [Input::ATTACK, Input::NONE], defs);
+    while !state.players[0].move_connected {
+        assert_eq!(state.players[0].current_move, Some(MOVE_LIGHT));
+        state = step(&state, [Input::NONE; 2], defs);
+    }
+    state = step(&state, [Input::SPECIAL, Input::NONE], defs);
+    assert_eq!(state.players[0].current_move, Some(MOVE_SPECIAL));
+}
@@ -0,0 +1,96 @@
+//! Overflow-checked arithmetic for on-chain counters and balances.
+//!
+//! Release builds of the programs enable `overflow-checks`, which turns an
+//! overflowing `+=` into a panic: the transaction still fails, but with an
+//! opaque error and after burning its compute. Every counter, tally and
+//! lamport balance the programs update goes through these helpers instead,
+//! which leave the value untouched and return `Overflow`, for the program to
+//! surface as its own error variant.
+//!
+//! `move_lamports` checks both sides of a transfer before writing either, so
+//! a failed transfer never leaves lamports created or destroyed.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Overflow;
+
+impl fmt::Display for Overflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "arithmetic overflow")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Overflow {}
+
+/// Integer types the helpers work on.
+pub trait Checked: Copy {
+    const ONE: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_checked {
+    ($($t:ty),*) => {$(
+        impl Checked for $t {
+            const ONE: Self = 1;
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+        }
+    )*};
+}
+
+impl_checked!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+pub fn add<T: Checked>(a: T, b: T) -> Result<T, Overflow> {
+    a.checked_add(b).ok_or(Overflow)
+}
+
+pub fn sub<T: Checked>(a: T, b: T) -> Result<T, Overflow> {
+    a.checked_sub(b).ok_or(Overflow)
+}
+
+pub fn mul<T: Checked>(a: T, b: T) -> Result<T, Overflow> {
+    a.checked_mul(b).ok_or(Overflow)
+}
+
+/// `*value += rhs`, leaving `value` unchanged on overflow.
+pub fn add_assign<T: Checked>(value: &mut T, rhs: T) -> Result<(), Overflow> {
+    *value = add(*value, rhs)?;
+    Ok(())
+}
+
+/// `*value -= rhs`, leaving `value` unchanged on underflow.
+pub fn sub_assign<T: Checked>(value: &mut T, rhs: T) -> Result<(), Overflow> {
+    *value = sub(*value, rhs)?;
+    Ok(())
+}
+
+/// `*value += 1`, leaving `value` unchanged at the type's maximum.
+pub fn increment<T: Checked>(value: &mut T) -> Result<(), Overflow> {
+    add_assign(value, T::ONE)
+}
+
+/// Moves `amount` from `from` to `to`, or neither balance changes.
+pub fn move_lamports(from: &mut u64, to: &mut u64, amount: u64) -> Result<(), Overflow> {
+    let new_from = sub(*from, amount)?;
+    let new_to = add(*to, amount)?;
+    *from = new_from;
+    *to = new_to;
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use checked_math::{add, add_assign, increment, move_lamports, mul, sub, sub_assign, Overflow};
+use proptest::prelude::*;
+
+#[test]
+fn counters_stop_at_the_maximum() {
+    let mut count = u32::MAX - 1;
+    assert_eq!(increment(&mut count), Ok(()));
+    assert_eq!(increment(&mut count), Err(Overflow));
+    assert_eq!(count, u32::MAX);
+
+    let mut rating = i64::MIN;
+    assert_eq!(sub_assign(&mut rating, 1), Err(Overflow));
+    assert_eq!(rating, i64::MIN);
+}
+
+#[test]
+fn move_lamports_keeps_the_total_on_failure() {
+    let (mut from, mut to) = (10, u64::MAX - 5);
+    assert_eq!(move_lamports(&mut from, &mut to, 6), Err(Overflow));
+    assert_eq!((from, to), (10, u64::MAX - 5));
+
+    let (mut from, mut to) = (3, 0);
+    assert_eq!(move_lamports(&mut from, &mut to, 4), Err(Overflow));
+    assert_eq!((from, to), (3, 0));
+}
+
+/// Values at and around the edges of each type, where counters overflow.
+fn boundary_u64() -> impl Strategy<Value = u64> {
+    prop_oneof![
+        Just(0),
+        Just(1),
+        Just(u64::MAX),
+        Just(u64::MAX - 1),
+        Just(u64::MAX / 2),
+        any::<u64>(),
+    ]
+}
+
+fn boundary_u32() -> impl Strategy<Value = u32> {
+    prop_oneof![
+        Just(0),
+        Just(1),
+        Just(u32::MAX),
+        Just(u32::MAX - 1),
+        any::<u32>(),
+    ]
+}
+
+fn boundary_i64() -> impl Strategy<Value = i64> {
+    prop_oneof![
+        Just(0),
+        Just(-1),
+        Just(1),
+        Just(i64::MIN),
+        Just(i64::MAX),
+        any::<i64>(),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn add_matches_wide_arithmetic(a in boundary_u64(), b in boundary_u64()) {
+        let wide = a as u128 + b as u128;
+        match add(a, b) {
+            Ok(sum) => prop_assert_eq!(sum as u128, wide),
+            Err(Overflow) => prop_assert!(wide > u64::MAX as u128),
+        }
+    }
+
+    #[test]
+    fn sub_matches_wide_arithmetic(a in boundary_i64(), b in boundary_i64()) {
+        let wide = a as i128 - b as i128;
+        match sub(a, b) {
+            Ok(diff) => prop_assert_eq!(diff as i128, wide),
+            Err(Overflow) => prop_assert!(wide < i64::MIN as i128 || wide > i64::MAX as i128),
+        }
+    }
+
+    #[test]
+    fn mul_matches_wide_arithmetic(a in boundary_u32(), b in boundary_u32()) {
+        let wide = a as u64 * b as u64;
+        match mul(a, b) {
+            Ok(product) => prop_assert_eq!(product as u64, wide),
+            Err(Overflow) => prop_assert!(wide > u32::MAX as u64),
+        }
+    }
+
+    #[test]
+    fn assign_helpers_leave_the_value_on_failure(start in boundary_u32(), rhs in boundary_u32()) {
+        let mut value = start;
+        if add_assign(&mut value, rhs).is_err() {
+            prop_assert_eq!(value, start);
+        }
+        let mut value = start;
+        if sub_assign(&mut value, rhs).is_err() {
+            prop_assert_eq!(value, start);
+        }
+        let mut value = start;
+        match increment(&mut value) {
+            Ok(()) => prop_assert_eq!(value, start + 1),
+            Err(Overflow) => prop_assert_eq!(value, u32::MAX),
+        }
+    }
+
+    #[test]
+    fn move_lamports_conserves_the_total(
+        from in boundary_u64(),
+        to in boundary_u64(),
+        amount in boundary_u64(),
+    ) {
+        let (mut new_from, mut new_to) = (from, to);
+        let moved = move_lamports(&mut new_from, &mut new_to, amount);
+        prop_assert_eq!(
+            new_from as u128 + new_to as u128,
+            from as u128 + to as u128
+        );
+        if moved.is_ok() {
+            prop_assert_eq!(new_from, from - amount);
+        } else {
+            prop_assert_eq!((new_from, new_to), (from, to));
+        }
+    }
+}
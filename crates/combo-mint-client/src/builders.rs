@@ -0,0 +1,386 @@
+//! Instructions for the calls bots and tooling make most, with every
+//! account that can be derived filled in. Anything else goes through
+//! `crate::instruction` with the program's generated types.
+
+use anchor_lang::solana_program::{system_program, sysvar};
+use combo_mint::combo_sim::simulate_combo;
+use combo_mint::{compute_combo_seed, ComboAccount, RewardConfig};
+use move_registry::CharacterFrameData;
+use referral::Referral;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use tournament::BracketFormat;
+
+use crate::{instruction, pda};
+
+/// Arguments of `combo_mint::create_combo`.
+#[derive(Clone, Debug)]
+pub struct NewCombo {
+    pub name: String,
+    pub damage: u32,
+    pub move_count: u8,
+    pub character_id: u8,
+    pub moves: Vec<u8>,
+    pub royalty_bps: u16,
+    pub tags: u32,
+}
+
+/// The `combo_hash` `create_combo` will give `combo`, simulated against the
+/// character's fetched `frame_data` the way the program does.
+pub fn new_combo_hash(
+    frame_data: &CharacterFrameData,
+    combo: &NewCombo,
+) -> anchor_lang::Result<[u8; 32]> {
+    let meter_gain = simulate_combo(frame_data, &combo.moves)?.meter_gain;
+    Ok(compute_combo_seed(
+        combo.name.as_bytes(),
+        combo.damage,
+        meter_gain,
+        combo.move_count,
+        combo.character_id,
+        &combo.moves,
+    ))
+}
+
+/// `combo_mint::create_combo`. `combo_hash` is the hash the program will
+/// compute for the combo, which addresses its `ComboHashIndex`; see
+/// `new_combo_hash`.
+pub fn create_combo(authority: Pubkey, combo: NewCombo, combo_hash: &[u8; 32]) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::CreateCombo {
+            authority,
+            combo_pda: pda::combo_mint::combo(&authority, &combo.name),
+            name_index: pda::combo_mint::name_index(&authority, &combo.name),
+            name_filter: pda::combo_mint::name_filter(),
+            frame_data: pda::move_registry::frame_data(combo.character_id),
+            config: pda::combo_mint::config(),
+            character_gate: pda::combo_mint::character_gate(combo.character_id),
+            hash_index: pda::combo_mint::hash_index(combo_hash),
+            character_index: pda::combo_mint::character_index(combo.character_id),
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+            event_authority: pda::combo_mint::event_authority(),
+            program: combo_mint::ID,
+        },
+        combo_mint::instruction::CreateCombo {
+            combo_name: combo.name,
+            damage: combo.damage,
+            move_count: combo.move_count,
+            character_id: combo.character_id,
+            moves: combo.moves,
+            royalty_bps: combo.royalty_bps,
+            tags: combo.tags,
+        },
+    )
+}
+
+/// `combo_mint::verify_combo` for `combo` at `combo_address`, paying the
+/// reward described by the fetched `rewards` into `verifier_token`.
+pub fn verify_combo(
+    verifier: Pubkey,
+    combo_address: Pubkey,
+    combo: &ComboAccount,
+    rewards: &RewardConfig,
+    verifier_token: Pubkey,
+    token_program: Pubkey,
+    moves: Vec<u8>,
+) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::VerifyCombo {
+            combo_pda: combo_address,
+            frame_data: pda::move_registry::frame_data(combo.character_id),
+            config: pda::combo_mint::config(),
+            verifier_registry: pda::combo_mint::verifier_registry(),
+            receipt: pda::combo_mint::receipt(&combo_address, &verifier),
+            verifier_quota: pda::combo_mint::verifier_quota(&verifier),
+            verifier,
+            reward_config: pda::combo_mint::reward_config(),
+            reward_vault: rewards.vault,
+            reward_mint: rewards.mint,
+            verifier_token,
+            token_program,
+            system_program: system_program::ID,
+            event_authority: pda::combo_mint::event_authority(),
+            program: combo_mint::ID,
+        },
+        combo_mint::instruction::VerifyCombo { moves },
+    )
+}
+
+/// `combo_mint::close_combo`, returning the rent of the combo and its
+/// indexes to `destination`. `children` holds the combo's `combo_data` if
+/// it has any, which is closed with it.
+pub fn close_combo(
+    authority: Pubkey,
+    combo_address: Pubkey,
+    combo: &ComboAccount,
+    destination: Pubkey,
+    children: &[Pubkey],
+) -> Instruction {
+    let mut ix = instruction(
+        combo_mint::ID,
+        combo_mint::accounts::CloseCombo {
+            combo_pda: combo_address,
+            hash_index: pda::combo_mint::hash_index(&combo.combo_hash),
+            name_index: pda::combo_mint::name_index(&combo.creator, &combo.name),
+            listing: pda::combo_mint::listing(&combo_address),
+            rental: pda::combo_mint::rental(&combo_address),
+            auction: pda::combo_mint::auction(&combo_address),
+            authority,
+            destination,
+        },
+        combo_mint::instruction::CloseCombo {},
+    );
+    ix.accounts
+        .extend(children.iter().map(|child| AccountMeta::new(*child, false)));
+    ix
+}
+
+/// `matchmaking::join_queue`.
+pub fn join_queue(player: Pubkey) -> Instruction {
+    instruction(
+        matchmaking::ID,
+        matchmaking::accounts::JoinQueue {
+            player,
+            rating: pda::ranking::rating(&player),
+            queue: pda::matchmaking::queue(),
+            ranked_config: pda::matchmaking::ranked_config(),
+            registration: pda::matchmaking::registration(&player),
+            disconnects: pda::match_result::disconnects(&player),
+            system_program: system_program::ID,
+        },
+        matchmaking::instruction::JoinQueue {},
+    )
+}
+
+/// `matchmaking::leave_queue`.
+pub fn leave_queue(player: Pubkey) -> Instruction {
+    instruction(
+        matchmaking::ID,
+        matchmaking::accounts::LeaveQueue {
+            player,
+            queue: pda::matchmaking::queue(),
+        },
+        matchmaking::instruction::LeaveQueue {},
+    )
+}
+
+/// `ranking::apply_match_result` for a finalized match, given its players
+/// as `[winner, loser]`.
+pub fn apply_match_result(match_id: u64, winner_and_loser: [Pubkey; 2]) -> Instruction {
+    let [winner, loser] = winner_and_loser;
+    instruction(
+        ranking::ID,
+        ranking::accounts::ApplyMatchResult {
+            match_account: pda::match_result::match_account(match_id),
+            winner_rating: pda::ranking::rating(&winner),
+            loser_rating: pda::ranking::rating(&loser),
+            config: pda::ranking::config(),
+            season_state: pda::season::state(),
+            rater: pda::ranking::rater(),
+            match_result_program: match_result::ID,
+            winner_profile: pda::profile::profile(&winner),
+            loser_profile: pda::profile::profile(&loser),
+            profile_config: pda::profile::config(),
+            profile_writer: pda::profile::writer(&ranking::ID),
+            profile_program: profile::ID,
+        },
+        ranking::instruction::ApplyMatchResult {},
+    )
+}
+
+/// `ranking::apply_decay`.
+pub fn apply_decay(player: Pubkey) -> Instruction {
+    instruction(
+        ranking::ID,
+        ranking::accounts::ApplyDecay {
+            rating: pda::ranking::rating(&player),
+            config: pda::ranking::config(),
+        },
+        ranking::instruction::ApplyDecay {},
+    )
+}
+
+/// `ranking::claim_rank` with the Merkle `proof` of the player's leaf.
+pub fn claim_rank(
+    player: Pubkey,
+    epoch: u64,
+    rank: u32,
+    rating: u32,
+    proof: Vec<[u8; 32]>,
+) -> Instruction {
+    instruction(
+        ranking::ID,
+        ranking::accounts::ClaimRank {
+            player,
+            snapshot: pda::ranking::snapshot(epoch),
+            claim: pda::ranking::rank_claim(epoch, &player),
+            system_program: system_program::ID,
+        },
+        ranking::instruction::ClaimRank {
+            epoch,
+            rank,
+            rating,
+            proof,
+        },
+    )
+}
+
+/// `match_result::record_match`, which both players sign.
+pub fn record_match(
+    players: [Pubkey; 2],
+    match_id: u64,
+    winner: u8,
+    score: [u8; 2],
+    replay_hash: [u8; 32],
+    frame_count: u32,
+    checksum_root: [u8; 32],
+) -> Instruction {
+    instruction(
+        match_result::ID,
+        match_result::accounts::RecordMatch {
+            player_one: players[0],
+            player_two: players[1],
+            match_account: pda::match_result::match_account(match_id),
+            system_program: system_program::ID,
+            event_authority: pda::match_result::event_authority(),
+            program: match_result::ID,
+        },
+        match_result::instruction::RecordMatch {
+            match_id,
+            winner,
+            score,
+            replay_hash,
+            frame_count,
+            checksum_root,
+        },
+    )
+}
+
+/// Arguments of `tournament::create_tournament`.
+#[derive(Clone, Debug)]
+pub struct NewTournament {
+    pub tournament_id: u64,
+    pub format: BracketFormat,
+    pub entry_fee: u64,
+    pub max_entrants: u8,
+    pub prize_split: Vec<u16>,
+    pub organizer_fee_bps: u16,
+}
+
+/// `tournament::create_tournament`.
+pub fn create_tournament(organizer: Pubkey, tournament: NewTournament) -> Instruction {
+    let address = pda::tournament::tournament(&organizer, tournament.tournament_id);
+    instruction(
+        tournament::ID,
+        tournament::accounts::CreateTournament {
+            organizer,
+            config: pda::tournament::config(),
+            tournament: address,
+            vault: pda::tournament::vault(&address),
+            system_program: system_program::ID,
+        },
+        tournament::instruction::CreateTournament {
+            tournament_id: tournament.tournament_id,
+            format: tournament.format,
+            entry_fee: tournament.entry_fee,
+            max_entrants: tournament.max_entrants,
+            prize_split: tournament.prize_split,
+            organizer_fee_bps: tournament.organizer_fee_bps,
+        },
+    )
+}
+
+/// `tournament::register`. Players who were referred pass their fetched
+/// `Referral`, so the referrer's share of the entry fee is paid.
+pub fn register(player: Pubkey, tournament: Pubkey, referral: Option<&Referral>) -> Instruction {
+    let mut ix = instruction(
+        tournament::ID,
+        tournament::accounts::Register {
+            player,
+            tournament,
+            vault: pda::tournament::vault(&tournament),
+            referral: pda::referral::referral(&player),
+            referral_program: referral::ID,
+            system_program: system_program::ID,
+        },
+        tournament::instruction::Register {},
+    );
+    if let Some(referral) = referral {
+        ix.accounts
+            .push(AccountMeta::new_readonly(pda::referral::config(), false));
+        ix.accounts.push(AccountMeta::new(referral.code, false));
+    }
+    ix
+}
+
+/// Arguments of `replay_registry::finalize_replay`.
+#[derive(Clone, Debug)]
+pub struct ReplayAnchor {
+    pub match_id: u64,
+    pub players: [Pubkey; 2],
+    pub character_ids: [u8; 2],
+    pub seed: u64,
+    pub game_version: u32,
+    pub format_version: u16,
+    pub frame_count: u32,
+    pub replay_hash: [u8; 32],
+}
+
+/// `replay_registry::finalize_replay`, signed by one of the players.
+pub fn finalize_replay(submitter: Pubkey, replay: ReplayAnchor) -> Instruction {
+    instruction(
+        replay_registry::ID,
+        replay_registry::accounts::FinalizeReplay {
+            submitter,
+            replay: pda::replay_registry::replay(replay.match_id),
+            system_program: system_program::ID,
+        },
+        replay_registry::instruction::FinalizeReplay {
+            match_id: replay.match_id,
+            players: replay.players,
+            character_ids: replay.character_ids,
+            seed: replay.seed,
+            game_version: replay.game_version,
+            format_version: replay.format_version,
+            frame_count: replay.frame_count,
+            replay_hash: replay.replay_hash,
+        },
+    )
+}
+
+/// `match_result::report_disconnect`. The relay's Ed25519 attestation
+/// instruction has to come right before it in the transaction.
+pub fn report_disconnect(
+    reporter: Pubkey,
+    match_id: u64,
+    players: [Pubkey; 2],
+    quitter: u8,
+    last_frame: u32,
+    last_checksum: u64,
+    waited_ms: u64,
+) -> Instruction {
+    instruction(
+        match_result::ID,
+        match_result::accounts::ReportDisconnect {
+            reporter,
+            attestation_config: pda::match_result::attestation_config(),
+            match_account: pda::match_result::match_account(match_id),
+            disconnects: pda::match_result::disconnects(&players[quitter as usize % 2]),
+            instructions: sysvar::instructions::ID,
+            system_program: system_program::ID,
+            event_authority: pda::match_result::event_authority(),
+            program: match_result::ID,
+        },
+        match_result::instruction::ReportDisconnect {
+            match_id,
+            players,
+            quitter,
+            last_frame,
+            last_checksum,
+            waited_ms,
+        },
+    )
+}
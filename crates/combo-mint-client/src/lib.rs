@@ -0,0 +1,95 @@
+//! Client for the on-chain programs, for bots and tooling.
+//!
+//! Every instruction of every program can be built from the types Anchor
+//! generates for it: `instruction` takes the program's
+//! `accounts::<Instruction>` struct and `instruction::<Instruction>` args
+//! and produces the `Instruction` with its account metas in the right order.
+//! `pda` derives the addresses those structs need, and `builders` fills in
+//! the derivable accounts for the most common calls. `blocking` and
+//! `nonblocking` fetch and decode accounts and send transactions over RPC;
+//! the `nonblocking` client runs on a tokio runtime.
+//!
+//! The program crates are re-exported, so their account types, events and
+//! constants come from here too.
+
+// `ClientError` is large, and nearly every call returns it.
+#![allow(clippy::result_large_err)]
+
+pub mod builders;
+pub mod pda;
+pub mod rpc;
+
+pub use rpc::{blocking, nonblocking};
+
+pub use achievements;
+pub use betting;
+pub use combo_mint;
+pub use dispute;
+pub use guild;
+pub use league;
+pub use match_result;
+pub use matchmaking;
+pub use move_registry;
+pub use profile;
+pub use ranking;
+pub use referral;
+pub use replay_registry;
+pub use rewards;
+pub use season;
+pub use staking;
+pub use tournament;
+pub use wager;
+
+use std::fmt;
+
+use anchor_lang::{AccountDeserialize, InstructionData, ToAccountMetas};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+
+/// Builds an instruction from its generated accounts and args, e.g.
+/// `instruction(ranking::ID, ranking::accounts::ApplyDecay { .. },
+/// ranking::instruction::ApplyDecay {})`.
+pub fn instruction<A: ToAccountMetas, D: InstructionData>(
+    program_id: Pubkey,
+    accounts: A,
+    args: D,
+) -> Instruction {
+    Instruction {
+        program_id,
+        accounts: accounts.to_account_metas(None),
+        data: args.data(),
+    }
+}
+
+/// Decodes account data, checking its discriminator.
+pub fn decode<T: AccountDeserialize>(data: &[u8]) -> Result<T, ClientError> {
+    let mut data = data;
+    T::try_deserialize(&mut data).map_err(ClientError::Decode)
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    Rpc(solana_client::client_error::ClientError),
+    /// The account exists but isn't the type asked for, or is on a layout
+    /// this client doesn't know; see `migrate_account`.
+    Decode(anchor_lang::error::Error),
+    AccountNotFound(Pubkey),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Rpc(err) => write!(f, "rpc error: {}", err),
+            ClientError::Decode(err) => write!(f, "cannot decode account: {}", err),
+            ClientError::AccountNotFound(address) => write!(f, "account {} not found", address),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<solana_client::client_error::ClientError> for ClientError {
+    fn from(err: solana_client::client_error::ClientError) -> Self {
+        ClientError::Rpc(err)
+    }
+}
@@ -0,0 +1,497 @@
+//! Addresses of the programs' PDAs, one module per program, with the seeds
+//! each program's account constraints check.
+
+use solana_sdk::pubkey::Pubkey;
+
+fn find(seeds: &[&[u8]], program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(seeds, program_id).0
+}
+
+/// Seed of the `event_authority` that signs a program's event self-CPI.
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// `wallet`'s associated token account for `mint`, under whichever token
+/// program owns the mint.
+pub fn associated_token(wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    find(
+        &[wallet.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+}
+
+pub mod achievements {
+    use super::*;
+    use ::achievements::ID;
+
+    pub fn config() -> Pubkey {
+        find(&[b"achievements_config"], &ID)
+    }
+
+    pub fn achievement(achievement_id: u16) -> Pubkey {
+        find(&[b"achievement", &achievement_id.to_le_bytes()], &ID)
+    }
+
+    pub fn badge(achievement_id: u16, player: &Pubkey) -> Pubkey {
+        find(
+            &[b"badge", &achievement_id.to_le_bytes(), player.as_ref()],
+            &ID,
+        )
+    }
+
+    /// Signing PDA `issuer_program` awards badges with.
+    pub fn issuer(issuer_program: &Pubkey) -> Pubkey {
+        find(&[b"achievement_issuer"], issuer_program)
+    }
+}
+
+pub mod betting {
+    use super::*;
+    use ::betting::ID;
+
+    pub fn config() -> Pubkey {
+        find(&[b"betting_config"], &ID)
+    }
+
+    pub fn market(match_id: u64) -> Pubkey {
+        find(&[b"market", &match_id.to_le_bytes()], &ID)
+    }
+
+    pub fn market_vault(market: &Pubkey) -> Pubkey {
+        find(&[b"market_vault", market.as_ref()], &ID)
+    }
+
+    pub fn bet(market: &Pubkey, bettor: &Pubkey) -> Pubkey {
+        find(&[b"bet", market.as_ref(), bettor.as_ref()], &ID)
+    }
+}
+
+pub mod combo_mint {
+    use super::*;
+    use ::combo_mint::{combo_name_seed, normalized_name_seed, ComboTier, ID};
+
+    pub fn config() -> Pubkey {
+        find(&[b"config"], &ID)
+    }
+
+    pub fn event_authority() -> Pubkey {
+        find(&[EVENT_AUTHORITY_SEED], &ID)
+    }
+
+    pub fn combo(creator: &Pubkey, name: &str) -> Pubkey {
+        find(&[b"combo", creator.as_ref(), &combo_name_seed(name)], &ID)
+    }
+
+    pub fn name_index(creator: &Pubkey, name: &str) -> Pubkey {
+        find(
+            &[b"combo_name", creator.as_ref(), &normalized_name_seed(name)],
+            &ID,
+        )
+    }
+
+    pub fn hash_index(combo_hash: &[u8; 32]) -> Pubkey {
+        find(&[b"combo_hash", combo_hash.as_ref()], &ID)
+    }
+
+    pub fn character_index(character_id: u8) -> Pubkey {
+        find(&[b"character_index", &[character_id]], &ID)
+    }
+
+    pub fn character_gate(character_id: u8) -> Pubkey {
+        find(&[b"character_gate", &[character_id]], &ID)
+    }
+
+    pub fn name_filter() -> Pubkey {
+        find(&[b"name_filter"], &ID)
+    }
+
+    pub fn verifier_registry() -> Pubkey {
+        find(&[b"verifier_registry"], &ID)
+    }
+
+    pub fn receipt(combo: &Pubkey, verifier: &Pubkey) -> Pubkey {
+        find(&[b"receipt", combo.as_ref(), verifier.as_ref()], &ID)
+    }
+
+    pub fn verifier_quota(verifier: &Pubkey) -> Pubkey {
+        find(&[b"verifier_quota", verifier.as_ref()], &ID)
+    }
+
+    pub fn reward_config() -> Pubkey {
+        find(&[b"reward_config"], &ID)
+    }
+
+    pub fn reward_vault() -> Pubkey {
+        find(&[b"reward_vault"], &ID)
+    }
+
+    pub fn listing(combo: &Pubkey) -> Pubkey {
+        find(&[b"listing", combo.as_ref()], &ID)
+    }
+
+    pub fn auction(combo: &Pubkey) -> Pubkey {
+        find(&[b"auction", combo.as_ref()], &ID)
+    }
+
+    pub fn rental(combo: &Pubkey) -> Pubkey {
+        find(&[b"rental", combo.as_ref()], &ID)
+    }
+
+    pub fn combo_flag(combo: &Pubkey) -> Pubkey {
+        find(&[b"combo_flag", combo.as_ref()], &ID)
+    }
+
+    pub fn combo_data(combo: &Pubkey) -> Pubkey {
+        find(&[b"combo_data", combo.as_ref()], &ID)
+    }
+
+    pub fn combo_history(combo: &Pubkey, version: u32) -> Pubkey {
+        find(
+            &[b"combo_history", combo.as_ref(), &version.to_le_bytes()],
+            &ID,
+        )
+    }
+
+    pub fn combo_nft(combo: &Pubkey) -> Pubkey {
+        find(&[b"combo_nft", combo.as_ref()], &ID)
+    }
+
+    pub fn collection(name: &str) -> Pubkey {
+        find(&[b"collection", &combo_name_seed(name)], &ID)
+    }
+
+    pub fn collection_progress(collection: &Pubkey, owner: &Pubkey) -> Pubkey {
+        find(
+            &[b"collection_progress", collection.as_ref(), owner.as_ref()],
+            &ID,
+        )
+    }
+
+    pub fn cnft_config() -> Pubkey {
+        find(&[b"cnft_config"], &ID)
+    }
+
+    pub fn tier_leaderboard(tier: ComboTier) -> Pubkey {
+        find(&[b"tier_leaderboard", &[tier as u8]], &ID)
+    }
+}
+
+pub mod dispute {
+    use super::*;
+    use ::dispute::ID;
+
+    pub fn config() -> Pubkey {
+        find(&[b"dispute_config"], &ID)
+    }
+
+    pub fn dispute(match_id: u64) -> Pubkey {
+        find(&[b"dispute", &match_id.to_le_bytes()], &ID)
+    }
+
+    pub fn vault(dispute: &Pubkey) -> Pubkey {
+        find(&[b"dispute_vault", dispute.as_ref()], &ID)
+    }
+}
+
+pub mod guild {
+    use super::*;
+    use ::guild::{guild_name_seed, ID};
+
+    pub fn guild(name: &str) -> Pubkey {
+        find(&[b"guild", &guild_name_seed(name)], &ID)
+    }
+
+    pub fn member(wallet: &Pubkey) -> Pubkey {
+        find(&[b"guild_member", wallet.as_ref()], &ID)
+    }
+
+    pub fn invite(guild: &Pubkey, invitee: &Pubkey) -> Pubkey {
+        find(&[b"guild_invite", guild.as_ref(), invitee.as_ref()], &ID)
+    }
+
+    pub fn treasury(guild: &Pubkey) -> Pubkey {
+        find(&[b"guild_treasury", guild.as_ref()], &ID)
+    }
+
+    pub fn proposal(guild: &Pubkey, proposal_id: u64) -> Pubkey {
+        find(
+            &[
+                b"guild_proposal",
+                guild.as_ref(),
+                &proposal_id.to_le_bytes(),
+            ],
+            &ID,
+        )
+    }
+
+    pub fn match_tag(match_id: u64, side: u8) -> Pubkey {
+        find(&[b"guild_match", &match_id.to_le_bytes(), &[side]], &ID)
+    }
+
+    pub fn leaderboard() -> Pubkey {
+        find(&[b"guild_leaderboard"], &ID)
+    }
+}
+
+pub mod league {
+    use super::*;
+    use ::league::ID;
+
+    pub fn league(organizer: &Pubkey, league_id: u64) -> Pubkey {
+        find(
+            &[b"league", organizer.as_ref(), &league_id.to_le_bytes()],
+            &ID,
+        )
+    }
+
+    pub fn standings(league: &Pubkey) -> Pubkey {
+        find(&[b"standings", league.as_ref()], &ID)
+    }
+}
+
+pub mod match_result {
+    use super::*;
+    use ::match_result::ID;
+
+    pub fn match_account(match_id: u64) -> Pubkey {
+        find(&[b"match", &match_id.to_le_bytes()], &ID)
+    }
+
+    pub fn crew_battle(match_id: u64) -> Pubkey {
+        find(&[b"crew_battle", &match_id.to_le_bytes()], &ID)
+    }
+
+    pub fn attestation_config() -> Pubkey {
+        find(&[b"attestation_config"], &ID)
+    }
+
+    pub fn disconnects(player: &Pubkey) -> Pubkey {
+        find(&[b"disconnects", player.as_ref()], &ID)
+    }
+
+    pub fn event_authority() -> Pubkey {
+        find(&[EVENT_AUTHORITY_SEED], &ID)
+    }
+}
+
+pub mod matchmaking {
+    use super::*;
+    use ::matchmaking::ID;
+
+    pub fn queue() -> Pubkey {
+        find(&[b"queue"], &ID)
+    }
+
+    pub fn pending_match(match_id: u64) -> Pubkey {
+        find(&[b"pending_match", &match_id.to_le_bytes()], &ID)
+    }
+
+    pub fn ranked_config() -> Pubkey {
+        find(&[b"ranked_config"], &ID)
+    }
+
+    pub fn registration(player: &Pubkey) -> Pubkey {
+        find(&[b"registration", player.as_ref()], &ID)
+    }
+}
+
+pub mod move_registry {
+    use super::*;
+    use ::move_registry::ID;
+
+    pub fn registry() -> Pubkey {
+        find(&[b"registry"], &ID)
+    }
+
+    pub fn frame_data(character_id: u8) -> Pubkey {
+        find(&[b"character", &[character_id]], &ID)
+    }
+}
+
+pub mod profile {
+    use super::*;
+    use ::profile::{ID, PROFILE_WRITER_SEED};
+
+    pub fn config() -> Pubkey {
+        find(&[b"profile_config"], &ID)
+    }
+
+    pub fn profile(owner: &Pubkey) -> Pubkey {
+        find(&[b"profile", owner.as_ref()], &ID)
+    }
+
+    /// Signing PDA `writer_program` updates profiles with.
+    pub fn writer(writer_program: &Pubkey) -> Pubkey {
+        find(&[PROFILE_WRITER_SEED], writer_program)
+    }
+}
+
+pub mod ranking {
+    use super::*;
+    use ::ranking::ID;
+
+    pub fn config() -> Pubkey {
+        find(&[b"ranking_config"], &ID)
+    }
+
+    pub fn rating(player: &Pubkey) -> Pubkey {
+        find(&[b"rating", player.as_ref()], &ID)
+    }
+
+    /// Signing PDA for `match_result::mark_rated`.
+    pub fn rater() -> Pubkey {
+        find(&[b"rater"], &ID)
+    }
+
+    pub fn snapshot(epoch: u64) -> Pubkey {
+        find(&[b"snapshot", &epoch.to_le_bytes()], &ID)
+    }
+
+    pub fn rank_claim(epoch: u64, player: &Pubkey) -> Pubkey {
+        find(&[b"rank_claim", &epoch.to_le_bytes(), player.as_ref()], &ID)
+    }
+}
+
+pub mod referral {
+    use super::*;
+    use ::referral::{code_seed, ID};
+
+    pub fn config() -> Pubkey {
+        find(&[b"referral_config"], &ID)
+    }
+
+    /// Codes are case-insensitive, so any casing gives the same address.
+    pub fn code(code: &str) -> Pubkey {
+        find(&[b"referral_code", &code_seed(code)], &ID)
+    }
+
+    pub fn referral(player: &Pubkey) -> Pubkey {
+        find(&[b"referral", player.as_ref()], &ID)
+    }
+}
+
+pub mod replay_registry {
+    use super::*;
+    use ::replay_registry::ID;
+
+    pub fn replay(match_id: u64) -> Pubkey {
+        find(&[b"replay", &match_id.to_le_bytes()], &ID)
+    }
+}
+
+pub mod rewards {
+    use super::*;
+    use ::rewards::ID;
+
+    pub fn distributor(distributor_id: u64) -> Pubkey {
+        find(&[b"distributor", &distributor_id.to_le_bytes()], &ID)
+    }
+
+    pub fn vault(distributor: &Pubkey) -> Pubkey {
+        find(&[b"vault", distributor.as_ref()], &ID)
+    }
+}
+
+pub mod season {
+    use super::*;
+    use ::season::ID;
+
+    pub fn state() -> Pubkey {
+        find(&[b"season_state"], &ID)
+    }
+
+    pub fn season(season_id: u32) -> Pubkey {
+        find(&[b"season", &season_id.to_le_bytes()], &ID)
+    }
+
+    pub fn standing(season_id: u32, player: &Pubkey) -> Pubkey {
+        find(
+            &[b"standing", &season_id.to_le_bytes(), player.as_ref()],
+            &ID,
+        )
+    }
+
+    /// Receipt for a match or combo already scored this season.
+    pub fn entry(season_id: u32, scored: &Pubkey) -> Pubkey {
+        find(
+            &[b"season_entry", &season_id.to_le_bytes(), scored.as_ref()],
+            &ID,
+        )
+    }
+
+    pub fn archive(season_id: u32) -> Pubkey {
+        find(&[b"archive", &season_id.to_le_bytes()], &ID)
+    }
+}
+
+pub mod staking {
+    use super::*;
+    use ::staking::ID;
+
+    pub fn pool() -> Pubkey {
+        find(&[b"staking_pool"], &ID)
+    }
+
+    pub fn emission_curve() -> Pubkey {
+        find(&[b"emission_curve"], &ID)
+    }
+
+    pub fn vault() -> Pubkey {
+        find(&[b"staking_vault"], &ID)
+    }
+
+    pub fn position(combo: &Pubkey) -> Pubkey {
+        find(&[b"stake", combo.as_ref()], &ID)
+    }
+
+    pub fn nft_vault(position: &Pubkey) -> Pubkey {
+        find(&[b"nft_vault", position.as_ref()], &ID)
+    }
+}
+
+pub mod tournament {
+    use super::*;
+    use ::tournament::ID;
+
+    pub fn config() -> Pubkey {
+        find(&[b"config"], &ID)
+    }
+
+    pub fn tournament(organizer: &Pubkey, tournament_id: u64) -> Pubkey {
+        find(
+            &[
+                b"tournament",
+                organizer.as_ref(),
+                &tournament_id.to_le_bytes(),
+            ],
+            &ID,
+        )
+    }
+
+    pub fn vault(tournament: &Pubkey) -> Pubkey {
+        find(&[b"vault", tournament.as_ref()], &ID)
+    }
+}
+
+pub mod wager {
+    use super::*;
+    use ::wager::ID;
+
+    pub fn wager(match_id: u64, creator: &Pubkey, opponent: &Pubkey) -> Pubkey {
+        find(
+            &[
+                b"wager",
+                &match_id.to_le_bytes(),
+                creator.as_ref(),
+                opponent.as_ref(),
+            ],
+            &ID,
+        )
+    }
+
+    pub fn escrow(wager: &Pubkey) -> Pubkey {
+        find(&[b"escrow", wager.as_ref()], &ID)
+    }
+}
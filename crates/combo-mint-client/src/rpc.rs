@@ -0,0 +1,174 @@
+//! Fetching accounts and sending transactions, over the blocking RPC client
+//! or the async one.
+
+use anchor_lang::{AccountDeserialize, Discriminator};
+use combo_mint::{ComboAccount, COMBO_AUTHORITY_OFFSET};
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::account::Account;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Signature, Signer};
+use solana_sdk::transaction::Transaction;
+
+use crate::{decode, ClientError};
+
+/// Decodes `data` if the account exists, `None` if it doesn't.
+fn decode_optional<T: AccountDeserialize>(data: Option<&[u8]>) -> Result<Option<T>, ClientError> {
+    data.map(decode).transpose()
+}
+
+/// `getProgramAccounts` query for the combos `authority` holds.
+fn combos_by_authority_config(authority: &Pubkey) -> RpcProgramAccountsConfig {
+    RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                0,
+                &ComboAccount::discriminator(),
+            )),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                COMBO_AUTHORITY_OFFSET,
+                authority.as_ref(),
+            )),
+        ]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(solana_account_decoder::UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    }
+}
+
+fn decode_all<T: AccountDeserialize>(
+    accounts: Vec<(Pubkey, Account)>,
+) -> Result<Vec<(Pubkey, T)>, ClientError> {
+    accounts
+        .into_iter()
+        .map(|(address, account)| Ok((address, decode(&account.data)?)))
+        .collect()
+}
+
+pub mod blocking {
+    use super::*;
+    use solana_client::rpc_client::RpcClient;
+
+    pub fn fetch<T: AccountDeserialize>(
+        rpc: &RpcClient,
+        address: &Pubkey,
+    ) -> Result<T, ClientError> {
+        fetch_optional(rpc, address)?.ok_or(ClientError::AccountNotFound(*address))
+    }
+
+    /// `None` for an account that doesn't exist, such as a PDA that hasn't
+    /// been created yet.
+    pub fn fetch_optional<T: AccountDeserialize>(
+        rpc: &RpcClient,
+        address: &Pubkey,
+    ) -> Result<Option<T>, ClientError> {
+        let account = rpc
+            .get_account_with_commitment(address, rpc.commitment())?
+            .value;
+        decode_optional(account.as_ref().map(|a| a.data.as_slice()))
+    }
+
+    /// Fetches several accounts of one type in a single request, `None` for
+    /// each that doesn't exist.
+    pub fn fetch_many<T: AccountDeserialize>(
+        rpc: &RpcClient,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<T>>, ClientError> {
+        rpc.get_multiple_accounts(addresses)?
+            .iter()
+            .map(|account| decode_optional(account.as_ref().map(|a| a.data.as_slice())))
+            .collect()
+    }
+
+    /// Every combo `authority` currently holds, with its address.
+    pub fn combos_by_authority(
+        rpc: &RpcClient,
+        authority: &Pubkey,
+    ) -> Result<Vec<(Pubkey, ComboAccount)>, ClientError> {
+        let accounts = rpc.get_program_accounts_with_config(
+            &combo_mint::ID,
+            combos_by_authority_config(authority),
+        )?;
+        decode_all(accounts)
+    }
+
+    /// Sends `instructions` in one transaction paid by `payer`, once it is
+    /// confirmed. `signers` must include `payer`.
+    pub fn send(
+        rpc: &RpcClient,
+        payer: &Pubkey,
+        signers: &[&dyn Signer],
+        instructions: &[Instruction],
+    ) -> Result<Signature, ClientError> {
+        let blockhash = rpc.get_latest_blockhash()?;
+        let transaction =
+            Transaction::new_signed_with_payer(instructions, Some(payer), signers, blockhash);
+        Ok(rpc.send_and_confirm_transaction(&transaction)?)
+    }
+}
+
+/// The same calls on `solana_client`'s async client, which needs a tokio
+/// runtime.
+pub mod nonblocking {
+    use super::*;
+    use solana_client::nonblocking::rpc_client::RpcClient;
+
+    pub async fn fetch<T: AccountDeserialize>(
+        rpc: &RpcClient,
+        address: &Pubkey,
+    ) -> Result<T, ClientError> {
+        fetch_optional(rpc, address)
+            .await?
+            .ok_or(ClientError::AccountNotFound(*address))
+    }
+
+    pub async fn fetch_optional<T: AccountDeserialize>(
+        rpc: &RpcClient,
+        address: &Pubkey,
+    ) -> Result<Option<T>, ClientError> {
+        let account = rpc
+            .get_account_with_commitment(address, rpc.commitment())
+            .await?
+            .value;
+        decode_optional(account.as_ref().map(|a| a.data.as_slice()))
+    }
+
+    pub async fn fetch_many<T: AccountDeserialize>(
+        rpc: &RpcClient,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<Option<T>>, ClientError> {
+        rpc.get_multiple_accounts(addresses)
+            .await?
+            .iter()
+            .map(|account| decode_optional(account.as_ref().map(|a| a.data.as_slice())))
+            .collect()
+    }
+
+    pub async fn combos_by_authority(
+        rpc: &RpcClient,
+        authority: &Pubkey,
+    ) -> Result<Vec<(Pubkey, ComboAccount)>, ClientError> {
+        let accounts = rpc
+            .get_program_accounts_with_config(
+                &combo_mint::ID,
+                combos_by_authority_config(authority),
+            )
+            .await?;
+        decode_all(accounts)
+    }
+
+    pub async fn send(
+        rpc: &RpcClient,
+        payer: &Pubkey,
+        signers: &[&dyn Signer],
+        instructions: &[Instruction],
+    ) -> Result<Signature, ClientError> {
+        let blockhash = rpc.get_latest_blockhash().await?;
+        let transaction =
+            Transaction::new_signed_with_payer(instructions, Some(payer), signers, blockhash);
+        Ok(rpc.send_and_confirm_transaction(&transaction).await?)
+    }
+}
@@ -0,0 +1,47 @@
+use anchor_lang::{Discriminator, InstructionData};
+use combo_mint_client::{builders, pda};
+use solana_sdk::pubkey::Pubkey;
+
+#[test]
+fn referral_codes_ignore_case() {
+    assert_eq!(
+        pda::referral::code("FrameTrap"),
+        pda::referral::code("frametrap")
+    );
+}
+
+#[test]
+fn combo_names_differ_by_case_but_share_a_name_index() {
+    let creator = Pubkey::new_unique();
+    assert_ne!(
+        pda::combo_mint::combo(&creator, "Loop"),
+        pda::combo_mint::combo(&creator, "loop")
+    );
+    assert_eq!(
+        pda::combo_mint::name_index(&creator, "Loop"),
+        pda::combo_mint::name_index(&creator, "loop")
+    );
+}
+
+#[test]
+fn join_queue_fills_in_the_players_accounts() {
+    let player = Pubkey::new_unique();
+    let ix = builders::join_queue(player);
+    assert_eq!(ix.program_id, matchmaking::ID);
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(
+        keys,
+        [
+            player,
+            pda::ranking::rating(&player),
+            pda::matchmaking::queue(),
+            pda::matchmaking::ranked_config(),
+            pda::matchmaking::registration(&player),
+            pda::match_result::disconnects(&player),
+            anchor_lang::system_program::ID,
+        ]
+    );
+    assert!(ix.accounts[0].is_signer && ix.accounts[0].is_writable);
+    assert_eq!(ix.data, matchmaking::instruction::JoinQueue {}.data());
+    assert_eq!(ix.data, matchmaking::instruction::JoinQueue::DISCRIMINATOR);
+}
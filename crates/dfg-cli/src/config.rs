@@ -0,0 +1,72 @@
+//! The RPC URL and keypair the Solana CLI is configured with, read from
+//! `~/.config/solana/cli/config.yml` so `solana config set` applies here
+//! too. `--url` and `--keypair` override either.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const DEFAULT_URL: &str = "https://api.mainnet-beta.solana.com";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Config {
+    pub json_rpc_url: String,
+    pub keypair_path: String,
+}
+
+impl Config {
+    /// The Solana CLI's config, or its defaults when there is none.
+    pub fn load() -> Self {
+        let text = home()
+            .map(|home| home.join(".config/solana/cli/config.yml"))
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        Self::parse(&text)
+    }
+
+    /// Picks the two keys out of the config file. It is flat YAML the
+    /// Solana CLI writes itself, so a line scan is enough.
+    pub fn parse(text: &str) -> Self {
+        let mut config = Config {
+            json_rpc_url: DEFAULT_URL.to_string(),
+            keypair_path: "~/.config/solana/id.json".to_string(),
+        };
+        for line in text.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            match key.trim() {
+                "json_rpc_url" if !value.is_empty() => config.json_rpc_url = value.to_string(),
+                "keypair_path" if !value.is_empty() => config.keypair_path = value.to_string(),
+                _ => {}
+            }
+        }
+        config
+    }
+}
+
+/// The URL for one of the cluster monikers `solana -u` takes, or `url`
+/// itself.
+pub fn cluster_url(url: &str) -> String {
+    match url {
+        "m" | "mainnet-beta" => DEFAULT_URL,
+        "d" | "devnet" => "https://api.devnet.solana.com",
+        "t" | "testnet" => "https://api.testnet.solana.com",
+        "l" | "localhost" => "http://localhost:8899",
+        url => url,
+    }
+    .to_string()
+}
+
+/// `path` with a leading `~/` replaced by the home directory.
+pub fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), home()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+fn home() -> Option<PathBuf> {
+    env::var_os("HOME").map(PathBuf::from)
+}
@@ -0,0 +1,528 @@
+//! `dfg-cli` manages combos, matches, tournaments and replays from the
+//! command line:
+//!
+//! ```text
+//! dfg-cli combo create "Corner Loop" 1 4200 3,5,5,9 --royalty 250
+//! dfg-cli --json combo list
+//! dfg-cli match report 77 match.dfgr 0 2-1 --opponent ~/keys/p2.json
+//! dfg-cli replay check match.dfgr
+//! ```
+//!
+//! The RPC URL and signing keypair come from the Solana CLI config unless
+//! `--url` or `--keypair` is given. With `--json` every command prints one
+//! JSON document on stdout instead of `key: value` lines, and failures
+//! print `{"error": ...}`; the exit status is non-zero either way.
+
+// `ClientError` is large, and nearly every call returns it.
+#![allow(clippy::result_large_err)]
+
+mod config;
+mod output;
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+use combo_mint_client::builders::{self, NewCombo, NewTournament, ReplayAnchor};
+use combo_mint_client::combo_mint::{ComboAccount, RewardConfig};
+use combo_mint_client::match_result::MatchAccount;
+use combo_mint_client::move_registry::CharacterFrameData;
+use combo_mint_client::referral::Referral;
+use combo_mint_client::tournament::BracketFormat;
+use combo_mint_client::{blocking, pda, ClientError};
+use replay::{Replay, ReplayError};
+use replay_check::{check, CheckOutcome, Divergence};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+
+use config::Config;
+use output::{fields, Output};
+
+const USAGE: &str = "\
+usage: dfg-cli [--json] [--url <url>] [--keypair <path>] <command>
+
+  combo create <name> <character-id> <damage> <moves> [--royalty <bps>] [--tags <bits>]
+  combo verify <combo> <moves>
+  combo list [<authority>]
+  combo close <combo> [--destination <wallet>]
+  match report <match-id> <replay> <winner> <score> --opponent <keypair>
+  tournament create <tournament-id> <single|double|swiss> <entry-fee> <max-entrants> <prize-split> [--organizer-fee <bps>]
+  tournament register <tournament>
+  replay check <replay>
+  replay anchor <match-id> <replay>
+
+<moves> and <prize-split> are comma-separated and <score> is e.g. 2-1.
+In `match report` the keypair is player one and the opponent player two;
+<winner> is 0 or 1 accordingly.";
+
+/// Flags that take a value, wherever they appear on the command line.
+const VALUE_FLAGS: &[&str] = &[
+    "--url",
+    "--keypair",
+    "--royalty",
+    "--tags",
+    "--destination",
+    "--opponent",
+    "--organizer-fee",
+];
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    let json = take_switch(&mut args, "--json");
+    let result = Flags::take(&mut args).and_then(|flags| {
+        let cli = Cli::new(&flags);
+        run(&cli, &args, &flags)
+    });
+    match result {
+        Ok(output) => {
+            output.print(json);
+            ExitCode::SUCCESS
+        }
+        Err(CliError::Usage) => {
+            eprintln!("{}", USAGE);
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            if json {
+                println!("{}", serde_json::json!({ "error": err.to_string() }));
+            } else {
+                eprintln!("{}", err);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: &Cli, args: &[String], flags: &Flags) -> Result<Output, CliError> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["combo", "create", name, character_id, damage, moves] => {
+            combo_create(cli, flags, name, character_id, damage, moves)
+        }
+        ["combo", "verify", combo, moves] => combo_verify(cli, combo, moves),
+        ["combo", "list"] => combo_list(cli, None),
+        ["combo", "list", authority] => combo_list(cli, Some(*authority)),
+        ["combo", "close", combo] => combo_close(cli, flags, combo),
+        ["match", "report", match_id, path, winner, score] => {
+            match_report(cli, flags, match_id, Path::new(path), winner, score)
+        }
+        ["tournament", "create", id, format, entry_fee, max_entrants, prize_split] => {
+            tournament_create(cli, flags, id, format, entry_fee, max_entrants, prize_split)
+        }
+        ["tournament", "register", tournament] => tournament_register(cli, tournament),
+        ["replay", "check", path] => replay_check(Path::new(path)),
+        ["replay", "anchor", match_id, path] => replay_anchor(cli, match_id, Path::new(path)),
+        _ => Err(CliError::Usage),
+    }
+}
+
+/// The RPC connection and keypair the commands act with.
+struct Cli {
+    rpc: RpcClient,
+    keypair_path: PathBuf,
+}
+
+impl Cli {
+    fn new(flags: &Flags) -> Self {
+        let config = Config::load();
+        let url = flags.get("--url").unwrap_or(&config.json_rpc_url);
+        let keypair_path = flags.get("--keypair").unwrap_or(&config.keypair_path);
+        Cli {
+            rpc: RpcClient::new_with_commitment(
+                config::cluster_url(url),
+                CommitmentConfig::confirmed(),
+            ),
+            keypair_path: config::expand_home(keypair_path),
+        }
+    }
+
+    fn signer(&self) -> Result<Keypair, CliError> {
+        read_keypair(&self.keypair_path)
+    }
+
+    fn send(
+        &self,
+        payer: &Keypair,
+        signers: &[&dyn Signer],
+        instructions: &[Instruction],
+    ) -> Result<Signature, CliError> {
+        Ok(blocking::send(
+            &self.rpc,
+            &payer.pubkey(),
+            signers,
+            instructions,
+        )?)
+    }
+}
+
+fn combo_create(
+    cli: &Cli,
+    flags: &Flags,
+    name: &str,
+    character_id: &str,
+    damage: &str,
+    moves: &str,
+) -> Result<Output, CliError> {
+    let authority = cli.signer()?;
+    let moves: Vec<u8> = parse_list("moves", moves)?;
+    let combo = NewCombo {
+        name: name.to_string(),
+        damage: parse("damage", damage)?,
+        move_count: u8::try_from(moves.len())
+            .map_err(|_| CliError::Invalid("too many moves".to_string()))?,
+        character_id: parse("character id", character_id)?,
+        moves,
+        royalty_bps: flags.parse_or("--royalty", 0)?,
+        tags: flags.parse_or("--tags", 0)?,
+    };
+    let frame_data: CharacterFrameData = blocking::fetch(
+        &cli.rpc,
+        &pda::move_registry::frame_data(combo.character_id),
+    )?;
+    let combo_hash = builders::new_combo_hash(&frame_data, &combo)
+        .map_err(|err| CliError::Invalid(format!("combo does not simulate: {}", err)))?;
+    let address = pda::combo_mint::combo(&authority.pubkey(), &combo.name);
+    let ix = builders::create_combo(authority.pubkey(), combo, &combo_hash);
+    let signature = cli.send(&authority, &[&authority], &[ix])?;
+    Ok(Output::Fields(fields![
+        "signature" => signature.to_string(),
+        "combo" => address.to_string(),
+        "combo_hash" => hex(&combo_hash),
+    ]))
+}
+
+/// Verifies the combo and takes the reward into the verifier's associated
+/// token account for the reward mint.
+fn combo_verify(cli: &Cli, combo: &str, moves: &str) -> Result<Output, CliError> {
+    let verifier = cli.signer()?;
+    let address = parse("combo address", combo)?;
+    let moves = parse_list("moves", moves)?;
+    let combo: ComboAccount = blocking::fetch(&cli.rpc, &address)?;
+    let rewards: RewardConfig = blocking::fetch(&cli.rpc, &pda::combo_mint::reward_config())?;
+    let token_program = cli
+        .rpc
+        .get_account(&rewards.mint)
+        .map_err(ClientError::from)?
+        .owner;
+    let verifier_token = pda::associated_token(&verifier.pubkey(), &rewards.mint, &token_program);
+    let ix = builders::verify_combo(
+        verifier.pubkey(),
+        address,
+        &combo,
+        &rewards,
+        verifier_token,
+        token_program,
+        moves,
+    );
+    let signature = cli.send(&verifier, &[&verifier], &[ix])?;
+    Ok(Output::Fields(fields![
+        "signature" => signature.to_string(),
+        "combo" => address.to_string(),
+        "verifier_token" => verifier_token.to_string(),
+    ]))
+}
+
+/// The combos `authority` holds, the keypair's own by default.
+fn combo_list(cli: &Cli, authority: Option<&str>) -> Result<Output, CliError> {
+    let authority = match authority {
+        Some(authority) => parse("authority", authority)?,
+        None => cli.signer()?.pubkey(),
+    };
+    let combos = blocking::combos_by_authority(&cli.rpc, &authority)?;
+    Ok(Output::List(
+        combos
+            .into_iter()
+            .map(|(address, combo)| {
+                fields![
+                    "combo" => address.to_string(),
+                    "name" => combo.name,
+                    "character_id" => combo.character_id,
+                    "damage" => combo.damage,
+                    "meter_gain" => combo.meter_gain,
+                    "moves" => combo.moves,
+                    "verification_count" => combo.verification_count,
+                    "official" => combo.official,
+                    "stale" => combo.stale,
+                ]
+            })
+            .collect(),
+    ))
+}
+
+/// Closes the combo along with its attached data. A listed or rented
+/// combo has to be delisted, or its rental finalized, first.
+fn combo_close(cli: &Cli, flags: &Flags, combo: &str) -> Result<Output, CliError> {
+    let authority = cli.signer()?;
+    let address = parse("combo address", combo)?;
+    let destination = match flags.get("--destination") {
+        Some(destination) => parse("destination", destination)?,
+        None => authority.pubkey(),
+    };
+    let combo: ComboAccount = blocking::fetch(&cli.rpc, &address)?;
+    let mut children = Vec::new();
+    if combo.combo_data != Pubkey::default() {
+        children.push(combo.combo_data);
+    }
+    let ix = builders::close_combo(authority.pubkey(), address, &combo, destination, &children);
+    let signature = cli.send(&authority, &[&authority], &[ix])?;
+    Ok(Output::Fields(fields![
+        "signature" => signature.to_string(),
+        "combo" => address.to_string(),
+        "closed_children" => children.len(),
+    ]))
+}
+
+/// Records the result with both players signing, taking the replay hash,
+/// frame count and checksum root from the replay file.
+fn match_report(
+    cli: &Cli,
+    flags: &Flags,
+    match_id: &str,
+    path: &Path,
+    winner: &str,
+    score: &str,
+) -> Result<Output, CliError> {
+    let match_id = parse("match id", match_id)?;
+    let winner = parse("winner", winner)?;
+    let score = match score.split_once('-') {
+        Some((one, two)) => [parse("score", one)?, parse("score", two)?],
+        None => return Err(CliError::Invalid(format!("invalid score: {}", score))),
+    };
+    let replay = read_replay(path)?;
+    let opponent = flags.get("--opponent").ok_or(CliError::Usage)?;
+    let player = cli.signer()?;
+    let opponent = read_keypair(&config::expand_home(opponent))?;
+    let replay_hash = replay.hash();
+    let ix = builders::record_match(
+        [player.pubkey(), opponent.pubkey()],
+        match_id,
+        winner,
+        score,
+        replay_hash,
+        replay.header.frame_count,
+        replay.checksum_root(),
+    );
+    let signature = cli.send(&player, &[&player, &opponent], &[ix])?;
+    Ok(Output::Fields(fields![
+        "signature" => signature.to_string(),
+        "match" => pda::match_result::match_account(match_id).to_string(),
+        "replay_hash" => hex(&replay_hash),
+    ]))
+}
+
+fn tournament_create(
+    cli: &Cli,
+    flags: &Flags,
+    tournament_id: &str,
+    format: &str,
+    entry_fee: &str,
+    max_entrants: &str,
+    prize_split: &str,
+) -> Result<Output, CliError> {
+    let organizer = cli.signer()?;
+    let tournament = NewTournament {
+        tournament_id: parse("tournament id", tournament_id)?,
+        format: match format {
+            "single" => BracketFormat::SingleElimination,
+            "double" => BracketFormat::DoubleElimination,
+            "swiss" => BracketFormat::Swiss,
+            _ => return Err(CliError::Invalid(format!("invalid format: {}", format))),
+        },
+        entry_fee: parse("entry fee", entry_fee)?,
+        max_entrants: parse("max entrants", max_entrants)?,
+        prize_split: parse_list("prize split", prize_split)?,
+        organizer_fee_bps: flags.parse_or("--organizer-fee", 0)?,
+    };
+    let address = pda::tournament::tournament(&organizer.pubkey(), tournament.tournament_id);
+    let ix = builders::create_tournament(organizer.pubkey(), tournament);
+    let signature = cli.send(&organizer, &[&organizer], &[ix])?;
+    Ok(Output::Fields(fields![
+        "signature" => signature.to_string(),
+        "tournament" => address.to_string(),
+        "vault" => pda::tournament::vault(&address).to_string(),
+    ]))
+}
+
+/// Registers the keypair, paying its referrer's share if it was referred.
+fn tournament_register(cli: &Cli, tournament: &str) -> Result<Output, CliError> {
+    let player = cli.signer()?;
+    let tournament = parse("tournament address", tournament)?;
+    let referral: Option<Referral> =
+        blocking::fetch_optional(&cli.rpc, &pda::referral::referral(&player.pubkey()))?;
+    let ix = builders::register(player.pubkey(), tournament, referral.as_ref());
+    let signature = cli.send(&player, &[&player], &[ix])?;
+    Ok(Output::Fields(fields![
+        "signature" => signature.to_string(),
+        "tournament" => tournament.to_string(),
+        "referred" => referral.is_some(),
+    ]))
+}
+
+/// Re-simulates the replay against its embedded checksums, locally.
+fn replay_check(path: &Path) -> Result<Output, CliError> {
+    let replay = read_replay(path)?;
+    match check(&replay).map_err(|err| CliError::Replay(path.to_path_buf(), err))? {
+        CheckOutcome::Match {
+            frames,
+            checkpoints,
+            final_checksum,
+        } => Ok(Output::Fields(fields![
+            "replay_hash" => hex(&replay.hash()),
+            "frames" => frames,
+            "checksums_matched" => checkpoints,
+            "final_checksum" => format!("{:#018x}", final_checksum),
+        ])),
+        CheckOutcome::Diverged(divergence) => {
+            Err(CliError::Diverged(path.to_path_buf(), divergence))
+        }
+    }
+}
+
+/// Anchors the replay of a recorded match, after checking it hashes to
+/// the `replay_hash` the match recorded.
+fn replay_anchor(cli: &Cli, match_id: &str, path: &Path) -> Result<Output, CliError> {
+    let match_id = parse("match id", match_id)?;
+    let replay = read_replay(path)?;
+    let submitter = cli.signer()?;
+    let recorded: MatchAccount =
+        blocking::fetch(&cli.rpc, &pda::match_result::match_account(match_id))?;
+    let replay_hash = replay.hash();
+    if recorded.replay_hash != replay_hash {
+        return Err(CliError::Invalid(format!(
+            "{} does not hash to the replay_hash match {} recorded",
+            path.display(),
+            match_id
+        )));
+    }
+    let header = replay.header;
+    let ix = builders::finalize_replay(
+        submitter.pubkey(),
+        ReplayAnchor {
+            match_id,
+            players: recorded.players,
+            character_ids: header.characters,
+            seed: header.seed,
+            game_version: header.game_version,
+            format_version: header.version,
+            frame_count: header.frame_count,
+            replay_hash,
+        },
+    );
+    let signature = cli.send(&submitter, &[&submitter], &[ix])?;
+    Ok(Output::Fields(fields![
+        "signature" => signature.to_string(),
+        "replay" => pda::replay_registry::replay(match_id).to_string(),
+        "replay_hash" => hex(&replay_hash),
+    ]))
+}
+
+/// Value flags pulled out of the arguments, leaving the positional ones.
+struct Flags(BTreeMap<&'static str, String>);
+
+impl Flags {
+    fn take(args: &mut Vec<String>) -> Result<Self, CliError> {
+        let mut flags = BTreeMap::new();
+        let mut positional = Vec::new();
+        let mut args_iter = mem::take(args).into_iter();
+        while let Some(arg) = args_iter.next() {
+            if let Some(&flag) = VALUE_FLAGS.iter().find(|&&flag| flag == arg) {
+                flags.insert(flag, args_iter.next().ok_or(CliError::Usage)?);
+            } else if arg.starts_with("--") {
+                return Err(CliError::Usage);
+            } else {
+                positional.push(arg);
+            }
+        }
+        *args = positional;
+        Ok(Flags(flags))
+    }
+
+    fn get(&self, flag: &str) -> Option<&str> {
+        self.0.get(flag).map(String::as_str)
+    }
+
+    fn parse_or<T: FromStr>(&self, flag: &str, default: T) -> Result<T, CliError> {
+        match self.get(flag) {
+            Some(value) => parse(flag.trim_start_matches("--"), value),
+            None => Ok(default),
+        }
+    }
+}
+
+fn take_switch(args: &mut Vec<String>, switch: &str) -> bool {
+    let before = args.len();
+    args.retain(|arg| arg != switch);
+    args.len() != before
+}
+
+fn parse<T: FromStr>(what: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse()
+        .map_err(|_| CliError::Invalid(format!("invalid {}: {}", what, value)))
+}
+
+fn parse_list<T: FromStr>(what: &str, value: &str) -> Result<Vec<T>, CliError> {
+    value
+        .split(',')
+        .map(|item| parse(what, item.trim()))
+        .collect()
+}
+
+fn read_keypair(path: &Path) -> Result<Keypair, CliError> {
+    read_keypair_file(path).map_err(|err| CliError::Keypair(path.to_path_buf(), err.to_string()))
+}
+
+fn read_replay(path: &Path) -> Result<Replay, CliError> {
+    let bytes = fs::read(path).map_err(|err| CliError::Read(path.to_path_buf(), err))?;
+    Replay::decode(&bytes).map_err(|err| CliError::Replay(path.to_path_buf(), err))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[derive(Debug)]
+enum CliError {
+    Usage,
+    Invalid(String),
+    Keypair(PathBuf, String),
+    Read(PathBuf, io::Error),
+    Replay(PathBuf, ReplayError),
+    Diverged(PathBuf, Box<Divergence>),
+    Client(ClientError),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Usage => f.write_str(USAGE),
+            CliError::Invalid(message) => f.write_str(message),
+            CliError::Keypair(path, err) => {
+                write!(f, "cannot read keypair {}: {}", path.display(), err)
+            }
+            CliError::Read(path, err) => write!(f, "{}: {}", path.display(), err),
+            CliError::Replay(path, err) => write!(f, "{}: {}", path.display(), err),
+            CliError::Diverged(path, divergence) => {
+                write!(f, "{}: {}", path.display(), divergence)
+            }
+            CliError::Client(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl From<ClientError> for CliError {
+    fn from(err: ClientError) -> Self {
+        CliError::Client(err)
+    }
+}
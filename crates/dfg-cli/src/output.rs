@@ -0,0 +1,58 @@
+//! What a command reports: `key: value` lines for people, or with `--json`
+//! a single JSON document on stdout for scripts.
+
+use serde_json::{Map, Value};
+
+pub type Fields = Map<String, Value>;
+
+pub enum Output {
+    Fields(Fields),
+    List(Vec<Fields>),
+}
+
+impl Output {
+    pub fn print(&self, json: bool) {
+        if json {
+            let value = match self {
+                Output::Fields(fields) => Value::Object(fields.clone()),
+                Output::List(items) => {
+                    Value::Array(items.iter().cloned().map(Value::Object).collect())
+                }
+            };
+            println!("{}", value);
+            return;
+        }
+        match self {
+            Output::Fields(fields) => print_fields(fields),
+            Output::List(items) => {
+                for (i, fields) in items.iter().enumerate() {
+                    if i > 0 {
+                        println!();
+                    }
+                    print_fields(fields);
+                }
+            }
+        }
+    }
+}
+
+fn print_fields(fields: &Fields) {
+    for (key, value) in fields {
+        match value {
+            Value::String(text) => println!("{}: {}", key, text),
+            value => println!("{}: {}", key, value),
+        }
+    }
+}
+
+/// Builds `Fields` in the order the keys are given, e.g.
+/// `fields!["signature" => sig.to_string(), "frames" => 640]`.
+macro_rules! fields {
+    ($($key:expr => $value:expr),* $(,)?) => {{
+        let mut fields = $crate::output::Fields::new();
+        $(fields.insert($key.to_string(), serde_json::Value::from($value));)*
+        fields
+    }};
+}
+
+pub(crate) use fields;
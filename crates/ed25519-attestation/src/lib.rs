@@ -0,0 +1,114 @@
+//! Game-server attestations checked through the ed25519 native program.
+//!
+//! Instead of signing the transaction itself, an approved server signs an
+//! attestation message off-chain and anyone can submit it: the transaction
+//! carries an ed25519 program instruction verifying the signature right
+//! before the attested instruction, and the program reads that instruction
+//! back from the instructions sysvar. The runtime has already rejected the
+//! transaction if the signature is bad, so all that is left to check is that
+//! the instruction signs exactly the expected message, with all of its data
+//! inline rather than borrowed from another instruction.
+//!
+//! Each program builds its own messages, which start with a domain tag so a
+//! signature for one instruction can never be replayed as another, and maps
+//! `AttestationError` onto its own error codes.
+
+use std::fmt;
+
+use solana_program::account_info::AccountInfo;
+use solana_program::ed25519_program;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey::Pubkey;
+use solana_program::sysvar::instructions::{
+    load_current_index_checked, load_instruction_at_checked,
+};
+
+/// Count and padding bytes before the offsets.
+const HEADER_LEN: usize = 2;
+/// `Ed25519SignatureOffsets`: seven little-endian `u16`s.
+const OFFSETS_LEN: usize = 14;
+const PUBKEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+/// Instruction index meaning "the ed25519 instruction itself".
+pub const THIS_INSTRUCTION: u16 = u16::MAX;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttestationError {
+    /// The instructions sysvar couldn't be read.
+    Sysvar(ProgramError),
+    /// No ed25519 program instruction precedes the current one.
+    Missing,
+    /// The ed25519 instruction doesn't sign exactly the expected message
+    /// with its data inline.
+    Invalid,
+}
+
+impl fmt::Display for AttestationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AttestationError::Sysvar(err) => write!(f, "instructions sysvar: {}", err),
+            AttestationError::Missing => {
+                write!(f, "no ed25519 attestation precedes this instruction")
+            }
+            AttestationError::Invalid => write!(f, "invalid ed25519 attestation"),
+        }
+    }
+}
+
+impl std::error::Error for AttestationError {}
+
+/// Returns the key that signed `message` in the ed25519 instruction right
+/// before the current one.
+pub fn verify_attestation(
+    instructions: &AccountInfo,
+    message: &[u8],
+) -> Result<Pubkey, AttestationError> {
+    let current = load_current_index_checked(instructions).map_err(AttestationError::Sysvar)?;
+    if current == 0 {
+        return Err(AttestationError::Missing);
+    }
+    let ix = load_instruction_at_checked(current as usize - 1, instructions)
+        .map_err(AttestationError::Sysvar)?;
+    if ix.program_id != ed25519_program::ID {
+        return Err(AttestationError::Missing);
+    }
+    signer(&ix.data, message)
+}
+
+/// Returns the key an ed25519 program instruction with `data` has signed
+/// `message` with, if it verifies exactly one signature over exactly that
+/// message and takes every field from its own data.
+pub fn signer(data: &[u8], message: &[u8]) -> Result<Pubkey, AttestationError> {
+    if data.len() < HEADER_LEN + OFFSETS_LEN || data[0] != 1 {
+        return Err(AttestationError::Invalid);
+    }
+    let field = |i: usize| {
+        let at = HEADER_LEN + 2 * i;
+        u16::from_le_bytes([data[at], data[at + 1]])
+    };
+    let signature_offset = field(0) as usize;
+    let signature_ix = field(1);
+    let pubkey_offset = field(2) as usize;
+    let pubkey_ix = field(3);
+    let message_offset = field(4) as usize;
+    let message_len = field(5) as usize;
+    let message_ix = field(6);
+    if signature_ix != THIS_INSTRUCTION
+        || pubkey_ix != THIS_INSTRUCTION
+        || message_ix != THIS_INSTRUCTION
+        || data.len() < signature_offset + SIGNATURE_LEN
+    {
+        return Err(AttestationError::Invalid);
+    }
+
+    let pubkey = data
+        .get(pubkey_offset..pubkey_offset + PUBKEY_LEN)
+        .ok_or(AttestationError::Invalid)?;
+    let signed = data
+        .get(message_offset..message_offset + message_len)
+        .ok_or(AttestationError::Invalid)?;
+    if signed != message {
+        return Err(AttestationError::Invalid);
+    }
+    Pubkey::try_from(pubkey).map_err(|_| AttestationError::Invalid)
+}
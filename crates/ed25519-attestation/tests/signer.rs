@@ -0,0 +1,82 @@
+use ed25519_attestation::{signer, AttestationError, THIS_INSTRUCTION};
+use solana_program::pubkey::Pubkey;
+
+const MESSAGE: &[u8] = b"test:attest:v1 payload";
+
+/// Data of an ed25519 program instruction verifying one signature by
+/// `pubkey` over `message`, laid out the way the SDK builds it: offsets,
+/// then the key, the signature and the message. The signature bytes don't
+/// matter here, as the runtime checks them before the program runs.
+fn ed25519_data(pubkey: &Pubkey, message: &[u8], indexes: [u16; 3]) -> Vec<u8> {
+    let pubkey_offset: u16 = 2 + 14;
+    let signature_offset = pubkey_offset + 32;
+    let message_offset = signature_offset + 64;
+    let offsets = [
+        signature_offset,
+        indexes[0],
+        pubkey_offset,
+        indexes[1],
+        message_offset,
+        message.len() as u16,
+        indexes[2],
+    ];
+
+    let mut data = vec![1, 0];
+    for offset in offsets {
+        data.extend_from_slice(&offset.to_le_bytes());
+    }
+    data.extend_from_slice(pubkey.as_ref());
+    data.extend_from_slice(&[7; 64]);
+    data.extend_from_slice(message);
+    data
+}
+
+fn inline(pubkey: &Pubkey, message: &[u8]) -> Vec<u8> {
+    ed25519_data(pubkey, message, [THIS_INSTRUCTION; 3])
+}
+
+#[test]
+fn returns_the_key_that_signed_the_message() {
+    let server = Pubkey::new_unique();
+    assert_eq!(signer(&inline(&server, MESSAGE), MESSAGE), Ok(server));
+}
+
+#[test]
+fn refuses_a_different_message() {
+    let data = inline(&Pubkey::new_unique(), b"test:attest:v1 other");
+    assert_eq!(signer(&data, MESSAGE), Err(AttestationError::Invalid));
+    let data = inline(&Pubkey::new_unique(), &MESSAGE[..MESSAGE.len() - 1]);
+    assert_eq!(signer(&data, MESSAGE), Err(AttestationError::Invalid));
+}
+
+#[test]
+fn refuses_fields_borrowed_from_another_instruction() {
+    for field in 0..3 {
+        let mut indexes = [THIS_INSTRUCTION; 3];
+        indexes[field] = 0;
+        let data = ed25519_data(&Pubkey::new_unique(), MESSAGE, indexes);
+        assert_eq!(signer(&data, MESSAGE), Err(AttestationError::Invalid));
+    }
+}
+
+#[test]
+fn refuses_anything_but_one_signature() {
+    let mut data = inline(&Pubkey::new_unique(), MESSAGE);
+    data[0] = 2;
+    assert_eq!(signer(&data, MESSAGE), Err(AttestationError::Invalid));
+    data[0] = 0;
+    assert_eq!(signer(&data, MESSAGE), Err(AttestationError::Invalid));
+}
+
+#[test]
+fn refuses_truncated_data() {
+    let data = inline(&Pubkey::new_unique(), MESSAGE);
+    for len in [0, 1, 15, 2 + 14 + 32, data.len() - 1] {
+        assert_eq!(
+            signer(&data[..len], MESSAGE),
+            Err(AttestationError::Invalid),
+            "length {}",
+            len
+        );
+    }
+}
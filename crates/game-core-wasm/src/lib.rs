@@ -0,0 +1,65 @@
+//! Browser bindings for the simulation core.
+//!
+//! The browser client drives a `Match` through the same `simulate_frame` and
+//! `state_hash` as the native client and the on-chain verifier, so a match
+//! played in the browser hashes identically everywhere. Inputs cross the
+//! boundary as the raw `Input` bits and states as `game_core::snapshot`
+//! bytes.
+
+use game_core::character::roster;
+use game_core::{simulate_frame, snapshot, GameState, Input};
+use wasm_bindgen::prelude::*;
+
+/// A match in progress, opaque to JavaScript.
+#[wasm_bindgen]
+pub struct Match {
+    state: GameState,
+}
+
+#[wasm_bindgen]
+impl Match {
+    #[wasm_bindgen(getter)]
+    pub fn frame(&self) -> u32 {
+        self.state.frame
+    }
+
+    /// Index of the winning player, or `undefined` while the round runs.
+    #[wasm_bindgen(getter)]
+    pub fn winner(&self) -> Option<u8> {
+        self.state.winner().map(|player| player as u8)
+    }
+}
+
+/// Starts a match between two roster characters with the RNG seeded from
+/// the match seed.
+#[wasm_bindgen]
+pub fn init_match(p1: u8, p2: u8, seed: u64) -> Result<Match, JsError> {
+    let (Some(p1), Some(p2)) = (roster(p1), roster(p2)) else {
+        return Err(JsError::new("unknown character id"));
+    };
+    Ok(Match {
+        state: GameState::with_seed(p1, p2, seed),
+    })
+}
+
+/// Advances one frame. Inputs setting reserved bits are rejected rather
+/// than truncated so the browser cannot drift from a recorded replay.
+#[wasm_bindgen]
+pub fn advance_frame(game: &mut Match, p1_input: u16, p2_input: u16) -> Result<(), JsError> {
+    let (Some(p1), Some(p2)) = (Input::from_bits(p1_input), Input::from_bits(p2_input)) else {
+        return Err(JsError::new("reserved input bits set"));
+    };
+    game.state = simulate_frame(&game.state, [p1, p2]);
+    Ok(())
+}
+
+/// The current state as `SNAPSHOT_LEN` snapshot bytes.
+#[wasm_bindgen]
+pub fn serialize_state(game: &Match) -> Vec<u8> {
+    snapshot::encode(&game.state).to_vec()
+}
+
+#[wasm_bindgen]
+pub fn state_hash(game: &Match) -> u32 {
+    game_core::state_hash(&game.state)
+}
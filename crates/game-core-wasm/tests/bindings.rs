@@ -0,0 +1,37 @@
+use game_core::character::ROSTER;
+use game_core::{simulate_frame, snapshot, GameState, Input};
+use game_core_wasm::{advance_frame, init_match, serialize_state, state_hash};
+
+#[test]
+fn matches_the_native_core_frame_for_frame() {
+    let mut game = init_match(0, 1, 42).unwrap();
+    let mut native = GameState::with_seed(&ROSTER[0], &ROSTER[1], 42);
+    for frame in 0..300u32 {
+        let p1 = if frame % 24 < 16 {
+            Input::RIGHT
+        } else {
+            Input::ATTACK
+        };
+        let p2 = if frame % 40 < 4 {
+            Input::SPECIAL
+        } else {
+            Input::DEFEND
+        };
+        advance_frame(&mut game, p1.bits(), p2.bits()).unwrap();
+        native = simulate_frame(&native, [p1, p2]);
+        assert_eq!(state_hash(&game), game_core::state_hash(&native));
+    }
+    assert_eq!(game.frame(), 300);
+    assert_eq!(game.winner(), native.winner().map(|player| player as u8));
+}
+
+#[test]
+fn serialized_state_decodes_to_the_current_state() {
+    let mut game = init_match(1, 0, 7).unwrap();
+    for _ in 0..30 {
+        advance_frame(&mut game, Input::LEFT.bits(), Input::ATTACK.bits()).unwrap();
+    }
+    let decoded = snapshot::decode(&serialize_state(&game)).unwrap();
+    assert_eq!(game_core::state_hash(&decoded), state_hash(&game));
+    assert_eq!(decoded.frame, 30);
+}
@@ -0,0 +1,279 @@
+//! Character and move definitions.
+//!
+//! Move ids and frame data line up with the on-chain `move_registry` program so
+//! a combo validated on-chain means the same thing as one played in the client.
+
+use crate::fixed::Fx32;
+use crate::meter::MoveMeter;
+
+/// Axis-aligned box relative to the owner's position, mirrored when facing left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rect {
+    pub offset_x: Fx32,
+    pub offset_y: Fx32,
+    pub width: Fx32,
+    pub height: Fx32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MoveDef {
+    pub id: u8,
+    pub startup: u8,
+    pub active: u8,
+    pub recovery: u8,
+    pub damage: i32,
+    pub chip_damage: i32,
+    pub hitstun: u16,
+    pub blockstun: u16,
+    /// Horizontal push applied to the defender, in pixels per frame.
+    pub pushback: Fx32,
+    /// Upward velocity given to the defender on hit; non-zero makes the move
+    /// a launcher.
+    pub launch: Fx32,
+    /// Juggle points a hit on an airborne defender costs.
+    pub juggle_cost: u8,
+    pub hitbox: Rect,
+    /// When two hits connect on the same frame the higher priority wins and
+    /// equal priorities trade.
+    pub priority: u8,
+    /// Replaces the character's hurtbox while the move runs.
+    pub hurtbox: Option<Rect>,
+    /// Spawned on the first active frame instead of a melee hitbox.
+    pub projectile: Option<ProjectileDef>,
+    pub meter: MoveMeter,
+}
+
+/// Projectile launched by a move. Damage, stun and pushback come from the
+/// move; `hitbox` is relative to the projectile's position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProjectileDef {
+    /// Pixels per frame in the owner's facing direction.
+    pub speed: Fx32,
+    /// Frames before the projectile fizzles.
+    pub lifetime: u16,
+    pub hitbox: Rect,
+}
+
+impl MoveDef {
+    pub fn total_frames(&self) -> u16 {
+        self.startup as u16 + self.active as u16 + self.recovery as u16
+    }
+
+    /// True on frames where the hitbox is out.
+    pub fn is_active(&self, frame: u16) -> bool {
+        let start = self.startup as u16;
+        frame >= start && frame < start + self.active as u16
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CharacterDef<'a> {
+    pub id: u8,
+    pub name: &'a str,
+    pub max_health: i32,
+    pub walk_speed: Fx32,
+    pub jump_force: Fx32,
+    pub gravity: Fx32,
+    pub max_fall_speed: Fx32,
+    pub ground_friction: Fx32,
+    pub air_friction: Fx32,
+    pub hurtbox: Rect,
+    pub moves: &'a [MoveDef],
+    pub cancels: &'a [Cancel],
+}
+
+/// Lets `from` be cut short by `to` once `from` has connected, on hit or on
+/// block.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cancel {
+    pub from: u8,
+    pub to: u8,
+}
+
+impl<'a> CharacterDef<'a> {
+    pub fn find_move(&self, id: u8) -> Option<&'a MoveDef> {
+        self.moves.iter().find(|m| m.id == id)
+    }
+
+    pub fn can_cancel(&self, from: u8, to: u8) -> bool {
+        self.cancels.iter().any(|c| c.from == from && c.to == to)
+    }
+}
+
+/// Move bound to the attack button.
+pub const MOVE_LIGHT: u8 = 0;
+/// Move bound to the special button.
+pub const MOVE_SPECIAL: u8 = 1;
+/// Move bound to attack and special pressed together, usually a metered EX
+/// version of the special. Falls back to `MOVE_LIGHT` when the character has
+/// none or cannot pay for it.
+pub const MOVE_EX: u8 = 2;
+
+const RONIN_MOVES: [MoveDef; 2] = [
+    MoveDef {
+        id: MOVE_LIGHT,
+        startup: 4,
+        active: 3,
+        recovery: 8,
+        damage: 30,
+        chip_damage: 0,
+        hitstun: 14,
+        blockstun: 9,
+        pushback: Fx32::from_int(2),
+        launch: Fx32::ZERO,
+        juggle_cost: 1,
+        hitbox: Rect {
+            offset_x: Fx32::from_int(45),
+            offset_y: Fx32::from_int(70),
+            width: Fx32::from_int(50),
+            height: Fx32::from_int(20),
+        },
+        priority: 0,
+        hurtbox: None,
+        projectile: None,
+        meter: MoveMeter {
+            on_hit: 5,
+            on_block: 2,
+            on_whiff: 1,
+            cost: 0,
+            super_freeze: 0,
+        },
+    },
+    MoveDef {
+        id: MOVE_SPECIAL,
+        startup: 12,
+        active: 4,
+        recovery: 20,
+        damage: 80,
+        chip_damage: 10,
+        hitstun: 22,
+        blockstun: 14,
+        pushback: Fx32::from_int(5),
+        launch: Fx32::ZERO,
+        juggle_cost: 2,
+        hitbox: Rect {
+            offset_x: Fx32::from_int(55),
+            offset_y: Fx32::from_int(60),
+            width: Fx32::from_int(70),
+            height: Fx32::from_int(40),
+        },
+        priority: 0,
+        hurtbox: None,
+        projectile: None,
+        meter: MoveMeter {
+            on_hit: 10,
+            on_block: 5,
+            on_whiff: 2,
+            cost: 0,
+            super_freeze: 0,
+        },
+    },
+];
+
+const KNIGHT_MOVES: [MoveDef; 2] = [
+    MoveDef {
+        id: MOVE_LIGHT,
+        startup: 5,
+        active: 3,
+        recovery: 10,
+        damage: 35,
+        chip_damage: 0,
+        hitstun: 14,
+        blockstun: 10,
+        pushback: Fx32::from_int(2),
+        launch: Fx32::ZERO,
+        juggle_cost: 1,
+        hitbox: Rect {
+            offset_x: Fx32::from_int(50),
+            offset_y: Fx32::from_int(75),
+            width: Fx32::from_int(55),
+            height: Fx32::from_int(20),
+        },
+        priority: 0,
+        hurtbox: None,
+        projectile: None,
+        meter: MoveMeter {
+            on_hit: 5,
+            on_block: 2,
+            on_whiff: 1,
+            cost: 0,
+            super_freeze: 0,
+        },
+    },
+    MoveDef {
+        id: MOVE_SPECIAL,
+        startup: 14,
+        active: 4,
+        recovery: 22,
+        damage: 95,
+        chip_damage: 12,
+        hitstun: 24,
+        blockstun: 15,
+        pushback: Fx32::from_int(6),
+        launch: Fx32::ZERO,
+        juggle_cost: 2,
+        hitbox: Rect {
+            offset_x: Fx32::from_int(60),
+            offset_y: Fx32::from_int(60),
+            width: Fx32::from_int(80),
+            height: Fx32::from_int(45),
+        },
+        priority: 0,
+        hurtbox: None,
+        projectile: None,
+        meter: MoveMeter {
+            on_hit: 10,
+            on_block: 5,
+            on_whiff: 2,
+            cost: 0,
+            super_freeze: 0,
+        },
+    },
+];
+
+/// Built-in roster, indexed by character id.
+pub const ROSTER: [CharacterDef<'static>; 2] = [
+    CharacterDef {
+        id: 0,
+        name: "Ronin",
+        max_health: 1000,
+        walk_speed: Fx32::from_int(4),
+        jump_force: Fx32::from_int(18),
+        gravity: Fx32::ONE,
+        max_fall_speed: Fx32::from_int(20),
+        ground_friction: Fx32::ONE,
+        air_friction: Fx32::from_ratio(1, 5),
+        hurtbox: Rect {
+            offset_x: Fx32::ZERO,
+            offset_y: Fx32::from_int(60),
+            width: Fx32::from_int(60),
+            height: Fx32::from_int(120),
+        },
+        moves: &RONIN_MOVES,
+        cancels: &[],
+    },
+    CharacterDef {
+        id: 1,
+        name: "Knight",
+        max_health: 1100,
+        walk_speed: Fx32::from_ratio(7, 2),
+        jump_force: Fx32::from_int(17),
+        gravity: Fx32::from_ratio(11, 10),
+        max_fall_speed: Fx32::from_int(21),
+        ground_friction: Fx32::from_ratio(5, 4),
+        air_friction: Fx32::from_ratio(1, 4),
+        hurtbox: Rect {
+            offset_x: Fx32::ZERO,
+            offset_y: Fx32::from_int(62),
+            width: Fx32::from_int(66),
+            height: Fx32::from_int(125),
+        },
+        moves: &KNIGHT_MOVES,
+        cancels: &[],
+    },
+];
+
+/// Looks up a built-in character by id.
+pub fn roster(id: u8) -> Option<&'static CharacterDef<'static>> {
+    ROSTER.get(id as usize)
+}
@@ -0,0 +1,196 @@
+//! Periodic full-state checksums for desync detection between peers.
+//!
+//! Every state type writes its fields, in declaration order and as
+//! little-endian bytes, into a 64-bit FNV-1a hasher. The byte layout is the
+//! contract: adding a field to a state type means adding it to its
+//! `StableHash` impl, so two builds only agree when their states match
+//! exactly. `state_hash` stays as the 32-bit hash shared with the C# engine.
+
+use crate::fixed::Fx32;
+use crate::proration::Proration;
+use crate::rng::Rng;
+use crate::state::{Facing, GameState, PlayerState, Projectile, Stage};
+
+/// Frames between checksums when the session config does not override it.
+pub const DEFAULT_CHECKSUM_INTERVAL: u32 = 30;
+
+const FNV64_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV64_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Checksum of the state at the start of `frame`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FrameChecksum {
+    pub frame: u32,
+    pub checksum: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ChecksumWriter {
+    hash: u64,
+}
+
+impl Default for ChecksumWriter {
+    fn default() -> Self {
+        ChecksumWriter {
+            hash: FNV64_OFFSET_BASIS,
+        }
+    }
+}
+
+impl ChecksumWriter {
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash = (self.hash ^ byte as u64).wrapping_mul(FNV64_PRIME);
+        }
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.write_bytes(&[value]);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_i32(&mut self, value: i32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    pub fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+/// Writes a value's fields into a checksum in a fixed order.
+pub trait StableHash {
+    fn stable_hash(&self, writer: &mut ChecksumWriter);
+}
+
+impl StableHash for u8 {
+    fn stable_hash(&self, writer: &mut ChecksumWriter) {
+        writer.write_u8(*self);
+    }
+}
+
+impl StableHash for Fx32 {
+    fn stable_hash(&self, writer: &mut ChecksumWriter) {
+        writer.write_i32(self.raw());
+    }
+}
+
+impl StableHash for Facing {
+    fn stable_hash(&self, writer: &mut ChecksumWriter) {
+        writer.write_i32(self.sign());
+    }
+}
+
+impl StableHash for Rng {
+    fn stable_hash(&self, writer: &mut ChecksumWriter) {
+        for word in self.state() {
+            writer.write_u64(word);
+        }
+    }
+}
+
+impl StableHash for Proration {
+    fn stable_hash(&self, writer: &mut ChecksumWriter) {
+        writer.write_u8(self.scale_percent);
+        writer.write_u8(self.floor_percent);
+    }
+}
+
+impl StableHash for Stage {
+    fn stable_hash(&self, writer: &mut ChecksumWriter) {
+        self.left_wall.stable_hash(writer);
+        self.right_wall.stable_hash(writer);
+        self.floor_y.stable_hash(writer);
+    }
+}
+
+impl StableHash for PlayerState {
+    fn stable_hash(&self, writer: &mut ChecksumWriter) {
+        writer.write_u8(self.character_id);
+        self.pos_x.stable_hash(writer);
+        self.pos_y.stable_hash(writer);
+        self.vel_x.stable_hash(writer);
+        self.vel_y.stable_hash(writer);
+        self.facing.stable_hash(writer);
+        writer.write_bool(self.grounded);
+        writer.write_bool(self.guarding);
+        writer.write_i32(self.health);
+        self.current_move.stable_hash(writer);
+        writer.write_u16(self.move_frame);
+        writer.write_bool(self.move_connected);
+        writer.write_u16(self.hitstun);
+        writer.write_u16(self.blockstun);
+        writer.write_u8(self.hit_spark);
+        writer.write_u16(self.combo_hits);
+        writer.write_u16(self.meter);
+        writer.write_bool(self.launched);
+        writer.write_u8(self.juggle);
+        writer.write_u16(self.invincible);
+    }
+}
+
+impl StableHash for Projectile {
+    fn stable_hash(&self, writer: &mut ChecksumWriter) {
+        writer.write_u8(self.move_id);
+        self.pos_x.stable_hash(writer);
+        self.pos_y.stable_hash(writer);
+        self.vel_x.stable_hash(writer);
+        self.facing.stable_hash(writer);
+        writer.write_u16(self.frames_left);
+    }
+}
+
+impl<T: StableHash> StableHash for Option<T> {
+    fn stable_hash(&self, writer: &mut ChecksumWriter) {
+        match self {
+            Some(value) => {
+                writer.write_u8(1);
+                value.stable_hash(writer);
+            }
+            None => writer.write_u8(0),
+        }
+    }
+}
+
+impl StableHash for GameState {
+    fn stable_hash(&self, writer: &mut ChecksumWriter) {
+        writer.write_u32(self.frame);
+        self.stage.stable_hash(writer);
+        for player in &self.players {
+            player.stable_hash(writer);
+        }
+        for projectile in &self.projectiles {
+            projectile.stable_hash(writer);
+        }
+        self.rng.stable_hash(writer);
+        self.proration.stable_hash(writer);
+        writer.write_u8(self.super_freeze);
+    }
+}
+
+pub fn checksum(state: &GameState) -> u64 {
+    let mut writer = ChecksumWriter::default();
+    state.stable_hash(&mut writer);
+    writer.finish()
+}
+
+/// Whether `frame` is one peers exchange checksums for. An interval of zero
+/// disables checksums.
+pub fn is_checksum_frame(frame: u32, interval: u32) -> bool {
+    interval != 0 && frame % interval == 0
+}
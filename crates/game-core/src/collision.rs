@@ -0,0 +1,183 @@
+//! Hitbox and hurtbox collision in fixed point.
+//!
+//! Boxes are authored as `Rect`s relative to their owner and resolved into
+//! world-space `Aabb`s each frame. Moving boxes (projectiles) are swept over
+//! the frame's motion so a fast projectile cannot tunnel through a body.
+
+use crate::character::{CharacterDef, MoveDef, Rect};
+use crate::fixed::Fx32;
+use crate::state::{Facing, PlayerState, Projectile};
+
+/// World-space axis-aligned box; edges are inclusive.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Aabb {
+    pub min_x: Fx32,
+    pub max_x: Fx32,
+    pub min_y: Fx32,
+    pub max_y: Fx32,
+}
+
+impl Aabb {
+    /// Places a box authored relative to its owner, mirroring the X offset
+    /// when the owner faces left.
+    pub fn from_rect(rect: &Rect, pos_x: Fx32, pos_y: Fx32, facing: Facing) -> Self {
+        let center_x = pos_x + rect.offset_x * facing.sign();
+        let center_y = pos_y + rect.offset_y;
+        Aabb {
+            min_x: center_x - rect.width / 2,
+            max_x: center_x + rect.width / 2,
+            min_y: center_y - rect.height / 2,
+            max_y: center_y + rect.height / 2,
+        }
+    }
+
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min_x <= other.max_x
+            && self.max_x >= other.min_x
+            && self.min_y <= other.max_y
+            && self.max_y >= other.min_y
+    }
+
+    /// Horizontal overlap with `other`, zero when apart.
+    pub fn overlap_x(&self, other: &Aabb) -> Fx32 {
+        (self.max_x.min(other.max_x) - self.min_x.max(other.min_x)).max(Fx32::ZERO)
+    }
+
+    pub fn translated(&self, dx: Fx32, dy: Fx32) -> Aabb {
+        Aabb {
+            min_x: self.min_x + dx,
+            max_x: self.max_x + dx,
+            min_y: self.min_y + dy,
+            max_y: self.max_y + dy,
+        }
+    }
+}
+
+/// Whether `moving`, translated by `(dx, dy)` over one frame, touches the
+/// static `target` at any point along the way.
+///
+/// Uses the slab method with entry and exit times kept as exact fractions,
+/// compared by cross-multiplication, so no division or rounding is involved.
+pub fn sweep(moving: &Aabb, dx: Fx32, dy: Fx32, target: &Aabb) -> bool {
+    let mut entry = Fraction { num: 0, den: 1 };
+    let mut exit = Fraction { num: 1, den: 1 };
+    let axes = [
+        (moving.min_x, moving.max_x, dx, target.min_x, target.max_x),
+        (moving.min_y, moving.max_y, dy, target.min_y, target.max_y),
+    ];
+
+    for (min, max, delta, target_min, target_max) in axes {
+        let (min, max, delta) = (min.raw() as i128, max.raw() as i128, delta.raw() as i128);
+        let (target_min, target_max) = (target_min.raw() as i128, target_max.raw() as i128);
+        if delta == 0 {
+            if min > target_max || max < target_min {
+                return false;
+            }
+            continue;
+        }
+        let (axis_entry, axis_exit) = if delta > 0 {
+            (target_min - max, target_max - min)
+        } else {
+            (min - target_max, max - target_min)
+        };
+        let den = delta.abs();
+        entry = entry.max(Fraction {
+            num: axis_entry,
+            den,
+        });
+        exit = exit.min(Fraction {
+            num: axis_exit,
+            den,
+        });
+    }
+
+    !entry.gt(&exit)
+}
+
+#[derive(Clone, Copy)]
+struct Fraction {
+    num: i128,
+    /// Always positive.
+    den: i128,
+}
+
+impl Fraction {
+    fn gt(&self, other: &Fraction) -> bool {
+        self.num * other.den > other.num * self.den
+    }
+
+    fn max(self, other: Fraction) -> Fraction {
+        if other.gt(&self) {
+            other
+        } else {
+            self
+        }
+    }
+
+    fn min(self, other: Fraction) -> Fraction {
+        if self.gt(&other) {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Outcome when both players' hitboxes connect on the same frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clash {
+    /// Both hits land.
+    Trade,
+    /// Only this player's hit lands.
+    Wins(usize),
+}
+
+/// Higher priority beats lower; equal priority trades.
+pub fn resolve_clash(first: &MoveDef, second: &MoveDef) -> Clash {
+    match first.priority.cmp(&second.priority) {
+        core::cmp::Ordering::Greater => Clash::Wins(0),
+        core::cmp::Ordering::Less => Clash::Wins(1),
+        core::cmp::Ordering::Equal => Clash::Trade,
+    }
+}
+
+/// Body box used for pushing, independent of the current move.
+pub fn pushbox(player: &PlayerState, def: &CharacterDef) -> Aabb {
+    Aabb::from_rect(&def.hurtbox, player.pos_x, player.pos_y, player.facing)
+}
+
+/// Box the player can be hit on this frame; a move may replace the
+/// character's default hurtbox while it runs.
+pub fn hurtbox(player: &PlayerState, def: &CharacterDef) -> Aabb {
+    let rect = player
+        .current_move
+        .and_then(|id| def.find_move(id))
+        .and_then(|m| m.hurtbox)
+        .unwrap_or(def.hurtbox);
+    Aabb::from_rect(&rect, player.pos_x, player.pos_y, player.facing)
+}
+
+/// Melee hitbox out on this frame, if any. Projectile moves have none; their
+/// hitbox travels with the projectile instead.
+pub fn hitbox<'a>(player: &PlayerState, def: &CharacterDef<'a>) -> Option<(Aabb, &'a MoveDef)> {
+    if player.move_connected {
+        return None;
+    }
+    let move_def = player.current_move.and_then(|id| def.find_move(id))?;
+    if move_def.projectile.is_some() || !move_def.is_active(player.move_frame) {
+        return None;
+    }
+    let aabb = Aabb::from_rect(&move_def.hitbox, player.pos_x, player.pos_y, player.facing);
+    Some((aabb, move_def))
+}
+
+/// Projectile hitbox at its current position.
+pub fn projectile_box(projectile: &Projectile, move_def: &MoveDef) -> Option<Aabb> {
+    let def = move_def.projectile?;
+    Some(Aabb::from_rect(
+        &def.hitbox,
+        projectile.pos_x,
+        projectile.pos_y,
+        projectile.facing,
+    ))
+}
@@ -0,0 +1,241 @@
+//! Frame data for training mode.
+//!
+//! `step_with_report` runs the same `step` as everywhere else and returns a
+//! `FrameReport` of what happened on the frame: which frame of which move
+//! each player ran, how long each has until they can act again and the
+//! advantage that gives, and every hitbox that overlapped a hurtbox along
+//! with what came of it. `ComboTracker` folds the reports into per-combo
+//! damage breakdowns. Nothing here feeds back into the simulation, so the
+//! resulting state is identical to `step`'s.
+//!
+//! Only compiled with the `debug` feature.
+
+use alloc::vec::Vec;
+
+use crate::character::{CharacterDef, MoveDef};
+use crate::input::Input;
+use crate::sim::{step_observed, Contact, Observer};
+use crate::state::{GameState, PlayerState};
+use crate::MAX_PLAYERS;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MovePhase {
+    Startup,
+    Active,
+    Recovery,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveFrame {
+    pub move_id: u8,
+    /// Frame of the move that ran, from 1.
+    pub frame: u16,
+    pub phase: MovePhase,
+    /// Frames of the current phase left after this one.
+    pub phase_remaining: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayerReport {
+    /// `None` while idle, stunned, or frozen by a super.
+    pub move_frame: Option<MoveFrame>,
+    /// Frames after this one before the player can act, `None` while
+    /// launched since that lasts until landing.
+    pub busy_frames: Option<u16>,
+    /// Opponent's busy frames minus this player's, when both are known.
+    /// Positive means this player acts first.
+    pub advantage: Option<i32>,
+    pub hitstun: u16,
+    pub blockstun: u16,
+    pub combo_hits: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OverlapResult {
+    Hit {
+        base_damage: i32,
+        /// Damage after proration.
+        damage: i32,
+        /// Position of the hit in the combo, from 1.
+        combo_hit: u16,
+    },
+    Blocked {
+        chip_damage: i32,
+    },
+    /// Beaten by a higher priority hit on the same frame.
+    LostClash,
+    /// Projectile destroyed by the other player's projectile.
+    Cancelled,
+}
+
+/// A hitbox that overlapped the opponent's hurtbox.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HitboxOverlap {
+    pub attacker: usize,
+    pub move_id: u8,
+    pub projectile: bool,
+    pub result: OverlapResult,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrameReport {
+    /// Frame that was simulated.
+    pub frame: u32,
+    /// The frame was skipped by a super freeze.
+    pub frozen: bool,
+    pub players: [PlayerReport; MAX_PLAYERS],
+    pub overlaps: Vec<HitboxOverlap>,
+}
+
+#[derive(Default)]
+struct Recorder {
+    move_frames: [Option<MoveFrame>; MAX_PLAYERS],
+    overlaps: Vec<HitboxOverlap>,
+}
+
+impl Observer for Recorder {
+    fn move_frame(&mut self, player: usize, move_def: &MoveDef, frame: u16) {
+        let startup = move_def.startup as u16;
+        let active_end = startup + move_def.active as u16;
+        let (phase, phase_end) = if frame < startup {
+            (MovePhase::Startup, startup)
+        } else if frame < active_end {
+            (MovePhase::Active, active_end)
+        } else {
+            (MovePhase::Recovery, move_def.total_frames())
+        };
+        self.move_frames[player] = Some(MoveFrame {
+            move_id: move_def.id,
+            frame: frame + 1,
+            phase,
+            phase_remaining: phase_end - frame - 1,
+        });
+    }
+
+    fn contact(&mut self, attacker: usize, move_def: &MoveDef, projectile: bool, contact: Contact) {
+        let result = match contact {
+            Contact::Hit {
+                base,
+                damage,
+                hits_before,
+            } => OverlapResult::Hit {
+                base_damage: base,
+                damage,
+                combo_hit: hits_before + 1,
+            },
+            Contact::Blocked { chip } => OverlapResult::Blocked { chip_damage: chip },
+            Contact::LostClash => OverlapResult::LostClash,
+            Contact::Cancelled => OverlapResult::Cancelled,
+        };
+        self.overlaps.push(HitboxOverlap {
+            attacker,
+            move_id: move_def.id,
+            projectile,
+            result,
+        });
+    }
+}
+
+/// `step` plus a report of the frame.
+pub fn step_with_report(
+    state: &GameState,
+    inputs: [Input; MAX_PLAYERS],
+    defs: [&CharacterDef; MAX_PLAYERS],
+) -> (GameState, FrameReport) {
+    let mut recorder = Recorder::default();
+    let next = step_observed(state, inputs, defs, &mut recorder);
+    let busy: [Option<u16>; MAX_PLAYERS] =
+        core::array::from_fn(|i| busy_frames(&next.players[i], defs[i]));
+    let players = core::array::from_fn(|i| {
+        let player = &next.players[i];
+        PlayerReport {
+            move_frame: recorder.move_frames[i],
+            busy_frames: busy[i],
+            advantage: match (busy[i], busy[1 - i]) {
+                (Some(own), Some(other)) => Some(other as i32 - own as i32),
+                _ => None,
+            },
+            hitstun: player.hitstun,
+            blockstun: player.blockstun,
+            combo_hits: player.combo_hits,
+        }
+    });
+    let report = FrameReport {
+        frame: state.frame,
+        frozen: state.super_freeze > 0,
+        players,
+        overlaps: recorder.overlaps,
+    };
+    (next, report)
+}
+
+/// Frames after the current one in which `player` cannot act.
+fn busy_frames(player: &PlayerState, def: &CharacterDef) -> Option<u16> {
+    if player.launched {
+        return None;
+    }
+    // Stun ticks down at the start of a frame and the player acts on the
+    // frame it reaches zero; hitstun runs out before blockstun starts.
+    let stun = (player.hitstun + player.blockstun).saturating_sub(1);
+    let recovery = match player.current_move.and_then(|id| def.find_move(id)) {
+        Some(move_def) => move_def.total_frames() - player.move_frame,
+        None => 0,
+    };
+    Some(stun.max(recovery))
+}
+
+/// One hit of a combo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboHit {
+    pub frame: u32,
+    pub move_id: u8,
+    pub projectile: bool,
+    pub base_damage: i32,
+    pub damage: i32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ComboBreakdown {
+    pub hits: Vec<ComboHit>,
+    pub total_damage: i32,
+}
+
+/// Damage breakdown of the latest combo on each player, fed one
+/// `FrameReport` at a time. A combo starts over on its first hit, so the
+/// breakdown stays readable after the combo ends.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ComboTracker {
+    combos: [ComboBreakdown; MAX_PLAYERS],
+}
+
+impl ComboTracker {
+    pub fn record(&mut self, report: &FrameReport) {
+        for overlap in &report.overlaps {
+            let OverlapResult::Hit {
+                base_damage,
+                damage,
+                combo_hit,
+            } = overlap.result
+            else {
+                continue;
+            };
+            let combo = &mut self.combos[1 - overlap.attacker];
+            if combo_hit == 1 {
+                *combo = ComboBreakdown::default();
+            }
+            combo.hits.push(ComboHit {
+                frame: report.frame,
+                move_id: overlap.move_id,
+                projectile: overlap.projectile,
+                base_damage,
+                damage,
+            });
+            combo.total_damage += damage;
+        }
+    }
+
+    /// Latest combo taken by `defender`.
+    pub fn combo(&self, defender: usize) -> &ComboBreakdown {
+        &self.combos[defender]
+    }
+}
@@ -0,0 +1,123 @@
+//! Execution difficulty of a combo.
+//!
+//! The score only looks at the combo's moves in order, so it comes out the
+//! same whether the moves are read from a `CharacterDef` or from the frame
+//! data registered on-chain, where `combo_mint` stores it with every combo.
+//! It adds four parts:
+//!
+//! - Links: a move input after the previous one fully recovers can start
+//!   within a window of frames and still hit before hitstun ends. Windows
+//!   narrower than `LENIENT_LINK_FRAMES` score a point per missing frame,
+//!   so a one-frame link is the hardest.
+//! - Cancels: a move with no link window can only connect by cutting the
+//!   previous move short, and scores `CANCEL_POINTS`.
+//! - Motions: special inputs score by the directions they take.
+//! - Charge: a charge motion scores `CHARGE_POINTS`, plus
+//!   `CHARGE_PARTITION_POINTS` when the charge has to be held through the
+//!   earlier moves of the combo.
+//!
+//! Every hit is assumed to land on the move's first active frame.
+
+use crate::character::MoveDef;
+use crate::motion::Motion;
+
+/// Link windows this wide or wider score nothing.
+pub const LENIENT_LINK_FRAMES: u16 = 8;
+pub const CANCEL_POINTS: u16 = 5;
+pub const CHARGE_POINTS: u16 = 4;
+pub const CHARGE_PARTITION_POINTS: u16 = 3;
+
+/// What the score needs to know about one move of a combo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboMove {
+    pub startup: u8,
+    pub active: u8,
+    pub recovery: u8,
+    pub hitstun: u16,
+    /// Motion the move is input with, `None` for a plain button press.
+    pub motion: Option<Motion>,
+}
+
+impl ComboMove {
+    pub fn from_def(def: &MoveDef, motion: Option<Motion>) -> Self {
+        ComboMove {
+            startup: def.startup,
+            active: def.active,
+            recovery: def.recovery,
+            hitstun: def.hitstun,
+            motion,
+        }
+    }
+}
+
+/// Score by part; `total` is what combos are ranked by.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Difficulty {
+    pub links: u16,
+    pub cancels: u16,
+    pub motions: u16,
+    pub charge: u16,
+}
+
+impl Difficulty {
+    pub fn total(&self) -> u16 {
+        self.links
+            .saturating_add(self.cancels)
+            .saturating_add(self.motions)
+            .saturating_add(self.charge)
+    }
+}
+
+/// Frames `next` can be input in after `previous` recovers and still hit
+/// before its hitstun ends. Zero or less means only a cancel connects.
+pub fn link_window(previous: &ComboMove, next: &ComboMove) -> i32 {
+    previous.hitstun as i32
+        - previous.active as i32
+        - previous.recovery as i32
+        - next.startup as i32
+        + 1
+}
+
+/// Points a motion adds to the motion part.
+pub fn motion_points(motion: Motion) -> u16 {
+    match motion {
+        Motion::QuarterCircleForward | Motion::QuarterCircleBack => 3,
+        // 623 turns back on itself, which quarter circles do not.
+        Motion::DragonPunch => 4,
+        Motion::FullCircle => 6,
+        // Scored in the charge part instead.
+        Motion::ChargeBackForward => 0,
+    }
+}
+
+pub fn score<I: IntoIterator<Item = ComboMove>>(moves: I) -> Difficulty {
+    let mut difficulty = Difficulty::default();
+    let mut previous: Option<ComboMove> = None;
+    for next in moves {
+        if let Some(previous) = previous {
+            let window = link_window(&previous, &next);
+            if window > 0 {
+                let missing =
+                    LENIENT_LINK_FRAMES.saturating_sub(window.min(u16::MAX as i32) as u16);
+                difficulty.links = difficulty.links.saturating_add(missing);
+            } else {
+                difficulty.cancels = difficulty.cancels.saturating_add(CANCEL_POINTS);
+            }
+        }
+        match next.motion {
+            Some(Motion::ChargeBackForward) => {
+                let mut points = CHARGE_POINTS;
+                if previous.is_some() {
+                    points += CHARGE_PARTITION_POINTS;
+                }
+                difficulty.charge = difficulty.charge.saturating_add(points);
+            }
+            Some(motion) => {
+                difficulty.motions = difficulty.motions.saturating_add(motion_points(motion));
+            }
+            None => {}
+        }
+        previous = Some(next);
+    }
+    difficulty
+}
@@ -0,0 +1,343 @@
+//! Q16.16 fixed-point arithmetic.
+//!
+//! `Fx32` replaces floats everywhere on the simulation path. The default
+//! operators wrap on overflow in both debug and release builds so results do
+//! not depend on the build profile; `saturating_*` and `checked_*` variants are
+//! available where clamping or explicit failure is wanted.
+
+use core::fmt;
+use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+pub struct Fx32(i32);
+
+impl Fx32 {
+    pub const FRAC_BITS: u32 = 16;
+    pub const ZERO: Fx32 = Fx32(0);
+    pub const ONE: Fx32 = Fx32(1 << Self::FRAC_BITS);
+    pub const HALF: Fx32 = Fx32(1 << (Self::FRAC_BITS - 1));
+    pub const MAX: Fx32 = Fx32(i32::MAX);
+    pub const MIN: Fx32 = Fx32(i32::MIN);
+    /// Smallest positive value, 1/65536.
+    pub const EPSILON: Fx32 = Fx32(1);
+    pub const PI: Fx32 = Fx32(205_887);
+    pub const HALF_PI: Fx32 = Fx32(102_944);
+    pub const TWO_PI: Fx32 = Fx32(411_775);
+
+    pub const fn from_raw(raw: i32) -> Self {
+        Fx32(raw)
+    }
+
+    pub const fn raw(self) -> i32 {
+        self.0
+    }
+
+    /// Converts an integer. Values outside `i16` range wrap; use
+    /// `saturating_from_int` when the input is not known to fit.
+    pub const fn from_int(n: i32) -> Self {
+        Fx32(n.wrapping_shl(Self::FRAC_BITS))
+    }
+
+    pub const fn saturating_from_int(n: i32) -> Self {
+        if n > (i32::MAX >> Self::FRAC_BITS) {
+            Self::MAX
+        } else if n < (i32::MIN >> Self::FRAC_BITS) {
+            Self::MIN
+        } else {
+            Fx32(n << Self::FRAC_BITS)
+        }
+    }
+
+    /// `num / den` as a fixed-point value, e.g. `from_ratio(1, 4)` is 0.25.
+    pub const fn from_ratio(num: i32, den: i32) -> Self {
+        Fx32((((num as i64) << Self::FRAC_BITS) / den as i64) as i32)
+    }
+
+    /// Rounds toward negative infinity.
+    pub const fn floor_int(self) -> i32 {
+        self.0 >> Self::FRAC_BITS
+    }
+
+    /// Rounds to the nearest integer, halves away from zero.
+    pub const fn round_int(self) -> i32 {
+        if self.0 >= 0 {
+            ((self.0 as i64 + Self::HALF.0 as i64) >> Self::FRAC_BITS) as i32
+        } else {
+            -(((-(self.0 as i64)) + Self::HALF.0 as i64) >> Self::FRAC_BITS) as i32
+        }
+    }
+
+    pub const fn frac(self) -> Fx32 {
+        Fx32(self.0 & (Self::ONE.0 - 1))
+    }
+
+    pub const fn abs(self) -> Fx32 {
+        Fx32(self.0.wrapping_abs())
+    }
+
+    pub const fn signum(self) -> i32 {
+        self.0.signum()
+    }
+
+    pub const fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub const fn wrapping_mul(self, rhs: Fx32) -> Fx32 {
+        Fx32(((self.0 as i64 * rhs.0 as i64) >> Self::FRAC_BITS) as i32)
+    }
+
+    /// Panics on division by zero, like integer division.
+    pub const fn wrapping_div(self, rhs: Fx32) -> Fx32 {
+        Fx32((((self.0 as i64) << Self::FRAC_BITS) / rhs.0 as i64) as i32)
+    }
+
+    pub const fn saturating_add(self, rhs: Fx32) -> Fx32 {
+        Fx32(self.0.saturating_add(rhs.0))
+    }
+
+    pub const fn saturating_sub(self, rhs: Fx32) -> Fx32 {
+        Fx32(self.0.saturating_sub(rhs.0))
+    }
+
+    pub const fn saturating_mul(self, rhs: Fx32) -> Fx32 {
+        Fx32(saturate((self.0 as i64 * rhs.0 as i64) >> Self::FRAC_BITS))
+    }
+
+    /// Division by zero saturates toward the sign of the dividend.
+    pub const fn saturating_div(self, rhs: Fx32) -> Fx32 {
+        if rhs.0 == 0 {
+            return if self.0 >= 0 { Self::MAX } else { Self::MIN };
+        }
+        Fx32(saturate(
+            ((self.0 as i64) << Self::FRAC_BITS) / rhs.0 as i64,
+        ))
+    }
+
+    pub const fn checked_add(self, rhs: Fx32) -> Option<Fx32> {
+        match self.0.checked_add(rhs.0) {
+            Some(raw) => Some(Fx32(raw)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_sub(self, rhs: Fx32) -> Option<Fx32> {
+        match self.0.checked_sub(rhs.0) {
+            Some(raw) => Some(Fx32(raw)),
+            None => None,
+        }
+    }
+
+    pub const fn checked_mul(self, rhs: Fx32) -> Option<Fx32> {
+        let wide = (self.0 as i64 * rhs.0 as i64) >> Self::FRAC_BITS;
+        if wide > i32::MAX as i64 || wide < i32::MIN as i64 {
+            None
+        } else {
+            Some(Fx32(wide as i32))
+        }
+    }
+
+    pub const fn checked_div(self, rhs: Fx32) -> Option<Fx32> {
+        if rhs.0 == 0 {
+            return None;
+        }
+        let wide = ((self.0 as i64) << Self::FRAC_BITS) / rhs.0 as i64;
+        if wide > i32::MAX as i64 || wide < i32::MIN as i64 {
+            None
+        } else {
+            Some(Fx32(wide as i32))
+        }
+    }
+
+    /// Square root, rounded down. Negative inputs return zero.
+    pub const fn sqrt(self) -> Fx32 {
+        if self.0 <= 0 {
+            return Self::ZERO;
+        }
+        Fx32(isqrt_u64((self.0 as u64) << Self::FRAC_BITS) as i32)
+    }
+
+    /// Sine of an angle in radians, from a 1024-step table with linear
+    /// interpolation. Accurate to within a few units in the last place.
+    pub const fn sin(self) -> Fx32 {
+        sin_steps(self.to_steps())
+    }
+
+    /// Cosine of an angle in radians; shares the sine table a quarter turn on.
+    pub const fn cos(self) -> Fx32 {
+        sin_steps(self.to_steps() + ((QUARTER as i64) << Self::FRAC_BITS))
+    }
+
+    /// Maps radians onto `SIN_STEPS` table steps per turn, keeping 16
+    /// fractional bits for interpolation.
+    const fn to_steps(self) -> i64 {
+        ((self.0 as i64) << (Self::FRAC_BITS + SIN_STEP_BITS)) / Self::TWO_PI.0 as i64
+    }
+}
+
+const fn sin_steps(steps: i64) -> Fx32 {
+    let step = (steps >> Fx32::FRAC_BITS) as i32;
+    let frac = steps & (Fx32::ONE.0 as i64 - 1);
+    let a = sin_step(step) as i64;
+    let b = sin_step(step.wrapping_add(1)) as i64;
+    Fx32((a + (((b - a) * frac) >> Fx32::FRAC_BITS)) as i32)
+}
+
+const fn saturate(wide: i64) -> i32 {
+    if wide > i32::MAX as i64 {
+        i32::MAX
+    } else if wide < i32::MIN as i64 {
+        i32::MIN
+    } else {
+        wide as i32
+    }
+}
+
+/// Integer square root by Newton iteration, rounded down.
+const fn isqrt_u64(n: u64) -> u64 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = x / 2 + 1;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+const SIN_STEP_BITS: u32 = 10;
+const SIN_STEPS: i32 = 1 << SIN_STEP_BITS;
+const QUARTER: i32 = SIN_STEPS / 4;
+
+/// `sin(i * pi / 512)` in Q16.16 for the first quarter turn, inclusive.
+const SIN_QUARTER: [i32; QUARTER as usize + 1] = [
+    0, 402, 804, 1206, 1608, 2010, 2412, 2814, 3216, 3617, 4019, 4420, 4821, 5222, 5623, 6023,
+    6424, 6824, 7224, 7623, 8022, 8421, 8820, 9218, 9616, 10014, 10411, 10808, 11204, 11600, 11996,
+    12391, 12785, 13180, 13573, 13966, 14359, 14751, 15143, 15534, 15924, 16314, 16703, 17091,
+    17479, 17867, 18253, 18639, 19024, 19409, 19792, 20175, 20557, 20939, 21320, 21699, 22078,
+    22457, 22834, 23210, 23586, 23961, 24335, 24708, 25080, 25451, 25821, 26190, 26558, 26925,
+    27291, 27656, 28020, 28383, 28745, 29106, 29466, 29824, 30182, 30538, 30893, 31248, 31600,
+    31952, 32303, 32652, 33000, 33347, 33692, 34037, 34380, 34721, 35062, 35401, 35738, 36075,
+    36410, 36744, 37076, 37407, 37736, 38064, 38391, 38716, 39040, 39362, 39683, 40002, 40320,
+    40636, 40951, 41264, 41576, 41886, 42194, 42501, 42806, 43110, 43412, 43713, 44011, 44308,
+    44604, 44898, 45190, 45480, 45769, 46056, 46341, 46624, 46906, 47186, 47464, 47741, 48015,
+    48288, 48559, 48828, 49095, 49361, 49624, 49886, 50146, 50404, 50660, 50914, 51166, 51417,
+    51665, 51911, 52156, 52398, 52639, 52878, 53114, 53349, 53581, 53812, 54040, 54267, 54491,
+    54714, 54934, 55152, 55368, 55582, 55794, 56004, 56212, 56418, 56621, 56823, 57022, 57219,
+    57414, 57607, 57798, 57986, 58172, 58356, 58538, 58718, 58896, 59071, 59244, 59415, 59583,
+    59750, 59914, 60075, 60235, 60392, 60547, 60700, 60851, 60999, 61145, 61288, 61429, 61568,
+    61705, 61839, 61971, 62101, 62228, 62353, 62476, 62596, 62714, 62830, 62943, 63054, 63162,
+    63268, 63372, 63473, 63572, 63668, 63763, 63854, 63944, 64031, 64115, 64197, 64277, 64354,
+    64429, 64501, 64571, 64639, 64704, 64766, 64827, 64884, 64940, 64993, 65043, 65091, 65137,
+    65180, 65220, 65259, 65294, 65328, 65358, 65387, 65413, 65436, 65457, 65476, 65492, 65505,
+    65516, 65525, 65531, 65535, 65536,
+];
+
+/// Table sine for a whole step index over the full circle.
+const fn sin_step(step: i32) -> i32 {
+    let step = step.rem_euclid(SIN_STEPS);
+    let quadrant = step / QUARTER;
+    let offset = step % QUARTER;
+    match quadrant {
+        0 => SIN_QUARTER[offset as usize],
+        1 => SIN_QUARTER[(QUARTER - offset) as usize],
+        2 => -SIN_QUARTER[offset as usize],
+        _ => -SIN_QUARTER[(QUARTER - offset) as usize],
+    }
+}
+
+impl fmt::Debug for Fx32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Fx32({})", self)
+    }
+}
+
+/// Prints five decimal places, truncated toward zero without going through
+/// floats. The exact expansion of a Q16.16 value can take 16; five are
+/// already finer than `EPSILON`, so no two values print the same.
+impl fmt::Display for Fx32 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let raw = self.0 as i64;
+        let sign = if raw < 0 { "-" } else { "" };
+        let abs = raw.abs();
+        let whole = abs >> Self::FRAC_BITS;
+        let frac = ((abs & 0xFFFF) * 100_000) >> Self::FRAC_BITS;
+        write!(f, "{}{}.{:05}", sign, whole, frac)
+    }
+}
+
+impl Add for Fx32 {
+    type Output = Fx32;
+    fn add(self, rhs: Fx32) -> Fx32 {
+        Fx32(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl Sub for Fx32 {
+    type Output = Fx32;
+    fn sub(self, rhs: Fx32) -> Fx32 {
+        Fx32(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl Mul for Fx32 {
+    type Output = Fx32;
+    fn mul(self, rhs: Fx32) -> Fx32 {
+        self.wrapping_mul(rhs)
+    }
+}
+
+impl Div for Fx32 {
+    type Output = Fx32;
+    fn div(self, rhs: Fx32) -> Fx32 {
+        self.wrapping_div(rhs)
+    }
+}
+
+impl Mul<i32> for Fx32 {
+    type Output = Fx32;
+    fn mul(self, rhs: i32) -> Fx32 {
+        Fx32(self.0.wrapping_mul(rhs))
+    }
+}
+
+impl Div<i32> for Fx32 {
+    type Output = Fx32;
+    fn div(self, rhs: i32) -> Fx32 {
+        Fx32(self.0.wrapping_div(rhs))
+    }
+}
+
+impl Neg for Fx32 {
+    type Output = Fx32;
+    fn neg(self) -> Fx32 {
+        Fx32(self.0.wrapping_neg())
+    }
+}
+
+impl AddAssign for Fx32 {
+    fn add_assign(&mut self, rhs: Fx32) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Fx32 {
+    fn sub_assign(&mut self, rhs: Fx32) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for Fx32 {
+    fn mul_assign(&mut self, rhs: Fx32) {
+        *self = *self * rhs;
+    }
+}
+
+impl DivAssign for Fx32 {
+    fn div_assign(&mut self, rhs: Fx32) {
+        *self = *self / rhs;
+    }
+}
@@ -0,0 +1,67 @@
+//! FNV-1a state hashing for desync detection, matching `StateHash.cs`.
+
+use crate::state::GameState;
+
+const FNV_PRIME: u32 = 16_777_619;
+const FNV_OFFSET_BASIS: u32 = 2_166_136_261;
+
+fn fnv1a(hash: u32, data: u32) -> u32 {
+    (hash ^ data).wrapping_mul(FNV_PRIME)
+}
+
+/// Hashes every field of the state in a fixed order.
+pub fn state_hash(state: &GameState) -> u32 {
+    let mut hash = FNV_OFFSET_BASIS;
+    hash = fnv1a(hash, state.frame);
+    hash = fnv1a(hash, state.stage.left_wall.raw() as u32);
+    hash = fnv1a(hash, state.stage.right_wall.raw() as u32);
+    hash = fnv1a(hash, state.stage.floor_y.raw() as u32);
+
+    for player in &state.players {
+        hash = fnv1a(hash, player.character_id as u32);
+        hash = fnv1a(hash, player.pos_x.raw() as u32);
+        hash = fnv1a(hash, player.pos_y.raw() as u32);
+        hash = fnv1a(hash, player.vel_x.raw() as u32);
+        hash = fnv1a(hash, player.vel_y.raw() as u32);
+        hash = fnv1a(hash, player.facing.sign() as u32);
+        hash = fnv1a(hash, player.grounded as u32);
+        hash = fnv1a(hash, player.guarding as u32);
+        hash = fnv1a(hash, player.health as u32);
+        hash = fnv1a(hash, player.current_move.map_or(u32::MAX, u32::from));
+        hash = fnv1a(hash, player.move_frame as u32);
+        hash = fnv1a(hash, player.move_connected as u32);
+        hash = fnv1a(hash, player.hitstun as u32);
+        hash = fnv1a(hash, player.blockstun as u32);
+        hash = fnv1a(hash, player.hit_spark as u32);
+        hash = fnv1a(hash, player.combo_hits as u32);
+        hash = fnv1a(hash, player.meter as u32);
+        hash = fnv1a(hash, player.launched as u32);
+        hash = fnv1a(hash, player.juggle as u32);
+        hash = fnv1a(hash, player.invincible as u32);
+    }
+
+    for projectile in &state.projectiles {
+        match projectile {
+            Some(p) => {
+                hash = fnv1a(hash, p.move_id as u32);
+                hash = fnv1a(hash, p.pos_x.raw() as u32);
+                hash = fnv1a(hash, p.pos_y.raw() as u32);
+                hash = fnv1a(hash, p.vel_x.raw() as u32);
+                hash = fnv1a(hash, p.facing.sign() as u32);
+                hash = fnv1a(hash, p.frames_left as u32);
+            }
+            None => hash = fnv1a(hash, u32::MAX),
+        }
+    }
+
+    for word in state.rng.state() {
+        hash = fnv1a(hash, word as u32);
+        hash = fnv1a(hash, (word >> 32) as u32);
+    }
+
+    hash = fnv1a(hash, state.proration.scale_percent as u32);
+    hash = fnv1a(hash, state.proration.floor_percent as u32);
+    hash = fnv1a(hash, state.super_freeze as u32);
+
+    hash
+}
@@ -0,0 +1,201 @@
+//! Packed per-frame input.
+//!
+//! Bits 0-3 hold the four directions and bits 4-9 the six buttons; the low
+//! eight bits keep the values of `InputBits` in the C# engine. The remaining
+//! bits are reserved and rejected by `Input::from_bits`.
+//!
+//! Long input streams are diff-compressed: only frames where the input
+//! changes are written, each as a LEB128 run length followed by the new
+//! input as a little-endian `u16`.
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::{BitAnd, BitOr, BitOrAssign};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[repr(transparent)]
+pub struct Input(u16);
+
+impl Input {
+    pub const NONE: Input = Input(0);
+
+    pub const UP: Input = Input(1 << 0);
+    pub const DOWN: Input = Input(1 << 1);
+    pub const LEFT: Input = Input(1 << 2);
+    pub const RIGHT: Input = Input(1 << 3);
+
+    pub const JUMP: Input = Input(1 << 4);
+    pub const ATTACK: Input = Input(1 << 5);
+    pub const SPECIAL: Input = Input(1 << 6);
+    pub const DEFEND: Input = Input(1 << 7);
+    pub const GRAB: Input = Input(1 << 8);
+    pub const TAUNT: Input = Input(1 << 9);
+
+    pub const DIRECTIONS: Input = Input(0x000f);
+    pub const BUTTONS: Input = Input(0x03f0);
+    pub const ALL: Input = Input(0x03ff);
+
+    /// Parses raw bits, rejecting any reserved bit.
+    pub const fn from_bits(bits: u16) -> Option<Input> {
+        if bits & !Self::ALL.0 == 0 {
+            Some(Input(bits))
+        } else {
+            None
+        }
+    }
+
+    /// Parses raw bits, dropping reserved ones.
+    pub const fn from_bits_truncate(bits: u16) -> Input {
+        Input(bits & Self::ALL.0)
+    }
+
+    pub const fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub const fn contains(self, other: Input) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn intersects(self, other: Input) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn directions(self) -> Input {
+        Input(self.0 & Self::DIRECTIONS.0)
+    }
+
+    pub const fn buttons(self) -> Input {
+        Input(self.0 & Self::BUTTONS.0)
+    }
+
+    /// Resolves simultaneous opposite directions: left + right becomes
+    /// neutral and up + down becomes up.
+    pub const fn socd_cleaned(self) -> Input {
+        let mut bits = self.0;
+        if bits & Self::LEFT.0 != 0 && bits & Self::RIGHT.0 != 0 {
+            bits &= !(Self::LEFT.0 | Self::RIGHT.0);
+        }
+        if bits & Self::UP.0 != 0 && bits & Self::DOWN.0 != 0 {
+            bits &= !Self::DOWN.0;
+        }
+        Input(bits)
+    }
+
+    /// -1 for left, 1 for right, 0 for neutral. Expects a cleaned input.
+    pub const fn horizontal(self) -> i32 {
+        (self.contains(Self::RIGHT) as i32) - (self.contains(Self::LEFT) as i32)
+    }
+
+    pub const fn to_le_bytes(self) -> [u8; 2] {
+        self.0.to_le_bytes()
+    }
+
+    pub const fn from_le_bytes(bytes: [u8; 2]) -> Option<Input> {
+        Self::from_bits(u16::from_le_bytes(bytes))
+    }
+}
+
+impl BitOr for Input {
+    type Output = Input;
+
+    fn bitor(self, rhs: Input) -> Input {
+        Input(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Input {
+    fn bitor_assign(&mut self, rhs: Input) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for Input {
+    type Output = Input;
+
+    fn bitand(self, rhs: Input) -> Input {
+        Input(self.0 & rhs.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputStreamError {
+    Truncated,
+    /// A run length of zero, or one that overflows `u32`.
+    InvalidRun,
+    InvalidBits(u16),
+}
+
+impl fmt::Display for InputStreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputStreamError::Truncated => write!(f, "input stream truncated"),
+            InputStreamError::InvalidRun => write!(f, "invalid run length in input stream"),
+            InputStreamError::InvalidBits(bits) => {
+                write!(f, "reserved input bits set: {:#06x}", bits)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InputStreamError {}
+
+/// Diff-compresses an input stream into (run length, input) records.
+pub fn encode_stream(inputs: &[Input]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut rest = inputs;
+    while let Some(&first) = rest.first() {
+        let run = rest.iter().take_while(|&&input| input == first).count();
+        write_varint(&mut out, run as u32);
+        out.extend_from_slice(&first.to_le_bytes());
+        rest = &rest[run..];
+    }
+    out
+}
+
+pub fn decode_stream(bytes: &[u8]) -> Result<Vec<Input>, InputStreamError> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < bytes.len() {
+        let run = read_varint(bytes, &mut pos)?;
+        if run == 0 {
+            return Err(InputStreamError::InvalidRun);
+        }
+        let raw = bytes.get(pos..pos + 2).ok_or(InputStreamError::Truncated)?;
+        let bits = u16::from_le_bytes([raw[0], raw[1]]);
+        let input = Input::from_bits(bits).ok_or(InputStreamError::InvalidBits(bits))?;
+        pos += 2;
+        out.extend(core::iter::repeat(input).take(run as usize));
+    }
+    Ok(out)
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> Result<u32, InputStreamError> {
+    let mut value: u32 = 0;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes.get(*pos).ok_or(InputStreamError::Truncated)?;
+        *pos += 1;
+        let chunk = (byte & 0x7f) as u32;
+        if shift == 28 && chunk > 0x0f {
+            return Err(InputStreamError::InvalidRun);
+        }
+        value |= chunk << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(InputStreamError::InvalidRun)
+}
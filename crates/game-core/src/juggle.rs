@@ -0,0 +1,41 @@
+//! Launchers and air juggles.
+//!
+//! A hit with a non-zero `launch`, or any hit on an airborne defender, knocks
+//! the defender into the air along the move's knockback vector of
+//! `(pushback, launch)` and leaves them launched: stunned until they land.
+//! Each such hit adds the move's `juggle_cost` to the defender's juggle
+//! points. A launched defender falls faster with every point, and a hit
+//! whose cost would take them past `JUGGLE_LIMIT` passes through them, so
+//! every air combo ends.
+//!
+//! The on-chain combo simulator uses the same limit and gravity scaling to
+//! reject air routes where the opponent would already have landed.
+
+use crate::character::MoveDef;
+use crate::fixed::Fx32;
+use crate::state::PlayerState;
+
+pub const JUGGLE_LIMIT: u8 = 10;
+/// Extra gravity per juggle point, in percent of the base gravity.
+pub const JUGGLE_GRAVITY_PERCENT: i32 = 10;
+
+/// Whether `move_def` can still hit `defender`.
+pub fn can_juggle(defender: &PlayerState, move_def: &MoveDef) -> bool {
+    !defender.launched || defender.juggle.saturating_add(move_def.juggle_cost) <= JUGGLE_LIMIT
+}
+
+/// Gravity acting on a launched player with `juggle` points.
+pub fn juggle_gravity(gravity: Fx32, juggle: u8) -> Fx32 {
+    gravity * (100 + juggle as i32 * JUGGLE_GRAVITY_PERCENT) / 100
+}
+
+/// Applies the vertical part of an unblocked hit's knockback.
+pub fn apply_launch(defender: &mut PlayerState, move_def: &MoveDef) {
+    if move_def.launch == Fx32::ZERO && defender.grounded {
+        return;
+    }
+    defender.vel_y = move_def.launch;
+    defender.grounded = false;
+    defender.launched = true;
+    defender.juggle = defender.juggle.saturating_add(move_def.juggle_cost);
+}
@@ -0,0 +1,72 @@
+//! Deterministic fighting game simulation core.
+//!
+//! Every value on the simulation path is an integer or a Q16.16 `Fx32` in
+//! pixel units, floats are rejected by lint, and `simulate_frame` never
+//! allocates, so the same inputs always produce the same state hash on every
+//! platform.
+//!
+//! The crate is `no_std` unless the default `std` feature is on, so the same
+//! rules compile into native and WASM clients and into on-chain verifiers.
+//! Only input stream compression allocates, through `alloc`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+#![deny(clippy::float_arithmetic)]
+
+extern crate alloc;
+
+pub mod character;
+pub mod checksum;
+pub mod collision;
+#[cfg(feature = "debug")]
+pub mod debug;
+pub mod difficulty;
+pub mod fixed;
+pub mod hash;
+pub mod input;
+pub mod juggle;
+pub mod lockstep;
+pub mod meter;
+pub mod motion;
+pub mod netcode;
+pub mod netsim;
+pub mod pool;
+pub mod proration;
+pub mod rng;
+pub mod sim;
+pub mod snapshot;
+pub mod spectate;
+pub mod state;
+pub mod team;
+pub mod transport;
+pub mod trials;
+
+pub use character::{Cancel, CharacterDef, MoveDef, ProjectileDef, Rect};
+pub use checksum::{checksum, FrameChecksum};
+pub use difficulty::{ComboMove, Difficulty};
+pub use fixed::Fx32;
+pub use hash::state_hash;
+pub use input::Input;
+pub use lockstep::{LockstepAdvance, LockstepConfig, LockstepError, LockstepSession};
+pub use meter::{Carryover, MoveMeter};
+pub use motion::{InputBuffer, Motion, MotionConfig};
+pub use netcode::{FrameAdvance, Session, SessionConfig, SessionError, SessionStats};
+pub use netsim::{NetworkConditions, NetworkStats, SimNetwork, SimTransport};
+pub use pool::SnapshotPool;
+pub use proration::Proration;
+pub use rng::Rng;
+pub use sim::{simulate_frame, step};
+pub use snapshot::{SnapshotError, SNAPSHOT_LEN};
+pub use spectate::{
+    Publisher, SpectateConfig, SpectateError, SpectateHost, Spectator, SpectatorMessage,
+};
+pub use state::{Facing, GameState, PlayerState, Projectile, Stage};
+pub use team::TeamBattle;
+#[cfg(feature = "std")]
+pub use transport::UdpTransport;
+pub use transport::{
+    Channel, ChannelConfig, ChannelError, ChannelEvent, ChannelStats, MemoryTransport, Transport,
+};
+pub use trials::{ComboTrial, TrialCompletion, TrialError};
+
+/// Number of players in a match.
+pub const MAX_PLAYERS: usize = 2;
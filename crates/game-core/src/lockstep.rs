@@ -0,0 +1,220 @@
+//! Lockstep networking for LAN play.
+//!
+//! A `LockstepSession` never predicts: `advance_frame` only simulates once
+//! both players' inputs for the frame are known, so every simulated frame is
+//! final and no state is ever rolled back. This suits low-latency setups
+//! such as tournament LANs, and because it runs the same `step` as
+//! `Session` on confirmed inputs only, its states are the reference a
+//! rollback session must converge to.
+//!
+//! The caller passes the current time to `advance_frame`. While a frame is
+//! waiting on the remote input the session reports how long it has waited,
+//! and once that exceeds `disconnect_timeout_ms` it reports the peer as
+//! disconnected. Local inputs can be delayed like in `Session` to cover the
+//! round trip.
+
+use core::fmt;
+
+use crate::character::CharacterDef;
+use crate::input::Input;
+use crate::netcode::{MAX_INPUT_DELAY, RING_SIZE};
+use crate::sim::step;
+use crate::state::GameState;
+use crate::MAX_PLAYERS;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LockstepConfig {
+    /// Index of the player driven by this client.
+    pub local_player: usize,
+    /// Frames inputs are delayed by. Both peers must use the same delay,
+    /// since the first `input_delay` frames run with no input from either.
+    pub input_delay: u8,
+    /// How long a frame may wait on the remote input before the peer is
+    /// considered gone.
+    pub disconnect_timeout_ms: u64,
+}
+
+impl Default for LockstepConfig {
+    fn default() -> Self {
+        LockstepConfig {
+            local_player: 0,
+            input_delay: 1,
+            disconnect_timeout_ms: 5000,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockstepError {
+    InvalidConfig,
+    /// `advance_frame` was called before `add_local_input` for this frame.
+    MissingLocalInput {
+        frame: u32,
+    },
+    /// A remote input skipped ahead of the next expected frame.
+    InputGap {
+        expected: u32,
+        received: u32,
+    },
+    /// A remote input is too far ahead of the current frame to buffer.
+    InputTooFarAhead {
+        frame: u32,
+    },
+    /// No remote input for `frame` arrived within the timeout.
+    Disconnected {
+        frame: u32,
+        waited_ms: u64,
+    },
+}
+
+impl fmt::Display for LockstepError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LockstepError::InvalidConfig => write!(f, "invalid lockstep configuration"),
+            LockstepError::MissingLocalInput { frame } => {
+                write!(f, "no local input for frame {}", frame)
+            }
+            LockstepError::InputGap { expected, received } => write!(
+                f,
+                "remote input for frame {} arrived before frame {}",
+                received, expected
+            ),
+            LockstepError::InputTooFarAhead { frame } => {
+                write!(f, "remote input for frame {} is too far ahead", frame)
+            }
+            LockstepError::Disconnected { frame, waited_ms } => write!(
+                f,
+                "peer disconnected: no input for frame {} after {} ms",
+                frame, waited_ms
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LockstepError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LockstepAdvance {
+    /// Frame that was just simulated.
+    Advanced { frame: u32 },
+    /// The remote input for `frame` has not arrived yet.
+    Waiting { frame: u32, waited_ms: u64 },
+}
+
+pub struct LockstepSession<'a> {
+    config: LockstepConfig,
+    defs: [&'a CharacterDef<'a>; MAX_PLAYERS],
+    state: GameState,
+    current_frame: u32,
+    local_inputs: [Input; RING_SIZE],
+    remote_inputs: [Input; RING_SIZE],
+    /// Highest frame for which the local input is known.
+    last_local: Option<u32>,
+    /// Highest frame for which the remote input is known.
+    last_remote: Option<u32>,
+    /// When the current frame started waiting on the remote input.
+    waiting_since: Option<u64>,
+}
+
+impl<'a> LockstepSession<'a> {
+    pub fn new(
+        initial: GameState,
+        defs: [&'a CharacterDef<'a>; MAX_PLAYERS],
+        config: LockstepConfig,
+    ) -> Result<Self, LockstepError> {
+        if config.local_player >= MAX_PLAYERS || config.input_delay > MAX_INPUT_DELAY {
+            return Err(LockstepError::InvalidConfig);
+        }
+        Ok(LockstepSession {
+            config,
+            defs,
+            state: initial,
+            current_frame: 0,
+            local_inputs: [Input::NONE; RING_SIZE],
+            remote_inputs: [Input::NONE; RING_SIZE],
+            last_local: (config.input_delay as u32).checked_sub(1),
+            last_remote: (config.input_delay as u32).checked_sub(1),
+            waiting_since: None,
+        })
+    }
+
+    pub fn config(&self) -> &LockstepConfig {
+        &self.config
+    }
+
+    /// State after every frame simulated so far, all of them confirmed.
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// Next frame to be simulated.
+    pub fn current_frame(&self) -> u32 {
+        self.current_frame
+    }
+
+    /// Sets the local input for the current frame plus the input delay and
+    /// returns that frame, for the caller to send the input to the peer
+    /// tagged with it. While the session waits on the remote input the
+    /// target frame already has its input, which may have been sent, so the
+    /// call is ignored and returns `None`.
+    pub fn add_local_input(&mut self, input: Input) -> Option<u32> {
+        let frame = self.current_frame + self.config.input_delay as u32;
+        if self.last_local.map_or(false, |last| last >= frame) {
+            return None;
+        }
+        self.local_inputs[slot(frame)] = input;
+        self.last_local = Some(frame);
+        Some(frame)
+    }
+
+    /// Records the remote player's input for `frame`. Inputs must arrive in
+    /// order; duplicates of already-received frames are ignored.
+    pub fn add_remote_input(&mut self, frame: u32, input: Input) -> Result<(), LockstepError> {
+        let expected = self.last_remote.map_or(0, |f| f + 1);
+        if frame < expected {
+            return Ok(());
+        }
+        if frame > expected {
+            return Err(LockstepError::InputGap {
+                expected,
+                received: frame,
+            });
+        }
+        if frame >= self.current_frame + RING_SIZE as u32 {
+            return Err(LockstepError::InputTooFarAhead { frame });
+        }
+        self.remote_inputs[slot(frame)] = input;
+        self.last_remote = Some(frame);
+        Ok(())
+    }
+
+    /// Simulates the current frame if both inputs are known, otherwise
+    /// reports how long it has been waiting at `now_ms`.
+    pub fn advance_frame(&mut self, now_ms: u64) -> Result<LockstepAdvance, LockstepError> {
+        let frame = self.current_frame;
+        if self.last_local.map_or(true, |last| last < frame) {
+            return Err(LockstepError::MissingLocalInput { frame });
+        }
+        if self.last_remote.map_or(true, |last| last < frame) {
+            let since = *self.waiting_since.get_or_insert(now_ms);
+            let waited_ms = now_ms.saturating_sub(since);
+            if waited_ms > self.config.disconnect_timeout_ms {
+                return Err(LockstepError::Disconnected { frame, waited_ms });
+            }
+            return Ok(LockstepAdvance::Waiting { frame, waited_ms });
+        }
+
+        let mut inputs = [Input::NONE; MAX_PLAYERS];
+        inputs[self.config.local_player] = self.local_inputs[slot(frame)];
+        inputs[1 - self.config.local_player] = self.remote_inputs[slot(frame)];
+        self.state = step(&self.state, inputs, self.defs);
+        self.current_frame += 1;
+        self.waiting_since = None;
+        Ok(LockstepAdvance::Advanced { frame })
+    }
+}
+
+fn slot(frame: u32) -> usize {
+    frame as usize % RING_SIZE
+}
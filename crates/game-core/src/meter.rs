@@ -0,0 +1,71 @@
+//! Super meter.
+//!
+//! Moves build meter for their user when they hit, when they are blocked, or
+//! when they finish without their hitbox connecting. A projectile move pays
+//! its whiff gain when the move ends and its projectile pays the hit or block
+//! gain when it lands. Moves with a cost can only start when the user has the
+//! meter for it, and a move with super freeze frames stops the whole
+//! simulation for that long after it starts.
+//!
+//! Gain is capped at `MAX_METER`, which the on-chain combo simulator also
+//! applies when it derives a combo's meter gain.
+
+use crate::state::GameState;
+
+pub const MAX_METER: u16 = 100;
+
+/// Meter behaviour of a single move.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MoveMeter {
+    pub on_hit: u16,
+    pub on_block: u16,
+    pub on_whiff: u16,
+    /// Meter spent to start the move, e.g. for EX moves and supers.
+    pub cost: u16,
+    /// Frames the simulation freezes for after the move starts.
+    pub super_freeze: u8,
+}
+
+impl MoveMeter {
+    pub const NONE: MoveMeter = MoveMeter {
+        on_hit: 0,
+        on_block: 0,
+        on_whiff: 0,
+        cost: 0,
+        super_freeze: 0,
+    };
+}
+
+/// What happens to each player's meter when a new round starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Carryover {
+    /// Every round starts with an empty meter.
+    Reset,
+    /// Meter carries over in full.
+    Keep,
+    /// This percent of the meter carries over, rounded down.
+    Percent(u8),
+}
+
+impl Carryover {
+    pub fn apply(self, meter: u16) -> u16 {
+        match self {
+            Carryover::Reset => 0,
+            Carryover::Keep => meter,
+            Carryover::Percent(percent) => (meter as u32 * percent.min(100) as u32 / 100) as u16,
+        }
+    }
+}
+
+/// Adds `amount` to a meter, capped at `MAX_METER`.
+pub fn gain(meter: u16, amount: u16) -> u16 {
+    meter.saturating_add(amount).min(MAX_METER)
+}
+
+/// Carries meter from the final state of one round into the initial state
+/// of the next.
+pub fn carry_over(previous: &GameState, next: &mut GameState, rule: Carryover) {
+    for (player, last) in next.players.iter_mut().zip(&previous.players) {
+        player.meter = rule.apply(last.meter);
+    }
+}
@@ -0,0 +1,210 @@
+//! Motion input detection over a rolling input buffer.
+//!
+//! Directions are read in numpad notation relative to facing (6 is forward,
+//! 4 is back, 2 is down). Sequence motions match when their directions
+//! appear in order within the leniency window ending on the current frame;
+//! other directions in between are ignored, so 6323 still reads as a 623.
+
+use crate::input::Input;
+use crate::state::Facing;
+
+/// Frames of input history kept by `InputBuffer`.
+pub const BUFFER_LEN: usize = 64;
+
+/// Fixed-size history of the most recent inputs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InputBuffer {
+    inputs: [Input; BUFFER_LEN],
+    /// Slot the next input is written to.
+    head: usize,
+    len: usize,
+}
+
+impl Default for InputBuffer {
+    fn default() -> Self {
+        InputBuffer {
+            inputs: [Input::NONE; BUFFER_LEN],
+            head: 0,
+            len: 0,
+        }
+    }
+}
+
+impl InputBuffer {
+    pub fn push(&mut self, input: Input) {
+        self.inputs[self.head] = input;
+        self.head = (self.head + 1) % BUFFER_LEN;
+        self.len = (self.len + 1).min(BUFFER_LEN);
+    }
+
+    /// Input from `frames_ago` frames back, 0 being the latest.
+    pub fn get(&self, frames_ago: usize) -> Option<Input> {
+        if frames_ago >= self.len {
+            return None;
+        }
+        Some(self.inputs[(self.head + BUFFER_LEN - 1 - frames_ago) % BUFFER_LEN])
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Motion {
+    /// 236
+    QuarterCircleForward,
+    /// 214
+    QuarterCircleBack,
+    /// 623
+    DragonPunch,
+    /// Back held, then forward.
+    ChargeBackForward,
+    /// Every cardinal direction within the window.
+    FullCircle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MotionConfig {
+    /// Frames a quarter-circle or dragon punch may take.
+    pub motion_window: u8,
+    /// Frames a full circle may take.
+    pub circle_window: u8,
+    /// Frames back must be held to count as charged.
+    pub charge_frames: u8,
+    /// Frames allowed between releasing back and pressing forward.
+    pub charge_leniency: u8,
+}
+
+impl Default for MotionConfig {
+    fn default() -> Self {
+        MotionConfig {
+            motion_window: 12,
+            circle_window: 30,
+            charge_frames: 30,
+            charge_leniency: 8,
+        }
+    }
+}
+
+impl Motion {
+    /// Every motion, in the order of `code`.
+    pub const ALL: [Motion; 5] = [
+        Motion::QuarterCircleForward,
+        Motion::QuarterCircleBack,
+        Motion::DragonPunch,
+        Motion::ChargeBackForward,
+        Motion::FullCircle,
+    ];
+
+    /// Byte code for frame data that stores motions compactly, such as the
+    /// on-chain move registry. Code 0 is left for moves without a motion.
+    pub const fn code(self) -> u8 {
+        self as u8 + 1
+    }
+
+    /// Inverse of `code`; `None` for 0 and for unknown codes.
+    pub fn from_code(code: u8) -> Option<Motion> {
+        let index = code.checked_sub(1)? as usize;
+        Motion::ALL.get(index).copied()
+    }
+
+    /// Directions in numpad notation, for sequence motions.
+    pub const fn sequence(self) -> Option<&'static [u8]> {
+        match self {
+            Motion::QuarterCircleForward => Some(&[2, 3, 6]),
+            Motion::QuarterCircleBack => Some(&[2, 1, 4]),
+            Motion::DragonPunch => Some(&[6, 2, 3]),
+            Motion::ChargeBackForward | Motion::FullCircle => None,
+        }
+    }
+
+    /// Fewest frames the motion can physically be input in.
+    pub fn min_frames(self, config: &MotionConfig) -> u32 {
+        match self {
+            Motion::ChargeBackForward => config.charge_frames as u32 + 1,
+            Motion::FullCircle => 4,
+            _ => self.sequence().map_or(0, |seq| seq.len() as u32),
+        }
+    }
+
+    /// Whether the buffer holds the motion, finishing within its window of
+    /// the latest frame.
+    pub fn detect(self, buffer: &InputBuffer, facing: Facing, config: &MotionConfig) -> bool {
+        match self {
+            Motion::ChargeBackForward => detect_charge(buffer, facing, config),
+            Motion::FullCircle => detect_circle(buffer, config),
+            _ => self.sequence().map_or(false, |seq| {
+                detect_sequence(buffer, facing, seq, config.motion_window as usize)
+            }),
+        }
+    }
+}
+
+/// Numpad direction of an input relative to facing.
+pub fn numpad(input: Input, facing: Facing) -> u8 {
+    let input = input.socd_cleaned();
+    let row = if input.contains(Input::DOWN) {
+        1
+    } else if input.contains(Input::UP) {
+        7
+    } else {
+        4
+    };
+    let column = match input.horizontal() * facing.sign() {
+        -1 => 0,
+        0 => 1,
+        _ => 2,
+    };
+    row + column
+}
+
+fn is_back(direction: u8) -> bool {
+    matches!(direction, 1 | 4 | 7)
+}
+
+fn is_forward(direction: u8) -> bool {
+    matches!(direction, 3 | 6 | 9)
+}
+
+fn detect_sequence(buffer: &InputBuffer, facing: Facing, sequence: &[u8], window: usize) -> bool {
+    let mut remaining = sequence.len();
+    for frames_ago in 0..window.min(buffer.len()) {
+        let direction = numpad(buffer.get(frames_ago).unwrap_or_default(), facing);
+        if direction == sequence[remaining - 1] {
+            remaining -= 1;
+            if remaining == 0 {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+fn detect_charge(buffer: &InputBuffer, facing: Facing, config: &MotionConfig) -> bool {
+    let direction_at = |frames_ago| numpad(buffer.get(frames_ago).unwrap_or_default(), facing);
+    if buffer.is_empty() || !is_forward(direction_at(0)) {
+        return false;
+    }
+
+    let leniency_end = (config.charge_leniency as usize + 1).min(buffer.len());
+    let Some(release) = (1..leniency_end).find(|&ago| is_back(direction_at(ago))) else {
+        return false;
+    };
+    let held = (release..buffer.len())
+        .take_while(|&ago| is_back(direction_at(ago)))
+        .count();
+    held >= config.charge_frames as usize
+}
+
+fn detect_circle(buffer: &InputBuffer, config: &MotionConfig) -> bool {
+    let mut seen = Input::NONE;
+    for frames_ago in 0..(config.circle_window as usize).min(buffer.len()) {
+        seen |= buffer.get(frames_ago).unwrap_or_default().socd_cleaned() & Input::DIRECTIONS;
+    }
+    seen == Input::DIRECTIONS
+}
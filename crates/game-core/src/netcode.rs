@@ -0,0 +1,502 @@
+//! Rollback netcode (GGPO-style) on top of the deterministic core.
+//!
+//! Each frame the client calls `add_local_input`, feeds any remote inputs
+//! that arrived with `add_remote_input`, then `advance_frame`. Missing remote
+//! inputs are predicted by repeating the last confirmed one; when a confirmed
+//! input contradicts a prediction the session restores the snapshot taken
+//! before that frame and re-simulates up to the present.
+//!
+//! Once a checksum frame is confirmed the session computes its checksum and
+//! reports it in `FrameAdvance`; the client sends it to the peer, who feeds
+//! it back through `add_remote_checksum`. A mismatch on either side raises
+//! `SessionError::DesyncDetected`.
+//!
+//! Local inputs can also be delayed by up to `MAX_INPUT_DELAY` frames, which
+//! gives remote inputs that much longer to arrive before they have to be
+//! predicted. With `adaptive_delay` on, the session picks the delay from the
+//! one-way latency samples fed to `add_ping_sample`: enough frames that
+//! inputs arriving within the mean latency plus twice the jitter roll back
+//! at most `HIDDEN_ROLLBACK_FRAMES`, which players do not notice.
+//! `Session::stats` reports the delay and the rollbacks so far.
+
+use core::fmt;
+
+use crate::character::CharacterDef;
+use crate::checksum::{checksum, is_checksum_frame, FrameChecksum, DEFAULT_CHECKSUM_INTERVAL};
+use crate::input::Input;
+use crate::pool::SnapshotPool;
+use crate::sim::step;
+use crate::state::GameState;
+use crate::MAX_PLAYERS;
+
+/// Frames of state and input history kept in the ring buffers.
+pub const RING_SIZE: usize = 64;
+
+/// Most frames local inputs can be delayed by.
+pub const MAX_INPUT_DELAY: u8 = 3;
+
+/// Largest prediction window a session can be configured with. Delayed
+/// local inputs are written ahead of the current frame, so the window
+/// leaves room for them in the ring.
+pub const MAX_PREDICTION_LIMIT: u32 = RING_SIZE as u32 - 2 - MAX_INPUT_DELAY as u32;
+
+/// Rollbacks this short are not noticeable, so adaptive delay only covers
+/// latency beyond them.
+pub const HIDDEN_ROLLBACK_FRAMES: u32 = 2;
+
+/// Simulation rate the latency samples are converted at.
+const FRAMES_PER_SECOND: u32 = 60;
+
+/// Local and remote checksums kept for comparison.
+const CHECKSUM_HISTORY: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SessionConfig {
+    /// Index of the player driven by this client.
+    pub local_player: usize,
+    /// How many frames the session may run ahead of the last confirmed
+    /// remote input before `advance_frame` refuses to continue.
+    pub max_prediction: u32,
+    /// Frames between exchanged checksums, zero to disable them.
+    pub checksum_interval: u32,
+    /// Frames local inputs are delayed by, and the starting delay when
+    /// `adaptive_delay` is on.
+    pub input_delay: u8,
+    /// Re-pick the input delay from latency samples.
+    pub adaptive_delay: bool,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            local_player: 0,
+            max_prediction: 8,
+            checksum_interval: DEFAULT_CHECKSUM_INTERVAL,
+            input_delay: 0,
+            adaptive_delay: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionError {
+    InvalidConfig,
+    /// `advance_frame` was called before `add_local_input` for this frame.
+    MissingLocalInput {
+        frame: u32,
+    },
+    /// Running further ahead would exceed the prediction window; the caller
+    /// should wait for remote inputs.
+    PredictionThreshold {
+        frame: u32,
+        confirmed: Option<u32>,
+    },
+    /// A remote input skipped ahead of the next expected frame.
+    InputGap {
+        expected: u32,
+        received: u32,
+    },
+    /// The peers' confirmed states differ at the start of `frame`.
+    DesyncDetected {
+        frame: u32,
+        local: u64,
+        remote: u64,
+    },
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::InvalidConfig => write!(f, "invalid session config"),
+            SessionError::MissingLocalInput { frame } => {
+                write!(f, "no local input for frame {}", frame)
+            }
+            SessionError::PredictionThreshold { frame, confirmed } => write!(
+                f,
+                "frame {} exceeds prediction window (confirmed {:?})",
+                frame, confirmed
+            ),
+            SessionError::InputGap { expected, received } => write!(
+                f,
+                "remote input for frame {} arrived before frame {}",
+                received, expected
+            ),
+            SessionError::DesyncDetected {
+                frame,
+                local,
+                remote,
+            } => write!(
+                f,
+                "desync at frame {}: local checksum {:#018x}, remote {:#018x}",
+                frame, local, remote
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SessionError {}
+
+/// Latency and rollback figures for a running session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SessionStats {
+    /// Frames local inputs are currently delayed by.
+    pub input_delay: u8,
+    /// Smoothed one-way latency, `None` before the first sample.
+    pub ping_ms: Option<u32>,
+    /// Smoothed deviation of the latency samples.
+    pub jitter_ms: u32,
+    /// Advances that had to roll back.
+    pub rollbacks: u32,
+    /// Frames re-simulated across all rollbacks.
+    pub rollback_frames: u64,
+    pub max_rollback: u32,
+}
+
+/// Exponentially weighted one-way latency and jitter, in microseconds so
+/// the integer smoothing keeps sub-millisecond precision.
+#[derive(Clone, Copy, Debug, Default)]
+struct LatencyEstimate {
+    mean_us: Option<u64>,
+    jitter_us: u64,
+}
+
+impl LatencyEstimate {
+    fn add_sample(&mut self, one_way_ms: u32) {
+        let sample = one_way_ms as u64 * 1000;
+        match self.mean_us {
+            None => self.mean_us = Some(sample),
+            Some(mean) => {
+                let deviation = sample.abs_diff(mean);
+                self.jitter_us = (self.jitter_us * 3 + deviation) / 4;
+                self.mean_us = Some((mean * 7 + sample) / 8);
+            }
+        }
+    }
+
+    /// Delay that keeps rollbacks from inputs arriving within the mean
+    /// plus twice the jitter at `HIDDEN_ROLLBACK_FRAMES` or less.
+    fn input_delay(&self) -> Option<u8> {
+        let worst_us = self.mean_us? + 2 * self.jitter_us;
+        let frames = (worst_us * FRAMES_PER_SECOND as u64 + 999_999) / 1_000_000;
+        let delay = frames.saturating_sub(HIDDEN_ROLLBACK_FRAMES as u64);
+        Some(delay.min(MAX_INPUT_DELAY as u64) as u8)
+    }
+}
+
+/// Result of a successful `advance_frame`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameAdvance {
+    /// Frame that was just simulated.
+    pub frame: u32,
+    /// Frames re-simulated because of a misprediction, zero when none.
+    pub rollback_frames: u32,
+    /// Latest checksum confirmed by this advance, to be sent to the peer.
+    pub checksum: Option<FrameChecksum>,
+}
+
+pub struct Session<'a> {
+    config: SessionConfig,
+    defs: [&'a CharacterDef<'a>; MAX_PLAYERS],
+    state: GameState,
+    current_frame: u32,
+    /// State at the start of each frame, before its inputs are applied.
+    snapshots: SnapshotPool<RING_SIZE>,
+    local_inputs: [Input; RING_SIZE],
+    remote_inputs: [Input; RING_SIZE],
+    /// Remote input each frame was last simulated with.
+    remote_used: [Input; RING_SIZE],
+    /// Highest frame for which the local input is known.
+    last_local: Option<u32>,
+    input_delay: u8,
+    latency: LatencyEstimate,
+    stats: SessionStats,
+    /// Highest frame for which the remote input is known.
+    last_remote: Option<u32>,
+    /// Earliest frame simulated with a prediction that turned out wrong.
+    pending_rollback: Option<u32>,
+    /// Next checksum frame whose local checksum has not been computed.
+    next_checksum: u32,
+    local_checksums: [Option<FrameChecksum>; CHECKSUM_HISTORY],
+    remote_checksums: [Option<FrameChecksum>; CHECKSUM_HISTORY],
+}
+
+impl<'a> Session<'a> {
+    pub fn new(
+        initial: GameState,
+        defs: [&'a CharacterDef<'a>; MAX_PLAYERS],
+        config: SessionConfig,
+    ) -> Result<Self, SessionError> {
+        if config.local_player >= MAX_PLAYERS
+            || config.max_prediction > MAX_PREDICTION_LIMIT
+            || config.input_delay > MAX_INPUT_DELAY
+        {
+            return Err(SessionError::InvalidConfig);
+        }
+        Ok(Session {
+            config,
+            defs,
+            state: initial,
+            current_frame: 0,
+            snapshots: SnapshotPool::new(initial),
+            local_inputs: [Input::NONE; RING_SIZE],
+            remote_inputs: [Input::NONE; RING_SIZE],
+            remote_used: [Input::NONE; RING_SIZE],
+            // The first `input_delay` frames run with no local input.
+            last_local: (config.input_delay as u32).checked_sub(1),
+            input_delay: config.input_delay,
+            latency: LatencyEstimate::default(),
+            stats: SessionStats::default(),
+            last_remote: None,
+            pending_rollback: None,
+            next_checksum: 0,
+            local_checksums: [None; CHECKSUM_HISTORY],
+            remote_checksums: [None; CHECKSUM_HISTORY],
+        })
+    }
+
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    /// Current (possibly predicted) state.
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// Next frame to be simulated.
+    pub fn current_frame(&self) -> u32 {
+        self.current_frame
+    }
+
+    /// Last frame whose inputs are known for every player.
+    pub fn confirmed_frame(&self) -> Option<u32> {
+        match self.last_remote {
+            Some(remote) if self.current_frame > 0 => Some(remote.min(self.current_frame - 1)),
+            _ => None,
+        }
+    }
+
+    /// State at the start of `frame` once every input before it is
+    /// confirmed, while it is still in the snapshot ring. Unlike `state`,
+    /// both peers agree on it.
+    pub fn confirmed_state(&self, frame: u32) -> Option<GameState> {
+        if frame > self.current_frame
+            || !self.inputs_confirmed_before(frame)
+            || self.pending_rollback.map_or(false, |from| frame > from)
+        {
+            return None;
+        }
+        if frame == self.current_frame {
+            return Some(self.state);
+        }
+        self.snapshots
+            .contains(frame)
+            .then(|| self.snapshots.state(frame))
+    }
+
+    pub fn stats(&self) -> SessionStats {
+        SessionStats {
+            input_delay: self.input_delay,
+            ping_ms: self.latency.mean_us.map(|us| (us / 1000) as u32),
+            jitter_ms: (self.latency.jitter_us / 1000) as u32,
+            ..self.stats
+        }
+    }
+
+    /// Sets the local input for the current frame plus the input delay.
+    /// Calling it again before `advance_frame` replaces the earlier value.
+    ///
+    /// When the delay has grown since the last call, the frames skipped over
+    /// repeat the previous input; when it has shrunk, the input already set
+    /// for the target frame is replaced.
+    pub fn add_local_input(&mut self, input: Input) {
+        let target = self.current_frame + self.input_delay as u32;
+        let next = self.last_local.map_or(0, |f| f + 1);
+        if target > next {
+            let repeat = match self.last_local {
+                Some(last) => self.local_inputs[slot(last)],
+                None => Input::NONE,
+            };
+            for frame in next..target {
+                self.local_inputs[slot(frame)] = repeat;
+            }
+        }
+        self.local_inputs[slot(target)] = input;
+        self.last_local = Some(target);
+    }
+
+    /// Feeds a one-way latency measurement to the peer. With adaptive delay
+    /// on, the new delay applies from the next `add_local_input`.
+    pub fn add_ping_sample(&mut self, one_way_ms: u32) {
+        self.latency.add_sample(one_way_ms);
+        if self.config.adaptive_delay {
+            if let Some(delay) = self.latency.input_delay() {
+                self.input_delay = delay;
+            }
+        }
+    }
+
+    /// Records the remote player's confirmed input for `frame`. Inputs must
+    /// arrive in order; duplicates of already-confirmed frames are ignored.
+    pub fn add_remote_input(&mut self, frame: u32, input: Input) -> Result<(), SessionError> {
+        let expected = self.last_remote.map_or(0, |f| f + 1);
+        if frame < expected {
+            return Ok(());
+        }
+        if frame > expected {
+            return Err(SessionError::InputGap {
+                expected,
+                received: frame,
+            });
+        }
+
+        self.remote_inputs[slot(frame)] = input;
+        self.last_remote = Some(frame);
+
+        if frame < self.current_frame && self.remote_used[slot(frame)] != input {
+            self.pending_rollback = Some(self.pending_rollback.map_or(frame, |f| f.min(frame)));
+        }
+        Ok(())
+    }
+
+    /// Simulates the current frame, rolling back first if a prediction was
+    /// wrong.
+    pub fn advance_frame(&mut self) -> Result<FrameAdvance, SessionError> {
+        let frame = self.current_frame;
+        if self.last_local.map_or(true, |last| last < frame) {
+            return Err(SessionError::MissingLocalInput { frame });
+        }
+        let next_unconfirmed = self.last_remote.map_or(0, |f| f + 1);
+        if frame >= next_unconfirmed && frame - next_unconfirmed >= self.config.max_prediction {
+            return Err(SessionError::PredictionThreshold {
+                frame,
+                confirmed: self.last_remote,
+            });
+        }
+
+        let rollback_frames = match self.pending_rollback.take() {
+            Some(from) => {
+                self.resimulate_from(from);
+                frame - from
+            }
+            None => 0,
+        };
+
+        self.simulate(frame);
+        self.current_frame += 1;
+        if rollback_frames > 0 {
+            self.stats.rollbacks += 1;
+            self.stats.rollback_frames += rollback_frames as u64;
+            self.stats.max_rollback = self.stats.max_rollback.max(rollback_frames);
+        }
+
+        let checksum = self.confirm_checksums()?;
+
+        Ok(FrameAdvance {
+            frame,
+            rollback_frames,
+            checksum,
+        })
+    }
+
+    /// Records the peer's checksum for `frame`, comparing it right away if
+    /// the local one is already known.
+    pub fn add_remote_checksum(&mut self, remote: FrameChecksum) -> Result<(), SessionError> {
+        let interval = self.config.checksum_interval;
+        if !is_checksum_frame(remote.frame, interval) {
+            return Ok(());
+        }
+        let slot = checksum_slot(remote.frame, interval);
+        match self.local_checksums[slot] {
+            Some(local) if local.frame == remote.frame => compare(local, remote),
+            Some(local) if local.frame > remote.frame => Ok(()),
+            _ => {
+                self.remote_checksums[slot] = Some(remote);
+                Ok(())
+            }
+        }
+    }
+
+    /// Computes checksums for every checksum frame whose preceding inputs
+    /// are all confirmed, and compares them against stored remote ones.
+    fn confirm_checksums(&mut self) -> Result<Option<FrameChecksum>, SessionError> {
+        let interval = self.config.checksum_interval;
+        if interval == 0 {
+            return Ok(None);
+        }
+
+        let mut latest = None;
+        while self.next_checksum < self.current_frame
+            && self.inputs_confirmed_before(self.next_checksum)
+        {
+            let frame = self.next_checksum;
+            let local = FrameChecksum {
+                frame,
+                checksum: checksum(&self.snapshots.state(frame)),
+            };
+            let slot = checksum_slot(frame, interval);
+            self.local_checksums[slot] = Some(local);
+            self.next_checksum += interval;
+            latest = Some(local);
+
+            if let Some(remote) = self.remote_checksums[slot].take() {
+                if remote.frame == frame {
+                    compare(local, remote)?;
+                }
+            }
+        }
+        Ok(latest)
+    }
+
+    fn inputs_confirmed_before(&self, frame: u32) -> bool {
+        frame == 0 || self.last_remote.map_or(false, |last| last + 1 >= frame)
+    }
+
+    fn resimulate_from(&mut self, from: u32) {
+        self.snapshots.restore_into(from, &mut self.state);
+        for frame in from..self.current_frame {
+            self.simulate(frame);
+        }
+    }
+
+    fn simulate(&mut self, frame: u32) {
+        let remote = self.remote_input_for(frame);
+        let mut inputs = [Input::NONE; MAX_PLAYERS];
+        inputs[self.config.local_player] = self.local_inputs[slot(frame)];
+        inputs[1 - self.config.local_player] = remote;
+
+        self.snapshots.save(frame, &self.state);
+        self.remote_used[slot(frame)] = remote;
+        self.state = step(&self.state, inputs, self.defs);
+    }
+
+    /// Confirmed remote input, or a repeat of the last confirmed one.
+    fn remote_input_for(&self, frame: u32) -> Input {
+        match self.last_remote {
+            Some(last) if frame <= last => self.remote_inputs[slot(frame)],
+            Some(last) => self.remote_inputs[slot(last)],
+            None => Input::NONE,
+        }
+    }
+}
+
+fn slot(frame: u32) -> usize {
+    frame as usize % RING_SIZE
+}
+
+fn checksum_slot(frame: u32, interval: u32) -> usize {
+    (frame / interval) as usize % CHECKSUM_HISTORY
+}
+
+fn compare(local: FrameChecksum, remote: FrameChecksum) -> Result<(), SessionError> {
+    if local.checksum == remote.checksum {
+        Ok(())
+    } else {
+        Err(SessionError::DesyncDetected {
+            frame: local.frame,
+            local: local.checksum,
+            remote: remote.checksum,
+        })
+    }
+}
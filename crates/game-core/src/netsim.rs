@@ -0,0 +1,154 @@
+//! Simulated network for testing the netcode deterministically.
+//!
+//! A `SimNetwork` links two `SimTransport` endpoints through a simulated
+//! wire with latency, jitter, loss and reordering, all drawn from a seeded
+//! `Rng`. Time only moves when the caller calls `advance`, so a test that
+//! drives two sessions over it replays exactly the same packet schedule for
+//! the same seed, however the host machine is loaded.
+
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::convert::Infallible;
+
+use crate::rng::Rng;
+use crate::transport::Transport;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetworkConditions {
+    /// One-way delay every packet gets.
+    pub latency_ms: u32,
+    /// Extra delay drawn uniformly from `0..=jitter_ms` per packet. Packets
+    /// may overtake each other by up to this much.
+    pub jitter_ms: u32,
+    /// Chance in percent that a packet is lost.
+    pub loss_percent: u32,
+    /// Chance in percent that a packet is held back by another full
+    /// latency, so that later packets arrive first.
+    pub reorder_percent: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NetworkStats {
+    pub sent: u64,
+    pub lost: u64,
+    pub reordered: u64,
+    pub delivered: u64,
+}
+
+struct InFlight {
+    deliver_at: u64,
+    /// Send order, which breaks ties between packets due at the same time.
+    sequence: u64,
+    to: usize,
+    data: Vec<u8>,
+}
+
+struct Wire {
+    conditions: NetworkConditions,
+    rng: Rng,
+    now_ms: u64,
+    next_sequence: u64,
+    in_flight: Vec<InFlight>,
+    stats: NetworkStats,
+}
+
+impl Wire {
+    fn send(&mut self, to: usize, packet: &[u8]) {
+        self.stats.sent += 1;
+        let conditions = self.conditions;
+        if self.rng.below(100) < conditions.loss_percent {
+            self.stats.lost += 1;
+            return;
+        }
+        let mut delay = conditions.latency_ms as u64
+            + self.rng.below(conditions.jitter_ms.saturating_add(1)) as u64;
+        if self.rng.below(100) < conditions.reorder_percent {
+            self.stats.reordered += 1;
+            delay += conditions.latency_ms.max(1) as u64;
+        }
+        self.in_flight.push(InFlight {
+            deliver_at: self.now_ms + delay,
+            sequence: self.next_sequence,
+            to,
+            data: packet.to_vec(),
+        });
+        self.next_sequence += 1;
+    }
+
+    fn recv(&mut self, to: usize) -> Option<Vec<u8>> {
+        let now = self.now_ms;
+        let (index, _) = self
+            .in_flight
+            .iter()
+            .enumerate()
+            .filter(|(_, packet)| packet.to == to && packet.deliver_at <= now)
+            .min_by_key(|(_, packet)| (packet.deliver_at, packet.sequence))?;
+        self.stats.delivered += 1;
+        Some(self.in_flight.remove(index).data)
+    }
+}
+
+/// Shared handle to the simulated wire and its clock.
+#[derive(Clone)]
+pub struct SimNetwork {
+    wire: Rc<RefCell<Wire>>,
+}
+
+impl SimNetwork {
+    pub fn new(conditions: NetworkConditions, seed: u64) -> Self {
+        SimNetwork {
+            wire: Rc::new(RefCell::new(Wire {
+                conditions,
+                rng: Rng::new(seed),
+                now_ms: 0,
+                next_sequence: 0,
+                in_flight: Vec::new(),
+                stats: NetworkStats::default(),
+            })),
+        }
+    }
+
+    /// The two ends of the wire; each receives what the other sends.
+    pub fn endpoints(&self) -> [SimTransport; 2] {
+        [0, 1].map(|side| SimTransport {
+            wire: self.wire.clone(),
+            side,
+        })
+    }
+
+    pub fn now_ms(&self) -> u64 {
+        self.wire.borrow().now_ms
+    }
+
+    /// Moves the clock forward, making packets due by then receivable.
+    pub fn advance(&self, ms: u64) {
+        self.wire.borrow_mut().now_ms += ms;
+    }
+
+    pub fn stats(&self) -> NetworkStats {
+        self.wire.borrow().stats
+    }
+}
+
+pub struct SimTransport {
+    wire: Rc<RefCell<Wire>>,
+    side: usize,
+}
+
+impl Transport for SimTransport {
+    type Error = Infallible;
+
+    fn send(&mut self, packet: &[u8]) -> Result<(), Infallible> {
+        self.wire.borrow_mut().send(1 - self.side, packet);
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Infallible> {
+        Ok(self.wire.borrow_mut().recv(self.side).map(|packet| {
+            let len = packet.len().min(buf.len());
+            buf[..len].copy_from_slice(&packet[..len]);
+            len
+        }))
+    }
+}
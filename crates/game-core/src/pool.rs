@@ -0,0 +1,171 @@
+//! Copy-on-write snapshot ring for rollback.
+//!
+//! Every slot is pre-allocated, so saving and restoring never allocate. A
+//! snapshot is split into the match globals, which change every frame, and
+//! one region per player. Saving a frame compares each player against the
+//! previous frame's snapshot and only writes the players that changed; an
+//! unchanged player is shared by pointing at the slot that holds it.
+//! Restoring likewise rewrites only the players that differ from the live
+//! state, so rolling back a frame costs the bytes that changed in it.
+//!
+//! When a slot is reused, players that later frames still share from it are
+//! first copied into the next frame's slot, so every stored frame stays
+//! readable until its own slot is overwritten.
+
+use crate::proration::Proration;
+use crate::rng::Rng;
+use crate::state::{GameState, PlayerState, Projectile, Stage};
+use crate::MAX_PLAYERS;
+
+#[derive(Clone, Copy)]
+struct Globals {
+    frame: u32,
+    stage: Stage,
+    projectiles: [Option<Projectile>; MAX_PLAYERS],
+    rng: Rng,
+    proration: Proration,
+    super_freeze: u8,
+}
+
+impl Globals {
+    fn of(state: &GameState) -> Self {
+        Globals {
+            frame: state.frame,
+            stage: state.stage,
+            projectiles: state.projectiles,
+            rng: state.rng,
+            proration: state.proration,
+            super_freeze: state.super_freeze,
+        }
+    }
+
+    fn write_to(&self, state: &mut GameState) {
+        state.frame = self.frame;
+        state.stage = self.stage;
+        state.projectiles = self.projectiles;
+        state.rng = self.rng;
+        state.proration = self.proration;
+        state.super_freeze = self.super_freeze;
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Slot {
+    /// Frame stored here, `None` until the slot is first saved.
+    frame: Option<u32>,
+    globals: Globals,
+    players: [PlayerState; MAX_PLAYERS],
+    /// Slot holding each player for this frame; this slot's own index when
+    /// the player was written here.
+    sources: [usize; MAX_PLAYERS],
+}
+
+pub struct SnapshotPool<const N: usize> {
+    slots: [Slot; N],
+}
+
+impl<const N: usize> SnapshotPool<N> {
+    /// Pool whose every slot reads back as `initial` until saved over.
+    pub fn new(initial: GameState) -> Self {
+        let mut slots = [Slot {
+            frame: None,
+            globals: Globals::of(&initial),
+            players: initial.players,
+            sources: [0; MAX_PLAYERS],
+        }; N];
+        for (index, slot) in slots.iter_mut().enumerate() {
+            slot.sources = [index; MAX_PLAYERS];
+        }
+        SnapshotPool { slots }
+    }
+
+    /// Stores `state` as the snapshot for `frame`, replacing whatever frame
+    /// shared its slot.
+    pub fn save(&mut self, frame: u32, state: &GameState) {
+        let index = Self::slot(frame);
+        self.evict(index);
+
+        let prev = Self::slot(frame.wrapping_sub(1));
+        let has_prev = frame > 0 && self.slots[prev].frame == Some(frame - 1);
+        for (player, new) in state.players.iter().enumerate() {
+            let source = self.slots[prev].sources[player];
+            if has_prev && self.slots[source].players[player] == *new {
+                self.slots[index].sources[player] = source;
+            } else {
+                self.slots[index].players[player] = *new;
+                self.slots[index].sources[player] = index;
+            }
+        }
+        self.slots[index].globals = Globals::of(state);
+        self.slots[index].frame = Some(frame);
+    }
+
+    /// Snapshot in `frame`'s slot. Reading a frame that was never saved, or
+    /// whose slot has been reused, returns whatever the slot holds.
+    pub fn state(&self, frame: u32) -> GameState {
+        let slot = &self.slots[Self::slot(frame)];
+        let globals = &slot.globals;
+        GameState {
+            frame: globals.frame,
+            stage: globals.stage,
+            players: core::array::from_fn(|player| {
+                self.slots[slot.sources[player]].players[player]
+            }),
+            projectiles: globals.projectiles,
+            rng: globals.rng,
+            proration: globals.proration,
+            super_freeze: globals.super_freeze,
+        }
+    }
+
+    /// Overwrites `live` with `frame`'s snapshot, writing only the players
+    /// that differ.
+    pub fn restore_into(&self, frame: u32, live: &mut GameState) {
+        let slot = &self.slots[Self::slot(frame)];
+        slot.globals.write_to(live);
+        for (player, live_player) in live.players.iter_mut().enumerate() {
+            let stored = &self.slots[slot.sources[player]].players[player];
+            if live_player != stored {
+                *live_player = *stored;
+            }
+        }
+    }
+
+    /// Whether `frame` is still stored.
+    pub fn contains(&self, frame: u32) -> bool {
+        self.slots[Self::slot(frame)].frame == Some(frame)
+    }
+
+    /// Hands the players later frames share from slot `index` to the next
+    /// frame's slot before `index` is overwritten.
+    fn evict(&mut self, index: usize) {
+        let Some(evicted) = self.slots[index].frame else {
+            return;
+        };
+        let heir = Self::slot(evicted.wrapping_add(1));
+        if heir == index || self.slots[heir].frame != Some(evicted.wrapping_add(1)) {
+            return;
+        }
+        for player in 0..MAX_PLAYERS {
+            if self.slots[heir].sources[player] != index {
+                continue;
+            }
+            self.slots[heir].players[player] = self.slots[index].players[player];
+
+            let mut frame = evicted.wrapping_add(1);
+            let mut cursor = heir;
+            while cursor != index
+                && self.slots[cursor].frame == Some(frame)
+                && self.slots[cursor].sources[player] == index
+            {
+                self.slots[cursor].sources[player] = heir;
+                frame = frame.wrapping_add(1);
+                cursor = Self::slot(frame);
+            }
+        }
+    }
+
+    fn slot(frame: u32) -> usize {
+        frame as usize % N
+    }
+}
@@ -0,0 +1,48 @@
+//! Combo damage proration.
+//!
+//! The first hit of a combo deals full damage and every later hit is scaled
+//! by a further `scale_percent`, compounding, until the scaling bottoms out at
+//! `floor_percent`. All math is in whole percents with truncating division,
+//! matching `combo_mint::combo_sim` so on-chain combo damage agrees with the
+//! client.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Proration {
+    /// Percent of the previous hit's scaling kept by each further hit.
+    pub scale_percent: u8,
+    /// Lowest scaling a hit can reach, in percent.
+    pub floor_percent: u8,
+}
+
+impl Proration {
+    pub const DEFAULT: Proration = Proration {
+        scale_percent: 90,
+        floor_percent: 30,
+    };
+
+    /// Scaling in percent for a hit landing after `hits_before` hits of the
+    /// same combo.
+    pub fn percent(&self, hits_before: u16) -> u32 {
+        let scale = self.scale_percent.min(100) as u32;
+        let floor = self.floor_percent.min(100) as u32;
+        let mut percent = 100;
+        for _ in 0..hits_before {
+            let next = (percent * scale / 100).max(floor);
+            if next == percent {
+                break;
+            }
+            percent = next;
+        }
+        percent
+    }
+
+    pub fn scale(&self, damage: i32, hits_before: u16) -> i32 {
+        (damage as i64 * self.percent(hits_before) as i64 / 100) as i32
+    }
+}
+
+impl Default for Proration {
+    fn default() -> Self {
+        Proration::DEFAULT
+    }
+}
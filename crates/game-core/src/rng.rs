@@ -0,0 +1,75 @@
+//! Deterministic xoshiro256** generator for randomized game elements.
+//!
+//! The generator lives in `GameState`, so it is snapshotted, rolled back and
+//! hashed with everything else. It is seeded from the match seed stored in
+//! the replay header and on-chain, which makes every draw reproducible.
+
+/// xoshiro256** by Blackman and Vigna.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    /// Expands a match seed into the full state with SplitMix64, which also
+    /// keeps a zero seed from producing the all-zero state.
+    pub const fn new(seed: u64) -> Self {
+        let mut sm = seed;
+        let mut state = [0; 4];
+        let mut i = 0;
+        while i < 4 {
+            sm = sm.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = sm;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            state[i] = z ^ (z >> 31);
+            i += 1;
+        }
+        Rng { state }
+    }
+
+    /// Restores a generator from raw state, e.g. from a snapshot. The state
+    /// must not be all zero.
+    pub const fn from_state(state: [u64; 4]) -> Self {
+        Rng { state }
+    }
+
+    pub const fn state(&self) -> [u64; 4] {
+        self.state
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+
+        result
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    /// Uniform value in `0..bound`, without modulo bias. Returns 0 when
+    /// `bound` is 0.
+    pub fn below(&mut self, bound: u32) -> u32 {
+        if bound == 0 {
+            return 0;
+        }
+        // Lemire's multiply-shift with rejection of the biased low range.
+        let threshold = bound.wrapping_neg() % bound;
+        loop {
+            let product = self.next_u32() as u64 * bound as u64;
+            if product as u32 >= threshold {
+                return (product >> 32) as u32;
+            }
+        }
+    }
+}
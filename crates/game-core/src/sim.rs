@@ -0,0 +1,538 @@
+//! Deterministic game loop.
+//!
+//! Simulation order (critical for determinism - do not change):
+//! 0. Super freeze countdown, which skips every later step while it runs
+//! 1. Stun and invincibility countdown
+//! 2. Input application
+//! 3. Facing
+//! 4. Physics (gravity, movement, stage bounds)
+//! 5. Body push
+//! 6. Projectile spawn
+//! 7. Combat resolution
+//! 8. Projectile movement
+//! 9. Move frame advance
+
+use crate::character::{roster, CharacterDef, MoveDef, MOVE_EX, MOVE_LIGHT, MOVE_SPECIAL, ROSTER};
+use crate::collision::{self, resolve_clash, sweep, Aabb, Clash};
+use crate::fixed::Fx32;
+use crate::input::Input;
+use crate::juggle;
+use crate::meter;
+use crate::state::{Facing, GameState, PlayerState, Projectile, Stage};
+use crate::MAX_PLAYERS;
+
+/// Advances one frame using the built-in roster. Unknown character ids fall
+/// back to the first roster entry.
+pub fn simulate_frame(state: &GameState, inputs: [Input; MAX_PLAYERS]) -> GameState {
+    let defs = [
+        roster(state.players[0].character_id).unwrap_or(&ROSTER[0]),
+        roster(state.players[1].character_id).unwrap_or(&ROSTER[0]),
+    ];
+    step(state, inputs, defs)
+}
+
+/// Advances one frame with explicit character definitions.
+pub fn step(
+    state: &GameState,
+    inputs: [Input; MAX_PLAYERS],
+    defs: [&CharacterDef; MAX_PLAYERS],
+) -> GameState {
+    step_observed(state, inputs, defs, &mut ())
+}
+
+/// What became of a hitbox found overlapping a hurtbox.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Contact {
+    Hit {
+        /// Damage before proration.
+        base: i32,
+        damage: i32,
+        /// Hits the defender had already taken in the combo.
+        hits_before: u16,
+    },
+    Blocked {
+        chip: i32,
+    },
+    /// Beaten by a higher priority hit on the same frame.
+    LostClash,
+    /// Projectile destroyed by the other player's projectile.
+    Cancelled,
+}
+
+/// Hooks `step` calls as it resolves a frame, so that debug tooling sees
+/// what the simulation decided instead of working it out again. Every hook
+/// defaults to nothing, which `()` relies on to compile away.
+pub(crate) trait Observer {
+    /// `player` ran frame `frame` of `move_def` this frame.
+    fn move_frame(&mut self, _player: usize, _move_def: &MoveDef, _frame: u16) {}
+
+    fn contact(
+        &mut self,
+        _attacker: usize,
+        _move_def: &MoveDef,
+        _projectile: bool,
+        _contact: Contact,
+    ) {
+    }
+}
+
+impl Observer for () {}
+
+pub(crate) fn step_observed<O: Observer>(
+    state: &GameState,
+    inputs: [Input; MAX_PLAYERS],
+    defs: [&CharacterDef; MAX_PLAYERS],
+    observer: &mut O,
+) -> GameState {
+    let mut next = *state;
+
+    if next.super_freeze > 0 {
+        next.super_freeze -= 1;
+        next.frame += 1;
+        return next;
+    }
+
+    for player in next.players.iter_mut() {
+        tick_stun(player);
+    }
+
+    for ((player, def), input) in next.players.iter_mut().zip(defs).zip(inputs) {
+        apply_input(player, def, input);
+    }
+    next.super_freeze = started_super_freeze(&next.players, defs);
+
+    face_opponents(&mut next.players);
+
+    let stage = next.stage;
+    for (player, def) in next.players.iter_mut().zip(defs) {
+        apply_physics(player, def, &stage);
+    }
+
+    push_apart(&mut next.players, defs, &stage);
+    spawn_projectiles(&mut next, defs);
+    resolve_combat(&mut next, defs, observer);
+    move_projectiles(&mut next.projectiles, &stage);
+
+    for (index, (player, def)) in next.players.iter_mut().zip(defs).enumerate() {
+        if let Some(move_def) = player.current_move.and_then(|id| def.find_move(id)) {
+            observer.move_frame(index, move_def, player.move_frame);
+        }
+        advance_move(player, def);
+    }
+
+    next.frame += 1;
+    next
+}
+
+fn tick_stun(player: &mut PlayerState) {
+    player.invincible = player.invincible.saturating_sub(1);
+    if player.hitstun > 0 {
+        player.hitstun -= 1;
+    } else if player.blockstun > 0 {
+        player.blockstun -= 1;
+    }
+    if player.hitstun == 0 && !player.launched {
+        player.combo_hits = 0;
+    }
+}
+
+fn apply_input(player: &mut PlayerState, def: &CharacterDef, input: Input) {
+    let input = input.socd_cleaned();
+    player.guarding = false;
+    if !player.is_alive() || player.is_stunned() {
+        apply_friction(player, def);
+        return;
+    }
+
+    let pressed = pressed_moves(input);
+    let cancel_from = player.current_move.filter(|_| player.move_connected);
+    if player.current_move.is_none() || cancel_from.is_some() {
+        for &id in pressed {
+            let allowed = cancel_from.map_or(true, |from| def.can_cancel(from, id));
+            if allowed && start_move(player, def, id) {
+                break;
+            }
+        }
+    }
+    if pressed.is_empty()
+        && player.current_move.is_none()
+        && input.contains(Input::DEFEND)
+        && player.grounded
+    {
+        player.guarding = true;
+    }
+
+    if player.current_move.is_some() || player.guarding || !player.grounded {
+        apply_friction(player, def);
+        return;
+    }
+
+    let dir = input.horizontal();
+    if dir != 0 {
+        player.vel_x = def.walk_speed * dir;
+    } else {
+        apply_friction(player, def);
+    }
+
+    if input.intersects(Input::JUMP | Input::UP) {
+        player.vel_y = def.jump_force;
+        player.grounded = false;
+    }
+}
+
+/// Moves the pressed buttons ask for, in the order they are tried.
+fn pressed_moves(input: Input) -> &'static [u8] {
+    if input.contains(Input::ATTACK | Input::SPECIAL) {
+        &[MOVE_EX, MOVE_LIGHT]
+    } else if input.contains(Input::ATTACK) {
+        &[MOVE_LIGHT]
+    } else if input.contains(Input::SPECIAL) {
+        &[MOVE_SPECIAL]
+    } else {
+        &[]
+    }
+}
+
+/// Starts a move if the character has it and the player can pay its meter
+/// cost.
+fn start_move(player: &mut PlayerState, def: &CharacterDef, id: u8) -> bool {
+    let Some(move_def) = def.find_move(id) else {
+        return false;
+    };
+    if player.meter < move_def.meter.cost {
+        return false;
+    }
+    player.meter -= move_def.meter.cost;
+    player.current_move = Some(id);
+    player.move_frame = 0;
+    player.move_connected = false;
+    true
+}
+
+/// Longest super freeze among moves started this frame.
+fn started_super_freeze(
+    players: &[PlayerState; MAX_PLAYERS],
+    defs: [&CharacterDef; MAX_PLAYERS],
+) -> u8 {
+    players
+        .iter()
+        .zip(defs)
+        .filter(|(player, _)| player.move_frame == 0)
+        .filter_map(|(player, def)| player.current_move.and_then(|id| def.find_move(id)))
+        .map(|m| m.meter.super_freeze)
+        .max()
+        .unwrap_or(0)
+}
+
+fn apply_friction(player: &mut PlayerState, def: &CharacterDef) {
+    let friction = if player.grounded {
+        def.ground_friction
+    } else {
+        def.air_friction
+    };
+    if player.vel_x > Fx32::ZERO {
+        player.vel_x = (player.vel_x - friction).max(Fx32::ZERO);
+    } else if player.vel_x < Fx32::ZERO {
+        player.vel_x = (player.vel_x + friction).min(Fx32::ZERO);
+    }
+}
+
+/// Idle grounded players always turn to face their opponent.
+fn face_opponents(players: &mut [PlayerState; MAX_PLAYERS]) {
+    let (x0, x1) = (players[0].pos_x, players[1].pos_x);
+    for (i, player) in players.iter_mut().enumerate() {
+        if !player.grounded || !player.is_actionable() {
+            continue;
+        }
+        let other = if i == 0 { x1 } else { x0 };
+        if other > player.pos_x {
+            player.facing = Facing::Right;
+        } else if other < player.pos_x {
+            player.facing = Facing::Left;
+        }
+    }
+}
+
+fn apply_physics(player: &mut PlayerState, def: &CharacterDef, stage: &Stage) {
+    if !player.grounded {
+        let gravity = if player.launched {
+            juggle::juggle_gravity(def.gravity, player.juggle)
+        } else {
+            def.gravity
+        };
+        player.vel_y = (player.vel_y - gravity).max(-def.max_fall_speed);
+    }
+
+    player.pos_x += player.vel_x;
+    player.pos_y += player.vel_y;
+
+    let half_width = def.hurtbox.width / 2;
+    if player.pos_x - half_width < stage.left_wall {
+        player.pos_x = stage.left_wall + half_width;
+        player.vel_x = Fx32::ZERO;
+    }
+    if player.pos_x + half_width > stage.right_wall {
+        player.pos_x = stage.right_wall - half_width;
+        player.vel_x = Fx32::ZERO;
+    }
+
+    if player.pos_y <= stage.floor_y {
+        player.pos_y = stage.floor_y;
+        player.vel_y = Fx32::ZERO;
+        player.grounded = true;
+        if player.launched {
+            player.launched = false;
+            player.juggle = 0;
+            player.hitstun = 0;
+        }
+    }
+}
+
+/// Keeps bodies from overlapping by splitting the overlap between both players.
+fn push_apart(
+    players: &mut [PlayerState; MAX_PLAYERS],
+    defs: [&CharacterDef; MAX_PLAYERS],
+    stage: &Stage,
+) {
+    let a = collision::pushbox(&players[0], defs[0]);
+    let b = collision::pushbox(&players[1], defs[1]);
+    if !a.overlaps(&b) {
+        return;
+    }
+
+    let overlap = a.overlap_x(&b);
+    let left_push = overlap / 2;
+    let right_push = overlap - left_push;
+    let (left, right) = if players[0].pos_x <= players[1].pos_x {
+        (0, 1)
+    } else {
+        (1, 0)
+    };
+    players[left].pos_x -= left_push;
+    players[right].pos_x += right_push;
+
+    for i in 0..MAX_PLAYERS {
+        let half_width = defs[i].hurtbox.width / 2;
+        players[i].pos_x = players[i]
+            .pos_x
+            .clamp(stage.left_wall + half_width, stage.right_wall - half_width);
+    }
+}
+
+/// Number of hit spark variants the renderer can pick from.
+pub const HIT_SPARK_VARIANTS: u32 = 4;
+
+#[derive(Clone, Copy)]
+struct Hit<'a> {
+    attacker: usize,
+    defender: usize,
+    blocked: bool,
+    move_def: &'a MoveDef,
+    /// Direction the defender is pushed.
+    push_sign: i32,
+    /// Set when the hit came from the attacker's projectile.
+    projectile: bool,
+}
+
+/// Launches a projectile on the first active frame of a projectile move,
+/// unless the owner already has one in flight.
+fn spawn_projectiles(state: &mut GameState, defs: [&CharacterDef; MAX_PLAYERS]) {
+    for ((player, def), slot) in state
+        .players
+        .iter()
+        .zip(defs)
+        .zip(state.projectiles.iter_mut())
+    {
+        let Some(move_def) = player.current_move.and_then(|id| def.find_move(id)) else {
+            continue;
+        };
+        let Some(projectile) = move_def.projectile else {
+            continue;
+        };
+        if slot.is_some() || player.move_frame != move_def.startup as u16 {
+            continue;
+        }
+        *slot = Some(Projectile {
+            move_id: move_def.id,
+            pos_x: player.pos_x,
+            pos_y: player.pos_y,
+            vel_x: projectile.speed * player.facing.sign(),
+            facing: player.facing,
+            frames_left: projectile.lifetime,
+        });
+    }
+}
+
+/// Hits are detected against the pre-combat state and applied together, so
+/// two moves that connect on the same frame trade unless one has higher
+/// priority. Projectiles are swept over this frame's motion and cancel each
+/// other out when they meet.
+fn resolve_combat<O: Observer>(
+    state: &mut GameState,
+    defs: [&CharacterDef; MAX_PLAYERS],
+    observer: &mut O,
+) {
+    let players = &state.players;
+    let mut melee: [Option<Hit>; MAX_PLAYERS] = [None; MAX_PLAYERS];
+    let mut ranged: [Option<Hit>; MAX_PLAYERS] = [None; MAX_PLAYERS];
+
+    for attacker in 0..MAX_PLAYERS {
+        let defender = 1 - attacker;
+        let atk = &players[attacker];
+        let def = &players[defender];
+        if !atk.is_alive() || !def.is_alive() || def.invincible > 0 {
+            continue;
+        }
+        let hurtbox = collision::hurtbox(def, defs[defender]);
+
+        if let Some((hitbox, move_def)) = collision::hitbox(atk, defs[attacker]) {
+            if hitbox.overlaps(&hurtbox) && juggle::can_juggle(def, move_def) {
+                melee[attacker] = Some(Hit {
+                    attacker,
+                    defender,
+                    blocked: blocks(def, atk.pos_x),
+                    move_def,
+                    push_sign: atk.facing.sign(),
+                    projectile: false,
+                });
+            }
+        }
+
+        if let Some((projectile, aabb, move_def)) = projectile_hitbox(state, defs, attacker) {
+            if sweep(&aabb, projectile.vel_x, Fx32::ZERO, &hurtbox)
+                && juggle::can_juggle(def, move_def)
+            {
+                ranged[attacker] = Some(Hit {
+                    attacker,
+                    defender,
+                    blocked: blocks(def, projectile.pos_x),
+                    move_def,
+                    push_sign: projectile.facing.sign(),
+                    projectile: true,
+                });
+            }
+        }
+    }
+
+    if let [Some(first), Some(second)] = melee {
+        match resolve_clash(first.move_def, second.move_def) {
+            Clash::Trade => {}
+            Clash::Wins(winner) => {
+                if let Some(loser) = melee[1 - winner].take() {
+                    observer.contact(loser.attacker, loser.move_def, false, Contact::LostClash);
+                }
+            }
+        }
+    }
+
+    if let (Some((a, a_box, _)), Some((b, b_box, _))) = (
+        projectile_hitbox(state, defs, 0),
+        projectile_hitbox(state, defs, 1),
+    ) {
+        if sweep(&a_box, a.vel_x - b.vel_x, Fx32::ZERO, &b_box) {
+            state.projectiles = [None; MAX_PLAYERS];
+            for hit in ranged.iter_mut().filter_map(Option::take) {
+                observer.contact(hit.attacker, hit.move_def, true, Contact::Cancelled);
+            }
+        }
+    }
+
+    for hit in melee.iter().chain(ranged.iter()).flatten() {
+        if hit.projectile {
+            state.projectiles[hit.attacker] = None;
+        } else {
+            state.players[hit.attacker].move_connected = true;
+        }
+        let gain = if hit.blocked {
+            hit.move_def.meter.on_block
+        } else {
+            hit.move_def.meter.on_hit
+        };
+        let attacker = &mut state.players[hit.attacker];
+        attacker.meter = meter::gain(attacker.meter, gain);
+
+        let defender = &mut state.players[hit.defender];
+        if hit.blocked {
+            defender.health = (defender.health - hit.move_def.chip_damage).max(0);
+            defender.blockstun = hit.move_def.blockstun;
+            let chip = hit.move_def.chip_damage;
+            observer.contact(
+                hit.attacker,
+                hit.move_def,
+                hit.projectile,
+                Contact::Blocked { chip },
+            );
+        } else {
+            let damage = state
+                .proration
+                .scale(hit.move_def.damage, defender.combo_hits);
+            observer.contact(
+                hit.attacker,
+                hit.move_def,
+                hit.projectile,
+                Contact::Hit {
+                    base: hit.move_def.damage,
+                    damage,
+                    hits_before: defender.combo_hits,
+                },
+            );
+            defender.health = (defender.health - damage).max(0);
+            defender.combo_hits = defender.combo_hits.saturating_add(1);
+            defender.hitstun = hit.move_def.hitstun;
+            defender.current_move = None;
+            defender.move_frame = 0;
+            defender.guarding = false;
+            defender.hit_spark = state.rng.below(HIT_SPARK_VARIANTS) as u8;
+            juggle::apply_launch(defender, hit.move_def);
+        }
+        defender.vel_x = hit.move_def.pushback * hit.push_sign;
+    }
+}
+
+/// A guarding defender blocks attacks coming from the side it faces.
+fn blocks(defender: &PlayerState, source_x: Fx32) -> bool {
+    let toward_source = (source_x - defender.pos_x).signum();
+    defender.guarding && defender.facing.sign() == toward_source
+}
+
+fn projectile_hitbox<'a>(
+    state: &GameState,
+    defs: [&CharacterDef<'a>; MAX_PLAYERS],
+    owner: usize,
+) -> Option<(Projectile, Aabb, &'a MoveDef)> {
+    let projectile = state.projectiles[owner]?;
+    let move_def = defs[owner].find_move(projectile.move_id)?;
+    let aabb = collision::projectile_box(&projectile, move_def)?;
+    Some((projectile, aabb, move_def))
+}
+
+/// Moves projectiles and removes those that expired or left the stage.
+fn move_projectiles(projectiles: &mut [Option<Projectile>; MAX_PLAYERS], stage: &Stage) {
+    for slot in projectiles.iter_mut() {
+        let Some(projectile) = slot else {
+            continue;
+        };
+        projectile.pos_x += projectile.vel_x;
+        projectile.frames_left = projectile.frames_left.saturating_sub(1);
+        let off_stage = projectile.pos_x < stage.left_wall || projectile.pos_x > stage.right_wall;
+        if projectile.frames_left == 0 || off_stage {
+            *slot = None;
+        }
+    }
+}
+
+fn advance_move(player: &mut PlayerState, def: &CharacterDef) {
+    let Some(id) = player.current_move else {
+        return;
+    };
+    player.move_frame += 1;
+    let move_def = def.find_move(id);
+    if move_def.map_or(true, |m| player.move_frame >= m.total_frames()) {
+        if let Some(m) = move_def.filter(|_| !player.move_connected) {
+            player.meter = meter::gain(player.meter, m.meter.on_whiff);
+        }
+        player.current_move = None;
+        player.move_frame = 0;
+        player.move_connected = false;
+    }
+}
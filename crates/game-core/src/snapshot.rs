@@ -0,0 +1,290 @@
+//! Fixed-layout byte encoding of `GameState`.
+//!
+//! Fields are written little-endian in the same order `checksum` hashes them,
+//! with booleans as one byte, `Facing` as its sign and every `Option` as a
+//! tag byte followed by the value, zero-filled when absent, so every snapshot
+//! is exactly `SNAPSHOT_LEN` bytes. Encoding never allocates.
+
+use core::fmt;
+
+use crate::fixed::Fx32;
+use crate::proration::Proration;
+use crate::rng::Rng;
+use crate::state::{Facing, GameState, PlayerState, Projectile, Stage};
+use crate::MAX_PLAYERS;
+
+const STAGE_LEN: usize = 12;
+const PLAYER_LEN: usize = 42;
+const PROJECTILE_LEN: usize = 17;
+
+pub const SNAPSHOT_LEN: usize = 4 // frame
+    + STAGE_LEN
+    + PLAYER_LEN * MAX_PLAYERS
+    + PROJECTILE_LEN * MAX_PLAYERS
+    + 32 // rng
+    + 2 // proration
+    + 1; // super_freeze
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The buffer is not `SNAPSHOT_LEN` bytes.
+    Length(usize),
+    InvalidFacing(u8),
+    InvalidBool(u8),
+    InvalidTag(u8),
+    /// The all-zero RNG state, which xoshiro never leaves.
+    ZeroRng,
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Length(len) => {
+                write!(f, "snapshot is {} bytes, expected {}", len, SNAPSHOT_LEN)
+            }
+            SnapshotError::InvalidFacing(byte) => write!(f, "invalid facing byte {:#04x}", byte),
+            SnapshotError::InvalidBool(byte) => write!(f, "invalid bool byte {:#04x}", byte),
+            SnapshotError::InvalidTag(byte) => write!(f, "invalid option tag {:#04x}", byte),
+            SnapshotError::ZeroRng => write!(f, "snapshot RNG state is all zero"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SnapshotError {}
+
+pub fn encode(state: &GameState) -> [u8; SNAPSHOT_LEN] {
+    let mut out = [0; SNAPSHOT_LEN];
+    let mut writer = Writer {
+        out: &mut out,
+        pos: 0,
+    };
+    writer.bytes(&state.frame.to_le_bytes());
+    writer.fx(state.stage.left_wall);
+    writer.fx(state.stage.right_wall);
+    writer.fx(state.stage.floor_y);
+    for player in &state.players {
+        writer.player(player);
+    }
+    for projectile in &state.projectiles {
+        writer.projectile(projectile);
+    }
+    for word in state.rng.state() {
+        writer.bytes(&word.to_le_bytes());
+    }
+    writer.u8(state.proration.scale_percent);
+    writer.u8(state.proration.floor_percent);
+    writer.u8(state.super_freeze);
+    debug_assert_eq!(writer.pos, SNAPSHOT_LEN);
+    out
+}
+
+pub fn decode(bytes: &[u8]) -> Result<GameState, SnapshotError> {
+    if bytes.len() != SNAPSHOT_LEN {
+        return Err(SnapshotError::Length(bytes.len()));
+    }
+    let mut reader = Reader { bytes, pos: 0 };
+    let frame = reader.u32();
+    let stage = Stage {
+        left_wall: reader.fx(),
+        right_wall: reader.fx(),
+        floor_y: reader.fx(),
+    };
+    let players = [reader.player()?, reader.player()?];
+    let projectiles = [reader.projectile()?, reader.projectile()?];
+    let rng_state = [reader.u64(), reader.u64(), reader.u64(), reader.u64()];
+    if rng_state == [0; 4] {
+        return Err(SnapshotError::ZeroRng);
+    }
+    let proration = Proration {
+        scale_percent: reader.u8(),
+        floor_percent: reader.u8(),
+    };
+    let super_freeze = reader.u8();
+    Ok(GameState {
+        frame,
+        stage,
+        players,
+        projectiles,
+        rng: Rng::from_state(rng_state),
+        proration,
+        super_freeze,
+    })
+}
+
+struct Writer<'a> {
+    out: &'a mut [u8; SNAPSHOT_LEN],
+    pos: usize,
+}
+
+impl Writer<'_> {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.out[self.pos..self.pos + bytes.len()].copy_from_slice(bytes);
+        self.pos += bytes.len();
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.bytes(&[value]);
+    }
+
+    fn bool(&mut self, value: bool) {
+        self.u8(value as u8);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.bytes(&value.to_le_bytes());
+    }
+
+    fn fx(&mut self, value: Fx32) {
+        self.bytes(&value.raw().to_le_bytes());
+    }
+
+    fn facing(&mut self, facing: Facing) {
+        self.u8(facing.sign() as u8);
+    }
+
+    fn player(&mut self, player: &PlayerState) {
+        self.u8(player.character_id);
+        self.fx(player.pos_x);
+        self.fx(player.pos_y);
+        self.fx(player.vel_x);
+        self.fx(player.vel_y);
+        self.facing(player.facing);
+        self.bool(player.grounded);
+        self.bool(player.guarding);
+        self.bytes(&player.health.to_le_bytes());
+        self.bool(player.current_move.is_some());
+        self.u8(player.current_move.unwrap_or(0));
+        self.u16(player.move_frame);
+        self.bool(player.move_connected);
+        self.u16(player.hitstun);
+        self.u16(player.blockstun);
+        self.u8(player.hit_spark);
+        self.u16(player.combo_hits);
+        self.u16(player.meter);
+        self.bool(player.launched);
+        self.u8(player.juggle);
+        self.u16(player.invincible);
+    }
+
+    fn projectile(&mut self, projectile: &Option<Projectile>) {
+        match projectile {
+            Some(projectile) => {
+                self.u8(1);
+                self.u8(projectile.move_id);
+                self.fx(projectile.pos_x);
+                self.fx(projectile.pos_y);
+                self.fx(projectile.vel_x);
+                self.facing(projectile.facing);
+                self.u16(projectile.frames_left);
+            }
+            None => self.pos += PROJECTILE_LEN,
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let mut out = [0; N];
+        out.copy_from_slice(&self.bytes[self.pos..self.pos + N]);
+        self.pos += N;
+        out
+    }
+
+    fn u8(&mut self) -> u8 {
+        self.take::<1>()[0]
+    }
+
+    fn u16(&mut self) -> u16 {
+        u16::from_le_bytes(self.take())
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_le_bytes(self.take())
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+
+    fn i32(&mut self) -> i32 {
+        i32::from_le_bytes(self.take())
+    }
+
+    fn fx(&mut self) -> Fx32 {
+        Fx32::from_raw(self.i32())
+    }
+
+    fn bool(&mut self) -> Result<bool, SnapshotError> {
+        match self.u8() {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(SnapshotError::InvalidBool(byte)),
+        }
+    }
+
+    fn tag(&mut self) -> Result<bool, SnapshotError> {
+        match self.u8() {
+            0 => Ok(false),
+            1 => Ok(true),
+            byte => Err(SnapshotError::InvalidTag(byte)),
+        }
+    }
+
+    fn facing(&mut self) -> Result<Facing, SnapshotError> {
+        match self.u8() as i8 {
+            -1 => Ok(Facing::Left),
+            1 => Ok(Facing::Right),
+            byte => Err(SnapshotError::InvalidFacing(byte as u8)),
+        }
+    }
+
+    fn player(&mut self) -> Result<PlayerState, SnapshotError> {
+        Ok(PlayerState {
+            character_id: self.u8(),
+            pos_x: self.fx(),
+            pos_y: self.fx(),
+            vel_x: self.fx(),
+            vel_y: self.fx(),
+            facing: self.facing()?,
+            grounded: self.bool()?,
+            guarding: self.bool()?,
+            health: self.i32(),
+            current_move: {
+                let present = self.tag()?;
+                let id = self.u8();
+                present.then_some(id)
+            },
+            move_frame: self.u16(),
+            move_connected: self.bool()?,
+            hitstun: self.u16(),
+            blockstun: self.u16(),
+            hit_spark: self.u8(),
+            combo_hits: self.u16(),
+            meter: self.u16(),
+            launched: self.bool()?,
+            juggle: self.u8(),
+            invincible: self.u16(),
+        })
+    }
+
+    fn projectile(&mut self) -> Result<Option<Projectile>, SnapshotError> {
+        if !self.tag()? {
+            self.pos += PROJECTILE_LEN - 1;
+            return Ok(None);
+        }
+        Ok(Some(Projectile {
+            move_id: self.u8(),
+            pos_x: self.fx(),
+            pos_y: self.fx(),
+            vel_x: self.fx(),
+            facing: self.facing()?,
+            frames_left: self.u16(),
+        }))
+    }
+}
@@ -0,0 +1,295 @@
+//! Spectator streaming.
+//!
+//! The match host feeds every confirmed frame to a `SpectateHost`, which
+//! publishes the inputs as `SpectatorMessage::Inputs` and, every
+//! `keyframe_interval` frames, a snapshot of the state at the start of the
+//! frame as `SpectatorMessage::Keyframe`. Messages go to a `Publisher`, the
+//! pub/sub channel the host broadcasts on, so any number of spectators can
+//! subscribe without the host tracking them.
+//!
+//! A `Spectator` can join at any point: it ignores inputs until the next
+//! keyframe, then re-simulates from it. Later keyframes are checked against
+//! its own state.
+//!
+//! `broadcast_delay` holds every message back until the host has confirmed
+//! that many frames past it, so a tournament stream runs behind the match
+//! and cannot be watched to read the players' screens.
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::character::CharacterDef;
+use crate::checksum::checksum;
+use crate::input::Input;
+use crate::sim::step;
+use crate::snapshot::{self, SnapshotError, SNAPSHOT_LEN};
+use crate::state::GameState;
+use crate::MAX_PLAYERS;
+
+const TAG_INPUTS: u8 = 0;
+const TAG_KEYFRAME: u8 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpectateConfig {
+    /// Frames between keyframes; spectators joining mid-match wait at most
+    /// this long. Zero sends only the initial keyframe.
+    pub keyframe_interval: u32,
+    /// Frames every message is held back before it is published.
+    pub broadcast_delay: u32,
+}
+
+impl Default for SpectateConfig {
+    fn default() -> Self {
+        SpectateConfig {
+            keyframe_interval: 300,
+            broadcast_delay: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SpectatorMessage {
+    Inputs {
+        frame: u32,
+        inputs: [Input; MAX_PLAYERS],
+    },
+    /// State at the start of `frame`, before its inputs.
+    Keyframe {
+        frame: u32,
+        snapshot: [u8; SNAPSHOT_LEN],
+    },
+}
+
+impl SpectatorMessage {
+    pub fn frame(&self) -> u32 {
+        match self {
+            SpectatorMessage::Inputs { frame, .. } | SpectatorMessage::Keyframe { frame, .. } => {
+                *frame
+            }
+        }
+    }
+
+    /// Wire form: a tag byte, the frame as a little-endian `u32`, then
+    /// either both players' input bits or the snapshot bytes.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(5 + SNAPSHOT_LEN);
+        match self {
+            SpectatorMessage::Inputs { frame, inputs } => {
+                out.push(TAG_INPUTS);
+                out.extend_from_slice(&frame.to_le_bytes());
+                for input in inputs {
+                    out.extend_from_slice(&input.to_le_bytes());
+                }
+            }
+            SpectatorMessage::Keyframe { frame, snapshot } => {
+                out.push(TAG_KEYFRAME);
+                out.extend_from_slice(&frame.to_le_bytes());
+                out.extend_from_slice(snapshot);
+            }
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, SpectateError> {
+        let (&tag, rest) = bytes.split_first().ok_or(SpectateError::Malformed)?;
+        let frame = rest.get(..4).ok_or(SpectateError::Malformed)?;
+        let frame = u32::from_le_bytes([frame[0], frame[1], frame[2], frame[3]]);
+        let body = &rest[4..];
+        match tag {
+            TAG_INPUTS if body.len() == 2 * MAX_PLAYERS => {
+                let mut inputs = [Input::NONE; MAX_PLAYERS];
+                for (input, raw) in inputs.iter_mut().zip(body.chunks_exact(2)) {
+                    let bits = u16::from_le_bytes([raw[0], raw[1]]);
+                    *input = Input::from_bits(bits).ok_or(SpectateError::Malformed)?;
+                }
+                Ok(SpectatorMessage::Inputs { frame, inputs })
+            }
+            TAG_KEYFRAME if body.len() == SNAPSHOT_LEN => {
+                let mut snapshot = [0; SNAPSHOT_LEN];
+                snapshot.copy_from_slice(body);
+                Ok(SpectatorMessage::Keyframe { frame, snapshot })
+            }
+            _ => Err(SpectateError::Malformed),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpectateError {
+    Malformed,
+    /// Confirmed frames must be fed, and inputs received, in order.
+    FrameGap {
+        expected: u32,
+        received: u32,
+    },
+    InvalidKeyframe(SnapshotError),
+    /// A keyframe disagrees with the spectator's simulated state.
+    Desync {
+        frame: u32,
+    },
+}
+
+impl fmt::Display for SpectateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SpectateError::Malformed => write!(f, "malformed spectator message"),
+            SpectateError::FrameGap { expected, received } => {
+                write!(f, "expected frame {}, received {}", expected, received)
+            }
+            SpectateError::InvalidKeyframe(err) => write!(f, "invalid keyframe: {}", err),
+            SpectateError::Desync { frame } => {
+                write!(f, "keyframe for frame {} does not match the stream", frame)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SpectateError {}
+
+/// The channel a host broadcasts on.
+pub trait Publisher {
+    fn publish(&mut self, message: &SpectatorMessage);
+}
+
+pub struct SpectateHost<'a, P: Publisher> {
+    publisher: P,
+    config: SpectateConfig,
+    defs: [&'a CharacterDef<'a>; MAX_PLAYERS],
+    /// State at the start of `next_frame`.
+    state: GameState,
+    next_frame: u32,
+    held: VecDeque<SpectatorMessage>,
+}
+
+impl<'a, P: Publisher> SpectateHost<'a, P> {
+    pub fn new(
+        initial: GameState,
+        defs: [&'a CharacterDef<'a>; MAX_PLAYERS],
+        config: SpectateConfig,
+        publisher: P,
+    ) -> Self {
+        SpectateHost {
+            publisher,
+            config,
+            defs,
+            state: initial,
+            next_frame: 0,
+            held: VecDeque::new(),
+        }
+    }
+
+    pub fn publisher(&self) -> &P {
+        &self.publisher
+    }
+
+    /// Streams the confirmed inputs for `frame`, preceded by a keyframe on
+    /// keyframe frames, and publishes whatever the delay now releases.
+    pub fn push_confirmed(
+        &mut self,
+        frame: u32,
+        inputs: [Input; MAX_PLAYERS],
+    ) -> Result<(), SpectateError> {
+        if frame != self.next_frame {
+            return Err(SpectateError::FrameGap {
+                expected: self.next_frame,
+                received: frame,
+            });
+        }
+        let interval = self.config.keyframe_interval;
+        if frame == 0 || (interval != 0 && frame % interval == 0) {
+            self.held.push_back(SpectatorMessage::Keyframe {
+                frame,
+                snapshot: snapshot::encode(&self.state),
+            });
+        }
+        self.held
+            .push_back(SpectatorMessage::Inputs { frame, inputs });
+        self.state = step(&self.state, inputs, self.defs);
+        self.next_frame += 1;
+
+        while let Some(message) = self.held.front() {
+            if message.frame() + self.config.broadcast_delay > frame {
+                break;
+            }
+            self.publisher.publish(message);
+            self.held.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Publishes every held message, e.g. once the match is over.
+    pub fn flush(&mut self) {
+        for message in self.held.drain(..) {
+            self.publisher.publish(&message);
+        }
+    }
+}
+
+pub struct Spectator<'a> {
+    defs: [&'a CharacterDef<'a>; MAX_PLAYERS],
+    /// State at the start of `next_frame`, once a keyframe has arrived.
+    state: Option<GameState>,
+    next_frame: u32,
+}
+
+impl<'a> Spectator<'a> {
+    pub fn new(defs: [&'a CharacterDef<'a>; MAX_PLAYERS]) -> Self {
+        Spectator {
+            defs,
+            state: None,
+            next_frame: 0,
+        }
+    }
+
+    /// Replayed state, `None` until the first keyframe.
+    pub fn state(&self) -> Option<&GameState> {
+        self.state.as_ref()
+    }
+
+    pub fn receive(&mut self, message: &SpectatorMessage) -> Result<(), SpectateError> {
+        match message {
+            SpectatorMessage::Keyframe { frame, snapshot } => {
+                let keyframe =
+                    snapshot::decode(snapshot).map_err(SpectateError::InvalidKeyframe)?;
+                match &self.state {
+                    Some(state) if *frame == self.next_frame => {
+                        if checksum(state) != checksum(&keyframe) {
+                            return Err(SpectateError::Desync { frame: *frame });
+                        }
+                    }
+                    Some(_) if *frame < self.next_frame => {}
+                    Some(_) => {
+                        return Err(SpectateError::FrameGap {
+                            expected: self.next_frame,
+                            received: *frame,
+                        })
+                    }
+                    None => {
+                        self.state = Some(keyframe);
+                        self.next_frame = *frame;
+                    }
+                }
+                Ok(())
+            }
+            SpectatorMessage::Inputs { frame, inputs } => {
+                let Some(state) = &mut self.state else {
+                    return Ok(());
+                };
+                if *frame < self.next_frame {
+                    return Ok(());
+                }
+                if *frame > self.next_frame {
+                    return Err(SpectateError::FrameGap {
+                        expected: self.next_frame,
+                        received: *frame,
+                    });
+                }
+                *state = step(state, *inputs, self.defs);
+                self.next_frame += 1;
+                Ok(())
+            }
+        }
+    }
+}
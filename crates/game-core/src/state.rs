@@ -0,0 +1,173 @@
+//! Authoritative simulation state.
+
+use crate::character::CharacterDef;
+use crate::fixed::Fx32;
+use crate::proration::Proration;
+use crate::rng::Rng;
+use crate::MAX_PLAYERS;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Facing {
+    Left = -1,
+    Right = 1,
+}
+
+impl Facing {
+    pub fn sign(self) -> i32 {
+        self as i32
+    }
+}
+
+/// Stage bounds in pixels. The floor is at `floor_y`, positive Y is up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Stage {
+    pub left_wall: Fx32,
+    pub right_wall: Fx32,
+    pub floor_y: Fx32,
+}
+
+impl Default for Stage {
+    fn default() -> Self {
+        Stage {
+            left_wall: Fx32::from_int(-480),
+            right_wall: Fx32::from_int(480),
+            floor_y: Fx32::ZERO,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PlayerState {
+    pub character_id: u8,
+    pub pos_x: Fx32,
+    pub pos_y: Fx32,
+    pub vel_x: Fx32,
+    pub vel_y: Fx32,
+    pub facing: Facing,
+    pub grounded: bool,
+    pub guarding: bool,
+    pub health: i32,
+    /// Active move id, or `None` when idle.
+    pub current_move: Option<u8>,
+    pub move_frame: u16,
+    /// Set once the active move has connected so it hits at most once.
+    pub move_connected: bool,
+    pub hitstun: u16,
+    pub blockstun: u16,
+    /// Spark variant shown for the last hit taken, drawn from the match RNG.
+    pub hit_spark: u8,
+    /// Unblocked hits taken since last leaving hitstun, for proration.
+    pub combo_hits: u16,
+    pub meter: u16,
+    /// Knocked into the air by a hit and stunned until landing.
+    pub launched: bool,
+    /// Juggle points taken since being launched.
+    pub juggle: u8,
+    /// Frames left during which hits pass through, given to the incoming
+    /// character of a team battle.
+    pub invincible: u16,
+}
+
+impl PlayerState {
+    pub fn new(def: &CharacterDef, pos_x: Fx32, facing: Facing) -> Self {
+        PlayerState {
+            character_id: def.id,
+            pos_x,
+            pos_y: Fx32::ZERO,
+            vel_x: Fx32::ZERO,
+            vel_y: Fx32::ZERO,
+            facing,
+            grounded: true,
+            guarding: false,
+            health: def.max_health,
+            current_move: None,
+            move_frame: 0,
+            move_connected: false,
+            hitstun: 0,
+            blockstun: 0,
+            hit_spark: 0,
+            combo_hits: 0,
+            meter: 0,
+            launched: false,
+            juggle: 0,
+            invincible: 0,
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.health > 0
+    }
+
+    /// True while hit or block stun, or being launched, prevents the player
+    /// from acting.
+    pub fn is_stunned(&self) -> bool {
+        self.hitstun > 0 || self.blockstun > 0 || self.launched
+    }
+
+    pub fn is_actionable(&self) -> bool {
+        self.is_alive() && !self.is_stunned() && self.current_move.is_none()
+    }
+}
+
+/// Projectile in flight, owned by the player whose slot it occupies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Projectile {
+    /// Move that launched it, for damage and box lookup.
+    pub move_id: u8,
+    pub pos_x: Fx32,
+    pub pos_y: Fx32,
+    pub vel_x: Fx32,
+    pub facing: Facing,
+    pub frames_left: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GameState {
+    pub frame: u32,
+    pub stage: Stage,
+    pub players: [PlayerState; MAX_PLAYERS],
+    /// At most one projectile per player, indexed by owner.
+    pub projectiles: [Option<Projectile>; MAX_PLAYERS],
+    pub rng: Rng,
+    pub proration: Proration,
+    /// Frames left in a super freeze, during which nothing else advances.
+    pub super_freeze: u8,
+}
+
+impl GameState {
+    /// Starting positions are mirrored around the stage center.
+    pub const START_OFFSET: Fx32 = Fx32::from_int(150);
+
+    pub fn new(p1: &CharacterDef, p2: &CharacterDef) -> Self {
+        Self::with_seed(p1, p2, 0)
+    }
+
+    /// Initial state with the RNG seeded from the match seed.
+    pub fn with_seed(p1: &CharacterDef, p2: &CharacterDef, seed: u64) -> Self {
+        GameState {
+            frame: 0,
+            stage: Stage::default(),
+            players: [
+                PlayerState::new(p1, -Self::START_OFFSET, Facing::Right),
+                PlayerState::new(p2, Self::START_OFFSET, Facing::Left),
+            ],
+            projectiles: [None; MAX_PLAYERS],
+            rng: Rng::new(seed),
+            proration: Proration::DEFAULT,
+            super_freeze: 0,
+        }
+    }
+
+    /// Index of the only player left standing, if the round is decided.
+    pub fn winner(&self) -> Option<usize> {
+        match (self.players[0].is_alive(), self.players[1].is_alive()) {
+            (true, false) => Some(0),
+            (false, true) => Some(1),
+            _ => None,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.players.iter().any(|p| !p.is_alive())
+    }
+}
@@ -0,0 +1,115 @@
+//! King-of-the-hill team battles.
+//!
+//! Each side brings `TEAM_SIZE` characters and fights with one at a time.
+//! When a character is knocked out, its side's next character comes in and
+//! the survivor stays in with the health it had left. Both sides go back to
+//! their starting positions, meter carries over by the battle's `Carryover`
+//! rule, and the incoming character is invincible for
+//! `INCOMING_INVINCIBILITY` frames so it can't be hit before it can act. A
+//! double knockout brings in the next character on both sides. The battle
+//! is over once a side has no characters left.
+//!
+//! Rosters are resolved like `simulate_frame`: unknown character ids fall
+//! back to the first roster entry.
+
+use crate::character::{roster, CharacterDef, ROSTER};
+use crate::input::Input;
+use crate::meter::{self, Carryover};
+use crate::sim::step;
+use crate::state::GameState;
+use crate::MAX_PLAYERS;
+
+pub const TEAM_SIZE: usize = 3;
+/// Frames the incoming character is invincible for.
+pub const INCOMING_INVINCIBILITY: u16 = 45;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TeamBattle {
+    /// Character ids per side, in the order they fight.
+    pub rosters: [[u8; TEAM_SIZE]; MAX_PLAYERS],
+    /// Characters each side has lost so far.
+    pub knocked_out: [u8; MAX_PLAYERS],
+    pub carryover: Carryover,
+    /// The fight between the two active characters.
+    pub state: GameState,
+}
+
+impl TeamBattle {
+    pub fn new(rosters: [[u8; TEAM_SIZE]; MAX_PLAYERS], seed: u64, carryover: Carryover) -> Self {
+        TeamBattle {
+            rosters,
+            knocked_out: [0; MAX_PLAYERS],
+            carryover,
+            state: GameState::with_seed(def(rosters[0][0]), def(rosters[1][0]), seed),
+        }
+    }
+
+    /// Roster slot of the character `side` is fighting with.
+    pub fn active(&self, side: usize) -> usize {
+        (self.knocked_out[side] as usize).min(TEAM_SIZE - 1)
+    }
+
+    pub fn defs(&self) -> [&'static CharacterDef<'static>; MAX_PLAYERS] {
+        core::array::from_fn(|side| def(self.rosters[side][self.active(side)]))
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.knocked_out
+            .iter()
+            .any(|&lost| lost as usize >= TEAM_SIZE)
+    }
+
+    /// Side that knocked out every opposing character and still has one
+    /// standing; `None` while the battle runs and after a final double
+    /// knockout.
+    pub fn winner(&self) -> Option<usize> {
+        match self.knocked_out.map(|lost| lost as usize >= TEAM_SIZE) {
+            [false, true] => Some(0),
+            [true, false] => Some(1),
+            _ => None,
+        }
+    }
+
+    /// Advances one frame, bringing in the next characters after a
+    /// knockout. Does nothing once the battle is over.
+    pub fn step(&mut self, inputs: [Input; MAX_PLAYERS]) {
+        if self.is_over() {
+            return;
+        }
+        self.state = step(&self.state, inputs, self.defs());
+        if self.state.is_over() {
+            self.next_characters();
+        }
+    }
+
+    fn next_characters(&mut self) {
+        let previous = self.state;
+        let fallen = previous.players.map(|player| !player.is_alive());
+        for (lost, fallen) in self.knocked_out.iter_mut().zip(fallen) {
+            *lost += fallen as u8;
+        }
+        if self.is_over() {
+            return;
+        }
+
+        let [p1, p2] = self.defs();
+        let mut next = GameState::new(p1, p2);
+        next.frame = previous.frame;
+        next.stage = previous.stage;
+        next.rng = previous.rng;
+        next.proration = previous.proration;
+        meter::carry_over(&previous, &mut next, self.carryover);
+        for ((player, last), fallen) in next.players.iter_mut().zip(&previous.players).zip(fallen) {
+            if fallen {
+                player.invincible = INCOMING_INVINCIBILITY;
+            } else {
+                player.health = last.health;
+            }
+        }
+        self.state = next;
+    }
+}
+
+fn def(id: u8) -> &'static CharacterDef<'static> {
+    roster(id).unwrap_or(&ROSTER[0])
+}
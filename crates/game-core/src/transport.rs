@@ -0,0 +1,462 @@
+//! Unreliable-ordered input channel for the netcode.
+//!
+//! A `Channel` carries one peer's inputs, and optionally checksums, over
+//! any `Transport`: `UdpTransport` for real play and `MemoryTransport` for
+//! deterministic tests. Packets are never resent. Instead every packet
+//! repeats each input the peer has not acknowledged yet, oldest first, up to
+//! `redundancy` inputs and whatever fits in `mtu`, so a lost packet is
+//! covered by the next one. Packets arriving after a newer one are dropped.
+//!
+//! Packet layout, little-endian:
+//!
+//! ```text
+//! sequence     u16
+//! ack          u16     newest sequence received from the peer
+//! ack_bits     u32     bit i set if sequence ack - 1 - i was received
+//! flags        u8      bit 0: a checksum follows, bit 1: ack fields are set
+//! checksum     u32 frame, u64 checksum, only with flag bit 0
+//! first_frame  u32
+//! count        u8
+//! inputs       count x u16
+//! ```
+
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::convert::Infallible;
+use core::fmt;
+
+use crate::checksum::FrameChecksum;
+use crate::input::Input;
+
+/// Header bytes before the inputs, with a checksum.
+const MAX_HEADER_LEN: usize = 2 + 2 + 4 + 1 + 12 + 4 + 1;
+/// Sent packets remembered for matching acks; also the ack bitfield width.
+const SENT_HISTORY: usize = 32;
+const FLAG_CHECKSUM: u8 = 1;
+const FLAG_ACK: u8 = 2;
+
+/// Sends and receives whole datagrams.
+pub trait Transport {
+    type Error;
+
+    fn send(&mut self, packet: &[u8]) -> Result<(), Self::Error>;
+
+    /// Copies the next waiting datagram into `buf` and returns its length,
+    /// or `None` when nothing is waiting.
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Self::Error>;
+}
+
+/// One end of an in-memory link that delivers every packet in order.
+pub struct MemoryTransport {
+    inbox: Rc<RefCell<VecDeque<Vec<u8>>>>,
+    outbox: Rc<RefCell<VecDeque<Vec<u8>>>>,
+}
+
+impl MemoryTransport {
+    pub fn pair() -> (MemoryTransport, MemoryTransport) {
+        let a = Rc::new(RefCell::new(VecDeque::new()));
+        let b = Rc::new(RefCell::new(VecDeque::new()));
+        (
+            MemoryTransport {
+                inbox: a.clone(),
+                outbox: b.clone(),
+            },
+            MemoryTransport {
+                inbox: b,
+                outbox: a,
+            },
+        )
+    }
+}
+
+impl Transport for MemoryTransport {
+    type Error = Infallible;
+
+    fn send(&mut self, packet: &[u8]) -> Result<(), Infallible> {
+        self.outbox.borrow_mut().push_back(packet.to_vec());
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Infallible> {
+        Ok(self.inbox.borrow_mut().pop_front().map(|packet| {
+            let len = packet.len().min(buf.len());
+            buf[..len].copy_from_slice(&packet[..len]);
+            len
+        }))
+    }
+}
+
+/// Non-blocking UDP socket connected to the peer.
+#[cfg(feature = "std")]
+pub struct UdpTransport {
+    socket: std::net::UdpSocket,
+}
+
+#[cfg(feature = "std")]
+impl UdpTransport {
+    pub fn connect(
+        local: std::net::SocketAddr,
+        peer: std::net::SocketAddr,
+    ) -> std::io::Result<Self> {
+        let socket = std::net::UdpSocket::bind(local)?;
+        socket.connect(peer)?;
+        socket.set_nonblocking(true)?;
+        Ok(UdpTransport { socket })
+    }
+
+    pub fn local_addr(&self) -> std::io::Result<std::net::SocketAddr> {
+        self.socket.local_addr()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Transport for UdpTransport {
+    type Error = std::io::Error;
+
+    fn send(&mut self, packet: &[u8]) -> std::io::Result<()> {
+        match self.socket.send(packet) {
+            Ok(_) => Ok(()),
+            // A full send buffer is just another lost packet.
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => Ok(()),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> std::io::Result<Option<usize>> {
+        match self.socket.recv(buf) {
+            Ok(len) => Ok(Some(len)),
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ChannelConfig {
+    /// Largest packet sent, in bytes.
+    pub mtu: usize,
+    /// Most inputs repeated in one packet.
+    pub redundancy: usize,
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        ChannelConfig {
+            mtu: 1200,
+            redundancy: 32,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelError<E> {
+    Transport(E),
+    /// The MTU cannot fit a header and one input, or `redundancy` is zero.
+    InvalidConfig,
+    /// Local inputs must be pushed for consecutive frames.
+    InputOutOfOrder {
+        expected: u32,
+        received: u32,
+    },
+}
+
+impl<E: fmt::Display> fmt::Display for ChannelError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChannelError::Transport(err) => write!(f, "transport error: {}", err),
+            ChannelError::InvalidConfig => write!(f, "invalid channel configuration"),
+            ChannelError::InputOutOfOrder { expected, received } => write!(
+                f,
+                "local input for frame {} pushed before frame {}",
+                received, expected
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for ChannelError<E> {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelEvent {
+    /// The peer's input for `frame`, delivered once and in frame order.
+    Input {
+        frame: u32,
+        input: Input,
+    },
+    Checksum(FrameChecksum),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    pub packets_sent: u32,
+    pub packets_received: u32,
+    /// Packets dropped for arriving after a newer one.
+    pub packets_stale: u32,
+    pub packets_malformed: u32,
+}
+
+pub struct Channel<T: Transport> {
+    transport: T,
+    config: ChannelConfig,
+    next_sequence: u16,
+    /// Newest sequence received and the ack bits for the ones before it.
+    remote_sequence: Option<u16>,
+    remote_bits: u32,
+    /// Sequence of each recent packet and the last input frame it carried.
+    sent: [Option<(u16, Option<u32>)>; SENT_HISTORY],
+    /// Local inputs from `pending_start` the peer has not acknowledged.
+    pending: VecDeque<Input>,
+    pending_start: u32,
+    outgoing_checksum: Option<FrameChecksum>,
+    next_remote_frame: u32,
+    stats: ChannelStats,
+}
+
+impl<T: Transport> Channel<T> {
+    pub fn new(transport: T, config: ChannelConfig) -> Result<Self, ChannelError<T::Error>> {
+        if config.mtu < MAX_HEADER_LEN + 2 || config.redundancy == 0 {
+            return Err(ChannelError::InvalidConfig);
+        }
+        Ok(Channel {
+            transport,
+            config,
+            next_sequence: 0,
+            remote_sequence: None,
+            remote_bits: 0,
+            sent: [None; SENT_HISTORY],
+            pending: VecDeque::new(),
+            pending_start: 0,
+            outgoing_checksum: None,
+            next_remote_frame: 0,
+            stats: ChannelStats::default(),
+        })
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn stats(&self) -> ChannelStats {
+        self.stats
+    }
+
+    /// Local inputs the peer has not acknowledged yet.
+    pub fn unacked_inputs(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Queues the local input for `frame`, which must follow the previous
+    /// one. It goes out with every packet until the peer acknowledges it.
+    pub fn push_local_input(
+        &mut self,
+        frame: u32,
+        input: Input,
+    ) -> Result<(), ChannelError<T::Error>> {
+        let expected = self.pending_start + self.pending.len() as u32;
+        if frame != expected {
+            return Err(ChannelError::InputOutOfOrder {
+                expected,
+                received: frame,
+            });
+        }
+        self.pending.push_back(input);
+        Ok(())
+    }
+
+    /// Attaches `checksum` to the next packet.
+    pub fn push_checksum(&mut self, checksum: FrameChecksum) {
+        self.outgoing_checksum = Some(checksum);
+    }
+
+    /// Sends one packet with the pending inputs and checksum.
+    pub fn send(&mut self) -> Result<(), ChannelError<T::Error>> {
+        let mut packet = Vec::with_capacity(self.config.mtu);
+        let sequence = self.next_sequence;
+        self.next_sequence = sequence.wrapping_add(1);
+        packet.extend_from_slice(&sequence.to_le_bytes());
+        packet.extend_from_slice(&self.remote_sequence.unwrap_or(0).to_le_bytes());
+        packet.extend_from_slice(&self.remote_bits.to_le_bytes());
+        let checksum = self.outgoing_checksum.take();
+        let mut flags = 0;
+        if checksum.is_some() {
+            flags |= FLAG_CHECKSUM;
+        }
+        if self.remote_sequence.is_some() {
+            flags |= FLAG_ACK;
+        }
+        packet.push(flags);
+        if let Some(checksum) = checksum {
+            packet.extend_from_slice(&checksum.frame.to_le_bytes());
+            packet.extend_from_slice(&checksum.checksum.to_le_bytes());
+        }
+
+        let room = (self.config.mtu - packet.len() - 5) / 2;
+        let count = self
+            .pending
+            .len()
+            .min(self.config.redundancy)
+            .min(room)
+            .min(u8::MAX as usize);
+        packet.extend_from_slice(&self.pending_start.to_le_bytes());
+        packet.push(count as u8);
+        for input in self.pending.iter().take(count) {
+            packet.extend_from_slice(&input.to_le_bytes());
+        }
+
+        let last_frame = (count > 0).then(|| self.pending_start + count as u32 - 1);
+        self.sent[sequence as usize % SENT_HISTORY] = Some((sequence, last_frame));
+        self.stats.packets_sent += 1;
+        self.transport
+            .send(&packet)
+            .map_err(ChannelError::Transport)
+    }
+
+    /// Reads every waiting packet and returns the new remote inputs and
+    /// checksums, in order.
+    pub fn poll(&mut self) -> Result<Vec<ChannelEvent>, ChannelError<T::Error>> {
+        let mut events = Vec::new();
+        let mut buf = [0; 2048];
+        while let Some(len) = self
+            .transport
+            .recv(&mut buf)
+            .map_err(ChannelError::Transport)?
+        {
+            match Packet::parse(&buf[..len]) {
+                Some(packet) => self.receive(&packet, &mut events),
+                None => self.stats.packets_malformed += 1,
+            }
+        }
+        Ok(events)
+    }
+
+    fn receive(&mut self, packet: &Packet, events: &mut Vec<ChannelEvent>) {
+        match self.remote_sequence {
+            Some(newest) if !sequence_newer(packet.sequence, newest) => {
+                self.stats.packets_stale += 1;
+                return;
+            }
+            Some(newest) => {
+                let shift = packet.sequence.wrapping_sub(newest) as u32;
+                self.remote_bits = if shift > SENT_HISTORY as u32 {
+                    0
+                } else {
+                    ((self.remote_bits as u64) << shift | 1 << (shift - 1)) as u32
+                };
+            }
+            None => self.remote_bits = 0,
+        }
+        self.remote_sequence = Some(packet.sequence);
+        self.stats.packets_received += 1;
+
+        if let Some((ack, ack_bits)) = packet.ack {
+            self.apply_acks(ack, ack_bits);
+        }
+
+        if let Some(checksum) = packet.checksum {
+            events.push(ChannelEvent::Checksum(checksum));
+        }
+        for (offset, input) in packet.inputs.iter().enumerate() {
+            let frame = packet.first_frame + offset as u32;
+            if frame == self.next_remote_frame {
+                events.push(ChannelEvent::Input {
+                    frame,
+                    input: *input,
+                });
+                self.next_remote_frame += 1;
+            }
+        }
+    }
+
+    /// Drops pending inputs carried by any packet the peer acknowledged.
+    fn apply_acks(&mut self, ack: u16, ack_bits: u32) {
+        let mut acked_frame = None;
+        for entry in self.sent.iter_mut() {
+            let Some((sequence, last_frame)) = *entry else {
+                continue;
+            };
+            let behind = ack.wrapping_sub(sequence) as u32;
+            let acked = behind == 0
+                || (behind <= SENT_HISTORY as u32 && ack_bits & (1 << (behind - 1)) != 0);
+            if acked {
+                acked_frame = acked_frame.max(last_frame);
+                *entry = None;
+            }
+        }
+        if let Some(frame) = acked_frame {
+            while self.pending_start <= frame && self.pending.pop_front().is_some() {
+                self.pending_start += 1;
+            }
+        }
+    }
+}
+
+struct Packet {
+    sequence: u16,
+    /// Newest sequence the peer received and its ack bits, once it has
+    /// received any.
+    ack: Option<(u16, u32)>,
+    checksum: Option<FrameChecksum>,
+    first_frame: u32,
+    inputs: Vec<Input>,
+}
+
+impl Packet {
+    fn parse(bytes: &[u8]) -> Option<Packet> {
+        let mut reader = Reader { bytes, pos: 0 };
+        let sequence = u16::from_le_bytes(reader.take()?);
+        let ack = u16::from_le_bytes(reader.take()?);
+        let ack_bits = u32::from_le_bytes(reader.take()?);
+        let flags = reader.take::<1>()?[0];
+        if flags & !(FLAG_CHECKSUM | FLAG_ACK) != 0 {
+            return None;
+        }
+        let checksum = if flags & FLAG_CHECKSUM != 0 {
+            Some(FrameChecksum {
+                frame: u32::from_le_bytes(reader.take()?),
+                checksum: u64::from_le_bytes(reader.take()?),
+            })
+        } else {
+            None
+        };
+        let first_frame = u32::from_le_bytes(reader.take()?);
+        let count = reader.take::<1>()?[0];
+        let mut inputs = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            inputs.push(Input::from_bits(u16::from_le_bytes(reader.take()?))?);
+        }
+        (reader.pos == bytes.len()).then_some(Packet {
+            sequence,
+            ack: (flags & FLAG_ACK != 0).then_some((ack, ack_bits)),
+            checksum,
+            first_frame,
+            inputs,
+        })
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.bytes.get(self.pos..self.pos + N)?;
+        self.pos += N;
+        let mut out = [0; N];
+        out.copy_from_slice(bytes);
+        Some(out)
+    }
+}
+
+/// Whether sequence `a` is newer than `b`, allowing for wrap-around.
+fn sequence_newer(a: u16, b: u16) -> bool {
+    (a.wrapping_sub(b) as i16) > 0
+}
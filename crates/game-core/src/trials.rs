@@ -0,0 +1,220 @@
+//! Combo trials.
+//!
+//! `validate` re-simulates a recorded attempt and decides whether the
+//! player performed a combo as stored on-chain in `combo_mint`'s
+//! `ComboAccount`: the character, then each of its moves in order, every
+//! one connecting as an unblocked hit while the opponent is still in
+//! hitstun from the one before. Verifiers attest trial completions from
+//! this result, so it only trusts what the simulation itself reports.
+//!
+//! Movement before the first move is free. From the first move on, every
+//! move the player starts must be the next one in the combo, and a move
+//! other than a projectile must have connected before the next starts.
+
+use core::fmt;
+
+use crate::character::{CharacterDef, MoveDef};
+use crate::input::Input;
+use crate::sim::{step_observed, Contact, Observer};
+use crate::state::GameState;
+use crate::MAX_PLAYERS;
+
+/// The parts of a `ComboAccount` a trial checks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboTrial<'a> {
+    pub character_id: u8,
+    pub moves: &'a [u8],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrialCompletion {
+    /// Index into the attempt's inputs of the frame the last hit landed on.
+    pub frame: usize,
+    /// Total damage after proration.
+    pub damage: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrialError {
+    EmptyCombo,
+    WrongCharacter {
+        expected: u8,
+        actual: u8,
+    },
+    /// The player started a move other than the combo's next one.
+    WrongMove {
+        index: usize,
+        expected: u8,
+        performed: u8,
+    },
+    /// The combo's move at `index` never hit.
+    Missed {
+        index: usize,
+    },
+    Blocked {
+        index: usize,
+    },
+    /// The opponent recovered before the hit at `index` landed.
+    Dropped {
+        index: usize,
+    },
+    /// The attempt ended after `hits` of the combo's hits.
+    Incomplete {
+        hits: usize,
+    },
+}
+
+impl fmt::Display for TrialError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrialError::EmptyCombo => write!(f, "combo has no moves"),
+            TrialError::WrongCharacter { expected, actual } => write!(
+                f,
+                "combo is for character {}, attempt played character {}",
+                expected, actual
+            ),
+            TrialError::WrongMove {
+                index,
+                expected,
+                performed,
+            } => write!(
+                f,
+                "move {} of the combo should be {} but was {}",
+                index, expected, performed
+            ),
+            TrialError::Missed { index } => write!(f, "move {} of the combo missed", index),
+            TrialError::Blocked { index } => write!(f, "move {} of the combo was blocked", index),
+            TrialError::Dropped { index } => {
+                write!(f, "combo dropped before move {}", index)
+            }
+            TrialError::Incomplete { hits } => {
+                write!(f, "attempt ended after {} hits", hits)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TrialError {}
+
+struct Attempt<'a> {
+    player: usize,
+    moves: &'a [u8],
+    started: usize,
+    hits: usize,
+    damage: i32,
+    /// The last started move fires a projectile, which may land after the
+    /// next move starts.
+    last_projectile: bool,
+    error: Option<TrialError>,
+}
+
+impl Attempt<'_> {
+    fn fail(&mut self, error: TrialError) {
+        self.error.get_or_insert(error);
+    }
+
+    fn done(&self) -> bool {
+        self.hits == self.moves.len()
+    }
+}
+
+impl Observer for Attempt<'_> {
+    fn move_frame(&mut self, player: usize, move_def: &MoveDef, frame: u16) {
+        if player != self.player || frame != 0 || self.done() {
+            return;
+        }
+        let index = self.started;
+        if index == self.moves.len() {
+            return self.fail(TrialError::Missed { index: self.hits });
+        }
+        if move_def.id != self.moves[index] {
+            return self.fail(TrialError::WrongMove {
+                index,
+                expected: self.moves[index],
+                performed: move_def.id,
+            });
+        }
+        if self.hits < index && !self.last_projectile {
+            return self.fail(TrialError::Missed { index: self.hits });
+        }
+        self.started += 1;
+        self.last_projectile = move_def.projectile.is_some();
+    }
+
+    fn contact(
+        &mut self,
+        attacker: usize,
+        move_def: &MoveDef,
+        _projectile: bool,
+        contact: Contact,
+    ) {
+        if attacker != self.player || self.done() {
+            return;
+        }
+        let index = self.hits;
+        match contact {
+            Contact::Hit {
+                damage,
+                hits_before,
+                ..
+            } => {
+                if index >= self.started || move_def.id != self.moves[index] {
+                    self.fail(TrialError::Missed { index });
+                } else if hits_before as usize != index {
+                    self.fail(TrialError::Dropped { index });
+                } else {
+                    self.hits += 1;
+                    self.damage += damage;
+                }
+            }
+            Contact::Blocked { .. } => self.fail(TrialError::Blocked { index }),
+            Contact::LostClash | Contact::Cancelled => self.fail(TrialError::Missed { index }),
+        }
+    }
+}
+
+/// Re-simulates `inputs` from `initial` and checks that `player` performed
+/// the trial's combo.
+pub fn validate(
+    trial: &ComboTrial,
+    initial: &GameState,
+    player: usize,
+    inputs: &[[Input; MAX_PLAYERS]],
+    defs: [&CharacterDef; MAX_PLAYERS],
+) -> Result<TrialCompletion, TrialError> {
+    if trial.moves.is_empty() {
+        return Err(TrialError::EmptyCombo);
+    }
+    let actual = initial.players[player].character_id;
+    if actual != trial.character_id {
+        return Err(TrialError::WrongCharacter {
+            expected: trial.character_id,
+            actual,
+        });
+    }
+
+    let mut attempt = Attempt {
+        player,
+        moves: trial.moves,
+        started: 0,
+        hits: 0,
+        damage: 0,
+        last_projectile: false,
+        error: None,
+    };
+    let mut state = *initial;
+    for (frame, frame_inputs) in inputs.iter().enumerate() {
+        state = step_observed(&state, *frame_inputs, defs, &mut attempt);
+        if let Some(error) = attempt.error {
+            return Err(error);
+        }
+        if attempt.done() {
+            return Ok(TrialCompletion {
+                frame,
+                damage: attempt.damage,
+            });
+        }
+    }
+    Err(TrialError::Incomplete { hits: attempt.hits })
+}
@@ -0,0 +1,79 @@
+use game_core::character::ROSTER;
+use game_core::checksum::checksum;
+use game_core::{GameState, Input, Session, SessionConfig, SessionError};
+
+fn input(player: usize, frame: u32) -> Input {
+    match (frame * 7 + player as u32 * 13) % 40 {
+        0..=9 => Input::RIGHT,
+        10..=13 => Input::ATTACK,
+        14..=19 => Input::LEFT,
+        20..=21 => Input::SPECIAL,
+        22..=29 => Input::DEFEND,
+        _ => Input::NONE,
+    }
+}
+
+/// Runs two sessions with a fixed input delay, exchanging checksums, and
+/// returns the first error either side reports.
+fn run_pair(seeds: [u64; 2], frames: u32) -> Result<u32, SessionError> {
+    let defs = [&ROSTER[0], &ROSTER[1]];
+    let mut sessions = [0, 1].map(|player| {
+        let initial = GameState::with_seed(defs[0], defs[1], seeds[player]);
+        let config = SessionConfig {
+            local_player: player,
+            checksum_interval: 10,
+            ..SessionConfig::default()
+        };
+        Session::new(initial, defs, config).unwrap()
+    });
+
+    let delay = 3;
+    let mut exchanged = 0;
+    for frame in 0..frames {
+        if frame >= delay {
+            sessions[0].add_remote_input(frame - delay, input(1, frame - delay))?;
+            sessions[1].add_remote_input(frame - delay, input(0, frame - delay))?;
+        }
+        let mut outgoing = [None; 2];
+        for (player, session) in sessions.iter_mut().enumerate() {
+            session.add_local_input(input(player, frame));
+            outgoing[player] = session.advance_frame()?.checksum;
+        }
+        for player in 0..2 {
+            if let Some(sent) = outgoing[player] {
+                sessions[1 - player].add_remote_checksum(sent)?;
+                exchanged += 1;
+            }
+        }
+    }
+    Ok(exchanged)
+}
+
+#[test]
+fn matching_peers_exchange_checksums() {
+    let exchanged = run_pair([5, 5], 300).unwrap();
+    assert!(exchanged >= 2 * 28);
+}
+
+#[test]
+fn diverged_peers_report_desync() {
+    match run_pair([5, 6], 300) {
+        Err(SessionError::DesyncDetected {
+            frame,
+            local,
+            remote,
+        }) => {
+            assert_eq!(frame, 0);
+            assert_ne!(local, remote);
+        }
+        other => panic!("expected desync, got {:?}", other),
+    }
+}
+
+#[test]
+fn checksum_covers_rng_state() {
+    let a = GameState::with_seed(&ROSTER[0], &ROSTER[1], 1);
+    let b = GameState::with_seed(&ROSTER[0], &ROSTER[1], 2);
+    assert_eq!(a.players, b.players);
+    assert_ne!(checksum(&a), checksum(&b));
+}
@@ -0,0 +1,135 @@
+use game_core::character::{MOVE_SPECIAL, ROSTER};
+use game_core::collision::{resolve_clash, sweep, Aabb, Clash};
+use game_core::{
+    step, CharacterDef, Fx32, GameState, Input, MoveDef, MoveMeter, ProjectileDef, Rect,
+};
+
+fn aabb(min_x: i32, max_x: i32, min_y: i32, max_y: i32) -> Aabb {
+    Aabb {
+        min_x: Fx32::from_int(min_x),
+        max_x: Fx32::from_int(max_x),
+        min_y: Fx32::from_int(min_y),
+        max_y: Fx32::from_int(max_y),
+    }
+}
+
+#[test]
+fn sweep_catches_tunneling() {
+    let bullet = aabb(0, 4, 0, 4);
+    let wall = aabb(50, 52, -10, 10);
+    assert!(!bullet.overlaps(&wall));
+    assert!(!bullet
+        .translated(Fx32::from_int(100), Fx32::ZERO)
+        .overlaps(&wall));
+    assert!(sweep(&bullet, Fx32::from_int(100), Fx32::ZERO, &wall));
+    assert!(!sweep(&bullet, Fx32::from_int(40), Fx32::ZERO, &wall));
+    assert!(!sweep(&bullet, Fx32::from_int(-100), Fx32::ZERO, &wall));
+}
+
+#[test]
+fn diagonal_sweep_misses_corner() {
+    let moving = aabb(0, 2, 0, 2);
+    let target = aabb(10, 12, 0, 2);
+    // Moving up and right passes above the target's near corner.
+    assert!(!sweep(
+        &moving,
+        Fx32::from_int(20),
+        Fx32::from_int(20),
+        &target
+    ));
+    assert!(sweep(
+        &moving,
+        Fx32::from_int(20),
+        Fx32::from_int(2),
+        &target
+    ));
+}
+
+#[test]
+fn priority_resolves_clashes() {
+    let light = ROSTER[0].moves[0];
+    let heavy = MoveDef {
+        priority: 2,
+        ..ROSTER[0].moves[1]
+    };
+    assert_eq!(resolve_clash(&light, &light), Clash::Trade);
+    assert_eq!(resolve_clash(&heavy, &light), Clash::Wins(0));
+    assert_eq!(resolve_clash(&light, &heavy), Clash::Wins(1));
+}
+
+const FIREBALL: [MoveDef; 1] = [MoveDef {
+    id: MOVE_SPECIAL,
+    startup: 10,
+    active: 2,
+    recovery: 20,
+    damage: 60,
+    chip_damage: 6,
+    hitstun: 18,
+    blockstun: 12,
+    pushback: Fx32::from_int(3),
+    launch: Fx32::ZERO,
+    juggle_cost: 1,
+    hitbox: Rect {
+        offset_x: Fx32::ZERO,
+        offset_y: Fx32::ZERO,
+        width: Fx32::ZERO,
+        height: Fx32::ZERO,
+    },
+    priority: 0,
+    hurtbox: None,
+    projectile: Some(ProjectileDef {
+        speed: Fx32::from_int(40),
+        lifetime: 60,
+        hitbox: Rect {
+            offset_x: Fx32::from_int(40),
+            offset_y: Fx32::from_int(70),
+            width: Fx32::from_int(8),
+            height: Fx32::from_int(20),
+        },
+    }),
+    meter: MoveMeter::NONE,
+}];
+
+#[test]
+fn projectile_travels_and_hits() {
+    let zoner = CharacterDef {
+        moves: &FIREBALL,
+        ..ROSTER[0]
+    };
+    let defs = [&zoner, &ROSTER[1]];
+    let mut state = GameState::new(defs[0], defs[1]);
+
+    state = step(&state, [Input::SPECIAL, Input::NONE], defs);
+    let mut spawned_at = None;
+    for frame in 1..40 {
+        state = step(&state, [Input::NONE; 2], defs);
+        if spawned_at.is_none() && state.projectiles[0].is_some() {
+            spawned_at = Some(frame);
+        }
+        if state.players[1].health < ROSTER[1].max_health {
+            break;
+        }
+    }
+
+    assert_eq!(spawned_at, Some(10));
+    assert_eq!(state.players[1].health, ROSTER[1].max_health - 60);
+    assert!(state.players[1].hitstun > 0);
+    assert!(state.projectiles[0].is_none());
+}
+
+#[test]
+fn projectiles_cancel_out() {
+    let zoner = CharacterDef {
+        moves: &FIREBALL,
+        ..ROSTER[0]
+    };
+    let defs = [&zoner, &zoner];
+    let mut state = GameState::new(defs[0], defs[1]);
+    state = step(&state, [Input::SPECIAL; 2], defs);
+    for _ in 0..40 {
+        state = step(&state, [Input::NONE; 2], defs);
+    }
+    assert_eq!(state.players[0].health, ROSTER[0].max_health);
+    assert_eq!(state.players[1].health, ROSTER[0].max_health);
+    assert_eq!(state.projectiles, [None, None]);
+}
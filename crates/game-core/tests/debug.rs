@@ -0,0 +1,159 @@
+use game_core::character::ROSTER;
+use game_core::debug::{
+    step_with_report, ComboTracker, FrameReport, HitboxOverlap, MoveFrame, MovePhase, OverlapResult,
+};
+use game_core::{step, Fx32, GameState, Input};
+
+const DEFS: [&game_core::CharacterDef; 2] = [&ROSTER[0], &ROSTER[0]];
+
+/// Ronin mirror with the players close enough for lights to connect.
+fn close() -> GameState {
+    let mut state = GameState::new(DEFS[0], DEFS[1]);
+    state.players[0].pos_x = Fx32::from_int(-40);
+    state.players[1].pos_x = Fx32::from_int(40);
+    state
+}
+
+fn run(state: &mut GameState, inputs: [Input; 2]) -> FrameReport {
+    let (next, report) = step_with_report(state, inputs, DEFS);
+    *state = next;
+    report
+}
+
+#[test]
+fn reported_state_matches_step() {
+    let mut state = close();
+    for frame in 0..300u32 {
+        let inputs = [
+            [Input::RIGHT, Input::ATTACK, Input::SPECIAL, Input::NONE][(frame / 7 % 4) as usize],
+            [Input::DEFEND, Input::LEFT, Input::ATTACK][(frame / 11 % 3) as usize],
+        ];
+        let expected = step(&state, inputs, DEFS);
+        run(&mut state, inputs);
+        assert_eq!(state, expected);
+    }
+}
+
+#[test]
+fn reports_move_phases() {
+    let mut state = GameState::new(DEFS[0], DEFS[1]);
+    let light = ROSTER[0].find_move(0).unwrap();
+    let mut phases = Vec::new();
+    for frame in 0..light.total_frames() + 1 {
+        let input = if frame == 0 {
+            Input::ATTACK
+        } else {
+            Input::NONE
+        };
+        phases.push(run(&mut state, [input, Input::NONE]).players[0].move_frame);
+    }
+    assert_eq!(
+        phases[0],
+        Some(MoveFrame {
+            move_id: 0,
+            frame: 1,
+            phase: MovePhase::Startup,
+            phase_remaining: 3,
+        })
+    );
+    assert_eq!(phases[4].unwrap().phase, MovePhase::Active);
+    assert_eq!(phases[4].unwrap().phase_remaining, 2);
+    assert_eq!(phases[7].unwrap().phase, MovePhase::Recovery);
+    assert_eq!(phases[14].unwrap().frame, 15);
+    assert_eq!(phases[14].unwrap().phase_remaining, 0);
+    assert_eq!(phases[15], None);
+}
+
+#[test]
+fn advantage_predicts_who_acts_first() {
+    let mut state = close();
+    let light = ROSTER[0].find_move(0).unwrap();
+    let mut reports = Vec::new();
+    for _ in 0..=light.startup {
+        reports.push(run(&mut state, [Input::ATTACK, Input::NONE]));
+    }
+    let hit = reports.last().unwrap();
+    assert_eq!(
+        hit.overlaps,
+        [HitboxOverlap {
+            attacker: 0,
+            move_id: 0,
+            projectile: false,
+            result: OverlapResult::Hit {
+                base_damage: 30,
+                damage: 30,
+                combo_hit: 1,
+            },
+        }]
+    );
+    let attacker_busy = hit.players[0].busy_frames.unwrap();
+    let defender_busy = hit.players[1].busy_frames.unwrap();
+    assert_eq!(defender_busy, light.hitstun - 1);
+    assert_eq!(hit.players[0].advantage, Some(3));
+    assert_eq!(hit.players[1].advantage, Some(-3));
+
+    // Both mash from here; each starts a move exactly when its busy frames
+    // run out.
+    let mut started = [None; 2];
+    for after in 1..=20u16 {
+        let report = run(&mut state, [Input::ATTACK, Input::ATTACK]);
+        for (player, started) in started.iter_mut().enumerate() {
+            if started.is_none()
+                && report.players[player]
+                    .move_frame
+                    .map_or(false, |m| m.frame == 1)
+            {
+                *started = Some(after - 1);
+            }
+        }
+    }
+    assert_eq!(started, [Some(attacker_busy), Some(defender_busy)]);
+}
+
+#[test]
+fn reports_blocks() {
+    let mut state = close();
+    let mut report = None;
+    for _ in 0..5 {
+        report = Some(run(&mut state, [Input::SPECIAL, Input::DEFEND]));
+    }
+    for _ in 5..13 {
+        report = Some(run(&mut state, [Input::NONE, Input::DEFEND]));
+    }
+    let report = report.unwrap();
+    assert_eq!(
+        report.overlaps[0].result,
+        OverlapResult::Blocked { chip_damage: 10 }
+    );
+    assert_eq!(report.players[1].blockstun, 14);
+}
+
+#[test]
+fn combo_tracker_totals_each_combo() {
+    let hit = |frame, damage, combo_hit| FrameReport {
+        frame,
+        frozen: false,
+        players: [step_with_report(&close(), [Input::NONE; 2], DEFS).1.players[0]; 2],
+        overlaps: vec![HitboxOverlap {
+            attacker: 0,
+            move_id: 0,
+            projectile: false,
+            result: OverlapResult::Hit {
+                base_damage: 30,
+                damage,
+                combo_hit,
+            },
+        }],
+    };
+    let mut tracker = ComboTracker::default();
+    tracker.record(&hit(10, 30, 1));
+    tracker.record(&hit(20, 27, 2));
+    assert_eq!(tracker.combo(1).hits.len(), 2);
+    assert_eq!(tracker.combo(1).total_damage, 57);
+    assert!(tracker.combo(0).hits.is_empty());
+
+    tracker.record(&hit(90, 30, 1));
+    assert_eq!(tracker.combo(1).hits.len(), 1);
+    assert_eq!(tracker.combo(1).hits[0].frame, 90);
+    assert_eq!(tracker.combo(1).total_damage, 30);
+}
@@ -0,0 +1,98 @@
+use game_core::character::ROSTER;
+use game_core::difficulty::{
+    link_window, score, CANCEL_POINTS, CHARGE_PARTITION_POINTS, CHARGE_POINTS, LENIENT_LINK_FRAMES,
+};
+use game_core::{ComboMove, Difficulty, Motion};
+
+fn button(startup: u8, active: u8, recovery: u8, hitstun: u16) -> ComboMove {
+    ComboMove {
+        startup,
+        active,
+        recovery,
+        hitstun,
+        motion: None,
+    }
+}
+
+#[test]
+fn link_window_counts_frames_left_in_hitstun() {
+    // Hitstun 20 minus 3 active and 10 recovery leaves 7 frames of
+    // advantage; a 4-frame startup can start in the first 4 of them.
+    let jab = button(4, 3, 10, 20);
+    assert_eq!(link_window(&jab, &jab), 4);
+    assert_eq!(link_window(&button(4, 3, 10, 16), &jab), 0);
+}
+
+#[test]
+fn tight_links_score_more_than_lenient_ones() {
+    let lenient = score([button(4, 3, 10, 30), button(4, 3, 10, 20)]);
+    assert_eq!(lenient, Difficulty::default());
+
+    let one_frame = score([button(4, 3, 10, 17), button(4, 3, 10, 20)]);
+    assert_eq!(one_frame.links, LENIENT_LINK_FRAMES - 1);
+    assert_eq!(one_frame.cancels, 0);
+
+    let two_frame = score([button(4, 3, 10, 18), button(4, 3, 10, 20)]);
+    assert!(two_frame.total() < one_frame.total());
+}
+
+#[test]
+fn moves_without_a_link_window_count_as_cancels() {
+    let heavy = button(10, 4, 20, 18);
+    let difficulty = score([heavy, heavy, heavy]);
+    assert_eq!(difficulty.cancels, 2 * CANCEL_POINTS);
+    assert_eq!(difficulty.links, 0);
+}
+
+#[test]
+fn motions_and_charge_add_their_points() {
+    let launcher = button(4, 3, 10, 40);
+    let special = |motion| ComboMove {
+        motion: Some(motion),
+        ..launcher
+    };
+
+    let qcf = score([special(Motion::QuarterCircleForward)]);
+    let dp = score([special(Motion::DragonPunch)]);
+    let circle = score([special(Motion::FullCircle)]);
+    assert!(qcf.motions < dp.motions && dp.motions < circle.motions);
+
+    let opener = score([special(Motion::ChargeBackForward)]);
+    assert_eq!(opener.charge, CHARGE_POINTS);
+    assert_eq!(opener.motions, 0);
+
+    let partitioned = score([launcher, special(Motion::ChargeBackForward)]);
+    assert_eq!(partitioned.charge, CHARGE_POINTS + CHARGE_PARTITION_POINTS);
+}
+
+#[test]
+fn roster_moves_score_from_their_definitions() {
+    let def = &ROSTER[0];
+    let light = def.moves[0];
+    let moves = [
+        ComboMove::from_def(&light, None),
+        ComboMove::from_def(&light, None),
+    ];
+    let window = link_window(&moves[0], &moves[1]);
+    let expected = if window > 0 {
+        Difficulty {
+            links: LENIENT_LINK_FRAMES.saturating_sub(window as u16),
+            ..Difficulty::default()
+        }
+    } else {
+        Difficulty {
+            cancels: CANCEL_POINTS,
+            ..Difficulty::default()
+        }
+    };
+    assert_eq!(score(moves), expected);
+}
+
+#[test]
+fn motion_codes_round_trip() {
+    assert_eq!(Motion::from_code(0), None);
+    for motion in Motion::ALL {
+        assert_eq!(Motion::from_code(motion.code()), Some(motion));
+    }
+    assert_eq!(Motion::from_code(Motion::ALL.len() as u8 + 1), None);
+}
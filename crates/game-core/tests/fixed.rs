@@ -0,0 +1,144 @@
+//! Determinism and accuracy tests for `Fx32`.
+
+use game_core::Fx32;
+
+const ONE: i64 = 1 << 16;
+
+fn fnv1a(hash: u32, data: u32) -> u32 {
+    (hash ^ data).wrapping_mul(16_777_619)
+}
+
+#[test]
+fn from_int_round_trips_every_i16() {
+    for n in i16::MIN as i32..=i16::MAX as i32 {
+        let fx = Fx32::from_int(n);
+        assert_eq!(fx.floor_int(), n);
+        assert_eq!(fx.round_int(), n);
+        assert_eq!(fx.frac(), Fx32::ZERO);
+    }
+}
+
+#[test]
+fn saturating_from_int_clamps() {
+    assert_eq!(Fx32::saturating_from_int(40_000), Fx32::MAX);
+    assert_eq!(Fx32::saturating_from_int(-40_000), Fx32::MIN);
+    assert_eq!(Fx32::saturating_from_int(-32_768), Fx32::from_int(-32_768));
+}
+
+#[test]
+fn mul_and_div_match_wide_reference() {
+    let samples: Vec<i32> = (-2048..=2048)
+        .map(|i| i * 977)
+        .chain([i32::MIN, i32::MAX, 1, -1, ONE as i32, -(ONE as i32)])
+        .collect();
+
+    for &a in &samples {
+        for &b in &samples {
+            let (fa, fb) = (Fx32::from_raw(a), Fx32::from_raw(b));
+            let wide_mul = (a as i64 * b as i64) >> 16;
+
+            assert_eq!((fa * fb).raw(), wide_mul as i32);
+            assert_eq!(
+                fa.saturating_mul(fb).raw() as i64,
+                wide_mul.clamp(i32::MIN as i64, i32::MAX as i64)
+            );
+            assert_eq!(
+                fa.checked_mul(fb).map(Fx32::raw).map(i64::from),
+                i32::try_from(wide_mul).ok().map(i64::from)
+            );
+
+            if b != 0 {
+                let wide_div = ((a as i64) << 16) / b as i64;
+                assert_eq!((fa / fb).raw(), wide_div as i32);
+                assert_eq!(
+                    fa.checked_div(fb).map(Fx32::raw).map(i64::from),
+                    i32::try_from(wide_div).ok().map(i64::from)
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn division_by_zero_is_explicit() {
+    assert_eq!(Fx32::ONE.checked_div(Fx32::ZERO), None);
+    assert_eq!(Fx32::ONE.saturating_div(Fx32::ZERO), Fx32::MAX);
+    assert_eq!((-Fx32::ONE).saturating_div(Fx32::ZERO), Fx32::MIN);
+}
+
+#[test]
+fn add_and_sub_wrap_and_saturate_at_bounds() {
+    assert_eq!(Fx32::MAX + Fx32::EPSILON, Fx32::MIN);
+    assert_eq!(Fx32::MIN - Fx32::EPSILON, Fx32::MAX);
+    assert_eq!(Fx32::MAX.saturating_add(Fx32::ONE), Fx32::MAX);
+    assert_eq!(Fx32::MIN.saturating_sub(Fx32::ONE), Fx32::MIN);
+    assert_eq!(Fx32::MAX.checked_add(Fx32::EPSILON), None);
+    assert_eq!(Fx32::MIN.checked_sub(Fx32::EPSILON), None);
+}
+
+#[test]
+fn sqrt_is_exact_floor_for_every_small_raw_value() {
+    let strided = (1u32 << 20..=i32::MAX as u32).step_by(4093);
+    for raw in (0u32..1 << 20).chain(strided).chain([i32::MAX as u32]) {
+        let root = Fx32::from_raw(raw as i32).sqrt().raw() as u128;
+        let target = (raw as u128) << 16;
+        assert!(root * root <= target, "sqrt({raw}) too large");
+        assert!((root + 1) * (root + 1) > target, "sqrt({raw}) too small");
+    }
+    assert_eq!(Fx32::from_int(-4).sqrt(), Fx32::ZERO);
+    assert_eq!(Fx32::from_int(9).sqrt(), Fx32::from_int(3));
+}
+
+#[test]
+fn sin_cos_track_reference_over_two_turns() {
+    let limit = 2 * Fx32::TWO_PI.raw();
+    let mut raw = -limit;
+    while raw <= limit {
+        let angle = Fx32::from_raw(raw);
+        let radians = raw as f64 / ONE as f64;
+        let sin_err = (angle.sin().raw() as f64 - radians.sin() * ONE as f64).abs();
+        let cos_err = (angle.cos().raw() as f64 - radians.cos() * ONE as f64).abs();
+        assert!(sin_err <= 4.0, "sin({raw}) off by {sin_err}");
+        assert!(cos_err <= 4.0, "cos({raw}) off by {cos_err}");
+        raw += 7;
+    }
+}
+
+#[test]
+fn sin_hits_exact_values_at_quadrants() {
+    assert_eq!(Fx32::ZERO.sin(), Fx32::ZERO);
+    assert_eq!(Fx32::ZERO.cos(), Fx32::ONE);
+    assert!((Fx32::HALF_PI.sin() - Fx32::ONE).abs() <= Fx32::EPSILON);
+    assert!((Fx32::PI.cos() + Fx32::ONE).abs() <= Fx32::EPSILON);
+    assert!((-Fx32::HALF_PI).sin() <= -Fx32::ONE + Fx32::EPSILON);
+}
+
+#[test]
+fn display_does_not_round_through_floats() {
+    assert_eq!(Fx32::from_ratio(3, 2).to_string(), "1.50000");
+    assert_eq!(Fx32::from_ratio(-1, 4).to_string(), "-0.25000");
+    assert_eq!(Fx32::EPSILON.to_string(), "0.00001");
+}
+
+/// Pins the bit-exact output of every transcendental over a fixed sweep. Any
+/// platform or compiler difference in the fixed-point path changes this hash.
+#[test]
+fn golden_sweep_hash_is_stable() {
+    let mut hash = 2_166_136_261u32;
+    let mut raw = i32::MIN;
+    loop {
+        let x = Fx32::from_raw(raw);
+        hash = fnv1a(hash, x.sin().raw() as u32);
+        hash = fnv1a(hash, x.cos().raw() as u32);
+        hash = fnv1a(hash, x.sqrt().raw() as u32);
+        hash = fnv1a(hash, (x * Fx32::PI).raw() as u32);
+        hash = fnv1a(hash, x.saturating_div(Fx32::from_ratio(7, 3)).raw() as u32);
+        match raw.checked_add(65_521) {
+            Some(next) => raw = next,
+            None => break,
+        }
+    }
+    assert_eq!(hash, GOLDEN_SWEEP_HASH);
+}
+
+const GOLDEN_SWEEP_HASH: u32 = 0xa930d43c;
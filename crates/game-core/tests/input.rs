@@ -0,0 +1,51 @@
+use game_core::input::{decode_stream, encode_stream, InputStreamError};
+use game_core::Input;
+
+#[test]
+fn reserved_bits_are_rejected() {
+    assert_eq!(Input::from_bits(0x03ff), Some(Input::ALL));
+    assert_eq!(Input::from_bits(0x0400), None);
+    assert_eq!(Input::from_bits_truncate(0xffff), Input::ALL);
+}
+
+#[test]
+fn socd_cleaning() {
+    let both = Input::LEFT | Input::RIGHT | Input::ATTACK;
+    assert_eq!(both.socd_cleaned(), Input::ATTACK);
+    assert_eq!((Input::UP | Input::DOWN).socd_cleaned(), Input::UP);
+    assert_eq!(
+        (Input::LEFT | Input::DOWN).socd_cleaned(),
+        Input::LEFT | Input::DOWN
+    );
+    assert_eq!(Input::LEFT.horizontal(), -1);
+    assert_eq!(Input::RIGHT.horizontal(), 1);
+    assert_eq!(both.socd_cleaned().horizontal(), 0);
+}
+
+#[test]
+fn stream_round_trip() {
+    let mut inputs = vec![Input::NONE; 300];
+    inputs.extend([Input::RIGHT; 20]);
+    inputs.push(Input::RIGHT | Input::ATTACK);
+    inputs.extend([Input::DOWN | Input::SPECIAL; 3]);
+
+    let encoded = encode_stream(&inputs);
+    // Four runs: a two-byte varint for 300 plus three one-byte ones.
+    assert_eq!(encoded.len(), 2 + 2 + 3 * 3);
+    assert_eq!(decode_stream(&encoded).unwrap(), inputs);
+    assert!(decode_stream(&encode_stream(&[])).unwrap().is_empty());
+}
+
+#[test]
+fn malformed_streams() {
+    assert_eq!(decode_stream(&[1, 0]), Err(InputStreamError::Truncated));
+    assert_eq!(decode_stream(&[0, 0, 0]), Err(InputStreamError::InvalidRun));
+    assert_eq!(
+        decode_stream(&[1, 0x00, 0x04]),
+        Err(InputStreamError::InvalidBits(0x0400))
+    );
+    assert_eq!(
+        decode_stream(&[0xff, 0xff, 0xff, 0xff, 0x7f, 0, 0]),
+        Err(InputStreamError::InvalidRun)
+    );
+}
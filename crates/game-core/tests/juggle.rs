@@ -0,0 +1,79 @@
+use game_core::character::ROSTER;
+use game_core::juggle::{juggle_gravity, JUGGLE_LIMIT};
+use game_core::{step, CharacterDef, Fx32, GameState, Input, MoveDef};
+
+fn launcher_ronin(moves: &mut Vec<MoveDef>) -> CharacterDef<'_> {
+    moves.extend_from_slice(ROSTER[0].moves);
+    moves[0].launch = Fx32::from_int(12);
+    CharacterDef { moves, ..ROSTER[0] }
+}
+
+/// Walks player one into range and lands a light attack.
+fn land_light(defs: [&CharacterDef; 2], mut state: GameState) -> GameState {
+    for _ in 0..55 {
+        state = step(&state, [Input::RIGHT, Input::NONE], defs);
+    }
+    state = step(&state, [Input::ATTACK, Input::NONE], defs);
+    while !state.players[0].move_connected {
+        state = step(&state, [Input::NONE; 2], defs);
+    }
+    state
+}
+
+#[test]
+fn gravity_grows_with_juggle_points() {
+    assert_eq!(juggle_gravity(Fx32::ONE, 0), Fx32::ONE);
+    assert_eq!(juggle_gravity(Fx32::from_int(2), 5), Fx32::from_int(3));
+}
+
+#[test]
+fn launched_defender_is_stunned_until_landing() {
+    let mut moves = Vec::new();
+    let ronin = launcher_ronin(&mut moves);
+    let defs = [&ronin, &ROSTER[0]];
+    let mut state = land_light(defs, GameState::new(defs[0], defs[1]));
+
+    let defender = &state.players[1];
+    assert!(defender.launched);
+    assert!(!defender.grounded);
+    assert_eq!(defender.vel_y, Fx32::from_int(12));
+    assert_eq!(defender.juggle, ROSTER[0].moves[0].juggle_cost);
+
+    let mut airborne = 0;
+    while !state.players[1].grounded {
+        assert!(state.players[1].is_stunned());
+        state = step(&state, [Input::NONE, Input::DEFEND], defs);
+        airborne += 1;
+        assert!(airborne < 60);
+    }
+    let defender = &state.players[1];
+    assert!(!defender.launched);
+    assert_eq!(defender.juggle, 0);
+    assert!(!defender.is_stunned());
+}
+
+#[test]
+fn hits_past_the_juggle_limit_pass_through() {
+    let mut moves = Vec::new();
+    let ronin = launcher_ronin(&mut moves);
+    let defs = [&ronin, &ROSTER[0]];
+    let mut state = GameState::new(defs[0], defs[1]);
+    state.players[1].launched = true;
+    state.players[1].grounded = false;
+    state.players[1].pos_y = Fx32::from_int(40);
+    state.players[1].juggle = JUGGLE_LIMIT;
+
+    for _ in 0..55 {
+        state = step(&state, [Input::RIGHT, Input::NONE], defs);
+        state.players[1].pos_y = Fx32::from_int(40);
+        state.players[1].vel_y = Fx32::ZERO;
+    }
+    state = step(&state, [Input::ATTACK, Input::NONE], defs);
+    for _ in 0..ROSTER[0].moves[0].total_frames() {
+        state.players[1].pos_y = Fx32::from_int(40);
+        state.players[1].vel_y = Fx32::ZERO;
+        state = step(&state, [Input::NONE; 2], defs);
+    }
+    assert_eq!(state.players[1].health, ROSTER[0].max_health);
+    assert!(!state.players[0].move_connected);
+}
@@ -0,0 +1,137 @@
+use game_core::character::ROSTER;
+use game_core::{
+    GameState, Input, LockstepAdvance, LockstepConfig, LockstepError, LockstepSession, Session,
+    SessionConfig,
+};
+
+/// Scripted inputs, neutral from frame 190 so the end of the match needs
+/// no prediction.
+fn input(player: usize, frame: u32) -> Input {
+    if frame >= 190 {
+        return Input::NONE;
+    }
+    match (frame * 5 + player as u32 * 11) % 36 {
+        0..=9 => Input::RIGHT,
+        10..=12 => Input::ATTACK,
+        13..=20 => Input::LEFT,
+        21 => Input::SPECIAL,
+        _ => Input::NONE,
+    }
+}
+
+fn pair(config: LockstepConfig) -> [LockstepSession<'static>; 2] {
+    let defs = [&ROSTER[0], &ROSTER[1]];
+    [0, 1].map(|player| {
+        let config = LockstepConfig {
+            local_player: player,
+            ..config
+        };
+        LockstepSession::new(GameState::new(defs[0], defs[1]), defs, config).unwrap()
+    })
+}
+
+#[test]
+fn peers_advance_in_step_and_match_rollback() {
+    let mut peers = pair(LockstepConfig::default());
+    let mut now = 0;
+    for _ in 0..200 {
+        for player in 0..2 {
+            let frame = peers[player].current_frame();
+            if let Some(target) = peers[player].add_local_input(input(player, frame)) {
+                let local = input(player, frame);
+                peers[1 - player].add_remote_input(target, local).unwrap();
+            }
+        }
+        for peer in peers.iter_mut() {
+            assert!(matches!(
+                peer.advance_frame(now).unwrap(),
+                LockstepAdvance::Advanced { .. }
+            ));
+        }
+        now += 16;
+    }
+    assert_eq!(peers[0].state(), peers[1].state());
+
+    // A rollback session fed the remote inputs three frames late re-simulates
+    // its way to the same state.
+    let defs = [&ROSTER[0], &ROSTER[1]];
+    let config = SessionConfig {
+        input_delay: 1,
+        ..SessionConfig::default()
+    };
+    let mut rollback = Session::new(GameState::new(defs[0], defs[1]), defs, config).unwrap();
+    for frame in 0..200 {
+        if frame >= 3 {
+            let late = frame - 3;
+            let remote = if late == 0 {
+                Input::NONE
+            } else {
+                input(1, late - 1)
+            };
+            rollback.add_remote_input(late, remote).unwrap();
+        }
+        rollback.add_local_input(input(0, frame));
+        rollback.advance_frame().unwrap();
+    }
+    assert!(rollback.stats().rollbacks > 0);
+    assert_eq!(rollback.state(), peers[0].state());
+}
+
+#[test]
+fn waits_for_remote_input_then_disconnects() {
+    let mut peers = pair(LockstepConfig {
+        input_delay: 0,
+        disconnect_timeout_ms: 100,
+        ..LockstepConfig::default()
+    });
+    let peer = &mut peers[0];
+    assert_eq!(peer.add_local_input(Input::RIGHT), Some(0));
+    assert_eq!(peer.add_local_input(Input::ATTACK), None);
+    assert_eq!(
+        peer.advance_frame(1000),
+        Ok(LockstepAdvance::Waiting {
+            frame: 0,
+            waited_ms: 0
+        })
+    );
+    assert_eq!(
+        peer.advance_frame(1100),
+        Ok(LockstepAdvance::Waiting {
+            frame: 0,
+            waited_ms: 100
+        })
+    );
+    assert_eq!(
+        peer.advance_frame(1101),
+        Err(LockstepError::Disconnected {
+            frame: 0,
+            waited_ms: 101
+        })
+    );
+
+    peer.add_remote_input(0, Input::NONE).unwrap();
+    assert_eq!(
+        peer.advance_frame(1200),
+        Ok(LockstepAdvance::Advanced { frame: 0 })
+    );
+}
+
+#[test]
+fn requires_local_input_and_in_order_remote_input() {
+    let mut peers = pair(LockstepConfig {
+        input_delay: 0,
+        ..LockstepConfig::default()
+    });
+    let peer = &mut peers[0];
+    assert_eq!(
+        peer.advance_frame(0),
+        Err(LockstepError::MissingLocalInput { frame: 0 })
+    );
+    assert_eq!(
+        peer.add_remote_input(3, Input::NONE),
+        Err(LockstepError::InputGap {
+            expected: 0,
+            received: 3
+        })
+    );
+}
@@ -0,0 +1,99 @@
+use game_core::character::{MOVE_EX, MOVE_LIGHT, ROSTER};
+use game_core::meter::{carry_over, MAX_METER};
+use game_core::{step, Carryover, CharacterDef, GameState, Input, MoveDef, MoveMeter};
+
+#[test]
+fn whiffs_and_hits_build_meter() {
+    let defs = [&ROSTER[0], &ROSTER[0]];
+    let light = ROSTER[0].moves[0];
+    let mut state = GameState::new(defs[0], defs[1]);
+
+    state = step(&state, [Input::ATTACK, Input::NONE], defs);
+    for _ in 0..light.total_frames() {
+        state = step(&state, [Input::NONE; 2], defs);
+    }
+    assert_eq!(state.players[0].meter, light.meter.on_whiff);
+
+    for _ in 0..55 {
+        state = step(&state, [Input::RIGHT, Input::NONE], defs);
+    }
+    state = step(&state, [Input::ATTACK, Input::NONE], defs);
+    for _ in 0..light.total_frames() {
+        state = step(&state, [Input::NONE; 2], defs);
+    }
+    assert_eq!(
+        state.players[0].meter,
+        light.meter.on_whiff + light.meter.on_hit
+    );
+}
+
+#[test]
+fn ex_moves_cost_meter_and_freeze() {
+    let mut moves = ROSTER[0].moves.to_vec();
+    moves.push(MoveDef {
+        id: MOVE_EX,
+        meter: MoveMeter {
+            cost: 50,
+            super_freeze: 10,
+            ..MoveMeter::NONE
+        },
+        ..ROSTER[0].moves[1]
+    });
+    let ronin = CharacterDef {
+        moves: &moves,
+        ..ROSTER[0]
+    };
+    let defs = [&ronin, &ROSTER[0]];
+    let both = Input::ATTACK | Input::SPECIAL;
+
+    // Without meter the buttons fall back to the light attack.
+    let state = GameState::new(defs[0], defs[1]);
+    let next = step(&state, [both, Input::NONE], defs);
+    assert_eq!(next.players[0].current_move, Some(MOVE_LIGHT));
+    assert_eq!(next.super_freeze, 0);
+
+    let mut state = GameState::new(defs[0], defs[1]);
+    state.players[0].meter = 60;
+    state = step(&state, [both, Input::RIGHT], defs);
+    assert_eq!(state.players[0].current_move, Some(MOVE_EX));
+    assert_eq!(state.players[0].meter, 10);
+    assert_eq!(state.super_freeze, 10);
+
+    let frozen = state;
+    for _ in 0..10 {
+        state = step(&state, [Input::NONE, Input::RIGHT], defs);
+    }
+    assert_eq!(state.frame, frozen.frame + 10);
+    assert_eq!(state.players, frozen.players);
+    assert_eq!(state.super_freeze, 0);
+
+    state = step(&state, [Input::NONE, Input::RIGHT], defs);
+    assert_eq!(
+        state.players[0].move_frame,
+        frozen.players[0].move_frame + 1
+    );
+}
+
+#[test]
+fn meter_is_capped() {
+    assert_eq!(game_core::meter::gain(MAX_METER - 3, 10), MAX_METER);
+    assert_eq!(game_core::meter::gain(10, u16::MAX), MAX_METER);
+}
+
+#[test]
+fn carryover_between_rounds() {
+    let defs = [&ROSTER[0], &ROSTER[1]];
+    let mut finished = GameState::new(defs[0], defs[1]);
+    finished.players[0].meter = 75;
+    finished.players[1].meter = 30;
+
+    for (rule, expected) in [
+        (Carryover::Reset, [0, 0]),
+        (Carryover::Keep, [75, 30]),
+        (Carryover::Percent(50), [37, 15]),
+    ] {
+        let mut next = GameState::new(defs[0], defs[1]);
+        carry_over(&finished, &mut next, rule);
+        assert_eq!([next.players[0].meter, next.players[1].meter], expected);
+    }
+}
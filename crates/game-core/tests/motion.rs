@@ -0,0 +1,79 @@
+use game_core::motion::numpad;
+use game_core::{Facing, Input, InputBuffer, Motion, MotionConfig};
+
+const DOWN_FORWARD: Input = Input::from_bits_truncate(0b1010);
+const DOWN_BACK: Input = Input::from_bits_truncate(0b0110);
+
+fn buffer(inputs: &[Input]) -> InputBuffer {
+    let mut buffer = InputBuffer::default();
+    for &input in inputs {
+        buffer.push(input);
+    }
+    buffer
+}
+
+#[test]
+fn numpad_is_relative_to_facing() {
+    assert_eq!(numpad(Input::NONE, Facing::Right), 5);
+    assert_eq!(numpad(Input::RIGHT, Facing::Right), 6);
+    assert_eq!(numpad(Input::RIGHT, Facing::Left), 4);
+    assert_eq!(numpad(DOWN_FORWARD, Facing::Right), 3);
+    assert_eq!(numpad(DOWN_BACK, Facing::Right), 1);
+    assert_eq!(numpad(Input::UP | Input::LEFT, Facing::Left), 9);
+}
+
+#[test]
+fn quarter_circles_and_dragon_punch() {
+    let config = MotionConfig::default();
+    let qcf = buffer(&[Input::DOWN, DOWN_FORWARD, Input::RIGHT]);
+    assert!(Motion::QuarterCircleForward.detect(&qcf, Facing::Right, &config));
+    assert!(!Motion::QuarterCircleBack.detect(&qcf, Facing::Right, &config));
+    // Mirrored when facing left.
+    assert!(Motion::QuarterCircleBack.detect(&qcf, Facing::Left, &config));
+
+    let dp = buffer(&[Input::RIGHT, DOWN_FORWARD, Input::DOWN, DOWN_FORWARD]);
+    assert!(Motion::DragonPunch.detect(&dp, Facing::Right, &config));
+}
+
+#[test]
+fn motions_expire_outside_the_window() {
+    let config = MotionConfig::default();
+    let mut inputs = vec![Input::DOWN, DOWN_FORWARD];
+    inputs.extend([Input::NONE; 12]);
+    inputs.push(Input::RIGHT);
+    let late = buffer(&inputs);
+    assert!(!Motion::QuarterCircleForward.detect(&late, Facing::Right, &config));
+}
+
+#[test]
+fn charge_requires_a_full_hold() {
+    let config = MotionConfig::default();
+    let mut inputs = vec![Input::LEFT; config.charge_frames as usize];
+    inputs.extend([Input::NONE; 4]);
+    inputs.push(Input::RIGHT);
+    assert!(Motion::ChargeBackForward.detect(&buffer(&inputs), Facing::Right, &config));
+
+    inputs.remove(0);
+    assert!(!Motion::ChargeBackForward.detect(&buffer(&inputs), Facing::Right, &config));
+}
+
+#[test]
+fn full_circle() {
+    let config = MotionConfig::default();
+    let spd = buffer(&[Input::RIGHT, Input::DOWN, Input::LEFT, Input::UP]);
+    assert!(Motion::FullCircle.detect(&spd, Facing::Right, &config));
+    let half = buffer(&[Input::RIGHT, Input::DOWN, Input::LEFT]);
+    assert!(!Motion::FullCircle.detect(&half, Facing::Right, &config));
+}
+
+#[test]
+fn buffer_wraps() {
+    let mut buffer = InputBuffer::default();
+    for frame in 0..100u16 {
+        buffer.push(Input::from_bits_truncate(frame));
+    }
+    assert_eq!(buffer.len(), 64);
+    assert_eq!(buffer.get(0), Some(Input::from_bits_truncate(99)));
+    assert_eq!(buffer.get(63), Some(Input::from_bits_truncate(36)));
+    assert_eq!(buffer.get(64), None);
+}
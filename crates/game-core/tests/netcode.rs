@@ -0,0 +1,171 @@
+use game_core::character::ROSTER;
+use game_core::netcode::MAX_INPUT_DELAY;
+use game_core::{GameState, Input, Session, SessionConfig, SessionError};
+
+fn session(config: SessionConfig) -> Session<'static> {
+    let defs = [&ROSTER[0], &ROSTER[1]];
+    Session::new(GameState::new(defs[0], defs[1]), defs, config).unwrap()
+}
+
+/// Advances with the remote input already confirmed as neutral.
+fn advance(session: &mut Session, input: Input) {
+    let frame = session.current_frame();
+    session.add_remote_input(frame, Input::NONE).unwrap();
+    session.add_local_input(input);
+    session.advance_frame().unwrap();
+}
+
+#[test]
+fn rejects_delay_above_maximum() {
+    let defs = [&ROSTER[0], &ROSTER[1]];
+    let config = SessionConfig {
+        input_delay: MAX_INPUT_DELAY + 1,
+        ..SessionConfig::default()
+    };
+    assert!(matches!(
+        Session::new(GameState::new(defs[0], defs[1]), defs, config),
+        Err(SessionError::InvalidConfig)
+    ));
+}
+
+#[test]
+fn delayed_inputs_land_later_frames() {
+    let mut delayed = session(SessionConfig {
+        input_delay: 2,
+        ..SessionConfig::default()
+    });
+    let mut direct = session(SessionConfig::default());
+
+    // The first two frames run without local input.
+    advance(&mut direct, Input::NONE);
+    advance(&mut direct, Input::NONE);
+    for frame in 0..30 {
+        let input = if frame % 3 == 0 {
+            Input::ATTACK
+        } else {
+            Input::RIGHT
+        };
+        advance(&mut delayed, input);
+        if frame < 28 {
+            advance(&mut direct, input);
+        }
+    }
+    assert_eq!(delayed.state(), direct.state());
+}
+
+#[test]
+fn delay_still_requires_input_once_the_buffer_runs_out() {
+    let mut session = session(SessionConfig {
+        input_delay: 1,
+        ..SessionConfig::default()
+    });
+    session.advance_frame().unwrap();
+    assert_eq!(
+        session.advance_frame(),
+        Err(SessionError::MissingLocalInput { frame: 1 })
+    );
+}
+
+#[test]
+fn adaptive_delay_follows_latency() {
+    let mut session = session(SessionConfig {
+        adaptive_delay: true,
+        ..SessionConfig::default()
+    });
+    assert_eq!(session.stats().ping_ms, None);
+
+    for _ in 0..20 {
+        session.add_ping_sample(10);
+    }
+    assert_eq!(session.stats().input_delay, 0);
+    assert_eq!(session.stats().ping_ms, Some(10));
+
+    for _ in 0..40 {
+        session.add_ping_sample(60);
+    }
+    let stats = session.stats();
+    assert_eq!(stats.input_delay, 2);
+    assert_eq!(stats.ping_ms, Some(59));
+
+    for ping in [20, 180, 20, 180, 20, 180] {
+        session.add_ping_sample(ping);
+    }
+    assert_eq!(session.stats().input_delay, MAX_INPUT_DELAY);
+}
+
+#[test]
+fn fixed_delay_ignores_latency() {
+    let mut session = session(SessionConfig {
+        input_delay: 1,
+        ..SessionConfig::default()
+    });
+    for _ in 0..10 {
+        session.add_ping_sample(200);
+    }
+    assert_eq!(session.stats().input_delay, 1);
+}
+
+#[test]
+fn growing_delay_repeats_the_last_input() {
+    let mut session = session(SessionConfig {
+        adaptive_delay: true,
+        ..SessionConfig::default()
+    });
+    session.add_local_input(Input::RIGHT);
+    session.advance_frame().unwrap();
+    for _ in 0..10 {
+        session.add_ping_sample(70);
+    }
+    assert_eq!(session.stats().input_delay, 3);
+    session.add_local_input(Input::ATTACK);
+    // Frames 1 to 3 repeat RIGHT, so three more advances need no new input.
+    for _ in 0..4 {
+        session.advance_frame().unwrap();
+    }
+    assert_eq!(
+        session.advance_frame(),
+        Err(SessionError::MissingLocalInput { frame: 5 })
+    );
+}
+
+#[test]
+fn stats_count_rollbacks() {
+    let mut session = session(SessionConfig::default());
+    for _ in 0..4 {
+        session.add_local_input(Input::NONE);
+        session.advance_frame().unwrap();
+    }
+    // Frames 0-3 were predicted as neutral; frame 1 says otherwise.
+    session.add_remote_input(0, Input::NONE).unwrap();
+    session.add_remote_input(1, Input::ATTACK).unwrap();
+    session.add_local_input(Input::NONE);
+    let advance = session.advance_frame().unwrap();
+    assert_eq!(advance.rollback_frames, 3);
+
+    let stats = session.stats();
+    assert_eq!(stats.rollbacks, 1);
+    assert_eq!(stats.rollback_frames, 3);
+    assert_eq!(stats.max_rollback, 3);
+}
+
+#[test]
+fn confirmed_state_waits_for_remote_inputs() {
+    let mut session = session(SessionConfig::default());
+    for _ in 0..3 {
+        session.add_local_input(Input::NONE);
+        session.advance_frame().unwrap();
+    }
+    assert!(session.confirmed_state(0).is_some());
+    assert_eq!(session.confirmed_state(1), None);
+
+    // The misprediction at frame 1 leaves later snapshots stale until the
+    // next advance re-simulates them.
+    session.add_remote_input(0, Input::NONE).unwrap();
+    session.add_remote_input(1, Input::ATTACK).unwrap();
+    assert!(session.confirmed_state(1).is_some());
+    assert_eq!(session.confirmed_state(2), None);
+    session.add_local_input(Input::NONE);
+    session.advance_frame().unwrap();
+    assert!(session.confirmed_state(2).is_some());
+    assert_eq!(session.confirmed_state(3), None);
+}
@@ -0,0 +1,240 @@
+use game_core::character::ROSTER;
+use game_core::{
+    state_hash, step, Channel, ChannelConfig, ChannelEvent, GameState, Input, NetworkConditions,
+    Rng, Session, SessionConfig, SessionError, SessionStats, SimNetwork, SimTransport, Transport,
+};
+
+const FRAMES: u32 = 600;
+const FRAME_MS: u64 = 16;
+const MOVES: [Input; 6] = [
+    Input::NONE,
+    Input::RIGHT,
+    Input::LEFT,
+    Input::ATTACK,
+    Input::SPECIAL,
+    Input::DEFEND,
+];
+
+/// Both players' inputs, held for a few frames at a time like a real
+/// player would. The first inputs run neutral to cover the input delay.
+fn script(seed: u64, delay: u8) -> Vec<[Input; 2]> {
+    let mut rng = Rng::new(seed);
+    let mut held = [Input::NONE; 2];
+    (0..FRAMES + 200)
+        .map(|frame| {
+            for input in &mut held {
+                if rng.below(6) == 0 {
+                    *input = MOVES[rng.below(MOVES.len() as u32) as usize];
+                }
+            }
+            if frame < delay as u32 {
+                [Input::NONE; 2]
+            } else {
+                held
+            }
+        })
+        .collect()
+}
+
+/// Hash of the state at the start of every frame, from plain simulation
+/// of the script.
+fn reference(script: &[[Input; 2]]) -> Vec<u32> {
+    let defs = [&ROSTER[0], &ROSTER[1]];
+    let mut state = GameState::new(defs[0], defs[1]);
+    let mut hashes = vec![state_hash(&state)];
+    for inputs in &script[..FRAMES as usize] {
+        state = step(&state, *inputs, defs);
+        hashes.push(state_hash(&state));
+    }
+    hashes
+}
+
+struct Peer {
+    session: Session<'static>,
+    channel: Channel<SimTransport>,
+    /// Next frame whose confirmed state has not been checked.
+    next_check: u32,
+}
+
+impl Peer {
+    fn new(player: usize, transport: SimTransport, delay: u8) -> Self {
+        let defs = [&ROSTER[0], &ROSTER[1]];
+        let config = SessionConfig {
+            local_player: player,
+            checksum_interval: 1,
+            input_delay: delay,
+            ..SessionConfig::default()
+        };
+        let session = Session::new(GameState::new(defs[0], defs[1]), defs, config).unwrap();
+        let mut channel = Channel::new(transport, ChannelConfig::default()).unwrap();
+        for frame in 0..delay as u32 {
+            channel.push_local_input(frame, Input::NONE).unwrap();
+        }
+        Peer {
+            session,
+            channel,
+            next_check: 0,
+        }
+    }
+
+    fn tick(&mut self, script: &[[Input; 2]], reference: &[u32]) {
+        for event in self.channel.poll().unwrap() {
+            match event {
+                ChannelEvent::Input { frame, input } => {
+                    self.session.add_remote_input(frame, input).unwrap()
+                }
+                ChannelEvent::Checksum(checksum) => {
+                    self.session.add_remote_checksum(checksum).unwrap()
+                }
+            }
+        }
+
+        let player = self.session.config().local_player;
+        let delay = self.session.config().input_delay as u32;
+        let target = self.session.current_frame() + delay;
+        let input = script[target as usize][player];
+        self.session.add_local_input(input);
+        match self.session.advance_frame() {
+            Ok(advance) => {
+                self.channel.push_local_input(target, input).unwrap();
+                if let Some(checksum) = advance.checksum {
+                    self.channel.push_checksum(checksum);
+                }
+            }
+            // Too far ahead of the peer; wait for its inputs.
+            Err(SessionError::PredictionThreshold { .. }) => {}
+            Err(err) => panic!("player {} failed: {}", player, err),
+        }
+        self.channel.send().unwrap();
+
+        while self.next_check <= FRAMES {
+            let Some(state) = self.session.confirmed_state(self.next_check) else {
+                break;
+            };
+            assert_eq!(
+                state_hash(&state),
+                reference[self.next_check as usize],
+                "player {} diverged at frame {}",
+                player,
+                self.next_check
+            );
+            self.next_check += 1;
+        }
+    }
+
+    fn done(&self) -> bool {
+        self.next_check > FRAMES
+    }
+}
+
+/// Plays a scripted match over the simulated network and checks both
+/// peers' confirmed state against the reference on every frame.
+fn run(conditions: NetworkConditions, seed: u64, delay: u8) -> [SessionStats; 2] {
+    let script = script(seed, delay);
+    let reference = reference(&script);
+    let network = SimNetwork::new(conditions, seed);
+    let mut peers = network
+        .endpoints()
+        .into_iter()
+        .enumerate()
+        .map(|(player, transport)| Peer::new(player, transport, delay))
+        .collect::<Vec<_>>();
+
+    for _ in 0..FRAMES * 4 {
+        if peers.iter().all(Peer::done) {
+            return [peers[0].session.stats(), peers[1].session.stats()];
+        }
+        for peer in &mut peers {
+            peer.tick(&script, &reference);
+        }
+        network.advance(FRAME_MS);
+    }
+    panic!(
+        "peers stalled at frames {} and {}",
+        peers[0].next_check, peers[1].next_check
+    );
+}
+
+fn conditions(latency_ms: u32, jitter_ms: u32, loss: u32, reorder: u32) -> NetworkConditions {
+    NetworkConditions {
+        latency_ms,
+        jitter_ms,
+        loss_percent: loss,
+        reorder_percent: reorder,
+    }
+}
+
+#[test]
+fn converges_on_a_perfect_network() {
+    // Peers tick one after the other, so a frame of delay covers the wait.
+    let stats = run(NetworkConditions::default(), 1, 1);
+    assert_eq!(stats[0].rollbacks, 0);
+    assert_eq!(stats[1].rollbacks, 0);
+}
+
+#[test]
+fn converges_under_latency_and_jitter() {
+    for seed in 0..4 {
+        let stats = run(conditions(50, 30, 0, 0), seed, 0);
+        assert!(stats[0].rollbacks > 0);
+    }
+}
+
+#[test]
+fn converges_under_loss_and_reordering() {
+    for seed in 0..4 {
+        run(conditions(30, 10, 15, 10), seed, 0);
+    }
+}
+
+#[test]
+fn converges_on_a_bad_network_with_input_delay() {
+    for seed in 0..4 {
+        run(conditions(80, 60, 25, 20), seed, 2);
+    }
+}
+
+#[test]
+fn same_seed_replays_the_same_match() {
+    let bad = conditions(60, 40, 20, 10);
+    assert_eq!(run(bad, 7, 1), run(bad, 7, 1));
+}
+
+#[test]
+fn network_applies_latency_and_loss() {
+    let network = SimNetwork::new(conditions(40, 0, 0, 0), 3);
+    let [mut a, mut b] = network.endpoints();
+    let mut buf = [0; 16];
+    a.send(&[1, 2, 3]).unwrap();
+    network.advance(39);
+    assert_eq!(b.recv(&mut buf).unwrap(), None);
+    network.advance(1);
+    assert_eq!(b.recv(&mut buf).unwrap(), Some(3));
+    assert_eq!(&buf[..3], [1, 2, 3]);
+
+    let lossy = SimNetwork::new(conditions(0, 0, 100, 0), 3);
+    let [mut a, mut b] = lossy.endpoints();
+    for _ in 0..10 {
+        a.send(&[0]).unwrap();
+    }
+    assert_eq!(b.recv(&mut buf).unwrap(), None);
+    assert_eq!(lossy.stats().lost, 10);
+}
+
+#[test]
+fn reordered_packets_arrive_after_later_ones() {
+    let network = SimNetwork::new(conditions(10, 0, 0, 50), 5);
+    let [mut a, mut b] = network.endpoints();
+    for byte in 0..20u8 {
+        a.send(&[byte]).unwrap();
+    }
+    network.advance(100);
+    let mut buf = [0; 1];
+    let mut order = Vec::new();
+    while b.recv(&mut buf).unwrap().is_some() {
+        order.push(buf[0]);
+    }
+    assert_eq!(order.len(), 20);
+    assert!(network.stats().reordered > 0);
+    assert!(order.windows(2).any(|pair| pair[0] > pair[1]));
+}
@@ -0,0 +1,94 @@
+use std::time::Instant;
+
+use game_core::character::ROSTER;
+use game_core::{simulate_frame, GameState, Input, Session, SessionConfig, SnapshotPool};
+
+const RING: usize = 16;
+
+fn inputs(frame: u32, variant: u32) -> [Input; 2] {
+    let p1 = match (frame + variant) % 30 {
+        0..=11 => Input::RIGHT,
+        12..=14 => Input::ATTACK,
+        _ => Input::NONE,
+    };
+    let p2 = match (frame * 3 + variant) % 50 {
+        0..=4 => Input::SPECIAL,
+        5..=19 => Input::LEFT,
+        _ => Input::DEFEND,
+    };
+    [p1, p2]
+}
+
+/// Saves and rolls back like a session would, checking every stored frame
+/// against plain copies.
+#[test]
+fn stored_frames_match_full_copies_across_rollbacks() {
+    let initial = GameState::with_seed(&ROSTER[0], &ROSTER[1], 3);
+    let mut pool = SnapshotPool::<RING>::new(initial);
+    let mut reference = [initial; RING];
+    let mut state = initial;
+
+    let mut frame = 0u32;
+    let mut latest = 0;
+    let mut variant = 0;
+    for step in 1..=400 {
+        pool.save(frame, &state);
+        latest = latest.max(frame);
+        reference[frame as usize % RING] = state;
+        state = simulate_frame(&state, inputs(frame, variant));
+        frame += 1;
+
+        if step % 37 == 0 {
+            let from = frame - 8;
+            variant += 1;
+            pool.restore_into(from, &mut state);
+            assert_eq!(state, reference[from as usize % RING]);
+            frame = from;
+        }
+
+        // Frames past a rollback point keep their slots until re-saved.
+        let oldest = (latest + 1).saturating_sub(RING as u32);
+        for stored in oldest..frame {
+            assert!(pool.contains(stored));
+            assert_eq!(pool.state(stored), reference[stored as usize % RING]);
+        }
+    }
+}
+
+#[test]
+fn unsaved_slots_read_back_as_initial() {
+    let initial = GameState::with_seed(&ROSTER[1], &ROSTER[0], 9);
+    let pool = SnapshotPool::<RING>::new(initial);
+    assert!(!pool.contains(5));
+    assert_eq!(pool.state(5), initial);
+}
+
+#[test]
+fn eight_frame_rollback_stays_under_a_millisecond() {
+    let defs = [&ROSTER[0], &ROSTER[1]];
+    let initial = GameState::new(defs[0], defs[1]);
+    let mut session = Session::new(initial, defs, SessionConfig::default()).unwrap();
+    for frame in 0..8 {
+        session.add_local_input(inputs(frame, 0)[0]);
+        session.advance_frame().unwrap();
+    }
+
+    const ROUNDS: u32 = 200;
+    let start = Instant::now();
+    for frame in 8..8 + ROUNDS {
+        // Every remote input contradicts the repeated prediction, so each
+        // advance re-simulates the eight unconfirmed frames.
+        session
+            .add_remote_input(frame - 8, Input::from_bits_truncate((frame % 7) as u16 + 1))
+            .unwrap();
+        session.add_local_input(inputs(frame, 0)[0]);
+        let advance = session.advance_frame().unwrap();
+        assert_eq!(advance.rollback_frames, 8);
+    }
+    let per_rollback = start.elapsed() / ROUNDS;
+    assert!(
+        per_rollback.as_micros() < 1000,
+        "{:?} per rollback",
+        per_rollback
+    );
+}
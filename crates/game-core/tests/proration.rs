@@ -0,0 +1,66 @@
+use game_core::character::{MOVE_LIGHT, MOVE_SPECIAL, ROSTER};
+use game_core::{step, Cancel, CharacterDef, GameState, Input, Proration};
+
+#[test]
+fn scaling_compounds_down_to_the_floor() {
+    let proration = Proration::DEFAULT;
+    let percents: Vec<u32> = (0..14).map(|hits| proration.percent(hits)).collect();
+    assert_eq!(
+        percents,
+        [100, 90, 81, 72, 64, 57, 51, 45, 40, 36, 32, 30, 30, 30]
+    );
+    assert_eq!(proration.percent(u16::MAX), 30);
+    assert_eq!(proration.scale(80, 1), 72);
+    assert_eq!(proration.scale(35, 3), 25);
+}
+
+#[test]
+fn full_scaling_never_prorates() {
+    let proration = Proration {
+        scale_percent: 100,
+        floor_percent: 0,
+    };
+    assert_eq!(proration.percent(500), 100);
+}
+
+#[test]
+fn combo_hits_are_prorated() {
+    let cancels = [Cancel {
+        from: MOVE_LIGHT,
+        to: MOVE_SPECIAL,
+    }];
+    let ronin = CharacterDef {
+        cancels: &cancels,
+        ..ROSTER[0]
+    };
+    let defs = [&ronin, &ROSTER[0]];
+    let mut state = GameState::new(defs[0], defs[1]);
+    for _ in 0..55 {
+        state = step(&state, [Input::RIGHT, Input::NONE], defs);
+    }
+    state = step(&state, [Input::ATTACK, Input::NONE], defs);
+    while !state.players[0].move_connected {
+        state = step(&state, [Input::NONE; 2], defs);
+    }
+    state = step(&state, [Input::SPECIAL, Input::NONE], defs);
+    for _ in 0..20 {
+        state = step(&state, [Input::NONE; 2], defs);
+        if state.players[1].combo_hits == 2 {
+            break;
+        }
+    }
+
+    let light = ROSTER[0].moves[0].damage;
+    let special = ROSTER[0].moves[1].damage;
+    assert_eq!(state.players[1].combo_hits, 2);
+    assert_eq!(
+        state.players[1].health,
+        ROSTER[0].max_health - light - special * 90 / 100
+    );
+
+    // Recovering from hitstun ends the combo.
+    for _ in 0..40 {
+        state = step(&state, [Input::NONE; 2], defs);
+    }
+    assert_eq!(state.players[1].combo_hits, 0);
+}
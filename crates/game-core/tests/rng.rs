@@ -0,0 +1,54 @@
+use game_core::character::ROSTER;
+use game_core::{simulate_frame, state_hash, GameState, Input, Rng};
+
+#[test]
+fn matches_reference_xoshiro256starstar() {
+    // First outputs of the reference implementation for state {1, 2, 3, 4}.
+    let mut rng = Rng::from_state([1, 2, 3, 4]);
+    assert_eq!(rng.next_u64(), 11520);
+    assert_eq!(rng.next_u64(), 0);
+    assert_eq!(rng.next_u64(), 1509978240);
+    assert_eq!(rng.next_u64(), 1215971899390074240);
+}
+
+#[test]
+fn seeding_is_deterministic() {
+    let mut a = Rng::new(42);
+    let mut b = Rng::new(42);
+    let mut c = Rng::new(43);
+    let draws: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+    assert_eq!(draws, (0..8).map(|_| b.next_u64()).collect::<Vec<_>>());
+    assert_ne!(draws, (0..8).map(|_| c.next_u64()).collect::<Vec<_>>());
+    assert_ne!(Rng::new(0).state(), [0; 4]);
+}
+
+#[test]
+fn below_stays_in_range() {
+    let mut rng = Rng::new(7);
+    let mut seen = [false; 5];
+    for _ in 0..1000 {
+        let value = rng.below(5);
+        assert!(value < 5);
+        seen[value as usize] = true;
+    }
+    assert!(seen.iter().all(|&s| s));
+    assert_eq!(rng.below(0), 0);
+}
+
+#[test]
+fn seed_changes_the_run() {
+    let run = |seed| {
+        let mut state = GameState::with_seed(&ROSTER[0], &ROSTER[1], seed);
+        for frame in 0..300u32 {
+            let p1 = if frame % 20 < 12 {
+                Input::RIGHT
+            } else {
+                Input::ATTACK
+            };
+            state = simulate_frame(&state, [p1, Input::ATTACK]);
+        }
+        state
+    };
+    assert_eq!(state_hash(&run(1)), state_hash(&run(1)));
+    assert_ne!(state_hash(&run(1)), state_hash(&run(2)));
+}
@@ -0,0 +1,70 @@
+use game_core::character::ROSTER;
+use game_core::checksum::checksum;
+use game_core::snapshot::{decode, encode};
+use game_core::{simulate_frame, GameState, Input, SnapshotError, SNAPSHOT_LEN};
+
+fn mid_match_state() -> GameState {
+    let mut state = GameState::with_seed(&ROSTER[0], &ROSTER[1], 7);
+    for frame in 0..240u32 {
+        let p1 = if frame % 20 < 12 {
+            Input::RIGHT
+        } else {
+            Input::ATTACK
+        };
+        let p2 = if frame % 30 < 5 {
+            Input::SPECIAL
+        } else {
+            Input::LEFT
+        };
+        state = simulate_frame(&state, [p1, p2]);
+    }
+    state
+}
+
+#[test]
+fn round_trips_initial_and_mid_match_states() {
+    for state in [
+        GameState::with_seed(&ROSTER[0], &ROSTER[1], 0),
+        mid_match_state(),
+    ] {
+        let bytes = encode(&state);
+        assert_eq!(bytes.len(), SNAPSHOT_LEN);
+        assert_eq!(decode(&bytes), Ok(state));
+    }
+}
+
+#[test]
+fn decoded_state_simulates_identically() {
+    let state = mid_match_state();
+    let mut original = state;
+    let mut restored = decode(&encode(&state)).unwrap();
+    for _ in 0..60 {
+        original = simulate_frame(&original, [Input::ATTACK, Input::RIGHT]);
+        restored = simulate_frame(&restored, [Input::ATTACK, Input::RIGHT]);
+    }
+    assert_eq!(checksum(&original), checksum(&restored));
+}
+
+#[test]
+fn rejects_wrong_length() {
+    let bytes = encode(&mid_match_state());
+    assert_eq!(
+        decode(&bytes[..SNAPSHOT_LEN - 1]),
+        Err(SnapshotError::Length(SNAPSHOT_LEN - 1))
+    );
+}
+
+#[test]
+fn rejects_invalid_facing() {
+    let mut bytes = encode(&mid_match_state());
+    // frame, stage, then character_id and four Fx32 of the first player.
+    bytes[4 + 12 + 1 + 16] = 0;
+    assert_eq!(decode(&bytes), Err(SnapshotError::InvalidFacing(0)));
+}
+
+#[test]
+fn rejects_zero_rng() {
+    let mut bytes = encode(&mid_match_state());
+    bytes[SNAPSHOT_LEN - 35..SNAPSHOT_LEN - 3].fill(0);
+    assert_eq!(decode(&bytes), Err(SnapshotError::ZeroRng));
+}
@@ -0,0 +1,155 @@
+use game_core::character::ROSTER;
+use game_core::{
+    simulate_frame, GameState, Input, Publisher, SpectateConfig, SpectateError, SpectateHost,
+    Spectator, SpectatorMessage,
+};
+
+/// Channel that keeps every message in wire form.
+#[derive(Default)]
+struct Recorder(Vec<Vec<u8>>);
+
+impl Publisher for Recorder {
+    fn publish(&mut self, message: &SpectatorMessage) {
+        self.0.push(message.encode());
+    }
+}
+
+fn inputs(frame: u32) -> [Input; 2] {
+    let p1 = if frame % 25 < 15 {
+        Input::RIGHT
+    } else {
+        Input::ATTACK
+    };
+    let p2 = if frame % 35 < 5 {
+        Input::SPECIAL
+    } else {
+        Input::LEFT
+    };
+    [p1, p2]
+}
+
+fn host(config: SpectateConfig) -> SpectateHost<'static, Recorder> {
+    let initial = GameState::new(&ROSTER[0], &ROSTER[1]);
+    SpectateHost::new(
+        initial,
+        [&ROSTER[0], &ROSTER[1]],
+        config,
+        Recorder::default(),
+    )
+}
+
+fn played(frames: u32) -> GameState {
+    let mut state = GameState::new(&ROSTER[0], &ROSTER[1]);
+    for frame in 0..frames {
+        state = simulate_frame(&state, inputs(frame));
+    }
+    state
+}
+
+#[test]
+fn spectators_joining_late_catch_up_at_a_keyframe() {
+    let config = SpectateConfig {
+        keyframe_interval: 60,
+        broadcast_delay: 0,
+    };
+    let mut host = host(config);
+    for frame in 0..200 {
+        host.push_confirmed(frame, inputs(frame)).unwrap();
+    }
+
+    let messages: Vec<_> = host
+        .publisher()
+        .0
+        .iter()
+        .map(|bytes| SpectatorMessage::decode(bytes).unwrap())
+        .collect();
+    let mut from_start = Spectator::new([&ROSTER[0], &ROSTER[1]]);
+    let mut late = Spectator::new([&ROSTER[0], &ROSTER[1]]);
+    for (index, message) in messages.iter().enumerate() {
+        from_start.receive(message).unwrap();
+        // Joins at frame 70, so the first usable keyframe is frame 120.
+        if index >= 75 {
+            late.receive(message).unwrap();
+        }
+    }
+    assert_eq!(from_start.state(), Some(&played(200)));
+    assert_eq!(late.state(), Some(&played(200)));
+}
+
+#[test]
+fn broadcast_delay_holds_frames_back() {
+    let mut host = host(SpectateConfig {
+        keyframe_interval: 0,
+        broadcast_delay: 30,
+    });
+    for frame in 0..29 {
+        host.push_confirmed(frame, inputs(frame)).unwrap();
+    }
+    assert!(host.publisher().0.is_empty());
+
+    host.push_confirmed(29, inputs(29)).unwrap();
+    host.push_confirmed(30, inputs(30)).unwrap();
+    let published: Vec<_> = host
+        .publisher()
+        .0
+        .iter()
+        .map(|bytes| SpectatorMessage::decode(bytes).unwrap().frame())
+        .collect();
+    // The initial keyframe and the inputs for frame 0.
+    assert_eq!(published, [0, 0]);
+
+    host.flush();
+    assert_eq!(host.publisher().0.len(), 2 + 30);
+}
+
+#[test]
+fn rejects_out_of_order_frames_and_desynced_keyframes() {
+    let mut host = host(SpectateConfig::default());
+    assert_eq!(
+        host.push_confirmed(1, inputs(1)),
+        Err(SpectateError::FrameGap {
+            expected: 0,
+            received: 1
+        })
+    );
+
+    let mut spectator = Spectator::new([&ROSTER[0], &ROSTER[1]]);
+    let keyframe = |state: &GameState, frame| SpectatorMessage::Keyframe {
+        frame,
+        snapshot: game_core::snapshot::encode(state),
+    };
+    spectator.receive(&keyframe(&played(0), 0)).unwrap();
+    spectator
+        .receive(&SpectatorMessage::Inputs {
+            frame: 0,
+            inputs: inputs(0),
+        })
+        .unwrap();
+    assert_eq!(
+        spectator.receive(&keyframe(&played(0), 1)),
+        Err(SpectateError::Desync { frame: 1 })
+    );
+    assert_eq!(
+        spectator.receive(&SpectatorMessage::Inputs {
+            frame: 5,
+            inputs: inputs(5),
+        }),
+        Err(SpectateError::FrameGap {
+            expected: 1,
+            received: 5
+        })
+    );
+}
+
+#[test]
+fn rejects_malformed_messages() {
+    assert_eq!(SpectatorMessage::decode(&[]), Err(SpectateError::Malformed));
+    assert_eq!(
+        SpectatorMessage::decode(&[0, 1, 0, 0, 0, 0xff, 0xff, 0, 0]),
+        Err(SpectateError::Malformed)
+    );
+    assert_eq!(
+        SpectatorMessage::decode(&[1, 0, 0, 0, 0, 1, 2, 3]),
+        Err(SpectateError::Malformed)
+    );
+}
@@ -0,0 +1,96 @@
+use game_core::character::ROSTER;
+use game_core::snapshot::{decode, encode};
+use game_core::team::{INCOMING_INVINCIBILITY, TEAM_SIZE};
+use game_core::{step, Carryover, GameState, Input, TeamBattle};
+
+const ROSTERS: [[u8; TEAM_SIZE]; 2] = [[0, 1, 0], [1, 0, 1]];
+
+fn battle() -> TeamBattle {
+    TeamBattle::new(ROSTERS, 9, Carryover::Keep)
+}
+
+/// Walks player one into range and attacks, returning the state once the
+/// attack has finished.
+fn attack(mut state: GameState) -> GameState {
+    let defs = [&ROSTER[0], &ROSTER[0]];
+    for _ in 0..55 {
+        state = step(&state, [Input::RIGHT, Input::NONE], defs);
+    }
+    state = step(&state, [Input::ATTACK, Input::NONE], defs);
+    while state.players[0].current_move.is_some() {
+        state = step(&state, [Input::NONE; 2], defs);
+    }
+    state
+}
+
+#[test]
+fn knockout_brings_in_the_next_character() {
+    let mut battle = battle();
+    battle.state.players[0].health = 123;
+    battle.state.players[0].meter = 40;
+    battle.state.players[1].health = 0;
+    battle.step([Input::NONE; 2]);
+
+    assert_eq!(battle.knocked_out, [0, 1]);
+    assert_eq!(battle.active(1), 1);
+    let [survivor, incoming] = battle.state.players;
+    assert_eq!(survivor.character_id, ROSTERS[0][0]);
+    assert_eq!(survivor.health, 123);
+    assert_eq!(survivor.meter, 40);
+    assert_eq!(survivor.invincible, 0);
+    assert_eq!(survivor.pos_x, -GameState::START_OFFSET);
+    assert_eq!(incoming.character_id, ROSTERS[1][1]);
+    assert_eq!(incoming.health, ROSTER[ROSTERS[1][1] as usize].max_health);
+    assert_eq!(incoming.invincible, INCOMING_INVINCIBILITY);
+    assert_eq!(battle.state.frame, 1);
+    assert_eq!(battle.winner(), None);
+}
+
+#[test]
+fn invincible_characters_are_not_hit() {
+    let fresh = GameState::new(&ROSTER[0], &ROSTER[0]);
+    let hit = attack(fresh);
+    assert!(hit.players[1].health < ROSTER[0].max_health);
+
+    let mut protected = fresh;
+    protected.players[1].invincible = 200;
+    let missed = attack(protected);
+    assert_eq!(missed.players[1].health, ROSTER[0].max_health);
+    assert!(!missed.players[0].move_connected);
+    assert!(missed.players[1].invincible > 0);
+}
+
+#[test]
+fn double_knockout_replaces_both_characters() {
+    let mut battle = battle();
+    battle.state.players[0].health = 0;
+    battle.state.players[1].health = 0;
+    battle.step([Input::NONE; 2]);
+
+    assert_eq!(battle.knocked_out, [1, 1]);
+    for (side, player) in battle.state.players.iter().enumerate() {
+        assert_eq!(player.character_id, ROSTERS[side][1]);
+        assert_eq!(player.invincible, INCOMING_INVINCIBILITY);
+    }
+}
+
+#[test]
+fn battle_ends_when_a_side_runs_out_of_characters() {
+    let mut battle = battle();
+    battle.knocked_out = [1, TEAM_SIZE as u8 - 1];
+    battle.state.players[1].health = 0;
+    battle.step([Input::NONE; 2]);
+    assert!(battle.is_over());
+    assert_eq!(battle.winner(), Some(0));
+
+    let finished = battle;
+    battle.step([Input::RIGHT; 2]);
+    assert_eq!(battle, finished);
+}
+
+#[test]
+fn invincibility_survives_snapshots() {
+    let mut state = GameState::new(&ROSTER[0], &ROSTER[1]);
+    state.players[1].invincible = INCOMING_INVINCIBILITY;
+    assert_eq!(decode(&encode(&state)), Ok(state));
+}
@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+use std::convert::Infallible;
+
+use game_core::{
+    Channel, ChannelConfig, ChannelError, ChannelEvent, FrameChecksum, Input, MemoryTransport,
+    Transport, UdpTransport,
+};
+
+fn input(frame: u32) -> Input {
+    Input::from_bits_truncate((frame % 0x3ff) as u16)
+}
+
+fn remote_inputs(events: &[ChannelEvent]) -> Vec<u32> {
+    events
+        .iter()
+        .filter_map(|event| match event {
+            ChannelEvent::Input { frame, input: got } => {
+                assert_eq!(*got, input(*frame));
+                Some(*frame)
+            }
+            ChannelEvent::Checksum(_) => None,
+        })
+        .collect()
+}
+
+/// Drops every `drop_every`th packet.
+struct Lossy {
+    inner: MemoryTransport,
+    drop_every: usize,
+    sent: usize,
+}
+
+impl Transport for Lossy {
+    type Error = Infallible;
+
+    fn send(&mut self, packet: &[u8]) -> Result<(), Infallible> {
+        self.sent += 1;
+        if self.sent % self.drop_every == 0 {
+            return Ok(());
+        }
+        self.inner.send(packet)
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Infallible> {
+        self.inner.recv(buf)
+    }
+}
+
+/// Delivers packets in the reverse of the order they were sent in.
+struct Reversing {
+    inner: MemoryTransport,
+    held: VecDeque<Vec<u8>>,
+}
+
+impl Transport for Reversing {
+    type Error = Infallible;
+
+    fn send(&mut self, packet: &[u8]) -> Result<(), Infallible> {
+        self.held.push_front(packet.to_vec());
+        Ok(())
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> Result<Option<usize>, Infallible> {
+        self.inner.recv(buf)
+    }
+}
+
+#[test]
+fn redundancy_covers_lost_packets() {
+    let (a, b) = MemoryTransport::pair();
+    let mut sender = Channel::new(
+        Lossy {
+            inner: a,
+            drop_every: 3,
+            sent: 0,
+        },
+        ChannelConfig::default(),
+    )
+    .unwrap();
+    let mut receiver = Channel::new(b, ChannelConfig::default()).unwrap();
+
+    let mut delivered = Vec::new();
+    for frame in 0..100 {
+        sender.push_local_input(frame, input(frame)).unwrap();
+        sender.send().unwrap();
+        delivered.extend(remote_inputs(&receiver.poll().unwrap()));
+        receiver.send().unwrap();
+        sender.poll().unwrap();
+    }
+    assert_eq!(delivered, (0..100).collect::<Vec<_>>());
+    // Acks keep the repeated window short.
+    assert!(sender.unacked_inputs() <= 2);
+}
+
+#[test]
+fn stale_packets_are_dropped() {
+    let (a, b) = MemoryTransport::pair();
+    let mut sender = Channel::new(
+        Reversing {
+            inner: a,
+            held: VecDeque::new(),
+        },
+        ChannelConfig::default(),
+    )
+    .unwrap();
+    let mut receiver = Channel::new(b, ChannelConfig::default()).unwrap();
+
+    for frame in 0..3 {
+        sender.push_local_input(frame, input(frame)).unwrap();
+        sender.send().unwrap();
+    }
+    let held: Vec<_> = sender.transport_mut().held.drain(..).collect();
+    for packet in &held {
+        sender.transport_mut().inner.send(packet).unwrap();
+    }
+
+    // The newest packet arrives first and carries every frame.
+    let events = receiver.poll().unwrap();
+    assert_eq!(remote_inputs(&events), [0, 1, 2]);
+    assert_eq!(receiver.stats().packets_received, 1);
+    assert_eq!(receiver.stats().packets_stale, 2);
+}
+
+#[test]
+fn packets_never_exceed_the_mtu() {
+    let (a, mut b) = MemoryTransport::pair();
+    let config = ChannelConfig {
+        mtu: 40,
+        redundancy: 64,
+    };
+    let mut sender = Channel::new(a, config).unwrap();
+    for frame in 0..50 {
+        sender.push_local_input(frame, input(frame)).unwrap();
+    }
+    sender.push_checksum(FrameChecksum {
+        frame: 0,
+        checksum: 7,
+    });
+    sender.send().unwrap();
+    let mut buf = [0; 2048];
+    let len = b.recv(&mut buf).unwrap().unwrap();
+    assert!(len <= 40);
+
+    let (a, b) = MemoryTransport::pair();
+    let mut sender = Channel::new(a, config).unwrap();
+    let mut receiver = Channel::new(b, ChannelConfig::default()).unwrap();
+    for frame in 0..50 {
+        sender.push_local_input(frame, input(frame)).unwrap();
+    }
+    sender.push_checksum(FrameChecksum {
+        frame: 0,
+        checksum: 7,
+    });
+    sender.send().unwrap();
+    let events = receiver.poll().unwrap();
+    assert_eq!(
+        events[0],
+        ChannelEvent::Checksum(FrameChecksum {
+            frame: 0,
+            checksum: 7
+        })
+    );
+    // 40 bytes leave room for 7 inputs next to a checksum.
+    assert_eq!(remote_inputs(&events), (0..7).collect::<Vec<_>>());
+}
+
+#[test]
+fn rejects_out_of_order_local_inputs_and_invalid_config() {
+    let (a, b) = MemoryTransport::pair();
+    let mut channel = Channel::new(a, ChannelConfig::default()).unwrap();
+    assert_eq!(
+        channel.push_local_input(1, Input::NONE),
+        Err(ChannelError::InputOutOfOrder {
+            expected: 0,
+            received: 1
+        })
+    );
+    assert!(matches!(
+        Channel::new(
+            b,
+            ChannelConfig {
+                mtu: 8,
+                redundancy: 4
+            }
+        ),
+        Err(ChannelError::InvalidConfig)
+    ));
+}
+
+#[test]
+fn malformed_packets_are_counted_and_ignored() {
+    let (mut a, b) = MemoryTransport::pair();
+    let mut receiver = Channel::new(b, ChannelConfig::default()).unwrap();
+    a.send(&[1, 2, 3]).unwrap();
+    assert!(receiver.poll().unwrap().is_empty());
+    assert_eq!(receiver.stats().packets_malformed, 1);
+}
+
+#[test]
+fn udp_loopback_delivers_inputs() {
+    let any = "127.0.0.1:0".parse().unwrap();
+    let probe = std::net::UdpSocket::bind(any).unwrap();
+    let b_addr = probe.local_addr().unwrap();
+    drop(probe);
+
+    let a = UdpTransport::connect(any, b_addr).unwrap();
+    let a_addr = a.local_addr().unwrap();
+    let b = UdpTransport::connect(b_addr, a_addr).unwrap();
+    let mut sender = Channel::new(a, ChannelConfig::default()).unwrap();
+    let mut receiver = Channel::new(b, ChannelConfig::default()).unwrap();
+
+    for frame in 0..5 {
+        sender.push_local_input(frame, input(frame)).unwrap();
+    }
+    sender.send().unwrap();
+    let mut delivered = Vec::new();
+    for _ in 0..200 {
+        delivered.extend(remote_inputs(&receiver.poll().unwrap()));
+        if delivered.len() == 5 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(5));
+    }
+    assert_eq!(delivered, [0, 1, 2, 3, 4]);
+}
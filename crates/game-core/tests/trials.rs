@@ -0,0 +1,142 @@
+use game_core::character::{MOVE_LIGHT, MOVE_SPECIAL, ROSTER};
+use game_core::trials::validate;
+use game_core::{
+    Cancel, CharacterDef, ComboTrial, Fx32, GameState, Input, Proration, TrialCompletion,
+    TrialError,
+};
+
+/// Ronin with light cancelling into special, which makes a two-hit combo.
+const CANCELS: [Cancel; 1] = [Cancel {
+    from: MOVE_LIGHT,
+    to: MOVE_SPECIAL,
+}];
+const RONIN: CharacterDef = CharacterDef {
+    cancels: &CANCELS,
+    ..ROSTER[0]
+};
+const DEFS: [&CharacterDef; 2] = [&RONIN, &ROSTER[1]];
+const LIGHT_SPECIAL: ComboTrial = ComboTrial {
+    character_id: 0,
+    moves: &[MOVE_LIGHT, MOVE_SPECIAL],
+};
+
+fn start(distance: i32) -> GameState {
+    let mut state = GameState::new(DEFS[0], DEFS[1]);
+    state.players[0].pos_x = Fx32::from_int(-distance / 2);
+    state.players[1].pos_x = Fx32::from_int(distance / 2);
+    state
+}
+
+/// Player 0 presses `presses` on the given frames, player 1 holds `defender`.
+fn attempt(presses: &[(usize, Input)], defender: Input) -> Vec<[Input; 2]> {
+    let mut inputs = vec![[Input::NONE, defender]; 60];
+    for &(frame, input) in presses {
+        inputs[frame][0] = input;
+    }
+    inputs
+}
+
+fn run(
+    trial: &ComboTrial,
+    state: &GameState,
+    inputs: &[[Input; 2]],
+) -> Result<TrialCompletion, TrialError> {
+    validate(trial, state, 0, inputs, DEFS)
+}
+
+#[test]
+fn cancelled_combo_completes() {
+    let inputs = attempt(&[(0, Input::ATTACK), (5, Input::SPECIAL)], Input::NONE);
+    let completion = run(&LIGHT_SPECIAL, &start(80), &inputs).unwrap();
+    assert_eq!(completion.frame, 17);
+    assert_eq!(completion.damage, 30 + Proration::DEFAULT.scale(80, 1));
+}
+
+#[test]
+fn movement_before_the_combo_is_allowed() {
+    let mut inputs = vec![[Input::RIGHT, Input::NONE]; 8];
+    inputs.extend(attempt(
+        &[(0, Input::ATTACK), (5, Input::SPECIAL)],
+        Input::NONE,
+    ));
+    assert!(run(&LIGHT_SPECIAL, &start(112), &inputs).is_ok());
+}
+
+#[test]
+fn wrong_first_move_fails() {
+    let inputs = attempt(&[(0, Input::SPECIAL)], Input::NONE);
+    assert_eq!(
+        run(&LIGHT_SPECIAL, &start(80), &inputs),
+        Err(TrialError::WrongMove {
+            index: 0,
+            expected: MOVE_LIGHT,
+            performed: MOVE_SPECIAL,
+        })
+    );
+}
+
+#[test]
+fn wrong_character_fails() {
+    let trial = ComboTrial {
+        character_id: 1,
+        ..LIGHT_SPECIAL
+    };
+    assert_eq!(
+        run(&trial, &start(80), &attempt(&[], Input::NONE)),
+        Err(TrialError::WrongCharacter {
+            expected: 1,
+            actual: 0
+        })
+    );
+}
+
+#[test]
+fn blocked_hit_fails() {
+    let inputs = attempt(&[(0, Input::ATTACK), (5, Input::SPECIAL)], Input::DEFEND);
+    assert_eq!(
+        run(&LIGHT_SPECIAL, &start(80), &inputs),
+        Err(TrialError::Blocked { index: 0 })
+    );
+}
+
+#[test]
+fn whiffed_move_fails() {
+    // Too far for the light to reach; the special starts after it whiffs.
+    let inputs = attempt(&[(0, Input::ATTACK), (16, Input::SPECIAL)], Input::NONE);
+    assert_eq!(
+        run(&LIGHT_SPECIAL, &start(300), &inputs),
+        Err(TrialError::Missed { index: 0 })
+    );
+}
+
+#[test]
+fn linking_after_the_opponent_recovers_is_a_drop() {
+    let trial = ComboTrial {
+        character_id: 0,
+        moves: &[MOVE_LIGHT, MOVE_LIGHT],
+    };
+    // The second light only starts once the first has recovered, after the
+    // opponent is out of hitstun.
+    let inputs = attempt(&[(0, Input::ATTACK), (15, Input::ATTACK)], Input::NONE);
+    assert_eq!(
+        run(&trial, &start(80), &inputs),
+        Err(TrialError::Dropped { index: 1 })
+    );
+}
+
+#[test]
+fn stopping_early_is_incomplete() {
+    let inputs = attempt(&[(0, Input::ATTACK)], Input::NONE);
+    assert_eq!(
+        run(&LIGHT_SPECIAL, &start(80), &inputs),
+        Err(TrialError::Incomplete { hits: 1 })
+    );
+    let empty = ComboTrial {
+        character_id: 0,
+        moves: &[],
+    };
+    assert_eq!(
+        run(&empty, &start(80), &inputs),
+        Err(TrialError::EmptyCombo)
+    );
+}
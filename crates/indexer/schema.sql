@@ -0,0 +1,80 @@
+-- Tables `indexer ingest` writes to. Runs unchanged on SQLite (3.24 or
+-- later) and Postgres.
+--
+-- Every row is keyed by the transaction signature and the event's position
+-- among the decoded events of that transaction, so feeding the same logs
+-- twice inserts nothing new. Keys are base58 strings and hashes are
+-- lowercase hex. Unsigned 64-bit amounts are NUMERIC(20) because they don't
+-- fit BIGINT; SQLite keeps them exact up to i64::MAX. Timestamps are the
+-- on-chain unix seconds.
+
+-- combo_mint::ComboCreated
+CREATE TABLE IF NOT EXISTS combo_created (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    combo TEXT NOT NULL,
+    authority TEXT NOT NULL,
+    character_id INTEGER NOT NULL,
+    damage BIGINT NOT NULL,
+    difficulty INTEGER NOT NULL,
+    tags BIGINT NOT NULL,
+    created_at BIGINT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS combo_created_authority ON combo_created (authority);
+
+-- combo_mint::ComboVerified. `counted` is false for repeat verifications.
+CREATE TABLE IF NOT EXISTS combo_verified (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    combo TEXT NOT NULL,
+    verifier TEXT NOT NULL,
+    moves_count INTEGER NOT NULL,
+    counted BOOLEAN NOT NULL,
+    verified_at BIGINT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS combo_verified_combo ON combo_verified (combo);
+
+-- match_result::MatchRecorded. Rosters are the character ids of each side,
+-- comma-separated; singles matches leave them all zero.
+CREATE TABLE IF NOT EXISTS match_recorded (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    match_account TEXT NOT NULL,
+    match_id NUMERIC(20) NOT NULL,
+    player_one TEXT NOT NULL,
+    player_two TEXT NOT NULL,
+    format INTEGER NOT NULL,
+    roster_one TEXT NOT NULL,
+    roster_two TEXT NOT NULL,
+    winner TEXT NOT NULL,
+    score_one INTEGER NOT NULL,
+    score_two INTEGER NOT NULL,
+    replay_hash TEXT NOT NULL,
+    frame_count BIGINT NOT NULL,
+    recorded_at BIGINT NOT NULL,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS match_recorded_player_one ON match_recorded (player_one);
+CREATE INDEX IF NOT EXISTS match_recorded_player_two ON match_recorded (player_two);
+
+-- combo_mint::ComboListed, ComboDelisted and ComboSold, one row each.
+-- `kind` is 'listed', 'delisted' or 'sold'; `buyer`, `price` and
+-- `payment_mint` are null where the event has none.
+CREATE TABLE IF NOT EXISTS marketplace (
+    signature TEXT NOT NULL,
+    event_index INTEGER NOT NULL,
+    slot BIGINT NOT NULL,
+    kind TEXT NOT NULL,
+    combo TEXT NOT NULL,
+    seller TEXT NOT NULL,
+    buyer TEXT,
+    price NUMERIC(20),
+    payment_mint TEXT,
+    PRIMARY KEY (signature, event_index)
+);
+CREATE INDEX IF NOT EXISTS marketplace_combo ON marketplace (combo);
@@ -0,0 +1,442 @@
+//! Decodes the events the on-chain programs emit into rows for the tables in
+//! `schema.sql`, so frontends query a database instead of each decoding
+//! Anchor events themselves.
+//!
+//! Events arrive two ways. Every event is logged as a `Program data:` line
+//! holding its base64 encoding, which is what `solana logs` and a
+//! `logsSubscribe` websocket deliver. Programs built with `event-cpi` also
+//! invoke themselves with the event as instruction data, which survives
+//! log truncation; a Geyser plugin or `getTransaction` hands those inner
+//! instructions to `decode_cpi_event`. Both carry Anchor's encoding: the
+//! event's 8-byte discriminator followed by its Borsh-serialized fields.
+//!
+//! The structs here mirror the `#[event]` structs of `combo_mint` and
+//! `match_result` field for field, and have to be kept in step with them.
+
+use std::fmt;
+
+use base64::Engine;
+use game_core::team::TEAM_SIZE;
+use sha2::{Digest, Sha256};
+
+/// First 8 bytes of `sha256("anchor:event")`, little-endian, which precede
+/// the event in self-CPI instruction data.
+pub const EVENT_IX_TAG_LE: [u8; 8] = 0x1d9a_cb51_2ea5_45e4u64.to_le_bytes();
+
+/// `schema.sql`, for `indexer schema`.
+pub const SCHEMA: &str = include_str!("../schema.sql");
+
+pub type Key = [u8; 32];
+
+/// `combo_mint::ComboCreated`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboCreated {
+    pub combo: Key,
+    pub authority: Key,
+    pub character_id: u8,
+    pub damage: u32,
+    pub difficulty: u16,
+    pub tags: u32,
+    pub timestamp: i64,
+}
+
+/// `combo_mint::ComboVerified`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboVerified {
+    pub combo: Key,
+    pub verifier: Key,
+    pub moves_count: u8,
+    pub counted: bool,
+    pub timestamp: i64,
+}
+
+/// `match_result::MatchRecorded`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatchRecorded {
+    pub match_account: Key,
+    pub match_id: u64,
+    pub players: [Key; 2],
+    pub format: u8,
+    pub rosters: [[u8; TEAM_SIZE]; 2],
+    pub winner: Key,
+    pub score: [u8; 2],
+    pub replay_hash: [u8; 32],
+    pub frame_count: u32,
+    pub timestamp: i64,
+}
+
+/// `combo_mint::ComboListed`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboListed {
+    pub combo: Key,
+    pub seller: Key,
+    pub price: u64,
+    pub payment_mint: Key,
+}
+
+/// `combo_mint::ComboDelisted`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboDelisted {
+    pub combo: Key,
+    pub seller: Key,
+}
+
+/// `combo_mint::ComboSold`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComboSold {
+    pub combo: Key,
+    pub seller: Key,
+    pub buyer: Key,
+    pub price: u64,
+    pub payment_mint: Key,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    ComboCreated(ComboCreated),
+    ComboVerified(ComboVerified),
+    MatchRecorded(MatchRecorded),
+    ComboListed(ComboListed),
+    ComboDelisted(ComboDelisted),
+    ComboSold(ComboSold),
+}
+
+/// Anchor's discriminator for the event named `name`, the first 8 bytes of
+/// `sha256("event:<name>")`.
+pub fn discriminator(name: &str) -> [u8; 8] {
+    let hash = Sha256::digest(format!("event:{}", name));
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Not valid base64.
+    Encoding,
+    /// A known event whose data is too short or too long for its fields.
+    Length(&'static str),
+    /// A `bool` field holding something other than 0 or 1.
+    InvalidBool(&'static str),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Encoding => write!(f, "program data is not valid base64"),
+            DecodeError::Length(name) => write!(f, "{} has the wrong length", name),
+            DecodeError::InvalidBool(name) => write!(f, "{} has an invalid bool", name),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+/// Reads Borsh fields in order, failing the whole event on a short read.
+struct Reader<'a> {
+    name: &'static str,
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        if self.bytes.len() < N {
+            return Err(DecodeError::Length(self.name));
+        }
+        let (head, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        let mut out = [0; N];
+        out.copy_from_slice(head);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    fn i64(&mut self) -> Result<i64, DecodeError> {
+        Ok(i64::from_le_bytes(self.take()?))
+    }
+
+    fn bool(&mut self) -> Result<bool, DecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::InvalidBool(self.name)),
+        }
+    }
+
+    /// Fails if fields are left over, which means the struct here no longer
+    /// matches the program's.
+    fn finish<T>(self, event: T) -> Result<T, DecodeError> {
+        if self.bytes.is_empty() {
+            Ok(event)
+        } else {
+            Err(DecodeError::Length(self.name))
+        }
+    }
+}
+
+impl Event {
+    /// Decodes an event from its discriminator and data. Events of other
+    /// types, and data that isn't an event at all, give `None`.
+    pub fn decode(bytes: &[u8]) -> Result<Option<Self>, DecodeError> {
+        if bytes.len() < 8 {
+            return Ok(None);
+        }
+        let (tag, data) = bytes.split_at(8);
+        let name = match EVENT_NAMES.iter().find(|name| discriminator(name) == tag) {
+            Some(name) => *name,
+            None => return Ok(None),
+        };
+        let mut r = Reader { name, bytes: data };
+        let event = match name {
+            "ComboCreated" => Event::ComboCreated(ComboCreated {
+                combo: r.take()?,
+                authority: r.take()?,
+                character_id: r.u8()?,
+                damage: r.u32()?,
+                difficulty: r.u16()?,
+                tags: r.u32()?,
+                timestamp: r.i64()?,
+            }),
+            "ComboVerified" => Event::ComboVerified(ComboVerified {
+                combo: r.take()?,
+                verifier: r.take()?,
+                moves_count: r.u8()?,
+                counted: r.bool()?,
+                timestamp: r.i64()?,
+            }),
+            "MatchRecorded" => Event::MatchRecorded(MatchRecorded {
+                match_account: r.take()?,
+                match_id: r.u64()?,
+                players: [r.take()?, r.take()?],
+                format: r.u8()?,
+                rosters: [r.take()?, r.take()?],
+                winner: r.take()?,
+                score: r.take()?,
+                replay_hash: r.take()?,
+                frame_count: r.u32()?,
+                timestamp: r.i64()?,
+            }),
+            "ComboListed" => Event::ComboListed(ComboListed {
+                combo: r.take()?,
+                seller: r.take()?,
+                price: r.u64()?,
+                payment_mint: r.take()?,
+            }),
+            "ComboDelisted" => Event::ComboDelisted(ComboDelisted {
+                combo: r.take()?,
+                seller: r.take()?,
+            }),
+            _ => Event::ComboSold(ComboSold {
+                combo: r.take()?,
+                seller: r.take()?,
+                buyer: r.take()?,
+                price: r.u64()?,
+                payment_mint: r.take()?,
+            }),
+        };
+        r.finish(Some(event))
+    }
+
+    /// Decodes the event in a log line, if it is a `Program data:` line.
+    pub fn from_log(line: &str) -> Result<Option<Self>, DecodeError> {
+        let data = match line.trim().strip_prefix("Program data: ") {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        // A line can hold several base64 chunks; events are always one.
+        let chunk = data.split_whitespace().next().unwrap_or("");
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(chunk)
+            .map_err(|_| DecodeError::Encoding)?;
+        Self::decode(&bytes)
+    }
+
+    /// `INSERT` statement for the event's row. `event_index` counts the
+    /// decoded events of the transaction from zero.
+    pub fn to_sql(&self, signature: &str, slot: u64, event_index: u32) -> String {
+        let (table, columns, values) = match self {
+            Event::ComboCreated(e) => (
+                "combo_created",
+                "combo, authority, character_id, damage, difficulty, tags, created_at",
+                vec![
+                    key(&e.combo),
+                    key(&e.authority),
+                    e.character_id.to_string(),
+                    e.damage.to_string(),
+                    e.difficulty.to_string(),
+                    e.tags.to_string(),
+                    e.timestamp.to_string(),
+                ],
+            ),
+            Event::ComboVerified(e) => (
+                "combo_verified",
+                "combo, verifier, moves_count, counted, verified_at",
+                vec![
+                    key(&e.combo),
+                    key(&e.verifier),
+                    e.moves_count.to_string(),
+                    e.counted.to_string().to_uppercase(),
+                    e.timestamp.to_string(),
+                ],
+            ),
+            Event::MatchRecorded(e) => (
+                "match_recorded",
+                "match_account, match_id, player_one, player_two, format, roster_one, \
+                 roster_two, winner, score_one, score_two, replay_hash, frame_count, recorded_at",
+                vec![
+                    key(&e.match_account),
+                    e.match_id.to_string(),
+                    key(&e.players[0]),
+                    key(&e.players[1]),
+                    e.format.to_string(),
+                    roster(&e.rosters[0]),
+                    roster(&e.rosters[1]),
+                    key(&e.winner),
+                    e.score[0].to_string(),
+                    e.score[1].to_string(),
+                    text(&hex(&e.replay_hash)),
+                    e.frame_count.to_string(),
+                    e.timestamp.to_string(),
+                ],
+            ),
+            Event::ComboListed(e) => (
+                "marketplace",
+                "kind, combo, seller, buyer, price, payment_mint",
+                vec![
+                    text("listed"),
+                    key(&e.combo),
+                    key(&e.seller),
+                    "NULL".to_string(),
+                    e.price.to_string(),
+                    key(&e.payment_mint),
+                ],
+            ),
+            Event::ComboDelisted(e) => (
+                "marketplace",
+                "kind, combo, seller, buyer, price, payment_mint",
+                vec![
+                    text("delisted"),
+                    key(&e.combo),
+                    key(&e.seller),
+                    "NULL".to_string(),
+                    "NULL".to_string(),
+                    "NULL".to_string(),
+                ],
+            ),
+            Event::ComboSold(e) => (
+                "marketplace",
+                "kind, combo, seller, buyer, price, payment_mint",
+                vec![
+                    text("sold"),
+                    key(&e.combo),
+                    key(&e.seller),
+                    key(&e.buyer),
+                    e.price.to_string(),
+                    key(&e.payment_mint),
+                ],
+            ),
+        };
+        format!(
+            "INSERT INTO {} (signature, event_index, slot, {}) VALUES ({}, {}, {}, {}) \
+             ON CONFLICT DO NOTHING;",
+            table,
+            columns,
+            text(signature),
+            event_index,
+            slot,
+            values.join(", ")
+        )
+    }
+}
+
+const EVENT_NAMES: [&str; 6] = [
+    "ComboCreated",
+    "ComboVerified",
+    "MatchRecorded",
+    "ComboListed",
+    "ComboDelisted",
+    "ComboSold",
+];
+
+/// Decodes a self-CPI event from its inner instruction data. Instructions
+/// that aren't events give `None`.
+pub fn decode_cpi_event(ix_data: &[u8]) -> Result<Option<Event>, DecodeError> {
+    match ix_data.strip_prefix(&EVENT_IX_TAG_LE) {
+        Some(event) => Event::decode(event),
+        None => Ok(None),
+    }
+}
+
+/// Follows `solana logs` output, which announces each transaction before its
+/// log lines, and numbers the events it decodes within each transaction.
+#[derive(Debug, Default)]
+pub struct LogStream {
+    signature: Option<String>,
+    slot: u64,
+    next_index: u32,
+}
+
+impl LogStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds one line of output, returning the `INSERT` for the event it
+    /// holds. Events seen before any `Signature:` line have no transaction
+    /// to key them by and are dropped.
+    pub fn line(&mut self, line: &str) -> Result<Option<String>, DecodeError> {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("Transaction executed in slot ") {
+            self.slot = rest.trim_end_matches(':').parse().unwrap_or(0);
+            self.signature = None;
+            return Ok(None);
+        }
+        if let Some(signature) = trimmed.strip_prefix("Signature: ") {
+            self.signature = Some(signature.to_string());
+            self.next_index = 0;
+            return Ok(None);
+        }
+        let Some(event) = Event::from_log(trimmed)? else {
+            return Ok(None);
+        };
+        let Some(signature) = &self.signature else {
+            return Ok(None);
+        };
+        let sql = event.to_sql(signature, self.slot, self.next_index);
+        self.next_index += 1;
+        Ok(Some(sql))
+    }
+}
+
+fn key(key: &Key) -> String {
+    text(&bs58::encode(key).into_string())
+}
+
+fn roster(characters: &[u8; TEAM_SIZE]) -> String {
+    let ids: Vec<String> = characters.iter().map(|c| c.to_string()).collect();
+    text(&ids.join(","))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SQL string literal.
+fn text(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
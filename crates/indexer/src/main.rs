@@ -0,0 +1,60 @@
+//! `indexer schema` prints the table definitions.
+//! `indexer ingest` reads `solana logs` output on stdin and prints an
+//! `INSERT` for every event it decodes, so either database's shell can
+//! apply them:
+//!
+//! ```text
+//! indexer schema | sqlite3 events.db
+//! solana logs <program-id> | indexer ingest | sqlite3 events.db
+//! ```
+//!
+//! Lines that fail to decode are reported on stderr and skipped.
+
+use std::env;
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+use indexer::{LogStream, SCHEMA};
+
+const USAGE: &str = "usage: indexer schema | indexer ingest";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let result = match args.as_slice() {
+        [command] if command == "schema" => {
+            print!("{}", SCHEMA);
+            Ok(())
+        }
+        [command] if command == "ingest" => ingest(),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn ingest() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+    let mut stream = LogStream::new();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        match stream.line(&line) {
+            Ok(Some(sql)) => {
+                writeln!(stdout, "{}", sql)?;
+                // Rows land as they arrive rather than when a buffer fills.
+                stdout.flush()?;
+            }
+            Ok(None) => {}
+            Err(err) => eprintln!("skipping {:?}: {}", line.trim(), err),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,185 @@
+use base64::Engine;
+use game_core::team::TEAM_SIZE;
+use indexer::{
+    decode_cpi_event, discriminator, ComboSold, ComboVerified, DecodeError, Event, LogStream,
+    MatchRecorded, EVENT_IX_TAG_LE,
+};
+
+fn encoded(name: &str, fields: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = discriminator(name).to_vec();
+    for field in fields {
+        bytes.extend_from_slice(field);
+    }
+    bytes
+}
+
+fn program_data(bytes: &[u8]) -> String {
+    format!(
+        "    Program data: {}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+fn verified_bytes() -> Vec<u8> {
+    encoded(
+        "ComboVerified",
+        &[
+            &[1; 32],
+            &[2; 32],
+            &[7],
+            &[1],
+            &1_700_000_000i64.to_le_bytes(),
+        ],
+    )
+}
+
+#[test]
+fn discriminator_matches_anchor() {
+    // First 8 bytes of sha256("event:MatchFound").
+    assert_eq!(
+        discriminator("MatchFound"),
+        [0x49, 0xa1, 0x46, 0x91, 0xe8, 0xf9, 0x48, 0xd3]
+    );
+}
+
+#[test]
+fn decodes_combo_verified() {
+    assert_eq!(
+        Event::decode(&verified_bytes()),
+        Ok(Some(Event::ComboVerified(ComboVerified {
+            combo: [1; 32],
+            verifier: [2; 32],
+            moves_count: 7,
+            counted: true,
+            timestamp: 1_700_000_000,
+        })))
+    );
+}
+
+#[test]
+fn decodes_match_recorded() {
+    let rosters = [3u8; 2 * TEAM_SIZE];
+    let bytes = encoded(
+        "MatchRecorded",
+        &[
+            &[9; 32],
+            &42u64.to_le_bytes(),
+            &[1; 32],
+            &[2; 32],
+            &[1],
+            &rosters,
+            &[2; 32],
+            &[1, 3],
+            &[0xab; 32],
+            &3600u32.to_le_bytes(),
+            &5i64.to_le_bytes(),
+        ],
+    );
+    assert_eq!(
+        Event::decode(&bytes),
+        Ok(Some(Event::MatchRecorded(MatchRecorded {
+            match_account: [9; 32],
+            match_id: 42,
+            players: [[1; 32], [2; 32]],
+            format: 1,
+            rosters: [[3; TEAM_SIZE]; 2],
+            winner: [2; 32],
+            score: [1, 3],
+            replay_hash: [0xab; 32],
+            frame_count: 3600,
+            timestamp: 5,
+        })))
+    );
+}
+
+#[test]
+fn rejects_wrong_length_and_bad_bools() {
+    let mut long = verified_bytes();
+    long.push(0);
+    assert_eq!(
+        Event::decode(&long),
+        Err(DecodeError::Length("ComboVerified"))
+    );
+    let short = &verified_bytes()[..40];
+    assert_eq!(
+        Event::decode(short),
+        Err(DecodeError::Length("ComboVerified"))
+    );
+    let mut bad_bool = verified_bytes();
+    bad_bool[8 + 65] = 2;
+    assert_eq!(
+        Event::decode(&bad_bool),
+        Err(DecodeError::InvalidBool("ComboVerified"))
+    );
+}
+
+#[test]
+fn ignores_other_events_and_log_lines() {
+    assert_eq!(Event::decode(&encoded("SetOpened", &[&[0; 80]])), Ok(None));
+    assert_eq!(
+        Event::from_log("Program log: Instruction: Verify"),
+        Ok(None)
+    );
+    assert_eq!(
+        Event::from_log("Program data: not base64!"),
+        Err(DecodeError::Encoding)
+    );
+}
+
+#[test]
+fn decodes_self_cpi_events() {
+    let mut ix_data = EVENT_IX_TAG_LE.to_vec();
+    ix_data.extend_from_slice(&verified_bytes());
+    assert_eq!(decode_cpi_event(&ix_data), Event::decode(&verified_bytes()));
+    assert_eq!(decode_cpi_event(&verified_bytes()), Ok(None));
+}
+
+#[test]
+fn log_stream_keys_rows_by_transaction() {
+    let sold = Event::ComboSold(ComboSold {
+        combo: [1; 32],
+        seller: [2; 32],
+        buyer: [3; 32],
+        price: u64::MAX,
+        payment_mint: [0; 32],
+    });
+    let sold_bytes = encoded(
+        "ComboSold",
+        &[
+            &[1; 32],
+            &[2; 32],
+            &[3; 32],
+            &u64::MAX.to_le_bytes(),
+            &[0; 32],
+        ],
+    );
+    let mut stream = LogStream::new();
+    // Nothing to key an event by before the first transaction.
+    assert_eq!(stream.line(&program_data(&sold_bytes)), Ok(None));
+
+    let lines = [
+        "Transaction executed in slot 77:".to_string(),
+        "  Signature: 5sig".to_string(),
+        "  Status: Ok".to_string(),
+        "  Log Messages:".to_string(),
+        program_data(&verified_bytes()),
+        program_data(&sold_bytes),
+    ];
+    let rows: Vec<String> = lines
+        .iter()
+        .filter_map(|line| stream.line(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 2);
+    assert!(rows[0].starts_with(
+        "INSERT INTO combo_verified (signature, event_index, slot, combo, verifier, \
+         moves_count, counted, verified_at) VALUES ('5sig', 0, 77, "
+    ));
+    assert!(rows[0].ends_with(", 7, TRUE, 1700000000) ON CONFLICT DO NOTHING;"));
+    assert_eq!(rows[1], sold.to_sql("5sig", 77, 1));
+    assert!(rows[1].contains(&format!(
+        "'sold', '{}', '{}', '{}', 18446744073709551615, '11111111111111111111111111111111'",
+        bs58::encode([1; 32]).into_string(),
+        bs58::encode([2; 32]).into_string(),
+        bs58::encode([3; 32]).into_string(),
+    )));
+}
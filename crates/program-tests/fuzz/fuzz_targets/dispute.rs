@@ -0,0 +1,6 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use program_tests_fuzz::{run, targets, FuzzInput};
+
+fuzz_target!(|input: FuzzInput| run(&targets::DISPUTE, input));
@@ -0,0 +1,254 @@
+//! Driver for the cargo-fuzz targets in `fuzz_targets/`, one per program.
+//!
+//! Each input is a short run of instructions for one program: an
+//! instruction's discriminator (or none, for raw data) followed by arbitrary
+//! bytes, and an account list drawn in any order from a pool of the harness
+//! wallets, the program's PDAs for them, sysvars and programs. Every
+//! instruction goes through the same in-process bank as the integration
+//! tests, so build the programs as `program-tests` describes, then run a
+//! target from `crates/program-tests`:
+//!
+//! ```text
+//! SBF_OUT_DIR=$PWD/../../target/deploy cargo fuzz run combo_mint
+//! ```
+//!
+//! Instructions failing is expected; the target only panics when one
+//! breaks an invariant:
+//!
+//! - the program panics,
+//! - the lamports of the accounts it was given add up to more afterwards,
+//! - it writes past an account's space, whether through serialization
+//!   Anchor refuses or a realloc the runtime does.
+
+pub mod targets;
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use anchor_lang::error::ErrorCode;
+use arbitrary::Arbitrary;
+use program_tests::{fixtures, Harness, TxResult};
+use solana_program_test::BanksClientError;
+use solana_sdk::hash::hash;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::TransactionError;
+use solana_sdk::{ed25519_program, system_program, sysvar};
+use tokio::runtime::Runtime;
+
+/// Instructions per input, each sent as its own transaction so one can set
+/// up state for the next.
+const MAX_INSTRUCTIONS: usize = 4;
+/// Caps that keep a transaction within the packet size, so oversized
+/// inputs reach the program rather than being refused by the client.
+const MAX_DATA_LEN: usize = 800;
+const MAX_ACCOUNTS: usize = 24;
+
+/// The name in name-keyed PDAs, and of the combo the `ComboMint` fixture
+/// creates for the first player.
+pub const FUZZ_NAME: &str = "Fuzzed";
+
+#[derive(Arbitrary, Debug)]
+pub struct FuzzInput {
+    pub instructions: Vec<FuzzInstruction>,
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct FuzzInstruction {
+    /// Index into the target's instructions, whose discriminator is put in
+    /// front of `data`; `None` sends `data` as it is.
+    pub instruction: Option<u8>,
+    pub data: Vec<u8>,
+    pub accounts: Vec<FuzzAccount>,
+}
+
+#[derive(Arbitrary, Debug)]
+pub struct FuzzAccount {
+    /// Index into the account pool.
+    pub index: u8,
+    /// Dropped for accounts without a keypair in the pool.
+    pub is_signer: bool,
+    pub is_writable: bool,
+}
+
+/// State a target's instructions should find already set up, so the
+/// fuzzer doesn't have to rediscover the initialization order.
+pub enum Fixtures {
+    None,
+    MoveRegistry,
+    /// Also creates a combo named `FUZZ_NAME` for the first player.
+    ComboMint,
+    Achievements,
+    /// Also gives each player a rating.
+    Ranking,
+}
+
+pub struct Target {
+    pub program_id: Pubkey,
+    /// The program's instructions, by their Rust names.
+    pub instructions: &'static [&'static str],
+    pub fixtures: Fixtures,
+    /// The program's PDAs for the pool's wallets, the bank's payer first.
+    pub addresses: fn(&[Pubkey]) -> Vec<Pubkey>,
+}
+
+impl Target {
+    /// Anchor's discriminator for the instruction at `index`, wrapping.
+    fn discriminator(&self, index: u8) -> [u8; 8] {
+        let name = self.instructions[index as usize % self.instructions.len()];
+        let digest = hash(format!("global:{}", name).as_bytes());
+        digest.to_bytes()[..8].try_into().unwrap()
+    }
+}
+
+struct Pool {
+    accounts: Vec<Pubkey>,
+    signers: Vec<Keypair>,
+}
+
+impl Pool {
+    fn meta(&self, account: &FuzzAccount) -> AccountMeta {
+        let pubkey = self.accounts[account.index as usize % self.accounts.len()];
+        let is_signer = account.is_signer && self.keypair(&pubkey).is_some();
+        AccountMeta {
+            pubkey,
+            is_signer,
+            is_writable: account.is_writable,
+        }
+    }
+
+    fn keypair(&self, pubkey: &Pubkey) -> Option<&Keypair> {
+        self.signers
+            .iter()
+            .find(|signer| signer.pubkey() == *pubkey)
+    }
+}
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    })
+}
+
+/// Runs `input` against a fresh bank set up for `target`, panicking if any
+/// of its instructions breaks an invariant.
+pub fn run(target: &Target, input: FuzzInput) {
+    runtime().block_on(async {
+        let mut h = Harness::start().await;
+        let pool = pool(&mut h, target).await;
+        for fuzzed in input.instructions.iter().take(MAX_INSTRUCTIONS) {
+            let ix = instruction(target, &pool, fuzzed);
+            let signers: Vec<&Keypair> = ix
+                .accounts
+                .iter()
+                .filter(|meta| meta.is_signer && meta.pubkey != h.admin())
+                .filter_map(|meta| pool.keypair(&meta.pubkey))
+                .collect();
+
+            let before = total_lamports(&mut h, &pool.accounts).await;
+            let result = h.send(&[ix], &signers).await;
+            check(&result);
+            let after = total_lamports(&mut h, &pool.accounts).await;
+            assert!(
+                after <= before,
+                "lamports grew from {} to {} after {:?}",
+                before,
+                after,
+                fuzzed
+            );
+        }
+    });
+}
+
+async fn pool(h: &mut Harness, target: &Target) -> Pool {
+    let players = [h.funded_player().await, h.funded_player().await];
+    match target.fixtures {
+        Fixtures::None => {}
+        Fixtures::MoveRegistry => fixtures::move_registry(h).await,
+        Fixtures::ComboMint => {
+            fixtures::combo_mint(h).await;
+            fixtures::combo(h, &players[0], FUZZ_NAME).await;
+        }
+        Fixtures::Achievements => fixtures::achievements(h).await,
+        Fixtures::Ranking => {
+            fixtures::ranking(h).await;
+            for player in &players {
+                fixtures::rating(h, player).await;
+            }
+        }
+    }
+
+    // A wallet the bank has never funded.
+    let signers: Vec<Keypair> = players.into_iter().chain([Keypair::new()]).collect();
+    let mut wallets = vec![h.admin()];
+    wallets.extend(signers.iter().map(Keypair::pubkey));
+
+    let mut accounts = wallets.clone();
+    accounts.extend((target.addresses)(&wallets));
+    accounts.extend([
+        target.program_id,
+        system_program::ID,
+        ed25519_program::ID,
+        sysvar::clock::ID,
+        sysvar::rent::ID,
+        sysvar::instructions::ID,
+        Pubkey::new_unique(),
+    ]);
+    accounts.extend(program_tests::PROGRAMS.iter().map(|(_, id)| *id));
+    Pool { accounts, signers }
+}
+
+fn instruction(target: &Target, pool: &Pool, fuzzed: &FuzzInstruction) -> Instruction {
+    let mut data = match fuzzed.instruction {
+        Some(index) => target.discriminator(index).to_vec(),
+        None => Vec::new(),
+    };
+    data.extend(fuzzed.data.iter().take(MAX_DATA_LEN));
+    Instruction {
+        program_id: target.program_id,
+        accounts: fuzzed
+            .accounts
+            .iter()
+            .take(MAX_ACCOUNTS)
+            .map(|account| pool.meta(account))
+            .collect(),
+        data,
+    }
+}
+
+/// Lamports across the distinct `accounts`. Fees only ever lower it.
+async fn total_lamports(h: &mut Harness, accounts: &[Pubkey]) -> u64 {
+    let distinct: HashSet<&Pubkey> = accounts.iter().collect();
+    let mut total = 0;
+    for address in distinct {
+        total += h.lamports(address).await;
+    }
+    total
+}
+
+/// Panics on the failures that mean the program broke an invariant; any
+/// other error is the program rejecting its input, as it should.
+fn check(result: &TxResult) {
+    let error = match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(_, error)))
+        | Err(BanksClientError::SimulationError {
+            err: TransactionError::InstructionError(_, error),
+            ..
+        }) => error,
+        _ => return,
+    };
+    match error {
+        InstructionError::ProgramFailedToComplete => panic!("program panicked"),
+        InstructionError::UnbalancedInstruction => panic!("instruction created lamports"),
+        InstructionError::InvalidRealloc => panic!("realloc past the permitted increase"),
+        InstructionError::Custom(code) if *code == u32::from(ErrorCode::AccountDidNotSerialize) => {
+            panic!("account serialized past its space")
+        }
+        _ => {}
+    }
+}
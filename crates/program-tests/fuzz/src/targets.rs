@@ -0,0 +1,532 @@
+//! One `Target` per program. Id-keyed PDAs use ids 0 and 1 and name-keyed
+//! ones use `FUZZ_NAME`, so the fuzzer can create an account and then find
+//! it again.
+
+use combo_mint_client::achievements::WIN_STREAK;
+use combo_mint_client::builders::new_combo_hash;
+use combo_mint_client::*;
+use program_tests::fixtures::{self, ROUTE};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{Fixtures, Target, FUZZ_NAME};
+
+fn per_wallet(wallets: &[Pubkey], address: impl Fn(&Pubkey) -> Pubkey) -> Vec<Pubkey> {
+    wallets.iter().map(address).collect()
+}
+
+pub const ACHIEVEMENTS: Target = Target {
+    program_id: achievements::ID,
+    instructions: &[
+        "initialize",
+        "define_achievement",
+        "award_badge",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::Achievements,
+    addresses: |wallets| {
+        let mut addresses = vec![
+            pda::achievements::config(),
+            pda::achievements::achievement(WIN_STREAK),
+            pda::achievements::issuer(&ranking::ID),
+        ];
+        addresses.extend(per_wallet(wallets, |w| {
+            pda::achievements::badge(WIN_STREAK, w)
+        }));
+        addresses
+    },
+};
+
+pub const BETTING: Target = Target {
+    program_id: betting::ID,
+    instructions: &[
+        "initialize",
+        "set_fee",
+        "open_market",
+        "place_bet",
+        "settle_market",
+        "cancel_market",
+        "claim",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let market = pda::betting::market(0);
+        let mut addresses = vec![
+            pda::betting::config(),
+            market,
+            pda::betting::market_vault(&market),
+            pda::match_result::match_account(0),
+        ];
+        addresses.extend(per_wallet(wallets, |w| pda::betting::bet(&market, w)));
+        addresses
+    },
+};
+
+pub const COMBO_MINT: Target = Target {
+    program_id: combo_mint::ID,
+    instructions: &[
+        "initialize_config",
+        "update_limits",
+        "bump_game_version",
+        "pause",
+        "unpause",
+        "initialize_rewards",
+        "fund_rewards",
+        "set_reward_rate",
+        "set_royalty_cap",
+        "set_verification_cooldown",
+        "initialize_verifier_registry",
+        "add_verifier",
+        "remove_verifier",
+        "initialize_name_filter",
+        "ban_name_prefix",
+        "unban_name_prefix",
+        "set_character_gate",
+        "set_official",
+        "set_moderator",
+        "flag_combo",
+        "freeze_combo",
+        "appeal_flag",
+        "resolve_flag",
+        "create_combo",
+        "fuse_combos",
+        "verify_combo",
+        "verify_combo_attested",
+        "verify_combos_batch",
+        "resize_combo",
+        "update_combo",
+        "attach_combo_data",
+        "verify_combo_data",
+        "mint_combo_nft",
+        "initialize_cnft_tree",
+        "mint_combo_cnft",
+        "transfer_combo",
+        "accept_combo_transfer",
+        "list_combo",
+        "delist_combo",
+        "buy_combo",
+        "buy_combo_with_token",
+        "create_auction",
+        "place_bid",
+        "settle_auction",
+        "finalize_auction_if_expired",
+        "rent_combo",
+        "use_combo_in_match",
+        "finalize_rental_if_expired",
+        "create_collection",
+        "fund_collection",
+        "register_in_collection",
+        "claim_completion_reward",
+        "set_tier_costs",
+        "upgrade_combo",
+        "upgrade_combo_with_duplicate",
+        "submit_to_leaderboard",
+        "close_combo",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::ComboMint,
+    addresses: |wallets| {
+        let combo = pda::combo_mint::combo(&wallets[1], FUZZ_NAME);
+        let combo_hash = new_combo_hash(
+            &fixtures::frame_data(),
+            &fixtures::new_combo(FUZZ_NAME, &ROUTE),
+        )
+        .unwrap();
+        let collection = pda::combo_mint::collection(FUZZ_NAME);
+        let mut addresses = vec![
+            pda::combo_mint::config(),
+            pda::combo_mint::event_authority(),
+            pda::combo_mint::name_filter(),
+            pda::combo_mint::verifier_registry(),
+            pda::combo_mint::reward_config(),
+            pda::combo_mint::reward_vault(),
+            pda::combo_mint::cnft_config(),
+            pda::combo_mint::character_index(fixtures::CHARACTER),
+            pda::combo_mint::character_gate(fixtures::CHARACTER),
+            pda::move_registry::frame_data(fixtures::CHARACTER),
+            combo,
+            pda::combo_mint::name_index(&wallets[1], FUZZ_NAME),
+            pda::combo_mint::hash_index(&combo_hash),
+            pda::combo_mint::listing(&combo),
+            pda::combo_mint::auction(&combo),
+            pda::combo_mint::rental(&combo),
+            pda::combo_mint::combo_flag(&combo),
+            pda::combo_mint::combo_data(&combo),
+            pda::combo_mint::combo_history(&combo, 1),
+            pda::combo_mint::combo_nft(&combo),
+            collection,
+        ];
+        addresses.extend(per_wallet(wallets, |w| pda::combo_mint::receipt(&combo, w)));
+        addresses.extend(per_wallet(wallets, pda::combo_mint::verifier_quota));
+        addresses.extend(per_wallet(wallets, |w| {
+            pda::combo_mint::collection_progress(&collection, w)
+        }));
+        addresses
+    },
+};
+
+pub const DISPUTE: Target = Target {
+    program_id: dispute::ID,
+    instructions: &[
+        "initialize",
+        "open_dispute",
+        "answer_dispute",
+        "vote",
+        "request_key_frames",
+        "post_key_frames",
+        "verify_transition",
+        "resolve_dispute",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |_| {
+        let dispute = pda::dispute::dispute(0);
+        vec![
+            pda::dispute::config(),
+            dispute,
+            pda::dispute::vault(&dispute),
+            pda::match_result::match_account(0),
+            pda::replay_registry::replay(0),
+        ]
+    },
+};
+
+pub const GUILD: Target = Target {
+    program_id: guild::ID,
+    instructions: &[
+        "create_guild",
+        "invite_member",
+        "accept_invite",
+        "leave_guild",
+        "kick_member",
+        "set_role",
+        "transfer_leadership",
+        "set_approval_threshold",
+        "propose_spend",
+        "approve_spend",
+        "execute_spend",
+        "tag_match",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let guild = pda::guild::guild(FUZZ_NAME);
+        let mut addresses = vec![
+            guild,
+            pda::guild::treasury(&guild),
+            pda::guild::proposal(&guild, 0),
+            pda::guild::match_tag(0, 0),
+            pda::guild::match_tag(0, 1),
+            pda::guild::leaderboard(),
+            pda::match_result::match_account(0),
+        ];
+        addresses.extend(per_wallet(wallets, pda::guild::member));
+        addresses.extend(per_wallet(wallets, |w| pda::guild::invite(&guild, w)));
+        addresses
+    },
+};
+
+pub const LEAGUE: Target = Target {
+    program_id: league::ID,
+    instructions: &["create_league", "record_result", "migrate_account"],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let league = pda::league::league(&wallets[0], 0);
+        vec![
+            league,
+            pda::league::standings(&league),
+            pda::match_result::match_account(0),
+        ]
+    },
+};
+
+pub const MATCH_RESULT: Target = Target {
+    program_id: match_result::ID,
+    instructions: &[
+        "record_match",
+        "record_team_match",
+        "open_set",
+        "record_set_game",
+        "finalize_set_if_expired",
+        "open_crew_battle",
+        "record_crew_game",
+        "finalize_crew_battle_if_expired",
+        "initialize_attestation_config",
+        "set_attestation_servers",
+        "record_match_attested",
+        "report_disconnect",
+        "mark_rated",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let mut addresses = vec![
+            pda::match_result::match_account(0),
+            pda::match_result::match_account(1),
+            pda::match_result::crew_battle(0),
+            pda::match_result::attestation_config(),
+            pda::match_result::event_authority(),
+            pda::ranking::rater(),
+        ];
+        addresses.extend(per_wallet(wallets, pda::match_result::disconnects));
+        addresses
+    },
+};
+
+pub const MATCHMAKING: Target = Target {
+    program_id: matchmaking::ID,
+    instructions: &[
+        "initialize_queue",
+        "initialize_ranked_config",
+        "set_ranked_requirements",
+        "register_player",
+        "record_unranked_match",
+        "join_queue",
+        "withdraw_stake",
+        "leave_queue",
+        "pair_players",
+        "commit_loadout",
+        "reveal_loadout",
+        "fulfill_randomness",
+        "claim_reveal_timeout",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let mut addresses = vec![
+            pda::matchmaking::queue(),
+            pda::matchmaking::ranked_config(),
+            pda::matchmaking::pending_match(0),
+            pda::matchmaking::pending_match(1),
+        ];
+        addresses.extend(per_wallet(wallets, pda::matchmaking::registration));
+        addresses.extend(per_wallet(wallets, pda::ranking::rating));
+        addresses
+    },
+};
+
+pub const MOVE_REGISTRY: Target = Target {
+    program_id: move_registry::ID,
+    instructions: &[
+        "initialize_registry",
+        "register_character",
+        "set_move",
+        "remove_move",
+        "set_definition_hash",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::MoveRegistry,
+    addresses: |_| {
+        vec![
+            pda::move_registry::registry(),
+            pda::move_registry::frame_data(fixtures::CHARACTER),
+            pda::move_registry::frame_data(fixtures::CHARACTER + 1),
+        ]
+    },
+};
+
+pub const PROFILE: Target = Target {
+    program_id: profile::ID,
+    instructions: &[
+        "initialize",
+        "add_writer",
+        "remove_writer",
+        "create_profile",
+        "update_profile",
+        "record_match",
+        "record_badge",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let mut addresses = vec![
+            pda::profile::config(),
+            pda::profile::writer(&ranking::ID),
+            pda::profile::writer(&achievements::ID),
+        ];
+        addresses.extend(per_wallet(wallets, pda::profile::profile));
+        addresses
+    },
+};
+
+pub const RANKING: Target = Target {
+    program_id: ranking::ID,
+    instructions: &[
+        "initialize_config",
+        "set_settings",
+        "set_oracle",
+        "publish_snapshot",
+        "claim_rank",
+        "initialize_rating",
+        "apply_match_result",
+        "apply_decay",
+        "award_streak_badge",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::Ranking,
+    addresses: |wallets| {
+        let mut addresses = vec![
+            pda::ranking::config(),
+            pda::ranking::rater(),
+            pda::ranking::snapshot(0),
+            pda::ranking::snapshot(1),
+            pda::match_result::match_account(0),
+            pda::achievements::config(),
+            pda::achievements::achievement(WIN_STREAK),
+            pda::achievements::issuer(&ranking::ID),
+            pda::profile::config(),
+            pda::profile::writer(&ranking::ID),
+            pda::profile::writer(&achievements::ID),
+        ];
+        addresses.extend(per_wallet(wallets, pda::ranking::rating));
+        addresses.extend(per_wallet(wallets, pda::profile::profile));
+        addresses.extend(per_wallet(wallets, |w| pda::ranking::rank_claim(1, w)));
+        addresses.extend(per_wallet(wallets, |w| {
+            pda::achievements::badge(WIN_STREAK, w)
+        }));
+        addresses
+    },
+};
+
+pub const REFERRAL: Target = Target {
+    program_id: referral::ID,
+    instructions: &[
+        "initialize",
+        "set_rates",
+        "create_code",
+        "register_referral",
+        "pay_referral",
+        "claim",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let mut addresses = vec![pda::referral::config(), pda::referral::code(FUZZ_NAME)];
+        addresses.extend(per_wallet(wallets, pda::referral::referral));
+        addresses
+    },
+};
+
+pub const REPLAY_REGISTRY: Target = Target {
+    program_id: replay_registry::ID,
+    instructions: &["finalize_replay", "migrate_account"],
+    fixtures: Fixtures::None,
+    addresses: |_| {
+        vec![
+            pda::replay_registry::replay(0),
+            pda::replay_registry::replay(1),
+        ]
+    },
+};
+
+pub const REWARDS: Target = Target {
+    program_id: rewards::ID,
+    instructions: &["create_distributor", "claim", "migrate_account"],
+    fixtures: Fixtures::None,
+    addresses: |_| {
+        let distributor = pda::rewards::distributor(0);
+        vec![distributor, pda::rewards::vault(&distributor)]
+    },
+};
+
+pub const SEASON: Target = Target {
+    program_id: season::ID,
+    instructions: &[
+        "initialize",
+        "start_season",
+        "join_season",
+        "record_match",
+        "record_combo",
+        "end_season",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let mut addresses = vec![
+            pda::season::state(),
+            pda::season::season(0),
+            pda::season::season(1),
+            pda::season::archive(1),
+            pda::match_result::match_account(0),
+        ];
+        addresses.extend(per_wallet(wallets, |w| pda::season::standing(1, w)));
+        addresses.extend(per_wallet(wallets, |w| pda::season::entry(1, w)));
+        addresses
+    },
+};
+
+pub const STAKING: Target = Target {
+    program_id: staking::ID,
+    instructions: &[
+        "initialize",
+        "set_emission_curve",
+        "stake_combo",
+        "stake_combo_nft",
+        "refresh_weight",
+        "claim_rewards",
+        "request_unstake",
+        "unstake_combo",
+        "unstake_combo_nft",
+        "slash",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let combo = pda::combo_mint::combo(&wallets[1], FUZZ_NAME);
+        let position = pda::staking::position(&combo);
+        vec![
+            pda::staking::pool(),
+            pda::staking::emission_curve(),
+            pda::staking::vault(),
+            combo,
+            position,
+            pda::staking::nft_vault(&position),
+        ]
+    },
+};
+
+pub const TOURNAMENT: Target = Target {
+    program_id: tournament::ID,
+    instructions: &[
+        "initialize_config",
+        "set_max_organizer_fee",
+        "create_tournament",
+        "register",
+        "report_result",
+        "pair_swiss_round",
+        "finish_swiss",
+        "award_champion_badge",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let tournament = pda::tournament::tournament(&wallets[0], 0);
+        vec![
+            pda::tournament::config(),
+            tournament,
+            pda::tournament::vault(&tournament),
+            pda::match_result::match_account(0),
+        ]
+    },
+};
+
+pub const WAGER: Target = Target {
+    program_id: wager::ID,
+    instructions: &[
+        "create_wager",
+        "accept_wager",
+        "claim_pot",
+        "cancel_wager",
+        "finalize_if_expired",
+        "migrate_account",
+    ],
+    fixtures: Fixtures::None,
+    addresses: |wallets| {
+        let wager = pda::wager::wager(0, &wallets[1], &wallets[2]);
+        vec![
+            wager,
+            pda::wager::escrow(&wager),
+            pda::match_result::match_account(0),
+        ]
+    },
+};
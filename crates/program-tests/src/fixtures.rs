@@ -0,0 +1,487 @@
+//! State most tests start from, built through the programs' own
+//! instructions: a registered character with a known moveset, configured
+//! combo, ranking, profile, dispute and referral programs, pre-created combos,
+//! recorded matches and open sets.
+//! The harness payer is the admin throughout.
+
+use combo_mint_client::achievements::WIN_STREAK;
+use combo_mint_client::builders::{self, NewCombo};
+use combo_mint_client::combo_mint::combo_sim::simulate_combo;
+use combo_mint_client::combo_mint::ValidationLimits;
+use combo_mint_client::move_registry::{CharacterFrameData, MoveFrameData};
+use combo_mint_client::ranking::{RankingSettings, K_FACTOR};
+use combo_mint_client::{
+    achievements, combo_mint, dispute, instruction, match_result, move_registry, pda, profile,
+    ranking, referral, season,
+};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+use crate::Harness;
+
+pub const CHARACTER: u8 = 1;
+pub const JAB: u8 = 1;
+pub const HEAVY: u8 = 2;
+pub const LAUNCHER: u8 = 3;
+/// Route of the combos `combo` creates.
+pub const ROUTE: [u8; 3] = [JAB, JAB, HEAVY];
+/// Seconds after recording during which `dispute` lets a match be disputed.
+pub const CHALLENGE_WINDOW: i64 = 60 * 60;
+/// Smallest stake `dispute` accepts.
+pub const MIN_STAKE: u64 = 100;
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// The moves `move_registry` registers for `CHARACTER`.
+pub fn moveset() -> Vec<MoveFrameData> {
+    vec![
+        MoveFrameData {
+            move_id: JAB,
+            startup: 3,
+            active: 2,
+            recovery: 6,
+            base_damage: 100,
+            meter_gain: 5,
+            launch: 0,
+            juggle_cost: 1,
+            hitstun: 12,
+            motion: 0,
+        },
+        MoveFrameData {
+            move_id: HEAVY,
+            startup: 8,
+            active: 3,
+            recovery: 14,
+            base_damage: 250,
+            meter_gain: 12,
+            launch: 0,
+            juggle_cost: 2,
+            hitstun: 20,
+            motion: 0,
+        },
+        MoveFrameData {
+            move_id: LAUNCHER,
+            startup: 6,
+            active: 3,
+            recovery: 18,
+            base_damage: 180,
+            meter_gain: 10,
+            launch: 12 << 16,
+            juggle_cost: 3,
+            hitstun: 24,
+            motion: 0,
+        },
+    ]
+}
+
+/// `CHARACTER`'s frame data as `move_registry` leaves it, for computing
+/// what the programs will derive from a route.
+pub fn frame_data() -> CharacterFrameData {
+    CharacterFrameData {
+        layout_version: 2,
+        character_id: CHARACTER,
+        moves: moveset(),
+        definition_revision: 0,
+        definition_hash: [0; 32],
+        bump: 0,
+    }
+}
+
+pub fn limits() -> ValidationLimits {
+    ValidationLimits {
+        max_damage: 10_000,
+        max_meter_gain: 100,
+        max_moves: 20,
+        max_name_len: 32,
+    }
+}
+
+/// Decay after 30 idle days, 25 points a week down to 1000, and no
+/// placement matches.
+pub fn ranking_settings() -> RankingSettings {
+    RankingSettings {
+        decay_after: 30 * DAY,
+        decay_period: 7 * DAY,
+        decay_per_period: 25,
+        decay_floor: 1000,
+        placement_games: 0,
+        placement_k_factor: K_FACTOR,
+        tier_floors: [1100, 1300, 1500, 1700, 1900],
+    }
+}
+
+pub fn set_move(admin: Pubkey, character_id: u8, move_data: MoveFrameData) -> Instruction {
+    instruction(
+        move_registry::ID,
+        move_registry::accounts::UpdateMoves {
+            admin,
+            registry: pda::move_registry::registry(),
+            frame_data: pda::move_registry::frame_data(character_id),
+        },
+        move_registry::instruction::SetMove { move_data },
+    )
+}
+
+/// The move registry with `CHARACTER` registered and given `moveset`.
+pub async fn move_registry(h: &mut Harness) {
+    let admin = h.admin();
+    let mut ixs = vec![
+        instruction(
+            move_registry::ID,
+            move_registry::accounts::InitializeRegistry {
+                admin,
+                registry: pda::move_registry::registry(),
+                system_program: system_program::ID,
+            },
+            move_registry::instruction::InitializeRegistry {},
+        ),
+        instruction(
+            move_registry::ID,
+            move_registry::accounts::RegisterCharacter {
+                admin,
+                registry: pda::move_registry::registry(),
+                frame_data: pda::move_registry::frame_data(CHARACTER),
+                system_program: system_program::ID,
+            },
+            move_registry::instruction::RegisterCharacter {
+                character_id: CHARACTER,
+            },
+        ),
+    ];
+    ixs.extend(
+        moveset()
+            .into_iter()
+            .map(|move_data| set_move(admin, CHARACTER, move_data)),
+    );
+    h.send(&ixs, &[]).await.unwrap();
+}
+
+/// `move_registry`, plus the combo program's config with `limits` and the
+/// admin as its only limit signer, an empty name filter and an empty
+/// verifier registry.
+pub async fn combo_mint(h: &mut Harness) {
+    move_registry(h).await;
+    let admin = h.admin();
+    let config = pda::combo_mint::config();
+    let ixs = [
+        instruction(
+            combo_mint::ID,
+            combo_mint::accounts::InitializeConfig {
+                admin,
+                config,
+                system_program: system_program::ID,
+            },
+            combo_mint::instruction::InitializeConfig {
+                limits: limits(),
+                signers: vec![admin],
+                threshold: 1,
+            },
+        ),
+        instruction(
+            combo_mint::ID,
+            combo_mint::accounts::InitializeNameFilter {
+                admin,
+                config,
+                name_filter: pda::combo_mint::name_filter(),
+                system_program: system_program::ID,
+            },
+            combo_mint::instruction::InitializeNameFilter {},
+        ),
+        instruction(
+            combo_mint::ID,
+            combo_mint::accounts::InitializeVerifierRegistry {
+                admin,
+                config,
+                verifier_registry: pda::combo_mint::verifier_registry(),
+                system_program: system_program::ID,
+            },
+            combo_mint::instruction::InitializeVerifierRegistry {},
+        ),
+    ];
+    h.send(&ixs, &[]).await.unwrap();
+}
+
+/// A `CHARACTER` combo called `name` along `route`, claiming the damage the
+/// route deals.
+pub fn new_combo(name: &str, route: &[u8]) -> NewCombo {
+    let damage = simulate_combo(&frame_data(), route).unwrap().damage;
+    NewCombo {
+        name: name.to_string(),
+        damage,
+        move_count: route.len() as u8,
+        character_id: CHARACTER,
+        moves: route.to_vec(),
+        royalty_bps: 0,
+        tags: 0,
+    }
+}
+
+pub fn create_combo(authority: Pubkey, combo: NewCombo) -> Instruction {
+    let combo_hash = builders::new_combo_hash(&frame_data(), &combo).unwrap();
+    builders::create_combo(authority, combo, &combo_hash)
+}
+
+/// Creates `authority`'s combo `name` along `ROUTE`, returning its address.
+/// Needs `combo_mint`.
+pub async fn combo(h: &mut Harness, authority: &Keypair, name: &str) -> Pubkey {
+    let ix = create_combo(authority.pubkey(), new_combo(name, &ROUTE));
+    h.send(&[ix], &[authority]).await.unwrap();
+    pda::combo_mint::combo(&authority.pubkey(), name)
+}
+
+/// Records a singles match both players sign, won 2-1 by
+/// `players[winner]`, returning the match account.
+pub async fn recorded_match(
+    h: &mut Harness,
+    match_id: u64,
+    players: [&Keypair; 2],
+    winner: u8,
+) -> Pubkey {
+    let mut score = [1; 2];
+    score[winner as usize] = 2;
+    let ix = builders::record_match(
+        [players[0].pubkey(), players[1].pubkey()],
+        match_id,
+        winner,
+        score,
+        [7; 32],
+        3_600,
+        [0; 32],
+    );
+    h.send(&[ix], &[players[0], players[1]]).await.unwrap();
+    pda::match_result::match_account(match_id)
+}
+
+/// Opens a best-of-three set between the players with no games recorded,
+/// so the match exists but has no final result, returning its account.
+pub async fn open_set(h: &mut Harness, match_id: u64, players: [&Keypair; 2]) -> Pubkey {
+    let match_account = pda::match_result::match_account(match_id);
+    let ix = instruction(
+        match_result::ID,
+        match_result::accounts::RecordMatch {
+            player_one: players[0].pubkey(),
+            player_two: players[1].pubkey(),
+            match_account,
+            system_program: system_program::ID,
+            event_authority: pda::match_result::event_authority(),
+            program: match_result::ID,
+        },
+        match_result::instruction::OpenSet {
+            match_id,
+            best_of: 3,
+        },
+    );
+    h.send(&[ix], &[players[0], players[1]]).await.unwrap();
+    match_account
+}
+
+/// The profile config, with the ranking and achievements programs as
+/// writers.
+pub async fn profile(h: &mut Harness) {
+    let admin = h.admin();
+    let mut ixs = vec![instruction(
+        profile::ID,
+        profile::accounts::Initialize {
+            admin,
+            config: pda::profile::config(),
+            system_program: system_program::ID,
+        },
+        profile::instruction::Initialize {},
+    )];
+    for program in [ranking::ID, achievements::ID] {
+        ixs.push(instruction(
+            profile::ID,
+            profile::accounts::UpdateWriters {
+                admin,
+                config: pda::profile::config(),
+            },
+            profile::instruction::AddWriter { program },
+        ));
+    }
+    h.send(&ixs, &[]).await.unwrap();
+}
+
+/// Creates `player`'s profile, returning its address. Needs `profile`.
+pub async fn player_profile(h: &mut Harness, player: &Keypair) -> Pubkey {
+    let ix = instruction(
+        profile::ID,
+        profile::accounts::CreateProfile {
+            owner: player.pubkey(),
+            profile: pda::profile::profile(&player.pubkey()),
+            system_program: system_program::ID,
+        },
+        profile::instruction::CreateProfile {
+            display_name: "Player".to_string(),
+            main_character: CHARACTER,
+        },
+    );
+    h.send(&[ix], &[player]).await.unwrap();
+    pda::profile::profile(&player.pubkey())
+}
+
+/// The season state, with no season running, the ranking config with
+/// `ranking_settings`, and `profile`.
+pub async fn ranking(h: &mut Harness) {
+    profile(h).await;
+    let admin = h.admin();
+    let ixs = [
+        instruction(
+            season::ID,
+            season::accounts::Initialize {
+                admin,
+                state: pda::season::state(),
+                system_program: system_program::ID,
+            },
+            season::instruction::Initialize {},
+        ),
+        instruction(
+            ranking::ID,
+            ranking::accounts::InitializeConfig {
+                admin,
+                config: pda::ranking::config(),
+                system_program: system_program::ID,
+            },
+            ranking::instruction::InitializeConfig {
+                settings: ranking_settings(),
+            },
+        ),
+    ];
+    h.send(&ixs, &[]).await.unwrap();
+}
+
+/// Creates `player`'s rating at `INITIAL_RATING`, returning its address.
+pub async fn rating(h: &mut Harness, player: &Keypair) -> Pubkey {
+    let rating = pda::ranking::rating(&player.pubkey());
+    let ix = instruction(
+        ranking::ID,
+        ranking::accounts::InitializeRating {
+            player: player.pubkey(),
+            rating,
+            system_program: system_program::ID,
+        },
+        ranking::instruction::InitializeRating {},
+    );
+    h.send(&[ix], &[player]).await.unwrap();
+    rating
+}
+
+/// A funded player with a rating, for ranked tests.
+pub async fn rated_player(h: &mut Harness) -> Keypair {
+    let player = h.funded_player().await;
+    rating(h, &player).await;
+    player
+}
+
+/// `ranking::apply_match_result` for `match_id`, won by `winner` against
+/// `loser`.
+pub async fn apply_result(h: &mut Harness, match_id: u64, winner: &Keypair, loser: &Keypair) {
+    let ix = builders::apply_match_result(match_id, [winner.pubkey(), loser.pubkey()]);
+    h.send(&[ix], &[]).await.unwrap();
+}
+
+pub fn define_achievement(
+    admin: Pubkey,
+    achievement_id: u16,
+    name: &str,
+    issuer_program: Pubkey,
+) -> Instruction {
+    instruction(
+        achievements::ID,
+        achievements::accounts::DefineAchievement {
+            admin,
+            config: pda::achievements::config(),
+            achievement: pda::achievements::achievement(achievement_id),
+            system_program: system_program::ID,
+        },
+        achievements::instruction::DefineAchievement {
+            achievement_id,
+            name: name.to_string(),
+            issuer_program,
+        },
+    )
+}
+
+/// The achievements config, with `WIN_STREAK` defined for the ranking
+/// program to award.
+pub async fn achievements(h: &mut Harness) {
+    let admin = h.admin();
+    let ixs = [
+        instruction(
+            achievements::ID,
+            achievements::accounts::Initialize {
+                admin,
+                config: pda::achievements::config(),
+                system_program: system_program::ID,
+            },
+            achievements::instruction::Initialize {},
+        ),
+        define_achievement(admin, WIN_STREAK, "Win streak", ranking::ID),
+    ];
+    h.send(&ixs, &[]).await.unwrap();
+}
+
+/// The dispute config staking `mint`, with `arbiters` and `quorum`,
+/// `CHALLENGE_WINDOW` and `MIN_STAKE`.
+pub async fn dispute(h: &mut Harness, mint: &Pubkey, arbiters: Vec<Pubkey>, quorum: u8) {
+    let ix = instruction(
+        dispute::ID,
+        dispute::accounts::Initialize {
+            admin: h.admin(),
+            config: pda::dispute::config(),
+            mint: *mint,
+            system_program: system_program::ID,
+        },
+        dispute::instruction::Initialize {
+            arbiters,
+            quorum,
+            challenge_window: CHALLENGE_WINDOW,
+            min_stake: MIN_STAKE,
+        },
+    );
+    h.send(&[ix], &[]).await.unwrap();
+}
+
+/// The referral config, paying referrers `entry_fee_bps` of entry fees.
+pub async fn referral(h: &mut Harness, entry_fee_bps: u16) {
+    let ix = instruction(
+        referral::ID,
+        referral::accounts::Initialize {
+            admin: h.admin(),
+            config: pda::referral::config(),
+            system_program: system_program::ID,
+        },
+        referral::instruction::Initialize { entry_fee_bps },
+    );
+    h.send(&[ix], &[]).await.unwrap();
+}
+
+pub fn create_code(owner: Pubkey, code: &str) -> Instruction {
+    instruction(
+        referral::ID,
+        referral::accounts::CreateCode {
+            owner,
+            referral_code: pda::referral::code(code),
+            system_program: system_program::ID,
+        },
+        referral::instruction::CreateCode {
+            code: code.to_string(),
+        },
+    )
+}
+
+/// `player` registering as referred by `referrer`, the owner of `code`.
+pub fn register_referral(player: Pubkey, code: &str, referrer: Pubkey) -> Instruction {
+    instruction(
+        referral::ID,
+        referral::accounts::RegisterReferral {
+            player,
+            referral_code: pda::referral::code(code),
+            referral: pda::referral::referral(&player),
+            referrer_referral: pda::referral::referral(&referrer),
+            system_program: system_program::ID,
+        },
+        referral::instruction::RegisterReferral {},
+    )
+}
@@ -0,0 +1,330 @@
+//! Integration-test harness for the on-chain programs, running them in
+//! `solana-program-test`'s in-process bank so the suite needs no devnet
+//! deploy.
+//!
+//! The bank runs the programs' SBF builds, so build them before testing:
+//!
+//! ```text
+//! for program in programs/*/; do
+//!     cargo build-sbf --manifest-path "$program/Cargo.toml" --sbf-out-dir target/deploy
+//! done
+//! SBF_OUT_DIR=$PWD/target/deploy cargo test --manifest-path crates/program-tests/Cargo.toml
+//! ```
+//!
+//! `Harness::start` loads every program into a fresh bank. The bank's payer
+//! is the admin of everything `fixtures` configures, and `funded_player`
+//! hands out other wallets. A program with a test file under `tests/` gets a
+//! happy path per instruction and a case for each custom error the
+//! instruction can reach; programs without one yet are still loaded, so a
+//! new file only needs its fixtures.
+//!
+//! `fuzz/` holds cargo-fuzz targets that run arbitrary instructions through
+//! the same harness.
+
+pub mod fixtures;
+
+use anchor_lang::{AccountDeserialize, AccountSerialize};
+use anchor_spl::token::spl_token;
+use anchor_spl::token::spl_token::state::{Account as TokenAccount, AccountState, Mint};
+use anchor_spl::token_2022::spl_token_2022;
+use anchor_spl::token_2022::spl_token_2022::extension::default_account_state::DefaultAccountState;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::{
+    TransferFee, TransferFeeConfig,
+};
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    BaseStateWithExtensions, ExtensionType, StateWithExtensions, StateWithExtensionsMut,
+};
+use anchor_spl::token_2022::spl_token_2022::state::{Account as Account2022, Mint as Mint2022};
+use combo_mint_client::*;
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::{Account, AccountSharedData};
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::program_option::COption;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::{Transaction, TransactionError};
+
+/// Every program, by the name of its SBF build.
+pub const PROGRAMS: &[(&str, Pubkey)] = &[
+    ("achievements", achievements::ID),
+    ("betting", betting::ID),
+    ("combo_mint", combo_mint::ID),
+    ("dispute", dispute::ID),
+    ("guild", guild::ID),
+    ("league", league::ID),
+    ("match_result", match_result::ID),
+    ("matchmaking", matchmaking::ID),
+    ("move_registry", move_registry::ID),
+    ("profile", profile::ID),
+    ("ranking", ranking::ID),
+    ("referral", referral::ID),
+    ("replay_registry", replay_registry::ID),
+    ("rewards", rewards::ID),
+    ("season", season::ID),
+    ("staking", staking::ID),
+    ("tournament", tournament::ID),
+    ("wager", wager::ID),
+];
+
+/// Lamports `funded_player` gives each new wallet.
+pub const PLAYER_FUNDS: u64 = 10 * LAMPORTS_PER_SOL;
+
+pub type TxResult = Result<(), BanksClientError>;
+
+pub struct Harness {
+    pub ctx: ProgramTestContext,
+}
+
+impl Harness {
+    pub async fn start() -> Self {
+        let mut test = ProgramTest::default();
+        test.prefer_bpf(true);
+        for (name, id) in PROGRAMS {
+            test.add_program(name, *id, None);
+        }
+        Harness {
+            ctx: test.start_with_context().await,
+        }
+    }
+
+    /// The bank's payer, which pays every transaction's fee.
+    pub fn admin(&self) -> Pubkey {
+        self.ctx.payer.pubkey()
+    }
+
+    /// A new wallet holding `PLAYER_FUNDS`.
+    pub async fn funded_player(&mut self) -> Keypair {
+        let player = Keypair::new();
+        let transfer =
+            system_instruction::transfer(&self.ctx.payer.pubkey(), &player.pubkey(), PLAYER_FUNDS);
+        self.send(&[transfer], &[]).await.unwrap();
+        player
+    }
+
+    /// Sends `instructions` in one transaction the payer pays for, signed
+    /// by the payer and `signers`.
+    pub async fn send(&mut self, instructions: &[Instruction], signers: &[&Keypair]) -> TxResult {
+        // A fresh blockhash per transaction, so sending the same
+        // instructions twice is two transactions rather than a duplicate.
+        let blockhash = self.ctx.get_new_latest_blockhash().await.unwrap();
+        let mut all_signers = vec![&self.ctx.payer];
+        all_signers.extend_from_slice(signers);
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.ctx.payer.pubkey()),
+            &all_signers,
+            blockhash,
+        );
+        self.ctx.banks_client.process_transaction(transaction).await
+    }
+
+    /// Decodes the account at `address`, panicking if it doesn't exist.
+    pub async fn account<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+        let account = self
+            .raw_account(address)
+            .await
+            .unwrap_or_else(|| panic!("account {} not found", address));
+        decode(&account.data).unwrap()
+    }
+
+    pub async fn raw_account(&mut self, address: &Pubkey) -> Option<Account> {
+        self.ctx.banks_client.get_account(*address).await.unwrap()
+    }
+
+    pub async fn exists(&mut self, address: &Pubkey) -> bool {
+        self.raw_account(address).await.is_some()
+    }
+
+    pub async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.raw_account(address)
+            .await
+            .map_or(0, |account| account.lamports)
+    }
+
+    /// Writes `value` straight into an account of `owner`, rent-exempt at
+    /// `space` bytes, bypassing the programs. For state no instruction can
+    /// produce any more, such as accounts on an old layout.
+    pub fn put_account<T: AccountSerialize>(
+        &mut self,
+        address: &Pubkey,
+        owner: &Pubkey,
+        value: &T,
+        space: usize,
+    ) {
+        let mut data = Vec::with_capacity(space);
+        value.try_serialize(&mut data).unwrap();
+        data.resize(space, 0);
+        self.put_raw_account(address, owner, data);
+    }
+
+    pub fn put_raw_account(&mut self, address: &Pubkey, owner: &Pubkey, data: Vec<u8>) {
+        let account = Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: *owner,
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.ctx
+            .set_account(address, &AccountSharedData::from(account));
+    }
+
+    /// Writes an SPL Token mint with no decimals and `authority` as its mint
+    /// authority, returning its address.
+    pub fn put_mint(&mut self, authority: &Pubkey) -> Pubkey {
+        let mut data = vec![0; Mint::LEN];
+        new_mint(authority).pack_into_slice(&mut data);
+        let address = Pubkey::new_unique();
+        self.put_raw_account(&address, &spl_token::ID, data);
+        address
+    }
+
+    /// Writes a Token-2022 mint like `put_mint`'s that withholds `bps` of
+    /// every transfer as a fee.
+    pub fn put_fee_mint(&mut self, authority: &Pubkey, bps: u16) -> Pubkey {
+        self.put_mint_2022(authority, ExtensionType::TransferFeeConfig, |state| {
+            let fee = TransferFee {
+                epoch: 0.into(),
+                maximum_fee: u64::MAX.into(),
+                transfer_fee_basis_points: bps.into(),
+            };
+            let config = state.init_extension::<TransferFeeConfig>(true).unwrap();
+            config.older_transfer_fee = fee;
+            config.newer_transfer_fee = fee;
+        })
+    }
+
+    /// Writes a Token-2022 mint like `put_mint`'s whose new accounts start
+    /// frozen, one of the extensions `token_compat::supported_mint`
+    /// refuses.
+    pub fn put_unsupported_mint(&mut self, authority: &Pubkey) -> Pubkey {
+        self.put_mint_2022(authority, ExtensionType::DefaultAccountState, |state| {
+            let default = state.init_extension::<DefaultAccountState>(true).unwrap();
+            default.state = AccountState::Frozen as u8;
+        })
+    }
+
+    fn put_mint_2022(
+        &mut self,
+        authority: &Pubkey,
+        extension: ExtensionType,
+        init: impl FnOnce(&mut StateWithExtensionsMut<Mint2022>),
+    ) -> Pubkey {
+        let len = ExtensionType::try_calculate_account_len::<Mint2022>(&[extension]).unwrap();
+        let mut data = vec![0; len];
+        let mut state =
+            StateWithExtensionsMut::<Mint2022>::unpack_uninitialized(&mut data).unwrap();
+        init(&mut state);
+        state.init_account_type().unwrap();
+        // The base state has the same layout in both token programs.
+        new_mint(authority).pack_into_slice(&mut data[..Mint::LEN]);
+        let address = Pubkey::new_unique();
+        self.put_raw_account(&address, &spl_token_2022::ID, data);
+        address
+    }
+
+    /// Writes a token account of `mint` for `owner` holding `amount`, with
+    /// the extensions a Token-2022 mint needs its accounts to have, adding
+    /// the amount to the mint's supply, and returns its address.
+    pub async fn put_token_account(
+        &mut self,
+        mint: &Pubkey,
+        owner: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        let Account {
+            data: mut mint_data,
+            owner: token_program,
+            ..
+        } = self.raw_account(mint).await.unwrap();
+        let mut mint_state = Mint::unpack_from_slice(&mint_data[..Mint::LEN]).unwrap();
+        mint_state.supply += amount;
+        mint_state.pack_into_slice(&mut mint_data[..Mint::LEN]);
+
+        let mut data = vec![0; TokenAccount::LEN];
+        if token_program == spl_token_2022::ID {
+            let mint_extensions = StateWithExtensions::<Mint2022>::unpack(&mint_data)
+                .unwrap()
+                .get_extension_types()
+                .unwrap();
+            let extensions = ExtensionType::get_required_init_account_extensions(&mint_extensions);
+            let len = ExtensionType::try_calculate_account_len::<Account2022>(&extensions).unwrap();
+            data = vec![0; len];
+            let mut state =
+                StateWithExtensionsMut::<Account2022>::unpack_uninitialized(&mut data).unwrap();
+            for extension in extensions {
+                state.init_account_extension_from_type(extension).unwrap();
+            }
+            state.init_account_type().unwrap();
+        }
+        self.put_raw_account(mint, &token_program, mint_data);
+
+        let account = TokenAccount {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        };
+        account.pack_into_slice(&mut data[..TokenAccount::LEN]);
+        let address = Pubkey::new_unique();
+        self.put_raw_account(&address, &token_program, data);
+        address
+    }
+
+    /// The balance of the token account at `address`, zero if it's closed.
+    pub async fn token_balance(&mut self, address: &Pubkey) -> u64 {
+        match self.raw_account(address).await {
+            Some(account) => {
+                TokenAccount::unpack_from_slice(&account.data[..TokenAccount::LEN])
+                    .unwrap()
+                    .amount
+            }
+            None => 0,
+        }
+    }
+
+    pub async fn now(&mut self) -> i64 {
+        self.clock().await.unix_timestamp
+    }
+
+    /// Moves the bank's clock `seconds` forward.
+    pub async fn advance(&mut self, seconds: i64) {
+        let mut clock = self.clock().await;
+        clock.unix_timestamp += seconds;
+        self.ctx.set_sysvar(&clock);
+    }
+
+    async fn clock(&mut self) -> Clock {
+        self.ctx.banks_client.get_sysvar::<Clock>().await.unwrap()
+    }
+}
+
+/// Asserts the transaction failed with `expected`, one of a program's
+/// `#[error_code]` errors or an Anchor `ErrorCode`.
+#[track_caller]
+pub fn assert_error(result: TxResult, expected: impl Into<u32>) {
+    let expected = expected.into();
+    match result.map_err(|err| err.unwrap()) {
+        Err(TransactionError::InstructionError(_, InstructionError::Custom(code))) => {
+            assert_eq!(code, expected, "wrong error code")
+        }
+        other => panic!("expected error code {}, got {:?}", expected, other),
+    }
+}
+
+/// A mint with no decimals, supply or freeze authority.
+fn new_mint(authority: &Pubkey) -> Mint {
+    Mint {
+        mint_authority: COption::Some(*authority),
+        supply: 0,
+        decimals: 0,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }
+}
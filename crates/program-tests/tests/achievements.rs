@@ -0,0 +1,98 @@
+use anchor_lang::error::ErrorCode;
+use combo_mint_client::achievements::{
+    Achievement, AchievementError, AchievementsConfig, WIN_STREAK,
+};
+use combo_mint_client::{achievements, instruction, pda, ranking};
+use program_tests::fixtures;
+use program_tests::{assert_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `award_badge` has to be signed by the issuer program's PDA, so its happy
+// path runs through `ranking::award_streak_badge` in `ranking.rs`.
+// `AchievementError::Overflow` needs 2^32 badges of one achievement.
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        achievements::ID,
+        achievements::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        achievements::instruction::MigrateAccount {},
+    )
+}
+
+#[tokio::test]
+async fn the_admin_defines_achievements() {
+    let mut h = Harness::start().await;
+    fixtures::achievements(&mut h).await;
+
+    let config: AchievementsConfig = h.account(&pda::achievements::config()).await;
+    assert_eq!(config.admin, h.admin());
+    let achievement: Achievement = h.account(&pda::achievements::achievement(WIN_STREAK)).await;
+    assert_eq!(achievement.name, "Win streak");
+    assert_eq!(achievement.issuer_program, ranking::ID);
+    assert_eq!(achievement.awarded_count, 0);
+}
+
+#[tokio::test]
+async fn only_the_admin_defines_achievements() {
+    let mut h = Harness::start().await;
+    fixtures::achievements(&mut h).await;
+    let intruder = h.funded_player().await;
+
+    let ix = fixtures::define_achievement(intruder.pubkey(), 9, "Mine", intruder.pubkey());
+    assert_error(
+        h.send(&[ix], &[&intruder]).await,
+        ErrorCode::ConstraintHasOne,
+    );
+}
+
+#[tokio::test]
+async fn caps_achievement_names() {
+    let mut h = Harness::start().await;
+    fixtures::achievements(&mut h).await;
+    let admin = h.admin();
+
+    let longest = "a".repeat(Achievement::MAX_NAME_LEN);
+    let ix = fixtures::define_achievement(admin, 9, &longest, ranking::ID);
+    h.send(&[ix], &[]).await.unwrap();
+
+    let too_long = "a".repeat(Achievement::MAX_NAME_LEN + 1);
+    let ix = fixtures::define_achievement(admin, 10, &too_long, ranking::ID);
+    assert_error(h.send(&[ix], &[]).await, AchievementError::NameTooLong);
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    fixtures::achievements(&mut h).await;
+    let admin = h.admin();
+
+    for account in [
+        pda::achievements::config(),
+        pda::achievements::achievement(WIN_STREAK),
+    ] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            AchievementError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &achievements::ID, vec![9; Achievement::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(
+        h.send(&[ix], &[]).await,
+        AchievementError::UnsupportedLayout,
+    );
+}
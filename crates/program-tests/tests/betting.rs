@@ -0,0 +1,547 @@
+use anchor_spl::token::spl_token;
+use combo_mint_client::betting::{
+    payout, Bet, BettingConfig, BettingError, Market, MarketStatus, MAX_FEE_BPS, RESULT_TIMEOUT,
+};
+use combo_mint_client::dispute::RESPONSE_WINDOW;
+use combo_mint_client::{betting, dispute, instruction, pda};
+use program_tests::fixtures::{self, CHALLENGE_WINDOW, MIN_STAKE};
+use program_tests::{assert_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::{system_program, sysvar};
+
+// `BettingError::Overflow` needs more than `u64::MAX` tokens staked on one
+// side, so no test reaches it.
+
+const MATCH_ID: u64 = 1;
+const FEE_BPS: u16 = 500;
+const FUNDS: u64 = 10_000;
+/// Seconds from opening a market to its lock.
+const BETTING_TIME: i64 = 600;
+
+/// The betting config, with `FEE_BPS` paid to `treasury`, and a dispute
+/// committee over the same mint.
+struct Book {
+    mint: Pubkey,
+    treasury: Pubkey,
+}
+
+impl Book {
+    async fn new(h: &mut Harness) -> Self {
+        let admin = h.admin();
+        let mint = h.put_mint(&admin);
+        let treasury = h.put_token_account(&mint, &admin, 0).await;
+        h.send(&[initialize(admin, mint, treasury, FEE_BPS)], &[])
+            .await
+            .unwrap();
+        fixtures::dispute(h, &mint, vec![admin], 1).await;
+        Book { mint, treasury }
+    }
+
+    /// A funded spectator and their token account.
+    async fn bettor(&self, h: &mut Harness) -> (Keypair, Pubkey) {
+        let bettor = h.funded_player().await;
+        let tokens = h
+            .put_token_account(&self.mint, &bettor.pubkey(), FUNDS)
+            .await;
+        (bettor, tokens)
+    }
+
+    /// Opens the market on `MATCH_ID` between `players`, locking in
+    /// `BETTING_TIME`.
+    async fn open(&self, h: &mut Harness, players: [Pubkey; 2]) {
+        let lock_at = h.now().await + BETTING_TIME;
+        let ix = open_market(h.admin(), self.mint, players, lock_at);
+        h.send(&[ix], &[]).await.unwrap();
+    }
+
+    fn place_bet(&self, bettor: Pubkey, tokens: Pubkey, side: u8, amount: u64) -> Instruction {
+        let market = pda::betting::market(MATCH_ID);
+        instruction(
+            betting::ID,
+            betting::accounts::PlaceBet {
+                bettor,
+                market,
+                vault: pda::betting::market_vault(&market),
+                bet: pda::betting::bet(&market, &bettor),
+                mint: self.mint,
+                bettor_token_account: tokens,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            },
+            betting::instruction::PlaceBet { side, amount },
+        )
+    }
+
+    fn settle_market(&self) -> Instruction {
+        let market = pda::betting::market(MATCH_ID);
+        instruction(
+            betting::ID,
+            betting::accounts::SettleMarket {
+                config: pda::betting::config(),
+                market,
+                vault: pda::betting::market_vault(&market),
+                treasury: self.treasury,
+                mint: self.mint,
+                match_account: pda::match_result::match_account(MATCH_ID),
+                dispute_config: pda::dispute::config(),
+                dispute: pda::dispute::dispute(MATCH_ID),
+                token_program: spl_token::ID,
+            },
+            betting::instruction::SettleMarket {},
+        )
+    }
+
+    fn claim(&self, bettor: Pubkey, tokens: Pubkey) -> Instruction {
+        let market = pda::betting::market(MATCH_ID);
+        instruction(
+            betting::ID,
+            betting::accounts::Claim {
+                bettor,
+                market,
+                vault: pda::betting::market_vault(&market),
+                bet: pda::betting::bet(&market, &bettor),
+                mint: self.mint,
+                bettor_token_account: tokens,
+                token_program: spl_token::ID,
+            },
+            betting::instruction::Claim {},
+        )
+    }
+}
+
+fn initialize(admin: Pubkey, mint: Pubkey, treasury: Pubkey, fee_bps: u16) -> Instruction {
+    instruction(
+        betting::ID,
+        betting::accounts::Initialize {
+            admin,
+            config: pda::betting::config(),
+            mint,
+            treasury,
+            system_program: system_program::ID,
+        },
+        betting::instruction::Initialize { fee_bps },
+    )
+}
+
+fn set_fee(admin: Pubkey, fee_bps: u16) -> Instruction {
+    instruction(
+        betting::ID,
+        betting::accounts::UpdateConfig {
+            admin,
+            config: pda::betting::config(),
+        },
+        betting::instruction::SetFee { fee_bps },
+    )
+}
+
+fn open_market(admin: Pubkey, mint: Pubkey, players: [Pubkey; 2], lock_at: i64) -> Instruction {
+    let market = pda::betting::market(MATCH_ID);
+    instruction(
+        betting::ID,
+        betting::accounts::OpenMarket {
+            admin,
+            config: pda::betting::config(),
+            market,
+            vault: pda::betting::market_vault(&market),
+            mint,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        betting::instruction::OpenMarket {
+            match_id: MATCH_ID,
+            players,
+            lock_at,
+        },
+    )
+}
+
+fn cancel_market() -> Instruction {
+    instruction(
+        betting::ID,
+        betting::accounts::CancelMarket {
+            market: pda::betting::market(MATCH_ID),
+            match_account: pda::match_result::match_account(MATCH_ID),
+        },
+        betting::instruction::CancelMarket {},
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        betting::ID,
+        betting::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        betting::instruction::MigrateAccount {},
+    )
+}
+
+async fn market(h: &mut Harness) -> Market {
+    h.account(&pda::betting::market(MATCH_ID)).await
+}
+
+async fn players(h: &mut Harness) -> [Keypair; 2] {
+    [h.funded_player().await, h.funded_player().await]
+}
+
+fn keys(players: &[Keypair; 2]) -> [Pubkey; 2] {
+    [players[0].pubkey(), players[1].pubkey()]
+}
+
+#[tokio::test]
+async fn the_fee_is_capped() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let mint = h.put_mint(&admin);
+    let treasury = h.put_token_account(&mint, &admin, 0).await;
+
+    let ix = initialize(admin, mint, treasury, MAX_FEE_BPS + 1);
+    assert_error(h.send(&[ix], &[]).await, BettingError::FeeTooHigh);
+    h.send(&[initialize(admin, mint, treasury, FEE_BPS)], &[])
+        .await
+        .unwrap();
+    let config: BettingConfig = h.account(&pda::betting::config()).await;
+    assert_eq!(config.mint, mint);
+    assert_eq!(config.treasury, treasury);
+    assert_eq!(config.fee_bps, FEE_BPS);
+
+    h.send(&[set_fee(admin, MAX_FEE_BPS)], &[]).await.unwrap();
+    let config: BettingConfig = h.account(&pda::betting::config()).await;
+    assert_eq!(config.fee_bps, MAX_FEE_BPS);
+    assert_error(
+        h.send(&[set_fee(admin, MAX_FEE_BPS + 1)], &[]).await,
+        BettingError::FeeTooHigh,
+    );
+}
+
+#[tokio::test]
+async fn markets_are_bet_in_a_supported_mint() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let mint = h.put_unsupported_mint(&admin);
+    let treasury = h.put_token_account(&mint, &admin, 0).await;
+
+    let ix = initialize(admin, mint, treasury, FEE_BPS);
+    assert_error(h.send(&[ix], &[]).await, BettingError::UnsupportedMint);
+}
+
+#[tokio::test]
+async fn markets_need_two_players_and_a_future_lock() {
+    let mut h = Harness::start().await;
+    let book = Book::new(&mut h).await;
+    let admin = h.admin();
+    let players = keys(&players(&mut h).await);
+    let now = h.now().await;
+
+    let ix = open_market(admin, book.mint, [players[0]; 2], now + BETTING_TIME);
+    assert_error(h.send(&[ix], &[]).await, BettingError::InvalidPlayers);
+    let ix = open_market(admin, book.mint, players, now);
+    assert_error(h.send(&[ix], &[]).await, BettingError::InvalidLockTime);
+
+    book.open(&mut h, players).await;
+    let market = market(&mut h).await;
+    assert_eq!(market.players, players);
+    assert_eq!(market.fee_bps, FEE_BPS);
+    assert_eq!(market.status, MarketStatus::Open);
+}
+
+#[tokio::test]
+async fn bets_add_up_on_one_side() {
+    let mut h = Harness::start().await;
+    let book = Book::new(&mut h).await;
+    let players = keys(&players(&mut h).await);
+    book.open(&mut h, players).await;
+    let (bettor, tokens) = book.bettor(&mut h).await;
+
+    for amount in [100, 50] {
+        let ix = book.place_bet(bettor.pubkey(), tokens, 1, amount);
+        h.send(&[ix], &[&bettor]).await.unwrap();
+    }
+    let market_address = pda::betting::market(MATCH_ID);
+    let bet: Bet = h
+        .account(&pda::betting::bet(&market_address, &bettor.pubkey()))
+        .await;
+    assert_eq!(bet.side, 1);
+    assert_eq!(bet.amount, 150);
+    assert_eq!(market(&mut h).await.pools, [0, 150]);
+    assert_eq!(h.token_balance(&tokens).await, FUNDS - 150);
+
+    let ix = book.place_bet(bettor.pubkey(), tokens, 0, 100);
+    assert_error(h.send(&[ix], &[&bettor]).await, BettingError::SideMismatch);
+    let ix = book.place_bet(bettor.pubkey(), tokens, 2, 100);
+    assert_error(h.send(&[ix], &[&bettor]).await, BettingError::InvalidSide);
+    let ix = book.place_bet(bettor.pubkey(), tokens, 1, 0);
+    assert_error(h.send(&[ix], &[&bettor]).await, BettingError::InvalidAmount);
+
+    h.advance(BETTING_TIME).await;
+    let ix = book.place_bet(bettor.pubkey(), tokens, 1, 100);
+    assert_error(h.send(&[ix], &[&bettor]).await, BettingError::MarketLocked);
+}
+
+#[tokio::test]
+async fn settling_pays_the_winning_side_less_the_fee() {
+    let mut h = Harness::start().await;
+    let book = Book::new(&mut h).await;
+    let players = players(&mut h).await;
+    book.open(&mut h, keys(&players)).await;
+    let mut bettors = Vec::new();
+    for (side, amount) in [(0, 1_000), (0, 3_000), (1, 2_000)] {
+        let (bettor, tokens) = book.bettor(&mut h).await;
+        let ix = book.place_bet(bettor.pubkey(), tokens, side, amount);
+        h.send(&[ix], &[&bettor]).await.unwrap();
+        bettors.push((bettor, tokens, amount));
+    }
+
+    let [one, two] = &players;
+    fixtures::recorded_match(&mut h, MATCH_ID, [one, two], 0).await;
+    assert_error(
+        h.send(&[book.settle_market()], &[]).await,
+        BettingError::ChallengeWindowOpen,
+    );
+    let (bettor, tokens, _) = &bettors[0];
+    assert_error(
+        h.send(&[book.claim(bettor.pubkey(), *tokens)], &[bettor])
+            .await,
+        BettingError::MarketOpen,
+    );
+
+    h.advance(CHALLENGE_WINDOW).await;
+    h.send(&[book.settle_market()], &[]).await.unwrap();
+    let market = market(&mut h).await;
+    assert_eq!(market.status, MarketStatus::Settled);
+    assert_eq!(market.winner, 0);
+    let fee = 2_000 * FEE_BPS as u64 / 10_000;
+    assert_eq!(market.fee, fee);
+    assert_eq!(h.token_balance(&book.treasury).await, fee);
+    assert_error(
+        h.send(&[book.settle_market()], &[]).await,
+        BettingError::MarketClosed,
+    );
+
+    for (bettor, tokens, amount) in &bettors[..2] {
+        h.send(&[book.claim(bettor.pubkey(), *tokens)], &[bettor])
+            .await
+            .unwrap();
+        let won = payout(&market, *amount);
+        assert!(won > *amount);
+        assert_eq!(h.token_balance(tokens).await, FUNDS - amount + won);
+    }
+    // The losing bet is closed for its rent.
+    let (bettor, tokens, amount) = &bettors[2];
+    h.send(&[book.claim(bettor.pubkey(), *tokens)], &[bettor])
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(tokens).await, FUNDS - amount);
+    let market_address = pda::betting::market(MATCH_ID);
+    assert!(
+        !h.exists(&pda::betting::bet(&market_address, &bettor.pubkey()))
+            .await
+    );
+}
+
+#[tokio::test]
+async fn one_sided_markets_are_refunded() {
+    let mut h = Harness::start().await;
+    let book = Book::new(&mut h).await;
+    let players = players(&mut h).await;
+    book.open(&mut h, keys(&players)).await;
+    let (bettor, tokens) = book.bettor(&mut h).await;
+    let ix = book.place_bet(bettor.pubkey(), tokens, 0, 1_000);
+    h.send(&[ix], &[&bettor]).await.unwrap();
+
+    let [one, two] = &players;
+    fixtures::recorded_match(&mut h, MATCH_ID, [one, two], 0).await;
+    h.advance(CHALLENGE_WINDOW).await;
+    h.send(&[book.settle_market()], &[]).await.unwrap();
+    assert_eq!(market(&mut h).await.status, MarketStatus::Cancelled);
+
+    h.send(&[book.claim(bettor.pubkey(), tokens)], &[&bettor])
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(&tokens).await, FUNDS);
+    let ix = book.place_bet(bettor.pubkey(), tokens, 0, 1_000);
+    assert_error(h.send(&[ix], &[&bettor]).await, BettingError::MarketClosed);
+}
+
+#[tokio::test]
+async fn settling_needs_a_final_result() {
+    let mut h = Harness::start().await;
+    let book = Book::new(&mut h).await;
+    let players = players(&mut h).await;
+    book.open(&mut h, keys(&players)).await;
+    let [one, two] = &players;
+    fixtures::open_set(&mut h, MATCH_ID, [one, two]).await;
+
+    h.advance(CHALLENGE_WINDOW).await;
+    assert_error(
+        h.send(&[book.settle_market()], &[]).await,
+        BettingError::ResultNotFinal,
+    );
+}
+
+#[tokio::test]
+async fn settling_needs_the_markets_players_in_the_match() {
+    let mut h = Harness::start().await;
+    let book = Book::new(&mut h).await;
+    let players = players(&mut h).await;
+    book.open(&mut h, keys(&players)).await;
+    let stranger = h.funded_player().await;
+    fixtures::recorded_match(&mut h, MATCH_ID, [&players[0], &stranger], 0).await;
+
+    h.advance(CHALLENGE_WINDOW).await;
+    assert_error(
+        h.send(&[book.settle_market()], &[]).await,
+        BettingError::PlayerMismatch,
+    );
+}
+
+#[tokio::test]
+async fn disputes_hold_up_or_refund_the_market() {
+    let mut h = Harness::start().await;
+    let book = Book::new(&mut h).await;
+    let players = players(&mut h).await;
+    book.open(&mut h, keys(&players)).await;
+    let mut bets = Vec::new();
+    for side in [0, 1] {
+        let (bettor, tokens) = book.bettor(&mut h).await;
+        let ix = book.place_bet(bettor.pubkey(), tokens, side, 1_000);
+        h.send(&[ix], &[&bettor]).await.unwrap();
+        bets.push((bettor, tokens));
+    }
+
+    // The loser disputes and the winner never answers.
+    let [winner, loser] = &players;
+    fixtures::recorded_match(&mut h, MATCH_ID, [winner, loser], 0).await;
+    let stakes = [
+        h.put_token_account(&book.mint, &winner.pubkey(), 0).await,
+        h.put_token_account(&book.mint, &loser.pubkey(), MIN_STAKE)
+            .await,
+    ];
+    let dispute = pda::dispute::dispute(MATCH_ID);
+    let vault = pda::dispute::vault(&dispute);
+    let ix = instruction(
+        dispute::ID,
+        dispute::accounts::OpenDispute {
+            challenger: loser.pubkey(),
+            config: pda::dispute::config(),
+            match_account: pda::match_result::match_account(MATCH_ID),
+            dispute,
+            vault,
+            mint: book.mint,
+            challenger_token_account: stakes[1],
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        dispute::instruction::OpenDispute {
+            claimed_replay_hash: [1; 32],
+            stake: MIN_STAKE,
+        },
+    );
+    h.send(&[ix], &[loser]).await.unwrap();
+    h.advance(CHALLENGE_WINDOW).await;
+    assert_error(
+        h.send(&[book.settle_market()], &[]).await,
+        BettingError::DisputePending,
+    );
+
+    h.advance(RESPONSE_WINDOW).await;
+    let ix = instruction(
+        dispute::ID,
+        dispute::accounts::ResolveDispute {
+            config: pda::dispute::config(),
+            dispute,
+            vault,
+            mint: book.mint,
+            challenger_token_account: stakes[1],
+            respondent_token_account: stakes[0],
+            token_program: spl_token::ID,
+        },
+        dispute::instruction::ResolveDispute {},
+    );
+    h.send(&[ix], &[]).await.unwrap();
+    h.send(&[book.settle_market()], &[]).await.unwrap();
+    assert_eq!(market(&mut h).await.status, MarketStatus::Cancelled);
+    for (bettor, tokens) in &bets {
+        h.send(&[book.claim(bettor.pubkey(), *tokens)], &[bettor])
+            .await
+            .unwrap();
+        assert_eq!(h.token_balance(tokens).await, FUNDS);
+    }
+}
+
+#[tokio::test]
+async fn markets_without_a_result_are_cancelled_after_the_timeout() {
+    let mut h = Harness::start().await;
+    let book = Book::new(&mut h).await;
+    let players = keys(&players(&mut h).await);
+    book.open(&mut h, players).await;
+    let (bettor, tokens) = book.bettor(&mut h).await;
+    let ix = book.place_bet(bettor.pubkey(), tokens, 0, 1_000);
+    h.send(&[ix], &[&bettor]).await.unwrap();
+
+    h.advance(BETTING_TIME).await;
+    assert_error(
+        h.send(&[cancel_market()], &[]).await,
+        BettingError::ResultTimeoutNotReached,
+    );
+    h.advance(RESULT_TIMEOUT).await;
+    h.send(&[cancel_market()], &[]).await.unwrap();
+    assert_eq!(market(&mut h).await.status, MarketStatus::Cancelled);
+    assert_error(
+        h.send(&[cancel_market()], &[]).await,
+        BettingError::MarketClosed,
+    );
+
+    h.send(&[book.claim(bettor.pubkey(), tokens)], &[&bettor])
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(&tokens).await, FUNDS);
+}
+
+#[tokio::test]
+async fn markets_with_a_final_result_are_not_cancelled() {
+    let mut h = Harness::start().await;
+    let book = Book::new(&mut h).await;
+    let players = players(&mut h).await;
+    book.open(&mut h, keys(&players)).await;
+    let [one, two] = &players;
+    fixtures::recorded_match(&mut h, MATCH_ID, [one, two], 1).await;
+
+    h.advance(BETTING_TIME + RESULT_TIMEOUT).await;
+    assert_error(
+        h.send(&[cancel_market()], &[]).await,
+        BettingError::ResultFinal,
+    );
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let book = Book::new(&mut h).await;
+    let players = keys(&players(&mut h).await);
+    book.open(&mut h, players).await;
+    let admin = h.admin();
+
+    for account in [pda::betting::config(), pda::betting::market(MATCH_ID)] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            BettingError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &betting::ID, vec![9; Market::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, BettingError::UnsupportedLayout);
+}
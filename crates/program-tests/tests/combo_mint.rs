@@ -0,0 +1,1385 @@
+use anchor_lang::error::ErrorCode;
+use anchor_lang::{AnchorSerialize, Space};
+use anchor_spl::token::spl_token;
+use combo_mint_client::builders::{self, NewCombo};
+use combo_mint_client::combo_mint::{
+    Auction, ComboAccount, ComboError, ComboFlag, Config, Listing, NameFilter, Rental,
+    RewardConfig, ValidationLimits, VerifierQuota, VerifierRegistry, COMBO_TAG_JUGGLE,
+    FLAG_REASON_EXPLOIT, FLAG_REASON_OTHER, KNOWN_COMBO_TAGS, MAX_BPS, MAX_COMBO_MOVES,
+    MAX_COMBO_NAME_LEN, MAX_LIMIT_SIGNERS, MAX_NAME_PREFIXES, MAX_NAME_PREFIX_LEN, MAX_VERIFIERS,
+};
+use combo_mint_client::{combo_mint, instruction, pda};
+use program_tests::fixtures::{self, limits, new_combo, CHARACTER, JAB, LAUNCHER, ROUTE};
+use program_tests::{assert_error, Harness};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::{system_program, sysvar};
+
+// `ComboError::Overflow` needs 2^32 game versions, so no test reaches it.
+// The simulator's other errors, such as `JuggleLimitExceeded`, are covered
+// by `combo_sim`'s route checks rather than through a transaction.
+
+fn admin_config(admin: Pubkey) -> combo_mint::accounts::SetPaused {
+    combo_mint::accounts::SetPaused {
+        admin,
+        config: pda::combo_mint::config(),
+    }
+}
+
+fn pause(admin: Pubkey) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        admin_config(admin),
+        combo_mint::instruction::Pause {},
+    )
+}
+
+fn unpause(admin: Pubkey) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        admin_config(admin),
+        combo_mint::instruction::Unpause {},
+    )
+}
+
+fn bump_game_version(admin: Pubkey) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::BumpGameVersion {
+            admin,
+            config: pda::combo_mint::config(),
+        },
+        combo_mint::instruction::BumpGameVersion {},
+    )
+}
+
+fn set_royalty_cap(admin: Pubkey, max_royalty_bps: u16) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::SetRoyaltyCap {
+            admin,
+            config: pda::combo_mint::config(),
+        },
+        combo_mint::instruction::SetRoyaltyCap { max_royalty_bps },
+    )
+}
+
+fn set_verification_cooldown(admin: Pubkey, cooldown: i64) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::SetVerificationCooldown {
+            admin,
+            config: pda::combo_mint::config(),
+        },
+        combo_mint::instruction::SetVerificationCooldown { cooldown },
+    )
+}
+
+/// `update_limits` approved by `signers`, passed as remaining accounts.
+fn update_limits(limits: ValidationLimits, signers: &[Pubkey]) -> Instruction {
+    let mut ix = instruction(
+        combo_mint::ID,
+        combo_mint::accounts::UpdateLimits {
+            config: pda::combo_mint::config(),
+        },
+        combo_mint::instruction::UpdateLimits { limits },
+    );
+    ix.accounts.extend(
+        signers
+            .iter()
+            .map(|signer| AccountMeta::new_readonly(*signer, true)),
+    );
+    ix
+}
+
+fn initialize_config(
+    admin: Pubkey,
+    limits: ValidationLimits,
+    signers: Vec<Pubkey>,
+    threshold: u8,
+) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::InitializeConfig {
+            admin,
+            config: pda::combo_mint::config(),
+            system_program: system_program::ID,
+        },
+        combo_mint::instruction::InitializeConfig {
+            limits,
+            signers,
+            threshold,
+        },
+    )
+}
+
+fn update_verifiers(admin: Pubkey) -> combo_mint::accounts::UpdateVerifiers {
+    combo_mint::accounts::UpdateVerifiers {
+        admin,
+        config: pda::combo_mint::config(),
+        verifier_registry: pda::combo_mint::verifier_registry(),
+    }
+}
+
+fn add_verifier(admin: Pubkey, verifier: Pubkey) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        update_verifiers(admin),
+        combo_mint::instruction::AddVerifier { verifier },
+    )
+}
+
+fn remove_verifier(admin: Pubkey, verifier: Pubkey) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        update_verifiers(admin),
+        combo_mint::instruction::RemoveVerifier { verifier },
+    )
+}
+
+fn update_name_filter(admin: Pubkey) -> combo_mint::accounts::UpdateNameFilter {
+    combo_mint::accounts::UpdateNameFilter {
+        admin,
+        config: pda::combo_mint::config(),
+        name_filter: pda::combo_mint::name_filter(),
+    }
+}
+
+fn ban_name_prefix(admin: Pubkey, prefix: &str) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        update_name_filter(admin),
+        combo_mint::instruction::BanNamePrefix {
+            prefix: prefix.to_string(),
+        },
+    )
+}
+
+fn unban_name_prefix(admin: Pubkey, prefix: &str) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        update_name_filter(admin),
+        combo_mint::instruction::UnbanNamePrefix {
+            prefix: prefix.to_string(),
+        },
+    )
+}
+
+fn set_official(admin: Pubkey, combo: Pubkey, official: bool) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::SetOfficial {
+            admin,
+            config: pda::combo_mint::config(),
+            combo_pda: combo,
+        },
+        combo_mint::instruction::SetOfficial { official },
+    )
+}
+
+fn set_moderator(admin: Pubkey, moderator: Pubkey) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::SetModerator {
+            admin,
+            config: pda::combo_mint::config(),
+        },
+        combo_mint::instruction::SetModerator { moderator },
+    )
+}
+
+fn flag_combo(reporter: Pubkey, combo: Pubkey, reason: u8) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::FlagCombo {
+            reporter,
+            combo_pda: combo,
+            flag: pda::combo_mint::combo_flag(&combo),
+            system_program: system_program::ID,
+        },
+        combo_mint::instruction::FlagCombo { reason },
+    )
+}
+
+fn freeze_combo(moderator: Pubkey, combo: Pubkey, frozen: bool) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::FreezeCombo {
+            moderator,
+            config: pda::combo_mint::config(),
+            combo_pda: combo,
+        },
+        combo_mint::instruction::FreezeCombo { frozen },
+    )
+}
+
+fn appeal_flag(authority: Pubkey, combo: Pubkey) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::AppealFlag {
+            authority,
+            combo_pda: combo,
+            flag: pda::combo_mint::combo_flag(&combo),
+        },
+        combo_mint::instruction::AppealFlag {},
+    )
+}
+
+fn resolve_flag(moderator: Pubkey, combo: Pubkey, reporter: Pubkey, upheld: bool) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::ResolveFlag {
+            moderator,
+            config: pda::combo_mint::config(),
+            combo_pda: combo,
+            flag: pda::combo_mint::combo_flag(&combo),
+            reporter,
+        },
+        combo_mint::instruction::ResolveFlag { upheld },
+    )
+}
+
+fn transfer_combo(
+    authority: Pubkey,
+    combo: Pubkey,
+    new_authority: Pubkey,
+    require_accept: bool,
+) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::TransferCombo {
+            authority,
+            combo_pda: combo,
+        },
+        combo_mint::instruction::TransferCombo {
+            new_authority,
+            require_accept,
+        },
+    )
+}
+
+fn accept_combo_transfer(new_authority: Pubkey, combo: Pubkey) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::AcceptComboTransfer {
+            new_authority,
+            combo_pda: combo,
+        },
+        combo_mint::instruction::AcceptComboTransfer {},
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        combo_mint::instruction::MigrateAccount {},
+    )
+}
+
+fn resize(authority: &Keypair, combo_pda: Pubkey) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::ResizeCombo {
+            authority: authority.pubkey(),
+            combo_pda,
+            system_program: system_program::ID,
+        },
+        combo_mint::instruction::ResizeCombo {},
+    )
+}
+
+/// Tokens each counted verification pays in the reward tests.
+const REWARD: u64 = 10;
+const REWARD_WINDOW: i64 = 60 * 60;
+
+/// Sets up rewards of `REWARD` a verification, at most `max_per_verifier`
+/// to one verifier per window, funded for a hundred verifications.
+async fn rewards(h: &mut Harness, max_per_verifier: u32) -> RewardConfig {
+    let admin = h.admin();
+    let mint = h.put_mint(&admin);
+    let funder_token = h.put_token_account(&mint, &admin, 100 * REWARD).await;
+    let config = pda::combo_mint::config();
+    let reward_config = pda::combo_mint::reward_config();
+    let reward_vault = pda::combo_mint::reward_vault();
+    let ixs = [
+        instruction(
+            combo_mint::ID,
+            combo_mint::accounts::InitializeRewards {
+                admin,
+                config,
+                reward_config,
+                mint,
+                reward_vault,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                rent: sysvar::rent::ID,
+            },
+            combo_mint::instruction::InitializeRewards {
+                amount: REWARD,
+                max_per_window: 100,
+                max_per_verifier,
+                window: REWARD_WINDOW,
+            },
+        ),
+        instruction(
+            combo_mint::ID,
+            combo_mint::accounts::FundRewards {
+                admin,
+                config,
+                reward_config,
+                reward_vault,
+                mint,
+                funder_token,
+                token_program: spl_token::ID,
+            },
+            combo_mint::instruction::FundRewards {
+                amount: 100 * REWARD,
+            },
+        ),
+    ];
+    h.send(&ixs, &[]).await.unwrap();
+    h.account(&reward_config).await
+}
+
+/// Has `verifier` verify the combo at `address` along `ROUTE`, paying any
+/// reward into `verifier_token`.
+async fn verify(
+    h: &mut Harness,
+    verifier: &Keypair,
+    address: Pubkey,
+    rewards: &RewardConfig,
+    verifier_token: Pubkey,
+) {
+    let current = combo(h, &address).await;
+    let ix = builders::verify_combo(
+        verifier.pubkey(),
+        address,
+        &current,
+        rewards,
+        verifier_token,
+        spl_token::ID,
+        ROUTE.to_vec(),
+    );
+    h.send(&[ix], &[verifier]).await.unwrap();
+}
+
+fn upgrade_with_duplicate(
+    authority: Pubkey,
+    combo: Pubkey,
+    duplicate: Pubkey,
+    current: &ComboAccount,
+) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::UpgradeComboWithDuplicate {
+            authority,
+            combo_pda: combo,
+            duplicate,
+            duplicate_hash_index: pda::combo_mint::hash_index(&current.combo_hash),
+            duplicate_name_index: pda::combo_mint::name_index(&current.creator, &current.name),
+            duplicate_listing: pda::combo_mint::listing(&duplicate),
+            duplicate_rental: pda::combo_mint::rental(&duplicate),
+            duplicate_auction: pda::combo_mint::auction(&duplicate),
+            config: pda::combo_mint::config(),
+        },
+        combo_mint::instruction::UpgradeComboWithDuplicate {},
+    )
+}
+
+async fn config(h: &mut Harness) -> Config {
+    h.account(&pda::combo_mint::config()).await
+}
+
+async fn combo(h: &mut Harness, address: &Pubkey) -> ComboAccount {
+    h.account(address).await
+}
+
+#[tokio::test]
+async fn initializes_the_config() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+
+    let config = config(&mut h).await;
+    assert_eq!(config.admin, h.admin());
+    assert_eq!(config.limits, limits());
+    assert_eq!(config.signers[0], h.admin());
+    assert_eq!((config.signer_count, config.threshold), (1, 1));
+    assert_eq!(config.game_version, 1);
+    assert!(!config.paused);
+    let registry: VerifierRegistry = h.account(&pda::combo_mint::verifier_registry()).await;
+    assert!(registry.verifiers.is_empty());
+    let filter: NameFilter = h.account(&pda::combo_mint::name_filter()).await;
+    assert!(filter.prefixes.is_empty());
+}
+
+#[tokio::test]
+async fn rejects_invalid_initial_config() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let keys: Vec<Pubkey> = (0..=MAX_LIMIT_SIGNERS)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+
+    let invalid_limits: [fn(&mut ValidationLimits); 4] = [
+        |l| l.max_damage = 0,
+        |l| l.max_meter_gain = 0,
+        |l| l.max_moves = MAX_COMBO_MOVES as u8 + 1,
+        |l| l.max_name_len = MAX_COMBO_NAME_LEN as u8 + 1,
+    ];
+    for invalidate in invalid_limits {
+        let mut limits = limits();
+        invalidate(&mut limits);
+        let ix = initialize_config(admin, limits, vec![admin], 1);
+        assert_error(h.send(&[ix], &[]).await, ComboError::InvalidLimits);
+    }
+
+    let invalid_signers = [
+        (vec![], 0),
+        (keys.clone(), 1),
+        (vec![admin], 0),
+        (vec![admin], 2),
+        (vec![admin, keys[0], admin], 2),
+    ];
+    for (signers, threshold) in invalid_signers {
+        let ix = initialize_config(admin, limits(), signers, threshold);
+        assert_error(h.send(&[ix], &[]).await, ComboError::InvalidSigners);
+    }
+}
+
+#[tokio::test]
+async fn limit_changes_need_the_signer_threshold() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+    let outsider = h.funded_player().await;
+    let tighter = ValidationLimits {
+        max_damage: 500,
+        ..limits()
+    };
+
+    let ix = update_limits(tighter.clone(), &[]);
+    assert_error(h.send(&[ix], &[]).await, ComboError::NotEnoughSigners);
+    let ix = update_limits(tighter.clone(), &[outsider.pubkey()]);
+    assert_error(
+        h.send(&[ix], &[&outsider]).await,
+        ComboError::NotEnoughSigners,
+    );
+    let invalid = ValidationLimits {
+        max_moves: 0,
+        ..limits()
+    };
+    let ix = update_limits(invalid, &[admin]);
+    assert_error(h.send(&[ix], &[]).await, ComboError::InvalidLimits);
+
+    h.send(&[update_limits(tighter.clone(), &[admin])], &[])
+        .await
+        .unwrap();
+    assert_eq!(config(&mut h).await.limits, tighter);
+}
+
+#[tokio::test]
+async fn the_admin_bumps_the_game_version_and_pauses() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+
+    h.send(&[bump_game_version(admin)], &[]).await.unwrap();
+    assert_eq!(config(&mut h).await.game_version, 2);
+
+    h.send(&[pause(admin)], &[]).await.unwrap();
+    assert!(config(&mut h).await.paused);
+    let ix = fixtures::create_combo(admin, new_combo("Paused", &ROUTE));
+    assert_error(h.send(&[ix], &[]).await, ComboError::ProgramPaused);
+
+    h.send(&[unpause(admin)], &[]).await.unwrap();
+    assert!(!config(&mut h).await.paused);
+}
+
+#[tokio::test]
+async fn admin_instructions_need_the_admin() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let combo = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+    let intruder = h.funded_player().await;
+    let key = intruder.pubkey();
+
+    for ix in [
+        bump_game_version(key),
+        pause(key),
+        unpause(key),
+        set_royalty_cap(key, 100),
+        set_verification_cooldown(key, 60),
+        add_verifier(key, key),
+        ban_name_prefix(key, "gg"),
+        set_official(key, combo, true),
+        set_moderator(key, key),
+    ] {
+        assert_error(
+            h.send(&[ix], &[&intruder]).await,
+            ErrorCode::ConstraintHasOne,
+        );
+    }
+}
+
+#[tokio::test]
+async fn sets_the_royalty_cap_and_verification_cooldown() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+
+    h.send(&[set_royalty_cap(admin, MAX_BPS)], &[])
+        .await
+        .unwrap();
+    h.send(&[set_verification_cooldown(admin, 60)], &[])
+        .await
+        .unwrap();
+    let config = config(&mut h).await;
+    assert_eq!(config.max_royalty_bps, MAX_BPS);
+    assert_eq!(config.verification_cooldown, 60);
+
+    let ix = set_royalty_cap(admin, MAX_BPS + 1);
+    assert_error(h.send(&[ix], &[]).await, ComboError::InvalidRoyalty);
+    let ix = set_verification_cooldown(admin, -1);
+    assert_error(h.send(&[ix], &[]).await, ComboError::InvalidCooldown);
+}
+
+#[tokio::test]
+async fn manages_approved_verifiers() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+    let verifier = Pubkey::new_unique();
+
+    h.send(&[add_verifier(admin, verifier)], &[]).await.unwrap();
+    let ix = add_verifier(admin, verifier);
+    assert_error(
+        h.send(&[ix], &[]).await,
+        ComboError::VerifierAlreadyApproved,
+    );
+
+    h.send(&[remove_verifier(admin, verifier)], &[])
+        .await
+        .unwrap();
+    let ix = remove_verifier(admin, verifier);
+    assert_error(h.send(&[ix], &[]).await, ComboError::UnknownVerifier);
+
+    for _ in 0..MAX_VERIFIERS / 8 {
+        let ixs: Vec<_> = (0..8)
+            .map(|_| add_verifier(admin, Pubkey::new_unique()))
+            .collect();
+        h.send(&ixs, &[]).await.unwrap();
+    }
+    let ix = add_verifier(admin, verifier);
+    assert_error(h.send(&[ix], &[]).await, ComboError::TooManyVerifiers);
+}
+
+#[tokio::test]
+async fn manages_banned_name_prefixes() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+
+    h.send(&[ban_name_prefix(admin, "GG")], &[]).await.unwrap();
+    let filter: NameFilter = h.account(&pda::combo_mint::name_filter()).await;
+    assert_eq!(filter.prefixes, vec!["gg".to_string()]);
+    let ix = ban_name_prefix(admin, "gg");
+    assert_error(h.send(&[ix], &[]).await, ComboError::PrefixAlreadyBanned);
+
+    let too_long = "a".repeat(MAX_NAME_PREFIX_LEN + 1);
+    for prefix in ["", "\tgg", too_long.as_str()] {
+        let ix = ban_name_prefix(admin, prefix);
+        assert_error(h.send(&[ix], &[]).await, ComboError::InvalidNameCharset);
+    }
+
+    h.send(&[unban_name_prefix(admin, "Gg")], &[])
+        .await
+        .unwrap();
+    let ix = unban_name_prefix(admin, "gg");
+    assert_error(h.send(&[ix], &[]).await, ComboError::UnknownPrefix);
+
+    for batch in 0..MAX_NAME_PREFIXES / 8 {
+        let ixs: Vec<_> = (0..8)
+            .map(|i| ban_name_prefix(admin, &format!("p{}", batch * 8 + i)))
+            .collect();
+        h.send(&ixs, &[]).await.unwrap();
+    }
+    let ix = ban_name_prefix(admin, "one more");
+    assert_error(h.send(&[ix], &[]).await, ComboError::TooManyPrefixes);
+}
+
+#[tokio::test]
+async fn creates_a_combo_matching_its_simulation() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+
+    let created = combo(&mut h, &address).await;
+    let expected = new_combo("Bread and butter", &ROUTE);
+    assert_eq!(created.authority, creator.pubkey());
+    assert_eq!(created.creator, creator.pubkey());
+    assert_eq!(created.character_id, CHARACTER);
+    assert_eq!(created.damage, expected.damage);
+    assert_eq!(created.moves, ROUTE.to_vec());
+    assert_eq!(created.game_version, 1);
+    assert_eq!(
+        created.combo_hash,
+        builders::new_combo_hash(&fixtures::frame_data(), &expected).unwrap()
+    );
+    assert!(
+        h.exists(&pda::combo_mint::hash_index(&created.combo_hash))
+            .await
+    );
+    assert!(
+        h.exists(&pda::combo_mint::name_index(
+            &creator.pubkey(),
+            "Bread and butter"
+        ))
+        .await
+    );
+}
+
+#[tokio::test]
+async fn rejects_invalid_names() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+    let creator = h.funded_player().await;
+    h.send(&[ban_name_prefix(admin, "gg")], &[]).await.unwrap();
+
+    let too_long = "a".repeat(limits().max_name_len as usize + 1);
+    let cases = [
+        (too_long.as_str(), ComboError::NameTooLong),
+        (" Leading", ComboError::InvalidNameCharset),
+        ("Trailing ", ComboError::InvalidNameCharset),
+        ("Tab\tcombo", ComboError::InvalidNameCharset),
+        ("GG combo", ComboError::BannedName),
+    ];
+    for (name, expected) in cases {
+        let ix = fixtures::create_combo(creator.pubkey(), new_combo(name, &ROUTE));
+        assert_error(h.send(&[ix], &[&creator]).await, expected);
+    }
+}
+
+#[tokio::test]
+async fn rejects_combos_outside_the_limits() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let valid = new_combo("Route", &ROUTE);
+    let max_moves = limits().max_moves;
+
+    let cases = [
+        (
+            NewCombo {
+                damage: 0,
+                ..valid.clone()
+            },
+            ComboError::InvalidDamage,
+        ),
+        (
+            NewCombo {
+                damage: limits().max_damage + 1,
+                ..valid.clone()
+            },
+            ComboError::InvalidDamage,
+        ),
+        (
+            NewCombo {
+                move_count: 0,
+                moves: vec![],
+                ..valid.clone()
+            },
+            ComboError::InvalidMoveCount,
+        ),
+        (
+            NewCombo {
+                move_count: ROUTE.len() as u8 + 1,
+                ..valid.clone()
+            },
+            ComboError::InvalidMoveCount,
+        ),
+        (
+            NewCombo {
+                move_count: max_moves + 1,
+                moves: vec![JAB; max_moves as usize + 1],
+                ..valid.clone()
+            },
+            ComboError::InvalidMoveCount,
+        ),
+        (
+            NewCombo {
+                damage: valid.damage + 1,
+                ..valid.clone()
+            },
+            ComboError::DamageMismatch,
+        ),
+        (
+            NewCombo {
+                moves: vec![JAB, JAB, 99],
+                ..valid.clone()
+            },
+            ComboError::UnknownMove,
+        ),
+        (
+            NewCombo {
+                moves: vec![LAUNCHER, JAB],
+                move_count: 2,
+                ..valid.clone()
+            },
+            ComboError::ComboDropped,
+        ),
+        (
+            NewCombo {
+                royalty_bps: 1,
+                ..valid.clone()
+            },
+            ComboError::InvalidRoyalty,
+        ),
+        (
+            NewCombo {
+                tags: !KNOWN_COMBO_TAGS,
+                ..valid.clone()
+            },
+            ComboError::UnknownTag,
+        ),
+        (
+            NewCombo {
+                tags: COMBO_TAG_JUGGLE,
+                ..valid
+            },
+            ComboError::TagMismatch,
+        ),
+    ];
+    for (combo, expected) in cases {
+        // The hash only addresses the index, which isn't reached here.
+        let ix = builders::create_combo(creator.pubkey(), combo, &[0; 32]);
+        assert_error(h.send(&[ix], &[&creator]).await, expected);
+    }
+}
+
+#[tokio::test]
+async fn meter_gain_is_capped_by_the_limits() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+    let creator = h.funded_player().await;
+    let stingy = ValidationLimits {
+        max_meter_gain: 10,
+        ..limits()
+    };
+    h.send(&[update_limits(stingy, &[admin])], &[])
+        .await
+        .unwrap();
+
+    let ix = fixtures::create_combo(creator.pubkey(), new_combo("Route", &ROUTE));
+    assert_error(
+        h.send(&[ix], &[&creator]).await,
+        ComboError::InvalidMeterGain,
+    );
+}
+
+#[tokio::test]
+async fn derived_tags_and_royalties_are_accepted() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+    let creator = h.funded_player().await;
+    h.send(&[set_royalty_cap(admin, 500)], &[]).await.unwrap();
+
+    let juggle = NewCombo {
+        tags: COMBO_TAG_JUGGLE,
+        royalty_bps: 500,
+        ..new_combo("Launch", &[JAB, LAUNCHER])
+    };
+    let ix = fixtures::create_combo(creator.pubkey(), juggle);
+    h.send(&[ix], &[&creator]).await.unwrap();
+
+    let created = combo(&mut h, &pda::combo_mint::combo(&creator.pubkey(), "Launch")).await;
+    assert_eq!((created.tags, created.royalty_bps), (COMBO_TAG_JUGGLE, 500));
+
+    let untagged = new_combo("Untagged launch", &[JAB, LAUNCHER]);
+    let ix = fixtures::create_combo(creator.pubkey(), untagged);
+    assert_error(h.send(&[ix], &[&creator]).await, ComboError::TagMismatch);
+}
+
+#[tokio::test]
+async fn a_route_and_name_is_claimed_once() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let first = h.funded_player().await;
+    let second = h.funded_player().await;
+    fixtures::combo(&mut h, &first, "Bread and butter").await;
+
+    let ix = fixtures::create_combo(second.pubkey(), new_combo("Bread and butter", &ROUTE));
+    assert_error(h.send(&[ix], &[&second]).await, ComboError::DuplicateCombo);
+
+    let ix = builders::create_combo(second.pubkey(), new_combo("Other", &ROUTE), &[0; 32]);
+    assert_error(
+        h.send(&[ix], &[&second]).await,
+        ComboError::InvalidHashIndex,
+    );
+}
+
+#[tokio::test]
+async fn the_admin_marks_combos_official() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+    let creator = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+
+    h.send(&[set_official(admin, address, true)], &[])
+        .await
+        .unwrap();
+    assert!(combo(&mut h, &address).await.official);
+}
+
+#[tokio::test]
+async fn moderates_flagged_combos() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+    let moderator = h.funded_player().await;
+    let creator = h.funded_player().await;
+    let reporter = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+
+    let ix = flag_combo(reporter.pubkey(), address, FLAG_REASON_OTHER + 1);
+    assert_error(
+        h.send(&[ix], &[&reporter]).await,
+        ComboError::InvalidFlagReason,
+    );
+    let ix = flag_combo(reporter.pubkey(), address, FLAG_REASON_EXPLOIT);
+    h.send(&[ix], &[&reporter]).await.unwrap();
+    let flag: ComboFlag = h.account(&pda::combo_mint::combo_flag(&address)).await;
+    assert_eq!(
+        (flag.reporter, flag.reason),
+        (reporter.pubkey(), FLAG_REASON_EXPLOIT)
+    );
+
+    h.send(&[appeal_flag(creator.pubkey(), address)], &[&creator])
+        .await
+        .unwrap();
+    let ix = appeal_flag(creator.pubkey(), address);
+    assert_error(
+        h.send(&[ix], &[&creator]).await,
+        ComboError::AlreadyAppealed,
+    );
+
+    let ix = resolve_flag(moderator.pubkey(), address, reporter.pubkey(), true);
+    assert_error(h.send(&[ix], &[&moderator]).await, ComboError::NoModerator);
+    h.send(&[set_moderator(admin, moderator.pubkey())], &[])
+        .await
+        .unwrap();
+    let ix = resolve_flag(reporter.pubkey(), address, reporter.pubkey(), true);
+    assert_error(h.send(&[ix], &[&reporter]).await, ComboError::Unauthorized);
+
+    let reporter_before = h.lamports(&reporter.pubkey()).await;
+    let ix = resolve_flag(moderator.pubkey(), address, reporter.pubkey(), true);
+    h.send(&[ix], &[&moderator]).await.unwrap();
+    assert!(combo(&mut h, &address).await.frozen);
+    assert!(!h.exists(&pda::combo_mint::combo_flag(&address)).await);
+    assert!(h.lamports(&reporter.pubkey()).await > reporter_before);
+}
+
+#[tokio::test]
+async fn the_moderator_freezes_and_thaws_combos() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+    let moderator = h.funded_player().await;
+    let creator = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+
+    let ix = freeze_combo(moderator.pubkey(), address, true);
+    assert_error(h.send(&[ix], &[&moderator]).await, ComboError::NoModerator);
+    h.send(&[set_moderator(admin, moderator.pubkey())], &[])
+        .await
+        .unwrap();
+    let ix = freeze_combo(creator.pubkey(), address, false);
+    assert_error(h.send(&[ix], &[&creator]).await, ComboError::Unauthorized);
+
+    h.send(
+        &[freeze_combo(moderator.pubkey(), address, true)],
+        &[&moderator],
+    )
+    .await
+    .unwrap();
+    assert!(combo(&mut h, &address).await.frozen);
+    let ix = transfer_combo(creator.pubkey(), address, Pubkey::new_unique(), false);
+    assert_error(h.send(&[ix], &[&creator]).await, ComboError::ComboFrozen);
+
+    h.send(
+        &[freeze_combo(moderator.pubkey(), address, false)],
+        &[&moderator],
+    )
+    .await
+    .unwrap();
+    assert!(!combo(&mut h, &address).await.frozen);
+}
+
+#[tokio::test]
+async fn transfers_combos() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let buyer = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+
+    let ix = transfer_combo(buyer.pubkey(), address, buyer.pubkey(), false);
+    assert_error(h.send(&[ix], &[&buyer]).await, ErrorCode::ConstraintHasOne);
+
+    let ix = transfer_combo(creator.pubkey(), address, buyer.pubkey(), false);
+    h.send(&[ix], &[&creator]).await.unwrap();
+    let transferred = combo(&mut h, &address).await;
+    assert_eq!(transferred.authority, buyer.pubkey());
+    assert_eq!(transferred.creator, creator.pubkey());
+}
+
+#[tokio::test]
+async fn two_step_transfers_wait_for_the_new_authority() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let buyer = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+
+    let ix = transfer_combo(creator.pubkey(), address, buyer.pubkey(), true);
+    h.send(&[ix], &[&creator]).await.unwrap();
+    let pending = combo(&mut h, &address).await;
+    assert_eq!(pending.authority, creator.pubkey());
+    assert_eq!(pending.pending_authority, buyer.pubkey());
+
+    let ix = accept_combo_transfer(creator.pubkey(), address);
+    assert_error(
+        h.send(&[ix], &[&creator]).await,
+        ComboError::NotPendingAuthority,
+    );
+    h.send(&[accept_combo_transfer(buyer.pubkey(), address)], &[&buyer])
+        .await
+        .unwrap();
+    let accepted = combo(&mut h, &address).await;
+    assert_eq!(accepted.authority, buyer.pubkey());
+    assert_eq!(accepted.pending_authority, Pubkey::default());
+}
+
+#[tokio::test]
+async fn closes_a_combo_and_frees_its_name_and_route() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let destination = h.funded_player().await.pubkey();
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+    let closed = combo(&mut h, &address).await;
+
+    let ix = builders::close_combo(creator.pubkey(), address, &closed, destination, &[]);
+    h.send(&[ix], &[&creator]).await.unwrap();
+    assert!(!h.exists(&address).await);
+    assert!(
+        !h.exists(&pda::combo_mint::hash_index(&closed.combo_hash))
+            .await
+    );
+
+    // Both are free again, so the same combo can be created anew.
+    fixtures::combo(&mut h, &creator, "Bread and butter").await;
+}
+
+#[tokio::test]
+async fn close_combo_checks_its_children() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let destination = h.funded_player().await.pubkey();
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+    let mut current = combo(&mut h, &address).await;
+
+    let not_a_child = [pda::combo_mint::config()];
+    let ix = builders::close_combo(
+        creator.pubkey(),
+        address,
+        &current,
+        destination,
+        &not_a_child,
+    );
+    assert_error(
+        h.send(&[ix], &[&creator]).await,
+        ComboError::UnknownChildAccount,
+    );
+
+    // Attaching combo data is `attach_combo_data`'s job; here only the link
+    // matters, so it is written straight into the combo.
+    current.combo_data = pda::combo_mint::combo_data(&address);
+    h.put_account(
+        &address,
+        &combo_mint::ID,
+        &current,
+        8 + ComboAccount::INIT_SPACE,
+    );
+    let ix = builders::close_combo(creator.pubkey(), address, &current, destination, &[]);
+    assert_error(
+        h.send(&[ix], &[&creator]).await,
+        ComboError::ComboDataAttached,
+    );
+
+    let intruder = Keypair::new();
+    let ix = builders::close_combo(intruder.pubkey(), address, &current, destination, &[]);
+    assert_error(
+        h.send(&[ix], &[&intruder]).await,
+        ErrorCode::ConstraintHasOne,
+    );
+}
+
+#[tokio::test]
+async fn a_listed_combo_cannot_be_closed() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+    let current = combo(&mut h, &address).await;
+
+    let listing = Listing {
+        layout_version: 1,
+        combo: address,
+        seller: creator.pubkey(),
+        price: 1_000_000,
+        payment_mint: Pubkey::default(),
+        bump: 0,
+    };
+    h.put_account(
+        &pda::combo_mint::listing(&address),
+        &combo_mint::ID,
+        &listing,
+        8 + Listing::INIT_SPACE,
+    );
+    let ix = builders::close_combo(creator.pubkey(), address, &current, creator.pubkey(), &[]);
+    assert_error(h.send(&[ix], &[&creator]).await, ComboError::ComboListed);
+    assert!(h.exists(&address).await);
+}
+
+#[tokio::test]
+async fn a_rented_combo_cannot_be_closed_until_the_rental_is_finalized() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let borrower = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+    let current = combo(&mut h, &address).await;
+
+    // Expired long ago, but still open until someone finalizes it.
+    let rental = Rental {
+        layout_version: 1,
+        combo: address,
+        owner: creator.pubkey(),
+        borrower: borrower.pubkey(),
+        fee: 0,
+        expires_at: 0,
+        uses: 0,
+        bump: 0,
+    };
+    h.put_account(
+        &pda::combo_mint::rental(&address),
+        &combo_mint::ID,
+        &rental,
+        8 + Rental::INIT_SPACE,
+    );
+    let ix = builders::close_combo(creator.pubkey(), address, &current, creator.pubkey(), &[]);
+    assert_error(h.send(&[ix], &[&creator]).await, ComboError::RentalOpen);
+    assert!(h.exists(&address).await);
+}
+
+#[tokio::test]
+async fn a_combo_cannot_be_closed_mid_auction() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let bidder = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+    let current = combo(&mut h, &address).await;
+
+    // Ended with a bid in escrow, which only settling pays out.
+    let auction = Auction {
+        layout_version: 1,
+        combo: address,
+        seller: creator.pubkey(),
+        reserve_price: 1_000_000,
+        min_increment: 1,
+        end_time: 0,
+        extension: 0,
+        highest_bid: 1_000_000,
+        highest_bidder: bidder.pubkey(),
+        bump: 0,
+    };
+    h.put_account(
+        &pda::combo_mint::auction(&address),
+        &combo_mint::ID,
+        &auction,
+        8 + Auction::INIT_SPACE,
+    );
+    let ix = builders::close_combo(creator.pubkey(), address, &current, creator.pubkey(), &[]);
+    assert_error(h.send(&[ix], &[&creator]).await, ComboError::AuctionOpen);
+    assert!(h.exists(&address).await);
+}
+
+#[tokio::test]
+async fn a_duplicate_is_only_burned_when_nothing_else_holds_it() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+    let duplicate = fixtures::combo(&mut h, &creator, "Butter and bread").await;
+    let current = combo(&mut h, &duplicate).await;
+
+    // Moderation, combo data and the listing are each another
+    // instruction's job; here only their marks matter, so they are written
+    // straight into the accounts, one at a time.
+    let mut frozen = current.clone();
+    frozen.frozen = true;
+    let mut attached = current.clone();
+    attached.combo_data = pda::combo_mint::combo_data(&duplicate);
+    for (state, error) in [
+        (frozen, ComboError::ComboFrozen),
+        (attached, ComboError::ComboDataAttached),
+    ] {
+        h.put_account(
+            &duplicate,
+            &combo_mint::ID,
+            &state,
+            8 + ComboAccount::INIT_SPACE,
+        );
+        let ix = upgrade_with_duplicate(creator.pubkey(), address, duplicate, &current);
+        assert_error(h.send(&[ix], &[&creator]).await, error);
+    }
+    h.put_account(
+        &duplicate,
+        &combo_mint::ID,
+        &current,
+        8 + ComboAccount::INIT_SPACE,
+    );
+    let listing = Listing {
+        layout_version: 1,
+        combo: duplicate,
+        seller: creator.pubkey(),
+        price: 1_000_000,
+        payment_mint: Pubkey::default(),
+        bump: 0,
+    };
+    h.put_account(
+        &pda::combo_mint::listing(&duplicate),
+        &combo_mint::ID,
+        &listing,
+        8 + Listing::INIT_SPACE,
+    );
+    let ix = upgrade_with_duplicate(creator.pubkey(), address, duplicate, &current);
+    assert_error(h.send(&[ix], &[&creator]).await, ComboError::ComboListed);
+    assert!(h.exists(&duplicate).await);
+}
+
+#[tokio::test]
+async fn only_approved_verifiers_are_rewarded_and_never_for_their_own_combos() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let rewards = rewards(&mut h, 10).await;
+    let admin = h.admin();
+    let creator = h.funded_player().await;
+    let stranger = h.funded_player().await;
+    let verifier = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+    let creator_token = h
+        .put_token_account(&rewards.mint, &creator.pubkey(), 0)
+        .await;
+    let stranger_token = h
+        .put_token_account(&rewards.mint, &stranger.pubkey(), 0)
+        .await;
+    let verifier_token = h
+        .put_token_account(&rewards.mint, &verifier.pubkey(), 0)
+        .await;
+
+    // Anyone's verification of a combo that isn't official counts, but
+    // only pays an approved verifier.
+    verify(&mut h, &stranger, address, &rewards, stranger_token).await;
+    assert_eq!(combo(&mut h, &address).await.verification_count, 1);
+    assert_eq!(h.token_balance(&stranger_token).await, 0);
+
+    let ixs = [
+        add_verifier(admin, creator.pubkey()),
+        add_verifier(admin, verifier.pubkey()),
+    ];
+    h.send(&ixs, &[]).await.unwrap();
+    verify(&mut h, &creator, address, &rewards, creator_token).await;
+    assert_eq!(combo(&mut h, &address).await.verification_count, 2);
+    assert_eq!(h.token_balance(&creator_token).await, 0);
+
+    verify(&mut h, &verifier, address, &rewards, verifier_token).await;
+    assert_eq!(combo(&mut h, &address).await.verification_count, 3);
+    assert_eq!(h.token_balance(&verifier_token).await, REWARD);
+}
+
+#[tokio::test]
+async fn each_verifier_is_paid_at_most_its_quota_per_window() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let rewards = rewards(&mut h, 1).await;
+    let admin = h.admin();
+    let creator = h.funded_player().await;
+    let verifiers = [h.funded_player().await, h.funded_player().await];
+    let mut tokens = Vec::new();
+    for verifier in &verifiers {
+        h.send(&[add_verifier(admin, verifier.pubkey())], &[])
+            .await
+            .unwrap();
+        tokens.push(
+            h.put_token_account(&rewards.mint, &verifier.pubkey(), 0)
+                .await,
+        );
+    }
+    let mut combos = Vec::new();
+    for name in ["First", "Second", "Third"] {
+        combos.push(fixtures::combo(&mut h, &creator, name).await);
+    }
+
+    verify(&mut h, &verifiers[0], combos[0], &rewards, tokens[0]).await;
+    verify(&mut h, &verifiers[0], combos[1], &rewards, tokens[0]).await;
+    assert_eq!(combo(&mut h, &combos[1]).await.verification_count, 1);
+    assert_eq!(h.token_balance(&tokens[0]).await, REWARD);
+
+    // The quota is per verifier, so another is still paid.
+    verify(&mut h, &verifiers[1], combos[1], &rewards, tokens[1]).await;
+    assert_eq!(h.token_balance(&tokens[1]).await, REWARD);
+
+    h.advance(REWARD_WINDOW).await;
+    verify(&mut h, &verifiers[0], combos[2], &rewards, tokens[0]).await;
+    assert_eq!(h.token_balance(&tokens[0]).await, 2 * REWARD);
+    let quota: VerifierQuota = h
+        .account(&pda::combo_mint::verifier_quota(&verifiers[0].pubkey()))
+        .await;
+    assert_eq!(quota.paid_in_window, 1);
+}
+
+/// `data` of a current account as it was before `layout_version`: without
+/// the version byte, the `removed` bytes later layouts inserted and the
+/// `appended` bytes they added at the end.
+fn layout_0(data: &[u8], removed: std::ops::Range<usize>, appended: usize) -> Vec<u8> {
+    let mut old = data[..data.len() - appended].to_vec();
+    old.drain(removed);
+    old.remove(8);
+    old
+}
+
+#[tokio::test]
+async fn migrates_accounts_from_before_layout_versions() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+    let creator = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+    let current = combo(&mut h, &address).await;
+    let config: Config = h.account(&pda::combo_mint::config()).await;
+
+    // Combos gained `tags` in layout 3 and `difficulty`, `frozen` and
+    // `tier` at the end; the config gained `moderator`. Layout 0 combos
+    // were sized to their content.
+    let data = h.raw_account(&address).await.unwrap().data;
+    let content = &data[..8 + current.try_to_vec().unwrap().len()];
+    let tags = combo_mint::COMBO_TAGS_OFFSET..combo_mint::COMBO_TAGS_OFFSET + 4;
+    h.put_raw_account(
+        &address,
+        &combo_mint::ID,
+        layout_0(content, tags, 2 + 1 + 1),
+    );
+    let data = h
+        .raw_account(&pda::combo_mint::config())
+        .await
+        .unwrap()
+        .data;
+    let old_config = layout_0(&data, 0..0, 32);
+    h.put_raw_account(&pda::combo_mint::config(), &combo_mint::ID, old_config);
+
+    h.send(&[migrate(admin, address)], &[]).await.unwrap();
+    let migrated = combo(&mut h, &address).await;
+    assert_eq!(migrated.layout_version, 5);
+    assert_eq!(migrated.authority, current.authority);
+    assert_eq!(migrated.character_id, current.character_id);
+    assert_eq!(migrated.tags, 0);
+    assert_eq!(migrated.name, current.name);
+    assert_eq!(migrated.moves, current.moves);
+    assert_eq!(migrated.combo_hash, current.combo_hash);
+    assert_eq!(migrated.creator, current.creator);
+    assert_eq!(migrated.parents, current.parents);
+    assert_eq!(migrated.difficulty, 0);
+    assert_eq!(
+        h.raw_account(&address).await.unwrap().data.len(),
+        content.len()
+    );
+
+    // Resizing gives it room for the longest name and moves.
+    h.send(&[resize(&creator, address)], &[&creator])
+        .await
+        .unwrap();
+    assert_eq!(
+        h.raw_account(&address).await.unwrap().data.len(),
+        8 + ComboAccount::INIT_SPACE
+    );
+    assert_eq!(combo(&mut h, &address).await.moves, current.moves);
+
+    h.send(&[migrate(admin, pda::combo_mint::config())], &[])
+        .await
+        .unwrap();
+    let migrated: Config = h.account(&pda::combo_mint::config()).await;
+    assert_eq!(migrated.layout_version, 2);
+    assert_eq!(migrated.admin, config.admin);
+    assert_eq!(migrated.game_version, config.game_version);
+    assert_eq!(migrated.signers, config.signers);
+    assert_eq!(migrated.verification_cooldown, config.verification_cooldown);
+    assert_eq!(migrated.max_royalty_bps, config.max_royalty_bps);
+    assert_eq!(migrated.bump, config.bump);
+    assert_eq!(migrated.moderator, Pubkey::default());
+
+    assert_error(
+        h.send(&[migrate(admin, address)], &[]).await,
+        ComboError::AlreadyMigrated,
+    );
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let admin = h.admin();
+    let creator = h.funded_player().await;
+    let address = fixtures::combo(&mut h, &creator, "Bread and butter").await;
+
+    for account in [
+        pda::combo_mint::config(),
+        pda::combo_mint::verifier_registry(),
+        pda::combo_mint::name_filter(),
+        address,
+    ] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            ComboError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(
+        &unknown,
+        &combo_mint::ID,
+        vec![9; 8 + ComboAccount::INIT_SPACE],
+    );
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, ComboError::UnsupportedLayout);
+}
@@ -0,0 +1,743 @@
+use anchor_spl::token::spl_token;
+use combo_mint_client::dispute::{
+    inputs_hash, Dispute, DisputeConfig, DisputeError, DisputeStatus, MAX_ARBITERS,
+    MAX_WINDOW_FRAMES, RESPONSE_WINDOW,
+};
+use combo_mint_client::{builders, dispute, instruction, pda};
+use game_core::character::ROSTER;
+use game_core::{checksum, simulate_frame, snapshot, GameState, Input};
+use program_tests::fixtures::{self, CHALLENGE_WINDOW, MIN_STAKE};
+use program_tests::{assert_error, Harness};
+use replay::Replay;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::{system_program, sysvar};
+
+// `DisputeError::Overflow` needs more votes than `MAX_ARBITERS`, and an
+// arbiter votes once, so no test reaches it.
+
+const MATCH_ID: u64 = 1;
+const FUNDS: u64 = 10_000;
+const STAKE: u64 = 1_000;
+/// Frames in the key-frame windows the tests request, and between the
+/// replay's key frames.
+const FRAMES: u16 = 10;
+/// Frames in the recorded replay.
+const MATCH_FRAMES: u32 = 600;
+
+/// The dispute config over a fresh mint with `arbiters` arbiters, and
+/// `replay` recorded as a match won by `winner` against `loser`, each
+/// holding `FUNDS`.
+struct Court {
+    mint: Pubkey,
+    arbiters: Vec<Keypair>,
+    winner: Keypair,
+    loser: Keypair,
+    /// The winner's and the loser's token accounts.
+    tokens: [Pubkey; 2],
+    replay: Replay,
+}
+
+impl Court {
+    async fn new(h: &mut Harness, arbiters: usize, quorum: u8) -> Self {
+        Court::with_replay(h, arbiters, quorum, replay()).await
+    }
+
+    async fn with_replay(h: &mut Harness, arbiters: usize, quorum: u8, replay: Replay) -> Self {
+        let admin = h.admin();
+        let mint = h.put_mint(&admin);
+        let arbiters: Vec<Keypair> = (0..arbiters).map(|_| Keypair::new()).collect();
+        let keys = arbiters.iter().map(Keypair::pubkey).collect();
+        fixtures::dispute(h, &mint, keys, quorum).await;
+        let winner = h.funded_player().await;
+        let loser = h.funded_player().await;
+        let ix = builders::record_match(
+            [winner.pubkey(), loser.pubkey()],
+            MATCH_ID,
+            0,
+            [2, 1],
+            replay.hash(),
+            replay.header.frame_count,
+            replay.checksum_root(),
+        );
+        h.send(&[ix], &[&winner, &loser]).await.unwrap();
+        let tokens = [
+            h.put_token_account(&mint, &winner.pubkey(), FUNDS).await,
+            h.put_token_account(&mint, &loser.pubkey(), FUNDS).await,
+        ];
+        Court {
+            mint,
+            arbiters,
+            winner,
+            loser,
+            tokens,
+            replay,
+        }
+    }
+
+    fn open_dispute(&self, challenger: Pubkey, tokens: Pubkey, stake: u64) -> Instruction {
+        let dispute = pda::dispute::dispute(MATCH_ID);
+        instruction(
+            dispute::ID,
+            dispute::accounts::OpenDispute {
+                challenger,
+                config: pda::dispute::config(),
+                match_account: pda::match_result::match_account(MATCH_ID),
+                dispute,
+                vault: pda::dispute::vault(&dispute),
+                mint: self.mint,
+                challenger_token_account: tokens,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+                rent: sysvar::rent::ID,
+            },
+            dispute::instruction::OpenDispute {
+                claimed_replay_hash: [1; 32],
+                stake,
+            },
+        )
+    }
+
+    /// The loser disputes the match, staking `STAKE`.
+    async fn open(&self, h: &mut Harness) {
+        let ix = self.open_dispute(self.loser.pubkey(), self.tokens[1], STAKE);
+        h.send(&[ix], &[&self.loser]).await.unwrap();
+    }
+
+    fn answer_dispute(&self) -> Instruction {
+        let dispute = pda::dispute::dispute(MATCH_ID);
+        instruction(
+            dispute::ID,
+            dispute::accounts::AnswerDispute {
+                respondent: self.winner.pubkey(),
+                dispute,
+                vault: pda::dispute::vault(&dispute),
+                mint: self.mint,
+                respondent_token_account: self.tokens[0],
+                token_program: spl_token::ID,
+            },
+            dispute::instruction::AnswerDispute {},
+        )
+    }
+
+    /// `open`, and the winner matches the stake.
+    async fn answered(&self, h: &mut Harness) {
+        self.open(h).await;
+        h.send(&[self.answer_dispute()], &[&self.winner])
+            .await
+            .unwrap();
+    }
+
+    /// The replay's checksum at `frame` and its proof against the match's
+    /// `checksum_root`, or a zero checksum with no proof if `frame` isn't a
+    /// key frame.
+    fn key_frame(&self, frame: u32) -> (u64, Vec<[u8; 32]>) {
+        let checksum = self.replay.checksums.iter().find(|c| c.frame == frame);
+        match (checksum, self.replay.checksum_proof(frame)) {
+            (Some(checksum), Some(proof)) => (checksum.checksum, proof),
+            _ => (0, Vec::new()),
+        }
+    }
+
+    fn request_window(
+        &self,
+        start_frame: u32,
+        frames: u16,
+        (start_checksum, start_proof): (u64, Vec<[u8; 32]>),
+        (end_checksum, end_proof): (u64, Vec<[u8; 32]>),
+    ) -> Instruction {
+        instruction(
+            dispute::ID,
+            dispute::accounts::RequestKeyFrames {
+                challenger: self.loser.pubkey(),
+                dispute: pda::dispute::dispute(MATCH_ID),
+            },
+            dispute::instruction::RequestKeyFrames {
+                start_frame,
+                frames,
+                start_checksum,
+                start_proof,
+                end_checksum,
+                end_proof,
+            },
+        )
+    }
+
+    /// Asks for the window with the replay's key frames at its ends.
+    fn request_key_frames(&self, start_frame: u32, frames: u16) -> Instruction {
+        let end_frame = start_frame + u32::from(frames);
+        self.request_window(
+            start_frame,
+            frames,
+            self.key_frame(start_frame),
+            self.key_frame(end_frame),
+        )
+    }
+
+    fn post_key_frames(&self, inputs: &[u16]) -> Instruction {
+        instruction(
+            dispute::ID,
+            dispute::accounts::PostKeyFrames {
+                respondent: self.winner.pubkey(),
+                dispute: pda::dispute::dispute(MATCH_ID),
+            },
+            dispute::instruction::PostKeyFrames {
+                inputs_hash: inputs_hash(inputs),
+            },
+        )
+    }
+
+    /// `answered`, then the loser asks for the first `FRAMES` frames and the
+    /// winner attests to `inputs` over them.
+    async fn attested(&self, h: &mut Harness, inputs: &[u16]) {
+        self.answered(h).await;
+        let ixs = [
+            self.request_key_frames(0, FRAMES),
+            self.post_key_frames(inputs),
+        ];
+        h.send(&ixs, &[&self.loser, &self.winner]).await.unwrap();
+    }
+
+    /// The replay's state at `start_frame`, its inputs over `frames` frames
+    /// and the state they leave.
+    fn window(&self, start_frame: u32, frames: u16) -> (GameState, Vec<u16>, GameState) {
+        let mut state = GameState::with_seed(&ROSTER[0], &ROSTER[1], 0);
+        for &frame_inputs in &self.replay.frames[..start_frame as usize] {
+            state = simulate_frame(&state, frame_inputs);
+        }
+        let start = state;
+        let mut inputs = Vec::new();
+        let end_frame = start_frame as usize + frames as usize;
+        for &frame_inputs in &self.replay.frames[start_frame as usize..end_frame] {
+            inputs.extend(frame_inputs.map(Input::bits));
+            state = simulate_frame(&state, frame_inputs);
+        }
+        (start, inputs, state)
+    }
+
+    fn vote(&self, arbiter: Pubkey, uphold: bool) -> Instruction {
+        instruction(
+            dispute::ID,
+            dispute::accounts::Vote {
+                arbiter,
+                config: pda::dispute::config(),
+                dispute: pda::dispute::dispute(MATCH_ID),
+            },
+            dispute::instruction::Vote { uphold },
+        )
+    }
+
+    fn resolve_accounts(&self) -> dispute::accounts::ResolveDispute {
+        let dispute = pda::dispute::dispute(MATCH_ID);
+        dispute::accounts::ResolveDispute {
+            config: pda::dispute::config(),
+            dispute,
+            vault: pda::dispute::vault(&dispute),
+            mint: self.mint,
+            challenger_token_account: self.tokens[1],
+            respondent_token_account: self.tokens[0],
+            token_program: spl_token::ID,
+        }
+    }
+
+    fn resolve_dispute(&self) -> Instruction {
+        instruction(
+            dispute::ID,
+            self.resolve_accounts(),
+            dispute::instruction::ResolveDispute {},
+        )
+    }
+
+    fn verify_transition(
+        &self,
+        start_state: Vec<u8>,
+        inputs: Vec<u16>,
+        claimed_state: Vec<u8>,
+    ) -> Instruction {
+        instruction(
+            dispute::ID,
+            self.resolve_accounts(),
+            dispute::instruction::VerifyTransition {
+                start_state,
+                inputs,
+                claimed_state,
+            },
+        )
+    }
+
+    async fn balances(&self, h: &mut Harness) -> [u64; 2] {
+        [
+            h.token_balance(&self.tokens[0]).await,
+            h.token_balance(&self.tokens[1]).await,
+        ]
+    }
+}
+
+fn initialize(admin: Pubkey, mint: Pubkey, arbiters: Vec<Pubkey>, quorum: u8) -> Instruction {
+    instruction(
+        dispute::ID,
+        dispute::accounts::Initialize {
+            admin,
+            config: pda::dispute::config(),
+            mint,
+            system_program: system_program::ID,
+        },
+        dispute::instruction::Initialize {
+            arbiters,
+            quorum,
+            challenge_window: CHALLENGE_WINDOW,
+            min_stake: MIN_STAKE,
+        },
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        dispute::ID,
+        dispute::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        dispute::instruction::MigrateAccount {},
+    )
+}
+
+async fn dispute(h: &mut Harness) -> Dispute {
+    h.account(&pda::dispute::dispute(MATCH_ID)).await
+}
+
+/// `MATCH_FRAMES` frames of both players walking in and throwing an attack
+/// every `FRAMES`th frame, with a key frame every `FRAMES` frames.
+fn replay() -> Replay {
+    let mut replay = Replay::new(1, [ROSTER[0].id, ROSTER[1].id], 0);
+    for frame in 0..MATCH_FRAMES {
+        if frame % u32::from(FRAMES) == u32::from(FRAMES) - 1 {
+            replay.record([Input::ATTACK, Input::ATTACK]);
+        } else {
+            replay.record([Input::RIGHT, Input::LEFT]);
+        }
+    }
+    replay.embed_checksums(u32::from(FRAMES)).unwrap();
+    replay
+}
+
+fn encode(state: &GameState) -> Vec<u8> {
+    snapshot::encode(state).to_vec()
+}
+
+#[tokio::test]
+async fn committees_need_arbiters_and_a_reachable_quorum() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let mint = h.put_mint(&admin);
+    let arbiters: Vec<Pubkey> = (0..=MAX_ARBITERS).map(|_| Pubkey::new_unique()).collect();
+
+    for (arbiters, quorum) in [
+        (vec![], 0),
+        (arbiters.clone(), 1),
+        (arbiters[..3].to_vec(), 0),
+        (arbiters[..3].to_vec(), 4),
+    ] {
+        let ix = initialize(admin, mint, arbiters, quorum);
+        assert_error(h.send(&[ix], &[]).await, DisputeError::InvalidCommittee);
+    }
+
+    let committee = arbiters[..MAX_ARBITERS].to_vec();
+    let ix = initialize(admin, mint, committee.clone(), 4);
+    h.send(&[ix], &[]).await.unwrap();
+    let config: DisputeConfig = h.account(&pda::dispute::config()).await;
+    assert_eq!(config.mint, mint);
+    assert_eq!(config.arbiters.to_vec(), committee);
+    assert_eq!(config.arbiter_count, MAX_ARBITERS as u8);
+    assert_eq!(config.quorum, 4);
+    assert_eq!(config.challenge_window, CHALLENGE_WINDOW);
+    assert_eq!(config.min_stake, MIN_STAKE);
+}
+
+#[tokio::test]
+async fn disputes_are_staked_in_a_supported_mint() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let mint = h.put_unsupported_mint(&admin);
+
+    let ix = initialize(admin, mint, vec![admin], 1);
+    assert_error(h.send(&[ix], &[]).await, DisputeError::UnsupportedMint);
+}
+
+#[tokio::test]
+async fn the_loser_stakes_on_a_dispute() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+
+    let ix = court.open_dispute(court.winner.pubkey(), court.tokens[0], STAKE);
+    assert_error(
+        h.send(&[ix], &[&court.winner]).await,
+        DisputeError::NotLoser,
+    );
+    let ix = court.open_dispute(court.loser.pubkey(), court.tokens[1], MIN_STAKE - 1);
+    assert_error(
+        h.send(&[ix], &[&court.loser]).await,
+        DisputeError::StakeTooLow,
+    );
+
+    court.open(&mut h).await;
+    let dispute = dispute(&mut h).await;
+    assert_eq!(dispute.challenger, court.loser.pubkey());
+    assert_eq!(dispute.respondent, court.winner.pubkey());
+    assert_eq!(dispute.recorded_replay_hash, court.replay.hash());
+    assert_eq!(dispute.claimed_replay_hash, [1; 32]);
+    assert_eq!(dispute.stake, STAKE);
+    assert_eq!(dispute.status, DisputeStatus::AwaitingResponse);
+    assert_eq!(dispute.checksum_root, court.replay.checksum_root());
+    let vault = pda::dispute::vault(&pda::dispute::dispute(MATCH_ID));
+    assert_eq!(h.token_balance(&vault).await, STAKE);
+    assert_eq!(court.balances(&mut h).await, [FUNDS, FUNDS - STAKE]);
+}
+
+#[tokio::test]
+async fn only_final_results_are_disputed() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let mint = h.put_mint(&admin);
+    fixtures::dispute(&mut h, &mint, vec![admin], 1).await;
+    let players = [h.funded_player().await, h.funded_player().await];
+    let [one, two] = &players;
+    fixtures::open_set(&mut h, MATCH_ID, [one, two]).await;
+    let tokens = h.put_token_account(&mint, &two.pubkey(), FUNDS).await;
+
+    let dispute = pda::dispute::dispute(MATCH_ID);
+    let ix = instruction(
+        dispute::ID,
+        dispute::accounts::OpenDispute {
+            challenger: two.pubkey(),
+            config: pda::dispute::config(),
+            match_account: pda::match_result::match_account(MATCH_ID),
+            dispute,
+            vault: pda::dispute::vault(&dispute),
+            mint,
+            challenger_token_account: tokens,
+            token_program: spl_token::ID,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        dispute::instruction::OpenDispute {
+            claimed_replay_hash: [1; 32],
+            stake: STAKE,
+        },
+    );
+    assert_error(h.send(&[ix], &[two]).await, DisputeError::MatchNotFinalized);
+}
+
+#[tokio::test]
+async fn the_challenge_window_closes() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+
+    h.advance(CHALLENGE_WINDOW).await;
+    let ix = court.open_dispute(court.loser.pubkey(), court.tokens[1], STAKE);
+    assert_error(
+        h.send(&[ix], &[&court.loser]).await,
+        DisputeError::ChallengeWindowClosed,
+    );
+}
+
+#[tokio::test]
+async fn the_winner_matches_the_stake() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+
+    court.answered(&mut h).await;
+    assert_eq!(dispute(&mut h).await.status, DisputeStatus::Voting);
+    let vault = pda::dispute::vault(&pda::dispute::dispute(MATCH_ID));
+    assert_eq!(h.token_balance(&vault).await, 2 * STAKE);
+    assert_eq!(court.balances(&mut h).await, [FUNDS - STAKE; 2]);
+
+    assert_error(
+        h.send(&[court.answer_dispute()], &[&court.winner]).await,
+        DisputeError::WrongStatus,
+    );
+}
+
+#[tokio::test]
+async fn unanswered_disputes_are_upheld_after_the_response_window() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+    court.open(&mut h).await;
+
+    assert_error(
+        h.send(&[court.resolve_dispute()], &[]).await,
+        DisputeError::NotResolvable,
+    );
+    h.advance(RESPONSE_WINDOW).await;
+    assert_error(
+        h.send(&[court.answer_dispute()], &[&court.winner]).await,
+        DisputeError::ResponseExpired,
+    );
+
+    h.send(&[court.resolve_dispute()], &[]).await.unwrap();
+    assert_eq!(dispute(&mut h).await.status, DisputeStatus::Upheld);
+    assert_eq!(court.balances(&mut h).await, [FUNDS; 2]);
+}
+
+#[tokio::test]
+async fn a_quorum_of_arbiters_resolves_the_dispute() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 3, 2).await;
+    let [first, second, third] = &court.arbiters[..] else {
+        unreachable!()
+    };
+
+    court.open(&mut h).await;
+    let ix = court.vote(first.pubkey(), true);
+    assert_error(h.send(&[ix], &[first]).await, DisputeError::WrongStatus);
+    h.send(&[court.answer_dispute()], &[&court.winner])
+        .await
+        .unwrap();
+    let stranger = Keypair::new();
+    let ix = court.vote(stranger.pubkey(), true);
+    assert_error(h.send(&[ix], &[&stranger]).await, DisputeError::NotArbiter);
+
+    let ixs = [
+        court.vote(first.pubkey(), true),
+        court.vote(second.pubkey(), false),
+    ];
+    h.send(&ixs, &[first, second]).await.unwrap();
+    let ix = court.vote(second.pubkey(), true);
+    assert_error(h.send(&[ix], &[second]).await, DisputeError::AlreadyVoted);
+    let dispute_account = dispute(&mut h).await;
+    assert_eq!(dispute_account.votes_for, 1);
+    assert_eq!(dispute_account.votes_against, 1);
+    assert_eq!(dispute_account.voted, 0b011);
+    assert_error(
+        h.send(&[court.resolve_dispute()], &[]).await,
+        DisputeError::NotResolvable,
+    );
+
+    let ix = court.vote(third.pubkey(), false);
+    h.send(&[ix], &[third]).await.unwrap();
+    h.send(&[court.resolve_dispute()], &[]).await.unwrap();
+    assert_eq!(dispute(&mut h).await.status, DisputeStatus::Rejected);
+    assert_eq!(court.balances(&mut h).await, [FUNDS + STAKE, FUNDS - STAKE]);
+    assert_error(
+        h.send(&[court.resolve_dispute()], &[]).await,
+        DisputeError::NotResolvable,
+    );
+}
+
+#[tokio::test]
+async fn key_frame_windows_fit_in_the_match() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+    court.open(&mut h).await;
+    let ix = court.request_key_frames(0, FRAMES);
+    assert_error(
+        h.send(&[ix], &[&court.loser]).await,
+        DisputeError::WrongStatus,
+    );
+    h.send(&[court.answer_dispute()], &[&court.winner])
+        .await
+        .unwrap();
+
+    let frame_count = dispute(&mut h).await.frame_count;
+    for (start_frame, frames) in [
+        (0, 0),
+        (0, MAX_WINDOW_FRAMES + 1),
+        (frame_count - u32::from(FRAMES) + 1, FRAMES),
+    ] {
+        let ix = court.request_key_frames(start_frame, frames);
+        assert_error(
+            h.send(&[ix], &[&court.loser]).await,
+            DisputeError::InvalidWindow,
+        );
+    }
+
+    let start_frame = frame_count - u32::from(MAX_WINDOW_FRAMES);
+    let ix = court.request_key_frames(start_frame, MAX_WINDOW_FRAMES);
+    h.send(&[ix], &[&court.loser]).await.unwrap();
+    let dispute_account = dispute(&mut h).await;
+    assert_eq!(dispute_account.status, DisputeStatus::AwaitingKeyFrames);
+    assert_eq!(dispute_account.window_start, start_frame);
+    assert_eq!(dispute_account.window_frames, MAX_WINDOW_FRAMES);
+    let (start, inputs, end) = court.window(start_frame, MAX_WINDOW_FRAMES);
+    assert_eq!(dispute_account.start_checksum, checksum(&start));
+    assert_eq!(dispute_account.end_checksum, checksum(&end));
+
+    let ix = court.post_key_frames(&inputs);
+    h.send(&[ix], &[&court.winner]).await.unwrap();
+    let dispute_account = dispute(&mut h).await;
+    assert_eq!(dispute_account.status, DisputeStatus::Simulating);
+    assert_eq!(dispute_account.inputs_hash, inputs_hash(&inputs));
+    let ix = court.post_key_frames(&inputs);
+    assert_error(
+        h.send(&[ix], &[&court.winner]).await,
+        DisputeError::WrongStatus,
+    );
+}
+
+#[tokio::test]
+async fn window_ends_are_key_frames_of_the_recorded_match() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+    court.answered(&mut h).await;
+
+    let frames = u32::from(FRAMES);
+    let (checksum, proof) = court.key_frame(0);
+    for ix in [
+        // Neither end of the window is a key frame.
+        court.request_key_frames(1, FRAMES),
+        // The right checksum under another frame's proof.
+        court.request_window(
+            0,
+            FRAMES,
+            (checksum, court.key_frame(frames).1),
+            court.key_frame(frames),
+        ),
+        // Another checksum under the right proof.
+        court.request_window(0, FRAMES, (checksum + 1, proof), court.key_frame(frames)),
+        // The start frame's checksum at the window's end.
+        court.request_window(0, FRAMES, court.key_frame(0), court.key_frame(0)),
+    ] {
+        assert_error(
+            h.send(&[ix], &[&court.loser]).await,
+            DisputeError::ChecksumNotCommitted,
+        );
+    }
+
+    let ix = court.request_key_frames(frames, 2 * FRAMES);
+    h.send(&[ix], &[&court.loser]).await.unwrap();
+    assert_eq!(
+        dispute(&mut h).await.status,
+        DisputeStatus::AwaitingKeyFrames
+    );
+}
+
+#[tokio::test]
+async fn matches_without_key_frames_are_left_to_the_committee() {
+    let mut h = Harness::start().await;
+    let mut replay = replay();
+    replay.checksums.clear();
+    let court = Court::with_replay(&mut h, 1, 1, replay).await;
+    court.answered(&mut h).await;
+    assert_eq!(dispute(&mut h).await.checksum_root, [0; 32]);
+
+    let ix = court.request_window(0, FRAMES, (0, Vec::new()), (0, Vec::new()));
+    assert_error(
+        h.send(&[ix], &[&court.loser]).await,
+        DisputeError::NoKeyFrames,
+    );
+}
+
+#[tokio::test]
+async fn unattested_windows_are_upheld_after_the_response_window() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+    court.answered(&mut h).await;
+    let ix = court.request_key_frames(0, FRAMES);
+    h.send(&[ix], &[&court.loser]).await.unwrap();
+
+    assert_error(
+        h.send(&[court.resolve_dispute()], &[]).await,
+        DisputeError::NotResolvable,
+    );
+    h.advance(RESPONSE_WINDOW).await;
+    let (_, inputs, _) = court.window(0, FRAMES);
+    let ix = court.post_key_frames(&inputs);
+    assert_error(
+        h.send(&[ix], &[&court.winner]).await,
+        DisputeError::ResponseExpired,
+    );
+
+    h.send(&[court.resolve_dispute()], &[]).await.unwrap();
+    assert_eq!(dispute(&mut h).await.status, DisputeStatus::Upheld);
+    assert_eq!(court.balances(&mut h).await, [FUNDS - STAKE, FUNDS + STAKE]);
+}
+
+#[tokio::test]
+async fn inputs_that_reach_the_next_key_frame_reject_the_dispute() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+    let (start, inputs, end) = court.window(0, FRAMES);
+    let ix = court.verify_transition(encode(&start), inputs.clone(), encode(&end));
+    // Nothing is attested before the challenger asks for a window.
+    court.answered(&mut h).await;
+    assert_error(h.send(&[ix], &[]).await, DisputeError::WrongStatus);
+    let ixs = [
+        court.request_key_frames(0, FRAMES),
+        court.post_key_frames(&inputs),
+    ];
+    h.send(&ixs, &[&court.loser, &court.winner]).await.unwrap();
+
+    let ix = court.verify_transition(vec![0; 3], inputs.clone(), encode(&end));
+    assert_error(h.send(&[ix], &[]).await, DisputeError::InvalidSnapshot);
+    let ix = court.verify_transition(encode(&start), inputs.clone(), vec![0; 3]);
+    assert_error(h.send(&[ix], &[]).await, DisputeError::InvalidSnapshot);
+    let ix = court.verify_transition(encode(&end), inputs.clone(), encode(&end));
+    assert_error(h.send(&[ix], &[]).await, DisputeError::StateMismatch);
+    let ix = court.verify_transition(encode(&start), inputs.clone(), encode(&start));
+    assert_error(h.send(&[ix], &[]).await, DisputeError::StateMismatch);
+    let mut tampered = inputs.clone();
+    tampered[0] = Input::NONE.bits();
+    let ix = court.verify_transition(encode(&start), tampered, encode(&end));
+    assert_error(h.send(&[ix], &[]).await, DisputeError::InputsMismatch);
+    let ix = court.verify_transition(encode(&start), inputs[2..].to_vec(), encode(&end));
+    assert_error(h.send(&[ix], &[]).await, DisputeError::InputsMismatch);
+
+    let ix = court.verify_transition(encode(&start), inputs, encode(&end));
+    h.send(&[ix], &[]).await.unwrap();
+    assert_eq!(dispute(&mut h).await.status, DisputeStatus::Rejected);
+    assert_eq!(court.balances(&mut h).await, [FUNDS + STAKE, FUNDS - STAKE]);
+}
+
+#[tokio::test]
+async fn inputs_that_miss_the_next_key_frame_uphold_the_dispute() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+    let (start, _, end) = court.window(0, FRAMES);
+    // The winner attests that the players stood still.
+    let inputs = vec![Input::NONE.bits(); 2 * FRAMES as usize];
+    court.attested(&mut h, &inputs).await;
+
+    let ix = court.verify_transition(encode(&start), inputs, encode(&end));
+    h.send(&[ix], &[]).await.unwrap();
+    assert_eq!(dispute(&mut h).await.status, DisputeStatus::Upheld);
+    assert_eq!(court.balances(&mut h).await, [FUNDS - STAKE, FUNDS + STAKE]);
+}
+
+#[tokio::test]
+async fn inputs_must_be_valid_bits() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+    let (start, mut inputs, end) = court.window(0, FRAMES);
+    inputs[0] = u16::MAX;
+    court.attested(&mut h, &inputs).await;
+
+    let ix = court.verify_transition(encode(&start), inputs, encode(&end));
+    assert_error(h.send(&[ix], &[]).await, DisputeError::InputsMismatch);
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let court = Court::new(&mut h, 1, 1).await;
+    court.open(&mut h).await;
+    let admin = h.admin();
+
+    for account in [pda::dispute::config(), pda::dispute::dispute(MATCH_ID)] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            DisputeError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &dispute::ID, vec![9; Dispute::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, DisputeError::UnsupportedLayout);
+}
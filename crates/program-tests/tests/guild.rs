@@ -0,0 +1,712 @@
+use anchor_lang::error::ErrorCode;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use combo_mint_client::guild::{
+    Guild, GuildError, GuildInvite, GuildLeaderboard, GuildMatchTag, GuildMember, GuildStanding,
+    Role, SpendProposal, PROPOSAL_TTL,
+};
+use combo_mint_client::{guild, instruction, pda};
+use program_tests::fixtures;
+use program_tests::{assert_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `GuildError::Overflow` needs more than `u32::MAX` tagged matches or
+// `u64::MAX` proposals, so no test reaches it.
+
+const NAME: &str = "Night Shift";
+const MATCH_ID: u64 = 1;
+/// What the treasury starts with.
+const FUNDS: u64 = 10_000;
+
+/// `NAME`, led by a funded player, with `FUNDS` in its treasury and spends
+/// needing two approvals.
+struct Crew {
+    leader: Keypair,
+    address: Pubkey,
+    mint: Pubkey,
+}
+
+impl Crew {
+    async fn new(h: &mut Harness) -> Self {
+        let leader = h.funded_player().await;
+        let admin = h.admin();
+        let mint = h.put_mint(&admin);
+        let ix = create_guild(leader.pubkey(), NAME, mint, spl_token::ID, 2);
+        h.send(&[ix], &[&leader]).await.unwrap();
+        let address = pda::guild::guild(NAME);
+        let funds = h.put_token_account(&mint, &leader.pubkey(), FUNDS).await;
+        let ix = spl_token::instruction::transfer(
+            &spl_token::ID,
+            &funds,
+            &pda::guild::treasury(&address),
+            &leader.pubkey(),
+            &[],
+            FUNDS,
+        )
+        .unwrap();
+        h.send(&[ix], &[&leader]).await.unwrap();
+        Crew {
+            leader,
+            address,
+            mint,
+        }
+    }
+
+    fn invite(&self, inviter: Pubkey, invitee: Pubkey) -> Instruction {
+        instruction(
+            guild::ID,
+            guild::accounts::InviteMember {
+                inviter,
+                inviter_member: pda::guild::member(&inviter),
+                guild: self.address,
+                invite: pda::guild::invite(&self.address, &invitee),
+                system_program: system_program::ID,
+            },
+            guild::instruction::InviteMember { invitee },
+        )
+    }
+
+    fn accept(&self, invitee: Pubkey, inviter: Pubkey) -> Instruction {
+        instruction(
+            guild::ID,
+            guild::accounts::AcceptInvite {
+                invitee,
+                invite: pda::guild::invite(&self.address, &invitee),
+                inviter,
+                guild: self.address,
+                member: pda::guild::member(&invitee),
+                system_program: system_program::ID,
+            },
+            guild::instruction::AcceptInvite {},
+        )
+    }
+
+    /// A funded player the leader invites and who accepts.
+    async fn join(&self, h: &mut Harness) -> Keypair {
+        let member = h.funded_player().await;
+        let ixs = [
+            self.invite(self.leader.pubkey(), member.pubkey()),
+            self.accept(member.pubkey(), self.leader.pubkey()),
+        ];
+        h.send(&ixs, &[&self.leader, &member]).await.unwrap();
+        member
+    }
+
+    fn set_role(&self, wallet: Pubkey, role: Role) -> Instruction {
+        set_role(self.leader.pubkey(), self.address, wallet, role)
+    }
+
+    /// `join`, made an officer.
+    async fn officer(&self, h: &mut Harness) -> Keypair {
+        let officer = self.join(h).await;
+        let ix = self.set_role(officer.pubkey(), Role::Officer);
+        h.send(&[ix], &[&self.leader]).await.unwrap();
+        officer
+    }
+
+    fn kick(&self, officer: Pubkey, wallet: Pubkey) -> Instruction {
+        instruction(
+            guild::ID,
+            guild::accounts::KickMember {
+                officer,
+                officer_member: pda::guild::member(&officer),
+                member: pda::guild::member(&wallet),
+                wallet,
+                guild: self.address,
+            },
+            guild::instruction::KickMember {},
+        )
+    }
+
+    fn leave(&self, wallet: Pubkey) -> Instruction {
+        instruction(
+            guild::ID,
+            guild::accounts::LeaveGuild {
+                wallet,
+                member: pda::guild::member(&wallet),
+                guild: self.address,
+            },
+            guild::instruction::LeaveGuild {},
+        )
+    }
+
+    fn set_approval_threshold(&self, approval_threshold: u8) -> Instruction {
+        instruction(
+            guild::ID,
+            guild::accounts::SetApprovalThreshold {
+                leader: self.leader.pubkey(),
+                guild: self.address,
+            },
+            guild::instruction::SetApprovalThreshold { approval_threshold },
+        )
+    }
+
+    fn propose(
+        &self,
+        proposer: Pubkey,
+        proposal_id: u64,
+        recipient: Pubkey,
+        amount: u64,
+    ) -> Instruction {
+        instruction(
+            guild::ID,
+            guild::accounts::ProposeSpend {
+                proposer,
+                proposer_member: pda::guild::member(&proposer),
+                guild: self.address,
+                proposal: pda::guild::proposal(&self.address, proposal_id),
+                recipient,
+                system_program: system_program::ID,
+            },
+            guild::instruction::ProposeSpend { amount },
+        )
+    }
+
+    fn approve(&self, approver: Pubkey, proposal_id: u64) -> Instruction {
+        instruction(
+            guild::ID,
+            guild::accounts::ApproveSpend {
+                approver,
+                approver_member: pda::guild::member(&approver),
+                proposal: pda::guild::proposal(&self.address, proposal_id),
+            },
+            guild::instruction::ApproveSpend {},
+        )
+    }
+
+    fn execute(&self, proposal_id: u64, recipient: Pubkey) -> Instruction {
+        instruction(
+            guild::ID,
+            guild::accounts::ExecuteSpend {
+                guild: self.address,
+                proposal: pda::guild::proposal(&self.address, proposal_id),
+                treasury: pda::guild::treasury(&self.address),
+                recipient,
+                mint: self.mint,
+                token_program: spl_token::ID,
+            },
+            guild::instruction::ExecuteSpend {},
+        )
+    }
+
+    async fn guild(&self, h: &mut Harness) -> Guild {
+        h.account(&self.address).await
+    }
+}
+
+fn create_guild(
+    leader: Pubkey,
+    name: &str,
+    mint: Pubkey,
+    token_program: Pubkey,
+    approval_threshold: u8,
+) -> Instruction {
+    let guild = pda::guild::guild(name);
+    instruction(
+        guild::ID,
+        guild::accounts::CreateGuild {
+            leader,
+            guild,
+            member: pda::guild::member(&leader),
+            mint,
+            treasury: pda::guild::treasury(&guild),
+            token_program,
+            system_program: system_program::ID,
+        },
+        guild::instruction::CreateGuild {
+            name: name.to_string(),
+            approval_threshold,
+        },
+    )
+}
+
+fn set_role(leader: Pubkey, guild: Pubkey, wallet: Pubkey, role: Role) -> Instruction {
+    instruction(
+        guild::ID,
+        guild::accounts::SetRole {
+            leader,
+            guild,
+            member: pda::guild::member(&wallet),
+        },
+        guild::instruction::SetRole { role },
+    )
+}
+
+fn transfer_leadership(leader: Pubkey, guild: Pubkey, wallet: Pubkey) -> Instruction {
+    instruction(
+        guild::ID,
+        guild::accounts::TransferLeadership {
+            leader,
+            guild,
+            leader_member: pda::guild::member(&leader),
+            member: pda::guild::member(&wallet),
+        },
+        guild::instruction::TransferLeadership {},
+    )
+}
+
+fn tag_match(payer: Pubkey, player: Pubkey, guild: Pubkey, side: u8) -> Instruction {
+    instruction(
+        guild::ID,
+        guild::accounts::TagMatch {
+            payer,
+            match_account: pda::match_result::match_account(MATCH_ID),
+            member: pda::guild::member(&player),
+            guild,
+            tag: pda::guild::match_tag(MATCH_ID, side),
+            leaderboard: pda::guild::leaderboard(),
+            system_program: system_program::ID,
+        },
+        guild::instruction::TagMatch { side },
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        guild::ID,
+        guild::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        guild::instruction::MigrateAccount {},
+    )
+}
+
+async fn member(h: &mut Harness, wallet: &Keypair) -> GuildMember {
+    h.account(&pda::guild::member(&wallet.pubkey())).await
+}
+
+#[tokio::test]
+async fn creates_a_guild_led_by_its_creator() {
+    let mut h = Harness::start().await;
+    let leader = h.funded_player().await;
+    let admin = h.admin();
+    let mint = h.put_mint(&admin);
+
+    let too_long = "x".repeat(Guild::MAX_NAME_LEN + 1);
+    for name in ["", too_long.as_str()] {
+        let ix = create_guild(leader.pubkey(), name, mint, spl_token::ID, 1);
+        assert_error(h.send(&[ix], &[&leader]).await, GuildError::InvalidName);
+    }
+    for threshold in [0, SpendProposal::MAX_APPROVALS as u8 + 1] {
+        let ix = create_guild(leader.pubkey(), NAME, mint, spl_token::ID, threshold);
+        assert_error(
+            h.send(&[ix], &[&leader]).await,
+            GuildError::InvalidThreshold,
+        );
+    }
+
+    let ix = create_guild(leader.pubkey(), NAME, mint, spl_token::ID, 1);
+    h.send(&[ix], &[&leader]).await.unwrap();
+    let address = pda::guild::guild(NAME);
+    let guild: Guild = h.account(&address).await;
+    assert_eq!(guild.name, NAME);
+    assert_eq!(guild.leader, leader.pubkey());
+    assert_eq!(guild.treasury, pda::guild::treasury(&address));
+    assert_eq!(guild.mint, mint);
+    assert_eq!(guild.member_count, 1);
+    assert_eq!(guild.approval_threshold, 1);
+    let member = member(&mut h, &leader).await;
+    assert_eq!(member.guild, address);
+    assert_eq!(member.role, Role::Leader);
+    assert_eq!(h.token_balance(&guild.treasury).await, 0);
+}
+
+#[tokio::test]
+async fn treasuries_hold_a_supported_mint() {
+    let mut h = Harness::start().await;
+    let leader = h.funded_player().await;
+    let admin = h.admin();
+    let mint = h.put_unsupported_mint(&admin);
+
+    let ix = create_guild(leader.pubkey(), NAME, mint, spl_token_2022::ID, 1);
+    assert_error(h.send(&[ix], &[&leader]).await, GuildError::UnsupportedMint);
+}
+
+#[tokio::test]
+async fn officers_invite_members() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let member = crew.join(&mut h).await;
+    let guild = crew.guild(&mut h).await;
+    assert_eq!(guild.member_count, 2);
+    let joined = self::member(&mut h, &member).await;
+    assert_eq!(joined.guild, crew.address);
+    assert_eq!(joined.role, Role::Member);
+    assert!(
+        !h.exists(&pda::guild::invite(&crew.address, &member.pubkey()))
+            .await
+    );
+
+    let invitee = Keypair::new().pubkey();
+    let ix = crew.invite(member.pubkey(), invitee);
+    assert_error(
+        h.send(&[ix], &[&member]).await,
+        GuildError::InsufficientRole,
+    );
+    let outsider = h.funded_player().await;
+    let ix = create_guild(outsider.pubkey(), "Day Shift", crew.mint, spl_token::ID, 1);
+    h.send(&[ix], &[&outsider]).await.unwrap();
+    let ix = crew.invite(outsider.pubkey(), invitee);
+    assert_error(h.send(&[ix], &[&outsider]).await, GuildError::NotAMember);
+
+    let ix = crew.set_role(member.pubkey(), Role::Officer);
+    h.send(&[ix], &[&crew.leader]).await.unwrap();
+    let ix = crew.invite(member.pubkey(), invitee);
+    h.send(&[ix], &[&member]).await.unwrap();
+    let invite: GuildInvite = h
+        .account(&pda::guild::invite(&crew.address, &invitee))
+        .await;
+    assert_eq!(invite.inviter, member.pubkey());
+}
+
+#[tokio::test]
+async fn a_full_guild_takes_no_one() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    // Filling the guild takes `MAX_MEMBERS` wallets; only the count
+    // matters, so it is written straight into the guild.
+    let mut guild = crew.guild(&mut h).await;
+    guild.member_count = Guild::MAX_MEMBERS;
+    h.put_account(&crew.address, &guild::ID, &guild, Guild::SPACE);
+
+    let invitee = h.funded_player().await;
+    let ixs = [
+        crew.invite(crew.leader.pubkey(), invitee.pubkey()),
+        crew.accept(invitee.pubkey(), crew.leader.pubkey()),
+    ];
+    assert_error(
+        h.send(&ixs, &[&crew.leader, &invitee]).await,
+        GuildError::GuildFull,
+    );
+}
+
+#[tokio::test]
+async fn the_leader_sets_roles_below_leader() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let member = crew.join(&mut h).await;
+
+    let ix = crew.set_role(member.pubkey(), Role::Leader);
+    assert_error(
+        h.send(&[ix], &[&crew.leader]).await,
+        GuildError::InvalidRole,
+    );
+    let ix = crew.set_role(crew.leader.pubkey(), Role::Member);
+    assert_error(
+        h.send(&[ix], &[&crew.leader]).await,
+        GuildError::InvalidRole,
+    );
+    let outsider = h.funded_player().await;
+    let ix = create_guild(outsider.pubkey(), "Day Shift", crew.mint, spl_token::ID, 1);
+    h.send(&[ix], &[&outsider]).await.unwrap();
+    let ix = crew.set_role(outsider.pubkey(), Role::Officer);
+    assert_error(h.send(&[ix], &[&crew.leader]).await, GuildError::NotAMember);
+
+    for role in [Role::Officer, Role::Member] {
+        let ix = crew.set_role(member.pubkey(), role);
+        h.send(&[ix], &[&crew.leader]).await.unwrap();
+        assert_eq!(self::member(&mut h, &member).await.role, role);
+    }
+}
+
+#[tokio::test]
+async fn officers_kick_lower_ranks() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let officer = crew.officer(&mut h).await;
+    let member = crew.join(&mut h).await;
+    let other = crew.join(&mut h).await;
+
+    let ix = crew.kick(member.pubkey(), other.pubkey());
+    assert_error(
+        h.send(&[ix], &[&member]).await,
+        GuildError::InsufficientRole,
+    );
+    let ix = crew.kick(officer.pubkey(), crew.leader.pubkey());
+    assert_error(
+        h.send(&[ix], &[&officer]).await,
+        GuildError::InsufficientRole,
+    );
+    let outsider = h.funded_player().await;
+    let ix = create_guild(outsider.pubkey(), "Day Shift", crew.mint, spl_token::ID, 1);
+    h.send(&[ix], &[&outsider]).await.unwrap();
+    let ix = crew.kick(officer.pubkey(), outsider.pubkey());
+    assert_error(h.send(&[ix], &[&officer]).await, GuildError::NotAMember);
+
+    let ix = crew.kick(officer.pubkey(), member.pubkey());
+    h.send(&[ix], &[&officer]).await.unwrap();
+    assert!(!h.exists(&pda::guild::member(&member.pubkey())).await);
+    assert_eq!(crew.guild(&mut h).await.member_count, 3);
+}
+
+#[tokio::test]
+async fn the_leader_hands_over_before_leaving() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let member = crew.join(&mut h).await;
+    let leader = crew.leader.pubkey();
+
+    assert_error(
+        h.send(&[crew.leave(leader)], &[&crew.leader]).await,
+        GuildError::LeaderCannotLeave,
+    );
+    let ix = transfer_leadership(leader, crew.address, leader);
+    assert_error(
+        h.send(&[ix], &[&crew.leader]).await,
+        GuildError::InvalidRole,
+    );
+
+    let ix = transfer_leadership(leader, crew.address, member.pubkey());
+    h.send(&[ix], &[&crew.leader]).await.unwrap();
+    assert_eq!(crew.guild(&mut h).await.leader, member.pubkey());
+    assert_eq!(self::member(&mut h, &member).await.role, Role::Leader);
+    assert_eq!(self::member(&mut h, &crew.leader).await.role, Role::Officer);
+
+    h.send(&[crew.leave(leader)], &[&crew.leader])
+        .await
+        .unwrap();
+    assert!(!h.exists(&pda::guild::member(&leader)).await);
+    assert_eq!(crew.guild(&mut h).await.member_count, 1);
+    // The new leader now sets roles.
+    let ix = set_role(leader, crew.address, member.pubkey(), Role::Officer);
+    assert_error(
+        h.send(&[ix], &[&crew.leader]).await,
+        ErrorCode::ConstraintHasOne,
+    );
+}
+
+#[tokio::test]
+async fn spending_needs_the_approval_threshold() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let officer = crew.officer(&mut h).await;
+    let member = crew.join(&mut h).await;
+    let recipient = h.put_token_account(&crew.mint, &member.pubkey(), 0).await;
+    let leader = crew.leader.pubkey();
+
+    let ix = crew.propose(member.pubkey(), 0, recipient, 100);
+    assert_error(
+        h.send(&[ix], &[&member]).await,
+        GuildError::InsufficientRole,
+    );
+    let ix = crew.propose(leader, 0, recipient, 0);
+    assert_error(
+        h.send(&[ix], &[&crew.leader]).await,
+        GuildError::InvalidAmount,
+    );
+    let ix = crew.propose(leader, 0, recipient, 100);
+    h.send(&[ix], &[&crew.leader]).await.unwrap();
+    let proposal: SpendProposal = h.account(&pda::guild::proposal(&crew.address, 0)).await;
+    assert_eq!(proposal.proposer, leader);
+    assert_eq!(proposal.recipient, recipient);
+    assert_eq!(proposal.amount, 100);
+    assert_eq!(proposal.approvals, vec![leader]);
+    assert_eq!(crew.guild(&mut h).await.proposal_count, 1);
+
+    assert_error(
+        h.send(&[crew.execute(0, recipient)], &[]).await,
+        GuildError::NotEnoughApprovals,
+    );
+    let ix = crew.approve(leader, 0);
+    assert_error(
+        h.send(&[ix], &[&crew.leader]).await,
+        GuildError::AlreadyApproved,
+    );
+    let ix = crew.approve(member.pubkey(), 0);
+    assert_error(
+        h.send(&[ix], &[&member]).await,
+        GuildError::InsufficientRole,
+    );
+    let ix = crew.approve(officer.pubkey(), 0);
+    h.send(&[ix], &[&officer]).await.unwrap();
+
+    h.send(&[crew.execute(0, recipient)], &[]).await.unwrap();
+    assert_eq!(h.token_balance(&recipient).await, 100);
+    let treasury = pda::guild::treasury(&crew.address);
+    assert_eq!(h.token_balance(&treasury).await, FUNDS - 100);
+    assert_error(
+        h.send(&[crew.execute(0, recipient)], &[]).await,
+        GuildError::AlreadyExecuted,
+    );
+    let ix = crew.approve(officer.pubkey(), 0);
+    assert_error(
+        h.send(&[ix], &[&officer]).await,
+        GuildError::AlreadyExecuted,
+    );
+}
+
+#[tokio::test]
+async fn the_leader_sets_the_approval_threshold() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+
+    for threshold in [0, SpendProposal::MAX_APPROVALS as u8 + 1] {
+        assert_error(
+            h.send(&[crew.set_approval_threshold(threshold)], &[&crew.leader])
+                .await,
+            GuildError::InvalidThreshold,
+        );
+    }
+    h.send(&[crew.set_approval_threshold(1)], &[&crew.leader])
+        .await
+        .unwrap();
+    assert_eq!(crew.guild(&mut h).await.approval_threshold, 1);
+
+    // The proposer's own approval is now enough.
+    let recipient = h
+        .put_token_account(&crew.mint, &crew.leader.pubkey(), 0)
+        .await;
+    let ix = crew.propose(crew.leader.pubkey(), 0, recipient, 100);
+    h.send(&[ix, crew.execute(0, recipient)], &[&crew.leader])
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(&recipient).await, 100);
+}
+
+#[tokio::test]
+async fn proposals_expire() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let officer = crew.officer(&mut h).await;
+    let recipient = h.put_token_account(&crew.mint, &officer.pubkey(), 0).await;
+    let ix = crew.propose(crew.leader.pubkey(), 0, recipient, 100);
+    h.send(&[ix], &[&crew.leader]).await.unwrap();
+
+    h.advance(PROPOSAL_TTL).await;
+    let ix = crew.approve(officer.pubkey(), 0);
+    assert_error(
+        h.send(&[ix], &[&officer]).await,
+        GuildError::ProposalExpired,
+    );
+    assert_error(
+        h.send(&[crew.execute(0, recipient)], &[]).await,
+        GuildError::ProposalExpired,
+    );
+}
+
+#[tokio::test]
+async fn proposals_hold_a_bounded_number_of_approvals() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let officer = crew.officer(&mut h).await;
+    let recipient = h.put_token_account(&crew.mint, &officer.pubkey(), 0).await;
+    let ix = crew.propose(crew.leader.pubkey(), 0, recipient, 100);
+    h.send(&[ix], &[&crew.leader]).await.unwrap();
+
+    // Filling the approvals takes `MAX_APPROVALS` officers; only the list
+    // matters, so it is written straight into the proposal.
+    let address = pda::guild::proposal(&crew.address, 0);
+    let mut proposal: SpendProposal = h.account(&address).await;
+    proposal
+        .approvals
+        .resize_with(SpendProposal::MAX_APPROVALS, Pubkey::new_unique);
+    h.put_account(&address, &guild::ID, &proposal, SpendProposal::SPACE);
+
+    let ix = crew.approve(officer.pubkey(), 0);
+    assert_error(
+        h.send(&[ix], &[&officer]).await,
+        GuildError::TooManyApprovals,
+    );
+}
+
+#[tokio::test]
+async fn tagged_matches_count_for_the_guild() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let rival = h.funded_player().await;
+    h.advance(1).await;
+    fixtures::recorded_match(&mut h, MATCH_ID, [&crew.leader, &rival], 0).await;
+    let admin = h.admin();
+
+    let ix = tag_match(admin, crew.leader.pubkey(), crew.address, 2);
+    assert_error(h.send(&[ix], &[]).await, GuildError::InvalidSide);
+    let ix = tag_match(admin, crew.leader.pubkey(), crew.address, 0);
+    h.send(&[ix], &[]).await.unwrap();
+    let guild = crew.guild(&mut h).await;
+    assert_eq!(guild.matches_played, 1);
+    assert_eq!(guild.wins, 1);
+    let tag: GuildMatchTag = h.account(&pda::guild::match_tag(MATCH_ID, 0)).await;
+    assert_eq!(tag.guild, crew.address);
+    let board: GuildLeaderboard = h.account(&pda::guild::leaderboard()).await;
+    assert_eq!(
+        board.standings,
+        vec![GuildStanding {
+            guild: crew.address,
+            wins: 1,
+            matches_played: 1,
+        }]
+    );
+}
+
+#[tokio::test]
+async fn only_final_results_are_tagged() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let rival = h.funded_player().await;
+    h.advance(1).await;
+    fixtures::open_set(&mut h, MATCH_ID, [&crew.leader, &rival]).await;
+
+    let ix = tag_match(h.admin(), crew.leader.pubkey(), crew.address, 0);
+    assert_error(h.send(&[ix], &[]).await, GuildError::MatchNotFinalized);
+}
+
+#[tokio::test]
+async fn members_who_joined_after_the_match_are_not_counted() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let rival = h.funded_player().await;
+    fixtures::recorded_match(&mut h, MATCH_ID, [&crew.leader, &rival], 1).await;
+
+    let ix = tag_match(h.admin(), crew.leader.pubkey(), crew.address, 0);
+    assert_error(h.send(&[ix], &[]).await, GuildError::JoinedAfterMatch);
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let crew = Crew::new(&mut h).await;
+    let invitee = Keypair::new().pubkey();
+    let recipient = h
+        .put_token_account(&crew.mint, &crew.leader.pubkey(), 0)
+        .await;
+    let rival = h.funded_player().await;
+    let ixs = [
+        crew.invite(crew.leader.pubkey(), invitee),
+        crew.propose(crew.leader.pubkey(), 0, recipient, 100),
+    ];
+    h.send(&ixs, &[&crew.leader]).await.unwrap();
+    h.advance(1).await;
+    fixtures::recorded_match(&mut h, MATCH_ID, [&crew.leader, &rival], 0).await;
+    let admin = h.admin();
+    let ix = tag_match(admin, crew.leader.pubkey(), crew.address, 0);
+    h.send(&[ix], &[]).await.unwrap();
+
+    for account in [
+        crew.address,
+        pda::guild::member(&crew.leader.pubkey()),
+        pda::guild::invite(&crew.address, &invitee),
+        pda::guild::proposal(&crew.address, 0),
+        pda::guild::match_tag(MATCH_ID, 0),
+        pda::guild::leaderboard(),
+    ] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            GuildError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &guild::ID, vec![9; GuildMember::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, GuildError::UnsupportedLayout);
+}
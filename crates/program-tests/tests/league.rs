@@ -0,0 +1,350 @@
+use anchor_lang::error::ErrorCode;
+use combo_mint_client::league::{
+    schedule, schedule_seed, League, LeagueError, LeagueStatus, Standing, Standings, NONE,
+    WIN_POINTS,
+};
+use combo_mint_client::{instruction, league, pda};
+use program_tests::{assert_error, fixtures, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `LeagueError::Overflow` needs more than `u8::MAX` fixtures or games, which
+// sixteen players can't reach.
+
+const LEAGUE_ID: u64 = 1;
+const MATCH_ID: u64 = 1;
+
+fn create_league(
+    organizer: Pubkey,
+    players: Vec<Pubkey>,
+    promotion_spots: u8,
+    relegation_spots: u8,
+) -> Instruction {
+    let league = pda::league::league(&organizer, LEAGUE_ID);
+    instruction(
+        league::ID,
+        league::accounts::CreateLeague {
+            organizer,
+            league,
+            standings: pda::league::standings(&league),
+            system_program: system_program::ID,
+        },
+        league::instruction::CreateLeague {
+            league_id: LEAGUE_ID,
+            players,
+            promotion_spots,
+            relegation_spots,
+        },
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        league::ID,
+        league::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        league::instruction::MigrateAccount {},
+    )
+}
+
+/// A funded organizer and roster, with the league not created yet.
+struct Table {
+    organizer: Keypair,
+    players: Vec<Keypair>,
+    address: Pubkey,
+}
+
+impl Table {
+    async fn new(h: &mut Harness, players: usize) -> Self {
+        let organizer = h.funded_player().await;
+        let mut roster = Vec::new();
+        for _ in 0..players {
+            roster.push(h.funded_player().await);
+        }
+        Table {
+            address: pda::league::league(&organizer.pubkey(), LEAGUE_ID),
+            organizer,
+            players: roster,
+        }
+    }
+
+    fn roster(&self) -> Vec<Pubkey> {
+        self.players.iter().map(Keypair::pubkey).collect()
+    }
+
+    async fn create(&self, h: &mut Harness, promotion_spots: u8, relegation_spots: u8) {
+        let ix = create_league(
+            self.organizer.pubkey(),
+            self.roster(),
+            promotion_spots,
+            relegation_spots,
+        );
+        h.send(&[ix], &[&self.organizer]).await.unwrap();
+    }
+
+    fn standings_address(&self) -> Pubkey {
+        pda::league::standings(&self.address)
+    }
+
+    async fn league(&self, h: &mut Harness) -> League {
+        h.account(&self.address).await
+    }
+
+    async fn standings(&self, h: &mut Harness) -> Vec<Standing> {
+        h.account::<Standings>(&self.standings_address()).await.rows
+    }
+
+    /// Index of the fixture between roster seeds `a` and `b`.
+    async fn fixture(&self, h: &mut Harness, a: u8, b: u8) -> u8 {
+        let league = self.league(h).await;
+        let index = league
+            .fixtures
+            .iter()
+            .position(|fixture| fixture.players == [a, b] || fixture.players == [b, a])
+            .unwrap();
+        index as u8
+    }
+
+    fn record_result(&self, fixture_index: u8, match_id: u64) -> Instruction {
+        instruction(
+            league::ID,
+            league::accounts::RecordResult {
+                organizer: self.organizer.pubkey(),
+                league: self.address,
+                standings: self.standings_address(),
+                match_account: pda::match_result::match_account(match_id),
+            },
+            league::instruction::RecordResult { fixture_index },
+        )
+    }
+
+    /// Records a 2-1 match between roster seeds `winner` and `loser`, listing
+    /// the winner first whichever slot the fixture gives them, and scores
+    /// their fixture from it.
+    async fn play(&self, h: &mut Harness, winner: u8, loser: u8) -> u8 {
+        let fixture_index = self.fixture(h, winner, loser).await;
+        let match_id = MATCH_ID + fixture_index as u64;
+        let players = [
+            &self.players[winner as usize],
+            &self.players[loser as usize],
+        ];
+        fixtures::recorded_match(h, match_id, players, 0).await;
+        let ix = self.record_result(fixture_index, match_id);
+        h.send(&[ix], &[&self.organizer]).await.unwrap();
+        fixture_index
+    }
+}
+
+#[tokio::test]
+async fn leagues_schedule_every_pairing_once() {
+    let mut h = Harness::start().await;
+    let table = Table::new(&mut h, 5).await;
+    table.create(&mut h, 1, 1).await;
+
+    let league = table.league(&mut h).await;
+    assert_eq!(league.organizer, table.organizer.pubkey());
+    assert_eq!(league.league_id, LEAGUE_ID);
+    assert_eq!(league.status, LeagueStatus::InProgress);
+    assert_eq!(league.players, table.roster());
+    assert_eq!(league.seed, schedule_seed(&table.address));
+    assert_eq!(league.started_at, h.now().await);
+    assert_eq!(league.fixtures, schedule(5, league.seed));
+    assert_eq!(league.reported, 0);
+    let mut pairs: Vec<[u8; 2]> = league
+        .fixtures
+        .iter()
+        .map(|fixture| {
+            let [a, b] = fixture.players;
+            [a.min(b), a.max(b)]
+        })
+        .collect();
+    pairs.sort();
+    let expected: Vec<[u8; 2]> = (0..5)
+        .flat_map(|a| (a + 1..5).map(move |b| [a, b]))
+        .collect();
+    assert_eq!(pairs, expected);
+    assert!(league.fixtures.iter().all(|fixture| fixture.winner == NONE));
+
+    let standings: Standings = h.account(&table.standings_address()).await;
+    assert_eq!(standings.league, table.address);
+    let rows: Vec<Standing> = table.roster().into_iter().map(Standing::new).collect();
+    assert_eq!(standings.rows, rows);
+}
+
+#[tokio::test]
+async fn rosters_are_checked_on_creation() {
+    let mut h = Harness::start().await;
+    let organizer = h.funded_player().await;
+    let player = Pubkey::new_unique();
+    let many = (0..=League::MAX_PLAYERS)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+    let cases = [
+        (vec![player], 0, 0, LeagueError::InvalidPlayerCount),
+        (many, 0, 0, LeagueError::InvalidPlayerCount),
+        (vec![player, player], 0, 0, LeagueError::DuplicatePlayer),
+        (
+            vec![player, Pubkey::new_unique()],
+            2,
+            1,
+            LeagueError::InvalidSpots,
+        ),
+    ];
+    for (players, promotion_spots, relegation_spots, error) in cases {
+        let ix = create_league(
+            organizer.pubkey(),
+            players,
+            promotion_spots,
+            relegation_spots,
+        );
+        assert_error(h.send(&[ix], &[&organizer]).await, error);
+    }
+}
+
+#[tokio::test]
+async fn results_score_the_table_and_the_last_one_ranks_it() {
+    let mut h = Harness::start().await;
+    let table = Table::new(&mut h, 3).await;
+    table.create(&mut h, 1, 1).await;
+
+    let first = table.play(&mut h, 0, 1).await;
+    let league = table.league(&mut h).await;
+    let fixture = league.fixtures[first as usize];
+    // The match listed the winner first; the fixture keeps its own slots.
+    let slot = fixture.players.iter().position(|&seed| seed == 0).unwrap();
+    assert_eq!(fixture.winner, slot as u8);
+    let mut score = [1; 2];
+    score[slot] = 2;
+    assert_eq!(fixture.score, score);
+    assert_eq!(fixture.match_id, MATCH_ID + first as u64);
+    assert_eq!(league.reported, 1);
+    assert_eq!(league.status, LeagueStatus::InProgress);
+    let rows = table.standings(&mut h).await;
+    assert_eq!(
+        (rows[0].points, rows[0].played, rows[0].wins, rows[0].losses),
+        (WIN_POINTS, 1, 1, 0)
+    );
+    assert_eq!((rows[0].games_won, rows[0].games_lost), (2, 1));
+    assert_eq!(
+        (rows[1].points, rows[1].played, rows[1].wins, rows[1].losses),
+        (0, 1, 0, 1)
+    );
+    assert_eq!((rows[1].games_won, rows[1].games_lost), (1, 2));
+    assert_eq!(rows[2], Standing::new(table.players[2].pubkey()));
+
+    table.play(&mut h, 0, 2).await;
+    table.play(&mut h, 1, 2).await;
+    assert_eq!(table.league(&mut h).await.status, LeagueStatus::Complete);
+    let rows = table.standings(&mut h).await;
+    let places: Vec<_> = rows
+        .iter()
+        .map(|row| (row.points, row.rank, row.promoted, row.relegated))
+        .collect();
+    assert_eq!(
+        places,
+        [
+            (2 * WIN_POINTS, 1, true, false),
+            (WIN_POINTS, 2, false, false),
+            (0, 3, false, true),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn results_must_come_from_the_fixture_players_since_the_start() {
+    let mut h = Harness::start().await;
+    let table = Table::new(&mut h, 3).await;
+    let [p0, p1, p2] = &table.players[..] else {
+        unreachable!()
+    };
+    let early = MATCH_ID + 10;
+    fixtures::recorded_match(&mut h, early, [p0, p1], 0).await;
+    h.advance(1).await;
+    table.create(&mut h, 0, 0).await;
+    let index = table.fixture(&mut h, 0, 1).await;
+    let organizer = &table.organizer;
+
+    let open = MATCH_ID + 11;
+    fixtures::open_set(&mut h, open, [p0, p1]).await;
+    let others = MATCH_ID + 12;
+    fixtures::recorded_match(&mut h, others, [p1, p2], 0).await;
+    let cases = [
+        (3, others, LeagueError::InvalidFixture),
+        (index, open, LeagueError::MatchNotFinalized),
+        (index, early, LeagueError::MatchBeforeLeague),
+        (index, others, LeagueError::PlayerMismatch),
+    ];
+    for (fixture_index, match_id, error) in cases {
+        let ix = table.record_result(fixture_index, match_id);
+        assert_error(h.send(&[ix], &[organizer]).await, error);
+    }
+
+    let stranger = h.funded_player().await;
+    let mut ix = table.record_result(index, others);
+    ix.accounts[0].pubkey = stranger.pubkey();
+    assert_error(
+        h.send(&[ix], &[&stranger]).await,
+        ErrorCode::ConstraintHasOne,
+    );
+
+    table.play(&mut h, 1, 0).await;
+    let again = MATCH_ID + 13;
+    fixtures::recorded_match(&mut h, again, [p0, p1], 0).await;
+    let ix = table.record_result(index, again);
+    assert_error(
+        h.send(&[ix], &[organizer]).await,
+        LeagueError::FixtureReported,
+    );
+}
+
+#[tokio::test]
+async fn finished_leagues_take_no_more_results() {
+    let mut h = Harness::start().await;
+    let table = Table::new(&mut h, 2).await;
+    table.create(&mut h, 0, 0).await;
+    let index = table.play(&mut h, 1, 0).await;
+    let rows = table.standings(&mut h).await;
+    assert_eq!((rows[1].rank, rows[0].rank), (1, 2));
+    assert!(rows.iter().all(|row| !row.promoted && !row.relegated));
+
+    let [p0, p1] = &table.players[..] else {
+        unreachable!()
+    };
+    let rematch = MATCH_ID + 10;
+    fixtures::recorded_match(&mut h, rematch, [p0, p1], 0).await;
+    let ix = table.record_result(index, rematch);
+    assert_error(
+        h.send(&[ix], &[&table.organizer]).await,
+        LeagueError::NotInProgress,
+    );
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let table = Table::new(&mut h, 2).await;
+    table.create(&mut h, 0, 0).await;
+
+    let admin = h.admin();
+    for account in [table.address, table.standings_address()] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            LeagueError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &league::ID, vec![9; League::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, LeagueError::UnsupportedLayout);
+}
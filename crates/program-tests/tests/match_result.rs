@@ -0,0 +1,839 @@
+use anchor_lang::error::ErrorCode;
+use combo_mint_client::match_result::attestation::{
+    record_match_message, report_disconnect_message,
+};
+use combo_mint_client::match_result::{
+    AttestationConfig, CrewBattle, DisconnectRecord, MatchAccount, MatchError, CRANK_REWARD,
+    FORMAT_TEAM, MAX_CREW_SIZE, MIN_DISCONNECT_WAIT_MS, STALE_TIMEOUT, TEAM_SIZE,
+};
+use combo_mint_client::{builders, instruction, match_result, pda};
+use program_tests::fixtures::{self, CHARACTER};
+use program_tests::{assert_error, Harness, PLAYER_FUNDS};
+use solana_sdk::ed25519_instruction::new_ed25519_instruction;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::{system_program, sysvar};
+
+// `NotFinalized` and `AlreadyRated` guard `mark_rated`, which only the
+// ranking program's rater PDA can sign, and ranking checks both first; see
+// `ranking.rs`. The rater constraint itself is covered below.
+
+const REPLAY: [u8; 32] = [7; 32];
+
+/// Two funded players, as player one and player two.
+async fn players(h: &mut Harness) -> (Keypair, Keypair) {
+    (h.funded_player().await, h.funded_player().await)
+}
+
+fn record_match_accounts(
+    one: &Keypair,
+    two: &Keypair,
+    match_id: u64,
+) -> match_result::accounts::RecordMatch {
+    match_result::accounts::RecordMatch {
+        player_one: one.pubkey(),
+        player_two: two.pubkey(),
+        match_account: pda::match_result::match_account(match_id),
+        system_program: system_program::ID,
+        event_authority: pda::match_result::event_authority(),
+        program: match_result::ID,
+    }
+}
+
+fn record_team_match(
+    one: &Keypair,
+    two: &Keypair,
+    match_id: u64,
+    winner: u8,
+    score: [u8; 2],
+) -> Instruction {
+    instruction(
+        match_result::ID,
+        record_match_accounts(one, two, match_id),
+        match_result::instruction::RecordTeamMatch {
+            match_id,
+            winner,
+            rosters: [[CHARACTER; TEAM_SIZE]; 2],
+            score,
+            replay_hash: REPLAY,
+            frame_count: 9_000,
+            checksum_root: [0; 32],
+        },
+    )
+}
+
+fn open_set(one: &Keypair, two: &Keypair, match_id: u64, best_of: u8) -> Instruction {
+    instruction(
+        match_result::ID,
+        record_match_accounts(one, two, match_id),
+        match_result::instruction::OpenSet { match_id, best_of },
+    )
+}
+
+fn record_set_game(
+    one: &Keypair,
+    two: &Keypair,
+    match_id: u64,
+    winner: u8,
+    frame_count: u32,
+) -> Instruction {
+    instruction(
+        match_result::ID,
+        match_result::accounts::RecordSetGame {
+            player_one: one.pubkey(),
+            player_two: two.pubkey(),
+            match_account: pda::match_result::match_account(match_id),
+            event_authority: pda::match_result::event_authority(),
+            program: match_result::ID,
+        },
+        match_result::instruction::RecordSetGame {
+            winner,
+            characters: [CHARACTER, CHARACTER],
+            replay_hash: [winner + 1; 32],
+            frame_count,
+        },
+    )
+}
+
+fn finalize_set(caller: Pubkey, player_one: Pubkey, match_id: u64) -> Instruction {
+    instruction(
+        match_result::ID,
+        match_result::accounts::FinalizeExpiredSet {
+            caller,
+            player_one,
+            match_account: pda::match_result::match_account(match_id),
+            event_authority: pda::match_result::event_authority(),
+            program: match_result::ID,
+        },
+        match_result::instruction::FinalizeSetIfExpired {},
+    )
+}
+
+/// Crews of `crew_size` fresh keys each, captained by `one` and `two`.
+fn crews(one: &Keypair, two: &Keypair, crew_size: usize) -> [[Pubkey; MAX_CREW_SIZE]; 2] {
+    let mut crews = [[Pubkey::default(); MAX_CREW_SIZE]; 2];
+    crews[0][0] = one.pubkey();
+    crews[1][0] = two.pubkey();
+    for crew in crews.iter_mut() {
+        for member in &mut crew[1..crew_size] {
+            *member = Pubkey::new_unique();
+        }
+    }
+    crews
+}
+
+fn open_crew_battle(
+    one: &Keypair,
+    two: &Keypair,
+    match_id: u64,
+    crews: [[Pubkey; MAX_CREW_SIZE]; 2],
+    crew_size: u8,
+    stocks: u8,
+) -> Instruction {
+    instruction(
+        match_result::ID,
+        match_result::accounts::OpenCrewBattle {
+            captain_one: one.pubkey(),
+            captain_two: two.pubkey(),
+            crew_battle: pda::match_result::crew_battle(match_id),
+            system_program: system_program::ID,
+            event_authority: pda::match_result::event_authority(),
+            program: match_result::ID,
+        },
+        match_result::instruction::OpenCrewBattle {
+            match_id,
+            crews,
+            crew_size,
+            stocks,
+        },
+    )
+}
+
+fn record_crew_game(
+    one: &Keypair,
+    two: &Keypair,
+    match_id: u64,
+    winner: u8,
+    stocks_left: u8,
+) -> Instruction {
+    instruction(
+        match_result::ID,
+        match_result::accounts::RecordCrewGame {
+            captain_one: one.pubkey(),
+            captain_two: two.pubkey(),
+            crew_battle: pda::match_result::crew_battle(match_id),
+            event_authority: pda::match_result::event_authority(),
+            program: match_result::ID,
+        },
+        match_result::instruction::RecordCrewGame {
+            winner,
+            stocks_left,
+            replay_hash: REPLAY,
+            frame_count: 3_600,
+        },
+    )
+}
+
+fn finalize_crew_battle(caller: Pubkey, captain_one: Pubkey, match_id: u64) -> Instruction {
+    instruction(
+        match_result::ID,
+        match_result::accounts::FinalizeExpiredCrewBattle {
+            caller,
+            captain_one,
+            crew_battle: pda::match_result::crew_battle(match_id),
+            event_authority: pda::match_result::event_authority(),
+            program: match_result::ID,
+        },
+        match_result::instruction::FinalizeCrewBattleIfExpired {},
+    )
+}
+
+fn initialize_attestation_config(admin: Pubkey, servers: Vec<Pubkey>) -> Instruction {
+    instruction(
+        match_result::ID,
+        match_result::accounts::InitializeAttestationConfig {
+            admin,
+            attestation_config: pda::match_result::attestation_config(),
+            system_program: system_program::ID,
+        },
+        match_result::instruction::InitializeAttestationConfig { servers },
+    )
+}
+
+fn set_attestation_servers(admin: Pubkey, servers: Vec<Pubkey>) -> Instruction {
+    instruction(
+        match_result::ID,
+        match_result::accounts::SetAttestationServers {
+            admin,
+            attestation_config: pda::match_result::attestation_config(),
+        },
+        match_result::instruction::SetAttestationServers { servers },
+    )
+}
+
+/// The ed25519 program instruction `server` verifying its signature of
+/// `message`, which attested instructions expect right before them.
+fn attestation(server: &Keypair, message: &[u8]) -> Instruction {
+    let signer = ed25519_dalek::Keypair::from_bytes(&server.to_bytes()).unwrap();
+    new_ed25519_instruction(&signer, message)
+}
+
+/// Arguments of `record_match_attested`, with its message.
+struct Attested {
+    match_id: u64,
+    players: [Pubkey; 2],
+    winner: u8,
+    score: [u8; 2],
+    frame_count: u32,
+}
+
+impl Attested {
+    fn new(players: [Pubkey; 2]) -> Self {
+        Attested {
+            match_id: 1,
+            players,
+            winner: 1,
+            score: [0, 2],
+            frame_count: 3_600,
+        }
+    }
+
+    fn message(&self) -> Vec<u8> {
+        record_match_message(
+            self.match_id,
+            &self.players,
+            self.winner,
+            &self.score,
+            &REPLAY,
+            self.frame_count,
+        )
+    }
+
+    fn instruction(&self, payer: Pubkey) -> Instruction {
+        instruction(
+            match_result::ID,
+            match_result::accounts::RecordMatchAttested {
+                payer,
+                attestation_config: pda::match_result::attestation_config(),
+                match_account: pda::match_result::match_account(self.match_id),
+                instructions: sysvar::instructions::ID,
+                system_program: system_program::ID,
+                event_authority: pda::match_result::event_authority(),
+                program: match_result::ID,
+            },
+            match_result::instruction::RecordMatchAttested {
+                match_id: self.match_id,
+                players: self.players,
+                winner: self.winner,
+                score: self.score,
+                replay_hash: REPLAY,
+                frame_count: self.frame_count,
+            },
+        )
+    }
+}
+
+/// An attestation config approving a fresh server key, which it returns.
+async fn attestation_server(h: &mut Harness) -> Keypair {
+    let server = Keypair::new();
+    let ix = initialize_attestation_config(h.admin(), vec![server.pubkey()]);
+    h.send(&[ix], &[]).await.unwrap();
+    server
+}
+
+async fn match_account(h: &mut Harness, match_id: u64) -> MatchAccount {
+    h.account(&pda::match_result::match_account(match_id)).await
+}
+
+#[tokio::test]
+async fn records_a_match_both_players_sign() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+
+    fixtures::recorded_match(&mut h, 1, [&one, &two], 1).await;
+
+    let record = match_account(&mut h, 1).await;
+    assert_eq!(record.players, [one.pubkey(), two.pubkey()]);
+    assert_eq!(record.winner_key(), two.pubkey());
+    assert_eq!(record.score, [1, 2]);
+    assert_eq!(record.replay_hash, REPLAY);
+    assert!(record.finalized && !record.rating_applied);
+}
+
+#[tokio::test]
+async fn records_the_key_frame_checksum_root() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    let root = [3; 32];
+
+    let players = [one.pubkey(), two.pubkey()];
+    let ix = builders::record_match(players, 1, 0, [2, 1], REPLAY, 3_600, root);
+    h.send(&[ix], &[&one, &two]).await.unwrap();
+    assert_eq!(match_account(&mut h, 1).await.checksum_root, root);
+}
+
+#[tokio::test]
+async fn rejects_invalid_results() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    let players = [one.pubkey(), two.pubkey()];
+
+    let cases = [
+        (0, [1, 2], 3_600, MatchError::ScoreMismatch),
+        (1, [1, 1], 3_600, MatchError::ScoreMismatch),
+        (2, [2, 1], 3_600, MatchError::InvalidWinner),
+        (0, [2, 1], 0, MatchError::EmptyMatch),
+    ];
+    for (winner, score, frame_count, expected) in cases {
+        let ix = builders::record_match(players, 1, winner, score, REPLAY, frame_count, [0; 32]);
+        assert_error(h.send(&[ix], &[&one, &two]).await, expected);
+    }
+
+    let ix = builders::record_match(
+        [one.pubkey(), one.pubkey()],
+        1,
+        0,
+        [2, 1],
+        REPLAY,
+        1,
+        [0; 32],
+    );
+    assert_error(h.send(&[ix], &[&one]).await, MatchError::InvalidPlayers);
+}
+
+#[tokio::test]
+async fn records_team_matches() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    let full = TEAM_SIZE as u8;
+
+    let ix = record_team_match(&one, &two, 1, 0, [full, full - 1]);
+    h.send(&[ix], &[&one, &two]).await.unwrap();
+    let record = match_account(&mut h, 1).await;
+    assert_eq!(record.format, FORMAT_TEAM);
+    assert_eq!(record.rosters, [[CHARACTER; TEAM_SIZE]; 2]);
+
+    // The winner knocks out every opposing character.
+    let ix = record_team_match(&one, &two, 2, 0, [full - 1, full - 2]);
+    assert_error(
+        h.send(&[ix], &[&one, &two]).await,
+        MatchError::ScoreMismatch,
+    );
+}
+
+#[tokio::test]
+async fn plays_out_a_set() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    let address = pda::match_result::match_account(1);
+
+    h.send(&[open_set(&one, &two, 1, 3)], &[&one, &two])
+        .await
+        .unwrap();
+    let record = match_account(&mut h, 1).await;
+    assert_eq!((record.best_of, record.finalized), (3, false));
+    let deposit = h.lamports(&address).await;
+
+    let player_one_before = h.lamports(&one.pubkey()).await;
+    for winner in [1, 0, 1] {
+        let ix = record_set_game(&one, &two, 1, winner, 3_600);
+        h.send(&[ix], &[&one, &two]).await.unwrap();
+    }
+    let record = match_account(&mut h, 1).await;
+    assert!(record.finalized);
+    assert_eq!(
+        (record.winner, record.score, record.game_count),
+        (1, [1, 2], 3)
+    );
+    assert_eq!(record.replay_hash, record.set_replay_hash());
+    // The harness payer pays the fees, so player one's balance only moves by
+    // the returned deposit.
+    assert_eq!(h.lamports(&address).await, deposit - CRANK_REWARD);
+    assert_eq!(
+        h.lamports(&one.pubkey()).await,
+        player_one_before + CRANK_REWARD
+    );
+
+    let ix = record_set_game(&one, &two, 1, 0, 3_600);
+    assert_error(h.send(&[ix], &[&one, &two]).await, MatchError::SetFinished);
+}
+
+#[tokio::test]
+async fn sets_are_an_odd_number_of_games() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+
+    for best_of in [0, 1, 2, 4, 7] {
+        let ix = open_set(&one, &two, 1, best_of);
+        assert_error(
+            h.send(&[ix], &[&one, &two]).await,
+            MatchError::InvalidSetLength,
+        );
+    }
+    let ix = open_set(&one, &one, 1, 3);
+    assert_error(h.send(&[ix], &[&one]).await, MatchError::InvalidPlayers);
+}
+
+#[tokio::test]
+async fn set_games_are_checked() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    let outsider = h.funded_player().await;
+    h.send(&[open_set(&one, &two, 1, 3)], &[&one, &two])
+        .await
+        .unwrap();
+
+    let ix = record_set_game(&one, &two, 1, 2, 3_600);
+    assert_error(
+        h.send(&[ix], &[&one, &two]).await,
+        MatchError::InvalidWinner,
+    );
+    let ix = record_set_game(&one, &outsider, 1, 0, 3_600);
+    assert_error(
+        h.send(&[ix], &[&one, &outsider]).await,
+        MatchError::InvalidPlayers,
+    );
+    let ix = record_set_game(&one, &two, 1, 0, 0);
+    assert_error(h.send(&[ix], &[&one, &two]).await, MatchError::EmptyMatch);
+}
+
+#[tokio::test]
+async fn voids_a_stale_set_even_with_a_player_ahead() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    let caller = h.funded_player().await.pubkey();
+    h.send(&[open_set(&one, &two, 1, 5)], &[&one, &two])
+        .await
+        .unwrap();
+    let ix = record_set_game(&one, &two, 1, 1, 3_600);
+    h.send(&[ix], &[&one, &two]).await.unwrap();
+
+    let ix = finalize_set(caller, one.pubkey(), 1);
+    assert_error(h.send(&[ix], &[]).await, MatchError::NotStale);
+
+    h.advance(STALE_TIMEOUT).await;
+    h.send(&[finalize_set(caller, one.pubkey(), 1)], &[])
+        .await
+        .unwrap();
+    assert!(!h.exists(&pda::match_result::match_account(1)).await);
+    assert_eq!(h.lamports(&caller).await, PLAYER_FUNDS + CRANK_REWARD);
+
+    // Void, so the players can play the set again. Finished, it is theirs
+    // to keep.
+    h.send(&[open_set(&one, &two, 1, 3)], &[&one, &two])
+        .await
+        .unwrap();
+    for _ in 0..2 {
+        let ix = record_set_game(&one, &two, 1, 0, 3_600);
+        h.send(&[ix], &[&one, &two]).await.unwrap();
+    }
+    h.advance(STALE_TIMEOUT).await;
+    let ix = finalize_set(caller, one.pubkey(), 1);
+    assert_error(h.send(&[ix], &[]).await, MatchError::AlreadyFinalized);
+}
+
+#[tokio::test]
+async fn plays_out_a_crew_battle() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    let crews = crews(&one, &two, 2);
+
+    let ix = open_crew_battle(&one, &two, 1, crews, 2, 2);
+    h.send(&[ix], &[&one, &two]).await.unwrap();
+    let address = pda::match_result::crew_battle(1);
+    let battle: CrewBattle = h.account(&address).await;
+    assert_eq!(battle.pool, [4, 4]);
+
+    // Crew one's first player takes out both of crew two's, losing a stock.
+    for stocks_left in [1, 1] {
+        let ix = record_crew_game(&one, &two, 1, 0, stocks_left);
+        h.send(&[ix], &[&one, &two]).await.unwrap();
+    }
+    let battle: CrewBattle = h.account(&address).await;
+    assert!(battle.finalized);
+    assert_eq!(
+        (battle.winner, battle.pool, battle.game_count),
+        (0, [3, 0], 2)
+    );
+
+    let ix = record_crew_game(&one, &two, 1, 0, 1);
+    assert_error(
+        h.send(&[ix], &[&one, &two]).await,
+        MatchError::CrewBattleFinished,
+    );
+}
+
+#[tokio::test]
+async fn crews_are_checked() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+
+    let mut repeated = crews(&one, &two, 2);
+    repeated[1][1] = one.pubkey();
+    let mut overfull = crews(&one, &two, 2);
+    overfull[0][2] = Pubkey::new_unique();
+    let cases = [
+        (crews(&one, &two, 2), 0, 2, MatchError::InvalidCrew),
+        (
+            crews(&one, &two, 2),
+            MAX_CREW_SIZE as u8 + 1,
+            2,
+            MatchError::InvalidCrew,
+        ),
+        (crews(&one, &two, 2), 3, 2, MatchError::InvalidCrew),
+        (repeated, 2, 2, MatchError::InvalidCrew),
+        (overfull, 2, 2, MatchError::InvalidCrew),
+        (crews(&one, &two, 2), 2, 0, MatchError::InvalidStocks),
+        (crews(&one, &two, 2), 2, 5, MatchError::InvalidStocks),
+    ];
+    for (crews, crew_size, stocks, expected) in cases {
+        let ix = open_crew_battle(&one, &two, 1, crews, crew_size, stocks);
+        assert_error(h.send(&[ix], &[&one, &two]).await, expected);
+    }
+
+    let ix = open_crew_battle(&one, &one, 1, crews(&one, &two, 1), 1, 1);
+    assert_error(h.send(&[ix], &[&one]).await, MatchError::InvalidPlayers);
+}
+
+#[tokio::test]
+async fn crew_games_are_checked() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    let ix = open_crew_battle(&one, &two, 1, crews(&one, &two, 2), 2, 2);
+    h.send(&[ix], &[&one, &two]).await.unwrap();
+
+    for (winner, stocks_left, expected) in [
+        (2, 1, MatchError::InvalidWinner),
+        (0, 0, MatchError::InvalidStocks),
+        (0, 3, MatchError::InvalidStocks),
+    ] {
+        let ix = record_crew_game(&one, &two, 1, winner, stocks_left);
+        assert_error(h.send(&[ix], &[&one, &two]).await, expected);
+    }
+    let ix = record_crew_game(&two, &one, 1, 0, 1);
+    assert_error(
+        h.send(&[ix], &[&one, &two]).await,
+        MatchError::InvalidPlayers,
+    );
+}
+
+#[tokio::test]
+async fn voids_stale_crew_battles() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    let caller = h.funded_player().await.pubkey();
+    for match_id in [1, 2] {
+        let ix = open_crew_battle(&one, &two, match_id, crews(&one, &two, 2), 2, 2);
+        h.send(&[ix], &[&one, &two]).await.unwrap();
+    }
+    let ix = record_crew_game(&one, &two, 1, 1, 2);
+    h.send(&[ix], &[&one, &two]).await.unwrap();
+
+    let ix = finalize_crew_battle(caller, one.pubkey(), 1);
+    assert_error(h.send(&[ix], &[]).await, MatchError::NotStale);
+
+    h.advance(STALE_TIMEOUT).await;
+    // Crew two is ahead in the first battle and the second is level; both
+    // are void.
+    for match_id in [1, 2] {
+        let ix = finalize_crew_battle(caller, one.pubkey(), match_id);
+        h.send(&[ix], &[]).await.unwrap();
+        assert!(!h.exists(&pda::match_result::crew_battle(match_id)).await);
+    }
+    assert_eq!(h.lamports(&caller).await, PLAYER_FUNDS + 2 * CRANK_REWARD);
+}
+
+#[tokio::test]
+async fn the_admin_manages_attestation_servers() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let intruder = h.funded_player().await;
+    let too_many: Vec<Pubkey> = (0..=AttestationConfig::MAX_SERVERS)
+        .map(|_| Pubkey::new_unique())
+        .collect();
+
+    let ix = initialize_attestation_config(admin, too_many.clone());
+    assert_error(h.send(&[ix], &[]).await, MatchError::TooManyServers);
+    attestation_server(&mut h).await;
+
+    let servers = too_many[..AttestationConfig::MAX_SERVERS].to_vec();
+    h.send(&[set_attestation_servers(admin, servers.clone())], &[])
+        .await
+        .unwrap();
+    let config: AttestationConfig = h.account(&pda::match_result::attestation_config()).await;
+    assert_eq!(config.servers, servers);
+
+    let ix = set_attestation_servers(admin, too_many);
+    assert_error(h.send(&[ix], &[]).await, MatchError::TooManyServers);
+    let ix = set_attestation_servers(intruder.pubkey(), vec![]);
+    assert_error(
+        h.send(&[ix], &[&intruder]).await,
+        ErrorCode::ConstraintHasOne,
+    );
+}
+
+#[tokio::test]
+async fn records_a_match_an_approved_server_attests() {
+    let mut h = Harness::start().await;
+    let server = attestation_server(&mut h).await;
+    let payer = h.admin();
+    let result = Attested::new([Pubkey::new_unique(), Pubkey::new_unique()]);
+
+    let ixs = [
+        attestation(&server, &result.message()),
+        result.instruction(payer),
+    ];
+    h.send(&ixs, &[]).await.unwrap();
+
+    let record = match_account(&mut h, 1).await;
+    assert_eq!(record.players, result.players);
+    assert_eq!(record.winner_key(), result.players[1]);
+    assert!(record.finalized);
+}
+
+#[tokio::test]
+async fn rejects_bad_attestations() {
+    let mut h = Harness::start().await;
+    let server = attestation_server(&mut h).await;
+    let payer = h.admin();
+    let result = Attested::new([Pubkey::new_unique(), Pubkey::new_unique()]);
+
+    assert_error(
+        h.send(&[result.instruction(payer)], &[]).await,
+        MatchError::MissingAttestation,
+    );
+
+    let tampered = Attested {
+        winner: 0,
+        score: [2, 0],
+        ..Attested::new(result.players)
+    };
+    let ixs = [
+        attestation(&server, &result.message()),
+        tampered.instruction(payer),
+    ];
+    assert_error(h.send(&ixs, &[]).await, MatchError::InvalidAttestation);
+
+    let impostor = Keypair::new();
+    let ixs = [
+        attestation(&impostor, &result.message()),
+        result.instruction(payer),
+    ];
+    assert_error(h.send(&ixs, &[]).await, MatchError::UnauthorizedAttester);
+}
+
+#[tokio::test]
+async fn attested_results_are_checked() {
+    let mut h = Harness::start().await;
+    let server = attestation_server(&mut h).await;
+    let payer = h.admin();
+    let player = Pubkey::new_unique();
+    let players = [player, Pubkey::new_unique()];
+
+    let cases = [
+        (Attested::new([player, player]), MatchError::InvalidPlayers),
+        (
+            Attested {
+                winner: 2,
+                ..Attested::new(players)
+            },
+            MatchError::InvalidWinner,
+        ),
+        (
+            Attested {
+                score: [2, 0],
+                ..Attested::new(players)
+            },
+            MatchError::ScoreMismatch,
+        ),
+        (
+            Attested {
+                frame_count: 0,
+                ..Attested::new(players)
+            },
+            MatchError::EmptyMatch,
+        ),
+    ];
+    for (result, expected) in cases {
+        let ixs = [
+            attestation(&server, &result.message()),
+            result.instruction(payer),
+        ];
+        assert_error(h.send(&ixs, &[]).await, expected);
+    }
+}
+
+/// `report_disconnect` by `reporter`, attested by `server`.
+fn disconnect(
+    server: &Keypair,
+    reporter: &Keypair,
+    match_id: u64,
+    players: [Pubkey; 2],
+    quitter: u8,
+    waited_ms: u64,
+) -> [Instruction; 2] {
+    let message = report_disconnect_message(match_id, &players, quitter, 1_200, 0xabc, waited_ms);
+    [
+        attestation(server, &message),
+        builders::report_disconnect(
+            reporter.pubkey(),
+            match_id,
+            players,
+            quitter,
+            1_200,
+            0xabc,
+            waited_ms,
+        ),
+    ]
+}
+
+#[tokio::test]
+async fn records_a_loss_for_a_quitter() {
+    let mut h = Harness::start().await;
+    let server = attestation_server(&mut h).await;
+    let stayer = h.funded_player().await;
+    let quitter = Pubkey::new_unique();
+    let players = [quitter, stayer.pubkey()];
+
+    for match_id in [1, 2] {
+        let ixs = disconnect(
+            &server,
+            &stayer,
+            match_id,
+            players,
+            0,
+            MIN_DISCONNECT_WAIT_MS,
+        );
+        h.send(&ixs, &[&stayer]).await.unwrap();
+    }
+
+    let record = match_account(&mut h, 2).await;
+    assert_eq!(record.winner_key(), stayer.pubkey());
+    assert_eq!((record.score, record.replay_hash), ([0, 1], [0; 32]));
+    let disconnects: DisconnectRecord = h.account(&pda::match_result::disconnects(&quitter)).await;
+    assert_eq!((disconnects.player, disconnects.count), (quitter, 2));
+    assert!(disconnects.restricted_until() > h.now().await);
+}
+
+#[tokio::test]
+async fn disconnect_reports_are_checked() {
+    let mut h = Harness::start().await;
+    let server = attestation_server(&mut h).await;
+    let stayer = h.funded_player().await;
+    let quitter = Keypair::new();
+    let players = [quitter.pubkey(), stayer.pubkey()];
+
+    let ixs = disconnect(&server, &stayer, 1, players, 1, MIN_DISCONNECT_WAIT_MS);
+    assert_error(
+        h.send(&ixs, &[&stayer]).await,
+        MatchError::NotRemainingPlayer,
+    );
+    let ixs = disconnect(&server, &stayer, 1, players, 0, MIN_DISCONNECT_WAIT_MS - 1);
+    assert_error(
+        h.send(&ixs, &[&stayer]).await,
+        MatchError::DisconnectTooShort,
+    );
+    let ixs = disconnect(
+        &server,
+        &stayer,
+        1,
+        [stayer.pubkey(), stayer.pubkey()],
+        0,
+        MIN_DISCONNECT_WAIT_MS,
+    );
+    assert_error(h.send(&ixs, &[&stayer]).await, MatchError::InvalidPlayers);
+
+    let impostor = Keypair::new();
+    let ixs = disconnect(&impostor, &stayer, 1, players, 0, MIN_DISCONNECT_WAIT_MS);
+    assert_error(
+        h.send(&ixs, &[&stayer]).await,
+        MatchError::UnauthorizedAttester,
+    );
+}
+
+#[tokio::test]
+async fn only_the_rater_marks_matches_rated() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    fixtures::recorded_match(&mut h, 1, [&one, &two], 0).await;
+
+    let ix = instruction(
+        match_result::ID,
+        match_result::accounts::MarkRated {
+            rater: one.pubkey(),
+            match_account: pda::match_result::match_account(1),
+        },
+        match_result::instruction::MarkRated {},
+    );
+    assert_error(h.send(&[ix], &[&one]).await, ErrorCode::ConstraintSeeds);
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let (one, two) = players(&mut h).await;
+    fixtures::recorded_match(&mut h, 1, [&one, &two], 0).await;
+    let payer = h.admin();
+    let migrate = |account| {
+        instruction(
+            match_result::ID,
+            match_result::accounts::MigrateAccount {
+                payer,
+                account,
+                system_program: system_program::ID,
+            },
+            match_result::instruction::MigrateAccount {},
+        )
+    };
+
+    let ix = migrate(pda::match_result::match_account(1));
+    assert_error(h.send(&[ix], &[]).await, MatchError::AlreadyMigrated);
+
+    let unknown = Pubkey::new_unique();
+    h.put_raw_account(&unknown, &match_result::ID, vec![9; MatchAccount::SPACE]);
+    assert_error(
+        h.send(&[migrate(unknown)], &[]).await,
+        MatchError::UnsupportedLayout,
+    );
+}
@@ -0,0 +1,748 @@
+use anchor_lang::error::ErrorCode;
+use combo_mint_client::match_result::DisconnectRecord;
+use combo_mint_client::matchmaking::{
+    loadout_hash, Loadout, MatchQueue, MatchmakingError, PendingMatch, PendingStatus,
+    PlayerRegistration, QueueEntry, RankedConfig, RankedRequirements, LOADOUT_DEPOSIT, MAX_QUEUE,
+    REVEAL_TIMEOUT, STAKE_LOCK,
+};
+use combo_mint_client::ranking::INITIAL_RATING;
+use combo_mint_client::{builders, instruction, match_result, matchmaking, pda};
+use orao_solana_vrf::randomness_account_address;
+use orao_solana_vrf::state::Randomness;
+use program_tests::{assert_error, fixtures, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `pair_players` requests its randomness from the ORAO VRF program, which
+// the bank doesn't load, so neither it nor `MatchmakingError::NoCompatiblePair`
+// is tested here; `Pending::new` writes the match it would open straight
+// in. `MatchmakingError::Overflow` needs more than `u32::MAX` unranked
+// matches or `u64::MAX` pending matches, so no test reaches it either.
+
+const MATCH_ID: u64 = 1;
+const SALT: [u8; 32] = [3; 32];
+const CHARACTER: u8 = 2;
+
+/// Requirements that let anyone registered queue.
+const OPEN: RankedRequirements = RankedRequirements {
+    min_account_age: 0,
+    min_unranked_matches: 0,
+    stake: 0,
+};
+
+fn initialize_ranked_config(admin: Pubkey, requirements: RankedRequirements) -> Instruction {
+    instruction(
+        matchmaking::ID,
+        matchmaking::accounts::InitializeRankedConfig {
+            admin,
+            ranked_config: pda::matchmaking::ranked_config(),
+            system_program: system_program::ID,
+        },
+        matchmaking::instruction::InitializeRankedConfig { requirements },
+    )
+}
+
+fn set_ranked_requirements(admin: Pubkey, requirements: RankedRequirements) -> Instruction {
+    instruction(
+        matchmaking::ID,
+        matchmaking::accounts::SetRankedRequirements {
+            admin,
+            ranked_config: pda::matchmaking::ranked_config(),
+        },
+        matchmaking::instruction::SetRankedRequirements { requirements },
+    )
+}
+
+/// The queue and the ranked config with `requirements`.
+async fn initialize(h: &mut Harness, requirements: RankedRequirements) {
+    let admin = h.admin();
+    let ixs = [
+        instruction(
+            matchmaking::ID,
+            matchmaking::accounts::InitializeQueue {
+                payer: admin,
+                queue: pda::matchmaking::queue(),
+                system_program: system_program::ID,
+            },
+            matchmaking::instruction::InitializeQueue {},
+        ),
+        initialize_ranked_config(admin, requirements),
+    ];
+    h.send(&ixs, &[]).await.unwrap();
+}
+
+fn register_player(player: Pubkey) -> Instruction {
+    instruction(
+        matchmaking::ID,
+        matchmaking::accounts::RegisterPlayer {
+            player,
+            registration: pda::matchmaking::registration(&player),
+            system_program: system_program::ID,
+        },
+        matchmaking::instruction::RegisterPlayer {},
+    )
+}
+
+fn record_unranked_match(player: Pubkey, match_id: u64) -> Instruction {
+    instruction(
+        matchmaking::ID,
+        matchmaking::accounts::RecordUnrankedMatch {
+            registration: pda::matchmaking::registration(&player),
+            match_account: pda::match_result::match_account(match_id),
+        },
+        matchmaking::instruction::RecordUnrankedMatch {},
+    )
+}
+
+fn withdraw_stake(player: Pubkey) -> Instruction {
+    instruction(
+        matchmaking::ID,
+        matchmaking::accounts::WithdrawStake {
+            player,
+            registration: pda::matchmaking::registration(&player),
+        },
+        matchmaking::instruction::WithdrawStake {},
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        matchmaking::ID,
+        matchmaking::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        matchmaking::instruction::MigrateAccount {},
+    )
+}
+
+/// A rated player who registered for ranked.
+async fn registered(h: &mut Harness) -> Keypair {
+    let player = fixtures::rated_player(h).await;
+    h.send(&[register_player(player.pubkey())], &[&player])
+        .await
+        .unwrap();
+    player
+}
+
+async fn registration(h: &mut Harness, player: &Keypair) -> PlayerRegistration {
+    h.account(&pda::matchmaking::registration(&player.pubkey()))
+        .await
+}
+
+async fn queue(h: &mut Harness) -> MatchQueue {
+    h.account(&pda::matchmaking::queue()).await
+}
+
+/// Two paired funded players and the VRF request for their match, with
+/// neither loadout committed.
+struct Pending {
+    players: [Keypair; 2],
+    address: Pubkey,
+    random: Pubkey,
+}
+
+impl Pending {
+    /// Pairing is `pair_players`' job, which needs the VRF program; here
+    /// only the match it opens matters, so it is written straight in.
+    async fn new(h: &mut Harness) -> Self {
+        let players = [h.funded_player().await, h.funded_player().await];
+        let (address, bump) = Pubkey::find_program_address(
+            &[b"pending_match", &MATCH_ID.to_le_bytes()],
+            &matchmaking::ID,
+        );
+        let random = randomness_account_address(&address.to_bytes());
+        let pending = PendingMatch {
+            layout_version: 1,
+            match_id: MATCH_ID,
+            players: [players[0].pubkey(), players[1].pubkey()],
+            mmr: [INITIAL_RATING; 2],
+            vrf_request: random,
+            seed: 0,
+            seeded: false,
+            sides_swapped: false,
+            stage_pick: 0,
+            created_at: h.now().await,
+            status: PendingStatus::Committing,
+            commitments: [[0; 32]; 2],
+            committed: [false; 2],
+            revealed: [false; 2],
+            loadouts: [Loadout::default(); 2],
+            stage: 0,
+            reveal_deadline: 0,
+            bump,
+        };
+        h.put_account(&address, &matchmaking::ID, &pending, PendingMatch::SPACE);
+        Pending {
+            players,
+            address,
+            random,
+        }
+    }
+
+    /// Fulfilling is the VRF program's job; here only the output matters,
+    /// so it is written straight into the request.
+    fn fulfil(&self, h: &mut Harness, randomness: [u8; 64]) {
+        let request = Randomness {
+            seed: self.address.to_bytes(),
+            randomness,
+            responses: Vec::new(),
+        };
+        // Discriminator, seed, randomness and an empty `responses`.
+        let space = 8 + 32 + 64 + 4;
+        h.put_account(&self.random, &orao_solana_vrf::ID, &request, space);
+    }
+
+    /// `players[side]` picking `CHARACTER` and a stage of its index.
+    fn commitment(&self, side: usize) -> [u8; 32] {
+        loadout_hash(CHARACTER, side as u8, &SALT, &self.players[side].pubkey())
+    }
+
+    fn commit(&self, side: usize, hash: [u8; 32]) -> Instruction {
+        instruction(
+            matchmaking::ID,
+            matchmaking::accounts::CommitLoadout {
+                player: self.players[side].pubkey(),
+                pending_match: self.address,
+                system_program: system_program::ID,
+            },
+            matchmaking::instruction::CommitLoadout { hash },
+        )
+    }
+
+    /// Both players commit to `commitment`.
+    async fn committed(&self, h: &mut Harness) {
+        let ixs = [
+            self.commit(0, self.commitment(0)),
+            self.commit(1, self.commitment(1)),
+        ];
+        h.send(&ixs, &[&self.players[0], &self.players[1]])
+            .await
+            .unwrap();
+    }
+
+    fn reveal_accounts(
+        &self,
+        side: usize,
+        opponent: Pubkey,
+    ) -> matchmaking::accounts::RevealLoadout {
+        matchmaking::accounts::RevealLoadout {
+            player: self.players[side].pubkey(),
+            opponent,
+            pending_match: self.address,
+        }
+    }
+
+    /// `players[side]` opening `commitment(side)`, salted with `salt`.
+    fn reveal(&self, side: usize, salt: [u8; 32]) -> Instruction {
+        let opponent = self.players[1 - side].pubkey();
+        instruction(
+            matchmaking::ID,
+            self.reveal_accounts(side, opponent),
+            matchmaking::instruction::RevealLoadout {
+                character: CHARACTER,
+                stage: side as u8,
+                salt,
+            },
+        )
+    }
+
+    fn claim_reveal_timeout(&self, side: usize) -> Instruction {
+        let opponent = self.players[1 - side].pubkey();
+        instruction(
+            matchmaking::ID,
+            self.reveal_accounts(side, opponent),
+            matchmaking::instruction::ClaimRevealTimeout {},
+        )
+    }
+
+    fn fulfill_randomness(&self) -> Instruction {
+        instruction(
+            matchmaking::ID,
+            matchmaking::accounts::FulfillRandomness {
+                pending_match: self.address,
+                random: self.random,
+            },
+            matchmaking::instruction::FulfillRandomness {},
+        )
+    }
+
+    async fn pending(&self, h: &mut Harness) -> PendingMatch {
+        h.account(&self.address).await
+    }
+
+    async fn balances(&self, h: &mut Harness) -> [u64; 2] {
+        [
+            h.lamports(&self.players[0].pubkey()).await,
+            h.lamports(&self.players[1].pubkey()).await,
+        ]
+    }
+}
+
+/// VRF output seeding 7, swapping sides and playing the second player's
+/// stage pick.
+fn randomness() -> [u8; 64] {
+    let mut randomness = [0; 64];
+    randomness[0] = 7;
+    randomness[8] = 1;
+    randomness[9] = 1;
+    randomness
+}
+
+#[tokio::test]
+async fn the_admin_sets_ranked_requirements() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let negative = RankedRequirements {
+        min_account_age: -1,
+        ..OPEN
+    };
+
+    assert_error(
+        h.send(&[initialize_ranked_config(admin, negative)], &[])
+            .await,
+        MatchmakingError::InvalidRequirements,
+    );
+    initialize(&mut h, OPEN).await;
+    assert_error(
+        h.send(&[set_ranked_requirements(admin, negative)], &[])
+            .await,
+        MatchmakingError::InvalidRequirements,
+    );
+    let stranger = h.funded_player().await;
+    assert_error(
+        h.send(
+            &[set_ranked_requirements(stranger.pubkey(), OPEN)],
+            &[&stranger],
+        )
+        .await,
+        ErrorCode::ConstraintHasOne,
+    );
+
+    let requirements = RankedRequirements {
+        min_account_age: 60,
+        min_unranked_matches: 5,
+        stake: LAMPORTS_PER_SOL,
+    };
+    h.send(&[set_ranked_requirements(admin, requirements)], &[])
+        .await
+        .unwrap();
+    let config: RankedConfig = h.account(&pda::matchmaking::ranked_config()).await;
+    assert_eq!(config.admin, admin);
+    assert_eq!(config.requirements, requirements);
+}
+
+#[tokio::test]
+async fn registered_players_queue_at_their_rating() {
+    let mut h = Harness::start().await;
+    initialize(&mut h, OPEN).await;
+    let player = registered(&mut h).await;
+    let registered = registration(&mut h, &player).await;
+    assert_eq!(registered.player, player.pubkey());
+    assert_eq!(registered.registered_at, h.now().await);
+
+    let join = || builders::join_queue(player.pubkey());
+    h.send(&[join()], &[&player]).await.unwrap();
+    assert_eq!(
+        queue(&mut h).await.entries,
+        [QueueEntry {
+            player: player.pubkey(),
+            mmr: INITIAL_RATING,
+            joined_at: h.now().await,
+        }]
+    );
+    assert_error(
+        h.send(&[join()], &[&player]).await,
+        MatchmakingError::AlreadyQueued,
+    );
+
+    let leave = || builders::leave_queue(player.pubkey());
+    h.send(&[leave()], &[&player]).await.unwrap();
+    assert!(queue(&mut h).await.entries.is_empty());
+    assert_error(
+        h.send(&[leave()], &[&player]).await,
+        MatchmakingError::NotQueued,
+    );
+}
+
+#[tokio::test]
+async fn the_queue_holds_at_most_max_queue() {
+    let mut h = Harness::start().await;
+    initialize(&mut h, OPEN).await;
+    let player = registered(&mut h).await;
+    // Queueing `MAX_QUEUE` players takes as many ratings and registrations;
+    // only the count matters, so the queue is filled straight in.
+    let mut full = queue(&mut h).await;
+    full.entries = (0..MAX_QUEUE)
+        .map(|_| QueueEntry {
+            player: Pubkey::new_unique(),
+            mmr: INITIAL_RATING,
+            joined_at: 0,
+        })
+        .collect();
+    let address = pda::matchmaking::queue();
+    h.put_account(&address, &matchmaking::ID, &full, MatchQueue::SPACE);
+
+    let ix = builders::join_queue(player.pubkey());
+    assert_error(h.send(&[ix], &[&player]).await, MatchmakingError::QueueFull);
+}
+
+#[tokio::test]
+async fn ranked_entry_needs_account_age_and_unranked_matches() {
+    let mut h = Harness::start().await;
+    let requirements = RankedRequirements {
+        min_account_age: 60 * 60,
+        min_unranked_matches: 1,
+        stake: 0,
+    };
+    initialize(&mut h, requirements).await;
+    let player = registered(&mut h).await;
+    let rival = h.funded_player().await;
+    fixtures::recorded_match(&mut h, MATCH_ID, [&player, &rival], 0).await;
+    let join = || builders::join_queue(player.pubkey());
+
+    assert_error(
+        h.send(&[join()], &[&player]).await,
+        MatchmakingError::AccountTooNew,
+    );
+    h.advance(requirements.min_account_age).await;
+    assert_error(
+        h.send(&[join()], &[&player]).await,
+        MatchmakingError::TooFewUnrankedMatches,
+    );
+    let ix = record_unranked_match(player.pubkey(), MATCH_ID);
+    h.send(&[ix], &[]).await.unwrap();
+    h.send(&[join()], &[&player]).await.unwrap();
+}
+
+#[tokio::test]
+async fn unranked_matches_are_counted_once_in_order() {
+    let mut h = Harness::start().await;
+    initialize(&mut h, OPEN).await;
+    let rival = h.funded_player().await;
+    // Played before registering.
+    let early = h.funded_player().await;
+    fixtures::recorded_match(&mut h, MATCH_ID, [&early, &rival], 0).await;
+    h.advance(1).await;
+    let player = registered(&mut h).await;
+    h.send(&[register_player(early.pubkey())], &[&early])
+        .await
+        .unwrap();
+
+    assert_error(
+        h.send(&[record_unranked_match(early.pubkey(), MATCH_ID)], &[])
+            .await,
+        MatchmakingError::NotUnrankedMatch,
+    );
+    assert_error(
+        h.send(&[record_unranked_match(player.pubkey(), MATCH_ID)], &[])
+            .await,
+        MatchmakingError::NotInMatch,
+    );
+    fixtures::open_set(&mut h, MATCH_ID + 1, [&player, &rival]).await;
+    assert_error(
+        h.send(&[record_unranked_match(player.pubkey(), MATCH_ID + 1)], &[])
+            .await,
+        MatchmakingError::NotUnrankedMatch,
+    );
+
+    fixtures::recorded_match(&mut h, MATCH_ID + 3, [&player, &rival], 0).await;
+    let ix = record_unranked_match(player.pubkey(), MATCH_ID + 3);
+    h.send(&[ix], &[]).await.unwrap();
+    let counted = registration(&mut h, &player).await;
+    assert_eq!(counted.unranked_matches, 1);
+    assert_eq!(counted.last_counted_match, MATCH_ID + 3);
+
+    // Neither the same match again nor an older one counts.
+    fixtures::recorded_match(&mut h, MATCH_ID + 2, [&player, &rival], 1).await;
+    for match_id in [MATCH_ID + 3, MATCH_ID + 2] {
+        assert_error(
+            h.send(&[record_unranked_match(player.pubkey(), match_id)], &[])
+                .await,
+            MatchmakingError::MatchAlreadyCounted,
+        );
+    }
+}
+
+#[tokio::test]
+async fn rated_matches_are_not_unranked() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    initialize(&mut h, OPEN).await;
+    let player = registered(&mut h).await;
+    let rival = fixtures::rated_player(&mut h).await;
+    fixtures::recorded_match(&mut h, MATCH_ID, [&player, &rival], 0).await;
+    fixtures::apply_result(&mut h, MATCH_ID, &player, &rival).await;
+
+    let ix = record_unranked_match(player.pubkey(), MATCH_ID);
+    assert_error(h.send(&[ix], &[]).await, MatchmakingError::NotUnrankedMatch);
+}
+
+#[tokio::test]
+async fn ranked_stakes_are_held_until_the_lock_passes() {
+    let mut h = Harness::start().await;
+    let stake = LAMPORTS_PER_SOL;
+    initialize(&mut h, RankedRequirements { stake, ..OPEN }).await;
+    let player = registered(&mut h).await;
+    let address = pda::matchmaking::registration(&player.pubkey());
+    let withdraw = || withdraw_stake(player.pubkey());
+
+    assert_error(
+        h.send(&[withdraw()], &[&player]).await,
+        MatchmakingError::NoStake,
+    );
+    let held = h.lamports(&address).await;
+    h.send(&[builders::join_queue(player.pubkey())], &[&player])
+        .await
+        .unwrap();
+    assert_eq!(registration(&mut h, &player).await.stake, stake);
+    assert_eq!(h.lamports(&address).await, held + stake);
+    assert_error(
+        h.send(&[withdraw()], &[&player]).await,
+        MatchmakingError::StakeLocked,
+    );
+
+    h.advance(STAKE_LOCK).await;
+    let before = h.lamports(&player.pubkey()).await;
+    h.send(&[withdraw()], &[&player]).await.unwrap();
+    assert_eq!(h.lamports(&player.pubkey()).await, before + stake);
+    assert_eq!(h.lamports(&address).await, held);
+    assert_eq!(registration(&mut h, &player).await.stake, 0);
+}
+
+#[tokio::test]
+async fn repeat_disconnecters_sit_out_ranked() {
+    let mut h = Harness::start().await;
+    initialize(&mut h, OPEN).await;
+    let player = registered(&mut h).await;
+    // Reporting disconnects takes server attestations, which match_result's
+    // tests cover; only the record matters here, so it is written straight
+    // in, two disconnects sitting the player out for ten minutes.
+    let address = pda::match_result::disconnects(&player.pubkey());
+    let record = DisconnectRecord {
+        layout_version: 1,
+        player: player.pubkey(),
+        count: 2,
+        last_at: h.now().await,
+        bump: 0,
+    };
+    h.put_account(
+        &address,
+        &match_result::ID,
+        &record,
+        DisconnectRecord::SPACE,
+    );
+    let join = || builders::join_queue(player.pubkey());
+
+    assert_error(
+        h.send(&[join()], &[&player]).await,
+        MatchmakingError::DisconnectRestricted,
+    );
+    h.advance(match_result::disconnect_restriction(2)).await;
+    h.send(&[join()], &[&player]).await.unwrap();
+}
+
+#[tokio::test]
+async fn revealed_loadouts_and_randomness_make_the_match_ready() {
+    let mut h = Harness::start().await;
+    let pending = Pending::new(&mut h).await;
+    let start = pending.balances(&mut h).await;
+
+    pending.committed(&mut h).await;
+    let committed = pending.pending(&mut h).await;
+    assert_eq!(committed.status, PendingStatus::Revealing);
+    assert_eq!(committed.reveal_deadline, h.now().await + REVEAL_TIMEOUT);
+    assert_eq!(
+        pending.balances(&mut h).await,
+        start.map(|balance| balance - LOADOUT_DEPOSIT)
+    );
+
+    let [p0, p1] = &pending.players;
+    h.send(
+        &[pending.reveal(0, SALT), pending.reveal(1, SALT)],
+        &[p0, p1],
+    )
+    .await
+    .unwrap();
+    assert_eq!(pending.balances(&mut h).await, start);
+    let revealed = pending.pending(&mut h).await;
+    assert_eq!(revealed.status, PendingStatus::Revealing);
+    assert_eq!(
+        revealed.loadouts,
+        [0, 1].map(|stage| Loadout {
+            character: CHARACTER,
+            stage,
+        })
+    );
+
+    pending.fulfil(&mut h, randomness());
+    h.send(&[pending.fulfill_randomness()], &[]).await.unwrap();
+    let ready = pending.pending(&mut h).await;
+    assert_eq!(ready.status, PendingStatus::Ready);
+    assert_eq!(ready.seed, 7);
+    assert!(ready.sides_swapped);
+    assert_eq!(ready.stage_pick, 1);
+    assert_eq!(ready.stage, 1);
+}
+
+#[tokio::test]
+async fn loadouts_are_committed_then_revealed_once() {
+    let mut h = Harness::start().await;
+    let pending = Pending::new(&mut h).await;
+    let [p0, p1] = &pending.players;
+
+    let stranger = h.funded_player().await;
+    let mut ix = pending.commit(0, [1; 32]);
+    ix.accounts[0].pubkey = stranger.pubkey();
+    assert_error(
+        h.send(&[ix], &[&stranger]).await,
+        MatchmakingError::NotInMatch,
+    );
+    assert_error(
+        h.send(&[pending.reveal(0, SALT)], &[p0]).await,
+        MatchmakingError::WrongPhase,
+    );
+    h.send(&[pending.commit(0, pending.commitment(0))], &[p0])
+        .await
+        .unwrap();
+    assert_error(
+        h.send(&[pending.commit(0, pending.commitment(0))], &[p0])
+            .await,
+        MatchmakingError::AlreadyCommitted,
+    );
+    h.send(&[pending.commit(1, pending.commitment(1))], &[p1])
+        .await
+        .unwrap();
+    assert_error(
+        h.send(&[pending.commit(1, pending.commitment(1))], &[p1])
+            .await,
+        MatchmakingError::WrongPhase,
+    );
+
+    let ix = instruction(
+        matchmaking::ID,
+        pending.reveal_accounts(0, stranger.pubkey()),
+        matchmaking::instruction::RevealLoadout {
+            character: CHARACTER,
+            stage: 0,
+            salt: SALT,
+        },
+    );
+    assert_error(h.send(&[ix], &[p0]).await, MatchmakingError::NotInMatch);
+    h.send(&[pending.reveal(0, SALT)], &[p0]).await.unwrap();
+    assert_error(
+        h.send(&[pending.reveal(0, SALT)], &[p0]).await,
+        MatchmakingError::AlreadyRevealed,
+    );
+}
+
+#[tokio::test]
+async fn a_mismatched_reveal_forfeits_both_deposits() {
+    let mut h = Harness::start().await;
+    let pending = Pending::new(&mut h).await;
+    let start = pending.balances(&mut h).await;
+    pending.committed(&mut h).await;
+
+    h.send(&[pending.reveal(0, [4; 32])], &[&pending.players[0]])
+        .await
+        .unwrap();
+    assert_eq!(
+        pending.balances(&mut h).await,
+        [start[0] - LOADOUT_DEPOSIT, start[1] + LOADOUT_DEPOSIT]
+    );
+    assert_eq!(
+        pending.pending(&mut h).await.status,
+        PendingStatus::Forfeited
+    );
+}
+
+#[tokio::test]
+async fn the_player_who_revealed_claims_after_the_deadline() {
+    let mut h = Harness::start().await;
+    let pending = Pending::new(&mut h).await;
+    let start = pending.balances(&mut h).await;
+    pending.committed(&mut h).await;
+    let [p0, p1] = &pending.players;
+    h.send(&[pending.reveal(0, SALT)], &[p0]).await.unwrap();
+
+    assert_error(
+        h.send(&[pending.claim_reveal_timeout(0)], &[p0]).await,
+        MatchmakingError::RevealStillOpen,
+    );
+    h.advance(REVEAL_TIMEOUT).await;
+    assert_error(
+        h.send(&[pending.claim_reveal_timeout(1)], &[p1]).await,
+        MatchmakingError::AlreadyRevealed,
+    );
+    h.send(&[pending.claim_reveal_timeout(0)], &[p0])
+        .await
+        .unwrap();
+    assert_eq!(
+        pending.balances(&mut h).await,
+        [start[0] + LOADOUT_DEPOSIT, start[1] - LOADOUT_DEPOSIT]
+    );
+    assert_eq!(
+        pending.pending(&mut h).await.status,
+        PendingStatus::Forfeited
+    );
+}
+
+#[tokio::test]
+async fn randomness_seeds_a_match_once_fulfilled() {
+    let mut h = Harness::start().await;
+    let pending = Pending::new(&mut h).await;
+
+    pending.fulfil(&mut h, [0; 64]);
+    assert_error(
+        h.send(&[pending.fulfill_randomness()], &[]).await,
+        MatchmakingError::RandomnessNotReady,
+    );
+    pending.fulfil(&mut h, randomness());
+    h.send(&[pending.fulfill_randomness()], &[]).await.unwrap();
+    let seeded = pending.pending(&mut h).await;
+    assert!(seeded.seeded);
+    // Loadouts are still to come.
+    assert_eq!(seeded.status, PendingStatus::Committing);
+    assert_error(
+        h.send(&[pending.fulfill_randomness()], &[]).await,
+        MatchmakingError::AlreadySeeded,
+    );
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    initialize(&mut h, OPEN).await;
+    let player = registered(&mut h).await;
+    let pending = Pending::new(&mut h).await;
+
+    let admin = h.admin();
+    for account in [
+        pda::matchmaking::queue(),
+        pda::matchmaking::ranked_config(),
+        pda::matchmaking::registration(&player.pubkey()),
+        pending.address,
+    ] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            MatchmakingError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &matchmaking::ID, vec![9; PendingMatch::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(
+        h.send(&[ix], &[]).await,
+        MatchmakingError::UnsupportedLayout,
+    );
+}
@@ -0,0 +1,286 @@
+use anchor_lang::{AnchorSerialize, Discriminator};
+use combo_mint_client::move_registry::{
+    CharacterFrameData, MoveFrameData, Registry, RegistryError,
+};
+use combo_mint_client::{instruction, move_registry, pda};
+use program_tests::fixtures::{self, CHARACTER, HEAVY, JAB};
+use program_tests::{assert_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `RegistryError::Overflow` needs 65536 registered characters, more than a
+// `u8` character id can address, so no test reaches it.
+
+fn update_moves(admin: Pubkey) -> move_registry::accounts::UpdateMoves {
+    move_registry::accounts::UpdateMoves {
+        admin,
+        registry: pda::move_registry::registry(),
+        frame_data: pda::move_registry::frame_data(CHARACTER),
+    }
+}
+
+fn remove_move(admin: Pubkey, move_id: u8) -> Instruction {
+    instruction(
+        move_registry::ID,
+        update_moves(admin),
+        move_registry::instruction::RemoveMove { move_id },
+    )
+}
+
+fn set_definition_hash(admin: Pubkey, revision: u16, definition_hash: [u8; 32]) -> Instruction {
+    instruction(
+        move_registry::ID,
+        update_moves(admin),
+        move_registry::instruction::SetDefinitionHash {
+            revision,
+            definition_hash,
+        },
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        move_registry::ID,
+        move_registry::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        move_registry::instruction::MigrateAccount {},
+    )
+}
+
+fn jab() -> MoveFrameData {
+    fixtures::moveset().remove(0)
+}
+
+async fn frame_data(h: &mut Harness) -> CharacterFrameData {
+    h.account(&pda::move_registry::frame_data(CHARACTER)).await
+}
+
+#[tokio::test]
+async fn registers_a_character_with_its_moves() {
+    let mut h = Harness::start().await;
+    fixtures::move_registry(&mut h).await;
+
+    let registry: Registry = h.account(&pda::move_registry::registry()).await;
+    assert_eq!(registry.admin, h.admin());
+    assert_eq!(registry.character_count, 1);
+    let frame_data = frame_data(&mut h).await;
+    assert_eq!(frame_data.character_id, CHARACTER);
+    assert_eq!(frame_data.moves, fixtures::moveset());
+}
+
+#[tokio::test]
+async fn only_the_admin_edits_moves() {
+    let mut h = Harness::start().await;
+    fixtures::move_registry(&mut h).await;
+    let intruder = h.funded_player().await;
+
+    let ix = fixtures::set_move(intruder.pubkey(), CHARACTER, jab());
+    assert_error(
+        h.send(&[ix], &[&intruder]).await,
+        RegistryError::Unauthorized,
+    );
+    let ix = remove_move(intruder.pubkey(), JAB);
+    assert_error(
+        h.send(&[ix], &[&intruder]).await,
+        RegistryError::Unauthorized,
+    );
+    let ix = set_definition_hash(intruder.pubkey(), 1, [1; 32]);
+    assert_error(
+        h.send(&[ix], &[&intruder]).await,
+        RegistryError::Unauthorized,
+    );
+}
+
+#[tokio::test]
+async fn only_the_admin_registers_characters() {
+    let mut h = Harness::start().await;
+    fixtures::move_registry(&mut h).await;
+    let intruder = h.funded_player().await;
+
+    let ix = instruction(
+        move_registry::ID,
+        move_registry::accounts::RegisterCharacter {
+            admin: intruder.pubkey(),
+            registry: pda::move_registry::registry(),
+            frame_data: pda::move_registry::frame_data(CHARACTER + 1),
+            system_program: system_program::ID,
+        },
+        move_registry::instruction::RegisterCharacter {
+            character_id: CHARACTER + 1,
+        },
+    );
+    assert_error(
+        h.send(&[ix], &[&intruder]).await,
+        RegistryError::Unauthorized,
+    );
+}
+
+#[tokio::test]
+async fn set_move_replaces_a_move_with_the_same_id() {
+    let mut h = Harness::start().await;
+    fixtures::move_registry(&mut h).await;
+
+    let mut faster = jab();
+    faster.startup = 2;
+    let ix = fixtures::set_move(h.admin(), CHARACTER, faster.clone());
+    h.send(&[ix], &[]).await.unwrap();
+
+    let frame_data = frame_data(&mut h).await;
+    assert_eq!(frame_data.moves.len(), fixtures::moveset().len());
+    assert_eq!(frame_data.find_move(JAB), Some(&faster));
+}
+
+#[tokio::test]
+async fn rejects_invalid_frame_data() {
+    let mut h = Harness::start().await;
+    fixtures::move_registry(&mut h).await;
+    let admin = h.admin();
+
+    let invalid: [fn(&mut MoveFrameData); 4] = [
+        |m| m.active = 0,
+        |m| m.base_damage = 0,
+        |m| m.launch = -1,
+        |m| m.motion = u8::MAX,
+    ];
+    for invalidate in invalid {
+        let mut move_data = jab();
+        invalidate(&mut move_data);
+        let ix = fixtures::set_move(admin, CHARACTER, move_data);
+        assert_error(h.send(&[ix], &[]).await, RegistryError::InvalidFrameData);
+    }
+}
+
+#[tokio::test]
+async fn caps_the_moves_per_character() {
+    let mut h = Harness::start().await;
+    fixtures::move_registry(&mut h).await;
+    let admin = h.admin();
+
+    let existing = fixtures::moveset().len();
+    let new_ids = 100..100 + (CharacterFrameData::MAX_MOVES - existing) as u8;
+    for move_id in new_ids.clone() {
+        let move_data = MoveFrameData { move_id, ..jab() };
+        let ix = fixtures::set_move(admin, CHARACTER, move_data);
+        h.send(&[ix], &[]).await.unwrap();
+    }
+
+    let one_more = MoveFrameData {
+        move_id: new_ids.end,
+        ..jab()
+    };
+    let ix = fixtures::set_move(admin, CHARACTER, one_more);
+    assert_error(h.send(&[ix], &[]).await, RegistryError::TooManyMoves);
+}
+
+#[tokio::test]
+async fn removes_moves() {
+    let mut h = Harness::start().await;
+    fixtures::move_registry(&mut h).await;
+    let admin = h.admin();
+
+    h.send(&[remove_move(admin, HEAVY)], &[]).await.unwrap();
+    assert_eq!(frame_data(&mut h).await.find_move(HEAVY), None);
+
+    assert_error(
+        h.send(&[remove_move(admin, HEAVY)], &[]).await,
+        RegistryError::UnknownMove,
+    );
+}
+
+#[tokio::test]
+async fn definition_revisions_only_increase() {
+    let mut h = Harness::start().await;
+    fixtures::move_registry(&mut h).await;
+    let admin = h.admin();
+
+    h.send(&[set_definition_hash(admin, 2, [2; 32])], &[])
+        .await
+        .unwrap();
+    let frame_data = frame_data(&mut h).await;
+    assert_eq!(frame_data.definition_revision, 2);
+    assert_eq!(frame_data.definition_hash, [2; 32]);
+
+    for revision in [1, 2] {
+        let ix = set_definition_hash(admin, revision, [3; 32]);
+        assert_error(h.send(&[ix], &[]).await, RegistryError::StaleRevision);
+    }
+}
+
+/// `CharacterFrameData` on layout 1, whose moves lack `hitstun` and
+/// `motion`.
+fn layout_1_frame_data(moves: &[MoveFrameData]) -> Vec<u8> {
+    const OLD_MOVE_SIZE: usize = MoveFrameData::SIZE - MoveFrameData::ADDED_IN_V2;
+    let mut data = CharacterFrameData::discriminator().to_vec();
+    data.extend([1, CHARACTER]);
+    data.extend((moves.len() as u32).to_le_bytes());
+    for move_data in moves {
+        data.extend(&move_data.try_to_vec().unwrap()[..OLD_MOVE_SIZE]);
+    }
+    data.extend(0u16.to_le_bytes());
+    data.extend([0; 32]);
+    data.push(0);
+    data.resize(
+        CharacterFrameData::SPACE - CharacterFrameData::MAX_MOVES * MoveFrameData::ADDED_IN_V2,
+        0,
+    );
+    data
+}
+
+#[tokio::test]
+async fn migrates_layout_1_frame_data() {
+    let mut h = Harness::start().await;
+    let address = Keypair::new().pubkey();
+    let moves = fixtures::moveset();
+    h.put_raw_account(&address, &move_registry::ID, layout_1_frame_data(&moves));
+
+    let payer = h.admin();
+    h.send(&[migrate(payer, address)], &[]).await.unwrap();
+
+    let migrated: CharacterFrameData = h.account(&address).await;
+    assert_eq!(migrated.layout_version, 2);
+    let carried_over: Vec<_> = moves
+        .into_iter()
+        .map(|m| MoveFrameData {
+            hitstun: 0,
+            motion: 0,
+            ..m
+        })
+        .collect();
+    assert_eq!(migrated.moves, carried_over);
+    assert_eq!(
+        h.raw_account(&address).await.unwrap().data.len(),
+        CharacterFrameData::SPACE
+    );
+
+    assert_error(
+        h.send(&[migrate(payer, address)], &[]).await,
+        RegistryError::AlreadyMigrated,
+    );
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let payer = h.admin();
+
+    let newer = Keypair::new().pubkey();
+    let mut data = layout_1_frame_data(&[]);
+    data[8] = 3;
+    h.put_raw_account(&newer, &move_registry::ID, data);
+    assert_error(
+        h.send(&[migrate(payer, newer)], &[]).await,
+        RegistryError::UnsupportedLayout,
+    );
+
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &move_registry::ID, vec![9; 64]);
+    assert_error(
+        h.send(&[migrate(payer, unknown)], &[]).await,
+        RegistryError::UnsupportedLayout,
+    );
+}
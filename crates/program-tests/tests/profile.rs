@@ -0,0 +1,268 @@
+use anchor_lang::error::ErrorCode;
+use combo_mint_client::profile::{Profile, ProfileConfig, ProfileError};
+use combo_mint_client::{builders, instruction, pda, profile, ranking};
+use program_tests::{assert_error, fixtures, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `record_match` and `record_badge` need a writer program's
+// `profile_writer` PDA to sign, which only a CPI from that program can do.
+// Ranked matches reach `record_match` through `ranking::apply_match_result`
+// here; badges reach `record_badge` through `achievements::award_badge` in
+// `ranking.rs`. There are too few badges to award to reach
+// `ProfileError::TooManyBadges`, and `ProfileError::Overflow` needs the
+// writers to count more than `u32::MAX` matches.
+
+const NAME: &str = "Ryu";
+const CHARACTER: u8 = 2;
+
+fn initialize(admin: Pubkey) -> Instruction {
+    instruction(
+        profile::ID,
+        profile::accounts::Initialize {
+            admin,
+            config: pda::profile::config(),
+            system_program: system_program::ID,
+        },
+        profile::instruction::Initialize {},
+    )
+}
+
+fn writers(admin: Pubkey) -> profile::accounts::UpdateWriters {
+    profile::accounts::UpdateWriters {
+        admin,
+        config: pda::profile::config(),
+    }
+}
+
+fn add_writer(admin: Pubkey, program: Pubkey) -> Instruction {
+    instruction(
+        profile::ID,
+        writers(admin),
+        profile::instruction::AddWriter { program },
+    )
+}
+
+fn remove_writer(admin: Pubkey, program: Pubkey) -> Instruction {
+    instruction(
+        profile::ID,
+        writers(admin),
+        profile::instruction::RemoveWriter { program },
+    )
+}
+
+fn create_profile(owner: Pubkey, display_name: &str) -> Instruction {
+    instruction(
+        profile::ID,
+        profile::accounts::CreateProfile {
+            owner,
+            profile: pda::profile::profile(&owner),
+            system_program: system_program::ID,
+        },
+        profile::instruction::CreateProfile {
+            display_name: display_name.to_string(),
+            main_character: CHARACTER,
+        },
+    )
+}
+
+fn update_profile(owner: Pubkey, display_name: &str, favorite_combo: Pubkey) -> Instruction {
+    instruction(
+        profile::ID,
+        profile::accounts::UpdateProfile {
+            owner,
+            profile: pda::profile::profile(&owner),
+        },
+        profile::instruction::UpdateProfile {
+            display_name: display_name.to_string(),
+            main_character: CHARACTER + 1,
+            favorite_combo,
+        },
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        profile::ID,
+        profile::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        profile::instruction::MigrateAccount {},
+    )
+}
+
+async fn config(h: &mut Harness) -> ProfileConfig {
+    h.account(&pda::profile::config()).await
+}
+
+/// A funded owner with a profile named `NAME`.
+async fn owner(h: &mut Harness) -> Keypair {
+    let owner = h.funded_player().await;
+    h.send(&[create_profile(owner.pubkey(), NAME)], &[&owner])
+        .await
+        .unwrap();
+    owner
+}
+
+#[tokio::test]
+async fn the_admin_adds_and_removes_writers() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    h.send(&[initialize(admin)], &[]).await.unwrap();
+    let writer = Pubkey::new_unique();
+
+    let stranger = h.funded_player().await;
+    assert_error(
+        h.send(&[add_writer(stranger.pubkey(), writer)], &[&stranger])
+            .await,
+        ErrorCode::ConstraintHasOne,
+    );
+    h.send(&[add_writer(admin, writer)], &[]).await.unwrap();
+    let added = config(&mut h).await;
+    assert_eq!(added.admin, admin);
+    assert_eq!(added.writers, [writer]);
+    assert_error(
+        h.send(&[add_writer(admin, writer)], &[]).await,
+        ProfileError::WriterAlreadyAdded,
+    );
+
+    h.send(&[remove_writer(admin, writer)], &[]).await.unwrap();
+    assert!(config(&mut h).await.writers.is_empty());
+    assert_error(
+        h.send(&[remove_writer(admin, writer)], &[]).await,
+        ProfileError::UnknownWriter,
+    );
+}
+
+#[tokio::test]
+async fn the_writer_list_holds_at_most_max_writers() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let full: Vec<_> = (0..ProfileConfig::MAX_WRITERS)
+        .map(|_| add_writer(admin, Pubkey::new_unique()))
+        .collect();
+    h.send(&[initialize(admin)], &[]).await.unwrap();
+    h.send(&full, &[]).await.unwrap();
+
+    assert_error(
+        h.send(&[add_writer(admin, Pubkey::new_unique())], &[])
+            .await,
+        ProfileError::TooManyWriters,
+    );
+}
+
+#[tokio::test]
+async fn owners_create_and_update_their_profile() {
+    let mut h = Harness::start().await;
+    let owner = owner(&mut h).await;
+    let address = pda::profile::profile(&owner.pubkey());
+
+    let created: Profile = h.account(&address).await;
+    assert_eq!(created.owner, owner.pubkey());
+    assert_eq!(created.display_name, NAME);
+    assert_eq!(created.main_character, CHARACTER);
+    assert_eq!(
+        (created.matches_played, created.wins, created.win_rate_bps),
+        (0, 0, 0)
+    );
+    assert_eq!(created.favorite_combo, Pubkey::default());
+    assert!(created.badges.is_empty());
+    assert_eq!(created.created_at, h.now().await);
+
+    let combo = Pubkey::new_unique();
+    let ix = update_profile(owner.pubkey(), "Ken", combo);
+    h.send(&[ix], &[&owner]).await.unwrap();
+    let updated: Profile = h.account(&address).await;
+    assert_eq!(updated.display_name, "Ken");
+    assert_eq!(updated.main_character, CHARACTER + 1);
+    assert_eq!(updated.favorite_combo, combo);
+    assert_eq!(updated.created_at, created.created_at);
+}
+
+#[tokio::test]
+async fn ranked_matches_are_counted_on_profiles() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let one = fixtures::rated_player(&mut h).await;
+    let two = fixtures::rated_player(&mut h).await;
+    let three = fixtures::rated_player(&mut h).await;
+    for player in [&one, &two] {
+        fixtures::player_profile(&mut h, player).await;
+    }
+
+    // Three never made a profile, so only one's side of their match counts.
+    fixtures::recorded_match(&mut h, 1, [&one, &two], 0).await;
+    fixtures::apply_result(&mut h, 1, &one, &two).await;
+    fixtures::recorded_match(&mut h, 2, [&three, &one], 0).await;
+    fixtures::apply_result(&mut h, 2, &three, &one).await;
+    let counts = |profile: Profile| (profile.matches_played, profile.wins, profile.win_rate_bps);
+    let profile = |player: &Keypair| pda::profile::profile(&player.pubkey());
+    assert_eq!(counts(h.account(&profile(&one)).await), (2, 1, 5_000));
+    assert_eq!(counts(h.account(&profile(&two)).await), (1, 0, 0));
+    assert!(!h.exists(&profile(&three)).await);
+
+    // Once ranking is no longer a writer, its results can't be applied to
+    // players with a profile.
+    h.send(&[remove_writer(h.admin(), ranking::ID)], &[])
+        .await
+        .unwrap();
+    fixtures::recorded_match(&mut h, 3, [&one, &two], 0).await;
+    let ix = builders::apply_match_result(3, [one.pubkey(), two.pubkey()]);
+    assert_error(h.send(&[ix], &[]).await, ProfileError::UnknownWriter);
+}
+
+#[tokio::test]
+async fn display_names_are_one_to_thirty_two_bytes() {
+    let mut h = Harness::start().await;
+    let owner = owner(&mut h).await;
+    let too_long = "a".repeat(Profile::MAX_DISPLAY_NAME_LEN + 1);
+
+    for name in ["", &too_long] {
+        let ix = update_profile(owner.pubkey(), name, Pubkey::default());
+        assert_error(
+            h.send(&[ix], &[&owner]).await,
+            ProfileError::InvalidDisplayName,
+        );
+        let newcomer = h.funded_player().await;
+        let ix = create_profile(newcomer.pubkey(), name);
+        assert_error(
+            h.send(&[ix], &[&newcomer]).await,
+            ProfileError::InvalidDisplayName,
+        );
+    }
+    let longest = "a".repeat(Profile::MAX_DISPLAY_NAME_LEN);
+    let ix = update_profile(owner.pubkey(), &longest, Pubkey::default());
+    h.send(&[ix], &[&owner]).await.unwrap();
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    h.send(&[initialize(admin)], &[]).await.unwrap();
+    let owner = owner(&mut h).await;
+
+    for account in [
+        pda::profile::config(),
+        pda::profile::profile(&owner.pubkey()),
+    ] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            ProfileError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &profile::ID, vec![9; Profile::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, ProfileError::UnsupportedLayout);
+}
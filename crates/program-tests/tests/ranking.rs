@@ -0,0 +1,409 @@
+use anchor_lang::error::ErrorCode;
+use anchor_lang::solana_program::hash::hashv;
+use combo_mint_client::achievements::{Achievement, Badge, WIN_STREAK};
+use combo_mint_client::builders;
+use combo_mint_client::profile::Profile;
+use combo_mint_client::ranking::{
+    rating_delta, snapshot_leaf, LeaderboardSnapshot, PlayerRating, RankClaim, RankTier,
+    RankingConfig, RankingError, RankingSettings, INITIAL_RATING, STREAK_BADGE_LENGTH,
+};
+use combo_mint_client::{achievements, instruction, match_result, pda, profile, ranking};
+use program_tests::fixtures::{self, ranking_settings};
+use program_tests::{assert_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `RankingError::Overflow` needs 2^32 wins or games in one season, so no test
+// reaches it.
+
+fn set_settings(admin: Pubkey, settings: RankingSettings) -> Instruction {
+    instruction(
+        ranking::ID,
+        ranking::accounts::SetSettings {
+            admin,
+            config: pda::ranking::config(),
+        },
+        ranking::instruction::SetSettings { settings },
+    )
+}
+
+fn set_oracle(admin: Pubkey, oracle: Pubkey) -> Instruction {
+    instruction(
+        ranking::ID,
+        ranking::accounts::SetSettings {
+            admin,
+            config: pda::ranking::config(),
+        },
+        ranking::instruction::SetOracle { oracle },
+    )
+}
+
+fn publish_snapshot(
+    oracle: Pubkey,
+    epoch: u64,
+    merkle_root: [u8; 32],
+    player_count: u32,
+) -> Instruction {
+    instruction(
+        ranking::ID,
+        ranking::accounts::PublishSnapshot {
+            oracle,
+            config: pda::ranking::config(),
+            snapshot: pda::ranking::snapshot(epoch),
+            system_program: system_program::ID,
+        },
+        ranking::instruction::PublishSnapshot {
+            epoch,
+            merkle_root,
+            player_count,
+        },
+    )
+}
+
+fn award_streak_badge(player: Pubkey, payer: Pubkey) -> Instruction {
+    instruction(
+        ranking::ID,
+        ranking::accounts::AwardStreakBadge {
+            rating: pda::ranking::rating(&player),
+            player,
+            issuer: pda::achievements::issuer(&ranking::ID),
+            achievement: pda::achievements::achievement(WIN_STREAK),
+            badge: pda::achievements::badge(WIN_STREAK, &player),
+            profile: pda::profile::profile(&player),
+            profile_config: pda::profile::config(),
+            profile_writer: pda::profile::writer(&achievements::ID),
+            profile_program: profile::ID,
+            payer,
+            achievements_program: achievements::ID,
+            system_program: system_program::ID,
+        },
+        ranking::instruction::AwardStreakBadge {},
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        ranking::ID,
+        ranking::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        ranking::instruction::MigrateAccount {},
+    )
+}
+
+async fn rating(h: &mut Harness, player: &Keypair) -> PlayerRating {
+    h.account(&pda::ranking::rating(&player.pubkey())).await
+}
+
+/// Two rated players and a match the first won against the second.
+async fn rated_match(h: &mut Harness, match_id: u64) -> (Keypair, Keypair) {
+    let winner = fixtures::rated_player(h).await;
+    let loser = fixtures::rated_player(h).await;
+    fixtures::recorded_match(h, match_id, [&winner, &loser], 0).await;
+    (winner, loser)
+}
+
+#[tokio::test]
+async fn initializes_the_config_and_ratings() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let player = fixtures::rated_player(&mut h).await;
+
+    let config: RankingConfig = h.account(&pda::ranking::config()).await;
+    assert_eq!(config.admin, h.admin());
+    assert_eq!(config.settings, ranking_settings());
+    let rating = rating(&mut h, &player).await;
+    assert_eq!(rating.player, player.pubkey());
+    assert_eq!(rating.rating, INITIAL_RATING);
+    assert_eq!(rating.tier, RankTier::Unranked);
+}
+
+#[tokio::test]
+async fn rejects_out_of_range_settings() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let admin = h.admin();
+
+    let invalid: [fn(&mut RankingSettings); 5] = [
+        |s| s.decay_after = -1,
+        |s| s.decay_period = 0,
+        |s| s.placement_k_factor = ranking::K_FACTOR - 1,
+        |s| s.placement_k_factor = ranking::MAX_K_FACTOR + 1,
+        |s| s.tier_floors[2] = s.tier_floors[1],
+    ];
+    for invalidate in invalid {
+        let mut settings = ranking_settings();
+        invalidate(&mut settings);
+        let ix = set_settings(admin, settings);
+        assert_error(h.send(&[ix], &[]).await, RankingError::InvalidSettings);
+    }
+}
+
+#[tokio::test]
+async fn only_the_admin_changes_settings() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let admin = h.admin();
+    let intruder = h.funded_player().await;
+
+    let settings = RankingSettings {
+        decay_per_period: 0,
+        ..ranking_settings()
+    };
+    h.send(&[set_settings(admin, settings)], &[]).await.unwrap();
+    let config: RankingConfig = h.account(&pda::ranking::config()).await;
+    assert_eq!(config.settings, settings);
+
+    let ix = set_settings(intruder.pubkey(), ranking_settings());
+    assert_error(
+        h.send(&[ix], &[&intruder]).await,
+        ErrorCode::ConstraintHasOne,
+    );
+    let ix = set_oracle(intruder.pubkey(), intruder.pubkey());
+    assert_error(
+        h.send(&[ix], &[&intruder]).await,
+        ErrorCode::ConstraintHasOne,
+    );
+}
+
+#[tokio::test]
+async fn applies_a_match_result_once() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let (winner, loser) = rated_match(&mut h, 1).await;
+
+    fixtures::apply_result(&mut h, 1, &winner, &loser).await;
+    let delta = rating_delta(INITIAL_RATING, INITIAL_RATING);
+    let won = rating(&mut h, &winner).await;
+    assert_eq!(won.rating, INITIAL_RATING + delta);
+    assert_eq!((won.wins, won.streak, won.last_match_id), (1, 1, 1));
+    let lost = rating(&mut h, &loser).await;
+    assert_eq!(lost.rating, INITIAL_RATING - delta);
+    assert_eq!((lost.losses, lost.streak), (1, -1));
+    let record: match_result::MatchAccount = h.account(&pda::match_result::match_account(1)).await;
+    assert!(record.rating_applied);
+
+    let ix = builders::apply_match_result(1, [winner.pubkey(), loser.pubkey()]);
+    assert_error(h.send(&[ix], &[]).await, RankingError::AlreadyApplied);
+}
+
+#[tokio::test]
+async fn waits_for_a_set_to_be_finalized() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let one = fixtures::rated_player(&mut h).await;
+    let two = fixtures::rated_player(&mut h).await;
+    let ix = instruction(
+        match_result::ID,
+        match_result::accounts::RecordMatch {
+            player_one: one.pubkey(),
+            player_two: two.pubkey(),
+            match_account: pda::match_result::match_account(1),
+            system_program: system_program::ID,
+            event_authority: pda::match_result::event_authority(),
+            program: match_result::ID,
+        },
+        match_result::instruction::OpenSet {
+            match_id: 1,
+            best_of: 3,
+        },
+    );
+    h.send(&[ix], &[&one, &two]).await.unwrap();
+
+    let ix = builders::apply_match_result(1, [one.pubkey(), two.pubkey()]);
+    assert_error(h.send(&[ix], &[]).await, RankingError::MatchNotFinalized);
+}
+
+#[tokio::test]
+async fn placement_matches_use_their_own_k_factor() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let settings = RankingSettings {
+        placement_games: 1,
+        placement_k_factor: 64,
+        ..ranking_settings()
+    };
+    let admin = h.admin();
+    h.send(&[set_settings(admin, settings)], &[]).await.unwrap();
+    let (winner, loser) = rated_match(&mut h, 1).await;
+
+    fixtures::apply_result(&mut h, 1, &winner, &loser).await;
+    let won = rating(&mut h, &winner).await;
+    assert_eq!(
+        won.rating,
+        INITIAL_RATING + 2 * rating_delta(INITIAL_RATING, INITIAL_RATING)
+    );
+    assert_eq!(won.season_games, 1);
+    assert_ne!(won.tier, RankTier::Unranked);
+}
+
+#[tokio::test]
+async fn decays_idle_ratings() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let (winner, loser) = rated_match(&mut h, 1).await;
+    fixtures::apply_result(&mut h, 1, &winner, &loser).await;
+    let before = rating(&mut h, &winner).await.rating;
+
+    // Nothing is owed before `decay_after`, and the tier is already current.
+    let ix = builders::apply_decay(winner.pubkey());
+    assert_error(h.send(&[ix], &[]).await, RankingError::NothingToDecay);
+
+    let settings = ranking_settings();
+    h.advance(settings.decay_after + settings.decay_period)
+        .await;
+    let ix = builders::apply_decay(winner.pubkey());
+    h.send(&[ix], &[]).await.unwrap();
+    assert_eq!(
+        rating(&mut h, &winner).await.rating,
+        before - 2 * settings.decay_per_period
+    );
+
+    // The same periods are never taken twice.
+    let ix = builders::apply_decay(winner.pubkey());
+    assert_error(h.send(&[ix], &[]).await, RankingError::NothingToDecay);
+}
+
+#[tokio::test]
+async fn decay_refreshes_a_stale_tier() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let player = fixtures::rated_player(&mut h).await;
+
+    // A new rating is Unranked until something places it.
+    let ix = builders::apply_decay(player.pubkey());
+    h.send(&[ix], &[]).await.unwrap();
+    assert_eq!(rating(&mut h, &player).await.tier, RankTier::Silver);
+    assert_eq!(rating(&mut h, &player).await.rating, INITIAL_RATING);
+}
+
+/// Root of a two-leaf tree, with each leaf's proof being the other leaf.
+fn two_leaf_root(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&left, &right]).to_bytes()
+}
+
+#[tokio::test]
+async fn players_claim_ranks_proven_by_the_snapshot() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let admin = h.admin();
+    let oracle = h.funded_player().await;
+    let first = h.funded_player().await;
+    let second = h.funded_player().await;
+    let first_leaf = snapshot_leaf(1, 1, &first.pubkey(), 1500);
+    let second_leaf = snapshot_leaf(1, 2, &second.pubkey(), 1400);
+    let root = two_leaf_root(first_leaf, second_leaf);
+
+    h.send(&[set_oracle(admin, oracle.pubkey())], &[])
+        .await
+        .unwrap();
+    let ix = publish_snapshot(oracle.pubkey(), 1, root, 2);
+    h.send(&[ix], &[&oracle]).await.unwrap();
+    let snapshot: LeaderboardSnapshot = h.account(&pda::ranking::snapshot(1)).await;
+    assert_eq!((snapshot.merkle_root, snapshot.player_count), (root, 2));
+
+    let ix = builders::claim_rank(first.pubkey(), 1, 1, 1500, vec![second_leaf]);
+    h.send(&[ix], &[&first]).await.unwrap();
+    let claim: RankClaim = h
+        .account(&pda::ranking::rank_claim(1, &first.pubkey()))
+        .await;
+    assert_eq!((claim.rank, claim.rating), (1, 1500));
+
+    let cases = [
+        (0, 1400, RankingError::InvalidRank),
+        (3, 1400, RankingError::InvalidRank),
+        (2, 1500, RankingError::InvalidMerkleProof),
+        (1, 1400, RankingError::InvalidMerkleProof),
+    ];
+    for (rank, claimed, expected) in cases {
+        let ix = builders::claim_rank(second.pubkey(), 1, rank, claimed, vec![first_leaf]);
+        assert_error(h.send(&[ix], &[&second]).await, expected);
+    }
+}
+
+#[tokio::test]
+async fn snapshots_need_players_and_a_newer_epoch() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let admin = h.admin();
+    let intruder = h.funded_player().await;
+    h.send(&[set_oracle(admin, admin)], &[]).await.unwrap();
+
+    h.send(&[publish_snapshot(admin, 2, [1; 32], 10)], &[])
+        .await
+        .unwrap();
+    let ix = publish_snapshot(admin, 1, [1; 32], 10);
+    assert_error(h.send(&[ix], &[]).await, RankingError::StaleEpoch);
+    let ix = publish_snapshot(admin, 3, [1; 32], 0);
+    assert_error(h.send(&[ix], &[]).await, RankingError::EmptySnapshot);
+
+    let ix = publish_snapshot(intruder.pubkey(), 3, [1; 32], 10);
+    assert_error(
+        h.send(&[ix], &[&intruder]).await,
+        ErrorCode::ConstraintHasOne,
+    );
+}
+
+#[tokio::test]
+async fn awards_the_streak_badge() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    fixtures::achievements(&mut h).await;
+    let payer = h.admin();
+    let streaker = fixtures::rated_player(&mut h).await;
+    let opponent = fixtures::rated_player(&mut h).await;
+    fixtures::player_profile(&mut h, &streaker).await;
+
+    for match_id in 1..STREAK_BADGE_LENGTH as u64 {
+        fixtures::recorded_match(&mut h, match_id, [&streaker, &opponent], 0).await;
+        fixtures::apply_result(&mut h, match_id, &streaker, &opponent).await;
+    }
+    let ix = award_streak_badge(streaker.pubkey(), payer);
+    assert_error(h.send(&[ix], &[]).await, RankingError::StreakTooShort);
+
+    let last = STREAK_BADGE_LENGTH as u64;
+    fixtures::recorded_match(&mut h, last, [&streaker, &opponent], 0).await;
+    fixtures::apply_result(&mut h, last, &streaker, &opponent).await;
+    let ix = award_streak_badge(streaker.pubkey(), payer);
+    h.send(&[ix], &[]).await.unwrap();
+
+    let badge: Badge = h
+        .account(&pda::achievements::badge(WIN_STREAK, &streaker.pubkey()))
+        .await;
+    assert_eq!(badge.player, streaker.pubkey());
+    assert_eq!(badge.achievement_id, WIN_STREAK);
+    let achievement: Achievement = h.account(&pda::achievements::achievement(WIN_STREAK)).await;
+    assert_eq!(achievement.awarded_count, 1);
+    let profile: Profile = h.account(&pda::profile::profile(&streaker.pubkey())).await;
+    assert_eq!(profile.badges, [WIN_STREAK]);
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    fixtures::ranking(&mut h).await;
+    let player = fixtures::rated_player(&mut h).await;
+    let payer = h.admin();
+
+    for account in [
+        pda::ranking::config(),
+        pda::ranking::rating(&player.pubkey()),
+    ] {
+        assert_error(
+            h.send(&[migrate(payer, account)], &[]).await,
+            RankingError::AlreadyMigrated,
+        );
+    }
+
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &ranking::ID, vec![9; PlayerRating::SPACE]);
+    assert_error(
+        h.send(&[migrate(payer, unknown)], &[]).await,
+        RankingError::UnsupportedLayout,
+    );
+}
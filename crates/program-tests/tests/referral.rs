@@ -0,0 +1,246 @@
+use anchor_lang::error::ErrorCode;
+use combo_mint_client::referral::{
+    FeeKind, Referral, ReferralCode, ReferralConfig, ReferralError, MAX_REFERRAL_BPS,
+};
+use combo_mint_client::{instruction, pda, referral};
+use program_tests::{assert_error, fixtures, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `ReferralError::Overflow` needs more than `u32::MAX` referrals or
+// `u64::MAX` lamports earned, so no test reaches it.
+
+const CODE: &str = "Rival";
+/// Share of entry fees the config pays referrers.
+const ENTRY_FEE_BPS: u16 = 1_000;
+const FEE: u64 = 1_000_000;
+
+fn set_rates(admin: Pubkey, entry_fee_bps: u16) -> Instruction {
+    instruction(
+        referral::ID,
+        referral::accounts::SetRates {
+            admin,
+            config: pda::referral::config(),
+        },
+        referral::instruction::SetRates { entry_fee_bps },
+    )
+}
+
+fn pay_referral(player: Pubkey, code: &str, kind: FeeKind, fee: u64) -> Instruction {
+    instruction(
+        referral::ID,
+        referral::accounts::PayReferral {
+            player,
+            referral: pda::referral::referral(&player),
+            config: pda::referral::config(),
+            referral_code: pda::referral::code(code),
+            system_program: system_program::ID,
+        },
+        referral::instruction::PayReferral { kind, fee },
+    )
+}
+
+fn claim(owner: Pubkey, code: &str) -> Instruction {
+    instruction(
+        referral::ID,
+        referral::accounts::Claim {
+            owner,
+            referral_code: pda::referral::code(code),
+        },
+        referral::instruction::Claim {},
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        referral::ID,
+        referral::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        referral::instruction::MigrateAccount {},
+    )
+}
+
+/// The config at `ENTRY_FEE_BPS`, a referrer owning `CODE` and a player it
+/// referred.
+async fn referred(h: &mut Harness) -> (Keypair, Keypair) {
+    fixtures::referral(h, ENTRY_FEE_BPS).await;
+    let referrer = h.funded_player().await;
+    let player = h.funded_player().await;
+    let ixs = [
+        fixtures::create_code(referrer.pubkey(), CODE),
+        fixtures::register_referral(player.pubkey(), CODE, referrer.pubkey()),
+    ];
+    h.send(&ixs, &[&referrer, &player]).await.unwrap();
+    (referrer, player)
+}
+
+#[tokio::test]
+async fn the_admin_sets_rates_up_to_the_maximum() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let too_high = MAX_REFERRAL_BPS + 1;
+    let ix = instruction(
+        referral::ID,
+        referral::accounts::Initialize {
+            admin,
+            config: pda::referral::config(),
+            system_program: system_program::ID,
+        },
+        referral::instruction::Initialize {
+            entry_fee_bps: too_high,
+        },
+    );
+    assert_error(h.send(&[ix], &[]).await, ReferralError::RateTooHigh);
+    fixtures::referral(&mut h, ENTRY_FEE_BPS).await;
+
+    assert_error(
+        h.send(&[set_rates(admin, too_high)], &[]).await,
+        ReferralError::RateTooHigh,
+    );
+    let stranger = h.funded_player().await;
+    assert_error(
+        h.send(&[set_rates(stranger.pubkey(), 0)], &[&stranger])
+            .await,
+        ErrorCode::ConstraintHasOne,
+    );
+    h.send(&[set_rates(admin, MAX_REFERRAL_BPS)], &[])
+        .await
+        .unwrap();
+    let config: ReferralConfig = h.account(&pda::referral::config()).await;
+    assert_eq!(config.admin, admin);
+    assert_eq!(config.entry_fee_bps, MAX_REFERRAL_BPS);
+}
+
+#[tokio::test]
+async fn codes_are_short_and_alphanumeric() {
+    let mut h = Harness::start().await;
+    let owner = h.funded_player().await;
+
+    let too_long = "a".repeat(ReferralCode::MAX_CODE_LEN + 1);
+    for code in ["ab", &too_long, "no-dash"] {
+        let ix = fixtures::create_code(owner.pubkey(), code);
+        assert_error(h.send(&[ix], &[&owner]).await, ReferralError::InvalidCode);
+    }
+
+    let ix = fixtures::create_code(owner.pubkey(), CODE);
+    h.send(&[ix], &[&owner]).await.unwrap();
+    // Any casing finds the same code.
+    let code: ReferralCode = h.account(&pda::referral::code("RIVAL")).await;
+    assert_eq!(code.owner, owner.pubkey());
+    assert_eq!(code.code, CODE);
+    assert_eq!(code.referred_count, 0);
+    assert_eq!(code.claimable, 0);
+}
+
+#[tokio::test]
+async fn players_register_who_referred_them() {
+    let mut h = Harness::start().await;
+    let (referrer, player) = referred(&mut h).await;
+
+    let referral: Referral = h.account(&pda::referral::referral(&player.pubkey())).await;
+    assert_eq!(referral.player, player.pubkey());
+    assert_eq!(referral.referrer, referrer.pubkey());
+    assert_eq!(referral.code, pda::referral::code(CODE));
+    assert_eq!(referral.registered_at, h.now().await);
+    let code: ReferralCode = h.account(&pda::referral::code(CODE)).await;
+    assert_eq!(code.referred_count, 1);
+}
+
+#[tokio::test]
+async fn wallets_cannot_refer_themselves() {
+    let mut h = Harness::start().await;
+    let (referrer, player) = referred(&mut h).await;
+
+    let ix = fixtures::register_referral(referrer.pubkey(), CODE, referrer.pubkey());
+    assert_error(
+        h.send(&[ix], &[&referrer]).await,
+        ReferralError::SelfReferral,
+    );
+    // Nor back through a wallet they referred.
+    let ixs = [
+        fixtures::create_code(player.pubkey(), "Back"),
+        fixtures::register_referral(referrer.pubkey(), "Back", player.pubkey()),
+    ];
+    assert_error(
+        h.send(&ixs, &[&player, &referrer]).await,
+        ReferralError::SelfReferral,
+    );
+}
+
+#[tokio::test]
+async fn referrers_earn_their_share_and_claim_it() {
+    let mut h = Harness::start().await;
+    let (referrer, player) = referred(&mut h).await;
+    let code = pda::referral::code(CODE);
+
+    assert_error(
+        h.send(&[claim(referrer.pubkey(), CODE)], &[&referrer])
+            .await,
+        ReferralError::NothingToClaim,
+    );
+    let code_before = h.lamports(&code).await;
+    let ix = pay_referral(player.pubkey(), CODE, FeeKind::Entry, FEE);
+    h.send(&[ix], &[&player]).await.unwrap();
+    let share = FEE * ENTRY_FEE_BPS as u64 / 10_000;
+    let earned: ReferralCode = h.account(&code).await;
+    assert_eq!(earned.claimable, share);
+    assert_eq!(earned.total_earned, share);
+    assert_eq!(h.lamports(&code).await, code_before + share);
+
+    let stranger = h.funded_player().await;
+    assert_error(
+        h.send(&[claim(stranger.pubkey(), CODE)], &[&stranger])
+            .await,
+        ErrorCode::ConstraintHasOne,
+    );
+    let owner_before = h.lamports(&referrer.pubkey()).await;
+    h.send(&[claim(referrer.pubkey(), CODE)], &[&referrer])
+        .await
+        .unwrap();
+    assert_eq!(h.lamports(&referrer.pubkey()).await, owner_before + share);
+    assert_eq!(h.lamports(&code).await, code_before);
+    let claimed: ReferralCode = h.account(&code).await;
+    assert_eq!(claimed.claimable, 0);
+    assert_eq!(claimed.total_earned, share);
+
+    // At a zero rate fees pay nothing.
+    let ixs = [
+        set_rates(h.admin(), 0),
+        pay_referral(player.pubkey(), CODE, FeeKind::Entry, FEE),
+    ];
+    h.send(&ixs, &[&player]).await.unwrap();
+    assert_eq!(h.lamports(&code).await, code_before);
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let (_, player) = referred(&mut h).await;
+
+    let admin = h.admin();
+    for account in [
+        pda::referral::config(),
+        pda::referral::code(CODE),
+        pda::referral::referral(&player.pubkey()),
+    ] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            ReferralError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &referral::ID, vec![9; Referral::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, ReferralError::UnsupportedLayout);
+}
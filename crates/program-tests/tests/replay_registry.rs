@@ -0,0 +1,140 @@
+use combo_mint_client::builders::{self, ReplayAnchor};
+use combo_mint_client::replay_registry::{
+    ReplayError, ReplayRecord, MIN_REPLAY_FORMAT_VERSION, REPLAY_FORMAT_VERSION,
+};
+use combo_mint_client::{instruction, pda, replay_registry};
+use program_tests::fixtures::CHARACTER;
+use program_tests::{assert_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+fn anchor(players: [Pubkey; 2]) -> ReplayAnchor {
+    ReplayAnchor {
+        match_id: 11,
+        players,
+        character_ids: [CHARACTER, CHARACTER],
+        seed: 0xfeed,
+        game_version: 3,
+        format_version: REPLAY_FORMAT_VERSION,
+        frame_count: 3_600,
+        replay_hash: [5; 32],
+    }
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        replay_registry::ID,
+        replay_registry::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        replay_registry::instruction::MigrateAccount {},
+    )
+}
+
+#[tokio::test]
+async fn a_player_anchors_the_replay() {
+    let mut h = Harness::start().await;
+    let player = h.funded_player().await;
+    let opponent = Keypair::new().pubkey();
+
+    let replay = anchor([opponent, player.pubkey()]);
+    let ix = builders::finalize_replay(player.pubkey(), replay.clone());
+    h.send(&[ix], &[&player]).await.unwrap();
+
+    let record: ReplayRecord = h.account(&pda::replay_registry::replay(11)).await;
+    assert_eq!(record.submitter, player.pubkey());
+    assert_eq!(record.players, replay.players);
+    assert_eq!(record.seed, replay.seed);
+    assert_eq!(record.frame_count, replay.frame_count);
+    assert_eq!(record.replay_hash, replay.replay_hash);
+    assert_eq!(record.finalized_at, h.now().await);
+}
+
+#[tokio::test]
+async fn accepts_every_supported_format() {
+    let mut h = Harness::start().await;
+    let player = h.funded_player().await;
+    let opponent = Keypair::new().pubkey();
+
+    for format_version in MIN_REPLAY_FORMAT_VERSION..=REPLAY_FORMAT_VERSION {
+        let replay = ReplayAnchor {
+            match_id: format_version as u64,
+            format_version,
+            ..anchor([player.pubkey(), opponent])
+        };
+        let ix = builders::finalize_replay(player.pubkey(), replay);
+        h.send(&[ix], &[&player]).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn rejects_invalid_replays() {
+    let mut h = Harness::start().await;
+    let player = h.funded_player().await;
+    let opponent = Keypair::new().pubkey();
+    let valid = anchor([player.pubkey(), opponent]);
+
+    let cases = [
+        (
+            anchor([opponent, Keypair::new().pubkey()]),
+            ReplayError::NotAParticipant,
+        ),
+        (
+            anchor([player.pubkey(), player.pubkey()]),
+            ReplayError::InvalidPlayers,
+        ),
+        (
+            ReplayAnchor {
+                frame_count: 0,
+                ..valid.clone()
+            },
+            ReplayError::EmptyReplay,
+        ),
+        (
+            ReplayAnchor {
+                format_version: MIN_REPLAY_FORMAT_VERSION - 1,
+                ..valid.clone()
+            },
+            ReplayError::UnsupportedFormat,
+        ),
+        (
+            ReplayAnchor {
+                format_version: REPLAY_FORMAT_VERSION + 1,
+                ..valid
+            },
+            ReplayError::UnsupportedFormat,
+        ),
+    ];
+    for (replay, expected) in cases {
+        let ix = builders::finalize_replay(player.pubkey(), replay);
+        assert_error(h.send(&[ix], &[&player]).await, expected);
+    }
+}
+
+#[tokio::test]
+async fn replays_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let player = h.funded_player().await;
+    let ix = builders::finalize_replay(
+        player.pubkey(),
+        anchor([player.pubkey(), Keypair::new().pubkey()]),
+    );
+    h.send(&[ix], &[&player]).await.unwrap();
+
+    let ix = migrate(h.admin(), pda::replay_registry::replay(11));
+    assert_error(h.send(&[ix], &[]).await, ReplayError::AlreadyMigrated);
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &replay_registry::ID, vec![9; ReplayRecord::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, ReplayError::UnsupportedLayout);
+}
@@ -0,0 +1,398 @@
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use combo_mint_client::rewards::{ClaimBitmap, Distributor, RewardsError, CLAIMS_PER_BITMAP};
+use combo_mint_client::{instruction, pda, rewards};
+use program_tests::{assert_error, Harness};
+use solana_sdk::hash::hashv;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::{system_program, sysvar};
+
+const DISTRIBUTOR_ID: u64 = 1;
+const AMOUNT: u64 = 100;
+
+fn create_distributor(
+    admin: Pubkey,
+    mint: Pubkey,
+    token_program: Pubkey,
+    merkle_root: [u8; 32],
+    claim_count: u32,
+    total_amount: u64,
+) -> Instruction {
+    let distributor = pda::rewards::distributor(DISTRIBUTOR_ID);
+    instruction(
+        rewards::ID,
+        rewards::accounts::CreateDistributor {
+            admin,
+            distributor,
+            vault: pda::rewards::vault(&distributor),
+            mint,
+            token_program,
+            system_program: system_program::ID,
+            rent: sysvar::rent::ID,
+        },
+        rewards::instruction::CreateDistributor {
+            distributor_id: DISTRIBUTOR_ID,
+            merkle_root,
+            claim_count,
+            total_amount,
+        },
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        rewards::ID,
+        rewards::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        rewards::instruction::MigrateAccount {},
+    )
+}
+
+/// The bitmap tracking claim `index`.
+fn bitmap(index: u32) -> Pubkey {
+    let distributor = pda::rewards::distributor(DISTRIBUTOR_ID);
+    let chunk = index / CLAIMS_PER_BITMAP;
+    Pubkey::find_program_address(
+        &[b"claims", distributor.as_ref(), &chunk.to_le_bytes()],
+        &rewards::ID,
+    )
+    .0
+}
+
+fn leaf(index: u32, claimant: &Pubkey, amount: u64) -> [u8; 32] {
+    hashv(&[
+        &index.to_le_bytes(),
+        claimant.as_ref(),
+        &amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+fn node(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    hashv(&[&low, &high]).to_bytes()
+}
+
+/// The root over `leaves` and each leaf's proof, pairing nodes level by
+/// level and carrying an odd one out up unpaired.
+fn merkle(leaves: &[[u8; 32]]) -> ([u8; 32], Vec<Vec<[u8; 32]>>) {
+    let mut proofs = vec![Vec::new(); leaves.len()];
+    let mut level: Vec<([u8; 32], Vec<usize>)> = leaves
+        .iter()
+        .enumerate()
+        .map(|(i, &leaf)| (leaf, vec![i]))
+        .collect();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [(a, left), (b, right)] => {
+                    left.iter().for_each(|&i| proofs[i].push(*b));
+                    right.iter().for_each(|&i| proofs[i].push(*a));
+                    (node(*a, *b), [&left[..], &right[..]].concat())
+                }
+                [carried] => carried.clone(),
+                _ => unreachable!(),
+            })
+            .collect();
+    }
+    (level[0].0, proofs)
+}
+
+/// One leaf of the distribution, owned by a funded claimant with an empty
+/// token account.
+struct Claimant {
+    wallet: Keypair,
+    tokens: Pubkey,
+    index: u32,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+}
+
+/// A distributor over a fresh mint, its vault funded with `total_amount`.
+struct Distribution {
+    address: Pubkey,
+    mint: Pubkey,
+    claimants: Vec<Claimant>,
+}
+
+impl Distribution {
+    /// One claimant per `(index, amount)` leaf.
+    async fn new(
+        h: &mut Harness,
+        leaves: &[(u32, u64)],
+        claim_count: u32,
+        total_amount: u64,
+    ) -> Self {
+        let admin = h.admin();
+        let mint = h.put_mint(&admin);
+        let mut wallets = Vec::new();
+        for _ in leaves {
+            wallets.push(h.funded_player().await);
+        }
+        let hashes: Vec<_> = leaves
+            .iter()
+            .zip(&wallets)
+            .map(|(&(index, amount), wallet)| leaf(index, &wallet.pubkey(), amount))
+            .collect();
+        let (root, proofs) = merkle(&hashes);
+
+        let address = pda::rewards::distributor(DISTRIBUTOR_ID);
+        let funds = h.put_token_account(&mint, &admin, total_amount).await;
+        let ixs = [
+            create_distributor(admin, mint, spl_token::ID, root, claim_count, total_amount),
+            spl_token::instruction::transfer(
+                &spl_token::ID,
+                &funds,
+                &pda::rewards::vault(&address),
+                &admin,
+                &[],
+                total_amount,
+            )
+            .unwrap(),
+        ];
+        h.send(&ixs, &[]).await.unwrap();
+
+        let mut claimants = Vec::new();
+        for ((wallet, proof), &(index, amount)) in wallets.into_iter().zip(proofs).zip(leaves) {
+            claimants.push(Claimant {
+                tokens: h.put_token_account(&mint, &wallet.pubkey(), 0).await,
+                wallet,
+                index,
+                amount,
+                proof,
+            });
+        }
+        Distribution {
+            address,
+            mint,
+            claimants,
+        }
+    }
+
+    async fn distributor(&self, h: &mut Harness) -> Distributor {
+        h.account(&self.address).await
+    }
+
+    /// `claimant` claiming `index` and `amount` with `proof`, which need not
+    /// be their own leaf's.
+    fn claim_as(
+        &self,
+        claimant: &Claimant,
+        index: u32,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Instruction {
+        instruction(
+            rewards::ID,
+            rewards::accounts::Claim {
+                claimant: claimant.wallet.pubkey(),
+                distributor: self.address,
+                bitmap: bitmap(index),
+                vault: pda::rewards::vault(&self.address),
+                mint: self.mint,
+                claimant_token_account: claimant.tokens,
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            },
+            rewards::instruction::Claim {
+                index,
+                amount,
+                proof,
+            },
+        )
+    }
+
+    /// `claimants[i]` claiming their own leaf.
+    fn claim(&self, i: usize) -> Instruction {
+        let claimant = &self.claimants[i];
+        self.claim_as(
+            claimant,
+            claimant.index,
+            claimant.amount,
+            claimant.proof.clone(),
+        )
+    }
+}
+
+#[tokio::test]
+async fn distributors_publish_a_root_over_a_funded_vault() {
+    let mut h = Harness::start().await;
+    let leaves = [(0, AMOUNT), (1, 2 * AMOUNT), (2, 3 * AMOUNT)];
+    let airdrop = Distribution::new(&mut h, &leaves, 3, 6 * AMOUNT).await;
+
+    let distributor = airdrop.distributor(&mut h).await;
+    assert_eq!(distributor.admin, h.admin());
+    assert_eq!(distributor.distributor_id, DISTRIBUTOR_ID);
+    assert_eq!(distributor.mint, airdrop.mint);
+    let hashes: Vec<_> = airdrop
+        .claimants
+        .iter()
+        .map(|c| leaf(c.index, &c.wallet.pubkey(), c.amount))
+        .collect();
+    assert_eq!(distributor.merkle_root, merkle(&hashes).0);
+    assert_eq!(distributor.claim_count, 3);
+    assert_eq!(distributor.total_amount, 6 * AMOUNT);
+    assert_eq!(distributor.claimed_amount, 0);
+    let vault = pda::rewards::vault(&airdrop.address);
+    assert_eq!(h.token_balance(&vault).await, 6 * AMOUNT);
+}
+
+#[tokio::test]
+async fn distributions_need_claims_and_a_supported_mint() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+
+    let mint = h.put_mint(&admin);
+    let ix = create_distributor(admin, mint, spl_token::ID, [1; 32], 0, AMOUNT);
+    assert_error(h.send(&[ix], &[]).await, RewardsError::EmptyDistribution);
+    let mint = h.put_unsupported_mint(&admin);
+    let ix = create_distributor(admin, mint, spl_token_2022::ID, [1; 32], 1, AMOUNT);
+    assert_error(h.send(&[ix], &[]).await, RewardsError::UnsupportedMint);
+}
+
+#[tokio::test]
+async fn claimants_are_paid_their_leaf_once() {
+    let mut h = Harness::start().await;
+    let leaves = [(0, AMOUNT), (1, 2 * AMOUNT), (2, 3 * AMOUNT)];
+    let airdrop = Distribution::new(&mut h, &leaves, 3, 6 * AMOUNT).await;
+
+    for i in [2, 0] {
+        let ix = airdrop.claim(i);
+        let claimant = &airdrop.claimants[i];
+        h.send(&[ix], &[&claimant.wallet]).await.unwrap();
+        assert_eq!(h.token_balance(&claimant.tokens).await, claimant.amount);
+    }
+    assert_eq!(airdrop.distributor(&mut h).await.claimed_amount, 4 * AMOUNT);
+    let vault = pda::rewards::vault(&airdrop.address);
+    assert_eq!(h.token_balance(&vault).await, 2 * AMOUNT);
+    let claimed: ClaimBitmap = h.account(&bitmap(0)).await;
+    assert_eq!(claimed.distributor, airdrop.address);
+    assert_eq!(claimed.chunk, 0);
+    assert_eq!(
+        [0, 1, 2].map(|bit| claimed.is_set(bit)),
+        [true, false, true]
+    );
+
+    let ix = airdrop.claim(0);
+    assert_error(
+        h.send(&[ix], &[&airdrop.claimants[0].wallet]).await,
+        RewardsError::AlreadyClaimed,
+    );
+}
+
+#[tokio::test]
+async fn claims_past_a_bitmap_start_the_next_one() {
+    let mut h = Harness::start().await;
+    let leaves = [(0, AMOUNT), (CLAIMS_PER_BITMAP, AMOUNT)];
+    let airdrop = Distribution::new(&mut h, &leaves, CLAIMS_PER_BITMAP + 1, 2 * AMOUNT).await;
+
+    let ix = airdrop.claim(1);
+    h.send(&[ix], &[&airdrop.claimants[1].wallet])
+        .await
+        .unwrap();
+    assert!(!h.exists(&bitmap(0)).await);
+    let claimed: ClaimBitmap = h.account(&bitmap(CLAIMS_PER_BITMAP)).await;
+    assert_eq!(claimed.chunk, 1);
+    assert!(claimed.is_set(0));
+    let ix = airdrop.claim(0);
+    h.send(&[ix], &[&airdrop.claimants[0].wallet])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn claims_must_prove_their_leaf() {
+    let mut h = Harness::start().await;
+    let leaves = [(0, AMOUNT), (1, AMOUNT)];
+    let airdrop = Distribution::new(&mut h, &leaves, 2, 2 * AMOUNT).await;
+    let [first, second] = &airdrop.claimants[..] else {
+        unreachable!()
+    };
+
+    let cases = [
+        // Past `claim_count`, even with a valid proof.
+        (
+            first,
+            2,
+            AMOUNT,
+            first.proof.clone(),
+            RewardsError::InvalidIndex,
+        ),
+        (
+            first,
+            0,
+            2 * AMOUNT,
+            first.proof.clone(),
+            RewardsError::InvalidProof,
+        ),
+        (
+            second,
+            0,
+            AMOUNT,
+            first.proof.clone(),
+            RewardsError::InvalidProof,
+        ),
+        (first, 0, AMOUNT, Vec::new(), RewardsError::InvalidProof),
+    ];
+    for (claimant, index, amount, proof, error) in cases {
+        let ix = airdrop.claim_as(claimant, index, amount, proof);
+        assert_error(h.send(&[ix], &[&claimant.wallet]).await, error);
+    }
+}
+
+#[tokio::test]
+async fn claims_stay_within_the_total() {
+    let mut h = Harness::start().await;
+    // A tree paying more than the total, and a leaf no total could cover.
+    let leaves = [(0, AMOUNT), (1, 2 * AMOUNT), (2, u64::MAX)];
+    let airdrop = Distribution::new(&mut h, &leaves, 3, 2 * AMOUNT).await;
+
+    let ix = airdrop.claim(0);
+    h.send(&[ix], &[&airdrop.claimants[0].wallet])
+        .await
+        .unwrap();
+    let ix = airdrop.claim(1);
+    assert_error(
+        h.send(&[ix], &[&airdrop.claimants[1].wallet]).await,
+        RewardsError::ExceedsTotal,
+    );
+    let ix = airdrop.claim(2);
+    assert_error(
+        h.send(&[ix], &[&airdrop.claimants[2].wallet]).await,
+        RewardsError::Overflow,
+    );
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let airdrop = Distribution::new(&mut h, &[(0, AMOUNT)], 1, AMOUNT).await;
+    let ix = airdrop.claim(0);
+    h.send(&[ix], &[&airdrop.claimants[0].wallet])
+        .await
+        .unwrap();
+
+    let admin = h.admin();
+    for account in [airdrop.address, bitmap(0)] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            RewardsError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &rewards::ID, vec![9; Distributor::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, RewardsError::UnsupportedLayout);
+}
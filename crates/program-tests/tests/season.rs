@@ -0,0 +1,447 @@
+use anchor_lang::error::ErrorCode;
+use anchor_lang::Space;
+use combo_mint_client::combo_mint::{ComboAccount, MIN_VERIFICATIONS_FOR_NFT};
+use combo_mint_client::season::{
+    ArchivedStanding, Season, SeasonArchive, SeasonError, SeasonStanding, SeasonState,
+    COMBO_POINTS, LOSS_POINTS, WIN_POINTS,
+};
+use combo_mint_client::{combo_mint, instruction, pda, season};
+use program_tests::{assert_error, fixtures, Harness};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `SeasonError::Overflow` needs more than `u32::MAX` seasons, players or
+// points, so no test reaches it.
+
+const MATCH_ID: u64 = 1;
+
+fn initialize(admin: Pubkey) -> Instruction {
+    instruction(
+        season::ID,
+        season::accounts::Initialize {
+            admin,
+            state: pda::season::state(),
+            system_program: system_program::ID,
+        },
+        season::instruction::Initialize {},
+    )
+}
+
+/// Starts season `season_id`, which must be the one after the newest.
+fn start_season(admin: Pubkey, season_id: u32) -> Instruction {
+    instruction(
+        season::ID,
+        season::accounts::StartSeason {
+            admin,
+            state: pda::season::state(),
+            season: pda::season::season(season_id),
+            system_program: system_program::ID,
+        },
+        season::instruction::StartSeason {},
+    )
+}
+
+fn join_season(player: Pubkey, season_id: u32) -> Instruction {
+    instruction(
+        season::ID,
+        season::accounts::JoinSeason {
+            player,
+            season: pda::season::season(season_id),
+            standing: pda::season::standing(season_id, &player),
+            system_program: system_program::ID,
+        },
+        season::instruction::JoinSeason {},
+    )
+}
+
+/// Scores match `match_id`, won by `winner`.
+fn record_match(
+    payer: Pubkey,
+    season_id: u32,
+    match_id: u64,
+    winner: Pubkey,
+    loser: Pubkey,
+) -> Instruction {
+    let match_account = pda::match_result::match_account(match_id);
+    instruction(
+        season::ID,
+        season::accounts::RecordMatch {
+            payer,
+            season: pda::season::season(season_id),
+            match_account,
+            winner_standing: pda::season::standing(season_id, &winner),
+            loser_standing: pda::season::standing(season_id, &loser),
+            entry: pda::season::entry(season_id, &match_account),
+            system_program: system_program::ID,
+        },
+        season::instruction::RecordMatch {},
+    )
+}
+
+fn record_combo(payer: Pubkey, season_id: u32, combo: Pubkey, creator: Pubkey) -> Instruction {
+    instruction(
+        season::ID,
+        season::accounts::RecordCombo {
+            payer,
+            season: pda::season::season(season_id),
+            combo,
+            standing: pda::season::standing(season_id, &creator),
+            entry: pda::season::entry(season_id, &combo),
+            system_program: system_program::ID,
+        },
+        season::instruction::RecordCombo {},
+    )
+}
+
+/// Ends the newest season, `season_id`, ranking `standings`.
+fn end_season(admin: Pubkey, season_id: u32, standings: &[Pubkey]) -> Instruction {
+    let mut ix = instruction(
+        season::ID,
+        season::accounts::EndSeason {
+            admin,
+            state: pda::season::state(),
+            season: pda::season::season(season_id),
+            archive: pda::season::archive(season_id),
+            system_program: system_program::ID,
+        },
+        season::instruction::EndSeason {},
+    );
+    ix.accounts.extend(
+        standings
+            .iter()
+            .map(|standing| AccountMeta::new_readonly(*standing, false)),
+    );
+    ix
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        season::ID,
+        season::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        season::instruction::MigrateAccount {},
+    )
+}
+
+/// The season state with season 1 running.
+async fn first_season(h: &mut Harness) {
+    let admin = h.admin();
+    h.send(&[initialize(admin), start_season(admin, 1)], &[])
+        .await
+        .unwrap();
+}
+
+/// Funded players who joined season `season_id`.
+async fn joined(h: &mut Harness, season_id: u32, count: usize) -> Vec<Keypair> {
+    let mut players = Vec::new();
+    for _ in 0..count {
+        let player = h.funded_player().await;
+        h.send(&[join_season(player.pubkey(), season_id)], &[&player])
+            .await
+            .unwrap();
+        players.push(player);
+    }
+    players
+}
+
+async fn standing(h: &mut Harness, season_id: u32, player: &Keypair) -> SeasonStanding {
+    h.account(&pda::season::standing(season_id, &player.pubkey()))
+        .await
+}
+
+/// Verifying and staleness are `combo_mint`'s job; here only the result
+/// matters, so it is written straight into the combo.
+async fn set_combo(h: &mut Harness, address: Pubkey, verification_count: u32, stale: bool) {
+    let mut combo: ComboAccount = h.account(&address).await;
+    combo.verification_count = verification_count;
+    combo.stale = stale;
+    h.put_account(
+        &address,
+        &combo_mint::ID,
+        &combo,
+        8 + ComboAccount::INIT_SPACE,
+    );
+}
+
+#[tokio::test]
+async fn seasons_run_one_at_a_time() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    h.send(&[initialize(admin)], &[]).await.unwrap();
+
+    let stranger = h.funded_player().await;
+    assert_error(
+        h.send(&[start_season(stranger.pubkey(), 1)], &[&stranger])
+            .await,
+        ErrorCode::ConstraintHasOne,
+    );
+    h.send(&[start_season(admin, 1)], &[]).await.unwrap();
+    let state: SeasonState = h.account(&pda::season::state()).await;
+    assert_eq!(state.admin, admin);
+    assert_eq!(state.current_season, 1);
+    assert!(state.active);
+    let season: Season = h.account(&pda::season::season(1)).await;
+    assert_eq!(season.id, 1);
+    assert_eq!(season.started_at, h.now().await);
+    assert_eq!(season.ended_at, 0);
+
+    assert_error(
+        h.send(&[start_season(admin, 2)], &[]).await,
+        SeasonError::SeasonActive,
+    );
+    h.send(&[end_season(admin, 1, &[])], &[]).await.unwrap();
+    h.send(&[start_season(admin, 2)], &[]).await.unwrap();
+    let state: SeasonState = h.account(&pda::season::state()).await;
+    assert_eq!(state.current_season, 2);
+}
+
+#[tokio::test]
+async fn players_join_running_seasons() {
+    let mut h = Harness::start().await;
+    first_season(&mut h).await;
+    let players = joined(&mut h, 1, 1).await;
+    let [player] = &players[..] else {
+        unreachable!()
+    };
+
+    let joined = standing(&mut h, 1, player).await;
+    assert_eq!(joined.season, 1);
+    assert_eq!(joined.player, player.pubkey());
+    assert_eq!(joined.points, 0);
+    let season: Season = h.account(&pda::season::season(1)).await;
+    assert_eq!(season.player_count, 1);
+
+    let admin = h.admin();
+    h.send(&[end_season(admin, 1, &[])], &[]).await.unwrap();
+    let latecomer = h.funded_player().await;
+    assert_error(
+        h.send(&[join_season(latecomer.pubkey(), 1)], &[&latecomer])
+            .await,
+        SeasonError::SeasonEnded,
+    );
+}
+
+#[tokio::test]
+async fn matches_score_both_players() {
+    let mut h = Harness::start().await;
+    first_season(&mut h).await;
+    let players = joined(&mut h, 1, 2).await;
+    let [winner, loser] = &players[..] else {
+        unreachable!()
+    };
+    fixtures::recorded_match(&mut h, MATCH_ID, [loser, winner], 1).await;
+
+    let admin = h.admin();
+    let ix = record_match(admin, 1, MATCH_ID, winner.pubkey(), loser.pubkey());
+    h.send(&[ix], &[]).await.unwrap();
+    let won = standing(&mut h, 1, winner).await;
+    assert_eq!((won.points, won.wins, won.losses), (WIN_POINTS, 1, 0));
+    let lost = standing(&mut h, 1, loser).await;
+    assert_eq!((lost.points, lost.wins, lost.losses), (LOSS_POINTS, 0, 1));
+    assert!(
+        h.exists(&pda::season::entry(
+            1,
+            &pda::match_result::match_account(MATCH_ID)
+        ))
+        .await
+    );
+}
+
+#[tokio::test]
+async fn only_final_matches_played_in_the_season_count() {
+    let mut h = Harness::start().await;
+    first_season(&mut h).await;
+    let players = joined(&mut h, 1, 2).await;
+    let [p0, p1] = &players[..] else {
+        unreachable!()
+    };
+    let admin = h.admin();
+
+    fixtures::open_set(&mut h, MATCH_ID, [p0, p1]).await;
+    assert_error(
+        h.send(
+            &[record_match(admin, 1, MATCH_ID, p0.pubkey(), p1.pubkey())],
+            &[],
+        )
+        .await,
+        SeasonError::MatchNotFinalized,
+    );
+
+    // A match from before season 2 doesn't count towards it.
+    let (p0, p1) = (h.funded_player().await, h.funded_player().await);
+    fixtures::recorded_match(&mut h, MATCH_ID + 1, [&p0, &p1], 0).await;
+    h.advance(1).await;
+    h.send(&[end_season(admin, 1, &[]), start_season(admin, 2)], &[])
+        .await
+        .unwrap();
+    let ixs = [join_season(p0.pubkey(), 2), join_season(p1.pubkey(), 2)];
+    h.send(&ixs, &[&p0, &p1]).await.unwrap();
+    let ix = record_match(admin, 2, MATCH_ID + 1, p0.pubkey(), p1.pubkey());
+    assert_error(h.send(&[ix], &[]).await, SeasonError::OutsideSeason);
+}
+
+#[tokio::test]
+async fn combos_score_once_verified_enough_to_mint() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    first_season(&mut h).await;
+    let players = joined(&mut h, 1, 1).await;
+    let [creator] = &players[..] else {
+        unreachable!()
+    };
+    let admin = h.admin();
+    let combo = fixtures::combo(&mut h, creator, "Opener").await;
+
+    let score = || record_combo(admin, 1, combo, creator.pubkey());
+    assert_error(
+        h.send(&[score()], &[]).await,
+        SeasonError::NotEnoughVerifications,
+    );
+    set_combo(&mut h, combo, MIN_VERIFICATIONS_FOR_NFT, true).await;
+    assert_error(h.send(&[score()], &[]).await, SeasonError::StaleCombo);
+    set_combo(&mut h, combo, MIN_VERIFICATIONS_FOR_NFT, false).await;
+
+    h.send(&[score()], &[]).await.unwrap();
+    let scored = standing(&mut h, 1, creator).await;
+    assert_eq!(scored.combos, 1);
+    assert_eq!(scored.points, COMBO_POINTS);
+}
+
+#[tokio::test]
+async fn combos_from_before_the_season_do_not_count() {
+    let mut h = Harness::start().await;
+    fixtures::combo_mint(&mut h).await;
+    let creator = h.funded_player().await;
+    let combo = fixtures::combo(&mut h, &creator, "Opener").await;
+    set_combo(&mut h, combo, MIN_VERIFICATIONS_FOR_NFT, false).await;
+    h.advance(1).await;
+    first_season(&mut h).await;
+    h.send(&[join_season(creator.pubkey(), 1)], &[&creator])
+        .await
+        .unwrap();
+
+    let ix = record_combo(h.admin(), 1, combo, creator.pubkey());
+    assert_error(h.send(&[ix], &[]).await, SeasonError::OutsideSeason);
+}
+
+#[tokio::test]
+async fn ending_a_season_archives_its_top_standings() {
+    let mut h = Harness::start().await;
+    first_season(&mut h).await;
+    let players = joined(&mut h, 1, 3).await;
+    let [p0, p1, p2] = &players[..] else {
+        unreachable!()
+    };
+    let admin = h.admin();
+    // 1 beats 2, giving 1 the most points and 2 more than 0, who didn't
+    // play.
+    fixtures::recorded_match(&mut h, MATCH_ID, [p1, p2], 0).await;
+    let ix = record_match(admin, 1, MATCH_ID, p1.pubkey(), p2.pubkey());
+    h.send(&[ix], &[]).await.unwrap();
+    let standings: Vec<Pubkey> = players
+        .iter()
+        .map(|player| pda::season::standing(1, &player.pubkey()))
+        .collect();
+
+    let stranger = h.funded_player().await;
+    let ix = end_season(stranger.pubkey(), 1, &standings);
+    assert_error(
+        h.send(&[ix], &[&stranger]).await,
+        ErrorCode::ConstraintHasOne,
+    );
+    let twice = [standings[0], standings[1], standings[0]];
+    assert_error(
+        h.send(&[end_season(admin, 1, &twice)], &[]).await,
+        SeasonError::DuplicateStanding,
+    );
+    h.send(&[end_season(admin, 1, &standings)], &[])
+        .await
+        .unwrap();
+
+    let archive: SeasonArchive = h.account(&pda::season::archive(1)).await;
+    assert_eq!(archive.season, 1);
+    assert_eq!(archive.ended_at, h.now().await);
+    let archived = |player: &Keypair, points, wins, losses| ArchivedStanding {
+        player: player.pubkey(),
+        points,
+        wins,
+        losses,
+    };
+    assert_eq!(
+        archive.top,
+        [
+            archived(p1, WIN_POINTS, 1, 0),
+            archived(p2, LOSS_POINTS, 0, 1),
+            archived(p0, 0, 0, 0),
+        ]
+    );
+    let state: SeasonState = h.account(&pda::season::state()).await;
+    assert!(!state.active);
+    let season: Season = h.account(&pda::season::season(1)).await;
+    assert_eq!(season.ended_at, archive.ended_at);
+}
+
+#[tokio::test]
+async fn archives_only_take_standings_from_their_season() {
+    let mut h = Harness::start().await;
+    first_season(&mut h).await;
+    let players = joined(&mut h, 1, 1).await;
+    let [player] = &players[..] else {
+        unreachable!()
+    };
+    let admin = h.admin();
+    h.send(&[end_season(admin, 1, &[]), start_season(admin, 2)], &[])
+        .await
+        .unwrap();
+
+    let old = pda::season::standing(1, &player.pubkey());
+    assert_error(
+        h.send(&[end_season(admin, 2, &[old])], &[]).await,
+        SeasonError::WrongSeason,
+    );
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    first_season(&mut h).await;
+    let players = joined(&mut h, 1, 2).await;
+    let [winner, loser] = &players[..] else {
+        unreachable!()
+    };
+    let match_account = fixtures::recorded_match(&mut h, MATCH_ID, [winner, loser], 0).await;
+    let admin = h.admin();
+    let ixs = [
+        record_match(admin, 1, MATCH_ID, winner.pubkey(), loser.pubkey()),
+        end_season(admin, 1, &[]),
+    ];
+    h.send(&ixs, &[]).await.unwrap();
+
+    for account in [
+        pda::season::state(),
+        pda::season::season(1),
+        pda::season::standing(1, &winner.pubkey()),
+        pda::season::entry(1, &match_account),
+        pda::season::archive(1),
+    ] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            SeasonError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &season::ID, vec![9; SeasonStanding::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, SeasonError::UnsupportedLayout);
+}
@@ -0,0 +1,713 @@
+use anchor_lang::Space;
+use anchor_spl::token::spl_token;
+use anchor_spl::token_2022::spl_token_2022;
+use combo_mint_client::combo_mint::ComboAccount;
+use combo_mint_client::staking::{
+    stake_weight, EmissionCurve, EmissionSegment, StakeKind, StakePosition, StakingError,
+    StakingPool, MAX_SEGMENTS, REWARD_SCALE,
+};
+use combo_mint_client::{combo_mint, instruction, pda, staking};
+use program_tests::fixtures;
+use program_tests::{assert_error, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `StakingError::Overflow` needs more than `u64::MAX` of weight or rewards,
+// so no test reaches it.
+
+const COOLDOWN: i64 = 7 * 24 * 60 * 60;
+/// Reward tokens the pool emits a second while the tests earn.
+const RATE: u64 = 10;
+/// Seconds the tests let positions earn for.
+const EARNING: i64 = 100;
+/// What the vault starts with, enough for every test's rewards.
+const FUNDING: u64 = 1_000_000;
+
+/// The staking pool over a fresh reward mint with `FUNDING` in its vault,
+/// on top of `fixtures::combo_mint` with the admin as moderator.
+struct Pool {
+    mint: Pubkey,
+}
+
+impl Pool {
+    async fn new(h: &mut Harness) -> Self {
+        Self::funded(h, FUNDING).await
+    }
+
+    async fn funded(h: &mut Harness, funding: u64) -> Self {
+        fixtures::combo_mint(h).await;
+        let admin = h.admin();
+        let mint = h.put_mint(&admin);
+        let funds = h.put_token_account(&mint, &admin, funding).await;
+        let ixs = [
+            initialize(admin, mint, spl_token::ID, COOLDOWN),
+            spl_token::instruction::transfer(
+                &spl_token::ID,
+                &funds,
+                &pda::staking::vault(),
+                &admin,
+                &[],
+                funding,
+            )
+            .unwrap(),
+            instruction(
+                combo_mint::ID,
+                combo_mint::accounts::SetModerator {
+                    admin,
+                    config: pda::combo_mint::config(),
+                },
+                combo_mint::instruction::SetModerator { moderator: admin },
+            ),
+        ];
+        h.send(&ixs, &[]).await.unwrap();
+        Pool { mint }
+    }
+
+    /// A funded player with a combo called `name` and a reward account.
+    async fn staker(&self, h: &mut Harness, name: &str) -> Staker {
+        let owner = h.funded_player().await;
+        let combo = fixtures::combo(h, &owner, name).await;
+        let rewards = h.put_token_account(&self.mint, &owner.pubkey(), 0).await;
+        Staker {
+            owner,
+            combo,
+            rewards,
+        }
+    }
+
+    fn claim(&self, staker: &Staker) -> Instruction {
+        instruction(
+            staking::ID,
+            staking::accounts::ClaimRewards {
+                owner: staker.owner.pubkey(),
+                pool: pda::staking::pool(),
+                curve: pda::staking::emission_curve(),
+                position: pda::staking::position(&staker.combo),
+                combo_pda: staker.combo,
+                vault: pda::staking::vault(),
+                reward_mint: self.mint,
+                owner_reward_account: staker.rewards,
+                token_program: spl_token::ID,
+            },
+            staking::instruction::ClaimRewards {},
+        )
+    }
+}
+
+struct Staker {
+    owner: Keypair,
+    combo: Pubkey,
+    rewards: Pubkey,
+}
+
+impl Staker {
+    fn stake(&self) -> Instruction {
+        instruction(
+            staking::ID,
+            staking::accounts::StakeCombo {
+                owner: self.owner.pubkey(),
+                pool: pda::staking::pool(),
+                curve: pda::staking::emission_curve(),
+                combo_pda: self.combo,
+                position: pda::staking::position(&self.combo),
+                combo_mint_program: combo_mint::ID,
+                system_program: system_program::ID,
+            },
+            staking::instruction::StakeCombo {},
+        )
+    }
+
+    fn position(&self) -> Pubkey {
+        pda::staking::position(&self.combo)
+    }
+
+    fn request_unstake(&self) -> Instruction {
+        instruction(
+            staking::ID,
+            staking::accounts::RequestUnstake {
+                owner: self.owner.pubkey(),
+                pool: pda::staking::pool(),
+                curve: pda::staking::emission_curve(),
+                position: self.position(),
+            },
+            staking::instruction::RequestUnstake {},
+        )
+    }
+
+    fn unstake(&self) -> Instruction {
+        instruction(
+            staking::ID,
+            staking::accounts::UnstakeCombo {
+                owner: self.owner.pubkey(),
+                position: self.position(),
+                pool: pda::staking::pool(),
+                vault: pda::staking::vault(),
+                combo_pda: self.combo,
+                combo_mint_program: combo_mint::ID,
+            },
+            staking::instruction::UnstakeCombo {},
+        )
+    }
+
+    fn unstake_nft(&self, owner_nft_account: Pubkey) -> Instruction {
+        let position = self.position();
+        instruction(
+            staking::ID,
+            staking::accounts::UnstakeComboNft {
+                owner: self.owner.pubkey(),
+                position,
+                pool: pda::staking::pool(),
+                vault: pda::staking::vault(),
+                nft_vault: pda::staking::nft_vault(&position),
+                owner_nft_account,
+                token_program: spl_token::ID,
+            },
+            staking::instruction::UnstakeComboNft {},
+        )
+    }
+
+    fn stake_nft(&self, nft_mint: Pubkey, owner_nft_account: Pubkey) -> Instruction {
+        let position = self.position();
+        instruction(
+            staking::ID,
+            staking::accounts::StakeComboNft {
+                owner: self.owner.pubkey(),
+                pool: pda::staking::pool(),
+                curve: pda::staking::emission_curve(),
+                combo_pda: self.combo,
+                nft_mint,
+                owner_nft_account,
+                position,
+                nft_vault: pda::staking::nft_vault(&position),
+                token_program: spl_token::ID,
+                system_program: system_program::ID,
+            },
+            staking::instruction::StakeComboNft {},
+        )
+    }
+
+    fn refresh(&self) -> Instruction {
+        instruction(
+            staking::ID,
+            staking::accounts::RefreshWeight {
+                pool: pda::staking::pool(),
+                curve: pda::staking::emission_curve(),
+                position: self.position(),
+                combo_pda: self.combo,
+            },
+            staking::instruction::RefreshWeight {},
+        )
+    }
+
+    fn slash(&self) -> Instruction {
+        instruction(
+            staking::ID,
+            staking::accounts::Slash {
+                pool: pda::staking::pool(),
+                curve: pda::staking::emission_curve(),
+                position: self.position(),
+                combo_pda: self.combo,
+            },
+            staking::instruction::Slash {},
+        )
+    }
+}
+
+fn initialize(
+    admin: Pubkey,
+    reward_mint: Pubkey,
+    token_program: Pubkey,
+    unstake_cooldown: i64,
+) -> Instruction {
+    instruction(
+        staking::ID,
+        staking::accounts::Initialize {
+            admin,
+            pool: pda::staking::pool(),
+            curve: pda::staking::emission_curve(),
+            reward_mint,
+            vault: pda::staking::vault(),
+            token_program,
+            system_program: system_program::ID,
+        },
+        staking::instruction::Initialize { unstake_cooldown },
+    )
+}
+
+fn set_emission_curve(admin: Pubkey, segments: Vec<EmissionSegment>) -> Instruction {
+    instruction(
+        staking::ID,
+        staking::accounts::SetEmissionCurve {
+            admin,
+            pool: pda::staking::pool(),
+            curve: pda::staking::emission_curve(),
+        },
+        staking::instruction::SetEmissionCurve { segments },
+    )
+}
+
+fn freeze_combo(moderator: Pubkey, combo: Pubkey, frozen: bool) -> Instruction {
+    instruction(
+        combo_mint::ID,
+        combo_mint::accounts::FreezeCombo {
+            moderator,
+            config: pda::combo_mint::config(),
+            combo_pda: combo,
+        },
+        combo_mint::instruction::FreezeCombo { frozen },
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        staking::ID,
+        staking::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        staking::instruction::MigrateAccount {},
+    )
+}
+
+/// Starts emitting `RATE` a second from now.
+async fn emit(h: &mut Harness) {
+    let now = h.now().await;
+    let segments = vec![EmissionSegment {
+        start: now,
+        rate: RATE,
+    }];
+    h.send(&[set_emission_curve(h.admin(), segments)], &[])
+        .await
+        .unwrap();
+}
+
+async fn freeze(h: &mut Harness, combo: Pubkey, frozen: bool) {
+    let ix = freeze_combo(h.admin(), combo, frozen);
+    h.send(&[ix], &[]).await.unwrap();
+}
+
+async fn combo(h: &mut Harness, address: &Pubkey) -> ComboAccount {
+    h.account(address).await
+}
+
+/// Verifying is `combo_mint`'s job; here only the count matters, so it is
+/// written straight into the combo.
+async fn set_verifications(h: &mut Harness, address: Pubkey, verification_count: u32) {
+    let mut current = combo(h, &address).await;
+    current.verification_count = verification_count;
+    h.put_account(
+        &address,
+        &combo_mint::ID,
+        &current,
+        8 + ComboAccount::INIT_SPACE,
+    );
+}
+
+async fn pool(h: &mut Harness) -> StakingPool {
+    h.account(&pda::staking::pool()).await
+}
+
+async fn position(h: &mut Harness, staker: &Staker) -> StakePosition {
+    h.account(&staker.position()).await
+}
+
+/// What a position of `weight` earns of `emitted` shared out over
+/// `total_weight`, rounded as the pool rounds it.
+fn earned(emitted: u64, weight: u64, total_weight: u64) -> u64 {
+    let per_weight = emitted as u128 * REWARD_SCALE / total_weight as u128;
+    (weight as u128 * per_weight / REWARD_SCALE) as u64
+}
+
+#[tokio::test]
+async fn initializes_the_pool() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let mint = h.put_mint(&admin);
+
+    let ix = initialize(admin, mint, spl_token::ID, -1);
+    assert_error(h.send(&[ix], &[]).await, StakingError::InvalidCooldown);
+    h.send(&[initialize(admin, mint, spl_token::ID, COOLDOWN)], &[])
+        .await
+        .unwrap();
+    let pool = pool(&mut h).await;
+    assert_eq!(pool.reward_mint, mint);
+    assert_eq!(pool.vault, pda::staking::vault());
+    assert_eq!(pool.unstake_cooldown, COOLDOWN);
+    assert_eq!(pool.total_weight, 0);
+    let curve: EmissionCurve = h.account(&pda::staking::emission_curve()).await;
+    assert!(curve.segments.is_empty());
+}
+
+#[tokio::test]
+async fn rewards_are_paid_in_a_supported_mint() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    let mint = h.put_unsupported_mint(&admin);
+
+    let ix = initialize(admin, mint, spl_token_2022::ID, COOLDOWN);
+    assert_error(h.send(&[ix], &[]).await, StakingError::UnsupportedMint);
+}
+
+#[tokio::test]
+async fn emission_curves_are_sorted_and_bounded() {
+    let mut h = Harness::start().await;
+    Pool::new(&mut h).await;
+    let admin = h.admin();
+    let segment = |start| EmissionSegment { start, rate: RATE };
+
+    let too_long = (0..=MAX_SEGMENTS as i64).map(segment).collect();
+    assert_error(
+        h.send(&[set_emission_curve(admin, too_long)], &[]).await,
+        StakingError::InvalidCurve,
+    );
+    for unsorted in [vec![segment(2), segment(1)], vec![segment(1), segment(1)]] {
+        assert_error(
+            h.send(&[set_emission_curve(admin, unsorted)], &[]).await,
+            StakingError::InvalidCurve,
+        );
+    }
+
+    let segments: Vec<_> = (0..MAX_SEGMENTS as i64).map(segment).collect();
+    h.send(&[set_emission_curve(admin, segments.clone())], &[])
+        .await
+        .unwrap();
+    let curve: EmissionCurve = h.account(&pda::staking::emission_curve()).await;
+    assert_eq!(curve.segments, segments);
+}
+
+#[tokio::test]
+async fn staking_hands_the_combo_to_the_position() {
+    let mut h = Harness::start().await;
+    let pool = Pool::new(&mut h).await;
+    let staker = pool.staker(&mut h, "Bread and butter").await;
+
+    let stranger = h.funded_player().await;
+    let mut ix = staker.stake();
+    ix.accounts[0].pubkey = stranger.pubkey();
+    assert_error(
+        h.send(&[ix], &[&stranger]).await,
+        StakingError::NotComboOwner,
+    );
+
+    let weight = stake_weight(&combo(&mut h, &staker.combo).await);
+    h.send(&[staker.stake()], &[&staker.owner]).await.unwrap();
+    assert_eq!(
+        combo(&mut h, &staker.combo).await.authority,
+        staker.position()
+    );
+    let position = position(&mut h, &staker).await;
+    assert_eq!(position.owner, staker.owner.pubkey());
+    assert_eq!(position.combo, staker.combo);
+    assert_eq!(position.kind, StakeKind::Authority);
+    assert_eq!(position.weight, weight);
+    assert_eq!(position.unlock_at, 0);
+    assert_eq!(self::pool(&mut h).await.total_weight, weight);
+}
+
+#[tokio::test]
+async fn frozen_combos_are_not_staked() {
+    let mut h = Harness::start().await;
+    let pool = Pool::new(&mut h).await;
+    let staker = pool.staker(&mut h, "Bread and butter").await;
+    freeze(&mut h, staker.combo, true).await;
+
+    assert_error(
+        h.send(&[staker.stake()], &[&staker.owner]).await,
+        StakingError::ComboFrozen,
+    );
+}
+
+#[tokio::test]
+async fn stakers_earn_by_weight() {
+    let mut h = Harness::start().await;
+    let pool = Pool::new(&mut h).await;
+    let one = pool.staker(&mut h, "Bread and butter").await;
+    let two = pool.staker(&mut h, "Corner carry").await;
+    set_verifications(&mut h, two.combo, 1).await;
+    for staker in [&one, &two] {
+        h.send(&[staker.stake()], &[&staker.owner]).await.unwrap();
+    }
+    let weights = [
+        position(&mut h, &one).await.weight,
+        position(&mut h, &two).await.weight,
+    ];
+    assert_eq!(weights[1], 2 * weights[0]);
+
+    emit(&mut h).await;
+    h.advance(EARNING).await;
+    let emitted = RATE * EARNING as u64;
+    for (staker, weight) in [&one, &two].into_iter().zip(weights) {
+        h.send(&[pool.claim(staker)], &[&staker.owner])
+            .await
+            .unwrap();
+        let reward = earned(emitted, weight, weights[0] + weights[1]);
+        assert_eq!(h.token_balance(&staker.rewards).await, reward);
+        assert_eq!(position(&mut h, staker).await.pending, 0);
+    }
+
+    // Nothing more is owed until time passes.
+    h.send(&[pool.claim(&one)], &[&one.owner]).await.unwrap();
+    let reward = earned(emitted, weights[0], weights[0] + weights[1]);
+    assert_eq!(h.token_balance(&one.rewards).await, reward);
+}
+
+#[tokio::test]
+async fn claims_are_capped_by_the_vault() {
+    let mut h = Harness::start().await;
+    let pool = Pool::funded(&mut h, 10).await;
+    let staker = pool.staker(&mut h, "Bread and butter").await;
+    h.send(&[staker.stake()], &[&staker.owner]).await.unwrap();
+    emit(&mut h).await;
+    h.advance(EARNING).await;
+
+    h.send(&[pool.claim(&staker)], &[&staker.owner])
+        .await
+        .unwrap();
+    let weight = position(&mut h, &staker).await.weight;
+    let owed = earned(RATE * EARNING as u64, weight, weight);
+    assert_eq!(h.token_balance(&staker.rewards).await, 10);
+    assert_eq!(position(&mut h, &staker).await.pending, owed - 10);
+}
+
+#[tokio::test]
+async fn a_dry_vault_lets_unstaking_forfeit_what_it_owes() {
+    let mut h = Harness::start().await;
+    let pool = Pool::funded(&mut h, 10).await;
+    let staker = pool.staker(&mut h, "Bread and butter").await;
+    h.send(&[staker.stake()], &[&staker.owner]).await.unwrap();
+    emit(&mut h).await;
+    h.advance(EARNING).await;
+    h.send(&[staker.request_unstake()], &[&staker.owner])
+        .await
+        .unwrap();
+    h.advance(COOLDOWN).await;
+
+    assert_error(
+        h.send(&[staker.unstake()], &[&staker.owner]).await,
+        StakingError::UnclaimedRewards,
+    );
+    h.send(&[pool.claim(&staker)], &[&staker.owner])
+        .await
+        .unwrap();
+    assert!(position(&mut h, &staker).await.pending > 0);
+    h.send(&[staker.unstake()], &[&staker.owner]).await.unwrap();
+    assert_eq!(h.token_balance(&staker.rewards).await, 10);
+    assert!(!h.exists(&staker.position()).await);
+}
+
+#[tokio::test]
+async fn refreshing_picks_up_new_verifications() {
+    let mut h = Harness::start().await;
+    let pool = Pool::new(&mut h).await;
+    let staker = pool.staker(&mut h, "Bread and butter").await;
+    h.send(&[staker.stake()], &[&staker.owner]).await.unwrap();
+    let weight = position(&mut h, &staker).await.weight;
+    emit(&mut h).await;
+    h.advance(EARNING).await;
+
+    set_verifications(&mut h, staker.combo, 2).await;
+    h.send(&[staker.refresh()], &[]).await.unwrap();
+    let position = position(&mut h, &staker).await;
+    assert_eq!(position.weight, 3 * weight);
+    assert_eq!(self::pool(&mut h).await.total_weight, 3 * weight);
+    // What was earned on the old weight is kept.
+    assert_eq!(
+        position.pending,
+        earned(RATE * EARNING as u64, weight, weight)
+    );
+
+    freeze(&mut h, staker.combo, true).await;
+    assert_error(
+        h.send(&[staker.refresh()], &[]).await,
+        StakingError::ComboFrozen,
+    );
+}
+
+#[tokio::test]
+async fn unstaking_waits_out_the_cooldown_and_a_claim() {
+    let mut h = Harness::start().await;
+    let pool = Pool::new(&mut h).await;
+    let staker = pool.staker(&mut h, "Bread and butter").await;
+    h.send(&[staker.stake()], &[&staker.owner]).await.unwrap();
+    emit(&mut h).await;
+    h.advance(EARNING).await;
+
+    assert_error(
+        h.send(&[staker.unstake()], &[&staker.owner]).await,
+        StakingError::NotUnstaking,
+    );
+    h.send(&[staker.request_unstake()], &[&staker.owner])
+        .await
+        .unwrap();
+    let now = h.now().await;
+    let position = position(&mut h, &staker).await;
+    assert_eq!(position.weight, 0);
+    assert_eq!(position.unlock_at, now + COOLDOWN);
+    assert_eq!(self::pool(&mut h).await.total_weight, 0);
+    assert_error(
+        h.send(&[staker.request_unstake()], &[&staker.owner]).await,
+        StakingError::Unstaking,
+    );
+    assert_error(
+        h.send(&[staker.refresh()], &[]).await,
+        StakingError::Unstaking,
+    );
+    assert_error(
+        h.send(&[staker.unstake()], &[&staker.owner]).await,
+        StakingError::CooldownActive,
+    );
+
+    h.advance(COOLDOWN).await;
+    assert_error(
+        h.send(&[staker.unstake()], &[&staker.owner]).await,
+        StakingError::UnclaimedRewards,
+    );
+    // Nothing accrues while unstaking.
+    h.send(&[pool.claim(&staker)], &[&staker.owner])
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(&staker.rewards).await, position.pending);
+
+    h.send(&[staker.unstake()], &[&staker.owner]).await.unwrap();
+    assert_eq!(
+        combo(&mut h, &staker.combo).await.authority,
+        staker.owner.pubkey()
+    );
+    assert!(!h.exists(&staker.position()).await);
+}
+
+#[tokio::test]
+async fn slashing_shares_a_frozen_positions_rewards_out() {
+    let mut h = Harness::start().await;
+    let pool = Pool::new(&mut h).await;
+    let cheat = pool.staker(&mut h, "Bread and butter").await;
+    let honest = pool.staker(&mut h, "Corner carry").await;
+    for staker in [&cheat, &honest] {
+        h.send(&[staker.stake()], &[&staker.owner]).await.unwrap();
+    }
+    let weight = position(&mut h, &cheat).await.weight;
+    emit(&mut h).await;
+    h.advance(EARNING).await;
+
+    assert_error(h.send(&[cheat.slash()], &[]).await, StakingError::NotFrozen);
+    freeze(&mut h, cheat.combo, true).await;
+    // Claiming first can't get ahead of the slash.
+    assert_error(
+        h.send(&[pool.claim(&cheat)], &[&cheat.owner]).await,
+        StakingError::ComboFrozen,
+    );
+    h.send(&[cheat.slash()], &[]).await.unwrap();
+    let slashed = position(&mut h, &cheat).await;
+    assert!(slashed.slashed);
+    assert_eq!(slashed.weight, 0);
+    assert_eq!(slashed.pending, 0);
+    assert_eq!(self::pool(&mut h).await.total_weight, weight);
+    assert_error(h.send(&[cheat.slash()], &[]).await, StakingError::Slashed);
+
+    h.send(&[pool.claim(&honest)], &[&honest.owner])
+        .await
+        .unwrap();
+    let emitted = RATE * EARNING as u64;
+    let forfeited = earned(emitted, weight, 2 * weight);
+    let per_weight = emitted as u128 * REWARD_SCALE / (2 * weight) as u128
+        + forfeited as u128 * REWARD_SCALE / weight as u128;
+    let reward = (weight as u128 * per_weight / REWARD_SCALE) as u64;
+    assert_eq!(h.token_balance(&honest.rewards).await, reward);
+    // All of it, but for rounding.
+    assert!(emitted - reward <= 2);
+
+    // Once unfrozen, the owner gets the combo back but nothing more.
+    freeze(&mut h, cheat.combo, false).await;
+    assert_error(h.send(&[cheat.refresh()], &[]).await, StakingError::Slashed);
+    h.send(&[cheat.request_unstake()], &[&cheat.owner])
+        .await
+        .unwrap();
+    h.advance(COOLDOWN).await;
+    h.send(&[cheat.unstake()], &[&cheat.owner]).await.unwrap();
+    assert_eq!(
+        combo(&mut h, &cheat.combo).await.authority,
+        cheat.owner.pubkey()
+    );
+}
+
+#[tokio::test]
+async fn nft_positions_hold_the_nft() {
+    let mut h = Harness::start().await;
+    let pool = Pool::new(&mut h).await;
+    let staker = pool.staker(&mut h, "Bread and butter").await;
+    let admin = h.admin();
+    let nft_mint = h.put_mint(&admin);
+    let nft_account = h
+        .put_token_account(&nft_mint, &staker.owner.pubkey(), 1)
+        .await;
+
+    let ix = staker.stake_nft(nft_mint, nft_account);
+    assert_error(h.send(&[ix], &[&staker.owner]).await, StakingError::NoNft);
+
+    // Minting goes through Token Metadata, which the bank does not load;
+    // here only the link matters, so it is written straight into the combo.
+    let mut current = combo(&mut h, &staker.combo).await;
+    current.nft_mint = nft_mint;
+    h.put_account(
+        &staker.combo,
+        &combo_mint::ID,
+        &current,
+        8 + ComboAccount::INIT_SPACE,
+    );
+    let ix = staker.stake_nft(nft_mint, nft_account);
+    h.send(&[ix], &[&staker.owner]).await.unwrap();
+    let nft_vault = pda::staking::nft_vault(&staker.position());
+    assert_eq!(h.token_balance(&nft_vault).await, 1);
+    assert_eq!(h.token_balance(&nft_account).await, 0);
+    let position = position(&mut h, &staker).await;
+    assert_eq!(position.kind, StakeKind::Nft);
+    assert_eq!(position.weight, stake_weight(&current));
+
+    h.send(&[staker.request_unstake()], &[&staker.owner])
+        .await
+        .unwrap();
+    h.advance(COOLDOWN).await;
+    assert_error(
+        h.send(&[staker.unstake()], &[&staker.owner]).await,
+        StakingError::WrongStakeKind,
+    );
+    h.send(&[staker.unstake_nft(nft_account)], &[&staker.owner])
+        .await
+        .unwrap();
+    assert_eq!(h.token_balance(&nft_account).await, 1);
+    assert!(!h.exists(&nft_vault).await);
+    assert!(!h.exists(&staker.position()).await);
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let pool = Pool::new(&mut h).await;
+    let staker = pool.staker(&mut h, "Bread and butter").await;
+    h.send(&[staker.stake()], &[&staker.owner]).await.unwrap();
+    let admin = h.admin();
+
+    for account in [
+        pda::staking::pool(),
+        pda::staking::emission_curve(),
+        staker.position(),
+    ] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            StakingError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &staking::ID, vec![9; StakePosition::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, StakingError::UnsupportedLayout);
+}
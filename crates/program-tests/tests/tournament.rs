@@ -0,0 +1,710 @@
+use anchor_lang::error::ErrorCode;
+use combo_mint_client::achievements::{Badge, FIRST_TOURNAMENT_WIN};
+use combo_mint_client::builders::{self, NewTournament};
+use combo_mint_client::referral::{Referral, ReferralCode};
+use combo_mint_client::tournament::{
+    BracketFormat, Tournament, TournamentConfig, TournamentError, TournamentStatus,
+};
+use combo_mint_client::{achievements, instruction, pda, profile, tournament};
+use program_tests::{assert_error, fixtures, Harness, PLAYER_FUNDS};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_program;
+
+// `TournamentError::Overflow` needs a Swiss score above `u8::MAX` or a pool
+// above `u64::MAX` lamports, neither of which sixteen entrants can reach.
+// `TournamentError::NotComplete` can't be reached either: until the
+// tournament completes its champion is the default key, which is not a
+// system account, so `award_champion_badge` fails on that account first.
+
+const TOURNAMENT_ID: u64 = 1;
+const ENTRY_FEE: u64 = LAMPORTS_PER_SOL;
+/// Cap the config puts on organizer fees.
+const MAX_ORGANIZER_FEE_BPS: u16 = 1_000;
+const ORGANIZER_FEE_BPS: u16 = 500;
+
+/// A tournament created by a funded organizer, and funded players to fill
+/// it, none of them registered yet.
+struct Cup {
+    organizer: Keypair,
+    players: Vec<Keypair>,
+    address: Pubkey,
+}
+
+impl Cup {
+    /// Also creates the config, so only one cup per test.
+    async fn new(
+        h: &mut Harness,
+        format: BracketFormat,
+        max_entrants: u8,
+        prize_split: Vec<u16>,
+    ) -> Self {
+        let admin = h.admin();
+        h.send(&[initialize_config(admin, MAX_ORGANIZER_FEE_BPS)], &[])
+            .await
+            .unwrap();
+        let organizer = h.funded_player().await;
+        let ix = builders::create_tournament(
+            organizer.pubkey(),
+            NewTournament {
+                format,
+                max_entrants,
+                prize_split,
+                ..settings()
+            },
+        );
+        h.send(&[ix], &[&organizer]).await.unwrap();
+        let mut players = Vec::new();
+        for _ in 0..max_entrants {
+            players.push(h.funded_player().await);
+        }
+        Cup {
+            address: pda::tournament::tournament(&organizer.pubkey(), TOURNAMENT_ID),
+            organizer,
+            players,
+        }
+    }
+
+    fn vault(&self) -> Pubkey {
+        pda::tournament::vault(&self.address)
+    }
+
+    async fn register(&self, h: &mut Harness, player: &Keypair) {
+        let ix = builders::register(player.pubkey(), self.address, None);
+        h.send(&[ix], &[player]).await.unwrap();
+    }
+
+    /// Registers every player, which starts the tournament.
+    async fn fill(&self, h: &mut Harness) {
+        for player in &self.players {
+            self.register(h, player).await;
+        }
+    }
+
+    async fn tournament(&self, h: &mut Harness) -> Tournament {
+        h.account(&self.address).await
+    }
+
+    fn report_result(&self, match_index: u8, winner: u8, players: [Pubkey; 2]) -> Instruction {
+        instruction(
+            tournament::ID,
+            tournament::accounts::ReportResult {
+                organizer: self.organizer.pubkey(),
+                tournament: self.address,
+                vault: self.vault(),
+                player_one: players[0],
+                player_two: players[1],
+                system_program: system_program::ID,
+            },
+            tournament::instruction::ReportResult {
+                match_index,
+                winner,
+            },
+        )
+    }
+
+    /// `report_result` for the players the bracket has in the match.
+    async fn report(&self, h: &mut Harness, match_index: u8, winner: u8) -> Instruction {
+        let tournament = self.tournament(h).await;
+        let seeds = tournament.matches[match_index as usize].players;
+        let players = seeds.map(|seed| tournament.entrants[seed as usize]);
+        self.report_result(match_index, winner, players)
+    }
+
+    /// Reports a match that isn't the last.
+    async fn play(&self, h: &mut Harness, match_index: u8, winner: u8) {
+        let ix = self.report(h, match_index, winner).await;
+        h.send(&[ix], &[&self.organizer]).await.unwrap();
+    }
+
+    fn pair_swiss_round(&self) -> Instruction {
+        instruction(
+            tournament::ID,
+            tournament::accounts::CrankSwiss {
+                tournament: self.address,
+            },
+            tournament::instruction::PairSwissRound {},
+        )
+    }
+
+    fn finish_swiss(&self) -> Instruction {
+        instruction(
+            tournament::ID,
+            tournament::accounts::FinishSwiss {
+                tournament: self.address,
+                vault: self.vault(),
+                organizer: self.organizer.pubkey(),
+                system_program: system_program::ID,
+            },
+            tournament::instruction::FinishSwiss {},
+        )
+    }
+
+    async fn balances(&self, h: &mut Harness) -> Vec<u64> {
+        let mut balances = Vec::new();
+        for player in &self.players {
+            balances.push(h.lamports(&player.pubkey()).await);
+        }
+        balances
+    }
+}
+
+/// `ENTRY_FEE`, `ORGANIZER_FEE_BPS` and a winner-take-all single
+/// elimination bracket for two.
+fn settings() -> NewTournament {
+    NewTournament {
+        tournament_id: TOURNAMENT_ID,
+        format: BracketFormat::SingleElimination,
+        entry_fee: ENTRY_FEE,
+        max_entrants: 2,
+        prize_split: vec![10_000],
+        organizer_fee_bps: ORGANIZER_FEE_BPS,
+    }
+}
+
+fn initialize_config(admin: Pubkey, max_organizer_fee_bps: u16) -> Instruction {
+    instruction(
+        tournament::ID,
+        tournament::accounts::InitializeConfig {
+            admin,
+            config: pda::tournament::config(),
+            system_program: system_program::ID,
+        },
+        tournament::instruction::InitializeConfig {
+            max_organizer_fee_bps,
+        },
+    )
+}
+
+fn set_max_organizer_fee(admin: Pubkey, max_organizer_fee_bps: u16) -> Instruction {
+    instruction(
+        tournament::ID,
+        tournament::accounts::UpdateConfig {
+            admin,
+            config: pda::tournament::config(),
+        },
+        tournament::instruction::SetMaxOrganizerFee {
+            max_organizer_fee_bps,
+        },
+    )
+}
+
+/// `ix` paying out to `payees`, as the last report or `finish_swiss` needs.
+fn paying(mut ix: Instruction, payees: &[&Keypair]) -> Instruction {
+    ix.accounts.extend(
+        payees
+            .iter()
+            .map(|payee| AccountMeta::new(payee.pubkey(), false)),
+    );
+    ix
+}
+
+fn award_champion_badge(payer: Pubkey, tournament: Pubkey, champion: Pubkey) -> Instruction {
+    instruction(
+        tournament::ID,
+        tournament::accounts::AwardChampionBadge {
+            tournament,
+            champion,
+            issuer: pda::achievements::issuer(&tournament::ID),
+            achievement: pda::achievements::achievement(FIRST_TOURNAMENT_WIN),
+            badge: pda::achievements::badge(FIRST_TOURNAMENT_WIN, &champion),
+            profile: pda::profile::profile(&champion),
+            profile_config: pda::profile::config(),
+            profile_writer: pda::profile::writer(&achievements::ID),
+            profile_program: profile::ID,
+            payer,
+            achievements_program: achievements::ID,
+            system_program: system_program::ID,
+        },
+        tournament::instruction::AwardChampionBadge {},
+    )
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        tournament::ID,
+        tournament::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        tournament::instruction::MigrateAccount {},
+    )
+}
+
+#[tokio::test]
+async fn the_admin_caps_organizer_fees() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+
+    assert_error(
+        h.send(&[initialize_config(admin, 10_001)], &[]).await,
+        TournamentError::InvalidFee,
+    );
+    h.send(&[initialize_config(admin, MAX_ORGANIZER_FEE_BPS)], &[])
+        .await
+        .unwrap();
+    assert_error(
+        h.send(&[set_max_organizer_fee(admin, 10_001)], &[]).await,
+        TournamentError::InvalidFee,
+    );
+    let stranger = h.funded_player().await;
+    assert_error(
+        h.send(&[set_max_organizer_fee(stranger.pubkey(), 0)], &[&stranger])
+            .await,
+        ErrorCode::ConstraintHasOne,
+    );
+
+    h.send(&[set_max_organizer_fee(admin, 2_000)], &[])
+        .await
+        .unwrap();
+    let config: TournamentConfig = h.account(&pda::tournament::config()).await;
+    assert_eq!(config.admin, admin);
+    assert_eq!(config.max_organizer_fee_bps, 2_000);
+}
+
+#[tokio::test]
+async fn tournaments_are_created_open_for_registration() {
+    let mut h = Harness::start().await;
+    let cup = Cup::new(
+        &mut h,
+        BracketFormat::DoubleElimination,
+        8,
+        vec![7_000, 2_000, 1_000],
+    )
+    .await;
+
+    let tournament = cup.tournament(&mut h).await;
+    assert_eq!(tournament.organizer, cup.organizer.pubkey());
+    assert_eq!(tournament.tournament_id, TOURNAMENT_ID);
+    assert_eq!(tournament.format, BracketFormat::DoubleElimination);
+    assert_eq!(tournament.status, TournamentStatus::Registration);
+    assert_eq!(tournament.entry_fee, ENTRY_FEE);
+    assert_eq!(tournament.max_entrants, 8);
+    assert_eq!(tournament.prize_split, [7_000, 2_000, 1_000]);
+    assert_eq!(tournament.runner_up_bps, 2_000);
+    assert_eq!(tournament.organizer_fee_bps, ORGANIZER_FEE_BPS);
+    assert!(tournament.entrants.is_empty());
+    assert!(tournament.matches.is_empty());
+}
+
+#[tokio::test]
+async fn tournament_settings_are_checked() {
+    let mut h = Harness::start().await;
+    let admin = h.admin();
+    h.send(&[initialize_config(admin, MAX_ORGANIZER_FEE_BPS)], &[])
+        .await
+        .unwrap();
+
+    for max_entrants in [1, 3, 32] {
+        let ix = builders::create_tournament(
+            admin,
+            NewTournament {
+                max_entrants,
+                ..settings()
+            },
+        );
+        assert_error(
+            h.send(&[ix], &[]).await,
+            TournamentError::InvalidEntrantCount,
+        );
+    }
+    let ix = builders::create_tournament(
+        admin,
+        NewTournament {
+            entry_fee: 1_000,
+            ..settings()
+        },
+    );
+    assert_error(h.send(&[ix], &[]).await, TournamentError::EntryFeeTooLow);
+    // Shares that don't sum to 100%, and a third place two entrants can't
+    // decide.
+    for prize_split in [vec![9_000], vec![6_000, 3_000, 1_000]] {
+        let ix = builders::create_tournament(
+            admin,
+            NewTournament {
+                prize_split,
+                ..settings()
+            },
+        );
+        assert_error(h.send(&[ix], &[]).await, TournamentError::InvalidPrizeSplit);
+    }
+    let ix = builders::create_tournament(
+        admin,
+        NewTournament {
+            organizer_fee_bps: MAX_ORGANIZER_FEE_BPS + 1,
+            ..settings()
+        },
+    );
+    assert_error(
+        h.send(&[ix], &[]).await,
+        TournamentError::OrganizerFeeTooHigh,
+    );
+
+    // Swiss fields needn't be a power of two, and free entry is allowed.
+    let ix = builders::create_tournament(
+        admin,
+        NewTournament {
+            format: BracketFormat::Swiss,
+            entry_fee: 0,
+            max_entrants: 3,
+            ..settings()
+        },
+    );
+    h.send(&[ix], &[]).await.unwrap();
+}
+
+#[tokio::test]
+async fn the_last_registration_builds_the_bracket() {
+    let mut h = Harness::start().await;
+    let cup = Cup::new(&mut h, BracketFormat::SingleElimination, 4, vec![10_000]).await;
+    let [first, .., last] = &cup.players[..] else {
+        unreachable!()
+    };
+
+    cup.register(&mut h, first).await;
+    let ix = builders::register(first.pubkey(), cup.address, None);
+    assert_error(
+        h.send(&[ix], &[first]).await,
+        TournamentError::AlreadyRegistered,
+    );
+    cup.register(&mut h, &cup.players[1]).await;
+    cup.register(&mut h, &cup.players[2]).await;
+    let tournament = cup.tournament(&mut h).await;
+    assert_eq!(tournament.status, TournamentStatus::Registration);
+    assert!(tournament.matches.is_empty());
+
+    cup.register(&mut h, last).await;
+    let tournament = cup.tournament(&mut h).await;
+    assert_eq!(tournament.status, TournamentStatus::InProgress);
+    let entrants: Vec<Pubkey> = cup.players.iter().map(Keypair::pubkey).collect();
+    assert_eq!(tournament.entrants, entrants);
+    assert_eq!(
+        tournament.matches,
+        tournament::build_bracket(tournament.format, 4)
+    );
+    assert_eq!(tournament.prize_pool, 4 * ENTRY_FEE);
+    assert_eq!(h.lamports(&cup.vault()).await, 4 * ENTRY_FEE);
+    assert_eq!(cup.balances(&mut h).await, [PLAYER_FUNDS - ENTRY_FEE; 4]);
+
+    let latecomer = h.funded_player().await;
+    let ix = builders::register(latecomer.pubkey(), cup.address, None);
+    assert_error(
+        h.send(&[ix], &[&latecomer]).await,
+        TournamentError::RegistrationClosed,
+    );
+}
+
+#[tokio::test]
+async fn referred_players_pay_their_referrer_a_share() {
+    let mut h = Harness::start().await;
+    // 10% of entry fees.
+    fixtures::referral(&mut h, 1_000).await;
+    let cup = Cup::new(&mut h, BracketFormat::SingleElimination, 2, vec![10_000]).await;
+    let referrer = h.funded_player().await;
+    let player = &cup.players[0];
+    let ixs = [
+        fixtures::create_code(referrer.pubkey(), "CUP"),
+        fixtures::register_referral(player.pubkey(), "CUP", referrer.pubkey()),
+    ];
+    h.send(&ixs, &[&referrer, player]).await.unwrap();
+    let referral: Referral = h.account(&pda::referral::referral(&player.pubkey())).await;
+    let before = h.lamports(&player.pubkey()).await;
+
+    let ix = builders::register(player.pubkey(), cup.address, None);
+    assert_error(
+        h.send(&[ix], &[player]).await,
+        TournamentError::MissingReferralAccounts,
+    );
+    let ix = builders::register(player.pubkey(), cup.address, Some(&referral));
+    h.send(&[ix], &[player]).await.unwrap();
+
+    let share = ENTRY_FEE / 10;
+    let code: ReferralCode = h.account(&referral.code).await;
+    assert_eq!(code.claimable, share);
+    assert_eq!(h.lamports(&cup.vault()).await, ENTRY_FEE - share);
+    let tournament = cup.tournament(&mut h).await;
+    assert_eq!(tournament.prize_pool, ENTRY_FEE - share);
+    assert_eq!(h.lamports(&player.pubkey()).await, before - ENTRY_FEE);
+}
+
+#[tokio::test]
+async fn the_final_pays_out_by_place() {
+    let mut h = Harness::start().await;
+    let cup = Cup::new(
+        &mut h,
+        BracketFormat::SingleElimination,
+        4,
+        vec![6_000, 3_000, 1_000],
+    )
+    .await;
+    cup.fill(&mut h).await;
+    // Seeds 0 and 3 win their semifinals, and 3 the final.
+    cup.play(&mut h, 0, 0).await;
+    cup.play(&mut h, 1, 1).await;
+    let [p0, p1, p2, p3] = &cup.players[..] else {
+        unreachable!()
+    };
+    let organizer_before = h.lamports(&cup.organizer.pubkey()).await;
+
+    let ix = cup.report(&mut h, 2, 1).await;
+    assert_error(
+        h.send(&[paying(ix.clone(), &[p0, p3, p1, p2])], &[&cup.organizer])
+            .await,
+        TournamentError::PlayerMismatch,
+    );
+    h.send(&[paying(ix, &[p3, p0, p1, p2])], &[&cup.organizer])
+        .await
+        .unwrap();
+
+    let pool = 4 * ENTRY_FEE;
+    let organizer_fee = pool * ORGANIZER_FEE_BPS as u64 / 10_000;
+    let prizes = pool - organizer_fee;
+    let entered = PLAYER_FUNDS - ENTRY_FEE;
+    assert_eq!(
+        cup.balances(&mut h).await,
+        [
+            entered + prizes * 3 / 10,
+            entered + prizes / 20,
+            entered + prizes / 20,
+            entered + prizes * 6 / 10,
+        ]
+    );
+    assert_eq!(
+        h.lamports(&cup.organizer.pubkey()).await,
+        organizer_before + organizer_fee
+    );
+    assert_eq!(h.lamports(&cup.vault()).await, 0);
+    let tournament = cup.tournament(&mut h).await;
+    assert_eq!(tournament.status, TournamentStatus::Complete);
+    assert_eq!(tournament.champion, p3.pubkey());
+    assert_eq!(tournament.prize_pool, 0);
+}
+
+#[tokio::test]
+async fn reports_must_match_the_bracket() {
+    let mut h = Harness::start().await;
+    let cup = Cup::new(&mut h, BracketFormat::SingleElimination, 4, vec![10_000]).await;
+    let keys: Vec<Pubkey> = cup.players.iter().map(Keypair::pubkey).collect();
+    let &[p0, p1, p2, p3] = &keys[..] else {
+        unreachable!()
+    };
+    let organizer = &cup.organizer;
+
+    let ix = cup.report_result(0, 0, [p0, p1]);
+    assert_error(
+        h.send(&[ix], &[organizer]).await,
+        TournamentError::NotInProgress,
+    );
+    cup.fill(&mut h).await;
+
+    let ix = cup.report_result(3, 0, [p0, p1]);
+    assert_error(
+        h.send(&[ix], &[organizer]).await,
+        TournamentError::InvalidMatch,
+    );
+    let ix = cup.report_result(2, 0, [p0, p2]);
+    assert_error(
+        h.send(&[ix], &[organizer]).await,
+        TournamentError::MatchNotReady,
+    );
+    let ix = cup.report_result(0, 2, [p0, p1]);
+    assert_error(
+        h.send(&[ix], &[organizer]).await,
+        TournamentError::InvalidWinner,
+    );
+    let ix = cup.report_result(0, 0, [p1, p0]);
+    assert_error(
+        h.send(&[ix], &[organizer]).await,
+        TournamentError::PlayerMismatch,
+    );
+    let stranger = h.funded_player().await;
+    let mut ix = cup.report_result(1, 0, [p2, p3]);
+    ix.accounts[0] = AccountMeta::new(stranger.pubkey(), true);
+    assert_error(
+        h.send(&[ix], &[&stranger]).await,
+        ErrorCode::ConstraintHasOne,
+    );
+
+    cup.play(&mut h, 0, 0).await;
+    let ix = cup.report_result(0, 1, [p0, p1]);
+    assert_error(
+        h.send(&[ix], &[organizer]).await,
+        TournamentError::MatchNotReady,
+    );
+}
+
+#[tokio::test]
+async fn double_elimination_runs_a_losers_bracket() {
+    let mut h = Harness::start().await;
+    let cup = Cup::new(
+        &mut h,
+        BracketFormat::DoubleElimination,
+        4,
+        vec![7_000, 2_000, 1_000],
+    )
+    .await;
+    cup.fill(&mut h).await;
+    let [p0, p1, p2, _] = &cup.players[..] else {
+        unreachable!()
+    };
+
+    // Seeds 0 and 2 win the first round and 0 the winners final, dropping
+    // 2 into the losers final. Seed 1 beats 3 for the other seat there,
+    // then loses to 2, who meets 0 again in the grand final.
+    cup.play(&mut h, 0, 0).await;
+    cup.play(&mut h, 1, 0).await;
+    cup.play(&mut h, 2, 0).await;
+    cup.play(&mut h, 3, 0).await;
+    cup.play(&mut h, 4, 1).await;
+    let tournament = cup.tournament(&mut h).await;
+    assert_eq!(tournament.matches[5].players, [0, 2]);
+
+    let ix = cup.report(&mut h, 5, 0).await;
+    h.send(&[paying(ix, &[p0, p2, p1])], &[&cup.organizer])
+        .await
+        .unwrap();
+    let tournament = cup.tournament(&mut h).await;
+    assert_eq!(tournament.status, TournamentStatus::Complete);
+    assert_eq!(tournament.champion, p0.pubkey());
+    assert_eq!(
+        tournament::placings(&tournament),
+        [vec![0], vec![2], vec![1]]
+    );
+}
+
+#[tokio::test]
+async fn swiss_rounds_are_paired_then_finished() {
+    let mut h = Harness::start().await;
+    let cup = Cup::new(&mut h, BracketFormat::Swiss, 3, vec![5_000, 3_000, 2_000]).await;
+    let [p0, p1, p2] = &cup.players[..] else {
+        unreachable!()
+    };
+
+    assert_error(
+        h.send(&[cup.pair_swiss_round()], &[]).await,
+        TournamentError::NotInProgress,
+    );
+    cup.fill(&mut h).await;
+
+    // Round one pairs 0 with 1 and gives 2 the bye.
+    h.send(&[cup.pair_swiss_round()], &[]).await.unwrap();
+    let tournament = cup.tournament(&mut h).await;
+    assert_eq!(tournament.matches.len(), 1);
+    assert_eq!(tournament.matches[0].players, [0, 1]);
+    assert!(tournament.swiss[2].had_bye);
+    assert_error(
+        h.send(&[cup.pair_swiss_round()], &[]).await,
+        TournamentError::RoundInProgress,
+    );
+    cup.play(&mut h, 0, 0).await;
+
+    // Round two pairs the leaders and gives 1 the bye.
+    h.send(&[cup.pair_swiss_round()], &[]).await.unwrap();
+    let tournament = cup.tournament(&mut h).await;
+    assert_eq!(tournament.matches[0].players, [0, 2]);
+    assert_error(
+        h.send(&[paying(cup.finish_swiss(), &[p2, p0, p1])], &[])
+            .await,
+        TournamentError::RoundInProgress,
+    );
+    cup.play(&mut h, 0, 1).await;
+    assert_error(
+        h.send(&[cup.pair_swiss_round()], &[]).await,
+        TournamentError::SwissRoundsPlayed,
+    );
+
+    // 2 leads on points; 0 edges 1 on Buchholz.
+    let entered = PLAYER_FUNDS - ENTRY_FEE;
+    h.send(&[paying(cup.finish_swiss(), &[p2, p0, p1])], &[])
+        .await
+        .unwrap();
+    let prizes = 3 * ENTRY_FEE - 3 * ENTRY_FEE * ORGANIZER_FEE_BPS as u64 / 10_000;
+    assert_eq!(
+        cup.balances(&mut h).await,
+        [
+            entered + prizes * 3 / 10,
+            entered + prizes / 5,
+            entered + prizes / 2,
+        ]
+    );
+    let tournament = cup.tournament(&mut h).await;
+    assert_eq!(tournament.status, TournamentStatus::Complete);
+    assert_eq!(tournament.champion, p2.pubkey());
+    assert_error(
+        h.send(&[paying(cup.finish_swiss(), &[p2, p0, p1])], &[])
+            .await,
+        TournamentError::NotInProgress,
+    );
+}
+
+#[tokio::test]
+async fn only_swiss_tournaments_are_paired_and_finished() {
+    let mut h = Harness::start().await;
+    let cup = Cup::new(&mut h, BracketFormat::SingleElimination, 2, vec![10_000]).await;
+    cup.fill(&mut h).await;
+
+    assert_error(
+        h.send(&[cup.pair_swiss_round()], &[]).await,
+        TournamentError::NotSwiss,
+    );
+    assert_error(
+        h.send(&[cup.finish_swiss()], &[]).await,
+        TournamentError::NotSwiss,
+    );
+}
+
+#[tokio::test]
+async fn champions_earn_the_tournament_badge() {
+    let mut h = Harness::start().await;
+    fixtures::profile(&mut h).await;
+    fixtures::achievements(&mut h).await;
+    let admin = h.admin();
+    let ix = fixtures::define_achievement(admin, FIRST_TOURNAMENT_WIN, "Champion", tournament::ID);
+    h.send(&[ix], &[]).await.unwrap();
+    let cup = Cup::new(&mut h, BracketFormat::SingleElimination, 2, vec![10_000]).await;
+    cup.fill(&mut h).await;
+    let champion = &cup.players[1];
+
+    let ix = cup.report(&mut h, 0, 1).await;
+    h.send(&[paying(ix, &[champion])], &[&cup.organizer])
+        .await
+        .unwrap();
+
+    let ix = award_champion_badge(admin, cup.address, champion.pubkey());
+    h.send(&[ix], &[]).await.unwrap();
+    let badge: Badge = h
+        .account(&pda::achievements::badge(
+            FIRST_TOURNAMENT_WIN,
+            &champion.pubkey(),
+        ))
+        .await;
+    assert_eq!(badge.player, champion.pubkey());
+    assert_eq!(badge.achievement_id, FIRST_TOURNAMENT_WIN);
+}
+
+#[tokio::test]
+async fn accounts_are_already_on_the_current_layout() {
+    let mut h = Harness::start().await;
+    let cup = Cup::new(&mut h, BracketFormat::SingleElimination, 2, vec![10_000]).await;
+
+    let admin = h.admin();
+    for account in [cup.address, pda::tournament::config()] {
+        assert_error(
+            h.send(&[migrate(admin, account)], &[]).await,
+            TournamentError::AlreadyMigrated,
+        );
+    }
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &tournament::ID, vec![9; Tournament::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, TournamentError::UnsupportedLayout);
+}
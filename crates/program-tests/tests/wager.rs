@@ -0,0 +1,509 @@
+use anchor_spl::token_2022::spl_token_2022;
+use combo_mint_client::wager::{
+    Wager, WagerError, WagerStatus, CRANK_REWARD, DEPOSIT_TIMEOUT, RESULT_TIMEOUT,
+};
+use combo_mint_client::{instruction, pda, wager};
+use program_tests::{assert_error, fixtures, Harness};
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::{system_program, sysvar};
+
+// `WagerError::AlreadySettled` can't be reached: settling or cancelling
+// closes the escrow, which `finalize_if_expired` needs before it looks at
+// the status.
+
+const MATCH_ID: u64 = 1;
+const STAKE: u64 = 1_000;
+
+/// Two players holding `STAKE` each of `mint`, the creator first.
+struct Table {
+    players: [Keypair; 2],
+    tokens: [Pubkey; 2],
+    mint: Pubkey,
+    token_program: Pubkey,
+}
+
+impl Table {
+    async fn new(h: &mut Harness, mint: Pubkey) -> Self {
+        let players = [h.funded_player().await, h.funded_player().await];
+        let mut tokens = [Pubkey::default(); 2];
+        for (token, player) in tokens.iter_mut().zip(&players) {
+            *token = h.put_token_account(&mint, &player.pubkey(), STAKE).await;
+        }
+        let token_program = h.raw_account(&mint).await.unwrap().owner;
+        Table {
+            players,
+            tokens,
+            mint,
+            token_program,
+        }
+    }
+
+    async fn spl(h: &mut Harness) -> Self {
+        let mint = h.put_mint(&h.admin());
+        Table::new(h, mint).await
+    }
+
+    fn creator(&self) -> Pubkey {
+        self.players[0].pubkey()
+    }
+
+    fn opponent(&self) -> Pubkey {
+        self.players[1].pubkey()
+    }
+
+    fn wager(&self) -> Pubkey {
+        pda::wager::wager(MATCH_ID, &self.creator(), &self.opponent())
+    }
+
+    fn escrow(&self) -> Pubkey {
+        pda::wager::escrow(&self.wager())
+    }
+
+    fn create_wager(&self, opponent: Pubkey, amount: u64) -> Instruction {
+        let wager = pda::wager::wager(MATCH_ID, &self.creator(), &opponent);
+        instruction(
+            wager::ID,
+            wager::accounts::CreateWager {
+                creator: self.creator(),
+                wager,
+                escrow: pda::wager::escrow(&wager),
+                mint: self.mint,
+                creator_token_account: self.tokens[0],
+                token_program: self.token_program,
+                system_program: system_program::ID,
+                rent: sysvar::rent::ID,
+            },
+            wager::instruction::CreateWager {
+                match_id: MATCH_ID,
+                opponent,
+                amount,
+            },
+        )
+    }
+
+    fn accept_wager(&self, opponent: Pubkey, token_account: Pubkey) -> Instruction {
+        instruction(
+            wager::ID,
+            wager::accounts::AcceptWager {
+                opponent,
+                wager: self.wager(),
+                escrow: self.escrow(),
+                mint: self.mint,
+                opponent_token_account: token_account,
+                token_program: self.token_program,
+            },
+            wager::instruction::AcceptWager {},
+        )
+    }
+
+    fn claim_pot(&self, winner: usize) -> Instruction {
+        instruction(
+            wager::ID,
+            wager::accounts::ClaimPot {
+                winner: self.players[winner].pubkey(),
+                creator: self.creator(),
+                wager: self.wager(),
+                escrow: self.escrow(),
+                match_account: pda::match_result::match_account(MATCH_ID),
+                mint: self.mint,
+                winner_token_account: self.tokens[winner],
+                token_program: self.token_program,
+            },
+            wager::instruction::ClaimPot {},
+        )
+    }
+
+    fn cancel_wager(&self, player: Pubkey) -> Instruction {
+        instruction(
+            wager::ID,
+            wager::accounts::CancelWager {
+                player,
+                creator: self.creator(),
+                wager: self.wager(),
+                escrow: self.escrow(),
+                mint: self.mint,
+                creator_token_account: self.tokens[0],
+                token_program: self.token_program,
+            },
+            wager::instruction::CancelWager {},
+        )
+    }
+
+    fn finalize_if_expired(&self, caller: Pubkey) -> Instruction {
+        instruction(
+            wager::ID,
+            wager::accounts::FinalizeIfExpired {
+                caller,
+                creator: self.creator(),
+                wager: self.wager(),
+                escrow: self.escrow(),
+                match_account: pda::match_result::match_account(MATCH_ID),
+                mint: self.mint,
+                creator_token_account: self.tokens[0],
+                opponent_token_account: self.tokens[1],
+                token_program: self.token_program,
+            },
+            wager::instruction::FinalizeIfExpired {},
+        )
+    }
+
+    /// Opens the wager and has the opponent accept it.
+    async fn matched(&self, h: &mut Harness) {
+        let ixs = [
+            self.create_wager(self.opponent(), STAKE),
+            self.accept_wager(self.opponent(), self.tokens[1]),
+        ];
+        h.send(&ixs, &[&self.players[0], &self.players[1]])
+            .await
+            .unwrap();
+    }
+
+    async fn balances(&self, h: &mut Harness) -> [u64; 2] {
+        [
+            h.token_balance(&self.tokens[0]).await,
+            h.token_balance(&self.tokens[1]).await,
+        ]
+    }
+}
+
+fn migrate(payer: Pubkey, account: Pubkey) -> Instruction {
+    instruction(
+        wager::ID,
+        wager::accounts::MigrateAccount {
+            payer,
+            account,
+            system_program: system_program::ID,
+        },
+        wager::instruction::MigrateAccount {},
+    )
+}
+
+#[tokio::test]
+async fn a_matched_wager_escrows_both_stakes() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+
+    h.send(
+        &[table.create_wager(table.opponent(), STAKE)],
+        &[&table.players[0]],
+    )
+    .await
+    .unwrap();
+    let wager: Wager = h.account(&table.wager()).await;
+    assert_eq!(wager.match_id, MATCH_ID);
+    assert_eq!(wager.players, [table.creator(), table.opponent()]);
+    assert_eq!(wager.mint, table.mint);
+    assert_eq!(wager.amount, STAKE);
+    assert_eq!(wager.status, WagerStatus::Open);
+    assert_eq!(h.token_balance(&table.escrow()).await, STAKE);
+
+    let ix = table.accept_wager(table.opponent(), table.tokens[1]);
+    h.send(&[ix], &[&table.players[1]]).await.unwrap();
+    let wager: Wager = h.account(&table.wager()).await;
+    assert_eq!(wager.status, WagerStatus::Matched);
+    assert_eq!(h.token_balance(&table.escrow()).await, 2 * STAKE);
+    assert_eq!(table.balances(&mut h).await, [0, 0]);
+}
+
+#[tokio::test]
+async fn wagers_need_an_opponent_a_stake_and_a_supported_mint() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    let creator = &table.players[0];
+
+    let ix = table.create_wager(table.creator(), STAKE);
+    assert_error(h.send(&[ix], &[creator]).await, WagerError::InvalidOpponent);
+    let ix = table.create_wager(table.opponent(), 0);
+    assert_error(h.send(&[ix], &[creator]).await, WagerError::InvalidAmount);
+
+    let mint = h.put_unsupported_mint(&h.admin());
+    let table = Table::new(&mut h, mint).await;
+    let ix = table.create_wager(table.opponent(), STAKE);
+    assert_error(
+        h.send(&[ix], &[&table.players[0]]).await,
+        WagerError::UnsupportedMint,
+    );
+}
+
+#[tokio::test]
+async fn stakes_are_what_arrives_after_transfer_fees() {
+    let mut h = Harness::start().await;
+    // 1%, so a stake of 1000 arrives as 990.
+    let mint = h.put_fee_mint(&h.admin(), 100);
+    let table = Table::new(&mut h, mint).await;
+    assert_eq!(table.token_program, spl_token_2022::ID);
+
+    table.matched(&mut h).await;
+    let wager: Wager = h.account(&table.wager()).await;
+    assert_eq!(wager.amount, 990);
+    // The opponent sends enough to match the stake after the fee.
+    let escrowed = h.token_balance(&table.escrow()).await;
+    assert!(escrowed >= 2 * wager.amount, "escrow holds {}", escrowed);
+    assert_eq!(table.balances(&mut h).await, [0, 0]);
+}
+
+#[tokio::test]
+async fn only_the_opponent_accepts_and_only_in_time() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    h.send(
+        &[table.create_wager(table.opponent(), STAKE)],
+        &[&table.players[0]],
+    )
+    .await
+    .unwrap();
+
+    let stranger = h.funded_player().await;
+    let tokens = h
+        .put_token_account(&table.mint, &stranger.pubkey(), STAKE)
+        .await;
+    let ix = table.accept_wager(stranger.pubkey(), tokens);
+    assert_error(h.send(&[ix], &[&stranger]).await, WagerError::Unauthorized);
+
+    h.advance(DEPOSIT_TIMEOUT).await;
+    let ix = table.accept_wager(table.opponent(), table.tokens[1]);
+    assert_error(
+        h.send(&[ix], &[&table.players[1]]).await,
+        WagerError::DepositExpired,
+    );
+}
+
+#[tokio::test]
+async fn wagers_are_accepted_once() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    table.matched(&mut h).await;
+
+    let ix = table.accept_wager(table.opponent(), table.tokens[1]);
+    assert_error(
+        h.send(&[ix], &[&table.players[1]]).await,
+        WagerError::NotOpen,
+    );
+}
+
+#[tokio::test]
+async fn the_winner_claims_the_pot() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    table.matched(&mut h).await;
+    let [creator, opponent] = &table.players;
+    fixtures::recorded_match(&mut h, MATCH_ID, [creator, opponent], 1).await;
+
+    assert_error(
+        h.send(&[table.claim_pot(0)], &[creator]).await,
+        WagerError::NotWinner,
+    );
+    h.send(&[table.claim_pot(1)], &[opponent]).await.unwrap();
+    assert_eq!(table.balances(&mut h).await, [0, 2 * STAKE]);
+    assert!(!h.exists(&table.escrow()).await);
+    let wager: Wager = h.account(&table.wager()).await;
+    assert_eq!(wager.status, WagerStatus::Settled);
+}
+
+#[tokio::test]
+async fn claims_need_a_matched_wager_and_a_final_result() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    let [creator, opponent] = &table.players;
+    h.send(&[table.create_wager(table.opponent(), STAKE)], &[creator])
+        .await
+        .unwrap();
+    fixtures::open_set(&mut h, MATCH_ID, [creator, opponent]).await;
+
+    assert_error(
+        h.send(&[table.claim_pot(1)], &[opponent]).await,
+        WagerError::NotMatched,
+    );
+    let ix = table.accept_wager(table.opponent(), table.tokens[1]);
+    h.send(&[ix], &[opponent]).await.unwrap();
+    assert_error(
+        h.send(&[table.claim_pot(1)], &[opponent]).await,
+        WagerError::ResultNotFinal,
+    );
+}
+
+#[tokio::test]
+async fn claims_need_the_wagers_players_in_the_match() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    table.matched(&mut h).await;
+    let stranger = h.funded_player().await;
+    fixtures::recorded_match(&mut h, MATCH_ID, [&table.players[1], &stranger], 0).await;
+
+    assert_error(
+        h.send(&[table.claim_pot(1)], &[&table.players[1]]).await,
+        WagerError::PlayerMismatch,
+    );
+}
+
+#[tokio::test]
+async fn unaccepted_wagers_are_cancelled_after_the_deposit_window() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    let [creator, opponent] = &table.players;
+    h.send(&[table.create_wager(table.opponent(), STAKE)], &[creator])
+        .await
+        .unwrap();
+
+    assert_error(
+        h.send(&[table.cancel_wager(table.opponent())], &[opponent])
+            .await,
+        WagerError::TimeoutNotReached,
+    );
+    h.advance(DEPOSIT_TIMEOUT).await;
+    let stranger = h.funded_player().await;
+    assert_error(
+        h.send(&[table.cancel_wager(stranger.pubkey())], &[&stranger])
+            .await,
+        WagerError::Unauthorized,
+    );
+
+    h.send(&[table.cancel_wager(table.opponent())], &[opponent])
+        .await
+        .unwrap();
+    assert_eq!(table.balances(&mut h).await, [STAKE, STAKE]);
+    assert!(!h.exists(&table.escrow()).await);
+    let wager: Wager = h.account(&table.wager()).await;
+    assert_eq!(wager.status, WagerStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn matched_wagers_cannot_be_cancelled() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    table.matched(&mut h).await;
+    h.advance(DEPOSIT_TIMEOUT).await;
+
+    assert_error(
+        h.send(&[table.cancel_wager(table.creator())], &[&table.players[0]])
+            .await,
+        WagerError::NotOpen,
+    );
+}
+
+#[tokio::test]
+async fn the_crank_refunds_unaccepted_wagers() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    h.send(
+        &[table.create_wager(table.opponent(), STAKE)],
+        &[&table.players[0]],
+    )
+    .await
+    .unwrap();
+    let caller = h.funded_player().await.pubkey();
+
+    assert_error(
+        h.send(&[table.finalize_if_expired(caller)], &[]).await,
+        WagerError::TimeoutNotReached,
+    );
+    h.advance(DEPOSIT_TIMEOUT).await;
+    let before = h.lamports(&caller).await;
+    h.send(&[table.finalize_if_expired(caller)], &[])
+        .await
+        .unwrap();
+    assert_eq!(table.balances(&mut h).await, [STAKE, STAKE]);
+    assert_eq!(h.lamports(&caller).await, before + CRANK_REWARD);
+    let wager: Wager = h.account(&table.wager()).await;
+    assert_eq!(wager.status, WagerStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn the_crank_pays_the_winner_of_a_final_result() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    table.matched(&mut h).await;
+    let [creator, opponent] = &table.players;
+    fixtures::recorded_match(&mut h, MATCH_ID, [creator, opponent], 0).await;
+
+    let caller = h.funded_player().await.pubkey();
+    h.send(&[table.finalize_if_expired(caller)], &[])
+        .await
+        .unwrap();
+    assert_eq!(table.balances(&mut h).await, [2 * STAKE, 0]);
+    let wager: Wager = h.account(&table.wager()).await;
+    assert_eq!(wager.status, WagerStatus::Settled);
+}
+
+#[tokio::test]
+async fn the_crank_counts_a_match_between_others_as_no_result() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    table.matched(&mut h).await;
+    let stranger = h.funded_player().await;
+    fixtures::recorded_match(&mut h, MATCH_ID, [&stranger, &table.players[1]], 0).await;
+
+    let caller = h.funded_player().await.pubkey();
+    assert_error(
+        h.send(&[table.finalize_if_expired(caller)], &[]).await,
+        WagerError::TimeoutNotReached,
+    );
+    h.advance(RESULT_TIMEOUT).await;
+    h.send(&[table.finalize_if_expired(caller)], &[])
+        .await
+        .unwrap();
+    assert_eq!(table.balances(&mut h).await, [STAKE, STAKE]);
+    let wager: Wager = h.account(&table.wager()).await;
+    assert_eq!(wager.status, WagerStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn the_crank_splits_the_pot_when_no_result_arrives() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    table.matched(&mut h).await;
+    let caller = h.funded_player().await.pubkey();
+
+    h.advance(DEPOSIT_TIMEOUT).await;
+    assert_error(
+        h.send(&[table.finalize_if_expired(caller)], &[]).await,
+        WagerError::TimeoutNotReached,
+    );
+    h.advance(RESULT_TIMEOUT - DEPOSIT_TIMEOUT).await;
+    h.send(&[table.finalize_if_expired(caller)], &[])
+        .await
+        .unwrap();
+    assert_eq!(table.balances(&mut h).await, [STAKE, STAKE]);
+    let wager: Wager = h.account(&table.wager()).await;
+    assert_eq!(wager.status, WagerStatus::Cancelled);
+}
+
+#[tokio::test]
+async fn migrates_wagers_from_before_layout_versions() {
+    let mut h = Harness::start().await;
+    let table = Table::spl(&mut h).await;
+    h.send(
+        &[table.create_wager(table.opponent(), STAKE)],
+        &[&table.players[0]],
+    )
+    .await
+    .unwrap();
+    let payer = h.admin();
+    assert_error(
+        h.send(&[migrate(payer, table.wager())], &[]).await,
+        WagerError::AlreadyMigrated,
+    );
+
+    // The same wager without its version byte.
+    let mut data = h.raw_account(&table.wager()).await.unwrap().data;
+    data.remove(8);
+    h.put_raw_account(&table.wager(), &wager::ID, data);
+    h.send(&[migrate(payer, table.wager())], &[]).await.unwrap();
+    let wager: Wager = h.account(&table.wager()).await;
+    assert_eq!(wager.layout_version, 1);
+    assert_eq!(wager.players, [table.creator(), table.opponent()]);
+    assert_eq!(wager.amount, STAKE);
+    assert_eq!(wager.status, WagerStatus::Open);
+}
+
+#[tokio::test]
+async fn refuses_layouts_it_cannot_migrate() {
+    let mut h = Harness::start().await;
+    let unknown = Keypair::new().pubkey();
+    h.put_raw_account(&unknown, &wager::ID, vec![9; Wager::SPACE]);
+
+    let ix = migrate(h.admin(), unknown);
+    assert_error(h.send(&[ix], &[]).await, WagerError::UnsupportedLayout);
+}
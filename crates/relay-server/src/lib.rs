@@ -0,0 +1,429 @@
+//! UDP rendezvous and relay for peer-to-peer matches.
+//!
+//! Both players of a match register with the server from the socket they
+//! play on. Once both have, the server answers each with the other's public
+//! address as the server saw it, and the clients punch through their NATs
+//! by sending to each other directly. Clients for whom punching fails wrap
+//! their game packets in `Message::Relay` and the server forwards them to
+//! the peer's registered address.
+//!
+//! Registering takes a per-match, per-side token. The matchmaking service
+//! derives it from a secret it shares with the relay when the
+//! `matchmaking` program emits `MatchFound`, and hands each player theirs,
+//! so only the two players of a live match can use the relay.
+//!
+//! Messages, integers little-endian:
+//!
+//! ```text
+//! register  tag 1, match_id u64, side u8, token [u8; 32]   client to server
+//! peer      tag 2, family u8 (4 or 6), ip, port u16         server to client
+//! relay     tag 3, payload                                  both ways
+//! rejected  tag 4, reason u8                                server to client
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+pub const TOKEN_LEN: usize = 32;
+
+const TAG_REGISTER: u8 = 1;
+const TAG_PEER: u8 = 2;
+const TAG_RELAY: u8 = 3;
+const TAG_REJECTED: u8 = 4;
+
+pub type Token = [u8; TOKEN_LEN];
+
+/// Token for one side of a match, as SHA-256 of the shared secret, the
+/// match id and the side.
+pub fn match_token(secret: &[u8], match_id: u64, side: u8) -> Token {
+    let mut hasher = Sha256::new();
+    hasher.update(b"relay-token");
+    hasher.update(secret);
+    hasher.update(match_id.to_le_bytes());
+    hasher.update([side]);
+    hasher.finalize().into()
+}
+
+/// `matchmaking::MatchFound` as emitted on-chain.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MatchFound {
+    pub pending_match: [u8; 32],
+    pub match_id: u64,
+    pub players: [[u8; 32]; 2],
+}
+
+impl MatchFound {
+    const LEN: usize = 8 + 32 + 8 + 2 * 32;
+
+    /// Anchor's event discriminator, the first 8 bytes of
+    /// `sha256("event:MatchFound")`.
+    pub fn discriminator() -> [u8; 8] {
+        let hash = Sha256::digest(b"event:MatchFound");
+        let mut discriminator = [0; 8];
+        discriminator.copy_from_slice(&hash[..8]);
+        discriminator
+    }
+
+    /// Parses the base64 payload of a `Program data:` log line.
+    pub fn from_program_data(data: &str) -> Result<Self, RelayError> {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data.trim())
+            .map_err(|_| RelayError::Malformed)?;
+        if bytes.len() != Self::LEN || bytes[..8] != Self::discriminator() {
+            return Err(RelayError::Malformed);
+        }
+        let key = |offset: usize| {
+            let mut key = [0; 32];
+            key.copy_from_slice(&bytes[offset..offset + 32]);
+            key
+        };
+        let mut match_id = [0; 8];
+        match_id.copy_from_slice(&bytes[40..48]);
+        Ok(MatchFound {
+            pending_match: key(8),
+            match_id: u64::from_le_bytes(match_id),
+            players: [key(48), key(80)],
+        })
+    }
+
+    /// Each player's base58 key with the token for their side.
+    pub fn tokens(&self, secret: &[u8]) -> [(String, Token); 2] {
+        [0, 1].map(|side| {
+            (
+                bs58::encode(self.players[side]).into_string(),
+                match_token(secret, self.match_id, side as u8),
+            )
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RejectReason {
+    BadToken,
+    /// The server is already serving `max_matches` matches.
+    Full,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Message {
+    Register {
+        match_id: u64,
+        side: u8,
+        token: Token,
+    },
+    Peer(SocketAddr),
+    Relay(Vec<u8>),
+    Rejected(RejectReason),
+}
+
+impl Message {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        match self {
+            Message::Register {
+                match_id,
+                side,
+                token,
+            } => {
+                out.push(TAG_REGISTER);
+                out.extend_from_slice(&match_id.to_le_bytes());
+                out.push(*side);
+                out.extend_from_slice(token);
+            }
+            Message::Peer(addr) => {
+                out.push(TAG_PEER);
+                match addr.ip() {
+                    IpAddr::V4(ip) => {
+                        out.push(4);
+                        out.extend_from_slice(&ip.octets());
+                    }
+                    IpAddr::V6(ip) => {
+                        out.push(6);
+                        out.extend_from_slice(&ip.octets());
+                    }
+                }
+                out.extend_from_slice(&addr.port().to_le_bytes());
+            }
+            Message::Relay(payload) => {
+                out.push(TAG_RELAY);
+                out.extend_from_slice(payload);
+            }
+            Message::Rejected(reason) => {
+                out.push(TAG_REJECTED);
+                out.push(match reason {
+                    RejectReason::BadToken => 0,
+                    RejectReason::Full => 1,
+                });
+            }
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, RelayError> {
+        let (&tag, body) = bytes.split_first().ok_or(RelayError::Malformed)?;
+        match tag {
+            TAG_REGISTER if body.len() == 8 + 1 + TOKEN_LEN => {
+                let mut match_id = [0; 8];
+                match_id.copy_from_slice(&body[..8]);
+                let mut token = [0; TOKEN_LEN];
+                token.copy_from_slice(&body[9..]);
+                Ok(Message::Register {
+                    match_id: u64::from_le_bytes(match_id),
+                    side: body[8],
+                    token,
+                })
+            }
+            TAG_PEER => {
+                let (&family, rest) = body.split_first().ok_or(RelayError::Malformed)?;
+                let (ip, port) = match (family, rest.len()) {
+                    (4, 6) => {
+                        let octets: [u8; 4] = rest[..4].try_into().unwrap();
+                        (IpAddr::V4(Ipv4Addr::from(octets)), &rest[4..])
+                    }
+                    (6, 18) => {
+                        let octets: [u8; 16] = rest[..16].try_into().unwrap();
+                        (IpAddr::V6(Ipv6Addr::from(octets)), &rest[16..])
+                    }
+                    _ => return Err(RelayError::Malformed),
+                };
+                Ok(Message::Peer(SocketAddr::new(
+                    ip,
+                    u16::from_le_bytes([port[0], port[1]]),
+                )))
+            }
+            TAG_RELAY => Ok(Message::Relay(body.to_vec())),
+            TAG_REJECTED if body.len() == 1 => match body[0] {
+                0 => Ok(Message::Rejected(RejectReason::BadToken)),
+                1 => Ok(Message::Rejected(RejectReason::Full)),
+                _ => Err(RelayError::Malformed),
+            },
+            _ => Err(RelayError::Malformed),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelayError {
+    Malformed,
+    BadToken,
+    Full,
+    /// A relay packet came from an address no match registered.
+    Unregistered,
+    PayloadTooLarge(usize),
+}
+
+impl fmt::Display for RelayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayError::Malformed => write!(f, "malformed message"),
+            RelayError::BadToken => write!(f, "invalid match token"),
+            RelayError::Full => write!(f, "relay is at capacity"),
+            RelayError::Unregistered => write!(f, "sender is not registered"),
+            RelayError::PayloadTooLarge(len) => {
+                write!(f, "relay payload of {} bytes is too large", len)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RelayError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RelayConfig {
+    /// Matches with no traffic for this long are forgotten, which also
+    /// retires their tokens' registrations.
+    pub idle_timeout_ms: u64,
+    pub max_matches: usize,
+    pub max_payload: usize,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        RelayConfig {
+            idle_timeout_ms: 60_000,
+            max_matches: 10_000,
+            max_payload: 1200,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RelayStats {
+    pub registrations: u64,
+    pub relayed_packets: u64,
+    pub relayed_bytes: u64,
+    pub dropped: u64,
+}
+
+struct MatchSlot {
+    endpoints: [Option<SocketAddr>; 2],
+    last_seen_ms: u64,
+}
+
+/// Server state, free of any I/O: `handle` takes one received datagram and
+/// returns the datagrams to send in response.
+pub struct Relay {
+    secret: Vec<u8>,
+    config: RelayConfig,
+    matches: HashMap<u64, MatchSlot>,
+    /// Match and side each registered address plays.
+    senders: HashMap<SocketAddr, (u64, usize)>,
+    stats: RelayStats,
+}
+
+impl Relay {
+    pub fn new(secret: Vec<u8>, config: RelayConfig) -> Self {
+        Relay {
+            secret,
+            config,
+            matches: HashMap::new(),
+            senders: HashMap::new(),
+            stats: RelayStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> RelayStats {
+        self.stats
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn handle(
+        &mut self,
+        now_ms: u64,
+        from: SocketAddr,
+        packet: &[u8],
+    ) -> Vec<(SocketAddr, Vec<u8>)> {
+        let result = Message::decode(packet).and_then(|message| match message {
+            Message::Register {
+                match_id,
+                side,
+                token,
+            } => self.register(now_ms, from, match_id, side, &token),
+            Message::Relay(payload) => self.relay(now_ms, from, payload),
+            Message::Peer(_) | Message::Rejected(_) => Err(RelayError::Malformed),
+        });
+        match result {
+            Ok(out) => out,
+            Err(err) => {
+                self.stats.dropped += 1;
+                match err {
+                    RelayError::BadToken => {
+                        vec![(from, Message::Rejected(RejectReason::BadToken).encode())]
+                    }
+                    RelayError::Full => {
+                        vec![(from, Message::Rejected(RejectReason::Full).encode())]
+                    }
+                    _ => Vec::new(),
+                }
+            }
+        }
+    }
+
+    /// Forgets matches idle past the timeout.
+    pub fn expire(&mut self, now_ms: u64) {
+        let timeout = self.config.idle_timeout_ms;
+        let senders = &mut self.senders;
+        self.matches.retain(|_, slot| {
+            let live = now_ms.saturating_sub(slot.last_seen_ms) <= timeout;
+            if !live {
+                for addr in slot.endpoints.iter().flatten() {
+                    senders.remove(addr);
+                }
+            }
+            live
+        });
+    }
+
+    fn register(
+        &mut self,
+        now_ms: u64,
+        from: SocketAddr,
+        match_id: u64,
+        side: u8,
+        token: &Token,
+    ) -> Result<Vec<(SocketAddr, Vec<u8>)>, RelayError> {
+        if side > 1 || !tokens_equal(&match_token(&self.secret, match_id, side), token) {
+            return Err(RelayError::BadToken);
+        }
+        if !self.matches.contains_key(&match_id) && self.matches.len() >= self.config.max_matches {
+            return Err(RelayError::Full);
+        }
+        self.stats.registrations += 1;
+
+        let side = side as usize;
+        let slot = self.matches.entry(match_id).or_insert(MatchSlot {
+            endpoints: [None; 2],
+            last_seen_ms: now_ms,
+        });
+        slot.last_seen_ms = now_ms;
+        // A new address for the same side means the player's NAT rebound.
+        if let Some(old) = slot.endpoints[side].replace(from) {
+            if old != from {
+                self.senders.remove(&old);
+            }
+        }
+        self.senders.insert(from, (match_id, side));
+
+        // Clients retry until they hear about the peer, so answer every
+        // registration once both are in.
+        Ok(match slot.endpoints {
+            [Some(a), Some(b)] => vec![
+                (a, Message::Peer(b).encode()),
+                (b, Message::Peer(a).encode()),
+            ],
+            _ => Vec::new(),
+        })
+    }
+
+    fn relay(
+        &mut self,
+        now_ms: u64,
+        from: SocketAddr,
+        payload: Vec<u8>,
+    ) -> Result<Vec<(SocketAddr, Vec<u8>)>, RelayError> {
+        if payload.len() > self.config.max_payload {
+            return Err(RelayError::PayloadTooLarge(payload.len()));
+        }
+        let (match_id, side) = *self.senders.get(&from).ok_or(RelayError::Unregistered)?;
+        let slot = self
+            .matches
+            .get_mut(&match_id)
+            .ok_or(RelayError::Unregistered)?;
+        slot.last_seen_ms = now_ms;
+        let Some(peer) = slot.endpoints[1 - side] else {
+            return Ok(Vec::new());
+        };
+        self.stats.relayed_packets += 1;
+        self.stats.relayed_bytes += payload.len() as u64;
+        Ok(vec![(peer, Message::Relay(payload).encode())])
+    }
+}
+
+/// Compares without an early exit, so timing does not leak how much of a
+/// guessed token was right.
+fn tokens_equal(a: &Token, b: &Token) -> bool {
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Parses a hex string, e.g. the shared secret from the environment.
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -0,0 +1,86 @@
+//! `relay-server serve <bind-addr>` runs the relay.
+//! `relay-server issue <program-data>` prints the players' tokens for a
+//! `MatchFound` event taken from a `Program data:` log line.
+//!
+//! Both read the shared secret, hex-encoded, from `RELAY_SECRET`.
+
+use std::env;
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use relay_server::{decode_hex, encode_hex, MatchFound, Relay, RelayConfig};
+
+const USAGE: &str = "usage: relay-server serve <bind-addr> | relay-server issue <program-data>";
+
+/// How often idle matches are swept.
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(5);
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let Some(secret) = env::var("RELAY_SECRET")
+        .ok()
+        .and_then(|hex| decode_hex(&hex))
+    else {
+        eprintln!("RELAY_SECRET must be set to a hex-encoded secret");
+        return ExitCode::FAILURE;
+    };
+    let result = match args.as_slice() {
+        [command, addr] if command == "serve" => serve(addr, secret),
+        [command, data] if command == "issue" => issue(data, &secret),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn issue(data: &str, secret: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+    let event = MatchFound::from_program_data(data)?;
+    for (side, (player, token)) in event.tokens(secret).iter().enumerate() {
+        println!(
+            "{} {} {} {}",
+            event.match_id,
+            side,
+            player,
+            encode_hex(token)
+        );
+    }
+    Ok(())
+}
+
+fn serve(addr: &str, secret: Vec<u8>) -> Result<(), Box<dyn std::error::Error>> {
+    let socket = UdpSocket::bind(addr)?;
+    socket.set_read_timeout(Some(EXPIRE_INTERVAL))?;
+    println!("relay listening on {}", socket.local_addr()?);
+
+    let mut relay = Relay::new(secret, RelayConfig::default());
+    let start = Instant::now();
+    let mut last_expire = start;
+    let mut buf = [0; 2048];
+    loop {
+        match socket.recv_from(&mut buf) {
+            Ok((len, from)) => {
+                let now_ms = start.elapsed().as_millis() as u64;
+                for (to, packet) in relay.handle(now_ms, from, &buf[..len]) {
+                    // Delivery is best effort; the clients resend.
+                    let _ = socket.send_to(&packet, to);
+                }
+            }
+            Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(err) => return Err(err.into()),
+        }
+        if last_expire.elapsed() >= EXPIRE_INTERVAL {
+            relay.expire(start.elapsed().as_millis() as u64);
+            last_expire = Instant::now();
+        }
+    }
+}
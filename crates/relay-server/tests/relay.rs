@@ -0,0 +1,190 @@
+use std::net::SocketAddr;
+
+use base64::Engine;
+use relay_server::{
+    decode_hex, encode_hex, match_token, MatchFound, Message, RejectReason, Relay, RelayConfig,
+};
+
+const SECRET: &[u8] = b"test secret";
+
+fn addr(s: &str) -> SocketAddr {
+    s.parse().unwrap()
+}
+
+fn register(match_id: u64, side: u8) -> Vec<u8> {
+    Message::Register {
+        match_id,
+        side,
+        token: match_token(SECRET, match_id, side),
+    }
+    .encode()
+}
+
+fn decoded(out: Vec<(SocketAddr, Vec<u8>)>) -> Vec<(SocketAddr, Message)> {
+    out.into_iter()
+        .map(|(to, packet)| (to, Message::decode(&packet).unwrap()))
+        .collect()
+}
+
+#[test]
+fn both_players_learn_each_others_address() {
+    let mut relay = Relay::new(SECRET.to_vec(), RelayConfig::default());
+    let a = addr("203.0.113.5:40000");
+    let b = addr("[2001:db8::7]:51000");
+    assert!(relay.handle(0, a, &register(9, 0)).is_empty());
+    assert_eq!(
+        decoded(relay.handle(10, b, &register(9, 1))),
+        [(a, Message::Peer(b)), (b, Message::Peer(a))]
+    );
+}
+
+#[test]
+fn relays_between_registered_players() {
+    let mut relay = Relay::new(SECRET.to_vec(), RelayConfig::default());
+    let a = addr("10.0.0.1:1000");
+    let b = addr("10.0.0.2:2000");
+    relay.handle(0, a, &register(1, 0));
+    // Nobody to forward to yet.
+    assert!(relay
+        .handle(1, a, &Message::Relay(vec![1]).encode())
+        .is_empty());
+    relay.handle(2, b, &register(1, 1));
+
+    let out = relay.handle(3, a, &Message::Relay(vec![1, 2, 3]).encode());
+    assert_eq!(decoded(out), [(b, Message::Relay(vec![1, 2, 3]))]);
+    let out = relay.handle(4, b, &Message::Relay(vec![4]).encode());
+    assert_eq!(decoded(out), [(a, Message::Relay(vec![4]))]);
+    assert_eq!(relay.stats().relayed_packets, 2);
+    assert_eq!(relay.stats().relayed_bytes, 4);
+}
+
+#[test]
+fn rejects_bad_tokens_and_unregistered_senders() {
+    let mut relay = Relay::new(SECRET.to_vec(), RelayConfig::default());
+    let a = addr("10.0.0.1:1000");
+    // A token for the other side, or another match, does not work.
+    let forged = Message::Register {
+        match_id: 5,
+        side: 0,
+        token: match_token(SECRET, 5, 1),
+    };
+    assert_eq!(
+        decoded(relay.handle(0, a, &forged.encode())),
+        [(a, Message::Rejected(RejectReason::BadToken))]
+    );
+    let other_secret = Message::Register {
+        match_id: 5,
+        side: 0,
+        token: match_token(b"other", 5, 0),
+    };
+    assert_eq!(relay.handle(0, a, &other_secret.encode()).len(), 1);
+    assert_eq!(relay.match_count(), 0);
+
+    assert!(relay
+        .handle(1, a, &Message::Relay(vec![0]).encode())
+        .is_empty());
+    assert!(relay.handle(1, a, &[0xff, 1, 2]).is_empty());
+    assert_eq!(relay.stats().dropped, 4);
+}
+
+#[test]
+fn caps_matches_and_payloads() {
+    let config = RelayConfig {
+        max_matches: 1,
+        max_payload: 4,
+        ..RelayConfig::default()
+    };
+    let mut relay = Relay::new(SECRET.to_vec(), config);
+    let a = addr("10.0.0.1:1000");
+    let b = addr("10.0.0.2:2000");
+    relay.handle(0, a, &register(1, 0));
+    relay.handle(0, b, &register(1, 1));
+    let c = addr("10.0.0.3:3000");
+    assert_eq!(
+        decoded(relay.handle(0, c, &register(2, 0))),
+        [(c, Message::Rejected(RejectReason::Full))]
+    );
+    assert!(relay
+        .handle(0, a, &Message::Relay(vec![0; 5]).encode())
+        .is_empty());
+}
+
+#[test]
+fn rebinding_moves_the_registration() {
+    let mut relay = Relay::new(SECRET.to_vec(), RelayConfig::default());
+    let a = addr("10.0.0.1:1000");
+    let moved = addr("10.0.0.1:1001");
+    let b = addr("10.0.0.2:2000");
+    relay.handle(0, a, &register(1, 0));
+    relay.handle(0, b, &register(1, 1));
+    relay.handle(5, moved, &register(1, 0));
+
+    assert!(relay
+        .handle(6, a, &Message::Relay(vec![1]).encode())
+        .is_empty());
+    let out = relay.handle(6, b, &Message::Relay(vec![2]).encode());
+    assert_eq!(decoded(out), [(moved, Message::Relay(vec![2]))]);
+}
+
+#[test]
+fn idle_matches_expire() {
+    let config = RelayConfig {
+        idle_timeout_ms: 1000,
+        ..RelayConfig::default()
+    };
+    let mut relay = Relay::new(SECRET.to_vec(), config);
+    let a = addr("10.0.0.1:1000");
+    let b = addr("10.0.0.2:2000");
+    relay.handle(0, a, &register(1, 0));
+    relay.handle(0, b, &register(1, 1));
+    relay.handle(800, a, &Message::Relay(vec![1]).encode());
+    relay.expire(1500);
+    assert_eq!(relay.match_count(), 1);
+    relay.expire(1801);
+    assert_eq!(relay.match_count(), 0);
+    assert!(relay
+        .handle(1802, a, &Message::Relay(vec![1]).encode())
+        .is_empty());
+}
+
+#[test]
+fn tokens_come_from_the_match_found_event() {
+    let players = [[1u8; 32], [2u8; 32]];
+    let mut data = MatchFound::discriminator().to_vec();
+    data.extend_from_slice(&[7; 32]);
+    data.extend_from_slice(&42u64.to_le_bytes());
+    data.extend_from_slice(&players[0]);
+    data.extend_from_slice(&players[1]);
+    let encoded = base64::engine::general_purpose::STANDARD.encode(&data);
+
+    let event = MatchFound::from_program_data(&encoded).unwrap();
+    assert_eq!(event.match_id, 42);
+    assert_eq!(event.players, players);
+    let tokens = event.tokens(SECRET);
+    assert_eq!(tokens[0].0, bs58::encode([1u8; 32]).into_string());
+    assert_eq!(tokens[1].1, match_token(SECRET, 42, 1));
+
+    data[0] ^= 1;
+    let wrong_event = base64::engine::general_purpose::STANDARD.encode(&data);
+    assert!(MatchFound::from_program_data(&wrong_event).is_err());
+}
+
+#[test]
+fn hex_round_trips() {
+    let bytes = [0x00, 0x7f, 0xff, 0x10];
+    assert_eq!(decode_hex(&encode_hex(&bytes)).unwrap(), bytes);
+    assert_eq!(decode_hex("abc"), None);
+    assert_eq!(decode_hex("zz"), None);
+}
+
+#[test]
+fn messages_round_trip() {
+    for message in [
+        Message::Peer(addr("192.0.2.1:9")),
+        Message::Peer(addr("[::1]:65535")),
+        Message::Relay(Vec::new()),
+        Message::Rejected(RejectReason::Full),
+    ] {
+        assert_eq!(Message::decode(&message.encode()).unwrap(), message);
+    }
+}
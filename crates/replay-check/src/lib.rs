@@ -0,0 +1,117 @@
+//! Replay verification.
+//!
+//! `check` re-simulates a replay and compares the checksums embedded in it,
+//! taken on the recording machine, against the states simulated here. A
+//! desync does not heal: the checksum covers the whole state and every
+//! difference feeds into the next frame. So the mismatching checkpoints
+//! are a suffix of the list, and the first of them is found by bisection.
+//! The report then carries the simulated state at the last checkpoint that
+//! still matched and at the first that did not, to step through by hand.
+
+use core::fmt;
+
+use game_core::character::CharacterDef;
+use game_core::{checksum, step, GameState, MAX_PLAYERS};
+use replay::{Replay, ReplayError};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckOutcome {
+    /// Every embedded checksum matched.
+    Match {
+        frames: u32,
+        checkpoints: usize,
+        final_checksum: u64,
+    },
+    Diverged(Box<Divergence>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// First checkpoint frame whose checksum disagrees.
+    pub frame: u32,
+    /// Checksum the recording machine embedded.
+    pub expected: u64,
+    /// Checksum simulated here.
+    pub actual: u64,
+    /// Last checkpoint that matched, with the state simulated at it.
+    /// `None` when the very first checkpoint disagrees.
+    pub last_good: Option<(u32, GameState)>,
+    /// State simulated at `frame`.
+    pub state: GameState,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "diverged at frame {}: expected checksum {:#018x}, simulated {:#018x}",
+            self.frame, self.expected, self.actual
+        )?;
+        match &self.last_good {
+            Some((frame, state)) => {
+                writeln!(f, "last matching frame {}:", frame)?;
+                writeln!(f, "{:#?}", state)?;
+            }
+            None => writeln!(f, "no earlier checkpoint matched")?,
+        }
+        writeln!(f, "state at frame {}:", self.frame)?;
+        write!(f, "{:#?}", self.state)
+    }
+}
+
+/// Checks the replay with the built-in roster.
+pub fn check(replay: &Replay) -> Result<CheckOutcome, ReplayError> {
+    Ok(check_with(replay, replay.roster_defs()?))
+}
+
+/// Checks the replay with explicit character definitions.
+pub fn check_with(replay: &Replay, defs: [&CharacterDef; MAX_PLAYERS]) -> CheckOutcome {
+    // State at the start of every checkpoint frame, then the final state.
+    let mut states = Vec::with_capacity(replay.checksums.len());
+    let mut state = GameState::with_seed(defs[0], defs[1], replay.header.seed);
+    let mut checkpoints = replay.checksums.iter().peekable();
+    for (frame, inputs) in replay.frames.iter().enumerate() {
+        while checkpoints.next_if(|c| c.frame == frame as u32).is_some() {
+            states.push(state);
+        }
+        state = step(&state, *inputs, defs);
+    }
+    if checkpoints.next().is_some() {
+        states.push(state);
+    }
+
+    let mismatch = |index: usize| checksum(&states[index]) != replay.checksums[index].checksum;
+    let Some(index) = bisect(0, states.len(), mismatch) else {
+        return CheckOutcome::Match {
+            frames: replay.frames.len() as u32,
+            checkpoints: replay.checksums.len(),
+            final_checksum: checksum(&state),
+        };
+    };
+
+    let recorded = replay.checksums[index];
+    CheckOutcome::Diverged(Box::new(Divergence {
+        frame: recorded.frame,
+        expected: recorded.checksum,
+        actual: checksum(&states[index]),
+        last_good: index
+            .checked_sub(1)
+            .map(|good| (replay.checksums[good].frame, states[good])),
+        state: states[index],
+    }))
+}
+
+/// First index in `low..high` for which `bad` holds, assuming it holds for
+/// every index after that too.
+fn bisect(mut low: usize, mut high: usize, bad: impl Fn(usize) -> bool) -> Option<usize> {
+    let end = high;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if bad(mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    (low < end).then_some(low)
+}
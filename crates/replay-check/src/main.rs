@@ -0,0 +1,52 @@
+//! `replay-check <file>` re-simulates a replay against its embedded
+//! checksums and, on a divergence, prints where it starts along with the
+//! states on either side. Exits non-zero on a divergence or a bad file.
+
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+use replay::Replay;
+use replay_check::{check, CheckOutcome};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let [path] = args.as_slice() else {
+        eprintln!("usage: replay-check <file>");
+        return ExitCode::FAILURE;
+    };
+    let replay = match fs::read(path)
+        .map_err(|err| err.to_string())
+        .and_then(|bytes| Replay::decode(&bytes).map_err(|err| err.to_string()))
+    {
+        Ok(replay) => replay,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            return ExitCode::FAILURE;
+        }
+    };
+    match check(&replay) {
+        Ok(CheckOutcome::Match {
+            frames,
+            checkpoints,
+            final_checksum,
+        }) => {
+            if checkpoints == 0 {
+                println!("{}: no embedded checksums to compare", path);
+            }
+            println!(
+                "{}: {} frames, {} checksums matched, final checksum {:#018x}",
+                path, frames, checkpoints, final_checksum
+            );
+            ExitCode::SUCCESS
+        }
+        Ok(CheckOutcome::Diverged(divergence)) => {
+            println!("{}: {}", path, divergence);
+            ExitCode::FAILURE
+        }
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            ExitCode::FAILURE
+        }
+    }
+}
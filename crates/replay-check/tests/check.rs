@@ -0,0 +1,103 @@
+use game_core::{FrameChecksum, Input};
+use replay::{Replay, ReplayError};
+use replay_check::{check, CheckOutcome};
+
+fn recorded(frames: u32, interval: u32) -> Replay {
+    let mut replay = Replay::new(1, [0, 1], 42);
+    for frame in 0..frames {
+        let p1 = if frame % 40 < 25 {
+            Input::RIGHT
+        } else {
+            Input::ATTACK
+        };
+        let p2 = if frame % 30 < 10 {
+            Input::LEFT
+        } else {
+            Input::DEFEND
+        };
+        replay.record([p1, p2]);
+    }
+    replay.embed_checksums(interval).unwrap();
+    replay
+}
+
+#[test]
+fn matching_replay_passes() {
+    let replay = recorded(300, 10);
+    assert_eq!(replay.checksums.len(), 31);
+    let final_checksum = game_core::checksum(&replay.simulate().unwrap());
+    assert_eq!(
+        check(&replay).unwrap(),
+        CheckOutcome::Match {
+            frames: 300,
+            checkpoints: 31,
+            final_checksum,
+        }
+    );
+}
+
+#[test]
+fn finds_the_first_divergent_checkpoint() {
+    let mut replay = recorded(300, 10);
+    // Change an input after the checksums were taken: frame 123 runs
+    // differently here, so the checkpoint at 130 is the first to disagree.
+    replay.frames[123] = [Input::SPECIAL, Input::SPECIAL];
+
+    let CheckOutcome::Diverged(divergence) = check(&replay).unwrap() else {
+        panic!("divergence not found");
+    };
+    assert_eq!(divergence.frame, 130);
+    let (good_frame, _) = divergence.last_good.unwrap();
+    assert_eq!(good_frame, 120);
+    assert_ne!(divergence.expected, divergence.actual);
+    assert_eq!(divergence.actual, game_core::checksum(&divergence.state));
+    let report = divergence.to_string();
+    assert!(report.contains("diverged at frame 130"));
+    assert!(report.contains("last matching frame 120"));
+}
+
+#[test]
+fn divergence_at_the_first_checkpoint_has_no_good_state() {
+    let mut replay = recorded(50, 1);
+    // A different seed changes the RNG state before the first frame.
+    replay.header.seed ^= 1;
+    let CheckOutcome::Diverged(divergence) = check(&replay).unwrap() else {
+        panic!("divergence not found");
+    };
+    assert_eq!(divergence.frame, 0);
+    assert_eq!(divergence.last_good, None);
+}
+
+#[test]
+fn checksums_survive_encoding() {
+    let replay = recorded(100, 7);
+    let decoded = Replay::decode(&replay.encode()).unwrap();
+    assert_eq!(decoded, replay);
+    assert_eq!(decoded.checksums.last().unwrap().frame, 98);
+}
+
+#[test]
+fn rejects_out_of_order_checksums() {
+    let mut replay = recorded(20, 0);
+    replay.record_checksum(FrameChecksum {
+        frame: 5,
+        checksum: 0,
+    });
+    replay.record_checksum(FrameChecksum {
+        frame: 5,
+        checksum: 0,
+    });
+    assert_eq!(
+        Replay::decode(&replay.encode()),
+        Err(ReplayError::InvalidChecksumFrame(5))
+    );
+}
+
+#[test]
+fn version_one_replays_still_decode() {
+    let mut replay = recorded(20, 0);
+    replay.header.version = 1;
+    let decoded = Replay::decode(&replay.encode()).unwrap();
+    assert!(decoded.checksums.is_empty());
+    assert_eq!(decoded.frames, replay.frames);
+}
@@ -0,0 +1,170 @@
+//! Golden replays with their final state hashes, recorded once and
+//! committed. Every platform the game ships on must reproduce the hashes
+//! bit for bit; CI runs this on x86_64, aarch64 and wasm32. The replays are
+//! compiled in so the wasm runner needs no filesystem access.
+//!
+//! After an intentional simulation change, regenerate the corpus with
+//! `cargo test --test golden -- --ignored` and commit the result.
+
+use game_core::{checksum, state_hash, Input, Rng};
+use replay::Replay;
+use replay_check::{check, CheckOutcome};
+
+/// Frames between embedded checksums.
+const CHECKSUM_INTERVAL: u32 = 60;
+
+struct Golden {
+    name: &'static str,
+    characters: [u8; 2],
+    seed: u64,
+    frames: u32,
+    /// Chance in 16 that a player changes input on a frame; 16 mashes.
+    change_odds: u32,
+    bytes: &'static [u8],
+}
+
+const CORPUS: [Golden; 5] = [
+    Golden {
+        name: "ronin_mirror",
+        characters: [0, 0],
+        seed: 1,
+        frames: 1200,
+        change_odds: 3,
+        bytes: include_bytes!("golden/ronin_mirror.dfgr"),
+    },
+    Golden {
+        name: "knight_mirror",
+        characters: [1, 1],
+        seed: 2,
+        frames: 1200,
+        change_odds: 3,
+        bytes: include_bytes!("golden/knight_mirror.dfgr"),
+    },
+    Golden {
+        name: "ronin_vs_knight",
+        characters: [0, 1],
+        seed: 0x5eed_0000_0000_0003,
+        frames: 3600,
+        change_odds: 2,
+        bytes: include_bytes!("golden/ronin_vs_knight.dfgr"),
+    },
+    Golden {
+        name: "knight_vs_ronin",
+        characters: [1, 0],
+        seed: u64::MAX,
+        frames: 3600,
+        change_odds: 4,
+        bytes: include_bytes!("golden/knight_vs_ronin.dfgr"),
+    },
+    Golden {
+        name: "mashing",
+        characters: [0, 1],
+        seed: 0,
+        frames: 2400,
+        change_odds: 16,
+        bytes: include_bytes!("golden/mashing.dfgr"),
+    },
+];
+
+/// `name frames checksum state_hash` per line.
+const EXPECTED: &str = include_str!("golden/expected.txt");
+
+const MOVES: [Input; 12] = [
+    Input::NONE,
+    Input::RIGHT,
+    Input::LEFT,
+    Input::DOWN,
+    Input::JUMP,
+    Input::ATTACK,
+    Input::SPECIAL,
+    Input::DEFEND,
+    Input::GRAB,
+    Input::from_bits_truncate(Input::RIGHT.bits() | Input::ATTACK.bits()),
+    Input::from_bits_truncate(Input::DOWN.bits() | Input::SPECIAL.bits()),
+    Input::from_bits_truncate(Input::JUMP.bits() | Input::LEFT.bits()),
+];
+
+fn record(golden: &Golden) -> Replay {
+    let mut rng = Rng::new(golden.seed ^ 0x676f_6c64);
+    let mut replay = Replay::new(1, golden.characters, golden.seed);
+    let mut held = [Input::NONE; 2];
+    for _ in 0..golden.frames {
+        for input in &mut held {
+            if golden.change_odds >= 16 {
+                *input = Input::from_bits_truncate(rng.next_u32() as u16);
+            } else if rng.below(16) < golden.change_odds {
+                *input = MOVES[rng.below(MOVES.len() as u32) as usize];
+            }
+        }
+        replay.record(held);
+    }
+    replay.embed_checksums(CHECKSUM_INTERVAL).unwrap();
+    replay
+}
+
+fn expected(name: &str) -> (u32, u64, u32) {
+    let line = EXPECTED
+        .lines()
+        .find(|line| line.split(' ').next() == Some(name))
+        .unwrap_or_else(|| panic!("no expected hashes for {}", name));
+    let fields: Vec<&str> = line.split(' ').collect();
+    (
+        fields[1].parse().unwrap(),
+        u64::from_str_radix(fields[2].trim_start_matches("0x"), 16).unwrap(),
+        u32::from_str_radix(fields[3].trim_start_matches("0x"), 16).unwrap(),
+    )
+}
+
+#[test]
+fn golden_replays_reproduce_recorded_hashes() {
+    for golden in &CORPUS {
+        let replay = Replay::decode(golden.bytes).unwrap();
+        let (frames, expected_checksum, expected_hash) = expected(golden.name);
+        match check(&replay).unwrap() {
+            CheckOutcome::Match {
+                frames: simulated,
+                final_checksum,
+                ..
+            } => {
+                assert_eq!(simulated, frames, "{}", golden.name);
+                assert_eq!(final_checksum, expected_checksum, "{}", golden.name);
+            }
+            CheckOutcome::Diverged(divergence) => panic!("{}: {}", golden.name, divergence),
+        }
+        let state = replay.simulate().unwrap();
+        assert_eq!(state_hash(&state), expected_hash, "{}", golden.name);
+    }
+}
+
+/// The recorded files come from the generator; a mismatch means the
+/// generator or the format changed without regenerating the corpus.
+#[test]
+fn golden_files_match_the_generator() {
+    for golden in &CORPUS {
+        assert!(
+            record(golden).encode() == golden.bytes,
+            "{} is stale",
+            golden.name
+        );
+    }
+}
+
+#[test]
+#[ignore = "rewrites the committed corpus"]
+fn regenerate_golden_corpus() {
+    let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut expected = String::new();
+    for golden in &CORPUS {
+        let replay = record(golden);
+        let state = replay.simulate().unwrap();
+        std::fs::write(dir.join(format!("{}.dfgr", golden.name)), replay.encode()).unwrap();
+        expected.push_str(&format!(
+            "{} {} {:#018x} {:#010x}\n",
+            golden.name,
+            golden.frames,
+            checksum(&state),
+            state_hash(&state)
+        ));
+    }
+    std::fs::write(dir.join("expected.txt"), expected).unwrap();
+}
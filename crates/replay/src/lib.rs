@@ -0,0 +1,348 @@
+//! Binary replay format.
+//!
+//! Layout (all integers little-endian):
+//!
+//! ```text
+//! header  magic        [u8; 4]  "DFGR"
+//!         version      u16      format version, currently 2
+//!         game_version u32      simulation build the match was played on
+//!         characters   [u8; 2]  character id per player
+//!         seed         u64      match seed, feeds the simulation RNG
+//!         frame_count  u32
+//! body    frame_count x [u16; 2] per-frame input bits for each player
+//! sums    count        u32      version 2 only
+//!         count x (frame u32, checksum u64)
+//! ```
+//!
+//! The checksums are `game_core::checksum` of the state at the start of
+//! each listed frame on the recording machine, in increasing frame order,
+//! so a re-simulation can tell where it stopped matching. Version 1 files
+//! carry none.
+//!
+//! The SHA-256 of the encoded bytes is what `replay_registry::finalize_replay`
+//! anchors on-chain, so anyone holding the file can re-simulate it and check
+//! the result against the stored hash. `match_result::record_match` also
+//! commits to the checksums' Merkle root, so a `dispute` can check the
+//! key frames around a window against the recorded match.
+
+use core::fmt;
+
+use game_core::character::{roster, CharacterDef};
+use game_core::{checksum, step, FrameChecksum, GameState, Input, MAX_PLAYERS};
+use sha2::{Digest, Sha256};
+
+pub const MAGIC: [u8; 4] = *b"DFGR";
+pub const FORMAT_VERSION: u16 = 2;
+/// Oldest version `decode` still reads.
+pub const MIN_FORMAT_VERSION: u16 = 1;
+pub const HEADER_LEN: usize = 4 + 2 + 4 + MAX_PLAYERS + 8 + 4;
+pub const FRAME_LEN: usize = 2 * MAX_PLAYERS;
+pub const CHECKSUM_LEN: usize = 4 + 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplayHeader {
+    pub version: u16,
+    pub game_version: u32,
+    pub characters: [u8; MAX_PLAYERS],
+    pub seed: u64,
+    pub frame_count: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub frames: Vec<[Input; MAX_PLAYERS]>,
+    pub checksums: Vec<FrameChecksum>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayError {
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u16),
+    /// Header frame count disagrees with the body length.
+    FrameCountMismatch {
+        header: u32,
+        body: usize,
+    },
+    UnknownCharacter(u8),
+    /// A frame sets reserved input bits.
+    InvalidInput(u16),
+    /// A checksum is past the last frame or not after the one before it.
+    InvalidChecksumFrame(u32),
+    TrailingData,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Truncated => write!(f, "replay data truncated"),
+            ReplayError::BadMagic => write!(f, "not a replay file"),
+            ReplayError::UnsupportedVersion(v) => write!(f, "unsupported replay version {}", v),
+            ReplayError::FrameCountMismatch { header, body } => write!(
+                f,
+                "header declares {} frames but body holds {}",
+                header, body
+            ),
+            ReplayError::UnknownCharacter(id) => write!(f, "unknown character id {}", id),
+            ReplayError::InvalidInput(bits) => write!(f, "invalid input bits {:#06x}", bits),
+            ReplayError::InvalidChecksumFrame(frame) => {
+                write!(f, "checksum for frame {} is out of order", frame)
+            }
+            ReplayError::TrailingData => write!(f, "unexpected data after the checksums"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+impl Replay {
+    pub fn new(game_version: u32, characters: [u8; MAX_PLAYERS], seed: u64) -> Self {
+        Replay {
+            header: ReplayHeader {
+                version: FORMAT_VERSION,
+                game_version,
+                characters,
+                seed,
+                frame_count: 0,
+            },
+            frames: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    /// Appends one frame of inputs.
+    pub fn record(&mut self, inputs: [Input; MAX_PLAYERS]) {
+        self.frames.push(inputs);
+        self.header.frame_count = self.frames.len() as u32;
+    }
+
+    /// Appends the recording machine's checksum of the state at the start
+    /// of `checksum.frame`. Checksums must be recorded in frame order.
+    pub fn record_checksum(&mut self, checksum: FrameChecksum) {
+        self.checksums.push(checksum);
+    }
+
+    /// Replaces the checksums with ones computed here, every `interval`
+    /// frames from frame 0 through the end of the replay.
+    pub fn embed_checksums(&mut self, interval: u32) -> Result<(), ReplayError> {
+        let defs = self.roster_defs()?;
+        let mut state = GameState::with_seed(defs[0], defs[1], self.header.seed);
+        self.checksums.clear();
+        for frame in 0..=self.frames.len() as u32 {
+            if interval != 0 && frame.is_multiple_of(interval) {
+                self.checksums.push(FrameChecksum {
+                    frame,
+                    checksum: checksum(&state),
+                });
+            }
+            if let Some(inputs) = self.frames.get(frame as usize) {
+                state = step(&state, *inputs, defs);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(
+            HEADER_LEN + self.frames.len() * FRAME_LEN + 4 + self.checksums.len() * CHECKSUM_LEN,
+        );
+        out.extend_from_slice(&MAGIC);
+        out.extend_from_slice(&self.header.version.to_le_bytes());
+        out.extend_from_slice(&self.header.game_version.to_le_bytes());
+        out.extend_from_slice(&self.header.characters);
+        out.extend_from_slice(&self.header.seed.to_le_bytes());
+        out.extend_from_slice(&(self.frames.len() as u32).to_le_bytes());
+        for frame in &self.frames {
+            for input in frame {
+                out.extend_from_slice(&input.to_le_bytes());
+            }
+        }
+        if self.header.version >= 2 {
+            out.extend_from_slice(&(self.checksums.len() as u32).to_le_bytes());
+            for checksum in &self.checksums {
+                out.extend_from_slice(&checksum.frame.to_le_bytes());
+                out.extend_from_slice(&checksum.checksum.to_le_bytes());
+            }
+        }
+        out
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, ReplayError> {
+        let mut reader = Reader { bytes, pos: 0 };
+        if reader.take::<4>()? != MAGIC {
+            return Err(ReplayError::BadMagic);
+        }
+        let version = u16::from_le_bytes(reader.take()?);
+        if !(MIN_FORMAT_VERSION..=FORMAT_VERSION).contains(&version) {
+            return Err(ReplayError::UnsupportedVersion(version));
+        }
+        let game_version = u32::from_le_bytes(reader.take()?);
+        let characters = reader.take::<MAX_PLAYERS>()?;
+        let seed = u64::from_le_bytes(reader.take()?);
+        let frame_count = u32::from_le_bytes(reader.take()?);
+
+        // Version 1 files end with the inputs.
+        let body = if version == 1 {
+            &bytes[reader.pos..]
+        } else {
+            let end = reader.pos + frame_count as usize * FRAME_LEN;
+            bytes.get(reader.pos..end).ok_or(ReplayError::Truncated)?
+        };
+        if !body.len().is_multiple_of(FRAME_LEN) || body.len() / FRAME_LEN != frame_count as usize {
+            return Err(ReplayError::FrameCountMismatch {
+                header: frame_count,
+                body: body.len() / FRAME_LEN,
+            });
+        }
+
+        let frames = body
+            .chunks_exact(FRAME_LEN)
+            .map(|chunk| {
+                let mut inputs = [Input::NONE; MAX_PLAYERS];
+                for (input, bytes) in inputs.iter_mut().zip(chunk.chunks_exact(2)) {
+                    let bits = u16::from_le_bytes([bytes[0], bytes[1]]);
+                    *input = Input::from_bits(bits).ok_or(ReplayError::InvalidInput(bits))?;
+                }
+                Ok(inputs)
+            })
+            .collect::<Result<_, _>>()?;
+        reader.pos += body.len();
+
+        let mut checksums = Vec::new();
+        if version >= 2 {
+            let count = u32::from_le_bytes(reader.take()?);
+            for _ in 0..count {
+                let frame = u32::from_le_bytes(reader.take()?);
+                let checksum = u64::from_le_bytes(reader.take()?);
+                let in_order = checksums
+                    .last()
+                    .is_none_or(|last: &FrameChecksum| last.frame < frame);
+                if !in_order || frame > frame_count {
+                    return Err(ReplayError::InvalidChecksumFrame(frame));
+                }
+                checksums.push(FrameChecksum { frame, checksum });
+            }
+            if reader.pos != bytes.len() {
+                return Err(ReplayError::TrailingData);
+            }
+        }
+
+        Ok(Replay {
+            header: ReplayHeader {
+                version,
+                game_version,
+                characters,
+                seed,
+                frame_count,
+            },
+            frames,
+            checksums,
+        })
+    }
+
+    /// SHA-256 of the encoded replay, as anchored on-chain.
+    pub fn hash(&self) -> [u8; 32] {
+        Sha256::digest(self.encode()).into()
+    }
+
+    /// Merkle root over `key_frame_leaf` of each checksum, in order, with
+    /// each pair hashed in sorted order and an odd node carried up as is.
+    /// Zero for a replay without checksums.
+    pub fn checksum_root(&self) -> [u8; 32] {
+        let mut level = self.leaves();
+        if level.is_empty() {
+            return [0; 32];
+        }
+        while level.len() > 1 {
+            level = level.chunks(2).map(parent).collect();
+        }
+        level[0]
+    }
+
+    /// Proof of the checksum at `frame` against `checksum_root`, or `None`
+    /// if the replay has no checksum for that frame.
+    pub fn checksum_proof(&self, frame: u32) -> Option<Vec<[u8; 32]>> {
+        let mut index = self.checksums.iter().position(|c| c.frame == frame)?;
+        let mut level = self.leaves();
+        let mut proof = Vec::new();
+        while level.len() > 1 {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            level = level.chunks(2).map(parent).collect();
+            index /= 2;
+        }
+        Some(proof)
+    }
+
+    fn leaves(&self) -> Vec<[u8; 32]> {
+        self.checksums.iter().map(key_frame_leaf).collect()
+    }
+
+    /// Re-simulates the replay from the initial state with the built-in roster.
+    pub fn simulate(&self) -> Result<GameState, ReplayError> {
+        let defs = self.roster_defs()?;
+        Ok(self.simulate_with(defs))
+    }
+
+    /// Re-simulates the replay with explicit character definitions.
+    pub fn simulate_with(&self, defs: [&CharacterDef; MAX_PLAYERS]) -> GameState {
+        let mut state = GameState::with_seed(defs[0], defs[1], self.header.seed);
+        for inputs in &self.frames {
+            state = step(&state, *inputs, defs);
+        }
+        state
+    }
+
+    /// Built-in roster entries for the header's character ids.
+    pub fn roster_defs(&self) -> Result<[&'static CharacterDef<'static>; MAX_PLAYERS], ReplayError> {
+        let [p1, p2] = self.header.characters;
+        Ok([
+            roster(p1).ok_or(ReplayError::UnknownCharacter(p1))?,
+            roster(p2).ok_or(ReplayError::UnknownCharacter(p2))?,
+        ])
+    }
+}
+
+/// Leaf of `Replay::checksum_root` for one checksum, matching
+/// `dispute::key_frame_leaf`.
+pub fn key_frame_leaf(checksum: &FrameChecksum) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(checksum.frame.to_le_bytes());
+    hasher.update(checksum.checksum.to_le_bytes());
+    hasher.finalize().into()
+}
+
+/// Node above `pair`; a lone node is its own parent.
+fn parent(pair: &[[u8; 32]]) -> [u8; 32] {
+    match pair {
+        [a, b] => {
+            let (low, high) = if a <= b { (a, b) } else { (b, a) };
+            let mut hasher = Sha256::new();
+            hasher.update(low);
+            hasher.update(high);
+            hasher.finalize().into()
+        }
+        _ => pair[0],
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ReplayError> {
+        let end = self.pos + N;
+        let slice = self
+            .bytes
+            .get(self.pos..end)
+            .ok_or(ReplayError::Truncated)?;
+        self.pos = end;
+        let mut out = [0; N];
+        out.copy_from_slice(slice);
+        Ok(out)
+    }
+}
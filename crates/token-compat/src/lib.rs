@@ -0,0 +1,158 @@
+//! Token flows that work with both the SPL Token program and Token-2022,
+//! shared by every program that escrows or pays out tokens.
+//!
+//! Token accounts and mints are `anchor_spl::token_interface` types, so
+//! either token program can own them, and every transfer is a
+//! `transfer_checked` against the mint. A Token-2022 transfer fee is
+//! withheld from what the destination receives: `transfer` returns the
+//! amount that actually arrived, which is what deposits should be credited
+//! with, and `gross_amount` inverts the fee for a deposit that has to
+//! arrive at a set amount. Withheld fees also keep a token account from
+//! closing, so `close` harvests them to the mint first.
+//!
+//! Mints whose extensions would let someone other than this program move,
+//! freeze or gate the tokens it holds are refused by `supported_mint`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::invoke;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::{
+    instruction::harvest_withheld_tokens_to_mint, TransferFeeConfig,
+};
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use anchor_spl::token_2022::spl_token_2022::state::Mint as MintState;
+use anchor_spl::token_interface::{
+    self, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+/// Mint extensions the token flows can't support. A transfer hook runs a
+/// program whose extra accounts aren't validated here, a permanent delegate
+/// can pull tokens out of escrow, non-transferable tokens can't be paid out,
+/// a default frozen state freezes new escrows, and confidential balances
+/// can't be seen or paid from.
+pub const UNSUPPORTED_EXTENSIONS: [ExtensionType; 5] = [
+    ExtensionType::TransferHook,
+    ExtensionType::PermanentDelegate,
+    ExtensionType::NonTransferable,
+    ExtensionType::DefaultAccountState,
+    ExtensionType::ConfidentialTransferMint,
+];
+
+/// Whether the mint is free of `UNSUPPORTED_EXTENSIONS`. SPL Token mints
+/// have no extensions.
+pub fn supported_mint(mint: &InterfaceAccount<Mint>) -> Result<bool> {
+    let info = mint.to_account_info();
+    if *info.owner != anchor_spl::token_2022::ID {
+        return Ok(true);
+    }
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    let extensions = state.get_extension_types()?;
+    Ok(!extensions
+        .iter()
+        .any(|e| UNSUPPORTED_EXTENSIONS.contains(e)))
+}
+
+/// The part of a transfer of `amount` that Token-2022 withholds this epoch.
+pub fn transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let info = mint.to_account_info();
+    if *info.owner != anchor_spl::token_2022::ID {
+        return Ok(0);
+    }
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    match state.get_extension::<TransferFeeConfig>() {
+        Ok(config) => Ok(config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?),
+        Err(_) => Ok(0),
+    }
+}
+
+/// What to send so that `net` arrives after this epoch's fee. Rounding in
+/// the fee can make a larger amount arrive, never a smaller one.
+pub fn gross_amount(mint: &InterfaceAccount<Mint>, net: u64) -> Result<u64> {
+    let info = mint.to_account_info();
+    if *info.owner != anchor_spl::token_2022::ID {
+        return Ok(net);
+    }
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    match state.get_extension::<TransferFeeConfig>() {
+        Ok(config) => Ok(config
+            .get_epoch_fee(Clock::get()?.epoch)
+            .calculate_pre_fee_amount(net)
+            .ok_or(ProgramError::ArithmeticOverflow)?),
+        Err(_) => Ok(net),
+    }
+}
+
+fn has_transfer_fee(mint: &InterfaceAccount<Mint>) -> Result<bool> {
+    let info = mint.to_account_info();
+    if *info.owner != anchor_spl::token_2022::ID {
+        return Ok(false);
+    }
+    let data = info.try_borrow_data()?;
+    let state = StateWithExtensions::<MintState>::unpack(&data)?;
+    Ok(state.get_extension::<TransferFeeConfig>().is_ok())
+}
+
+/// Transfers `amount` from `from`, signed with `signer_seeds` when the
+/// authority is a PDA, and returns what `to` received after the fee.
+pub fn transfer<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    from: &InterfaceAccount<'info, TokenAccount>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<u64> {
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: from.to_account_info(),
+                mint: mint.to_account_info(),
+                to: to.to_account_info(),
+                authority: authority.clone(),
+            },
+            signer_seeds,
+        ),
+        amount,
+        mint.decimals,
+    )?;
+    Ok(amount - transfer_fee(mint, amount)?)
+}
+
+/// Closes an empty token account, returning its rent to `destination`.
+/// Fees withheld in it are harvested to the mint, which must be writable.
+pub fn close<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    account: &InterfaceAccount<'info, TokenAccount>,
+    mint: &InterfaceAccount<'info, Mint>,
+    destination: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    if has_transfer_fee(mint)? {
+        invoke(
+            &harvest_withheld_tokens_to_mint(token_program.key, &mint.key(), &[&account.key()])?,
+            &[
+                mint.to_account_info(),
+                account.to_account_info(),
+                token_program.to_account_info(),
+            ],
+        )?;
+    }
+    token_interface::close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: account.to_account_info(),
+            destination: destination.clone(),
+            authority: authority.clone(),
+        },
+        signer_seeds,
+    ))
+}
@@ -0,0 +1,319 @@
+// Rust Anchor Program for Achievement Badges
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use checked_math::{increment, Overflow};
+use profile::program::Profile;
+use profile::{ProfileConfig, PROFILE_WRITER_SEED};
+
+declare_id!("ACHIEVEMENTS_PROGRAM_ID_HERE");
+
+/// Achievement ids awarded by the other game programs.
+pub const FIRST_TOURNAMENT_WIN: u16 = 1;
+pub const WIN_STREAK: u16 = 2;
+
+#[program]
+pub mod achievements {
+    use super::*;
+
+    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.layout_version = AchievementsConfig::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.bump = ctx.bumps.config;
+        Ok(())
+    }
+
+    /// Registers an achievement that only `issuer_program` can award, by
+    /// signing with its `achievement_issuer` PDA.
+    pub fn define_achievement(
+        ctx: Context<DefineAchievement>,
+        achievement_id: u16,
+        name: String,
+        issuer_program: Pubkey,
+    ) -> Result<()> {
+        require_gte!(Achievement::MAX_NAME_LEN, name.len(), AchievementError::NameTooLong);
+        let achievement = &mut ctx.accounts.achievement;
+        achievement.layout_version = Achievement::LAYOUT_VERSION;
+        achievement.achievement_id = achievement_id;
+        achievement.name = name;
+        achievement.issuer_program = issuer_program;
+        achievement.awarded_count = 0;
+        achievement.bump = ctx.bumps.achievement;
+        Ok(())
+    }
+
+    /// Creates the player's badge. Badges are PDAs of this program with no
+    /// transfer or close instruction, so they stay bound to the player; each
+    /// achievement is awarded to a player at most once. A player with a
+    /// profile also gets the badge recorded on it.
+    pub fn award_badge(ctx: Context<AwardBadge>) -> Result<()> {
+        let achievement = &mut ctx.accounts.achievement;
+        increment(&mut achievement.awarded_count).map_err(AchievementError::from)?;
+
+        let badge = &mut ctx.accounts.badge;
+        badge.layout_version = Badge::LAYOUT_VERSION;
+        badge.player = *ctx.accounts.player.key;
+        badge.achievement_id = achievement.achievement_id;
+        badge.awarded_at = Clock::get()?.unix_timestamp;
+        badge.bump = ctx.bumps.badge;
+
+        emit!(BadgeAwarded {
+            player: badge.player,
+            achievement_id: badge.achievement_id,
+            timestamp: badge.awarded_at,
+        });
+
+        let accounts = &ctx.accounts;
+        if accounts.profile.owner != &profile::ID {
+            return Ok(());
+        }
+        let seeds = &[PROFILE_WRITER_SEED, &[ctx.bumps.profile_writer]];
+        profile::cpi::record_badge(
+            CpiContext::new_with_signer(
+                accounts.profile_program.to_account_info(),
+                profile::cpi::accounts::WriteProfile {
+                    writer: accounts.profile_writer.to_account_info(),
+                    config: accounts.profile_config.to_account_info(),
+                    profile: accounts.profile.to_account_info(),
+                },
+                &[&seeds[..]],
+            ),
+            crate::ID,
+            accounts.achievement.achievement_id,
+        )
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<AchievementsConfig>(AchievementsConfig::discriminator()),
+            Layout::new::<Achievement>(Achievement::discriminator()),
+            Layout::new::<Badge>(Badge::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<AchievementError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"achievements_config"],
+        bump,
+        space = AchievementsConfig::SPACE,
+        payer = admin,
+    )]
+    pub config: Account<'info, AchievementsConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(achievement_id: u16)]
+pub struct DefineAchievement<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"achievements_config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, AchievementsConfig>,
+    #[account(
+        init,
+        seeds = [b"achievement".as_ref(), &achievement_id.to_le_bytes()],
+        bump,
+        space = Achievement::SPACE,
+        payer = admin,
+    )]
+    pub achievement: Account<'info, Achievement>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AwardBadge<'info> {
+    /// PDA of the achievement's issuer program, verified by seeds
+    #[account(
+        seeds = [b"achievement_issuer"],
+        bump,
+        seeds::program = achievement.issuer_program,
+    )]
+    pub issuer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"achievement", &achievement.achievement_id.to_le_bytes()],
+        bump = achievement.bump,
+    )]
+    pub achievement: Account<'info, Achievement>,
+    /// Any wallet can hold a badge
+    pub player: SystemAccount<'info>,
+    #[account(
+        init,
+        seeds = [
+            b"badge".as_ref(),
+            &achievement.achievement_id.to_le_bytes(),
+            player.key.as_ref(),
+        ],
+        bump,
+        space = Badge::SPACE,
+        payer = payer,
+    )]
+    pub badge: Account<'info, Badge>,
+    /// CHECK: the player's profile PDA, verified by seeds; only written when
+    /// the profile program owns it, i.e. the player made a profile
+    #[account(
+        mut,
+        seeds = [b"profile", player.key.as_ref()],
+        bump,
+        seeds::program = profile::ID,
+    )]
+    pub profile: UncheckedAccount<'info>,
+    #[account(seeds = [b"profile_config"], bump = profile_config.bump, seeds::program = profile::ID)]
+    pub profile_config: Account<'info, ProfileConfig>,
+    /// CHECK: signing PDA for `profile::record_badge`
+    #[account(seeds = [PROFILE_WRITER_SEED], bump)]
+    pub profile_writer: UncheckedAccount<'info>,
+    pub profile_program: Program<'info, Profile>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct AchievementsConfig {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    pub bump: u8,
+}
+
+impl AchievementsConfig {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 1; // bump
+}
+
+impl Versioned for AchievementsConfig {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[account]
+pub struct Achievement {
+    pub layout_version: u8,
+    pub achievement_id: u16,
+    pub name: String,
+    /// Program whose `achievement_issuer` PDA may award this achievement.
+    pub issuer_program: Pubkey,
+    pub awarded_count: u32,
+    pub bump: u8,
+}
+
+impl Achievement {
+    pub const MAX_NAME_LEN: usize = 48;
+
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 2 // achievement_id
+        + 4 + Self::MAX_NAME_LEN // name
+        + 32 // issuer_program
+        + 4 // awarded_count
+        + 1; // bump
+}
+
+impl Versioned for Achievement {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[account]
+pub struct Badge {
+    pub layout_version: u8,
+    pub player: Pubkey,
+    pub achievement_id: u16,
+    pub awarded_at: i64,
+    pub bump: u8,
+}
+
+impl Badge {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // player
+        + 2 // achievement_id
+        + 8 // awarded_at
+        + 1; // bump
+}
+
+impl Versioned for Badge {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[event]
+pub struct BadgeAwarded {
+    pub player: Pubkey,
+    pub achievement_id: u16,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum AchievementError {
+    #[msg("Achievement name is too long")]
+    NameTooLong,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for AchievementError {
+    fn from(_: Overflow) -> Self {
+        AchievementError::Overflow
+    }
+}
+
+impl From<MigrationError> for AchievementError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => AchievementError::AlreadyMigrated,
+            _ => AchievementError::UnsupportedLayout,
+        }
+    }
+}
@@ -0,0 +1,711 @@
+// Rust Anchor Program for Spectator Betting
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{Layout, MigrationError, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use checked_math::{add_assign, Overflow};
+use dispute::{Dispute, DisputeConfig, DisputeStatus};
+use match_result::MatchAccount;
+
+
+declare_id!("BETTING_PROGRAM_ID_HERE");
+
+/// Basis points in 100%.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+/// Highest protocol fee the admin can set.
+pub const MAX_FEE_BPS: u16 = 1_000;
+/// Seconds after a market locks before it can be cancelled for want of a
+/// finalized result.
+pub const RESULT_TIMEOUT: i64 = 24 * 60 * 60;
+
+#[program]
+pub mod betting {
+    use super::*;
+
+    #[access_control(validate_mint(&ctx))]
+    pub fn initialize(ctx: Context<Initialize>, fee_bps: u16) -> Result<()> {
+        require_gte!(MAX_FEE_BPS, fee_bps, BettingError::FeeTooHigh);
+        let config = &mut ctx.accounts.config;
+        config.layout_version = BettingConfig::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.mint = ctx.accounts.mint.key();
+        config.treasury = ctx.accounts.treasury.key();
+        config.fee_bps = fee_bps;
+        config.bump = ctx.bumps.config;
+        Ok(())
+    }
+
+    /// Only applies to markets opened from now on.
+    pub fn set_fee(ctx: Context<UpdateConfig>, fee_bps: u16) -> Result<()> {
+        require_gte!(MAX_FEE_BPS, fee_bps, BettingError::FeeTooHigh);
+        ctx.accounts.config.fee_bps = fee_bps;
+        Ok(())
+    }
+
+    /// Opens betting on `match_id` between `players` until `lock_at`.
+    #[access_control(validate_market(&players, lock_at))]
+    pub fn open_market(
+        ctx: Context<OpenMarket>,
+        match_id: u64,
+        players: [Pubkey; 2],
+        lock_at: i64,
+    ) -> Result<()> {
+        let market = &mut ctx.accounts.market;
+        market.layout_version = Market::LAYOUT_VERSION;
+        market.match_id = match_id;
+        market.players = players;
+        market.lock_at = lock_at;
+        market.pools = [0; 2];
+        market.fee_bps = ctx.accounts.config.fee_bps;
+        market.fee = 0;
+        market.status = MarketStatus::Open;
+        market.winner = 0;
+        market.bump = ctx.bumps.market;
+        market.vault_bump = ctx.bumps.vault;
+
+        emit!(MarketOpened {
+            market: market.key(),
+            match_id,
+            players,
+            lock_at,
+        });
+
+        Ok(())
+    }
+
+    /// Deposits `amount` on `side` (an index into the market's players).
+    /// A spectator may add to their bet but not switch sides. The bet is
+    /// credited with what reaches the vault, after any transfer fee.
+    #[access_control(validate_bet(&ctx, side, amount))]
+    pub fn place_bet(ctx: Context<PlaceBet>, side: u8, amount: u64) -> Result<()> {
+        let amount = token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.bettor_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.mint,
+            &ctx.accounts.bettor,
+            &[],
+            amount,
+        )?;
+
+        let market = &mut ctx.accounts.market;
+        add_assign(&mut market.pools[side as usize], amount).map_err(BettingError::from)?;
+
+        let bet = &mut ctx.accounts.bet;
+        bet.layout_version = Bet::LAYOUT_VERSION;
+        bet.market = market.key();
+        bet.bettor = *ctx.accounts.bettor.key;
+        bet.side = side;
+        add_assign(&mut bet.amount, amount).map_err(BettingError::from)?;
+        bet.bump = ctx.bumps.bet;
+
+        emit!(BetPlaced {
+            market: market.key(),
+            bettor: bet.bettor,
+            side,
+            amount,
+            pools: market.pools,
+        });
+
+        Ok(())
+    }
+
+    /// Settles the market on the finalized match once the dispute challenge
+    /// window has passed, and sends the protocol fee on the losing pool to
+    /// the treasury. A dispute that was upheld refunds every bet instead; one
+    /// still open has to be resolved first. With no bets on one side there
+    /// is nothing to share, so that refunds too. Anyone may crank this.
+    #[access_control(validate_settle(&ctx))]
+    pub fn settle_market(ctx: Context<SettleMarket>) -> Result<()> {
+        let dispute_info = &ctx.accounts.dispute;
+        let upheld = if dispute_info.owner == &dispute::ID {
+            let dispute = Dispute::try_deserialize(&mut &dispute_info.data.borrow()[..])?;
+            match dispute.status {
+                DisputeStatus::Upheld => true,
+                DisputeStatus::Rejected => false,
+                _ => return err!(BettingError::DisputePending),
+            }
+        } else {
+            false
+        };
+
+        let winner = ctx.accounts.match_account.winner_key();
+        let market = &ctx.accounts.market;
+        let side = market.players.iter().position(|p| *p == winner).unwrap() as u8;
+        if upheld || market.pools.contains(&0) {
+            return cancel(&mut ctx.accounts.market);
+        }
+
+        let fee = market.pools[1 - side as usize] * market.fee_bps as u64 / BPS_DENOMINATOR;
+        if fee > 0 {
+            let match_id = market.match_id.to_le_bytes();
+            let signer_seeds: &[&[u8]] = &[b"market", &match_id, &[market.bump]];
+            token_compat::transfer(
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault,
+                &ctx.accounts.treasury,
+                &ctx.accounts.mint,
+                &ctx.accounts.market.to_account_info(),
+                &[signer_seeds],
+                fee,
+            )?;
+        }
+
+        let market = &mut ctx.accounts.market;
+        market.status = MarketStatus::Settled;
+        market.winner = side;
+        market.fee = fee;
+
+        emit!(MarketSettled {
+            market: market.key(),
+            match_id: market.match_id,
+            winner,
+            pools: market.pools,
+            fee,
+        });
+
+        Ok(())
+    }
+
+    /// Refunds every bet when no finalized result for the match turned up
+    /// within `RESULT_TIMEOUT` of the lock. Anyone may crank this.
+    #[access_control(validate_cancel(&ctx))]
+    pub fn cancel_market(ctx: Context<CancelMarket>) -> Result<()> {
+        cancel(&mut ctx.accounts.market)
+    }
+
+    /// Pays a bet out of a settled market, or refunds it from a cancelled
+    /// one, and closes it. Losing bets are closed for their rent.
+    pub fn claim(ctx: Context<Claim>) -> Result<()> {
+        let market = &ctx.accounts.market;
+        let bet = &ctx.accounts.bet;
+        let amount = match market.status {
+            MarketStatus::Open => return err!(BettingError::MarketOpen),
+            MarketStatus::Cancelled => bet.amount,
+            MarketStatus::Settled if bet.side == market.winner => payout(market, bet.amount),
+            MarketStatus::Settled => 0,
+        };
+
+        if amount > 0 {
+            let match_id = market.match_id.to_le_bytes();
+            let signer_seeds: &[&[u8]] = &[b"market", &match_id, &[market.bump]];
+            token_compat::transfer(
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault,
+                &ctx.accounts.bettor_token_account,
+                &ctx.accounts.mint,
+                &ctx.accounts.market.to_account_info(),
+                &[signer_seeds],
+                amount,
+            )?;
+        }
+
+        emit!(BetClaimed {
+            market: ctx.accounts.market.key(),
+            bettor: bet.bettor,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<BettingConfig>(BettingConfig::discriminator()),
+            Layout::new::<Market>(Market::discriminator()),
+            Layout::new::<Bet>(Bet::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<BettingError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+fn cancel(market: &mut Account<Market>) -> Result<()> {
+    market.status = MarketStatus::Cancelled;
+
+    emit!(MarketCancelled {
+        market: market.key(),
+        match_id: market.match_id,
+        pools: market.pools,
+    });
+
+    Ok(())
+}
+
+/// A winning bet's stake plus its pro-rata share of the losing pool after
+/// the protocol fee, rounded down; the rounding dust stays in the vault.
+pub fn payout(market: &Market, stake: u64) -> u64 {
+    let winning = market.pools[market.winner as usize];
+    let losing = market.pools[1 - market.winner as usize] - market.fee;
+    stake + (losing as u128 * stake as u128 / winning as u128) as u64
+}
+
+fn validate_market(players: &[Pubkey; 2], lock_at: i64) -> Result<()> {
+    require_keys_neq!(players[0], players[1], BettingError::InvalidPlayers);
+    require_gt!(lock_at, Clock::get()?.unix_timestamp, BettingError::InvalidLockTime);
+    Ok(())
+}
+
+fn validate_bet(ctx: &Context<PlaceBet>, side: u8, amount: u64) -> Result<()> {
+    let market = &ctx.accounts.market;
+    let bet = &ctx.accounts.bet;
+    require!(market.status == MarketStatus::Open, BettingError::MarketClosed);
+    require_gt!(market.lock_at, Clock::get()?.unix_timestamp, BettingError::MarketLocked);
+    require_gt!(2, side, BettingError::InvalidSide);
+    require_gt!(amount, 0, BettingError::InvalidAmount);
+    require!(bet.amount == 0 || bet.side == side, BettingError::SideMismatch);
+    Ok(())
+}
+
+fn validate_settle(ctx: &Context<SettleMarket>) -> Result<()> {
+    let market = &ctx.accounts.market;
+    let record = &ctx.accounts.match_account;
+    require!(market.status == MarketStatus::Open, BettingError::MarketClosed);
+    require!(record.finalized, BettingError::ResultNotFinal);
+    require!(
+        record.players.contains(&market.players[0]) && record.players.contains(&market.players[1]),
+        BettingError::PlayerMismatch
+    );
+    require_gte!(
+        Clock::get()?.unix_timestamp,
+        record.recorded_at + ctx.accounts.dispute_config.challenge_window,
+        BettingError::ChallengeWindowOpen
+    );
+    Ok(())
+}
+
+fn validate_mint(ctx: &Context<Initialize>) -> Result<()> {
+    require!(
+        token_compat::supported_mint(&ctx.accounts.mint)?,
+        BettingError::UnsupportedMint
+    );
+    Ok(())
+}
+
+/// A match account that never got created, or holds a set still being
+/// played, has no finalized result.
+fn validate_cancel(ctx: &Context<CancelMarket>) -> Result<()> {
+    let market = &ctx.accounts.market;
+    require!(market.status == MarketStatus::Open, BettingError::MarketClosed);
+    require_gte!(
+        Clock::get()?.unix_timestamp,
+        market.lock_at + RESULT_TIMEOUT,
+        BettingError::ResultTimeoutNotReached
+    );
+    let info = &ctx.accounts.match_account;
+    if info.owner == &match_result::ID {
+        let record = MatchAccount::try_deserialize(&mut &info.data.borrow()[..])?;
+        require!(!record.finalized, BettingError::ResultFinal);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"betting_config"],
+        bump,
+        space = BettingConfig::SPACE,
+        payer = admin,
+    )]
+    pub config: Account<'info, BettingConfig>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(token::mint = mint)]
+    pub treasury: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"betting_config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, BettingConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(match_id: u64)]
+pub struct OpenMarket<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"betting_config"], bump = config.bump, has_one = admin, has_one = mint)]
+    pub config: Account<'info, BettingConfig>,
+    #[account(
+        init,
+        seeds = [b"market".as_ref(), &match_id.to_le_bytes()],
+        bump,
+        space = Market::SPACE,
+        payer = admin,
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        init,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = market,
+        token::token_program = token_program,
+        payer = admin,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBet<'info> {
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"market", &market.match_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref()],
+        bump,
+        space = Bet::SPACE,
+        payer = bettor,
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(address = vault.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = vault.mint, token::authority = bettor)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SettleMarket<'info> {
+    #[account(seeds = [b"betting_config"], bump = config.bump, has_one = treasury)]
+    pub config: Account<'info, BettingConfig>,
+    #[account(
+        mut,
+        seeds = [b"market", &market.match_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub treasury: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = vault.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        seeds = [b"match", &market.match_id.to_le_bytes()],
+        bump = match_account.bump,
+        seeds::program = match_result::ID,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    #[account(
+        seeds = [b"dispute_config"],
+        bump = dispute_config.bump,
+        seeds::program = dispute::ID,
+    )]
+    pub dispute_config: Account<'info, DisputeConfig>,
+    /// CHECK: the match's dispute PDA, verified by seeds; only read when the
+    /// dispute program owns it, i.e. a dispute was opened
+    #[account(
+        seeds = [b"dispute", &market.match_id.to_le_bytes()],
+        bump,
+        seeds::program = dispute::ID,
+    )]
+    pub dispute: UncheckedAccount<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CancelMarket<'info> {
+    #[account(
+        mut,
+        seeds = [b"market", &market.match_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    /// CHECK: the match PDA, verified by seeds; only read when the match
+    /// result program owns it, i.e. the match was recorded
+    #[account(
+        seeds = [b"match", &market.match_id.to_le_bytes()],
+        bump,
+        seeds::program = match_result::ID,
+    )]
+    pub match_account: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    #[account(mut)]
+    pub bettor: Signer<'info>,
+    #[account(
+        seeds = [b"market", &market.match_id.to_le_bytes()],
+        bump = market.bump,
+    )]
+    pub market: Account<'info, Market>,
+    #[account(
+        mut,
+        seeds = [b"market_vault", market.key().as_ref()],
+        bump = market.vault_bump,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"bet", market.key().as_ref(), bettor.key().as_ref()],
+        bump = bet.bump,
+        close = bettor,
+    )]
+    pub bet: Account<'info, Bet>,
+    #[account(address = vault.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = vault.mint, token::authority = bettor)]
+    pub bettor_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct BettingConfig {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    /// Token every market is bet in.
+    pub mint: Pubkey,
+    /// Token account receiving protocol fees.
+    pub treasury: Pubkey,
+    /// Protocol fee on the losing pool, for new markets.
+    pub fee_bps: u16,
+    pub bump: u8,
+}
+
+impl BettingConfig {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 32 // mint
+        + 32 // treasury
+        + 2 // fee_bps
+        + 1; // bump
+}
+
+impl Versioned for BettingConfig {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[account]
+pub struct Market {
+    pub layout_version: u8,
+    pub match_id: u64,
+    pub players: [Pubkey; 2],
+    /// No bets are taken from this time on.
+    pub lock_at: i64,
+    /// Total staked on each player.
+    pub pools: [u64; 2],
+    /// Protocol fee on the losing pool, fixed when the market opens.
+    pub fee_bps: u16,
+    /// Fee taken at settlement.
+    pub fee: u64,
+    pub status: MarketStatus,
+    /// Index into `players`, set once settled.
+    pub winner: u8,
+    pub bump: u8,
+    pub vault_bump: u8,
+}
+
+impl Market {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 8 // match_id
+        + 32 * 2 // players
+        + 8 // lock_at
+        + 8 * 2 // pools
+        + 2 // fee_bps
+        + 8 // fee
+        + 1 // status
+        + 1 // winner
+        + 1 // bump
+        + 1; // vault_bump
+}
+
+impl Versioned for Market {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MarketStatus {
+    /// Taking bets until `lock_at`, then waiting for the result.
+    Open,
+    Settled,
+    /// Every bet is refunded.
+    Cancelled,
+}
+
+/// A spectator's stake in one market.
+#[account]
+pub struct Bet {
+    pub layout_version: u8,
+    pub market: Pubkey,
+    pub bettor: Pubkey,
+    /// Index into the market's players.
+    pub side: u8,
+    pub amount: u64,
+    pub bump: u8,
+}
+
+impl Bet {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // market
+        + 32 // bettor
+        + 1 // side
+        + 8 // amount
+        + 1; // bump
+}
+
+impl Versioned for Bet {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[event]
+pub struct MarketOpened {
+    pub market: Pubkey,
+    pub match_id: u64,
+    pub players: [Pubkey; 2],
+    pub lock_at: i64,
+}
+
+#[event]
+pub struct BetPlaced {
+    pub market: Pubkey,
+    pub bettor: Pubkey,
+    pub side: u8,
+    pub amount: u64,
+    pub pools: [u64; 2],
+}
+
+#[event]
+pub struct MarketSettled {
+    pub market: Pubkey,
+    pub match_id: u64,
+    pub winner: Pubkey,
+    pub pools: [u64; 2],
+    pub fee: u64,
+}
+
+#[event]
+pub struct MarketCancelled {
+    pub market: Pubkey,
+    pub match_id: u64,
+    pub pools: [u64; 2],
+}
+
+#[event]
+pub struct BetClaimed {
+    pub market: Pubkey,
+    pub bettor: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum BettingError {
+    #[msg("Protocol fee exceeds MAX_FEE_BPS")]
+    FeeTooHigh,
+    #[msg("Players must be distinct")]
+    InvalidPlayers,
+    #[msg("Lock time must be in the future")]
+    InvalidLockTime,
+    #[msg("Market is no longer open")]
+    MarketClosed,
+    #[msg("Market is locked")]
+    MarketLocked,
+    #[msg("Market has not been settled or cancelled")]
+    MarketOpen,
+    #[msg("Side must be 0 or 1")]
+    InvalidSide,
+    #[msg("Bet amount must be positive")]
+    InvalidAmount,
+    #[msg("Bet is already on the other side")]
+    SideMismatch,
+    #[msg("Match result is not finalized")]
+    ResultNotFinal,
+    #[msg("Match result is finalized")]
+    ResultFinal,
+    #[msg("Match players do not match the market")]
+    PlayerMismatch,
+    #[msg("Dispute challenge window has not passed")]
+    ChallengeWindowOpen,
+    #[msg("Mint has a token extension markets can't support")]
+    UnsupportedMint,
+    #[msg("Match dispute is not resolved")]
+    DisputePending,
+    #[msg("Result timeout has not been reached")]
+    ResultTimeoutNotReached,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for BettingError {
+    fn from(_: Overflow) -> Self {
+        BettingError::Overflow
+    }
+}
+
+impl From<MigrationError> for BettingError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => BettingError::AlreadyMigrated,
+            _ => BettingError::UnsupportedLayout,
+        }
+    }
+}
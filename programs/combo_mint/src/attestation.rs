@@ -0,0 +1,33 @@
+//! Attestation messages game servers sign for `verify_combo_attested`,
+//! checked by `ed25519_attestation`.
+//!
+//! Messages start with a domain tag so a signature for one instruction can
+//! never be replayed as another.
+
+use anchor_lang::prelude::*;
+use ed25519_attestation::AttestationError;
+
+use crate::ComboError;
+
+pub const VERIFY_COMBO_DOMAIN: &[u8] = b"combo-mint:verify_combo:v1";
+
+/// Message a server signs to attest that `moves` reproduce version
+/// `version` of `combo`.
+pub fn verify_combo_message(combo: &Pubkey, version: u32, moves: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(VERIFY_COMBO_DOMAIN.len() + 32 + 4 + moves.len());
+    message.extend_from_slice(VERIFY_COMBO_DOMAIN);
+    message.extend_from_slice(combo.as_ref());
+    message.extend_from_slice(&version.to_le_bytes());
+    message.extend_from_slice(moves);
+    message
+}
+
+/// Returns the key that signed `message` in the ed25519 instruction right
+/// before the current one.
+pub fn verify_attestation(instructions: &AccountInfo, message: &[u8]) -> Result<Pubkey> {
+    ed25519_attestation::verify_attestation(instructions, message).map_err(|err| match err {
+        AttestationError::Sysvar(err) => err.into(),
+        AttestationError::Missing => ComboError::MissingAttestation.into(),
+        AttestationError::Invalid => ComboError::InvalidAttestation.into(),
+    })
+}
@@ -0,0 +1,231 @@
+//! Frame-by-frame replay of a combo against a character's registered frame
+//! data.
+//!
+//! Each move runs through its startup, active and recovery frames in order,
+//! and the next move starts on the frame after recovery ends. A move deals
+//! its damage and meter on its first active frame, so a move registered
+//! without active frames never connects.
+//!
+//! Timed combos also carry the frame each move is input on, counted from the
+//! start of the combo. A move input before the previous one has recovered is
+//! impossible without cancels and is rejected.
+//!
+//! Damage is prorated the way the game core does it: the first hit deals
+//! full damage and each later hit keeps `PRORATION_SCALE_PERCENT` of the
+//! previous scaling, never dropping below `PRORATION_FLOOR_PERCENT`. The
+//! constants match `game_core::Proration::DEFAULT`.
+//!
+//! Meter gain is each connecting move's `meter_gain`, capped at `MAX_METER`
+//! like the game core's meter, so a combo's gain follows from its moves.
+//!
+//! Air routes are checked against a reference opponent with the game core's
+//! juggle rules: launchers knock the opponent up, gravity pulls them down a
+//! little harder for every juggle point, and once a launched opponent lands
+//! every later hit would whiff, so the combo is rejected as dropped.
+//!
+//! Difficulty is scored by `game_core::difficulty` itself, from each move's
+//! registered frames, hitstun and motion, so clients rank combos the same.
+
+use anchor_lang::prelude::*;
+use game_core::difficulty::{self, ComboMove};
+use game_core::Motion;
+use move_registry::{CharacterFrameData, MoveFrameData};
+
+use crate::{ComboError, COMBO_TAG_CHARGE, COMBO_TAG_JUGGLE};
+
+/// Longest combo the simulator will run: 20 seconds at 60 frames per second.
+pub const MAX_COMBO_FRAMES: u32 = 1200;
+
+pub const PRORATION_SCALE_PERCENT: u32 = 90;
+pub const PRORATION_FLOOR_PERCENT: u32 = 30;
+
+/// Matches `game_core::meter::MAX_METER`.
+pub const MAX_METER: u32 = 100;
+
+/// Air physics of the reference opponent, raw Q16.16 like the game core's
+/// first roster character.
+pub const DUMMY_GRAVITY: i32 = 1 << 16;
+pub const DUMMY_MAX_FALL_SPEED: i32 = 20 << 16;
+
+/// Match `game_core::juggle`.
+pub const JUGGLE_LIMIT: u8 = 10;
+pub const JUGGLE_GRAVITY_PERCENT: i32 = 10;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ComboOutcome {
+    pub damage: u32,
+    pub meter_gain: u32,
+    pub hits: u8,
+    /// Frames from the first move's startup to the last move's recovery.
+    pub frames: u32,
+}
+
+/// Vertical state of the opponent being comboed.
+#[derive(Clone, Copy, Default)]
+struct Opponent {
+    height: i32,
+    vel_y: i32,
+    juggle: u8,
+    launched: bool,
+    /// Set once a launched opponent has hit the ground.
+    landed: bool,
+}
+
+impl Opponent {
+    fn fall(&mut self) {
+        if !self.launched {
+            return;
+        }
+        let gravity = DUMMY_GRAVITY * (100 + self.juggle as i32 * JUGGLE_GRAVITY_PERCENT) / 100;
+        self.vel_y = (self.vel_y - gravity).max(-DUMMY_MAX_FALL_SPEED);
+        self.height += self.vel_y;
+        if self.height <= 0 {
+            *self = Opponent {
+                landed: true,
+                ..Opponent::default()
+            };
+        }
+    }
+
+    fn hit(&mut self, frame: &MoveFrameData) -> Result<()> {
+        require!(!self.landed, ComboError::ComboDropped);
+        if self.launched {
+            require_gte!(
+                JUGGLE_LIMIT,
+                self.juggle.saturating_add(frame.juggle_cost),
+                ComboError::JuggleLimitExceeded
+            );
+        }
+        if frame.launch > 0 || self.launched {
+            self.vel_y = frame.launch;
+            self.launched = true;
+            self.juggle = self.juggle.saturating_add(frame.juggle_cost);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Startup,
+    Active,
+    Recovery,
+}
+
+pub fn simulate_combo(frame_data: &CharacterFrameData, moves: &[u8]) -> Result<ComboOutcome> {
+    let mut outcome = ComboOutcome::default();
+    let mut opponent = Opponent::default();
+    for move_id in moves {
+        let frame = frame_data
+            .find_move(*move_id)
+            .ok_or(ComboError::UnknownMove)?;
+        run_move(frame, &mut outcome, &mut opponent)?;
+    }
+    Ok(outcome)
+}
+
+/// Total `game_core::difficulty` score of `moves`.
+pub fn combo_difficulty(frame_data: &CharacterFrameData, moves: &[u8]) -> Result<u16> {
+    let mut combo_moves = Vec::with_capacity(moves.len());
+    for move_id in moves {
+        let frame = frame_data
+            .find_move(*move_id)
+            .ok_or(ComboError::UnknownMove)?;
+        combo_moves.push(ComboMove {
+            startup: frame.startup,
+            active: frame.active,
+            recovery: frame.recovery,
+            hitstun: frame.hitstun,
+            motion: Motion::from_code(frame.motion),
+        });
+    }
+    Ok(difficulty::score(combo_moves).total())
+}
+
+/// The `DERIVED_COMBO_TAGS` that `moves` carry.
+pub fn derived_tags(frame_data: &CharacterFrameData, moves: &[u8]) -> Result<u32> {
+    let mut tags = 0;
+    for move_id in moves {
+        let frame = frame_data
+            .find_move(*move_id)
+            .ok_or(ComboError::UnknownMove)?;
+        if frame.launch > 0 {
+            tags |= COMBO_TAG_JUGGLE;
+        }
+        if Motion::from_code(frame.motion) == Some(Motion::ChargeBackForward) {
+            tags |= COMBO_TAG_CHARGE;
+        }
+    }
+    Ok(tags)
+}
+
+pub fn simulate_timed_combo(
+    frame_data: &CharacterFrameData,
+    moves: &[u8],
+    timing: &[u16],
+) -> Result<ComboOutcome> {
+    require_eq!(moves.len(), timing.len(), ComboError::InvalidTiming);
+    let mut outcome = ComboOutcome::default();
+    let mut opponent = Opponent::default();
+    for (move_id, start) in moves.iter().zip(timing) {
+        let frame = frame_data
+            .find_move(*move_id)
+            .ok_or(ComboError::UnknownMove)?;
+        let start = *start as u32;
+        require_gte!(start, outcome.frames, ComboError::InvalidTiming);
+        require_gte!(MAX_COMBO_FRAMES, start, ComboError::ComboTooLong);
+        // The opponent keeps falling while the attacker waits to input.
+        for _ in outcome.frames..start {
+            opponent.fall();
+        }
+        outcome.frames = start;
+        run_move(frame, &mut outcome, &mut opponent)?;
+    }
+    Ok(outcome)
+}
+
+fn run_move(
+    frame: &MoveFrameData,
+    outcome: &mut ComboOutcome,
+    opponent: &mut Opponent,
+) -> Result<()> {
+    let mut connected = false;
+    for tick in 0..frame.total_frames() {
+        outcome.frames += 1;
+        require_gte!(MAX_COMBO_FRAMES, outcome.frames, ComboError::ComboTooLong);
+        opponent.fall();
+
+        if phase_at(frame, tick) == Phase::Active && !connected {
+            connected = true;
+            opponent.hit(frame)?;
+            outcome.damage += prorate(frame.base_damage as u32, outcome.hits);
+            outcome.meter_gain = (outcome.meter_gain + frame.meter_gain as u32).min(MAX_METER);
+            outcome.hits += 1;
+        }
+    }
+    Ok(())
+}
+
+/// Scales a hit landing after `hits_before` earlier hits of the combo.
+pub fn prorate(damage: u32, hits_before: u8) -> u32 {
+    let mut percent = 100;
+    for _ in 0..hits_before {
+        let next = (percent * PRORATION_SCALE_PERCENT / 100).max(PRORATION_FLOOR_PERCENT);
+        if next == percent {
+            break;
+        }
+        percent = next;
+    }
+    damage * percent / 100
+}
+
+fn phase_at(frame: &MoveFrameData, tick: u16) -> Phase {
+    let startup = frame.startup as u16;
+    if tick < startup {
+        Phase::Startup
+    } else if tick < startup + frame.active as u16 {
+        Phase::Active
+    } else {
+        Phase::Recovery
+    }
+}
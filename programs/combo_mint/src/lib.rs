@@ -1,34 +1,506 @@
 // Rust Anchor Program for Combo Minting
 // Deploy with: anchor deploy --provider.cluster devnet
 
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::hash::hash;
+use anchor_lang::solana_program::hash::{hash, hashv};
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::metadata::mpl_token_metadata::accounts::Metadata as MetadataAccount;
+use anchor_spl::metadata::mpl_token_metadata::types::{Creator, DataV2};
+use anchor_spl::metadata::{
+    create_master_edition_v3, create_metadata_accounts_v3, CreateMasterEditionV3,
+    CreateMetadataAccountsV3, Metadata,
+};
+use anchor_spl::token::{self, Mint, MintTo, Token, TokenAccount};
+use anchor_spl::token_interface::{self, TokenInterface};
+use checked_math::{self as checked, add, increment, Overflow};
+use move_registry::CharacterFrameData;
+use mpl_bubblegum::instructions::{
+    CreateTreeConfigCpi, CreateTreeConfigCpiAccounts, CreateTreeConfigInstructionArgs, MintV1Cpi,
+    MintV1CpiAccounts, MintV1InstructionArgs,
+};
+use mpl_bubblegum::types::{
+    Creator as CompressedCreator, MetadataArgs, TokenProgramVersion, TokenStandard,
+};
+
+mod attestation;
+pub mod combo_sim;
+mod event_cpi;
+
+use attestation::{verify_attestation, verify_combo_message};
+use combo_sim::{combo_difficulty, derived_tags, simulate_combo, simulate_timed_combo};
+use event_cpi::{EventSink, EVENT_AUTHORITY_SEED};
 
 declare_id!("COMBO_MINT_PROGRAM_ID_HERE");
 
+/// Verifications a combo needs before it can be minted as an NFT.
+pub const MIN_VERIFICATIONS_FOR_NFT: u32 = 3;
+pub const NFT_SYMBOL: &str = "COMBO";
+pub const MAX_BASE_URI_LEN: usize = 96;
+/// Most signers the limits multisig can have.
+pub const MAX_LIMIT_SIGNERS: usize = 5;
+/// Most combos a collection can list.
+pub const MAX_COLLECTION_ENTRIES: usize = 32;
+/// Longest combo name, in bytes.
+pub const MAX_COMBO_NAME_LEN: usize = 64;
+/// Most moves one combo can hold.
+pub const MAX_COMBO_MOVES: usize = 20;
+/// Longest collection name, in bytes.
+pub const MAX_COLLECTION_NAME_LEN: usize = 32;
+/// Most verifiers the `VerifierRegistry` holds.
+pub const MAX_VERIFIERS: usize = 32;
+/// Most prefixes the `NameFilter` holds, and the longest one in bytes.
+pub const MAX_NAME_PREFIXES: usize = 32;
+pub const MAX_NAME_PREFIX_LEN: usize = 16;
+/// Combos each `TierLeaderboard` ranks.
+pub const MAX_LEADERBOARD_ENTRIES: usize = 10;
+/// Basis points in a whole sale price.
+pub const MAX_BPS: u16 = 10_000;
+/// Most combos `verify_combos_batch` takes in one transaction.
+pub const MAX_BATCH_COMBOS: usize = 8;
+/// Lamports of an auction's or rental's rent paid to whoever cranks it
+/// closed with `finalize_auction_if_expired` or `finalize_rental_if_expired`.
+pub const CRANK_REWARD: u64 = 5_000;
+
+/// `ComboFlag.reason` codes.
+pub const FLAG_REASON_OFFENSIVE_NAME: u8 = 0;
+/// The combo only works through an engine bug.
+pub const FLAG_REASON_EXPLOIT: u8 = 1;
+pub const FLAG_REASON_OTHER: u8 = 2;
+
+/// `ComboAccount.tags` bits. Starter, position and meter tags are the
+/// creator's claim; `COMBO_TAG_JUGGLE` and `COMBO_TAG_CHARGE` are checked
+/// against the moves.
+#[constant]
+pub const COMBO_TAG_ANTI_AIR_STARTER: u32 = 1 << 0;
+#[constant]
+pub const COMBO_TAG_CORNER_ONLY: u32 = 1 << 1;
+#[constant]
+pub const COMBO_TAG_METERLESS: u32 = 1 << 2;
+#[constant]
+pub const COMBO_TAG_COUNTER_HIT: u32 = 1 << 3;
+/// Contains a launcher.
+#[constant]
+pub const COMBO_TAG_JUGGLE: u32 = 1 << 4;
+/// Contains a charge motion.
+#[constant]
+pub const COMBO_TAG_CHARGE: u32 = 1 << 5;
+/// Tags that follow from the moves.
+pub const DERIVED_COMBO_TAGS: u32 = COMBO_TAG_JUGGLE | COMBO_TAG_CHARGE;
+pub const KNOWN_COMBO_TAGS: u32 = COMBO_TAG_ANTI_AIR_STARTER
+    | COMBO_TAG_CORNER_ONLY
+    | COMBO_TAG_METERLESS
+    | COMBO_TAG_COUNTER_HIT
+    | DERIVED_COMBO_TAGS;
+
+/// Byte offsets into `ComboAccount` data for `getProgramAccounts` memcmp
+/// filters. These fields sit ahead of the variable-length name, so the
+/// offsets hold for every combo. `tags` is a little-endian u32 and memcmp
+/// compares whole bytes, so a tag filter matches combos whose tags in that
+/// byte are exactly the given ones; every tag so far is in the first byte.
+#[constant]
+pub const COMBO_AUTHORITY_OFFSET: usize = 9;
+#[constant]
+pub const COMBO_CHARACTER_ID_OFFSET: usize = 41;
+#[constant]
+pub const COMBO_TAGS_OFFSET: usize = 42;
+
+/// The `EventSink` for a context carrying the self-CPI accounts.
+macro_rules! event_sink {
+    ($ctx:expr) => {
+        EventSink::new(
+            &$ctx.accounts.event_authority,
+            &$ctx.accounts.program,
+            $ctx.bumps.event_authority,
+        )
+    };
+}
+
 #[program]
 pub mod combo_mint {
     use super::*;
 
-    #[access_control(validate_combo_data(&ctx, &combo_name, damage, meter_gain, move_count))]
-    pub fn create_combo(
-        ctx: Context<CreateCombo>,
+    #[access_control(
+        validate_limits(&limits)
+        validate_limit_signers(&signers, threshold)
+    )]
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        limits: ValidationLimits,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.layout_version = Config::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.game_version = 1;
+        config.limits = limits;
+        config.signer_count = signers.len() as u8;
+        config.signers = [Pubkey::default(); MAX_LIMIT_SIGNERS];
+        config.signers[..signers.len()].copy_from_slice(&signers);
+        config.threshold = threshold;
+        config.paused = false;
+        config.verification_cooldown = 0;
+        config.max_royalty_bps = 0;
+        config.bump = ctx.bumps.config;
+        config.moderator = Pubkey::default();
+        Ok(())
+    }
+
+    /// Replaces the combo validation limits. Needs `threshold` of the
+    /// configured signers to sign, passed as remaining accounts.
+    #[access_control(
+        validate_limits(&limits)
+        require_limit_signers(&ctx.accounts.config, ctx.remaining_accounts)
+    )]
+    pub fn update_limits(ctx: Context<UpdateLimits>, limits: ValidationLimits) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.limits = limits.clone();
+
+        emit!(LimitsUpdated {
+            limits,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Moves to a new patch. Combos created under earlier versions are marked
+    /// stale the next time someone tries to verify them.
+    pub fn bump_game_version(ctx: Context<BumpGameVersion>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        increment(&mut config.game_version).map_err(ComboError::from)?;
+
+        emit!(GameVersionBumped {
+            game_version: config.game_version,
+            timestamp: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// Circuit breaker: stops combo creation, verification and minting
+    /// until `unpause`.
+    pub fn pause(ctx: Context<SetPaused>) -> Result<()> {
+        set_paused(&mut ctx.accounts.config, true)
+    }
+
+    pub fn unpause(ctx: Context<SetPaused>) -> Result<()> {
+        set_paused(&mut ctx.accounts.config, false)
+    }
+
+    /// Creates the reward vault that pays verifiers of `mint` tokens, which
+    /// may be a Token-2022 mint without extensions the vault can't support.
+    #[access_control(validate_reward_mint(&ctx))]
+    pub fn initialize_rewards(
+        ctx: Context<InitializeRewards>,
+        amount: u64,
+        max_per_window: u32,
+        max_per_verifier: u32,
+        window: i64,
+    ) -> Result<()> {
+        require_gt!(window, 0, ComboError::InvalidRewardRate);
+        let rewards = &mut ctx.accounts.reward_config;
+        rewards.layout_version = RewardConfig::LAYOUT_VERSION;
+        rewards.mint = ctx.accounts.mint.key();
+        rewards.vault = ctx.accounts.reward_vault.key();
+        rewards.amount = amount;
+        rewards.max_per_window = max_per_window;
+        rewards.window = window;
+        rewards.window_start = 0;
+        rewards.paid_in_window = 0;
+        rewards.bump = ctx.bumps.reward_config;
+        rewards.tier_costs = [0; 2];
+        rewards.max_per_verifier = max_per_verifier;
+        Ok(())
+    }
+
+    pub fn fund_rewards(ctx: Context<FundRewards>, amount: u64) -> Result<()> {
+        let amount = token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.funder_token,
+            &ctx.accounts.reward_vault,
+            &ctx.accounts.mint,
+            &ctx.accounts.admin,
+            &[],
+            amount,
+        )?;
+
+        emit!(RewardsFunded {
+            amount,
+            balance: add(ctx.accounts.reward_vault.amount, amount).map_err(ComboError::from)?,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the reward per verification and how many rewards can be paid
+    /// in each `window` seconds, in all and to any one verifier.
+    pub fn set_reward_rate(
+        ctx: Context<SetRewardRate>,
+        amount: u64,
+        max_per_window: u32,
+        max_per_verifier: u32,
+        window: i64,
+    ) -> Result<()> {
+        require_gt!(window, 0, ComboError::InvalidRewardRate);
+        let rewards = &mut ctx.accounts.reward_config;
+        rewards.amount = amount;
+        rewards.max_per_window = max_per_window;
+        rewards.max_per_verifier = max_per_verifier;
+        rewards.window = window;
+        Ok(())
+    }
+
+    /// Caps the royalty new combos can set and existing combos are paid.
+    pub fn set_royalty_cap(ctx: Context<SetRoyaltyCap>, max_royalty_bps: u16) -> Result<()> {
+        require_gte!(MAX_BPS, max_royalty_bps, ComboError::InvalidRoyalty);
+        ctx.accounts.config.max_royalty_bps = max_royalty_bps;
+        Ok(())
+    }
+
+    /// Seconds a verifier has to wait between verifications of one combo.
+    pub fn set_verification_cooldown(
+        ctx: Context<SetVerificationCooldown>,
+        cooldown: i64,
+    ) -> Result<()> {
+        require_gte!(cooldown, 0, ComboError::InvalidCooldown);
+        ctx.accounts.config.verification_cooldown = cooldown;
+        Ok(())
+    }
+
+    pub fn initialize_verifier_registry(ctx: Context<InitializeVerifierRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.verifier_registry;
+        registry.layout_version = VerifierRegistry::LAYOUT_VERSION;
+        registry.verifiers = Vec::new();
+        registry.bump = ctx.bumps.verifier_registry;
+        Ok(())
+    }
+
+    pub fn add_verifier(ctx: Context<UpdateVerifiers>, verifier: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.verifier_registry;
+        require!(
+            !registry.verifiers.contains(&verifier),
+            ComboError::VerifierAlreadyApproved
+        );
+        require_gt!(
+            MAX_VERIFIERS,
+            registry.verifiers.len(),
+            ComboError::TooManyVerifiers
+        );
+        registry.verifiers.push(verifier);
+
+        emit!(VerifierChanged {
+            verifier,
+            approved: true,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_verifier(ctx: Context<UpdateVerifiers>, verifier: Pubkey) -> Result<()> {
+        let registry = &mut ctx.accounts.verifier_registry;
+        let before = registry.verifiers.len();
+        registry.verifiers.retain(|v| *v != verifier);
+        require_gt!(before, registry.verifiers.len(), ComboError::UnknownVerifier);
+
+        emit!(VerifierChanged {
+            verifier,
+            approved: false,
+        });
+
+        Ok(())
+    }
+
+    pub fn initialize_name_filter(ctx: Context<InitializeNameFilter>) -> Result<()> {
+        let filter = &mut ctx.accounts.name_filter;
+        filter.layout_version = NameFilter::LAYOUT_VERSION;
+        filter.prefixes = Vec::new();
+        filter.bump = ctx.bumps.name_filter;
+        Ok(())
+    }
+
+    /// Rejects new combo names starting with `prefix`, compared lowercased.
+    pub fn ban_name_prefix(ctx: Context<UpdateNameFilter>, prefix: String) -> Result<()> {
+        require!(
+            prefix.len() <= MAX_NAME_PREFIX_LEN && is_printable(&prefix),
+            ComboError::InvalidNameCharset
+        );
+        let prefix = prefix.to_ascii_lowercase();
+        let filter = &mut ctx.accounts.name_filter;
+        require!(!filter.prefixes.contains(&prefix), ComboError::PrefixAlreadyBanned);
+        require_gt!(
+            MAX_NAME_PREFIXES,
+            filter.prefixes.len(),
+            ComboError::TooManyPrefixes
+        );
+        filter.prefixes.push(prefix);
+        Ok(())
+    }
+
+    pub fn unban_name_prefix(ctx: Context<UpdateNameFilter>, prefix: String) -> Result<()> {
+        let prefix = prefix.to_ascii_lowercase();
+        let filter = &mut ctx.accounts.name_filter;
+        let before = filter.prefixes.len();
+        filter.prefixes.retain(|p| *p != prefix);
+        require_gt!(before, filter.prefixes.len(), ComboError::UnknownPrefix);
+        Ok(())
+    }
+
+    /// Restricts `create_combo` for `character_id` to holders of an NFT from
+    /// the verified Metaplex `collection`.
+    pub fn set_character_gate(
+        ctx: Context<SetCharacterGate>,
+        character_id: u8,
+        collection: Pubkey,
+        enabled: bool,
+    ) -> Result<()> {
+        let gate = &mut ctx.accounts.character_gate;
+        gate.layout_version = CharacterGate::LAYOUT_VERSION;
+        gate.character_id = character_id;
+        gate.collection = collection;
+        gate.enabled = enabled;
+        gate.bump = ctx.bumps.character_gate;
+
+        emit!(CharacterGateChanged {
+            character_id,
+            collection,
+            enabled,
+        });
+
+        Ok(())
+    }
+
+    /// Official combos only count verifications from approved verifiers.
+    pub fn set_official(ctx: Context<SetOfficial>, official: bool) -> Result<()> {
+        ctx.accounts.combo_pda.official = official;
+        Ok(())
+    }
+
+    /// Hands the moderator role to `moderator`; `Pubkey::default()` leaves
+    /// the program without one.
+    pub fn set_moderator(ctx: Context<SetModerator>, moderator: Pubkey) -> Result<()> {
+        ctx.accounts.config.moderator = moderator;
+        Ok(())
+    }
+
+    /// Reports a combo to the moderator with one of the `FLAG_REASON_*`
+    /// codes. A combo has at most one open flag; the reporter pays for it
+    /// and gets the rent back when the moderator resolves it.
+    pub fn flag_combo(ctx: Context<FlagCombo>, reason: u8) -> Result<()> {
+        require_gte!(FLAG_REASON_OTHER, reason, ComboError::InvalidFlagReason);
+        let flag = &mut ctx.accounts.flag;
+        flag.layout_version = ComboFlag::LAYOUT_VERSION;
+        flag.combo = ctx.accounts.combo_pda.key();
+        flag.reporter = *ctx.accounts.reporter.key;
+        flag.reason = reason;
+        flag.appealed = false;
+        flag.flagged_at = Clock::get()?.unix_timestamp;
+        flag.bump = ctx.bumps.flag;
+
+        emit!(ComboFlagged {
+            combo: flag.combo,
+            reporter: flag.reporter,
+            reason,
+        });
+
+        Ok(())
+    }
+
+    /// Freezes or thaws a combo. Needs no open flag, so exploit combos can
+    /// be stopped as soon as they are found.
+    #[access_control(is_moderator(&ctx.accounts.config, ctx.accounts.moderator.key))]
+    pub fn freeze_combo(ctx: Context<FreezeCombo>, frozen: bool) -> Result<()> {
+        ctx.accounts.combo_pda.frozen = frozen;
+
+        emit!(ComboFrozen {
+            combo: ctx.accounts.combo_pda.key(),
+            moderator: *ctx.accounts.moderator.key,
+            frozen,
+        });
+
+        Ok(())
+    }
+
+    /// The combo's authority contests the open flag. Each flag can be
+    /// appealed once.
+    pub fn appeal_flag(ctx: Context<AppealFlag>) -> Result<()> {
+        let flag = &mut ctx.accounts.flag;
+        require!(!flag.appealed, ComboError::AlreadyAppealed);
+        flag.appealed = true;
+
+        emit!(FlagAppealed {
+            combo: flag.combo,
+            authority: *ctx.accounts.authority.key,
+        });
+
+        Ok(())
+    }
+
+    /// Closes the open flag. Upholding it freezes the combo; rejecting it
+    /// thaws the combo.
+    #[access_control(is_moderator(&ctx.accounts.config, ctx.accounts.moderator.key))]
+    pub fn resolve_flag(ctx: Context<ResolveFlag>, upheld: bool) -> Result<()> {
+        ctx.accounts.combo_pda.frozen = upheld;
+
+        emit!(FlagResolved {
+            combo: ctx.accounts.combo_pda.key(),
+            moderator: *ctx.accounts.moderator.key,
+            upheld,
+            appealed: ctx.accounts.flag.appealed,
+        });
+
+        Ok(())
+    }
+
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        validate_combo_data(&ctx, &combo_name, damage, move_count, &moves)
+        validate_against_frame_data(&ctx, damage, &moves)
+        validate_royalty(&ctx.accounts.config, royalty_bps)
+        validate_tags(&ctx.accounts.frame_data, tags, &moves)
+        check_character_gate(&ctx)
+    )]
+    pub fn create_combo<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CreateCombo<'info>>,
         combo_name: String,
         damage: u32,
-        meter_gain: u32,
         move_count: u8,
         character_id: u8,
-    ) -> ProgramResult {
+        moves: Vec<u8>,
+        royalty_bps: u16,
+        tags: u32,
+    ) -> Result<()> {
+        // Meter gain is derived from the moves rather than supplied.
+        let meter_gain = simulate_combo(&ctx.accounts.frame_data, &moves)?.meter_gain;
+        let difficulty = combo_difficulty(&ctx.accounts.frame_data, &moves)?;
         let combo = &mut ctx.accounts.combo_pda;
         
+        combo.layout_version = ComboAccount::LAYOUT_VERSION;
         combo.authority = *ctx.accounts.authority.key;
+        combo.creator = *ctx.accounts.authority.key;
+        combo.pending_authority = Pubkey::default();
+        combo.royalty_bps = royalty_bps;
         combo.character_id = character_id;
         combo.name = combo_name;
         combo.damage = damage;
         combo.meter_gain = meter_gain;
         combo.move_count = move_count;
+        combo.moves = moves;
         combo.timestamp = Clock::get()?.unix_timestamp;
         combo.bump = ctx.bumps.combo_pda;
+        combo.version = 1;
+        combo.last_history = Pubkey::default();
+        combo.game_version = ctx.accounts.config.game_version;
+        combo.stale = false;
+        combo.official = false;
+        combo.parents = [[0; 32]; 2];
+        combo.difficulty = difficulty;
+        combo.tags = tags;
+        combo.tier = ComboTier::Bronze;
 
         let combo_seed = compute_combo_seed(
             combo.name.as_bytes(),
@@ -36,170 +508,4609 @@ pub mod combo_mint {
             meter_gain,
             move_count,
             character_id,
+            &combo.moves,
         );
         combo.combo_hash = combo_seed;
+        claim_combo_hash(
+            &ctx.accounts.hash_index,
+            combo.key(),
+            &combo_seed,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+        )?;
+        index_combo(
+            &mut ctx.accounts.character_index,
+            combo.key(),
+            character_id,
+            ctx.bumps.character_index,
+        )?;
+        let name_index = &mut ctx.accounts.name_index;
+        name_index.layout_version = ComboNameIndex::LAYOUT_VERSION;
+        name_index.combo = combo.key();
+        name_index.bump = ctx.bumps.name_index;
 
-        emit!(ComboCreated {
-            combo: ctx.accounts.combo_pda.key(),
+        event_sink!(ctx).emit(&ComboCreated {
+            combo: combo.key(),
             authority: combo.authority,
             character_id,
             damage,
+            difficulty,
+            tags,
             timestamp: combo.timestamp,
+        })?;
+
+        Ok(())
+    }
+
+    /// Burns two of the signer's combos and creates `combo_name` from the
+    /// first's moves followed by the second's. The new combo is simulated
+    /// like a fresh one and keeps both parents' hashes for provenance. The
+    /// parents' hash and name indexes are released. Neither parent may be
+    /// frozen, carry combo data, or be listed, rented or auctioned.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        validate_fusion(&ctx, &combo_name)
+    )]
+    pub fn fuse_combos(ctx: Context<FuseCombos>, combo_name: String) -> Result<()> {
+        let first = &ctx.accounts.first;
+        let second = &ctx.accounts.second;
+        let mut moves = first.moves.clone();
+        moves.extend_from_slice(&second.moves);
+        let outcome = simulate_combo(&ctx.accounts.frame_data, &moves)?;
+        let difficulty = combo_difficulty(&ctx.accounts.frame_data, &moves)?;
+        let tags = fused_tags(first.tags, second.tags)
+            | derived_tags(&ctx.accounts.frame_data, &moves)?;
+        let parents = [first.combo_hash, second.combo_hash];
+        let parent_keys = [first.key(), second.key()];
+        let character_id = first.character_id;
+
+        let combo = &mut ctx.accounts.fused;
+        combo.layout_version = ComboAccount::LAYOUT_VERSION;
+        combo.authority = *ctx.accounts.authority.key;
+        combo.creator = *ctx.accounts.authority.key;
+        combo.pending_authority = Pubkey::default();
+        combo.royalty_bps = 0;
+        combo.character_id = character_id;
+        combo.name = combo_name;
+        combo.damage = outcome.damage;
+        combo.meter_gain = outcome.meter_gain;
+        combo.move_count = moves.len() as u8;
+        combo.moves = moves;
+        combo.timestamp = Clock::get()?.unix_timestamp;
+        combo.bump = ctx.bumps.fused;
+        combo.version = 1;
+        combo.last_history = Pubkey::default();
+        combo.game_version = ctx.accounts.config.game_version;
+        combo.stale = false;
+        combo.official = false;
+        combo.parents = parents;
+        combo.difficulty = difficulty;
+        combo.tags = tags;
+        combo.tier = ComboTier::Bronze;
+        combo.combo_hash = compute_combo_seed(
+            combo.name.as_bytes(),
+            combo.damage,
+            combo.meter_gain,
+            combo.move_count,
+            character_id,
+            &combo.moves,
+        );
+        claim_combo_hash(
+            &ctx.accounts.hash_index,
+            combo.key(),
+            &combo.combo_hash,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+        )?;
+        index_combo(
+            &mut ctx.accounts.character_index,
+            combo.key(),
+            character_id,
+            ctx.bumps.character_index,
+        )?;
+        let name_index = &mut ctx.accounts.name_index;
+        name_index.layout_version = ComboNameIndex::LAYOUT_VERSION;
+        name_index.combo = combo.key();
+        name_index.bump = ctx.bumps.name_index;
+
+        emit!(CombosFused {
+            combo: combo.key(),
+            authority: combo.authority,
+            parents: parent_keys,
+            parent_hashes: parents,
+            damage: combo.damage,
         });
 
         Ok(())
     }
 
-    pub fn verify_combo(ctx: Context<VerifyCombo>, moves: Vec<u8>) -> ProgramResult {
+    /// Replays the submitted moves against the character's frame data and
+    /// only counts the verification if the outcome matches the stored combo.
+    /// A combo from an older game version is marked stale instead.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        verify_move_sequence(&ctx, &moves)
+        check_verifier(
+            &ctx.accounts.combo_pda,
+            &ctx.accounts.verifier_registry,
+            ctx.accounts.verifier.key,
+        )
+        not_frozen(&ctx.accounts.combo_pda)
+    )]
+    pub fn verify_combo(ctx: Context<VerifyCombo>, moves: Vec<u8>) -> Result<()> {
+        if mark_if_stale(&mut ctx.accounts.combo_pda, &ctx.accounts.config)? {
+            return Ok(());
+        }
+        check_replay(&ctx.accounts.combo_pda, &ctx.accounts.frame_data, &moves)?;
         let combo = &mut ctx.accounts.combo_pda;
-        combo.verification_count += 1;
-        combo.last_verified = Clock::get()?.unix_timestamp;
 
-        emit!(ComboVerified {
+        let now = Clock::get()?.unix_timestamp;
+        let counted = record_verification(
+            &mut ctx.accounts.receipt,
+            combo,
+            ctx.accounts.verifier.key(),
+            ctx.bumps.receipt,
+            now,
+            ctx.accounts.config.verification_cooldown,
+        )?;
+        if counted {
+            increment(&mut combo.verification_count).map_err(ComboError::from)?;
+        }
+        combo.last_verified = now;
+
+        let quota = &mut ctx.accounts.verifier_quota;
+        quota.layout_version = VerifierQuota::LAYOUT_VERSION;
+        quota.verifier = ctx.accounts.verifier.key();
+        quota.bump = ctx.bumps.verifier_quota;
+        if counted {
+            pay_reward(ctx.accounts, now)?;
+        }
+
+        event_sink!(ctx).emit(&ComboVerified {
             combo: ctx.accounts.combo_pda.key(),
+            verifier: ctx.accounts.verifier.key(),
             moves_count: moves.len() as u8,
-            timestamp: combo.last_verified,
-        });
+            counted,
+            timestamp: now,
+        })?;
 
         Ok(())
     }
 
-    #[access_control(only_authority(&ctx))]
-    pub fn close_combo(ctx: Context<CloseCombo>) -> ProgramResult {
-        let destination = &ctx.accounts.destination;
-        let combo_pda = &mut ctx.accounts.combo_pda;
+    /// Like `verify_combo`, but the replay ran on an approved game server,
+    /// which signs the moves off-chain instead of signing the transaction.
+    /// The signature is checked by an ed25519 program instruction placed
+    /// right before this one, so anyone can submit it and pay for the
+    /// receipt. The receipt is the server's own, and no reward is paid.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        verify_attested_sequence(&ctx, &moves, &server)
+        not_frozen(&ctx.accounts.combo_pda)
+    )]
+    pub fn verify_combo_attested(
+        ctx: Context<VerifyComboAttested>,
+        moves: Vec<u8>,
+        server: Pubkey,
+    ) -> Result<()> {
+        let message = verify_combo_message(
+            &ctx.accounts.combo_pda.key(),
+            ctx.accounts.combo_pda.version,
+            &moves,
+        );
+        let attester = verify_attestation(&ctx.accounts.instructions, &message)?;
+        require_keys_eq!(attester, server, ComboError::InvalidAttestation);
+
+        if mark_if_stale(&mut ctx.accounts.combo_pda, &ctx.accounts.config)? {
+            return Ok(());
+        }
+        check_replay(&ctx.accounts.combo_pda, &ctx.accounts.frame_data, &moves)?;
+        let combo = &mut ctx.accounts.combo_pda;
+
+        let now = Clock::get()?.unix_timestamp;
+        let counted = record_verification(
+            &mut ctx.accounts.receipt,
+            combo,
+            server,
+            ctx.bumps.receipt,
+            now,
+            ctx.accounts.config.verification_cooldown,
+        )?;
+        if counted {
+            increment(&mut combo.verification_count).map_err(ComboError::from)?;
+        }
+        combo.last_verified = now;
 
-        **destination.try_borrow_mut_lamports()? += **combo_pda.try_borrow_lamports()?;
-        **combo_pda.try_borrow_mut_lamports()? = 0;
+        event_sink!(ctx).emit(&ComboVerified {
+            combo: ctx.accounts.combo_pda.key(),
+            verifier: server,
+            moves_count: moves.len() as u8,
+            counted,
+            timestamp: now,
+        })?;
 
         Ok(())
     }
-}
 
-fn compute_combo_seed(
-    name: &[u8],
-    damage: u32,
-    meter_gain: u32,
-    move_count: u8,
-    character_id: u8,
-) -> [u8; 32] {
-    let mut input = Vec::with_capacity(64);
-    input.extend_from_slice(name);
-    input.extend_from_slice(&damage.to_le_bytes());
-    input.extend_from_slice(&meter_gain.to_le_bytes());
-    input.push(move_count);
-    input.push(character_id);
-    
-    let hash_result = hash(&input);
-    hash_result.to_bytes()
-}
+    /// Verifies several combos under one verifier signature, e.g. for a
+    /// tournament organizer checking every submission at once. Each combo is
+    /// passed as a `[combo, frame_data, receipt]` triple in the remaining
+    /// accounts, and `moves` holds every combo's moves back to back, split
+    /// by `lengths`. Missing receipts are created at the verifier's expense.
+    /// Stale combos are marked and skipped; no rewards are paid.
+    #[access_control(not_paused(&ctx.accounts.config))]
+    pub fn verify_combos_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, VerifyCombosBatch<'info>>,
+        moves: Vec<u8>,
+        lengths: Vec<u8>,
+    ) -> Result<()> {
+        let count = lengths.len();
+        require!(
+            count > 0 && count <= MAX_BATCH_COMBOS,
+            ComboError::InvalidBatch
+        );
+        require_eq!(ctx.remaining_accounts.len(), count * 3, ComboError::InvalidBatch);
+        require!(
+            lengths.iter().map(|&len| len as usize).sum::<usize>() == moves.len(),
+            ComboError::InvalidBatch
+        );
 
-fn validate_combo_data(
-    _ctx: &Context<CreateCombo>,
-    combo_name: &String,
-    damage: &u32,
-    meter_gain: &u32,
-    move_count: &u8,
-) -> Result<()> {
-    require!(combo_name.len() <= 64, ComboError::NameTooLong);
-    require!(*damage > 0 && *damage <= 1000, ComboError::InvalidDamage);
-    require!(*meter_gain > 0 && *meter_gain <= 100, ComboError::InvalidMeterGain);
-    require!(*move_count > 0 && *move_count <= 20, ComboError::InvalidMoveCount);
-    Ok(())
-}
+        let verifier = ctx.accounts.verifier.key();
+        let now = Clock::get()?.unix_timestamp;
+        let events = event_sink!(ctx);
+        let mut rest = moves.as_slice();
+        for (infos, &len) in ctx.remaining_accounts.chunks_exact(3).zip(&lengths) {
+            let (combo_moves, tail) = rest.split_at(len as usize);
+            rest = tail;
+            require_gte!(MAX_COMBO_MOVES, combo_moves.len(), ComboError::TooManyMoves);
 
-fn verify_move_sequence(_ctx: &Context<VerifyCombo>, moves: &Vec<u8>) -> Result<()> {
-    require!(moves.len() <= 20, ComboError::TooManyMoves);
-    Ok(())
-}
+            require!(infos[0].is_writable, ComboError::InvalidBatch);
+            let mut combo: Account<ComboAccount> = Account::try_from(&infos[0])?;
+            not_frozen(&combo)?;
+            check_verifier(&combo, &ctx.accounts.verifier_registry, &verifier)?;
+            if mark_if_stale(&mut combo, &ctx.accounts.config)? {
+                combo.exit(&crate::ID)?;
+                continue;
+            }
 
-fn only_authority(ctx: &Context<CloseCombo>) -> Result<()> {
-    require!(
-        ctx.accounts.combo_pda.authority == *ctx.accounts.authority.key,
-        ComboError::Unauthorized
-    );
-    Ok(())
-}
+            let frame_data: Account<CharacterFrameData> = Account::try_from(&infos[1])?;
+            let frame_data_address = Pubkey::create_program_address(
+                &[b"character", &[combo.character_id], &[frame_data.bump]],
+                &move_registry::ID,
+            )
+            .map_err(|_| ComboError::InvalidBatch)?;
+            require_keys_eq!(frame_data.key(), frame_data_address, ComboError::InvalidBatch);
+            check_replay(&combo, &frame_data, combo_moves)?;
 
-#[derive(Accounts)]
-pub struct CreateCombo<'info> {
-    #[account(signer)]
-    pub authority: AccountInfo<'info>,
-    #[account(
-        init,
-        seeds = [b"combo", authority.key.as_ref()],
-        bump,
-        space = 256,
-        payer = authority,
+            let (mut receipt, bump) = load_receipt(
+                &infos[2],
+                &combo.key(),
+                &ctx.accounts.verifier,
+                &ctx.accounts.system_program,
+            )?;
+            let counted = record_verification(
+                &mut receipt,
+                &combo,
+                verifier,
+                bump,
+                now,
+                ctx.accounts.config.verification_cooldown,
+            )?;
+            if counted {
+                increment(&mut combo.verification_count).map_err(ComboError::from)?;
+            }
+            combo.last_verified = now;
+            receipt.exit(&crate::ID)?;
+            combo.exit(&crate::ID)?;
+
+            events.emit(&ComboVerified {
+                combo: combo.key(),
+                verifier,
+                moves_count: len,
+                counted,
+                timestamp: now,
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Sizes the combo's account to `8 + ComboAccount::INIT_SPACE`, room
+    /// for the longest name and move list, without writing a new version.
+    /// Combos from when accounts were sized to their content grow at the
+    /// authority's expense; `update_combo` grows them the same way.
+    pub fn resize_combo(ctx: Context<ResizeCombo>) -> Result<()> {
+        emit!(ComboResized {
+            combo: ctx.accounts.combo_pda.key(),
+            space: ctx.accounts.combo_pda.to_account_info().data_len() as u32,
+        });
+        Ok(())
+    }
+
+    /// Writes a new version of the combo, archiving the current one into a
+    /// `ComboHistory` entry that links back to the entry before it. The new
+    /// version starts with no verifications. Combos still sized to their
+    /// content grow to full size first, charging the authority the rent.
+    /// The combo's hash index moves to the new hash, which must not belong
+    /// to another combo.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        validate_combo_update(&ctx, damage, move_count, &moves)
     )]
-    pub combo_pda: Account<'info, ComboAccount>,
-    pub system_program: Program<'info, System>,
-    #[account(address = sysvar::rent::ID)]
-    pub rent: Sysvar<'info, Rent>,
-}
+    pub fn update_combo(
+        ctx: Context<UpdateCombo>,
+        damage: u32,
+        move_count: u8,
+        moves: Vec<u8>,
+    ) -> Result<()> {
+        let meter_gain = simulate_combo(&ctx.accounts.frame_data, &moves)?.meter_gain;
+        let difficulty = combo_difficulty(&ctx.accounts.frame_data, &moves)?;
+        let derived = derived_tags(&ctx.accounts.frame_data, &moves)?;
+        let now = Clock::get()?.unix_timestamp;
+        let combo = &mut ctx.accounts.combo_pda;
 
-#[derive(Accounts)]
-pub struct VerifyCombo<'info> {
-    #[account(mut)]
-    pub combo_pda: Account<'info, ComboAccount>,
-    /// CHECK
-    #[account(signer)]
-    pub verifier: UncheckedAccount<'info>,
-}
+        let history = &mut ctx.accounts.history;
+        history.layout_version = ComboHistory::LAYOUT_VERSION;
+        history.combo = combo.key();
+        history.version = combo.version;
+        history.combo_hash = combo.combo_hash;
+        history.damage = combo.damage;
+        history.meter_gain = combo.meter_gain;
+        history.moves = combo.moves.clone();
+        history.verification_count = combo.verification_count;
+        history.archived_at = now;
+        history.previous = combo.last_history;
+        history.bump = ctx.bumps.history;
 
-#[derive(Accounts)]
-pub struct CloseCombo<'info> {
-    #[account(mut)]
-    pub combo_pda: Account<'info, ComboAccount>,
-    #[account(signer)]
-    pub authority: AccountInfo<'info>,
-    /// CHECK
-    pub destination: UncheckedAccount<'info>,
-}
+        combo.last_history = ctx.accounts.history.key();
+        increment(&mut combo.version).map_err(ComboError::from)?;
+        combo.game_version = ctx.accounts.config.game_version;
+        combo.stale = false;
+        combo.damage = damage;
+        combo.meter_gain = meter_gain;
+        combo.move_count = move_count;
+        combo.moves = moves;
+        combo.timestamp = now;
+        combo.verification_count = 0;
+        combo.last_verified = 0;
+        combo.difficulty = difficulty;
+        // Claimed tags carry over; derived ones follow the new moves.
+        combo.tags = (combo.tags & !DERIVED_COMBO_TAGS) | derived;
+        combo.combo_hash = compute_combo_seed(
+            combo.name.as_bytes(),
+            damage,
+            meter_gain,
+            move_count,
+            combo.character_id,
+            &combo.moves,
+        );
+        require!(
+            combo.combo_hash != ctx.accounts.history.combo_hash,
+            ComboError::DuplicateCombo
+        );
+        claim_combo_hash(
+            &ctx.accounts.hash_index,
+            combo.key(),
+            &combo.combo_hash,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+        )?;
 
-#[account]
-pub struct ComboAccount {
-    pub authority: Pubkey,
-    pub character_id: u8,
-    pub name: String,
-    pub damage: u32,
-    pub meter_gain: u32,
-    pub move_count: u8,
-    pub timestamp: i64,
-    pub combo_hash: [u8; 32],
-    pub verification_count: u32,
-    pub last_verified: i64,
-    pub bump: u8,
-}
+        emit!(ComboUpdated {
+            combo: combo.key(),
+            version: combo.version,
+            previous_hash: ctx.accounts.history.combo_hash,
+            combo_hash: combo.combo_hash,
+            timestamp: now,
+        });
 
-#[event]
-pub struct ComboCreated {
-    pub combo: Pubkey,
-    pub authority: Pubkey,
-    pub character_id: u8,
-    pub damage: u32,
-    pub timestamp: i64,
-}
+        Ok(())
+    }
 
-#[event]
-pub struct ComboVerified {
-    pub combo: Pubkey,
-    pub moves_count: u8,
-    pub timestamp: i64,
-}
+    /// Stores the combo's moves and input timing in a zero-copy account so
+    /// timed verification reads fixed-size arrays instead of deserializing
+    /// vectors.
+    #[access_control(validate_combo_data_attach(&ctx, &timing))]
+    pub fn attach_combo_data(ctx: Context<AttachComboData>, timing: Vec<u16>) -> Result<()> {
+        let combo = &mut ctx.accounts.combo_pda;
+        let mut data = ctx.accounts.combo_data.load_init()?;
 
-#[error]
-pub enum ComboError {
-    #[msg("Combo name too long")]
-    NameTooLong,
-    #[msg("Invalid damage value")]
-    InvalidDamage,
-    #[msg("Invalid meter gain value")]
-    InvalidMeterGain,
+        data.layout_version = ComboDataAccount::LAYOUT_VERSION;
+        data.combo = combo.key();
+        data.move_count = combo.move_count;
+        data.moves[..combo.moves.len()].copy_from_slice(&combo.moves);
+        let mut frames = [0; MAX_COMBO_MOVES];
+        frames[..timing.len()].copy_from_slice(&timing);
+        data.timing = frames;
+        data.bump = ctx.bumps.combo_data;
+
+        combo.combo_data = ctx.accounts.combo_data.key();
+
+        Ok(())
+    }
+
+    /// Timed counterpart of `verify_combo`: the submitted moves and timing
+    /// must match the stored ones and replay to the stored damage and meter.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        check_verifier(
+            &ctx.accounts.combo_pda,
+            &ctx.accounts.verifier_registry,
+            ctx.accounts.verifier.key,
+        )
+        not_frozen(&ctx.accounts.combo_pda)
+    )]
+    pub fn verify_combo_data(
+        ctx: Context<VerifyComboData>,
+        moves: Vec<u8>,
+        timing: Vec<u16>,
+    ) -> Result<()> {
+        if mark_if_stale(&mut ctx.accounts.combo_pda, &ctx.accounts.config)? {
+            return Ok(());
+        }
+        let data = ctx.accounts.combo_data.load()?;
+        let count = data.move_count as usize;
+        // Copied out, as the packed layout leaves `timing` unaligned.
+        let frames = data.timing;
+        require!(moves == data.moves[..count], ComboError::MoveMismatch);
+        require!(timing == frames[..count], ComboError::InvalidTiming);
+
+        let outcome = simulate_timed_combo(
+            &ctx.accounts.frame_data,
+            &data.moves[..count],
+            &frames[..count],
+        )?;
+        let combo = &mut ctx.accounts.combo_pda;
+        require_eq!(outcome.damage, combo.damage, ComboError::DamageMismatch);
+        require_eq!(outcome.meter_gain, combo.meter_gain, ComboError::MeterGainMismatch);
+
+        let now = Clock::get()?.unix_timestamp;
+        let counted = record_verification(
+            &mut ctx.accounts.receipt,
+            combo,
+            ctx.accounts.verifier.key(),
+            ctx.bumps.receipt,
+            now,
+            ctx.accounts.config.verification_cooldown,
+        )?;
+        if counted {
+            increment(&mut combo.verification_count).map_err(ComboError::from)?;
+        }
+        combo.last_verified = now;
+
+        event_sink!(ctx).emit(&ComboVerified {
+            combo: combo.key(),
+            verifier: ctx.accounts.verifier.key(),
+            moves_count: count as u8,
+            counted,
+            timestamp: now,
+        })?;
+
+        Ok(())
+    }
+
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        validate_nft_mint(&ctx, &base_uri)
+    )]
+    pub fn mint_combo_nft(ctx: Context<MintComboNft>, base_uri: String) -> Result<()> {
+        let combo = &ctx.accounts.combo_pda;
+        let name_seed = combo_name_seed(&combo.name);
+        let signer_seeds: &[&[u8]] = &[
+            b"combo",
+            combo.creator.as_ref(),
+            &name_seed,
+            &[combo.bump],
+        ];
+
+        token::mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    to: ctx.accounts.nft_token_account.to_account_info(),
+                    authority: ctx.accounts.combo_pda.to_account_info(),
+                },
+                &[signer_seeds],
+            ),
+            1,
+        )?;
+
+        let data = DataV2 {
+            name: nft_name(&combo.name),
+            symbol: NFT_SYMBOL.to_string(),
+            uri: nft_uri(&base_uri, combo),
+            seller_fee_basis_points: 0,
+            creators: Some(vec![Creator {
+                address: combo.authority,
+                verified: false,
+                share: 100,
+            }]),
+            collection: None,
+            uses: None,
+        };
+
+        create_metadata_accounts_v3(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                CreateMetadataAccountsV3 {
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    mint_authority: ctx.accounts.combo_pda.to_account_info(),
+                    payer: ctx.accounts.authority.to_account_info(),
+                    update_authority: ctx.accounts.combo_pda.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                &[signer_seeds],
+            ),
+            data,
+            false,
+            true,
+            None,
+        )?;
+
+        create_master_edition_v3(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_metadata_program.to_account_info(),
+                CreateMasterEditionV3 {
+                    edition: ctx.accounts.master_edition.to_account_info(),
+                    mint: ctx.accounts.nft_mint.to_account_info(),
+                    update_authority: ctx.accounts.combo_pda.to_account_info(),
+                    mint_authority: ctx.accounts.combo_pda.to_account_info(),
+                    payer: ctx.accounts.authority.to_account_info(),
+                    metadata: ctx.accounts.metadata.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    rent: ctx.accounts.rent.to_account_info(),
+                },
+                &[signer_seeds],
+            ),
+            Some(0),
+        )?;
+
+        let combo = &mut ctx.accounts.combo_pda;
+        combo.nft_mint = ctx.accounts.nft_mint.key();
+
+        emit!(ComboNftMinted {
+            combo: combo.key(),
+            mint: combo.nft_mint,
+            authority: combo.authority,
+            combo_hash: combo.combo_hash,
+        });
+
+        Ok(())
+    }
+
+    /// Creates the Bubblegum tree every compressed combo is minted into,
+    /// delegated to `cnft_config`. Only the config's admin may, since there
+    /// is just one.
+    pub fn initialize_cnft_tree(
+        ctx: Context<InitializeCnftTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.cnft_config;
+        config.layout_version = CnftConfig::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.merkle_tree = ctx.accounts.merkle_tree.key();
+        config.minted = 0;
+        config.bump = ctx.bumps.cnft_config;
+
+        let signer_seeds: &[&[u8]] = &[b"cnft_config", &[config.bump]];
+        let accounts = &ctx.accounts;
+        CreateTreeConfigCpi::new(
+            &accounts.bubblegum_program,
+            CreateTreeConfigCpiAccounts {
+                tree_config: &accounts.tree_authority,
+                merkle_tree: &accounts.merkle_tree,
+                payer: &accounts.admin,
+                tree_creator: &accounts.cnft_config.to_account_info(),
+                log_wrapper: &accounts.log_wrapper,
+                compression_program: &accounts.compression_program,
+                system_program: &accounts.system_program,
+            },
+            CreateTreeConfigInstructionArgs {
+                max_depth,
+                max_buffer_size,
+                public: Some(false),
+            },
+        )
+        .invoke_signed(&[signer_seeds])?;
+
+        Ok(())
+    }
+
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        validate_cnft_mint(&ctx, &base_uri)
+    )]
+    pub fn mint_combo_cnft(ctx: Context<MintComboCnft>, base_uri: String) -> Result<()> {
+        let combo = &ctx.accounts.combo_pda;
+        let message = MetadataArgs {
+            name: nft_name(&combo.name),
+            symbol: NFT_SYMBOL.to_string(),
+            uri: nft_uri(&base_uri, combo),
+            seller_fee_basis_points: 0,
+            primary_sale_happened: false,
+            is_mutable: false,
+            edition_nonce: None,
+            token_standard: Some(TokenStandard::NonFungible),
+            collection: None,
+            uses: None,
+            token_program_version: TokenProgramVersion::Original,
+            creators: vec![CompressedCreator {
+                address: combo.authority,
+                verified: false,
+                share: 100,
+            }],
+        };
+
+        let config_bump = ctx.accounts.cnft_config.bump;
+        let signer_seeds: &[&[u8]] = &[b"cnft_config", &[config_bump]];
+        let accounts = &ctx.accounts;
+        MintV1Cpi::new(
+            &accounts.bubblegum_program,
+            MintV1CpiAccounts {
+                tree_config: &accounts.tree_authority,
+                leaf_owner: &accounts.authority,
+                leaf_delegate: &accounts.authority,
+                merkle_tree: &accounts.merkle_tree,
+                payer: &accounts.authority,
+                tree_creator_or_delegate: &accounts.cnft_config.to_account_info(),
+                log_wrapper: &accounts.log_wrapper,
+                compression_program: &accounts.compression_program,
+                system_program: &accounts.system_program,
+            },
+            MintV1InstructionArgs { metadata: message },
+        )
+        .invoke_signed(&[signer_seeds])?;
+
+        let config = &mut ctx.accounts.cnft_config;
+        increment(&mut config.minted).map_err(ComboError::from)?;
+
+        let combo = &mut ctx.accounts.combo_pda;
+        combo.cnft_tree = config.merkle_tree;
+
+        emit!(ComboCnftMinted {
+            combo: combo.key(),
+            merkle_tree: config.merkle_tree,
+            leaf_index: config.minted - 1,
+            authority: combo.authority,
+            combo_hash: combo.combo_hash,
+        });
+
+        Ok(())
+    }
+
+    /// Hands the combo to `new_authority`. With `require_accept` the new
+    /// authority has to sign `accept_combo_transfer` before it takes effect,
+    /// so a mistyped address can't take the combo.
+    #[access_control(not_frozen(&ctx.accounts.combo_pda))]
+    pub fn transfer_combo(
+        ctx: Context<TransferCombo>,
+        new_authority: Pubkey,
+        require_accept: bool,
+    ) -> Result<()> {
+        let combo = &mut ctx.accounts.combo_pda;
+        if require_accept {
+            combo.pending_authority = new_authority;
+            emit!(ComboTransferProposed {
+                combo: combo.key(),
+                authority: combo.authority,
+                pending_authority: new_authority,
+            });
+            return Ok(());
+        }
+
+        let previous = combo.authority;
+        combo.authority = new_authority;
+        combo.pending_authority = Pubkey::default();
+        emit!(ComboTransferred {
+            combo: combo.key(),
+            previous_authority: previous,
+            authority: new_authority,
+        });
+
+        Ok(())
+    }
+
+    #[access_control(not_frozen(&ctx.accounts.combo_pda))]
+    pub fn accept_combo_transfer(ctx: Context<AcceptComboTransfer>) -> Result<()> {
+        let combo = &mut ctx.accounts.combo_pda;
+        let previous = combo.authority;
+        combo.authority = combo.pending_authority;
+        combo.pending_authority = Pubkey::default();
+        emit!(ComboTransferred {
+            combo: combo.key(),
+            previous_authority: previous,
+            authority: combo.authority,
+        });
+
+        Ok(())
+    }
+
+    /// Lists the combo for sale at `price`, in lamports or, with
+    /// `payment_mint`, in that SPL token.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        not_frozen(&ctx.accounts.combo_pda)
+    )]
+    pub fn list_combo(
+        ctx: Context<ListCombo>,
+        price: u64,
+        payment_mint: Option<Pubkey>,
+    ) -> Result<()> {
+        require_gt!(price, 0, ComboError::InvalidPrice);
+        let listing = &mut ctx.accounts.listing;
+        listing.layout_version = Listing::LAYOUT_VERSION;
+        listing.combo = ctx.accounts.combo_pda.key();
+        listing.seller = *ctx.accounts.authority.key;
+        listing.price = price;
+        listing.payment_mint = payment_mint.unwrap_or_default();
+        listing.bump = ctx.bumps.listing;
+
+        event_sink!(ctx).emit(&ComboListed {
+            combo: listing.combo,
+            seller: listing.seller,
+            price,
+            payment_mint: listing.payment_mint,
+        })?;
+
+        Ok(())
+    }
+
+    pub fn delist_combo(ctx: Context<DelistCombo>) -> Result<()> {
+        event_sink!(ctx).emit(&ComboDelisted {
+            combo: ctx.accounts.combo_pda.key(),
+            seller: ctx.accounts.listing.seller,
+        })?;
+        Ok(())
+    }
+
+    /// Buys a combo listed in lamports. The payment, the authority change
+    /// and closing the listing happen in one instruction.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        not_frozen(&ctx.accounts.combo_pda)
+    )]
+    pub fn buy_combo(ctx: Context<BuyCombo>) -> Result<()> {
+        let listing = &ctx.accounts.listing;
+        require_keys_eq!(listing.payment_mint, Pubkey::default(), ComboError::WrongPaymentMint);
+
+        let (royalty, proceeds) =
+            split_sale(listing.price, &ctx.accounts.combo_pda, listing.seller, &ctx.accounts.config);
+        if royalty > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.buyer.to_account_info(),
+                        to: ctx.accounts.creator.to_account_info(),
+                    },
+                ),
+                royalty,
+            )?;
+            emit_royalty(
+                &event_sink!(ctx),
+                &ctx.accounts.combo_pda,
+                royalty,
+                listing.payment_mint,
+            )?;
+        }
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.buyer.to_account_info(),
+                    to: ctx.accounts.seller.to_account_info(),
+                },
+            ),
+            proceeds,
+        )?;
+
+        complete_sale(
+            &event_sink!(ctx),
+            &mut ctx.accounts.combo_pda,
+            listing,
+            *ctx.accounts.buyer.key,
+        )
+    }
+
+    /// Token counterpart of `buy_combo`, for SPL Token and Token-2022
+    /// payment mints. The buyer pays the listed price; a transfer fee
+    /// comes out of what the seller and creator receive.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        not_frozen(&ctx.accounts.combo_pda)
+    )]
+    pub fn buy_combo_with_token(ctx: Context<BuyComboWithToken>) -> Result<()> {
+        let listing = &ctx.accounts.listing;
+        require_keys_neq!(listing.payment_mint, Pubkey::default(), ComboError::WrongPaymentMint);
+        require!(
+            token_compat::supported_mint(&ctx.accounts.payment_mint)?,
+            ComboError::UnsupportedMint
+        );
+
+        let (royalty, proceeds) =
+            split_sale(listing.price, &ctx.accounts.combo_pda, listing.seller, &ctx.accounts.config);
+        if royalty > 0 {
+            token_compat::transfer(
+                &ctx.accounts.token_program,
+                &ctx.accounts.buyer_token,
+                &ctx.accounts.creator_token,
+                &ctx.accounts.payment_mint,
+                &ctx.accounts.buyer,
+                &[],
+                royalty,
+            )?;
+            emit_royalty(
+                &event_sink!(ctx),
+                &ctx.accounts.combo_pda,
+                royalty,
+                listing.payment_mint,
+            )?;
+        }
+        token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.buyer_token,
+            &ctx.accounts.seller_token,
+            &ctx.accounts.payment_mint,
+            &ctx.accounts.buyer,
+            &[],
+            proceeds,
+        )?;
+
+        complete_sale(
+            &event_sink!(ctx),
+            &mut ctx.accounts.combo_pda,
+            listing,
+            *ctx.accounts.buyer.key,
+        )
+    }
+
+    /// Opens an English auction in lamports that ends `duration` seconds
+    /// from now. Bids landing within `extension` seconds of the end push the
+    /// end back to `extension` seconds after the bid.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        not_frozen(&ctx.accounts.combo_pda)
+    )]
+    pub fn create_auction(
+        ctx: Context<CreateAuction>,
+        reserve_price: u64,
+        min_increment: u64,
+        duration: i64,
+        extension: i64,
+    ) -> Result<()> {
+        require!(reserve_price > 0 && min_increment > 0, ComboError::InvalidPrice);
+        require!(duration > 0 && extension >= 0, ComboError::InvalidAuctionWindow);
+        let now = Clock::get()?.unix_timestamp;
+
+        let auction = &mut ctx.accounts.auction;
+        auction.layout_version = Auction::LAYOUT_VERSION;
+        auction.combo = ctx.accounts.combo_pda.key();
+        auction.seller = *ctx.accounts.authority.key;
+        auction.reserve_price = reserve_price;
+        auction.min_increment = min_increment;
+        auction.end_time = add(now, duration).map_err(ComboError::from)?;
+        auction.extension = extension;
+        auction.highest_bid = 0;
+        auction.highest_bidder = Pubkey::default();
+        auction.bump = ctx.bumps.auction;
+
+        event_sink!(ctx).emit(&AuctionCreated {
+            combo: auction.combo,
+            seller: auction.seller,
+            reserve_price,
+            end_time: auction.end_time,
+        })?;
+
+        Ok(())
+    }
+
+    /// Escrows `amount` in the auction account and refunds the bidder it
+    /// outbids.
+    #[access_control(not_paused(&ctx.accounts.config))]
+    pub fn place_bid(ctx: Context<PlaceBid>, amount: u64) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let auction = &ctx.accounts.auction;
+        require_gt!(auction.end_time, now, ComboError::AuctionEnded);
+        require_gte!(amount, auction.reserve_price, ComboError::BidTooLow);
+        if auction.highest_bid > 0 {
+            require_gte!(
+                amount,
+                auction.highest_bid.saturating_add(auction.min_increment),
+                ComboError::BidTooLow
+            );
+        }
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.bidder.to_account_info(),
+                    to: ctx.accounts.auction.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+        if auction.highest_bid > 0 {
+            move_lamports(
+                &ctx.accounts.auction.to_account_info(),
+                &ctx.accounts.previous_bidder,
+                auction.highest_bid,
+            )?;
+        }
+
+        let auction = &mut ctx.accounts.auction;
+        auction.highest_bid = amount;
+        auction.highest_bidder = *ctx.accounts.bidder.key;
+        if auction.end_time - now < auction.extension {
+            auction.end_time = add(now, auction.extension).map_err(ComboError::from)?;
+        }
+
+        event_sink!(ctx).emit(&BidPlaced {
+            combo: auction.combo,
+            bidder: auction.highest_bidder,
+            amount,
+            end_time: auction.end_time,
+        })?;
+
+        Ok(())
+    }
+
+    /// Ends the auction once its time is up. The winner gets the combo and
+    /// the seller the winning bid, less the creator's royalty. Without a
+    /// winning bid, or when the seller no longer owns the combo, the combo
+    /// stays put and any bid is refunded, as it is for a frozen combo.
+    /// Anyone can settle.
+    pub fn settle_auction(ctx: Context<SettleAuction>) -> Result<()> {
+        let events = event_sink!(ctx);
+        settle(
+            &events,
+            &mut ctx.accounts.combo_pda,
+            &ctx.accounts.auction,
+            &ctx.accounts.seller,
+            &ctx.accounts.creator,
+            &ctx.accounts.highest_bidder,
+            &ctx.accounts.config,
+        )
+    }
+
+    /// Settles an ended auction like `settle_auction`, paying `caller`
+    /// `CRANK_REWARD` out of the auction's rent so a seller or winner gone
+    /// offline can't leave the combo and the bid stranded. Takes no
+    /// signature, so it can run from any crank or a Clockwork thread
+    /// triggered at `end_time`.
+    pub fn finalize_auction_if_expired(ctx: Context<FinalizeExpiredAuction>) -> Result<()> {
+        let events = event_sink!(ctx);
+        settle(
+            &events,
+            &mut ctx.accounts.combo_pda,
+            &ctx.accounts.auction,
+            &ctx.accounts.seller,
+            &ctx.accounts.creator,
+            &ctx.accounts.highest_bidder,
+            &ctx.accounts.config,
+        )?;
+        let info = ctx.accounts.auction.to_account_info();
+        move_lamports(&info, &ctx.accounts.caller, info.lamports().min(CRANK_REWARD))
+    }
+
+    /// Lends the combo to `borrower` for `duration` seconds against an
+    /// upfront `fee` in lamports. Both sides sign. The borrower only gains
+    /// `use_combo_in_match`; the combo can't be rented again until the
+    /// current rental runs out.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        not_frozen(&ctx.accounts.combo_pda)
+    )]
+    pub fn rent_combo(ctx: Context<RentCombo>, fee: u64, duration: i64) -> Result<()> {
+        require_gt!(duration, 0, ComboError::InvalidRentalDuration);
+        let now = Clock::get()?.unix_timestamp;
+        require_gte!(now, ctx.accounts.rental.expires_at, ComboError::AlreadyRented);
+
+        if fee > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.borrower.to_account_info(),
+                        to: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                fee,
+            )?;
+        }
+
+        let rental = &mut ctx.accounts.rental;
+        rental.layout_version = Rental::LAYOUT_VERSION;
+        rental.combo = ctx.accounts.combo_pda.key();
+        rental.owner = *ctx.accounts.authority.key;
+        rental.borrower = *ctx.accounts.borrower.key;
+        rental.fee = fee;
+        rental.expires_at = add(now, duration).map_err(ComboError::from)?;
+        rental.uses = 0;
+        rental.bump = ctx.bumps.rental;
+
+        event_sink!(ctx).emit(&ComboRented {
+            combo: rental.combo,
+            owner: rental.owner,
+            borrower: rental.borrower,
+            fee,
+            expires_at: rental.expires_at,
+        })?;
+
+        Ok(())
+    }
+
+    /// Records the borrower using a rented combo in a match.
+    pub fn use_combo_in_match(ctx: Context<UseComboInMatch>, match_id: [u8; 32]) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let rental = &mut ctx.accounts.rental;
+        require_gt!(rental.expires_at, now, ComboError::RentalExpired);
+        increment(&mut rental.uses).map_err(ComboError::from)?;
+
+        emit!(ComboUsedInMatch {
+            combo: rental.combo,
+            borrower: rental.borrower,
+            match_id,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Closes a rental once it has run out, returning its rent to the
+    /// borrower who paid it, less `CRANK_REWARD` for `caller`. Takes no
+    /// signature, so it can run from any crank or a Clockwork thread
+    /// triggered at `expires_at`.
+    pub fn finalize_rental_if_expired(ctx: Context<FinalizeExpiredRental>) -> Result<()> {
+        let rental = &ctx.accounts.rental;
+        require_gte!(
+            Clock::get()?.unix_timestamp,
+            rental.expires_at,
+            ComboError::AlreadyRented
+        );
+        let info = rental.to_account_info();
+        move_lamports(&info, &ctx.accounts.caller, info.lamports().min(CRANK_REWARD))?;
+
+        event_sink!(ctx).emit(&RentalClosed {
+            combo: rental.combo,
+            owner: rental.owner,
+            borrower: rental.borrower,
+            uses: rental.uses,
+        })
+    }
+
+    /// Creates a set of combos whose entries are the leaves
+    /// `hashv([index as u16 le, combo_hash])` of `merkle_root`. Wallets that
+    /// hold a combo for every entry can claim `reward` lamports once, paid
+    /// from lamports deposited into the collection account.
+    pub fn create_collection(
+        ctx: Context<CreateCollection>,
+        name: String,
+        merkle_root: [u8; 32],
+        entry_count: u16,
+        reward: u64,
+    ) -> Result<()> {
+        require_gte!(MAX_COLLECTION_NAME_LEN, name.len(), ComboError::NameTooLong);
+        require!(
+            entry_count > 0 && entry_count as usize <= MAX_COLLECTION_ENTRIES,
+            ComboError::InvalidCollection
+        );
+        let collection = &mut ctx.accounts.collection;
+        collection.layout_version = Collection::LAYOUT_VERSION;
+        collection.name = name;
+        collection.merkle_root = merkle_root;
+        collection.entry_count = entry_count;
+        collection.reward = reward;
+        collection.bump = ctx.bumps.collection;
+
+        emit!(CollectionCreated {
+            collection: collection.key(),
+            merkle_root,
+            entry_count,
+            reward,
+        });
+
+        Ok(())
+    }
+
+    pub fn fund_collection(ctx: Context<FundCollection>, amount: u64) -> Result<()> {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.funder.to_account_info(),
+                    to: ctx.accounts.collection.to_account_info(),
+                },
+            ),
+            amount,
+        )
+    }
+
+    /// Proves the combo is entry `index` of the collection and records it
+    /// for the signer.
+    pub fn register_in_collection(
+        ctx: Context<RegisterInCollection>,
+        index: u16,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let collection = &ctx.accounts.collection;
+        let combo = &ctx.accounts.combo_pda;
+        require_gt!(collection.entry_count, index, ComboError::InvalidCollection);
+        let leaf = hashv(&[&index.to_le_bytes(), &combo.combo_hash]).to_bytes();
+        require!(
+            verify_merkle_proof(&proof, collection.merkle_root, leaf),
+            ComboError::InvalidMerkleProof
+        );
+
+        let progress = &mut ctx.accounts.progress;
+        progress.layout_version = CollectionProgress::LAYOUT_VERSION;
+        progress.collection = collection.key();
+        progress.owner = *ctx.accounts.authority.key;
+        progress.entries[index as usize] = combo.combo_hash;
+        progress.registered |= 1 << index;
+        progress.bump = ctx.bumps.progress;
+
+        emit!(CollectionEntryRegistered {
+            collection: progress.collection,
+            owner: progress.owner,
+            combo: combo.key(),
+            index,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the completion reward. The signer still has to hold every
+    /// registered combo: pass them as remaining accounts in entry order.
+    #[access_control(not_paused(&ctx.accounts.config))]
+    pub fn claim_completion_reward<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClaimCompletionReward<'info>>,
+    ) -> Result<()> {
+        let collection = &ctx.accounts.collection;
+        let progress = &ctx.accounts.progress;
+        let count = collection.entry_count as usize;
+        require!(!progress.claimed, ComboError::RewardAlreadyClaimed);
+        require_eq!(
+            progress.registered.count_ones() as usize,
+            count,
+            ComboError::CollectionIncomplete
+        );
+        require_eq!(ctx.remaining_accounts.len(), count, ComboError::CollectionIncomplete);
+        for (info, entry) in ctx.remaining_accounts.iter().zip(&progress.entries) {
+            let combo: Account<ComboAccount> = Account::try_from(info)?;
+            require_keys_eq!(combo.authority, progress.owner, ComboError::Unauthorized);
+            require!(combo.combo_hash == *entry, ComboError::CollectionIncomplete);
+        }
+
+        let reward = collection.reward;
+        move_lamports(
+            &ctx.accounts.collection.to_account_info(),
+            &ctx.accounts.owner,
+            reward,
+        )?;
+        ctx.accounts.progress.claimed = true;
+
+        emit!(CollectionCompleted {
+            collection: ctx.accounts.collection.key(),
+            owner: *ctx.accounts.owner.key,
+            reward,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the reward tokens `upgrade_combo` burns to reach silver and
+    /// gold. A cost of zero turns that upgrade off.
+    pub fn set_tier_costs(ctx: Context<SetTierCosts>, tier_costs: [u64; 2]) -> Result<()> {
+        ctx.accounts.reward_config.tier_costs = tier_costs;
+        Ok(())
+    }
+
+    /// Raises the combo one tier by burning the reward tokens
+    /// `RewardConfig::tier_costs` asks for it.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        not_frozen(&ctx.accounts.combo_pda)
+    )]
+    pub fn upgrade_combo(ctx: Context<UpgradeCombo>) -> Result<()> {
+        let tier = ctx.accounts.combo_pda.tier.next().ok_or(ComboError::MaxTier)?;
+        let cost = ctx.accounts.reward_config.tier_costs[tier as usize - 1];
+        require_gt!(cost, 0, ComboError::TierUpgradeDisabled);
+
+        token_interface::burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token_interface::Burn {
+                    mint: ctx.accounts.mint.to_account_info(),
+                    from: ctx.accounts.authority_token.to_account_info(),
+                    authority: ctx.accounts.authority.to_account_info(),
+                },
+            ),
+            cost,
+        )?;
+
+        let combo = &mut ctx.accounts.combo_pda;
+        combo.tier = tier;
+
+        emit!(ComboUpgraded {
+            combo: combo.key(),
+            authority: combo.authority,
+            tier,
+            burned: cost,
+            duplicate: Pubkey::default(),
+        });
+
+        Ok(())
+    }
+
+    /// Raises the combo one tier by burning `duplicate`, another of the
+    /// signer's combos with the same character and moves at no lower tier.
+    /// The duplicate's hash and name indexes are released. The duplicate is
+    /// held to the same checks as a fusion parent.
+    #[access_control(
+        not_paused(&ctx.accounts.config)
+        validate_duplicate(&ctx.accounts.combo_pda, &ctx.accounts.duplicate)
+    )]
+    pub fn upgrade_combo_with_duplicate(ctx: Context<UpgradeComboWithDuplicate>) -> Result<()> {
+        let tier = ctx.accounts.combo_pda.tier.next().ok_or(ComboError::MaxTier)?;
+        let duplicate = ctx.accounts.duplicate.key();
+
+        let combo = &mut ctx.accounts.combo_pda;
+        combo.tier = tier;
+
+        emit!(ComboUpgraded {
+            combo: combo.key(),
+            authority: combo.authority,
+            tier,
+            burned: 0,
+            duplicate,
+        });
+
+        Ok(())
+    }
+
+    /// Enters the combo on the leaderboard for `tier`, open to combos of
+    /// that tier or higher, ranked by damage. Entering again updates the
+    /// combo's score.
+    #[access_control(
+        not_frozen(&ctx.accounts.combo_pda)
+        min_tier(&ctx.accounts.combo_pda, tier)
+    )]
+    pub fn submit_to_leaderboard(ctx: Context<SubmitToLeaderboard>, tier: ComboTier) -> Result<()> {
+        let combo = &ctx.accounts.combo_pda;
+        let entry = LeaderboardEntry {
+            combo: combo.key(),
+            score: combo.damage,
+        };
+        let board = &mut ctx.accounts.leaderboard;
+        if board.layout_version == 0 {
+            board.layout_version = TierLeaderboard::LAYOUT_VERSION;
+            board.tier = tier;
+            board.bump = ctx.bumps.leaderboard;
+        }
+        let rank = board.insert(entry);
+
+        emit!(LeaderboardUpdated {
+            tier,
+            combo: entry.combo,
+            score: entry.score,
+            rank,
+        });
+
+        Ok(())
+    }
+
+    /// Closes the combo along with its attached `ComboDataAccount`, which
+    /// must be passed as a remaining account so it is never orphaned. A
+    /// combo that is listed, or has a rental not yet finalized, can't be
+    /// closed: `delist_combo` and `finalize_rental_if_expired` come first.
+    /// The combo's `ComboHashIndex` and `ComboNameIndex` are closed as well,
+    /// freeing its hash and name. All rent goes to `destination`.
+    pub fn close_combo<'info>(ctx: Context<'_, '_, 'info, 'info, CloseCombo<'info>>) -> Result<()> {
+        let combo = &ctx.accounts.combo_pda;
+        let mut data_closed = combo.combo_data == Pubkey::default();
+        for info in ctx.remaining_accounts.iter() {
+            require_keys_eq!(*info.key, combo.combo_data, ComboError::UnknownChildAccount);
+            require!(info.owner == &crate::ID, ComboError::UnknownChildAccount);
+            close_account(info, &ctx.accounts.destination)?;
+            data_closed = true;
+        }
+        require!(data_closed, ComboError::ComboDataAttached);
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<ComboAccount>(ComboAccount::discriminator()),
+            Layout::new::<Listing>(Listing::discriminator()),
+            Layout::new::<Auction>(Auction::discriminator()),
+            Layout::new::<Rental>(Rental::discriminator()),
+            Layout::new::<Collection>(Collection::discriminator()),
+            Layout::new::<CollectionProgress>(CollectionProgress::discriminator()),
+            Layout::new::<RewardConfig>(RewardConfig::discriminator()),
+            Layout::new::<VerificationReceipt>(VerificationReceipt::discriminator()),
+            Layout::new::<CharacterGate>(CharacterGate::discriminator()),
+            Layout::new::<VerifierRegistry>(VerifierRegistry::discriminator()),
+            Layout::new::<VerifierQuota>(VerifierQuota::discriminator()),
+            Layout::new::<ComboHistory>(ComboHistory::discriminator()),
+            Layout::new::<ComboDataAccount>(ComboDataAccount::discriminator()),
+            Layout::new::<Config>(Config::discriminator()),
+            Layout::new::<CnftConfig>(CnftConfig::discriminator()),
+            Layout::new::<ComboHashIndex>(ComboHashIndex::discriminator()),
+            Layout::new::<CharacterIndex>(CharacterIndex::discriminator()),
+            Layout::new::<ComboFlag>(ComboFlag::discriminator()),
+            Layout::new::<ComboNameIndex>(ComboNameIndex::discriminator()),
+            Layout::new::<NameFilter>(NameFilter::discriminator()),
+            Layout::new::<TierLeaderboard>(TierLeaderboard::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<ComboError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+
+    /// Receives the event self-CPIs sent by `EventSink::emit`.
+    pub fn fallback<'info>(
+        program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> Result<()> {
+        event_cpi::handle_event_ix(program_id, accounts, data)
+    }
+}
+
+/// Seed component derived from the combo name. Names can be up to 64 bytes,
+/// which exceeds the 32-byte seed limit, so the PDA uses the name's hash.
+pub fn combo_name_seed(name: &str) -> [u8; 32] {
+    hash(name.as_bytes()).to_bytes()
+}
+
+/// Seed for the `ComboNameIndex`: the hash of the lowercased name, so names
+/// differing only in case collide.
+pub fn normalized_name_seed(name: &str) -> [u8; 32] {
+    hash(name.to_ascii_lowercase().as_bytes()).to_bytes()
+}
+
+/// The combo's `combo_hash`, which also seeds its `ComboHashIndex`.
+/// `meter_gain` is what `combo_sim::simulate_combo` derives from the moves.
+pub fn compute_combo_seed(
+    name: &[u8],
+    damage: u32,
+    meter_gain: u32,
+    move_count: u8,
+    character_id: u8,
+    moves: &[u8],
+) -> [u8; 32] {
+    let mut input = Vec::with_capacity(64 + moves.len());
+    input.extend_from_slice(name);
+    input.extend_from_slice(&damage.to_le_bytes());
+    input.extend_from_slice(&meter_gain.to_le_bytes());
+    input.push(move_count);
+    input.push(character_id);
+    input.extend_from_slice(moves);
+
+    let hash_result = hash(&input);
+    hash_result.to_bytes()
+}
+
+fn validate_combo_data(
+    ctx: &Context<CreateCombo>,
+    combo_name: &String,
+    damage: u32,
+    move_count: u8,
+    moves: &Vec<u8>,
+) -> Result<()> {
+    let limits = &ctx.accounts.config.limits;
+    check_name(limits, &ctx.accounts.name_filter, combo_name)?;
+    check_limits(limits, damage, move_count, moves)
+}
+
+/// Names are printable ASCII without surrounding spaces, and must not start
+/// with a banned prefix once lowercased. Uniqueness of the lowercased name
+/// per creator is enforced by the `ComboNameIndex` the instruction creates.
+fn check_name(limits: &ValidationLimits, filter: &NameFilter, name: &str) -> Result<()> {
+    require_gte!(limits.max_name_len as usize, name.len(), ComboError::NameTooLong);
+    require!(
+        is_printable(name) && !name.starts_with(' ') && !name.ends_with(' '),
+        ComboError::InvalidNameCharset
+    );
+    let normalized = name.to_ascii_lowercase();
+    require!(
+        !filter.prefixes.iter().any(|prefix| normalized.starts_with(prefix.as_str())),
+        ComboError::BannedName
+    );
+    Ok(())
+}
+
+fn is_printable(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| (b' '..=b'~').contains(&b))
+}
+
+/// Limits shared by `create_combo` and `update_combo`.
+fn check_limits(limits: &ValidationLimits, damage: u32, move_count: u8, moves: &[u8]) -> Result<()> {
+    require!(damage > 0 && damage <= limits.max_damage, ComboError::InvalidDamage);
+    require!(
+        move_count > 0 && move_count <= limits.max_moves,
+        ComboError::InvalidMoveCount
+    );
+    require_eq!(moves.len(), move_count as usize, ComboError::InvalidMoveCount);
+    Ok(())
+}
+
+/// Limits can be tightened or loosened, but never past what the account
+/// layouts have room for.
+fn validate_limits(limits: &ValidationLimits) -> Result<()> {
+    require_gt!(limits.max_damage, 0, ComboError::InvalidLimits);
+    require_gt!(limits.max_meter_gain, 0, ComboError::InvalidLimits);
+    require!(
+        limits.max_moves > 0 && limits.max_moves as usize <= MAX_COMBO_MOVES,
+        ComboError::InvalidLimits
+    );
+    require!(
+        limits.max_name_len > 0 && limits.max_name_len as usize <= MAX_COMBO_NAME_LEN,
+        ComboError::InvalidLimits
+    );
+    Ok(())
+}
+
+fn validate_limit_signers(signers: &[Pubkey], threshold: u8) -> Result<()> {
+    require!(
+        !signers.is_empty() && signers.len() <= MAX_LIMIT_SIGNERS,
+        ComboError::InvalidSigners
+    );
+    require!(
+        threshold > 0 && threshold as usize <= signers.len(),
+        ComboError::InvalidSigners
+    );
+    for (i, signer) in signers.iter().enumerate() {
+        require!(!signers[..i].contains(signer), ComboError::InvalidSigners);
+    }
+    Ok(())
+}
+
+/// Counts distinct configured signers among the signing remaining accounts.
+fn require_limit_signers(config: &Config, accounts: &[AccountInfo]) -> Result<()> {
+    let signers = &config.signers[..config.signer_count as usize];
+    let mut approved = [false; MAX_LIMIT_SIGNERS];
+    for account in accounts.iter().filter(|a| a.is_signer) {
+        if let Some(i) = signers.iter().position(|s| s == account.key) {
+            approved[i] = true;
+        }
+    }
+    let approvals = approved.iter().filter(|a| **a).count();
+    require_gte!(approvals, config.threshold as usize, ComboError::NotEnoughSigners);
+    Ok(())
+}
+
+/// Rejects combos whose claimed damage differs from what the move sequence
+/// produces when simulated against the registered frame data, with damage
+/// prorated over the combo's hits.
+fn validate_against_frame_data(
+    ctx: &Context<CreateCombo>,
+    damage: u32,
+    moves: &Vec<u8>,
+) -> Result<()> {
+    let outcome = simulate_combo(&ctx.accounts.frame_data, moves)?;
+    require_eq!(outcome.damage, damage, ComboError::DamageMismatch);
+    require_gte!(
+        ctx.accounts.config.limits.max_meter_gain,
+        outcome.meter_gain,
+        ComboError::InvalidMeterGain
+    );
+    Ok(())
+}
+
+/// Tags must be known bits, and the derived ones must match the moves.
+fn validate_tags(frame_data: &CharacterFrameData, tags: u32, moves: &[u8]) -> Result<()> {
+    require_eq!(tags & !KNOWN_COMBO_TAGS, 0, ComboError::UnknownTag);
+    require_eq!(
+        tags & DERIVED_COMBO_TAGS,
+        derived_tags(frame_data, moves)?,
+        ComboError::TagMismatch
+    );
+    Ok(())
+}
+
+/// Claimed tags of a fused combo: the starter is the first parent's, it is
+/// corner-only if either part is and meterless only if both are.
+fn fused_tags(first: u32, second: u32) -> u32 {
+    let starter = first & (COMBO_TAG_ANTI_AIR_STARTER | COMBO_TAG_COUNTER_HIT);
+    let corner = (first | second) & COMBO_TAG_CORNER_ONLY;
+    let meterless = first & second & COMBO_TAG_METERLESS;
+    starter | corner | meterless
+}
+
+/// Parents must be distinct, unminted, current and for the same character,
+/// and the joined route has to fit the combo limits.
+fn validate_fusion(ctx: &Context<FuseCombos>, combo_name: &String) -> Result<()> {
+    let first = &ctx.accounts.first;
+    let second = &ctx.accounts.second;
+    let limits = &ctx.accounts.config.limits;
+    require_keys_neq!(first.key(), second.key(), ComboError::InvalidFusion);
+    require_eq!(first.character_id, second.character_id, ComboError::InvalidFusion);
+    for parent in [first, second] {
+        burnable(parent)?;
+        require!(!parent.stale, ComboError::StaleCombo);
+        require!(
+            parent.nft_mint == Pubkey::default() && parent.cnft_tree == Pubkey::default(),
+            ComboError::NftAlreadyMinted
+        );
+    }
+    check_name(limits, &ctx.accounts.name_filter, combo_name)?;
+    require_gte!(
+        limits.max_moves as usize,
+        first.moves.len() + second.moves.len(),
+        ComboError::TooManyMoves
+    );
+
+    let mut moves = first.moves.clone();
+    moves.extend_from_slice(&second.moves);
+    let outcome = simulate_combo(&ctx.accounts.frame_data, &moves)?;
+    require_gte!(limits.max_damage, outcome.damage, ComboError::InvalidDamage);
+    require_gte!(limits.max_meter_gain, outcome.meter_gain, ComboError::InvalidMeterGain);
+    Ok(())
+}
+
+/// When the character is gated, the authority has to pass a token account
+/// holding the NFT and the NFT's metadata as the first two remaining
+/// accounts, and the metadata must list the gate's collection as verified.
+fn check_character_gate<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, CreateCombo<'info>>,
+) -> Result<()> {
+    let gate_info = &ctx.accounts.character_gate;
+    if gate_info.data_is_empty() {
+        return Ok(());
+    }
+    let gate = CharacterGate::try_deserialize(&mut &gate_info.data.borrow()[..])?;
+    if !gate.enabled {
+        return Ok(());
+    }
+
+    require_gte!(ctx.remaining_accounts.len(), 2, ComboError::CharacterNotOwned);
+    let token_info = &ctx.remaining_accounts[0];
+    let metadata_info = &ctx.remaining_accounts[1];
+    let token_account: Account<TokenAccount> = Account::try_from(token_info)?;
+    require!(
+        token_account.owner == *ctx.accounts.authority.key && token_account.amount == 1,
+        ComboError::CharacterNotOwned
+    );
+    let (metadata_key, _) = MetadataAccount::find_pda(&token_account.mint);
+    require_keys_eq!(metadata_info.key(), metadata_key, ComboError::CharacterNotOwned);
+    let metadata = MetadataAccount::safe_deserialize(&metadata_info.data.borrow())
+        .map_err(|_| ComboError::CharacterNotOwned)?;
+    require!(
+        metadata
+            .collection
+            .map_or(false, |c| c.verified && c.key == gate.collection),
+        ComboError::CharacterNotOwned
+    );
+    Ok(())
+}
+
+fn validate_reward_mint(ctx: &Context<InitializeRewards>) -> Result<()> {
+    require!(
+        token_compat::supported_mint(&ctx.accounts.mint)?,
+        ComboError::UnsupportedMint
+    );
+    Ok(())
+}
+
+fn set_paused(config: &mut Account<Config>, paused: bool) -> Result<()> {
+    config.paused = paused;
+
+    emit!(PauseChanged {
+        paused,
+        timestamp: Clock::get()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+fn check_verifier(
+    combo: &ComboAccount,
+    registry: &VerifierRegistry,
+    verifier: &Pubkey,
+) -> Result<()> {
+    require!(
+        !combo.official || registry.verifiers.contains(verifier),
+        ComboError::UnapprovedVerifier
+    );
+    Ok(())
+}
+
+fn validate_royalty(config: &Config, royalty_bps: u16) -> Result<()> {
+    require_gte!(config.max_royalty_bps, royalty_bps, ComboError::InvalidRoyalty);
+    Ok(())
+}
+
+fn not_paused(config: &Config) -> Result<()> {
+    require!(!config.paused, ComboError::ProgramPaused);
+    Ok(())
+}
+
+/// Frozen combos can't be verified, listed, rented, sold or transferred;
+/// `settle_auction` refunds the bid instead.
+fn not_frozen(combo: &ComboAccount) -> Result<()> {
+    require!(!combo.frozen, ComboError::ComboFrozen);
+    Ok(())
+}
+
+fn min_tier(combo: &ComboAccount, tier: ComboTier) -> Result<()> {
+    require!(combo.tier >= tier, ComboError::TierTooLow);
+    Ok(())
+}
+
+/// A duplicate has the combo's character and moves under another name,
+/// and is burned whole, so it can't be frozen or carry combo data.
+fn validate_duplicate(combo: &ComboAccount, duplicate: &ComboAccount) -> Result<()> {
+    not_frozen(combo)?;
+    burnable(duplicate)?;
+    require!(
+        duplicate.character_id == combo.character_id && duplicate.moves == combo.moves,
+        ComboError::NotADuplicate
+    );
+    require!(duplicate.tier >= combo.tier, ComboError::TierTooLow);
+    Ok(())
+}
+
+/// Checks a combo an instruction closes to make another: a frozen combo
+/// stays put for the moderator, and an attached `ComboDataAccount` would be
+/// left with no combo to close it through. Its listing, rental and auction
+/// PDAs are checked by the instruction's accounts.
+fn burnable(combo: &ComboAccount) -> Result<()> {
+    not_frozen(combo)?;
+    require_keys_eq!(combo.combo_data, Pubkey::default(), ComboError::ComboDataAttached);
+    Ok(())
+}
+
+fn is_moderator(config: &Config, moderator: &Pubkey) -> Result<()> {
+    require_keys_neq!(config.moderator, Pubkey::default(), ComboError::NoModerator);
+    require_keys_eq!(config.moderator, *moderator, ComboError::Unauthorized);
+    Ok(())
+}
+
+/// Pays the verifier the reward for a counted verification. Only approved
+/// verifiers are paid, and never for a combo they created or hold, since
+/// anyone can verify a combo that isn't official. Nothing is paid either
+/// once the window's allowance, overall or the verifier's, is used up or
+/// the vault runs dry; the verification itself still counts.
+fn pay_reward(accounts: &mut VerifyCombo, now: i64) -> Result<()> {
+    let verifier = accounts.verifier.key;
+    let combo = &accounts.combo_pda;
+    if !accounts.verifier_registry.verifiers.contains(verifier)
+        || *verifier == combo.creator
+        || *verifier == combo.authority
+    {
+        return Ok(());
+    }
+    let amount = accounts.reward_config.amount;
+    if amount == 0 || accounts.reward_vault.amount < amount {
+        return Ok(());
+    }
+    if !accounts
+        .reward_config
+        .take_slot(&mut accounts.verifier_quota, now)
+    {
+        return Ok(());
+    }
+
+    let signer_seeds: &[&[u8]] = &[b"reward_config", &[accounts.reward_config.bump]];
+    token_compat::transfer(
+        &accounts.token_program,
+        &accounts.reward_vault,
+        &accounts.verifier_token,
+        &accounts.reward_mint,
+        &accounts.reward_config.to_account_info(),
+        &[signer_seeds],
+        amount,
+    )?;
+
+    emit!(VerifierRewarded {
+        combo: accounts.combo_pda.key(),
+        verifier: accounts.verifier.key(),
+        amount,
+    });
+
+    Ok(())
+}
+
+/// Updates the verifier's receipt for this combo and returns whether the
+/// verification counts: each verifier counts once per combo version, and
+/// must wait out the cooldown before verifying the same combo again.
+fn record_verification(
+    receipt: &mut Account<VerificationReceipt>,
+    combo: &ComboAccount,
+    verifier: Pubkey,
+    bump: u8,
+    now: i64,
+    cooldown: i64,
+) -> Result<bool> {
+    if receipt.last_verified != 0 {
+        require_gte!(
+            now.saturating_sub(receipt.last_verified),
+            cooldown,
+            ComboError::VerificationCooldown
+        );
+    }
+    let counted = receipt.combo_version != combo.version;
+    receipt.layout_version = VerificationReceipt::LAYOUT_VERSION;
+    receipt.verifier = verifier;
+    receipt.combo_version = combo.version;
+    receipt.last_verified = now;
+    receipt.bump = bump;
+    Ok(counted)
+}
+
+/// Loads the verifier's receipt for `combo` in a batch, creating it first
+/// if the verifier has never verified the combo.
+fn load_receipt<'info>(
+    info: &'info AccountInfo<'info>,
+    combo: &Pubkey,
+    verifier: &AccountInfo<'info>,
+    system: &Program<'info, System>,
+) -> Result<(Account<'info, VerificationReceipt>, u8)> {
+    let (address, bump) = Pubkey::find_program_address(
+        &[b"receipt", combo.as_ref(), verifier.key.as_ref()],
+        &crate::ID,
+    );
+    require!(
+        *info.key == address && info.is_writable,
+        ComboError::InvalidBatch
+    );
+    if info.owner == &crate::ID {
+        return Ok((Account::try_from(info)?, bump));
+    }
+
+    let signer_seeds: &[&[u8]] = &[b"receipt", combo.as_ref(), verifier.key.as_ref(), &[bump]];
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system.to_account_info(),
+            system_program::CreateAccount {
+                from: verifier.clone(),
+                to: info.clone(),
+            },
+            &[signer_seeds],
+        ),
+        Rent::get()?.minimum_balance(8 + VerificationReceipt::INIT_SPACE),
+        (8 + VerificationReceipt::INIT_SPACE) as u64,
+        &crate::ID,
+    )?;
+    Ok((Account::try_from_unchecked(info)?, bump))
+}
+
+/// Counts `combo` against its character and makes it the newest entry of
+/// the `CharacterIndex`, which `init_if_needed` may have just created.
+fn index_combo(
+    index: &mut CharacterIndex,
+    combo: Pubkey,
+    character_id: u8,
+    bump: u8,
+) -> Result<()> {
+    if index.layout_version == 0 {
+        index.layout_version = CharacterIndex::LAYOUT_VERSION;
+        index.character_id = character_id;
+        index.bump = bump;
+    }
+    let slot = (index.combo_count % CharacterIndex::RECENT_LEN as u64) as usize;
+    index.recent[slot] = combo;
+    increment(&mut index.combo_count).map_err(ComboError::from)?;
+    Ok(())
+}
+
+/// Creates the `ComboHashIndex` claiming `combo_hash` for `combo`. Fails
+/// with `DuplicateCombo` if another combo already holds the hash.
+fn claim_combo_hash<'info>(
+    info: &AccountInfo<'info>,
+    combo: Pubkey,
+    combo_hash: &[u8; 32],
+    payer: &AccountInfo<'info>,
+    system: &Program<'info, System>,
+) -> Result<()> {
+    let (address, bump) =
+        Pubkey::find_program_address(&[b"combo_hash", combo_hash.as_ref()], &crate::ID);
+    require_keys_eq!(*info.key, address, ComboError::InvalidHashIndex);
+    require!(info.owner != &crate::ID, ComboError::DuplicateCombo);
+
+    let signer_seeds: &[&[u8]] = &[b"combo_hash", combo_hash.as_ref(), &[bump]];
+    system_program::create_account(
+        CpiContext::new_with_signer(
+            system.to_account_info(),
+            system_program::CreateAccount {
+                from: payer.clone(),
+                to: info.clone(),
+            },
+            &[signer_seeds],
+        ),
+        Rent::get()?.minimum_balance(8 + ComboHashIndex::INIT_SPACE),
+        (8 + ComboHashIndex::INIT_SPACE) as u64,
+        &crate::ID,
+    )?;
+    let index = ComboHashIndex {
+        layout_version: ComboHashIndex::LAYOUT_VERSION,
+        combo,
+        bump,
+    };
+    index.try_serialize(&mut &mut info.try_borrow_mut_data()?[..])
+}
+
+/// Replays `moves` and checks the outcome against the stored combo.
+fn check_replay(
+    combo: &ComboAccount,
+    frame_data: &CharacterFrameData,
+    moves: &[u8],
+) -> Result<()> {
+    let outcome = simulate_combo(frame_data, moves)?;
+    require!(moves == combo.moves.as_slice(), ComboError::MoveMismatch);
+    require_eq!(outcome.damage, combo.damage, ComboError::DamageMismatch);
+    require_eq!(outcome.meter_gain, combo.meter_gain, ComboError::MeterGainMismatch);
+
+    let recomputed = compute_combo_seed(
+        combo.name.as_bytes(),
+        combo.damage,
+        combo.meter_gain,
+        combo.move_count,
+        combo.character_id,
+        &combo.moves,
+    );
+    require!(recomputed == combo.combo_hash, ComboError::MoveMismatch);
+    Ok(())
+}
+
+/// Marks a combo created under an older game version as stale, returning
+/// whether it did. Once stale, a combo can no longer be verified until it is
+/// updated for the current version.
+fn mark_if_stale(combo: &mut Account<ComboAccount>, config: &Config) -> Result<bool> {
+    require!(!combo.stale, ComboError::StaleCombo);
+    if combo.game_version >= config.game_version {
+        return Ok(false);
+    }
+    combo.stale = true;
+
+    emit!(ComboMarkedStale {
+        combo: combo.key(),
+        game_version: combo.game_version,
+        current_version: config.game_version,
+    });
+
+    Ok(true)
+}
+
+fn verify_move_sequence(_ctx: &Context<VerifyCombo>, moves: &Vec<u8>) -> Result<()> {
+    require_gte!(MAX_COMBO_MOVES, moves.len(), ComboError::TooManyMoves);
+    Ok(())
+}
+
+/// Attestations only count from approved servers, official combo or not.
+fn verify_attested_sequence(
+    ctx: &Context<VerifyComboAttested>,
+    moves: &[u8],
+    server: &Pubkey,
+) -> Result<()> {
+    require_gte!(MAX_COMBO_MOVES, moves.len(), ComboError::TooManyMoves);
+    require!(
+        ctx.accounts.verifier_registry.verifiers.contains(server),
+        ComboError::UnapprovedVerifier
+    );
+    Ok(())
+}
+
+fn validate_combo_update(
+    ctx: &Context<UpdateCombo>,
+    damage: u32,
+    move_count: u8,
+    moves: &[u8],
+) -> Result<()> {
+    let combo = &ctx.accounts.combo_pda;
+    require_keys_eq!(combo.authority, *ctx.accounts.authority.key, ComboError::Unauthorized);
+    // Attached timing belongs to the current moves and cannot be rewritten.
+    require_keys_eq!(combo.combo_data, Pubkey::default(), ComboError::ComboDataAttached);
+    let limits = &ctx.accounts.config.limits;
+    check_limits(limits, damage, move_count, moves)?;
+    let outcome = simulate_combo(&ctx.accounts.frame_data, moves)?;
+    require_eq!(outcome.damage, damage, ComboError::DamageMismatch);
+    require_gte!(limits.max_meter_gain, outcome.meter_gain, ComboError::InvalidMeterGain);
+    Ok(())
+}
+
+fn validate_combo_data_attach(ctx: &Context<AttachComboData>, timing: &[u16]) -> Result<()> {
+    let combo = &ctx.accounts.combo_pda;
+    require_keys_eq!(combo.authority, *ctx.accounts.authority.key, ComboError::Unauthorized);
+    require_eq!(timing.len(), combo.moves.len(), ComboError::InvalidTiming);
+    let outcome = simulate_timed_combo(&ctx.accounts.frame_data, &combo.moves, timing)?;
+    require_eq!(outcome.damage, combo.damage, ComboError::DamageMismatch);
+    require_eq!(outcome.meter_gain, combo.meter_gain, ComboError::MeterGainMismatch);
+    Ok(())
+}
+
+fn validate_nft_mint(ctx: &Context<MintComboNft>, base_uri: &String) -> Result<()> {
+    check_mintable(&ctx.accounts.combo_pda, ctx.accounts.authority.key, base_uri)
+}
+
+fn validate_cnft_mint(ctx: &Context<MintComboCnft>, base_uri: &String) -> Result<()> {
+    check_mintable(&ctx.accounts.combo_pda, ctx.accounts.authority.key, base_uri)
+}
+
+/// A combo is minted at most once, as either a regular or a compressed NFT.
+fn check_mintable(combo: &ComboAccount, authority: &Pubkey, base_uri: &str) -> Result<()> {
+    require_keys_eq!(combo.authority, *authority, ComboError::Unauthorized);
+    require_gte!(
+        combo.verification_count,
+        MIN_VERIFICATIONS_FOR_NFT,
+        ComboError::NotEnoughVerifications
+    );
+    require!(
+        combo.nft_mint == Pubkey::default() && combo.cnft_tree == Pubkey::default(),
+        ComboError::NftAlreadyMinted
+    );
+    require_gte!(MAX_BASE_URI_LEN, base_uri.len(), ComboError::UriTooLong);
+    Ok(())
+}
+
+/// Token Metadata caps names at 32 bytes; truncate on a char boundary.
+fn nft_name(name: &str) -> String {
+    let mut end = name.len().min(32);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    name[..end].to_string()
+}
+
+/// The metadata URI carries the combo hash, character and damage so the NFT
+/// is self-describing even before the off-chain JSON is resolved.
+fn nft_uri(base_uri: &str, combo: &ComboAccount) -> String {
+    let mut hash_hex = String::with_capacity(64);
+    for byte in &combo.combo_hash {
+        hash_hex.push_str(&format!("{:02x}", byte));
+    }
+    format!(
+        "{}?hash={}&character={}&damage={}&tier={}",
+        base_uri,
+        hash_hex,
+        combo.character_id,
+        combo.damage,
+        combo.tier.name()
+    )
+}
+
+/// Splits a sale price into the creator's royalty and the seller's
+/// proceeds. The creator selling their own combo pays no royalty, and the
+/// combo's rate is held to the current cap.
+fn split_sale(price: u64, combo: &ComboAccount, seller: Pubkey, config: &Config) -> (u64, u64) {
+    if seller == combo.creator {
+        return (0, price);
+    }
+    let bps = combo.royalty_bps.min(config.max_royalty_bps);
+    let royalty = (price as u128 * bps as u128 / MAX_BPS as u128) as u64;
+    (royalty, price - royalty)
+}
+
+fn emit_royalty(
+    events: &EventSink,
+    combo: &Account<ComboAccount>,
+    amount: u64,
+    payment_mint: Pubkey,
+) -> Result<()> {
+    events.emit(&RoyaltyPaid {
+        combo: combo.key(),
+        creator: combo.creator,
+        amount,
+        payment_mint,
+    })
+}
+
+/// Shared by `settle_auction` and `finalize_auction_if_expired`.
+fn settle(
+    events: &EventSink,
+    combo: &mut Account<ComboAccount>,
+    auction: &Account<Auction>,
+    seller: &AccountInfo,
+    creator: &AccountInfo,
+    highest_bidder: &AccountInfo,
+    config: &Config,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    require_gte!(now, auction.end_time, ComboError::AuctionNotEnded);
+
+    let bid = auction.highest_bid;
+    let sold = bid > 0 && combo.authority == auction.seller && !combo.frozen;
+    let escrow = auction.to_account_info();
+    if bid > 0 && !sold {
+        move_lamports(&escrow, highest_bidder, bid)?;
+    }
+    if sold {
+        let (royalty, proceeds) = split_sale(bid, combo, auction.seller, config);
+        if royalty > 0 {
+            move_lamports(&escrow, creator, royalty)?;
+            emit_royalty(events, combo, royalty, Pubkey::default())?;
+        }
+        move_lamports(&escrow, seller, proceeds)?;
+
+        combo.authority = auction.highest_bidder;
+        combo.pending_authority = Pubkey::default();
+    }
+
+    events.emit(&AuctionSettled {
+        combo: auction.combo,
+        seller: auction.seller,
+        winner: if sold { auction.highest_bidder } else { Pubkey::default() },
+        amount: if sold { bid } else { 0 },
+    })
+}
+
+/// Hands a sold combo to the buyer. Any pending two-step transfer is
+/// dropped along with the listing.
+fn complete_sale(
+    events: &EventSink,
+    combo: &mut Account<ComboAccount>,
+    listing: &Listing,
+    buyer: Pubkey,
+) -> Result<()> {
+    combo.authority = buyer;
+    combo.pending_authority = Pubkey::default();
+
+    events.emit(&ComboSold {
+        combo: combo.key(),
+        seller: listing.seller,
+        buyer,
+        price: listing.price,
+        payment_mint: listing.payment_mint,
+    })
+}
+
+/// Checks `leaf` against `root`, hashing each pair in sorted order so proofs
+/// carry no left/right flags.
+fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            hashv(&[&node, sibling]).to_bytes()
+        } else {
+            hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+    computed == root
+}
+
+/// Moves lamports out of an account this program owns.
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let mut from_lamports = from.try_borrow_mut_lamports()?;
+    let mut to_lamports = to.try_borrow_mut_lamports()?;
+    checked::move_lamports(&mut from_lamports, &mut to_lamports, amount)
+        .map_err(ComboError::from)?;
+    Ok(())
+}
+
+/// Whether a PDA holds no account: never created, or closed and handed back
+/// to the system program.
+fn is_closed(info: &AccountInfo) -> bool {
+    info.data_is_empty() || info.owner == &system_program::ID
+}
+
+/// Closes an account this program owns by hand, the way the `close`
+/// constraint does: its lamports go to `destination` and it is handed back
+/// to the system program with no data, so it can never be deserialized
+/// again even if refunded later in the same transaction.
+fn close_account(info: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    move_lamports(info, destination, info.lamports())?;
+    info.assign(&system_program::ID);
+    info.realloc(0, false)?;
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(combo_name: String, damage: u32, move_count: u8, character_id: u8)]
+pub struct CreateCombo<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"combo", authority.key.as_ref(), &combo_name_seed(&combo_name)],
+        bump,
+        space = 8 + ComboAccount::INIT_SPACE,
+        payer = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        init,
+        seeds = [b"combo_name", authority.key.as_ref(), &normalized_name_seed(&combo_name)],
+        bump,
+        space = 8 + ComboNameIndex::INIT_SPACE,
+        payer = authority,
+    )]
+    pub name_index: Account<'info, ComboNameIndex>,
+    #[account(seeds = [b"name_filter"], bump = name_filter.bump)]
+    pub name_filter: Account<'info, NameFilter>,
+    #[account(
+        seeds = [b"character", &[character_id]],
+        bump = frame_data.bump,
+        seeds::program = move_registry::ID,
+    )]
+    pub frame_data: Account<'info, CharacterFrameData>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: `CharacterGate` PDA, empty when the character is not gated
+    #[account(seeds = [b"character_gate", &[character_id]], bump)]
+    pub character_gate: UncheckedAccount<'info>,
+    /// CHECK: `ComboHashIndex` PDA for the new combo's hash, created by the
+    /// instruction
+    #[account(mut)]
+    pub hash_index: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [b"character_index".as_ref(), &[character_id]],
+        bump,
+        space = 8 + CharacterIndex::INIT_SPACE,
+        payer = authority,
+    )]
+    pub character_index: Account<'info, CharacterIndex>,
+    pub system_program: Program<'info, System>,
+    #[account(address = sysvar::rent::ID)]
+    pub rent: Sysvar<'info, Rent>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+#[instruction(character_id: u8)]
+pub struct SetCharacterGate<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        seeds = [b"character_gate".as_ref(), &[character_id]],
+        bump,
+        space = 8 + CharacterGate::INIT_SPACE,
+        payer = admin,
+    )]
+    pub character_gate: Account<'info, CharacterGate>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetModerator<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct FlagCombo<'info> {
+    #[account(mut)]
+    pub reporter: Signer<'info>,
+    #[account(
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        init,
+        seeds = [b"combo_flag", combo_pda.key().as_ref()],
+        bump,
+        space = 8 + ComboFlag::INIT_SPACE,
+        payer = reporter,
+    )]
+    pub flag: Account<'info, ComboFlag>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeCombo<'info> {
+    pub moderator: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+}
+
+#[derive(Accounts)]
+pub struct AppealFlag<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"combo_flag", combo_pda.key().as_ref()],
+        bump = flag.bump,
+    )]
+    pub flag: Account<'info, ComboFlag>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveFlag<'info> {
+    pub moderator: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"combo_flag", combo_pda.key().as_ref()],
+        bump = flag.bump,
+        has_one = reporter,
+        close = reporter,
+    )]
+    pub flag: Account<'info, ComboFlag>,
+    /// CHECK: receives the flag's rent, matched by `has_one`
+    #[account(mut)]
+    pub reporter: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(combo_name: String)]
+pub struct FuseCombos<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", first.creator.as_ref(), &combo_name_seed(&first.name)],
+        bump = first.bump,
+        has_one = authority,
+        close = authority,
+    )]
+    pub first: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"combo", second.creator.as_ref(), &combo_name_seed(&second.name)],
+        bump = second.bump,
+        has_one = authority,
+        close = authority,
+    )]
+    pub second: Account<'info, ComboAccount>,
+    #[account(
+        init,
+        seeds = [b"combo", authority.key.as_ref(), &combo_name_seed(&combo_name)],
+        bump,
+        space = 8 + ComboAccount::INIT_SPACE,
+        payer = authority,
+    )]
+    pub fused: Account<'info, ComboAccount>,
+    #[account(
+        init,
+        seeds = [b"combo_name", authority.key.as_ref(), &normalized_name_seed(&combo_name)],
+        bump,
+        space = 8 + ComboNameIndex::INIT_SPACE,
+        payer = authority,
+    )]
+    pub name_index: Account<'info, ComboNameIndex>,
+    #[account(seeds = [b"name_filter"], bump = name_filter.bump)]
+    pub name_filter: Account<'info, NameFilter>,
+    #[account(
+        mut,
+        seeds = [b"combo_name", first.creator.as_ref(), &normalized_name_seed(&first.name)],
+        bump = first_name_index.bump,
+        close = authority,
+    )]
+    pub first_name_index: Account<'info, ComboNameIndex>,
+    #[account(
+        mut,
+        seeds = [b"combo_name", second.creator.as_ref(), &normalized_name_seed(&second.name)],
+        bump = second_name_index.bump,
+        close = authority,
+    )]
+    pub second_name_index: Account<'info, ComboNameIndex>,
+    #[account(
+        mut,
+        seeds = [b"combo_hash", first.combo_hash.as_ref()],
+        bump = first_hash_index.bump,
+        close = authority,
+    )]
+    pub first_hash_index: Account<'info, ComboHashIndex>,
+    #[account(
+        mut,
+        seeds = [b"combo_hash", second.combo_hash.as_ref()],
+        bump = second_hash_index.bump,
+        close = authority,
+    )]
+    pub second_hash_index: Account<'info, ComboHashIndex>,
+    /// CHECK: the first combo's `Listing` PDA, which must not exist
+    #[account(
+        seeds = [b"listing", first.key().as_ref()],
+        bump,
+        constraint = is_closed(&first_listing) @ ComboError::ComboListed,
+    )]
+    pub first_listing: UncheckedAccount<'info>,
+    /// CHECK: the first combo's `Rental` PDA, which must not exist
+    #[account(
+        seeds = [b"rental", first.key().as_ref()],
+        bump,
+        constraint = is_closed(&first_rental) @ ComboError::RentalOpen,
+    )]
+    pub first_rental: UncheckedAccount<'info>,
+    /// CHECK: the first combo's `Auction` PDA, which must not exist
+    #[account(
+        seeds = [b"auction", first.key().as_ref()],
+        bump,
+        constraint = is_closed(&first_auction) @ ComboError::AuctionOpen,
+    )]
+    pub first_auction: UncheckedAccount<'info>,
+    /// CHECK: the second combo's `Listing` PDA, which must not exist
+    #[account(
+        seeds = [b"listing", second.key().as_ref()],
+        bump,
+        constraint = is_closed(&second_listing) @ ComboError::ComboListed,
+    )]
+    pub second_listing: UncheckedAccount<'info>,
+    /// CHECK: the second combo's `Rental` PDA, which must not exist
+    #[account(
+        seeds = [b"rental", second.key().as_ref()],
+        bump,
+        constraint = is_closed(&second_rental) @ ComboError::RentalOpen,
+    )]
+    pub second_rental: UncheckedAccount<'info>,
+    /// CHECK: the second combo's `Auction` PDA, which must not exist
+    #[account(
+        seeds = [b"auction", second.key().as_ref()],
+        bump,
+        constraint = is_closed(&second_auction) @ ComboError::AuctionOpen,
+    )]
+    pub second_auction: UncheckedAccount<'info>,
+    /// CHECK: `ComboHashIndex` PDA for the new combo's hash, created by the
+    /// instruction
+    #[account(mut)]
+    pub hash_index: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        seeds = [b"character_index".as_ref(), &[first.character_id]],
+        bump,
+        space = 8 + CharacterIndex::INIT_SPACE,
+        payer = authority,
+    )]
+    pub character_index: Account<'info, CharacterIndex>,
+    #[account(
+        seeds = [b"character", &[first.character_id]],
+        bump = frame_data.bump,
+        seeds::program = move_registry::ID,
+    )]
+    pub frame_data: Account<'info, CharacterFrameData>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyCombo<'info> {
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        seeds = [b"character", &[combo_pda.character_id]],
+        bump = frame_data.bump,
+        seeds::program = move_registry::ID,
+    )]
+    pub frame_data: Account<'info, CharacterFrameData>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [b"verifier_registry"], bump = verifier_registry.bump)]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    #[account(
+        init_if_needed,
+        seeds = [b"receipt", combo_pda.key().as_ref(), verifier.key.as_ref()],
+        bump,
+        space = 8 + VerificationReceipt::INIT_SPACE,
+        payer = verifier,
+    )]
+    pub receipt: Account<'info, VerificationReceipt>,
+    #[account(
+        init_if_needed,
+        seeds = [b"verifier_quota", verifier.key.as_ref()],
+        bump,
+        space = 8 + VerifierQuota::INIT_SPACE,
+        payer = verifier,
+    )]
+    pub verifier_quota: Account<'info, VerifierQuota>,
+    #[account(mut)]
+    pub verifier: Signer<'info>,
+    #[account(mut, seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Account<'info, RewardConfig>,
+    #[account(mut, address = reward_config.vault)]
+    pub reward_vault: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(address = reward_config.mint)]
+    pub reward_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(mut, token::mint = reward_config.mint)]
+    pub verifier_token: InterfaceAccount<'info, token_interface::TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+#[instruction(moves: Vec<u8>, server: Pubkey)]
+pub struct VerifyComboAttested<'info> {
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        seeds = [b"character", &[combo_pda.character_id]],
+        bump = frame_data.bump,
+        seeds::program = move_registry::ID,
+    )]
+    pub frame_data: Account<'info, CharacterFrameData>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [b"verifier_registry"], bump = verifier_registry.bump)]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    #[account(
+        init_if_needed,
+        seeds = [b"receipt", combo_pda.key().as_ref(), server.as_ref()],
+        bump,
+        space = 8 + VerificationReceipt::INIT_SPACE,
+        payer = payer,
+    )]
+    pub receipt: Account<'info, VerificationReceipt>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: the instructions sysvar, verified by address
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyCombosBatch<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [b"verifier_registry"], bump = verifier_registry.bump)]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    #[account(mut)]
+    pub verifier: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct MintComboNft<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        seeds = [b"combo_nft", combo_pda.key().as_ref()],
+        bump,
+        payer = authority,
+        mint::decimals = 0,
+        mint::authority = combo_pda,
+        mint::freeze_authority = combo_pda,
+    )]
+    pub nft_mint: Account<'info, Mint>,
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = nft_mint,
+        associated_token::authority = authority,
+    )]
+    pub nft_token_account: Account<'info, TokenAccount>,
+    /// CHECK: created and validated by the token metadata program
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: created and validated by the token metadata program
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    pub token_metadata_program: Program<'info, Metadata>,
+    pub token_program: Program<'info, Token>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"config"],
+        bump,
+        space = 8 + Config::INIT_SPACE,
+        payer = admin,
+    )]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateLimits<'info> {
+    #[account(mut, seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeVerifierRegistry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        seeds = [b"verifier_registry"],
+        bump,
+        space = 8 + VerifierRegistry::INIT_SPACE,
+        payer = admin,
+    )]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateVerifiers<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [b"verifier_registry"], bump = verifier_registry.bump)]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeNameFilter<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        seeds = [b"name_filter"],
+        bump,
+        space = 8 + NameFilter::INIT_SPACE,
+        payer = admin,
+    )]
+    pub name_filter: Account<'info, NameFilter>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateNameFilter<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [b"name_filter"], bump = name_filter.bump)]
+    pub name_filter: Account<'info, NameFilter>,
+}
+
+#[derive(Accounts)]
+pub struct SetOfficial<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRewards<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        seeds = [b"reward_config"],
+        bump,
+        space = 8 + RewardConfig::INIT_SPACE,
+        payer = admin,
+    )]
+    pub reward_config: Account<'info, RewardConfig>,
+    pub mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        init,
+        seeds = [b"reward_vault"],
+        bump,
+        token::mint = mint,
+        token::authority = reward_config,
+        token::token_program = token_program,
+        payer = admin,
+    )]
+    pub reward_vault: InterfaceAccount<'info, token_interface::TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    #[account(address = sysvar::rent::ID)]
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct FundRewards<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Account<'info, RewardConfig>,
+    #[account(mut, address = reward_config.vault)]
+    pub reward_vault: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(address = reward_config.mint)]
+    pub mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(mut, token::mint = reward_config.mint)]
+    pub funder_token: InterfaceAccount<'info, token_interface::TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct SetRewardRate<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Account<'info, RewardConfig>,
+}
+
+#[derive(Accounts)]
+pub struct SetRoyaltyCap<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct SetVerificationCooldown<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct BumpGameVersion<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeCnftTree<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        seeds = [b"cnft_config"],
+        bump,
+        space = 8 + CnftConfig::INIT_SPACE,
+        payer = admin,
+    )]
+    pub cnft_config: Account<'info, CnftConfig>,
+    /// CHECK: initialized by Bubblegum
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = bubblegum_program.key(),
+    )]
+    pub tree_authority: UncheckedAccount<'info>,
+    /// CHECK: pre-allocated by the admin, initialized by account compression
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub bubblegum_program: Program<'info, Bubblegum>,
+    pub log_wrapper: Program<'info, Noop>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintComboCnft<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [b"cnft_config"], bump = cnft_config.bump, has_one = merkle_tree)]
+    pub cnft_config: Account<'info, CnftConfig>,
+    /// CHECK: validated by Bubblegum
+    #[account(
+        mut,
+        seeds = [merkle_tree.key().as_ref()],
+        bump,
+        seeds::program = bubblegum_program.key(),
+    )]
+    pub tree_authority: UncheckedAccount<'info>,
+    /// CHECK: checked against the config and by account compression
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub bubblegum_program: Program<'info, Bubblegum>,
+    pub log_wrapper: Program<'info, Noop>,
+    pub compression_program: Program<'info, SplAccountCompression>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateCombo<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+        realloc = 8 + ComboAccount::INIT_SPACE,
+        realloc::payer = authority,
+        realloc::zero = false,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        init,
+        seeds = [b"combo_history", combo_pda.key().as_ref(), &combo_pda.version.to_le_bytes()],
+        bump,
+        space = 8 + ComboHistory::INIT_SPACE,
+        payer = authority,
+    )]
+    pub history: Account<'info, ComboHistory>,
+    /// Index of the hash being replaced, released by the update.
+    #[account(
+        mut,
+        seeds = [b"combo_hash", combo_pda.combo_hash.as_ref()],
+        bump = old_hash_index.bump,
+        close = authority,
+    )]
+    pub old_hash_index: Account<'info, ComboHashIndex>,
+    /// CHECK: `ComboHashIndex` PDA for the combo's new hash, created by the
+    /// instruction
+    #[account(mut)]
+    pub hash_index: UncheckedAccount<'info>,
+    #[account(
+        seeds = [b"character", &[combo_pda.character_id]],
+        bump = frame_data.bump,
+        seeds::program = move_registry::ID,
+    )]
+    pub frame_data: Account<'info, CharacterFrameData>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ResizeCombo<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+        realloc = 8 + ComboAccount::INIT_SPACE,
+        realloc::payer = authority,
+        realloc::zero = false,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AttachComboData<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        init,
+        seeds = [b"combo_data", combo_pda.key().as_ref()],
+        bump,
+        space = 8 + ComboDataAccount::INIT_SPACE,
+        payer = authority,
+    )]
+    pub combo_data: AccountLoader<'info, ComboDataAccount>,
+    #[account(
+        seeds = [b"character", &[combo_pda.character_id]],
+        bump = frame_data.bump,
+        seeds::program = move_registry::ID,
+    )]
+    pub frame_data: Account<'info, CharacterFrameData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyComboData<'info> {
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(address = combo_pda.combo_data)]
+    pub combo_data: AccountLoader<'info, ComboDataAccount>,
+    #[account(
+        seeds = [b"character", &[combo_pda.character_id]],
+        bump = frame_data.bump,
+        seeds::program = move_registry::ID,
+    )]
+    pub frame_data: Account<'info, CharacterFrameData>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [b"verifier_registry"], bump = verifier_registry.bump)]
+    pub verifier_registry: Account<'info, VerifierRegistry>,
+    #[account(
+        init_if_needed,
+        seeds = [b"receipt", combo_pda.key().as_ref(), verifier.key.as_ref()],
+        bump,
+        space = 8 + VerificationReceipt::INIT_SPACE,
+        payer = verifier,
+    )]
+    pub receipt: Account<'info, VerificationReceipt>,
+    #[account(mut)]
+    pub verifier: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct TransferCombo<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptComboTransfer<'info> {
+    pub new_authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        constraint = combo_pda.pending_authority == new_authority.key() @ ComboError::NotPendingAuthority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ListCombo<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        init,
+        seeds = [b"listing", combo_pda.key().as_ref()],
+        bump,
+        space = 8 + Listing::INIT_SPACE,
+        payer = authority,
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct DelistCombo<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"listing", combo_pda.key().as_ref()],
+        bump = listing.bump,
+        has_one = seller,
+        close = seller,
+    )]
+    pub listing: Account<'info, Listing>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct BuyCombo<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut, address = listing.seller)]
+    pub seller: SystemAccount<'info>,
+    #[account(mut, address = combo_pda.creator)]
+    pub creator: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"listing", combo_pda.key().as_ref()],
+        bump = listing.bump,
+        constraint = listing.seller == combo_pda.authority @ ComboError::ListingStale,
+        close = seller,
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct BuyComboWithToken<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut, address = listing.seller)]
+    pub seller: SystemAccount<'info>,
+    #[account(mut, address = combo_pda.creator)]
+    pub creator: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"listing", combo_pda.key().as_ref()],
+        bump = listing.bump,
+        constraint = listing.seller == combo_pda.authority @ ComboError::ListingStale,
+        close = seller,
+    )]
+    pub listing: Account<'info, Listing>,
+    #[account(address = listing.payment_mint @ ComboError::WrongPaymentMint)]
+    pub payment_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        mut,
+        token::mint = listing.payment_mint,
+        token::authority = buyer,
+    )]
+    pub buyer_token: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(
+        mut,
+        token::mint = listing.payment_mint,
+        token::authority = seller,
+    )]
+    pub seller_token: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(
+        mut,
+        token::mint = listing.payment_mint,
+        token::authority = combo_pda.creator,
+    )]
+    pub creator_token: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub token_program: Interface<'info, TokenInterface>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct CreateAuction<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        init,
+        seeds = [b"auction", combo_pda.key().as_ref()],
+        bump,
+        space = 8 + Auction::INIT_SPACE,
+        payer = authority,
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct PlaceBid<'info> {
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"auction", auction.combo.as_ref()],
+        bump = auction.bump,
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(
+        mut,
+        constraint = auction.highest_bid == 0
+            || previous_bidder.key() == auction.highest_bidder @ ComboError::WrongBidder,
+    )]
+    pub previous_bidder: SystemAccount<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct SettleAuction<'info> {
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"auction", combo_pda.key().as_ref()],
+        bump = auction.bump,
+        has_one = seller,
+        close = seller,
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(mut)]
+    pub seller: SystemAccount<'info>,
+    #[account(mut, address = combo_pda.creator)]
+    pub creator: SystemAccount<'info>,
+    #[account(
+        mut,
+        constraint = auction.highest_bid == 0
+            || highest_bidder.key() == auction.highest_bidder @ ComboError::WrongBidder,
+    )]
+    pub highest_bidder: SystemAccount<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeExpiredAuction<'info> {
+    /// Collects `CRANK_REWARD`
+    #[account(mut)]
+    pub caller: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"auction", combo_pda.key().as_ref()],
+        bump = auction.bump,
+        has_one = seller,
+        close = seller,
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(mut)]
+    pub seller: SystemAccount<'info>,
+    #[account(mut, address = combo_pda.creator)]
+    pub creator: SystemAccount<'info>,
+    #[account(
+        mut,
+        constraint = auction.highest_bid == 0
+            || highest_bidder.key() == auction.highest_bidder @ ComboError::WrongBidder,
+    )]
+    pub highest_bidder: SystemAccount<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct RentCombo<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub borrower: Signer<'info>,
+    #[account(
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        init_if_needed,
+        seeds = [b"rental", combo_pda.key().as_ref()],
+        bump,
+        space = 8 + Rental::INIT_SPACE,
+        payer = borrower,
+    )]
+    pub rental: Account<'info, Rental>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct UseComboInMatch<'info> {
+    pub borrower: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"rental", rental.combo.as_ref()],
+        bump = rental.bump,
+        has_one = borrower,
+    )]
+    pub rental: Account<'info, Rental>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeExpiredRental<'info> {
+    /// Collects `CRANK_REWARD`
+    #[account(mut)]
+    pub caller: SystemAccount<'info>,
+    #[account(mut)]
+    pub borrower: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"rental", rental.combo.as_ref()],
+        bump = rental.bump,
+        has_one = borrower,
+        close = borrower,
+    )]
+    pub rental: Account<'info, Rental>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::ComboMint>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CreateCollection<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init,
+        seeds = [b"collection".as_ref(), &combo_name_seed(&name)],
+        bump,
+        space = 8 + Collection::INIT_SPACE,
+        payer = admin,
+    )]
+    pub collection: Account<'info, Collection>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FundCollection<'info> {
+    #[account(mut)]
+    pub funder: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"collection", &combo_name_seed(&collection.name)],
+        bump = collection.bump,
+    )]
+    pub collection: Account<'info, Collection>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterInCollection<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        seeds = [b"collection", &combo_name_seed(&collection.name)],
+        bump = collection.bump,
+    )]
+    pub collection: Account<'info, Collection>,
+    #[account(
+        init_if_needed,
+        seeds = [b"collection_progress", collection.key().as_ref(), authority.key.as_ref()],
+        bump,
+        space = 8 + CollectionProgress::INIT_SPACE,
+        payer = authority,
+    )]
+    pub progress: Account<'info, CollectionProgress>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCompletionReward<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        mut,
+        seeds = [b"collection", &combo_name_seed(&collection.name)],
+        bump = collection.bump,
+    )]
+    pub collection: Account<'info, Collection>,
+    #[account(
+        mut,
+        seeds = [b"collection_progress", collection.key().as_ref(), owner.key.as_ref()],
+        bump = progress.bump,
+        has_one = owner,
+    )]
+    pub progress: Account<'info, CollectionProgress>,
+}
+
+#[derive(Accounts)]
+pub struct SetTierCosts<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Account<'info, RewardConfig>,
+}
+
+#[derive(Accounts)]
+pub struct UpgradeCombo<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(seeds = [b"reward_config"], bump = reward_config.bump)]
+    pub reward_config: Account<'info, RewardConfig>,
+    #[account(mut, address = reward_config.mint)]
+    pub mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(mut, token::mint = reward_config.mint, token::authority = authority)]
+    pub authority_token: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct UpgradeComboWithDuplicate<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"combo", duplicate.creator.as_ref(), &combo_name_seed(&duplicate.name)],
+        bump = duplicate.bump,
+        has_one = authority,
+        constraint = duplicate.key() != combo_pda.key() @ ComboError::NotADuplicate,
+        close = authority,
+    )]
+    pub duplicate: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"combo_hash", duplicate.combo_hash.as_ref()],
+        bump = duplicate_hash_index.bump,
+        close = authority,
+    )]
+    pub duplicate_hash_index: Account<'info, ComboHashIndex>,
+    #[account(
+        mut,
+        seeds = [b"combo_name", duplicate.creator.as_ref(), &normalized_name_seed(&duplicate.name)],
+        bump = duplicate_name_index.bump,
+        close = authority,
+    )]
+    pub duplicate_name_index: Account<'info, ComboNameIndex>,
+    /// CHECK: the duplicate's `Listing` PDA, which must not exist
+    #[account(
+        seeds = [b"listing", duplicate.key().as_ref()],
+        bump,
+        constraint = is_closed(&duplicate_listing) @ ComboError::ComboListed,
+    )]
+    pub duplicate_listing: UncheckedAccount<'info>,
+    /// CHECK: the duplicate's `Rental` PDA, which must not exist
+    #[account(
+        seeds = [b"rental", duplicate.key().as_ref()],
+        bump,
+        constraint = is_closed(&duplicate_rental) @ ComboError::RentalOpen,
+    )]
+    pub duplicate_rental: UncheckedAccount<'info>,
+    /// CHECK: the duplicate's `Auction` PDA, which must not exist
+    #[account(
+        seeds = [b"auction", duplicate.key().as_ref()],
+        bump,
+        constraint = is_closed(&duplicate_auction) @ ComboError::AuctionOpen,
+    )]
+    pub duplicate_auction: UncheckedAccount<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+}
+
+#[derive(Accounts)]
+#[instruction(tier: ComboTier)]
+pub struct SubmitToLeaderboard<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        init_if_needed,
+        seeds = [b"tier_leaderboard".as_ref(), &[tier as u8]],
+        bump,
+        space = 8 + TierLeaderboard::INIT_SPACE,
+        payer = payer,
+    )]
+    pub leaderboard: Account<'info, TierLeaderboard>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseCombo<'info> {
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        has_one = authority,
+        close = destination,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"combo_hash", combo_pda.combo_hash.as_ref()],
+        bump = hash_index.bump,
+        close = destination,
+    )]
+    pub hash_index: Account<'info, ComboHashIndex>,
+    #[account(
+        mut,
+        seeds = [b"combo_name", combo_pda.creator.as_ref(), &normalized_name_seed(&combo_pda.name)],
+        bump = name_index.bump,
+        close = destination,
+    )]
+    pub name_index: Account<'info, ComboNameIndex>,
+    /// CHECK: the combo's `Listing` PDA, which must not exist
+    #[account(
+        seeds = [b"listing", combo_pda.key().as_ref()],
+        bump,
+        constraint = is_closed(&listing) @ ComboError::ComboListed,
+    )]
+    pub listing: UncheckedAccount<'info>,
+    /// CHECK: the combo's `Rental` PDA, which must not exist
+    #[account(
+        seeds = [b"rental", combo_pda.key().as_ref()],
+        bump,
+        constraint = is_closed(&rental) @ ComboError::RentalOpen,
+    )]
+    pub rental: UncheckedAccount<'info>,
+    /// CHECK: the combo's `Auction` PDA, which must not exist
+    #[account(
+        seeds = [b"auction", combo_pda.key().as_ref()],
+        bump,
+        constraint = is_closed(&auction) @ ComboError::AuctionOpen,
+    )]
+    pub auction: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub destination: SystemAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct ComboAccount {
+    pub layout_version: u8,
+    pub authority: Pubkey,
+    /// At `COMBO_CHARACTER_ID_OFFSET`.
+    pub character_id: u8,
+    /// `COMBO_TAG_*` bits, at `COMBO_TAGS_OFFSET`.
+    pub tags: u32,
+    #[max_len(MAX_COMBO_NAME_LEN)]
+    pub name: String,
+    pub damage: u32,
+    pub meter_gain: u32,
+    pub move_count: u8,
+    #[max_len(MAX_COMBO_MOVES)]
+    pub moves: Vec<u8>,
+    pub timestamp: i64,
+    pub combo_hash: [u8; 32],
+    pub verification_count: u32,
+    pub last_verified: i64,
+    pub bump: u8,
+    pub nft_mint: Pubkey,
+    pub cnft_tree: Pubkey,
+    /// Zero-copy moves and timing, `Pubkey::default()` until attached.
+    pub combo_data: Pubkey,
+    /// Starts at 1 and increases with every `update_combo`.
+    pub version: u32,
+    /// Newest `ComboHistory` entry, `Pubkey::default()` before any update.
+    pub last_history: Pubkey,
+    /// `Config::game_version` when this version of the combo was written.
+    pub game_version: u32,
+    /// Set once verification finds the combo from an older game version.
+    pub stale: bool,
+    /// Only verifiers in the `VerifierRegistry` may verify official combos.
+    pub official: bool,
+    /// Wallet that created the combo. The PDA stays derived from it after
+    /// the combo changes hands.
+    pub creator: Pubkey,
+    /// Set by a two-step `transfer_combo`, `Pubkey::default()` otherwise.
+    pub pending_authority: Pubkey,
+    /// Share of secondary sales paid to `creator`, in basis points.
+    pub royalty_bps: u16,
+    /// Hashes of the two combos `fuse_combos` built this one from; zero for
+    /// combos created directly.
+    pub parents: [[u8; 32]; 2],
+    /// `game_core::difficulty` score of the moves; higher is harder to
+    /// execute.
+    pub difficulty: u16,
+    /// Set by the moderator; see `not_frozen`.
+    pub frozen: bool,
+    /// Raised by `upgrade_combo` and `upgrade_combo_with_duplicate`.
+    pub tier: ComboTier,
+}
+
+impl Versioned for ComboAccount {
+    const LAYOUT_VERSION: u8 = 5;
+    const MIGRATIONS: &'static [Step] = &[
+        ADD_LAYOUT_VERSION,
+        Step {
+            from: 1,
+            grow: 2,
+            upgrade: append_difficulty,
+        },
+        Step {
+            from: 2,
+            grow: 4,
+            upgrade: insert_tags,
+        },
+        Step {
+            from: 3,
+            grow: 1,
+            upgrade: append_zeroed,
+        },
+        Step {
+            from: 4,
+            grow: 1,
+            upgrade: append_zeroed,
+        },
+    ];
+
+    /// Layout 0 combos were sized to their content, so a layout 0 combo is
+    /// one whose name and moves, read at their layout 0 offsets, need
+    /// exactly the steps' growth more than its length to fit now.
+    fn is_unversioned(data: &[u8]) -> bool {
+        let len_at = |offset: usize| -> Option<usize> {
+            let bytes = data.get(offset..offset + 4)?;
+            Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+        };
+        // Discriminator, authority and character_id come first.
+        let name_offset = 8 + 32 + 1;
+        let name = match len_at(name_offset) {
+            Some(len) if len <= MAX_COMBO_NAME_LEN => len,
+            _ => return false,
+        };
+        // Then the name, damage, meter_gain and move_count.
+        let moves = match len_at(name_offset + 4 + name + 4 + 4 + 1) {
+            Some(len) if len <= MAX_COMBO_MOVES => len,
+            _ => return false,
+        };
+        let space = 8 + Self::INIT_SPACE - MAX_COMBO_NAME_LEN - MAX_COMBO_MOVES + name + moves;
+        data.len() + account_migration::growth(Self::MIGRATIONS) == space
+    }
+}
+
+/// How far a combo has been upgraded. Tiers gate `TierLeaderboard`s and
+/// show in the combo's NFT metadata. Combos start at bronze.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ComboTier {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl ComboTier {
+    pub fn next(self) -> Option<Self> {
+        match self {
+            ComboTier::Bronze => Some(ComboTier::Silver),
+            ComboTier::Silver => Some(ComboTier::Gold),
+            ComboTier::Gold => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ComboTier::Bronze => "bronze",
+            ComboTier::Silver => "silver",
+            ComboTier::Gold => "gold",
+        }
+    }
+}
+
+/// Layout 2 appends `difficulty`. The new bytes are already zero, so combos
+/// carried over from layout 1 score 0 until their next update.
+fn append_difficulty(_: &mut [u8]) {}
+
+/// For layouts that only append fields whose zero value is the default,
+/// like `frozen` in combo layout 4, `tier` in combo layout 5, `moderator`
+/// in config layout 2 and `max_per_verifier` in reward config layout 3.
+fn append_zeroed(_: &mut [u8]) {}
+
+/// Layout 3 inserts `tags` after `character_id`. Combos carried over from
+/// layout 2 have no tags until their next update derives them.
+fn insert_tags(data: &mut [u8]) {
+    let end = data.len() - 4;
+    data.copy_within(COMBO_TAGS_OFFSET..end, COMBO_TAGS_OFFSET + 4);
+    data[COMBO_TAGS_OFFSET..COMBO_TAGS_OFFSET + 4].fill(0);
+}
+
+/// An open sale of one combo. `payment_mint` is `Pubkey::default()` for
+/// sales in lamports.
+#[account]
+#[derive(InitSpace)]
+pub struct Listing {
+    pub layout_version: u8,
+    pub combo: Pubkey,
+    pub seller: Pubkey,
+    pub price: u64,
+    pub payment_mint: Pubkey,
+    pub bump: u8,
+}
+
+impl Versioned for Listing {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// An English auction of one combo. The account escrows the highest bid on
+/// top of its rent.
+#[account]
+#[derive(InitSpace)]
+pub struct Auction {
+    pub layout_version: u8,
+    pub combo: Pubkey,
+    pub seller: Pubkey,
+    pub reserve_price: u64,
+    pub min_increment: u64,
+    pub end_time: i64,
+    /// Anti-snipe window in seconds.
+    pub extension: i64,
+    pub highest_bid: u64,
+    /// `Pubkey::default()` until the first bid.
+    pub highest_bidder: Pubkey,
+    pub bump: u8,
+}
+
+impl Versioned for Auction {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// Time-boxed usage delegation of a combo. The account is reused by the
+/// next rental once `expires_at` has passed.
+#[account]
+#[derive(InitSpace)]
+pub struct Rental {
+    pub layout_version: u8,
+    pub combo: Pubkey,
+    pub owner: Pubkey,
+    pub borrower: Pubkey,
+    pub fee: u64,
+    pub expires_at: i64,
+    /// `use_combo_in_match` calls during this rental.
+    pub uses: u32,
+    pub bump: u8,
+}
+
+impl Versioned for Rental {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// A set of combos, e.g. a character's starter routes, with a one-time
+/// reward for holding all of them.
+#[account]
+#[derive(InitSpace)]
+pub struct Collection {
+    pub layout_version: u8,
+    #[max_len(MAX_COLLECTION_NAME_LEN)]
+    pub name: String,
+    pub merkle_root: [u8; 32],
+    pub entry_count: u16,
+    /// Lamports paid to each wallet that completes the collection.
+    pub reward: u64,
+    pub bump: u8,
+}
+
+impl Versioned for Collection {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// One wallet's registered entries in a collection.
+#[account]
+#[derive(InitSpace)]
+pub struct CollectionProgress {
+    pub layout_version: u8,
+    pub collection: Pubkey,
+    pub owner: Pubkey,
+    /// Bit `i` is set once entry `i` is registered.
+    pub registered: u32,
+    /// Combo hash registered for each entry.
+    pub entries: [[u8; 32]; MAX_COLLECTION_ENTRIES],
+    pub claimed: bool,
+    pub bump: u8,
+}
+
+impl Versioned for CollectionProgress {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// Owns the reward vault and rate-limits payouts to `max_per_window`
+/// rewards every `window` seconds.
+#[account]
+#[derive(InitSpace)]
+pub struct RewardConfig {
+    pub layout_version: u8,
+    pub mint: Pubkey,
+    pub vault: Pubkey,
+    /// Tokens paid per counted verification.
+    pub amount: u64,
+    pub max_per_window: u32,
+    pub window: i64,
+    pub window_start: i64,
+    pub paid_in_window: u32,
+    pub bump: u8,
+    /// Tokens `upgrade_combo` burns to reach silver and gold; zero turns
+    /// that upgrade off.
+    pub tier_costs: [u64; 2],
+    /// Rewards one verifier can be paid per window. Zero, as configs from
+    /// layout 2 carry over, pays nobody until `set_reward_rate` sets it.
+    pub max_per_verifier: u32,
+}
+
+impl RewardConfig {
+    /// Claims one reward in the current window for `quota`'s verifier,
+    /// starting a new window for either if its last one is over. Neither
+    /// count moves unless both have room.
+    pub fn take_slot(&mut self, quota: &mut VerifierQuota, now: i64) -> bool {
+        if now.saturating_sub(self.window_start) >= self.window {
+            self.window_start = now;
+            self.paid_in_window = 0;
+        }
+        if now.saturating_sub(quota.window_start) >= self.window {
+            quota.window_start = now;
+            quota.paid_in_window = 0;
+        }
+        if self.paid_in_window >= self.max_per_window
+            || quota.paid_in_window >= self.max_per_verifier
+        {
+            return false;
+        }
+        self.paid_in_window += 1;
+        quota.paid_in_window += 1;
+        true
+    }
+}
+
+impl Versioned for RewardConfig {
+    const LAYOUT_VERSION: u8 = 3;
+    const MIGRATIONS: &'static [Step] = &[
+        ADD_LAYOUT_VERSION,
+        Step {
+            from: 1,
+            grow: 16,
+            upgrade: append_zeroed,
+        },
+        Step {
+            from: 2,
+            grow: 4,
+            upgrade: append_zeroed,
+        },
+    ];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// One per verifier; counts the rewards it was paid in the current reward
+/// window.
+#[account]
+#[derive(InitSpace)]
+pub struct VerifierQuota {
+    pub layout_version: u8,
+    pub verifier: Pubkey,
+    pub window_start: i64,
+    pub paid_in_window: u32,
+    pub bump: u8,
+}
+
+impl Versioned for VerifierQuota {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// One per (combo, verifier) pair; remembers which combo version the
+/// verifier last counted towards and when.
+#[account]
+#[derive(InitSpace)]
+pub struct VerificationReceipt {
+    pub layout_version: u8,
+    pub verifier: Pubkey,
+    pub combo_version: u32,
+    pub last_verified: i64,
+    pub bump: u8,
+}
+
+impl Versioned for VerificationReceipt {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// Limits combo creation for one character to holders of its NFTs.
+#[account]
+#[derive(InitSpace)]
+pub struct CharacterGate {
+    pub layout_version: u8,
+    pub character_id: u8,
+    /// Verified Metaplex collection the holder's NFT must belong to.
+    pub collection: Pubkey,
+    pub enabled: bool,
+    pub bump: u8,
+}
+
+impl Versioned for CharacterGate {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// Verifiers approved by the admin, e.g. official game servers.
+#[account]
+#[derive(InitSpace)]
+pub struct VerifierRegistry {
+    pub layout_version: u8,
+    #[max_len(MAX_VERIFIERS)]
+    pub verifiers: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl Versioned for VerifierRegistry {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// Lowercased prefixes new combo names may not start with.
+#[account]
+#[derive(InitSpace)]
+pub struct NameFilter {
+    pub layout_version: u8,
+    #[max_len(MAX_NAME_PREFIXES, MAX_NAME_PREFIX_LEN)]
+    pub prefixes: Vec<String>,
+    pub bump: u8,
+}
+
+impl Versioned for NameFilter {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// Archived version of a combo. Entries form a chain from the newest, linked
+/// from `ComboAccount::last_history`, back to the first version.
+#[account]
+#[derive(InitSpace)]
+pub struct ComboHistory {
+    pub layout_version: u8,
+    pub combo: Pubkey,
+    pub version: u32,
+    pub combo_hash: [u8; 32],
+    pub damage: u32,
+    pub meter_gain: u32,
+    #[max_len(MAX_COMBO_MOVES)]
+    pub moves: Vec<u8>,
+    /// Verifications the version had when it was replaced.
+    pub verification_count: u32,
+    pub archived_at: i64,
+    /// Entry for the version before this one, `Pubkey::default()` for the
+    /// first version.
+    pub previous: Pubkey,
+    pub bump: u8,
+}
+
+impl Versioned for ComboHistory {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// Fixed-size copy of a combo's moves plus the frame each one is input on.
+/// Packed so the layout matches `INIT_SPACE` byte for byte.
+#[account(zero_copy(unsafe))]
+#[derive(InitSpace)]
+pub struct ComboDataAccount {
+    pub layout_version: u8,
+    pub combo: Pubkey,
+    pub timing: [u16; MAX_COMBO_MOVES],
+    pub moves: [u8; MAX_COMBO_MOVES],
+    pub move_count: u8,
+    pub bump: u8,
+}
+
+impl Versioned for ComboDataAccount {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// Program-wide settings, owned by the admin.
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    /// Current game patch. Combos record the version they were created under.
+    pub game_version: u32,
+    pub limits: ValidationLimits,
+    /// Multisig allowed to change `limits`; only the first `signer_count`
+    /// entries are set.
+    pub signers: [Pubkey; MAX_LIMIT_SIGNERS],
+    pub signer_count: u8,
+    pub threshold: u8,
+    /// Set by `pause`; blocks every instruction that creates, verifies or
+    /// mints combos.
+    pub paused: bool,
+    /// Seconds between verifications of one combo by the same verifier.
+    pub verification_cooldown: i64,
+    /// Highest royalty a combo can charge, in basis points.
+    pub max_royalty_bps: u16,
+    pub bump: u8,
+    /// May freeze combos and resolve flags; `Pubkey::default()` for none.
+    pub moderator: Pubkey,
+}
+
+impl Versioned for Config {
+    const LAYOUT_VERSION: u8 = 2;
+    const MIGRATIONS: &'static [Step] = &[
+        ADD_LAYOUT_VERSION,
+        Step {
+            from: 1,
+            grow: 32,
+            upgrade: append_zeroed,
+        },
+    ];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// Bounds `create_combo` and `update_combo` enforce. Capacity limits in
+/// `ComboAccount` cap `max_moves` and `max_name_len`.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Debug, PartialEq, Eq)]
+pub struct ValidationLimits {
+    pub max_damage: u32,
+    pub max_meter_gain: u32,
+    pub max_moves: u8,
+    pub max_name_len: u8,
+}
+
+/// The programs behind compressed mints, whose crates no longer ship Anchor
+/// program types.
+#[derive(Clone)]
+pub struct Bubblegum;
+
+impl Id for Bubblegum {
+    fn id() -> Pubkey {
+        mpl_bubblegum::ID
+    }
+}
+
+#[derive(Clone)]
+pub struct SplAccountCompression;
+
+impl Id for SplAccountCompression {
+    fn id() -> Pubkey {
+        mpl_bubblegum::programs::SPL_ACCOUNT_COMPRESSION_ID
+    }
+}
+
+#[derive(Clone)]
+pub struct Noop;
+
+impl Id for Noop {
+    fn id() -> Pubkey {
+        mpl_bubblegum::programs::SPL_NOOP_ID
+    }
+}
+
+/// Admin PDA that owns the shared Bubblegum tree and signs as its delegate.
+#[account]
+#[derive(InitSpace)]
+pub struct CnftConfig {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub minted: u64,
+    pub bump: u8,
+}
+
+impl Versioned for CnftConfig {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(8 + Self::INIT_SPACE);
+}
+
+/// Claim on a `combo_hash`, held by the one combo with that content. Lives
+/// at `[b"combo_hash", combo_hash]` and is released when the combo is
+/// closed, fused or updated to new content.
+#[account]
+#[derive(InitSpace)]
+pub struct ComboHashIndex {
+    pub layout_version: u8,
+    pub combo: Pubkey,
+    pub bump: u8,
+}
+
+impl Versioned for ComboHashIndex {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// Claim on a lowercased combo name for one creator, at `[b"combo_name",
+/// creator, normalized_name_seed(name)]`. Released when the combo is closed
+/// or fused.
+#[account]
+#[derive(InitSpace)]
+pub struct ComboNameIndex {
+    pub layout_version: u8,
+    pub combo: Pubkey,
+    pub bump: u8,
+}
+
+impl Versioned for ComboNameIndex {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// Open report against one combo, at `[b"combo_flag", combo]`.
+#[account]
+#[derive(InitSpace)]
+pub struct ComboFlag {
+    pub layout_version: u8,
+    pub combo: Pubkey,
+    pub reporter: Pubkey,
+    /// One of the `FLAG_REASON_*` codes.
+    pub reason: u8,
+    /// Set once the combo's authority contests the flag.
+    pub appealed: bool,
+    pub flagged_at: i64,
+    pub bump: u8,
+}
+
+impl Versioned for ComboFlag {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// Every combo created for one character, at `[b"character_index",
+/// character_id]`. Combo `n` of the character went to `recent[n %
+/// RECENT_LEN]`, so clients page back from `combo_count - 1` without
+/// scanning program accounts. Entries are not cleared when a combo is
+/// closed or fused away; clients skip keys that no longer resolve.
+#[account]
+#[derive(InitSpace)]
+pub struct CharacterIndex {
+    pub layout_version: u8,
+    pub character_id: u8,
+    /// Combos ever created for the character; only grows.
+    pub combo_count: u64,
+    pub recent: [Pubkey; CharacterIndex::RECENT_LEN],
+    pub bump: u8,
+}
+
+impl CharacterIndex {
+    pub const RECENT_LEN: usize = 16;
+}
+
+impl Versioned for CharacterIndex {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    pub combo: Pubkey,
+    pub score: u32,
+}
+
+/// Best-scoring combos of `tier` or higher, at `[b"tier_leaderboard",
+/// tier]`, highest score first. Like the `CharacterIndex`, entries are not
+/// cleared when a combo is closed or fused away.
+#[account]
+#[derive(InitSpace)]
+pub struct TierLeaderboard {
+    pub layout_version: u8,
+    pub tier: ComboTier,
+    #[max_len(MAX_LEADERBOARD_ENTRIES)]
+    pub entries: Vec<LeaderboardEntry>,
+    pub bump: u8,
+}
+
+impl TierLeaderboard {
+    /// Places `entry` by score, replacing any earlier entry for the same
+    /// combo, and returns its rank from 0, or `None` if it didn't make
+    /// the board.
+    pub fn insert(&mut self, entry: LeaderboardEntry) -> Option<u8> {
+        self.entries.retain(|e| e.combo != entry.combo);
+        let rank = self.entries.iter().take_while(|e| e.score >= entry.score).count();
+        if rank >= MAX_LEADERBOARD_ENTRIES {
+            return None;
+        }
+        self.entries.insert(rank, entry);
+        self.entries.truncate(MAX_LEADERBOARD_ENTRIES);
+        Some(rank as u8)
+    }
+}
+
+impl Versioned for TierLeaderboard {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[event]
+pub struct ComboFlagged {
+    pub combo: Pubkey,
+    pub reporter: Pubkey,
+    pub reason: u8,
+}
+
+#[event]
+pub struct ComboFrozen {
+    pub combo: Pubkey,
+    pub moderator: Pubkey,
+    pub frozen: bool,
+}
+
+#[event]
+pub struct FlagAppealed {
+    pub combo: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct FlagResolved {
+    pub combo: Pubkey,
+    pub moderator: Pubkey,
+    pub upheld: bool,
+    pub appealed: bool,
+}
+
+#[event]
+pub struct ComboCreated {
+    pub combo: Pubkey,
+    pub authority: Pubkey,
+    pub character_id: u8,
+    pub damage: u32,
+    pub difficulty: u16,
+    pub tags: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct GameVersionBumped {
+    pub game_version: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct CharacterGateChanged {
+    pub character_id: u8,
+    pub collection: Pubkey,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct VerifierChanged {
+    pub verifier: Pubkey,
+    pub approved: bool,
+}
+
+#[event]
+pub struct RewardsFunded {
+    pub amount: u64,
+    pub balance: u64,
+}
+
+#[event]
+pub struct VerifierRewarded {
+    pub combo: Pubkey,
+    pub verifier: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct PauseChanged {
+    pub paused: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct LimitsUpdated {
+    pub limits: ValidationLimits,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ComboMarkedStale {
+    pub combo: Pubkey,
+    pub game_version: u32,
+    pub current_version: u32,
+}
+
+#[event]
+pub struct CollectionCreated {
+    pub collection: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub entry_count: u16,
+    pub reward: u64,
+}
+
+#[event]
+pub struct CollectionEntryRegistered {
+    pub collection: Pubkey,
+    pub owner: Pubkey,
+    pub combo: Pubkey,
+    pub index: u16,
+}
+
+#[event]
+pub struct CollectionCompleted {
+    pub collection: Pubkey,
+    pub owner: Pubkey,
+    pub reward: u64,
+}
+
+#[event]
+pub struct CombosFused {
+    pub combo: Pubkey,
+    pub authority: Pubkey,
+    pub parents: [Pubkey; 2],
+    pub parent_hashes: [[u8; 32]; 2],
+    pub damage: u32,
+}
+
+#[event]
+pub struct ComboListed {
+    pub combo: Pubkey,
+    pub seller: Pubkey,
+    pub price: u64,
+    pub payment_mint: Pubkey,
+}
+
+#[event]
+pub struct ComboDelisted {
+    pub combo: Pubkey,
+    pub seller: Pubkey,
+}
+
+#[event]
+pub struct ComboSold {
+    pub combo: Pubkey,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub price: u64,
+    pub payment_mint: Pubkey,
+}
+
+#[event]
+pub struct AuctionCreated {
+    pub combo: Pubkey,
+    pub seller: Pubkey,
+    pub reserve_price: u64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct BidPlaced {
+    pub combo: Pubkey,
+    pub bidder: Pubkey,
+    pub amount: u64,
+    pub end_time: i64,
+}
+
+#[event]
+pub struct AuctionSettled {
+    pub combo: Pubkey,
+    pub seller: Pubkey,
+    /// `Pubkey::default()` when the combo did not sell.
+    pub winner: Pubkey,
+    pub amount: u64,
+}
+
+/// An expired rental closed by `finalize_rental_if_expired`.
+#[event]
+pub struct RentalClosed {
+    pub combo: Pubkey,
+    pub owner: Pubkey,
+    pub borrower: Pubkey,
+    pub uses: u32,
+}
+
+#[event]
+pub struct ComboRented {
+    pub combo: Pubkey,
+    pub owner: Pubkey,
+    pub borrower: Pubkey,
+    pub fee: u64,
+    pub expires_at: i64,
+}
+
+#[event]
+pub struct ComboUsedInMatch {
+    pub combo: Pubkey,
+    pub borrower: Pubkey,
+    pub match_id: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct RoyaltyPaid {
+    pub combo: Pubkey,
+    pub creator: Pubkey,
+    pub amount: u64,
+    pub payment_mint: Pubkey,
+}
+
+#[event]
+pub struct ComboTransferProposed {
+    pub combo: Pubkey,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+}
+
+#[event]
+pub struct ComboTransferred {
+    pub combo: Pubkey,
+    pub previous_authority: Pubkey,
+    pub authority: Pubkey,
+}
+
+#[event]
+pub struct ComboUpdated {
+    pub combo: Pubkey,
+    pub version: u32,
+    pub previous_hash: [u8; 32],
+    pub combo_hash: [u8; 32],
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ComboResized {
+    pub combo: Pubkey,
+    pub space: u32,
+}
+
+#[event]
+pub struct ComboVerified {
+    pub combo: Pubkey,
+    pub verifier: Pubkey,
+    pub moves_count: u8,
+    /// False when this verifier had already verified the current version.
+    pub counted: bool,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct ComboNftMinted {
+    pub combo: Pubkey,
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub combo_hash: [u8; 32],
+}
+
+#[event]
+pub struct ComboCnftMinted {
+    pub combo: Pubkey,
+    pub merkle_tree: Pubkey,
+    pub leaf_index: u64,
+    pub authority: Pubkey,
+    pub combo_hash: [u8; 32],
+}
+
+#[event]
+pub struct ComboUpgraded {
+    pub combo: Pubkey,
+    pub authority: Pubkey,
+    pub tier: ComboTier,
+    /// Reward tokens burned; zero when a duplicate was burned instead.
+    pub burned: u64,
+    /// Burned duplicate combo, `Pubkey::default()` when tokens were burned.
+    pub duplicate: Pubkey,
+}
+
+#[event]
+pub struct LeaderboardUpdated {
+    pub tier: ComboTier,
+    pub combo: Pubkey,
+    pub score: u32,
+    /// `None` when the score didn't make the board.
+    pub rank: Option<u8>,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum ComboError {
+    #[msg("Combo name too long")]
+    NameTooLong,
+    #[msg("Combo name must be printable ASCII without surrounding spaces")]
+    InvalidNameCharset,
+    #[msg("Combo name starts with a banned prefix")]
+    BannedName,
+    #[msg("Prefix is already banned")]
+    PrefixAlreadyBanned,
+    #[msg("Prefix is not banned")]
+    UnknownPrefix,
+    #[msg("Name filter is full")]
+    TooManyPrefixes,
+    #[msg("Invalid damage value")]
+    InvalidDamage,
+    #[msg("Invalid meter gain value")]
+    InvalidMeterGain,
     #[msg("Invalid move count")]
     InvalidMoveCount,
     #[msg("Too many moves")]
     TooManyMoves,
     #[msg("Unauthorized")]
     Unauthorized,
+    #[msg("Submitted moves do not match the stored combo")]
+    MoveMismatch,
+    #[msg("Move is not registered for this character")]
+    UnknownMove,
+    #[msg("Claimed damage does not match registered frame data")]
+    DamageMismatch,
+    #[msg("Claimed meter gain does not match registered frame data")]
+    MeterGainMismatch,
+    #[msg("Combo has not been verified enough times to mint")]
+    NotEnoughVerifications,
+    #[msg("Combo NFT already minted")]
+    NftAlreadyMinted,
+    #[msg("Metadata URI too long")]
+    UriTooLong,
+    #[msg("Combo runs longer than the simulator allows")]
+    ComboTooLong,
+    #[msg("Move timing is missing or physically impossible")]
+    InvalidTiming,
+    #[msg("Opponent lands before the hit connects")]
+    ComboDropped,
+    #[msg("Hit exceeds the juggle limit")]
+    JuggleLimitExceeded,
+    #[msg("Combo has timing data attached and cannot be updated")]
+    ComboDataAttached,
+    #[msg("Combo was created under an older game version")]
+    StaleCombo,
+    #[msg("Validation limits out of range")]
+    InvalidLimits,
+    #[msg("Invalid limit signer set or threshold")]
+    InvalidSigners,
+    #[msg("Not enough limit signers approved")]
+    NotEnoughSigners,
+    #[msg("Program is paused")]
+    ProgramPaused,
+    #[msg("Official combos need an approved verifier")]
+    UnapprovedVerifier,
+    #[msg("Verifier is already approved")]
+    VerifierAlreadyApproved,
+    #[msg("Verifier is not in the registry")]
+    UnknownVerifier,
+    #[msg("Verifier registry is full")]
+    TooManyVerifiers,
+    #[msg("Verifier must wait before verifying this combo again")]
+    VerificationCooldown,
+    #[msg("Cooldown cannot be negative")]
+    InvalidCooldown,
+    #[msg("Reward window must be positive")]
+    InvalidRewardRate,
+    #[msg("Signer is not the pending authority")]
+    NotPendingAuthority,
+    #[msg("Price must be greater than zero")]
+    InvalidPrice,
+    #[msg("Listing is not payable this way")]
+    WrongPaymentMint,
+    #[msg("Seller no longer owns the combo")]
+    ListingStale,
+    #[msg("Royalty exceeds the configured cap")]
+    InvalidRoyalty,
+    #[msg("Auction duration or extension is invalid")]
+    InvalidAuctionWindow,
+    #[msg("Auction has ended")]
+    AuctionEnded,
+    #[msg("Auction is still running")]
+    AuctionNotEnded,
+    #[msg("Bid is below the reserve or minimum increment")]
+    BidTooLow,
+    #[msg("Account is not the auction's highest bidder")]
+    WrongBidder,
+    #[msg("Rental duration must be positive")]
+    InvalidRentalDuration,
+    #[msg("Combo is already rented")]
+    AlreadyRented,
+    #[msg("Rental has expired")]
+    RentalExpired,
+    #[msg("Mint has a token extension this program can't support")]
+    UnsupportedMint,
+    #[msg("Combos cannot be fused")]
+    InvalidFusion,
+    #[msg("Collection entry count or index is out of range")]
+    InvalidCollection,
+    #[msg("Merkle proof does not match the root")]
+    InvalidMerkleProof,
+    #[msg("Not every collection entry is held")]
+    CollectionIncomplete,
+    #[msg("Completion reward already claimed")]
+    RewardAlreadyClaimed,
+    #[msg("Authority does not hold this character's NFT")]
+    CharacterNotOwned,
+    #[msg("No ed25519 attestation precedes this instruction")]
+    MissingAttestation,
+    #[msg("Attestation does not sign the expected payload")]
+    InvalidAttestation,
+    #[msg("Batch accounts or move lengths do not line up")]
+    InvalidBatch,
+    #[msg("Account is not a child of this combo")]
+    UnknownChildAccount,
+    #[msg("A combo with the same content already exists")]
+    DuplicateCombo,
+    #[msg("Hash index does not match the combo hash")]
+    InvalidHashIndex,
+    #[msg("Tag is not a known combo tag")]
+    UnknownTag,
+    #[msg("Derived tags do not match the moves")]
+    TagMismatch,
+    #[msg("Combo is frozen by the moderator")]
+    ComboFrozen,
+    #[msg("No moderator is set")]
+    NoModerator,
+    #[msg("Unknown flag reason")]
+    InvalidFlagReason,
+    #[msg("Flag was already appealed")]
+    AlreadyAppealed,
+    #[msg("Combo is already gold")]
+    MaxTier,
+    #[msg("Upgrading to this tier with reward tokens is turned off")]
+    TierUpgradeDisabled,
+    #[msg("Combo's tier is too low")]
+    TierTooLow,
+    #[msg("Combo is not a duplicate of the upgraded combo")]
+    NotADuplicate,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+    #[msg("Combo is listed for sale")]
+    ComboListed,
+    #[msg("Combo has a rental that is not finalized")]
+    RentalOpen,
+    #[msg("Combo has an auction that is not settled")]
+    AuctionOpen,
+}
+
+impl From<Overflow> for ComboError {
+    fn from(_: Overflow) -> Self {
+        ComboError::Overflow
+    }
+}
+
+impl From<MigrationError> for ComboError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => ComboError::AlreadyMigrated,
+            _ => ComboError::UnsupportedLayout,
+        }
+    }
 }
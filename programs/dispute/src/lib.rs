@@ -0,0 +1,822 @@
+// Rust Anchor Program for Match Disputes
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use checked_math::{increment, Overflow};
+use game_core::{checksum, simulate_frame, snapshot, GameState, Input};
+use match_result::MatchAccount;
+
+
+declare_id!("DISPUTE_PROGRAM_ID_HERE");
+
+/// Most arbiters on the committee.
+pub const MAX_ARBITERS: usize = 7;
+/// Seconds the winner has to match the challenger's stake.
+pub const RESPONSE_WINDOW: i64 = 24 * 60 * 60;
+/// Longest key-frame window re-simulated on-chain, bounded by transaction
+/// size and compute.
+pub const MAX_WINDOW_FRAMES: u16 = 60;
+
+#[program]
+pub mod dispute {
+    use super::*;
+
+    #[access_control(
+        validate_committee(&arbiters, quorum)
+        validate_mint(&ctx)
+    )]
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        arbiters: Vec<Pubkey>,
+        quorum: u8,
+        challenge_window: i64,
+        min_stake: u64,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.layout_version = DisputeConfig::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.mint = ctx.accounts.mint.key();
+        config.arbiters = [Pubkey::default(); MAX_ARBITERS];
+        config.arbiters[..arbiters.len()].copy_from_slice(&arbiters);
+        config.arbiter_count = arbiters.len() as u8;
+        config.quorum = quorum;
+        config.challenge_window = challenge_window;
+        config.min_stake = min_stake;
+        config.bump = ctx.bumps.config;
+        Ok(())
+    }
+
+    /// The match loser stakes `stake` tokens on the claim that the real
+    /// replay hashes to `claimed_replay_hash`, within the challenge window.
+    #[access_control(validate_challenge(&ctx, stake))]
+    pub fn open_dispute(
+        ctx: Context<OpenDispute>,
+        claimed_replay_hash: [u8; 32],
+        stake: u64,
+    ) -> Result<()> {
+        token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.challenger_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.mint,
+            &ctx.accounts.challenger,
+            &[],
+            stake,
+        )?;
+
+        let record = &ctx.accounts.match_account;
+        let dispute = &mut ctx.accounts.dispute;
+        dispute.layout_version = Dispute::LAYOUT_VERSION;
+        dispute.match_id = record.match_id;
+        dispute.challenger = *ctx.accounts.challenger.key;
+        dispute.respondent = record.winner_key();
+        dispute.recorded_replay_hash = record.replay_hash;
+        dispute.frame_count = record.frame_count;
+        dispute.claimed_replay_hash = claimed_replay_hash;
+        dispute.stake = stake;
+        dispute.status = DisputeStatus::AwaitingResponse;
+        dispute.opened_at = Clock::get()?.unix_timestamp;
+        dispute.votes_for = 0;
+        dispute.votes_against = 0;
+        dispute.voted = 0;
+        dispute.window_start = 0;
+        dispute.window_frames = 0;
+        dispute.window_requested_at = 0;
+        dispute.start_checksum = 0;
+        dispute.inputs_hash = [0; 32];
+        dispute.end_checksum = 0;
+        dispute.bump = ctx.bumps.dispute;
+        dispute.vault_bump = ctx.bumps.vault;
+        dispute.checksum_root = record.checksum_root;
+
+        emit!(DisputeOpened {
+            dispute: dispute.key(),
+            match_id: dispute.match_id,
+            challenger: dispute.challenger,
+            claimed_replay_hash,
+            stake,
+        });
+
+        Ok(())
+    }
+
+    /// The winner matches the stake to defend the recorded result.
+    pub fn answer_dispute(ctx: Context<AnswerDispute>) -> Result<()> {
+        let dispute = &ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::AwaitingResponse,
+            DisputeError::WrongStatus
+        );
+        require!(
+            Clock::get()?.unix_timestamp < dispute.opened_at + RESPONSE_WINDOW,
+            DisputeError::ResponseExpired
+        );
+
+        token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.respondent_token_account,
+            &ctx.accounts.vault,
+            &ctx.accounts.mint,
+            &ctx.accounts.respondent,
+            &[],
+            dispute.stake,
+        )?;
+
+        ctx.accounts.dispute.status = DisputeStatus::Voting;
+        Ok(())
+    }
+
+    /// An arbiter votes for (the challenger is right) or against.
+    pub fn vote(ctx: Context<Vote>, uphold: bool) -> Result<()> {
+        let config = &ctx.accounts.config;
+        let seat = config.arbiters[..config.arbiter_count as usize]
+            .iter()
+            .position(|a| a == ctx.accounts.arbiter.key)
+            .ok_or(DisputeError::NotArbiter)?;
+
+        let dispute = &mut ctx.accounts.dispute;
+        require!(dispute.status == DisputeStatus::Voting, DisputeError::WrongStatus);
+        require!(dispute.voted & (1 << seat) == 0, DisputeError::AlreadyVoted);
+        dispute.voted |= 1 << seat;
+        if uphold {
+            increment(&mut dispute.votes_for).map_err(DisputeError::from)?;
+        } else {
+            increment(&mut dispute.votes_against).map_err(DisputeError::from)?;
+        }
+
+        emit!(DisputeVoted {
+            dispute: dispute.key(),
+            arbiter: *ctx.accounts.arbiter.key,
+            uphold,
+        });
+
+        Ok(())
+    }
+
+    /// Instead of waiting on the committee, the challenger names a window of
+    /// `frames` frames from `start_frame` for the winner to attest to. Both
+    /// ends must be key frames of the recorded replay: `start_proof` and
+    /// `end_proof` show `start_checksum` and `end_checksum` under the
+    /// match's `checksum_root`.
+    pub fn request_key_frames(
+        ctx: Context<RequestKeyFrames>,
+        start_frame: u32,
+        frames: u16,
+        start_checksum: u64,
+        start_proof: Vec<[u8; 32]>,
+        end_checksum: u64,
+        end_proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require!(dispute.status == DisputeStatus::Voting, DisputeError::WrongStatus);
+        require!(dispute.checksum_root != [0; 32], DisputeError::NoKeyFrames);
+        require!(
+            frames > 0
+                && frames <= MAX_WINDOW_FRAMES
+                && start_frame as u64 + frames as u64 <= dispute.frame_count as u64,
+            DisputeError::InvalidWindow
+        );
+        let end_frame = start_frame + frames as u32;
+        require!(
+            verify_merkle_proof(
+                &start_proof,
+                dispute.checksum_root,
+                key_frame_leaf(start_frame, start_checksum),
+            ) && verify_merkle_proof(
+                &end_proof,
+                dispute.checksum_root,
+                key_frame_leaf(end_frame, end_checksum),
+            ),
+            DisputeError::ChecksumNotCommitted
+        );
+        dispute.window_start = start_frame;
+        dispute.window_frames = frames;
+        dispute.start_checksum = start_checksum;
+        dispute.end_checksum = end_checksum;
+        dispute.window_requested_at = Clock::get()?.unix_timestamp;
+        dispute.status = DisputeStatus::AwaitingKeyFrames;
+
+        emit!(KeyFramesRequested {
+            dispute: dispute.key(),
+            start_frame,
+            frames,
+        });
+
+        Ok(())
+    }
+
+    /// The winner attests to the inputs of their replay over the requested
+    /// window by their hash.
+    pub fn post_key_frames(ctx: Context<PostKeyFrames>, inputs_hash: [u8; 32]) -> Result<()> {
+        let dispute = &mut ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::AwaitingKeyFrames,
+            DisputeError::WrongStatus
+        );
+        require!(
+            Clock::get()?.unix_timestamp < dispute.window_requested_at + RESPONSE_WINDOW,
+            DisputeError::ResponseExpired
+        );
+        dispute.inputs_hash = inputs_hash;
+        dispute.status = DisputeStatus::Simulating;
+        Ok(())
+    }
+
+    /// Re-runs the game core over the attested window and settles the
+    /// dispute: the challenger wins if the winner's inputs do not take the
+    /// window's first key frame to its last. `inputs` holds both players'
+    /// input bits for each frame, player one first. Anyone holding the
+    /// replay may crank this.
+    pub fn verify_transition(
+        ctx: Context<ResolveDispute>,
+        start_state: Vec<u8>,
+        inputs: Vec<u16>,
+        claimed_state: Vec<u8>,
+    ) -> Result<()> {
+        let dispute = &ctx.accounts.dispute;
+        require!(
+            dispute.status == DisputeStatus::Simulating,
+            DisputeError::WrongStatus
+        );
+        let start = decode_state(&start_state)?;
+        let claimed = decode_state(&claimed_state)?;
+        require!(
+            start.frame == dispute.window_start && checksum(&start) == dispute.start_checksum,
+            DisputeError::StateMismatch
+        );
+        require!(
+            checksum(&claimed) == dispute.end_checksum,
+            DisputeError::StateMismatch
+        );
+        require!(
+            inputs.len() == 2 * dispute.window_frames as usize
+                && inputs_hash(&inputs) == dispute.inputs_hash,
+            DisputeError::InputsMismatch
+        );
+
+        let mut state = start;
+        for frame in inputs.chunks_exact(2) {
+            let p1 = Input::from_bits(frame[0]).ok_or(DisputeError::InputsMismatch)?;
+            let p2 = Input::from_bits(frame[1]).ok_or(DisputeError::InputsMismatch)?;
+            state = simulate_frame(&state, [p1, p2]);
+        }
+        let valid = checksum(&state) == checksum(&claimed);
+
+        emit!(TransitionVerified {
+            dispute: dispute.key(),
+            start_frame: dispute.window_start,
+            frames: dispute.window_frames,
+            valid,
+        });
+
+        settle(ctx, !valid)
+    }
+
+    /// Pays the whole vault to the honest party, slashing the other's stake.
+    /// The challenger wins once a quorum upholds the dispute or when the
+    /// winner never answered or never attested to a requested window; the
+    /// winner wins once a quorum rejects it. Anyone may crank this.
+    pub fn resolve_dispute(ctx: Context<ResolveDispute>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let quorum = ctx.accounts.config.quorum;
+        let dispute = &ctx.accounts.dispute;
+        let upheld = match dispute.status {
+            DisputeStatus::AwaitingResponse => {
+                require_gte!(now, dispute.opened_at + RESPONSE_WINDOW, DisputeError::NotResolvable);
+                true
+            }
+            DisputeStatus::AwaitingKeyFrames => {
+                require_gte!(
+                    now,
+                    dispute.window_requested_at + RESPONSE_WINDOW,
+                    DisputeError::NotResolvable
+                );
+                true
+            }
+            DisputeStatus::Voting if dispute.votes_for >= quorum => true,
+            DisputeStatus::Voting if dispute.votes_against >= quorum => false,
+            _ => return err!(DisputeError::NotResolvable),
+        };
+        settle(ctx, upheld)
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<DisputeConfig>(DisputeConfig::discriminator()),
+            Layout::new::<Dispute>(Dispute::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<DisputeError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+/// Sends the vault to the challenger if `upheld`, else to the respondent.
+fn settle(ctx: Context<ResolveDispute>, upheld: bool) -> Result<()> {
+    let dispute = &ctx.accounts.dispute;
+    let match_id = dispute.match_id.to_le_bytes();
+    let signer_seeds: &[&[u8]] = &[b"dispute", &match_id, &[dispute.bump]];
+    let (winner, recipient) = if upheld {
+        (dispute.challenger, &ctx.accounts.challenger_token_account)
+    } else {
+        (dispute.respondent, &ctx.accounts.respondent_token_account)
+    };
+    let payout = ctx.accounts.vault.amount;
+
+    token_compat::transfer(
+        &ctx.accounts.token_program,
+        &ctx.accounts.vault,
+        recipient,
+        &ctx.accounts.mint,
+        &ctx.accounts.dispute.to_account_info(),
+        &[signer_seeds],
+        payout,
+    )?;
+
+    let dispute = &mut ctx.accounts.dispute;
+    dispute.status = if upheld {
+        DisputeStatus::Upheld
+    } else {
+        DisputeStatus::Rejected
+    };
+
+    emit!(DisputeResolved {
+        dispute: dispute.key(),
+        match_id: dispute.match_id,
+        upheld,
+        winner,
+        payout,
+    });
+
+    Ok(())
+}
+
+fn decode_state(bytes: &[u8]) -> Result<GameState> {
+    snapshot::decode(bytes).map_err(|_| error!(DisputeError::InvalidSnapshot))
+}
+
+/// Leaf of a match's `checksum_root` for the checksum of the state at the
+/// start of `frame`, matching `replay::key_frame_leaf`.
+pub fn key_frame_leaf(frame: u32, checksum: u64) -> [u8; 32] {
+    hashv(&[&frame.to_le_bytes(), &checksum.to_le_bytes()]).to_bytes()
+}
+
+/// Checks `leaf` against `root`, hashing each pair in sorted order so proofs
+/// carry no left/right flags.
+fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            hashv(&[&node, sibling]).to_bytes()
+        } else {
+            hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+    computed == root
+}
+
+/// Hash of the inputs as little-endian `u16`s, the commitment posted by
+/// `post_key_frames`.
+pub fn inputs_hash(inputs: &[u16]) -> [u8; 32] {
+    let bytes: Vec<u8> = inputs.iter().flat_map(|bits| bits.to_le_bytes()).collect();
+    hashv(&[&bytes]).to_bytes()
+}
+
+fn validate_mint(ctx: &Context<Initialize>) -> Result<()> {
+    require!(
+        token_compat::supported_mint(&ctx.accounts.mint)?,
+        DisputeError::UnsupportedMint
+    );
+    Ok(())
+}
+
+fn validate_committee(arbiters: &[Pubkey], quorum: u8) -> Result<()> {
+    require!(
+        !arbiters.is_empty() && arbiters.len() <= MAX_ARBITERS,
+        DisputeError::InvalidCommittee
+    );
+    require!(
+        quorum > 0 && quorum as usize <= arbiters.len(),
+        DisputeError::InvalidCommittee
+    );
+    Ok(())
+}
+
+fn validate_challenge(ctx: &Context<OpenDispute>, stake: u64) -> Result<()> {
+    let record = &ctx.accounts.match_account;
+    require!(record.finalized, DisputeError::MatchNotFinalized);
+    require_keys_eq!(record.loser_key(), *ctx.accounts.challenger.key, DisputeError::NotLoser);
+    require!(
+        Clock::get()?.unix_timestamp < record.recorded_at + ctx.accounts.config.challenge_window,
+        DisputeError::ChallengeWindowClosed
+    );
+    require_gte!(stake, ctx.accounts.config.min_stake, DisputeError::StakeTooLow);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"dispute_config"],
+        bump,
+        space = DisputeConfig::SPACE,
+        payer = admin,
+    )]
+    pub config: Account<'info, DisputeConfig>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct OpenDispute<'info> {
+    #[account(mut)]
+    pub challenger: Signer<'info>,
+    #[account(seeds = [b"dispute_config"], bump = config.bump)]
+    pub config: Account<'info, DisputeConfig>,
+    #[account(
+        seeds = [b"match", &match_account.match_id.to_le_bytes()],
+        bump = match_account.bump,
+        seeds::program = match_result::ID,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    #[account(
+        init,
+        seeds = [b"dispute".as_ref(), &match_account.match_id.to_le_bytes()],
+        bump,
+        space = Dispute::SPACE,
+        payer = challenger,
+    )]
+    pub dispute: Account<'info, Dispute>,
+    #[account(
+        init,
+        seeds = [b"dispute_vault", dispute.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = dispute,
+        token::token_program = token_program,
+        payer = challenger,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = config.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = config.mint, token::authority = challenger)]
+    pub challenger_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct AnswerDispute<'info> {
+    pub respondent: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"dispute", &dispute.match_id.to_le_bytes()],
+        bump = dispute.bump,
+        has_one = respondent,
+    )]
+    pub dispute: Account<'info, Dispute>,
+    #[account(
+        mut,
+        seeds = [b"dispute_vault", dispute.key().as_ref()],
+        bump = dispute.vault_bump,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = vault.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = vault.mint, token::authority = respondent)]
+    pub respondent_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RequestKeyFrames<'info> {
+    pub challenger: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"dispute", &dispute.match_id.to_le_bytes()],
+        bump = dispute.bump,
+        has_one = challenger,
+    )]
+    pub dispute: Account<'info, Dispute>,
+}
+
+#[derive(Accounts)]
+pub struct PostKeyFrames<'info> {
+    pub respondent: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"dispute", &dispute.match_id.to_le_bytes()],
+        bump = dispute.bump,
+        has_one = respondent,
+    )]
+    pub dispute: Account<'info, Dispute>,
+}
+
+#[derive(Accounts)]
+pub struct Vote<'info> {
+    pub arbiter: Signer<'info>,
+    #[account(seeds = [b"dispute_config"], bump = config.bump)]
+    pub config: Account<'info, DisputeConfig>,
+    #[account(
+        mut,
+        seeds = [b"dispute", &dispute.match_id.to_le_bytes()],
+        bump = dispute.bump,
+    )]
+    pub dispute: Account<'info, Dispute>,
+}
+
+#[derive(Accounts)]
+pub struct ResolveDispute<'info> {
+    #[account(seeds = [b"dispute_config"], bump = config.bump)]
+    pub config: Account<'info, DisputeConfig>,
+    #[account(
+        mut,
+        seeds = [b"dispute", &dispute.match_id.to_le_bytes()],
+        bump = dispute.bump,
+    )]
+    pub dispute: Account<'info, Dispute>,
+    #[account(
+        mut,
+        seeds = [b"dispute_vault", dispute.key().as_ref()],
+        bump = dispute.vault_bump,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = vault.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = vault.mint, token::authority = dispute.challenger)]
+    pub challenger_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = vault.mint, token::authority = dispute.respondent)]
+    pub respondent_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct DisputeConfig {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    /// Token both sides stake. A Token-2022 transfer fee comes out of each
+    /// stake, and the winner is paid whatever the vault holds.
+    pub mint: Pubkey,
+    /// Only the first `arbiter_count` entries are set.
+    pub arbiters: [Pubkey; MAX_ARBITERS],
+    pub arbiter_count: u8,
+    /// Matching votes needed to resolve a dispute.
+    pub quorum: u8,
+    /// Seconds after a match is recorded during which it can be disputed.
+    pub challenge_window: i64,
+    pub min_stake: u64,
+    pub bump: u8,
+}
+
+impl DisputeConfig {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 32 // mint
+        + 32 * MAX_ARBITERS // arbiters
+        + 1 // arbiter_count
+        + 1 // quorum
+        + 8 // challenge_window
+        + 8 // min_stake
+        + 1; // bump
+}
+
+impl Versioned for DisputeConfig {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[account]
+pub struct Dispute {
+    pub layout_version: u8,
+    pub match_id: u64,
+    /// The match loser.
+    pub challenger: Pubkey,
+    /// The recorded winner.
+    pub respondent: Pubkey,
+    pub recorded_replay_hash: [u8; 32],
+    pub claimed_replay_hash: [u8; 32],
+    pub frame_count: u32,
+    /// Stake per side.
+    pub stake: u64,
+    pub status: DisputeStatus,
+    pub opened_at: i64,
+    pub votes_for: u8,
+    pub votes_against: u8,
+    /// Bit `i` is set once arbiter `i` has voted.
+    pub voted: u8,
+    /// Key-frame window the challenger asked the winner to attest to.
+    pub window_start: u32,
+    pub window_frames: u16,
+    pub window_requested_at: i64,
+    /// Checksums at the window's ends, proven against `checksum_root` by
+    /// `request_key_frames`.
+    pub start_checksum: u64,
+    /// The winner's attestation, set by `post_key_frames`.
+    pub inputs_hash: [u8; 32],
+    pub end_checksum: u64,
+    pub bump: u8,
+    pub vault_bump: u8,
+    /// The match's `checksum_root`; zero if it committed no key frames, so
+    /// only the committee can settle the dispute.
+    pub checksum_root: [u8; 32],
+}
+
+impl Dispute {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 8 // match_id
+        + 32 // challenger
+        + 32 // respondent
+        + 32 // recorded_replay_hash
+        + 32 // claimed_replay_hash
+        + 4 // frame_count
+        + 8 // stake
+        + 1 // status
+        + 8 // opened_at
+        + 1 // votes_for
+        + 1 // votes_against
+        + 1 // voted
+        + 4 // window_start
+        + 2 // window_frames
+        + 8 // window_requested_at
+        + 8 // start_checksum
+        + 32 // inputs_hash
+        + 8 // end_checksum
+        + 1 // bump
+        + 1 // vault_bump
+        + 32; // checksum_root
+}
+
+impl Versioned for Dispute {
+    const LAYOUT_VERSION: u8 = 2;
+    const MIGRATIONS: &'static [Step] = &[
+        ADD_LAYOUT_VERSION,
+        Step {
+            from: 1,
+            grow: 32,
+            upgrade: append_checksum_root,
+        },
+    ];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+/// Layout 2 appends `checksum_root`. Zero, disputes opened before then
+/// can't request key frames.
+fn append_checksum_root(_: &mut [u8]) {}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisputeStatus {
+    /// Only the challenger has staked.
+    AwaitingResponse,
+    /// Both stakes are in the vault and arbiters are voting.
+    Voting,
+    /// The challenger asked for a key-frame window the winner has not
+    /// attested to yet.
+    AwaitingKeyFrames,
+    /// The window is attested and waiting to be re-simulated.
+    Simulating,
+    /// The challenger was right.
+    Upheld,
+    /// The recorded result stands.
+    Rejected,
+}
+
+#[event]
+pub struct DisputeOpened {
+    pub dispute: Pubkey,
+    pub match_id: u64,
+    pub challenger: Pubkey,
+    pub claimed_replay_hash: [u8; 32],
+    pub stake: u64,
+}
+
+#[event]
+pub struct DisputeVoted {
+    pub dispute: Pubkey,
+    pub arbiter: Pubkey,
+    pub uphold: bool,
+}
+
+#[event]
+pub struct KeyFramesRequested {
+    pub dispute: Pubkey,
+    pub start_frame: u32,
+    pub frames: u16,
+}
+
+#[event]
+pub struct TransitionVerified {
+    pub dispute: Pubkey,
+    pub start_frame: u32,
+    pub frames: u16,
+    /// Whether the simulation reached the winner's claimed state.
+    pub valid: bool,
+}
+
+#[event]
+pub struct DisputeResolved {
+    pub dispute: Pubkey,
+    pub match_id: u64,
+    pub upheld: bool,
+    pub winner: Pubkey,
+    pub payout: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum DisputeError {
+    #[msg("Arbiter list or quorum is invalid")]
+    InvalidCommittee,
+    #[msg("Match result is not finalized")]
+    MatchNotFinalized,
+    #[msg("Only the match loser can dispute it")]
+    NotLoser,
+    #[msg("Challenge window has closed")]
+    ChallengeWindowClosed,
+    #[msg("Stake is below the minimum")]
+    StakeTooLow,
+    #[msg("Dispute is not in the right status")]
+    WrongStatus,
+    #[msg("Response window has expired")]
+    ResponseExpired,
+    #[msg("Signer is not an arbiter")]
+    NotArbiter,
+    #[msg("Arbiter has already voted")]
+    AlreadyVoted,
+    #[msg("Dispute cannot be resolved yet")]
+    NotResolvable,
+    #[msg("Key-frame window is empty, too long or past the match end")]
+    InvalidWindow,
+    #[msg("Game state snapshot is malformed")]
+    InvalidSnapshot,
+    #[msg("Game state does not match the window's checksum")]
+    StateMismatch,
+    #[msg("Inputs do not match the attested window")]
+    InputsMismatch,
+    #[msg("Mint has a token extension disputes can't support")]
+    UnsupportedMint,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+    #[msg("Match committed no key-frame checksums")]
+    NoKeyFrames,
+    #[msg("Checksum is not a key frame of the recorded match")]
+    ChecksumNotCommitted,
+}
+
+impl From<Overflow> for DisputeError {
+    fn from(_: Overflow) -> Self {
+        DisputeError::Overflow
+    }
+}
+
+impl From<MigrationError> for DisputeError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => DisputeError::AlreadyMigrated,
+            _ => DisputeError::UnsupportedLayout,
+        }
+    }
+}
@@ -0,0 +1,1030 @@
+// Rust Anchor Program for Guilds
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{Layout, MigrationError, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use checked_math::{increment, sub_assign, Overflow};
+use match_result::MatchAccount;
+
+
+declare_id!("GUILD_PROGRAM_ID_HERE");
+
+/// Seconds a spending proposal stays open for approvals and execution.
+pub const PROPOSAL_TTL: i64 = 7 * 24 * 60 * 60;
+
+#[program]
+pub mod guild {
+    use super::*;
+
+    /// Creates the guild with the signer as leader and a treasury of
+    /// `mint` tokens. Spending from the treasury needs `approval_threshold`
+    /// officers, the leader included, to approve.
+    #[access_control(validate_guild(&ctx, &name, approval_threshold))]
+    pub fn create_guild(ctx: Context<CreateGuild>, name: String, approval_threshold: u8) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let guild = &mut ctx.accounts.guild;
+        guild.layout_version = Guild::LAYOUT_VERSION;
+        guild.name = name;
+        guild.leader = *ctx.accounts.leader.key;
+        guild.treasury = ctx.accounts.treasury.key();
+        guild.mint = ctx.accounts.mint.key();
+        guild.member_count = 1;
+        guild.approval_threshold = approval_threshold;
+        guild.proposal_count = 0;
+        guild.matches_played = 0;
+        guild.wins = 0;
+        guild.created_at = now;
+        guild.bump = ctx.bumps.guild;
+
+        let member = &mut ctx.accounts.member;
+        member.layout_version = GuildMember::LAYOUT_VERSION;
+        member.guild = guild.key();
+        member.wallet = guild.leader;
+        member.role = Role::Leader;
+        member.joined_at = now;
+        member.bump = ctx.bumps.member;
+
+        emit!(GuildCreated {
+            guild: guild.key(),
+            name: guild.name.clone(),
+            leader: guild.leader,
+            treasury: guild.treasury,
+        });
+
+        Ok(())
+    }
+
+    /// Invites `invitee`, who joins by signing `accept_invite`. Officers
+    /// and the leader can invite.
+    #[access_control(min_role(&ctx.accounts.inviter_member, Role::Officer))]
+    pub fn invite_member(ctx: Context<InviteMember>, invitee: Pubkey) -> Result<()> {
+        let invite = &mut ctx.accounts.invite;
+        invite.layout_version = GuildInvite::LAYOUT_VERSION;
+        invite.guild = ctx.accounts.guild.key();
+        invite.invitee = invitee;
+        invite.inviter = *ctx.accounts.inviter.key;
+        invite.bump = ctx.bumps.invite;
+
+        emit!(MemberInvited {
+            guild: invite.guild,
+            invitee,
+            inviter: invite.inviter,
+        });
+
+        Ok(())
+    }
+
+    /// Joins the guild as a member. A wallet belongs to at most one guild,
+    /// so its matches are tagged with exactly one.
+    pub fn accept_invite(ctx: Context<AcceptInvite>) -> Result<()> {
+        let guild = &mut ctx.accounts.guild;
+        require_gt!(Guild::MAX_MEMBERS, guild.member_count, GuildError::GuildFull);
+        increment(&mut guild.member_count).map_err(GuildError::from)?;
+
+        let member = &mut ctx.accounts.member;
+        member.layout_version = GuildMember::LAYOUT_VERSION;
+        member.guild = guild.key();
+        member.wallet = *ctx.accounts.invitee.key;
+        member.role = Role::Member;
+        member.joined_at = Clock::get()?.unix_timestamp;
+        member.bump = ctx.bumps.member;
+
+        emit!(MemberJoined {
+            guild: member.guild,
+            wallet: member.wallet,
+        });
+
+        Ok(())
+    }
+
+    /// Leaves the guild. The leader has to hand over leadership first.
+    pub fn leave_guild(ctx: Context<LeaveGuild>) -> Result<()> {
+        require!(ctx.accounts.member.role != Role::Leader, GuildError::LeaderCannotLeave);
+        let guild = &mut ctx.accounts.guild;
+        sub_assign(&mut guild.member_count, 1).map_err(GuildError::from)?;
+
+        emit!(MemberLeft {
+            guild: guild.key(),
+            wallet: *ctx.accounts.wallet.key,
+            kicked: false,
+        });
+
+        Ok(())
+    }
+
+    /// Removes a member of lower rank than the signer. The membership's
+    /// rent goes back to the removed wallet.
+    pub fn kick_member(ctx: Context<KickMember>) -> Result<()> {
+        require!(
+            ctx.accounts.officer_member.role > ctx.accounts.member.role,
+            GuildError::InsufficientRole
+        );
+        let guild = &mut ctx.accounts.guild;
+        sub_assign(&mut guild.member_count, 1).map_err(GuildError::from)?;
+
+        emit!(MemberLeft {
+            guild: guild.key(),
+            wallet: ctx.accounts.member.wallet,
+            kicked: true,
+        });
+
+        Ok(())
+    }
+
+    /// Makes a member an officer or an officer a member. Leader only; see
+    /// `transfer_leadership` for handing over the guild.
+    pub fn set_role(ctx: Context<SetRole>, role: Role) -> Result<()> {
+        require!(role != Role::Leader, GuildError::InvalidRole);
+        let member = &mut ctx.accounts.member;
+        require!(member.role != Role::Leader, GuildError::InvalidRole);
+        member.role = role;
+
+        emit!(RoleChanged {
+            guild: member.guild,
+            wallet: member.wallet,
+            role,
+        });
+
+        Ok(())
+    }
+
+    /// Hands the guild to another member. The old leader stays on as an
+    /// officer.
+    pub fn transfer_leadership(ctx: Context<TransferLeadership>) -> Result<()> {
+        ctx.accounts.leader_member.role = Role::Officer;
+        let member = &mut ctx.accounts.member;
+        member.role = Role::Leader;
+        ctx.accounts.guild.leader = member.wallet;
+
+        emit!(RoleChanged {
+            guild: member.guild,
+            wallet: member.wallet,
+            role: Role::Leader,
+        });
+
+        Ok(())
+    }
+
+    pub fn set_approval_threshold(ctx: Context<SetApprovalThreshold>, approval_threshold: u8) -> Result<()> {
+        require!(
+            approval_threshold > 0 && approval_threshold as usize <= SpendProposal::MAX_APPROVALS,
+            GuildError::InvalidThreshold
+        );
+        ctx.accounts.guild.approval_threshold = approval_threshold;
+        Ok(())
+    }
+
+    /// Proposes paying `amount` treasury tokens to `recipient`, counting
+    /// the proposer's approval.
+    #[access_control(min_role(&ctx.accounts.proposer_member, Role::Officer))]
+    pub fn propose_spend(ctx: Context<ProposeSpend>, amount: u64) -> Result<()> {
+        require_gt!(amount, 0, GuildError::InvalidAmount);
+        let now = Clock::get()?.unix_timestamp;
+        let guild = &mut ctx.accounts.guild;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.layout_version = SpendProposal::LAYOUT_VERSION;
+        proposal.guild = guild.key();
+        proposal.proposal_id = guild.proposal_count;
+        proposal.proposer = *ctx.accounts.proposer.key;
+        proposal.recipient = ctx.accounts.recipient.key();
+        proposal.amount = amount;
+        proposal.approvals = vec![proposal.proposer];
+        proposal.expires_at = now + PROPOSAL_TTL;
+        proposal.executed = false;
+        proposal.bump = ctx.bumps.proposal;
+        increment(&mut guild.proposal_count).map_err(GuildError::from)?;
+
+        emit!(SpendProposed {
+            guild: proposal.guild,
+            proposal_id: proposal.proposal_id,
+            proposer: proposal.proposer,
+            recipient: proposal.recipient,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Adds an officer's approval. Approvals of officers who later lose the
+    /// role still count, which `PROPOSAL_TTL` bounds.
+    #[access_control(
+        min_role(&ctx.accounts.approver_member, Role::Officer)
+        validate_open(&ctx.accounts.proposal)
+    )]
+    pub fn approve_spend(ctx: Context<ApproveSpend>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        let approver = *ctx.accounts.approver.key;
+        require!(!proposal.approvals.contains(&approver), GuildError::AlreadyApproved);
+        require_gt!(
+            SpendProposal::MAX_APPROVALS,
+            proposal.approvals.len(),
+            GuildError::TooManyApprovals
+        );
+        proposal.approvals.push(approver);
+
+        emit!(SpendApproved {
+            guild: proposal.guild,
+            proposal_id: proposal.proposal_id,
+            approver,
+            approvals: proposal.approvals.len() as u8,
+        });
+
+        Ok(())
+    }
+
+    /// Pays out a proposal that has reached the guild's approval threshold.
+    /// Anyone can call it.
+    #[access_control(validate_open(&ctx.accounts.proposal))]
+    pub fn execute_spend(ctx: Context<ExecuteSpend>) -> Result<()> {
+        let guild = &ctx.accounts.guild;
+        require_gte!(
+            ctx.accounts.proposal.approvals.len(),
+            guild.approval_threshold as usize,
+            GuildError::NotEnoughApprovals
+        );
+        let name_seed = guild_name_seed(&guild.name);
+        let signer_seeds: &[&[u8]] = &[b"guild", &name_seed, &[guild.bump]];
+        let amount = ctx.accounts.proposal.amount;
+        token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.treasury,
+            &ctx.accounts.recipient,
+            &ctx.accounts.mint,
+            &ctx.accounts.guild.to_account_info(),
+            &[signer_seeds],
+            amount,
+        )?;
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.executed = true;
+
+        emit!(SpendExecuted {
+            guild: proposal.guild,
+            proposal_id: proposal.proposal_id,
+            recipient: proposal.recipient,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Counts a finalized match towards the guild of the player on `side`,
+    /// and moves the guild on the team leaderboard. Each side of a match is
+    /// counted once, and only for a player who was in the guild before the
+    /// match was recorded. Anyone can call it.
+    #[access_control(validate_tag(&ctx.accounts.match_account, &ctx.accounts.member, side))]
+    pub fn tag_match(ctx: Context<TagMatch>, side: u8) -> Result<()> {
+        let record = &ctx.accounts.match_account;
+        let won = record.winner == side;
+        let guild = &mut ctx.accounts.guild;
+        increment(&mut guild.matches_played).map_err(GuildError::from)?;
+        if won {
+            increment(&mut guild.wins).map_err(GuildError::from)?;
+        }
+
+        let tag = &mut ctx.accounts.tag;
+        tag.layout_version = GuildMatchTag::LAYOUT_VERSION;
+        tag.match_id = record.match_id;
+        tag.side = side;
+        tag.guild = guild.key();
+        tag.bump = ctx.bumps.tag;
+
+        let board = &mut ctx.accounts.leaderboard;
+        if board.layout_version == 0 {
+            board.layout_version = GuildLeaderboard::LAYOUT_VERSION;
+            board.bump = ctx.bumps.leaderboard;
+        }
+        let rank = board.insert(GuildStanding {
+            guild: guild.key(),
+            wins: guild.wins,
+            matches_played: guild.matches_played,
+        });
+
+        emit!(GuildMatchTagged {
+            guild: guild.key(),
+            match_id: record.match_id,
+            player: record.players[side as usize],
+            won,
+            rank,
+        });
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<Guild>(Guild::discriminator()),
+            Layout::new::<GuildMember>(GuildMember::discriminator()),
+            Layout::new::<GuildInvite>(GuildInvite::discriminator()),
+            Layout::new::<SpendProposal>(SpendProposal::discriminator()),
+            Layout::new::<GuildMatchTag>(GuildMatchTag::discriminator()),
+            Layout::new::<GuildLeaderboard>(GuildLeaderboard::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<GuildError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+/// Seed for a guild's PDA: the hash of its name.
+pub fn guild_name_seed(name: &str) -> [u8; 32] {
+    hash(name.as_bytes()).to_bytes()
+}
+
+fn validate_guild(ctx: &Context<CreateGuild>, name: &str, approval_threshold: u8) -> Result<()> {
+    require!(
+        !name.is_empty() && name.len() <= Guild::MAX_NAME_LEN,
+        GuildError::InvalidName
+    );
+    require!(
+        approval_threshold > 0 && approval_threshold as usize <= SpendProposal::MAX_APPROVALS,
+        GuildError::InvalidThreshold
+    );
+    require!(
+        token_compat::supported_mint(&ctx.accounts.mint)?,
+        GuildError::UnsupportedMint
+    );
+    Ok(())
+}
+
+fn min_role(member: &GuildMember, role: Role) -> Result<()> {
+    require!(member.role >= role, GuildError::InsufficientRole);
+    Ok(())
+}
+
+fn validate_open(proposal: &SpendProposal) -> Result<()> {
+    require!(!proposal.executed, GuildError::AlreadyExecuted);
+    require_gt!(
+        proposal.expires_at,
+        Clock::get()?.unix_timestamp,
+        GuildError::ProposalExpired
+    );
+    Ok(())
+}
+
+fn validate_tag(match_account: &MatchAccount, member: &GuildMember, side: u8) -> Result<()> {
+    require!(match_account.finalized, GuildError::MatchNotFinalized);
+    require_gt!(2, side, GuildError::InvalidSide);
+    require_gt!(
+        match_account.recorded_at,
+        member.joined_at,
+        GuildError::JoinedAfterMatch
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct CreateGuild<'info> {
+    #[account(mut)]
+    pub leader: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"guild".as_ref(), &guild_name_seed(&name)],
+        bump,
+        space = Guild::SPACE,
+        payer = leader,
+    )]
+    pub guild: Account<'info, Guild>,
+    #[account(
+        init,
+        seeds = [b"guild_member", leader.key.as_ref()],
+        bump,
+        space = GuildMember::SPACE,
+        payer = leader,
+    )]
+    pub member: Account<'info, GuildMember>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        seeds = [b"guild_treasury", guild.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = guild,
+        token::token_program = token_program,
+        payer = leader,
+    )]
+    pub treasury: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(invitee: Pubkey)]
+pub struct InviteMember<'info> {
+    #[account(mut)]
+    pub inviter: Signer<'info>,
+    #[account(
+        seeds = [b"guild_member", inviter.key.as_ref()],
+        bump = inviter_member.bump,
+        constraint = inviter_member.guild == guild.key() @ GuildError::NotAMember,
+    )]
+    pub inviter_member: Account<'info, GuildMember>,
+    #[account(seeds = [b"guild", &guild_name_seed(&guild.name)], bump = guild.bump)]
+    pub guild: Account<'info, Guild>,
+    #[account(
+        init,
+        seeds = [b"guild_invite", guild.key().as_ref(), invitee.as_ref()],
+        bump,
+        space = GuildInvite::SPACE,
+        payer = inviter,
+    )]
+    pub invite: Account<'info, GuildInvite>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptInvite<'info> {
+    #[account(mut)]
+    pub invitee: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"guild_invite", invite.guild.as_ref(), invitee.key.as_ref()],
+        bump = invite.bump,
+        has_one = inviter,
+        has_one = invitee,
+        close = inviter,
+    )]
+    pub invite: Account<'info, GuildInvite>,
+    /// Refunded the invite's rent
+    #[account(mut)]
+    pub inviter: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"guild", &guild_name_seed(&guild.name)],
+        bump = guild.bump,
+        address = invite.guild,
+    )]
+    pub guild: Account<'info, Guild>,
+    #[account(
+        init,
+        seeds = [b"guild_member", invitee.key.as_ref()],
+        bump,
+        space = GuildMember::SPACE,
+        payer = invitee,
+    )]
+    pub member: Account<'info, GuildMember>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct LeaveGuild<'info> {
+    #[account(mut)]
+    pub wallet: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"guild_member", wallet.key.as_ref()],
+        bump = member.bump,
+        close = wallet,
+    )]
+    pub member: Account<'info, GuildMember>,
+    #[account(
+        mut,
+        seeds = [b"guild", &guild_name_seed(&guild.name)],
+        bump = guild.bump,
+        address = member.guild,
+    )]
+    pub guild: Account<'info, Guild>,
+}
+
+#[derive(Accounts)]
+pub struct KickMember<'info> {
+    pub officer: Signer<'info>,
+    #[account(
+        seeds = [b"guild_member", officer.key.as_ref()],
+        bump = officer_member.bump,
+        constraint = officer_member.guild == guild.key() @ GuildError::NotAMember,
+    )]
+    pub officer_member: Account<'info, GuildMember>,
+    #[account(
+        mut,
+        seeds = [b"guild_member", member.wallet.as_ref()],
+        bump = member.bump,
+        has_one = wallet,
+        constraint = member.guild == guild.key() @ GuildError::NotAMember,
+        close = wallet,
+    )]
+    pub member: Account<'info, GuildMember>,
+    /// Refunded the membership's rent
+    #[account(mut)]
+    pub wallet: SystemAccount<'info>,
+    #[account(mut, seeds = [b"guild", &guild_name_seed(&guild.name)], bump = guild.bump)]
+    pub guild: Account<'info, Guild>,
+}
+
+#[derive(Accounts)]
+pub struct SetRole<'info> {
+    pub leader: Signer<'info>,
+    #[account(seeds = [b"guild", &guild_name_seed(&guild.name)], bump = guild.bump, has_one = leader)]
+    pub guild: Account<'info, Guild>,
+    #[account(
+        mut,
+        seeds = [b"guild_member", member.wallet.as_ref()],
+        bump = member.bump,
+        constraint = member.guild == guild.key() @ GuildError::NotAMember,
+    )]
+    pub member: Account<'info, GuildMember>,
+}
+
+#[derive(Accounts)]
+pub struct TransferLeadership<'info> {
+    pub leader: Signer<'info>,
+    #[account(mut, seeds = [b"guild", &guild_name_seed(&guild.name)], bump = guild.bump, has_one = leader)]
+    pub guild: Account<'info, Guild>,
+    #[account(
+        mut,
+        seeds = [b"guild_member", leader.key.as_ref()],
+        bump = leader_member.bump,
+    )]
+    pub leader_member: Account<'info, GuildMember>,
+    #[account(
+        mut,
+        seeds = [b"guild_member", member.wallet.as_ref()],
+        bump = member.bump,
+        constraint = member.guild == guild.key() @ GuildError::NotAMember,
+        constraint = member.wallet != leader.key() @ GuildError::InvalidRole,
+    )]
+    pub member: Account<'info, GuildMember>,
+}
+
+#[derive(Accounts)]
+pub struct SetApprovalThreshold<'info> {
+    pub leader: Signer<'info>,
+    #[account(mut, seeds = [b"guild", &guild_name_seed(&guild.name)], bump = guild.bump, has_one = leader)]
+    pub guild: Account<'info, Guild>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeSpend<'info> {
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    #[account(
+        seeds = [b"guild_member", proposer.key.as_ref()],
+        bump = proposer_member.bump,
+        constraint = proposer_member.guild == guild.key() @ GuildError::NotAMember,
+    )]
+    pub proposer_member: Account<'info, GuildMember>,
+    #[account(mut, seeds = [b"guild", &guild_name_seed(&guild.name)], bump = guild.bump)]
+    pub guild: Account<'info, Guild>,
+    #[account(
+        init,
+        seeds = [b"guild_proposal", guild.key().as_ref(), &guild.proposal_count.to_le_bytes()],
+        bump,
+        space = SpendProposal::SPACE,
+        payer = proposer,
+    )]
+    pub proposal: Account<'info, SpendProposal>,
+    #[account(token::mint = guild.mint)]
+    pub recipient: InterfaceAccount<'info, TokenAccount>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveSpend<'info> {
+    pub approver: Signer<'info>,
+    #[account(
+        seeds = [b"guild_member", approver.key.as_ref()],
+        bump = approver_member.bump,
+        constraint = approver_member.guild == proposal.guild @ GuildError::NotAMember,
+    )]
+    pub approver_member: Account<'info, GuildMember>,
+    #[account(
+        mut,
+        seeds = [b"guild_proposal", proposal.guild.as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump,
+    )]
+    pub proposal: Account<'info, SpendProposal>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteSpend<'info> {
+    #[account(
+        seeds = [b"guild", &guild_name_seed(&guild.name)],
+        bump = guild.bump,
+        has_one = treasury,
+        has_one = mint,
+    )]
+    pub guild: Account<'info, Guild>,
+    #[account(
+        mut,
+        seeds = [b"guild_proposal", guild.key().as_ref(), &proposal.proposal_id.to_le_bytes()],
+        bump = proposal.bump,
+        has_one = recipient,
+    )]
+    pub proposal: Account<'info, SpendProposal>,
+    #[account(mut)]
+    pub treasury: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub recipient: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(side: u8)]
+pub struct TagMatch<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [b"match", &match_account.match_id.to_le_bytes()],
+        bump = match_account.bump,
+        seeds::program = match_result::ID,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    #[account(
+        seeds = [b"guild_member", match_account.players[side as usize % 2].as_ref()],
+        bump = member.bump,
+    )]
+    pub member: Account<'info, GuildMember>,
+    #[account(
+        mut,
+        seeds = [b"guild", &guild_name_seed(&guild.name)],
+        bump = guild.bump,
+        address = member.guild,
+    )]
+    pub guild: Account<'info, Guild>,
+    #[account(
+        init,
+        seeds = [b"guild_match".as_ref(), &match_account.match_id.to_le_bytes(), &[side]],
+        bump,
+        space = GuildMatchTag::SPACE,
+        payer = payer,
+    )]
+    pub tag: Account<'info, GuildMatchTag>,
+    #[account(
+        init_if_needed,
+        seeds = [b"guild_leaderboard"],
+        bump,
+        space = GuildLeaderboard::SPACE,
+        payer = payer,
+    )]
+    pub leaderboard: Account<'info, GuildLeaderboard>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    Member,
+    Officer,
+    Leader,
+}
+
+/// A guild, at `[b"guild", guild_name_seed(name)]`.
+#[account]
+pub struct Guild {
+    pub layout_version: u8,
+    pub name: String,
+    pub leader: Pubkey,
+    /// Token account owned by the guild PDA; anyone can deposit with a
+    /// plain transfer.
+    pub treasury: Pubkey,
+    pub mint: Pubkey,
+    pub member_count: u16,
+    /// Approvals a `SpendProposal` needs before it can be executed.
+    pub approval_threshold: u8,
+    pub proposal_count: u64,
+    /// Tagged match sides; see `tag_match`.
+    pub matches_played: u32,
+    pub wins: u32,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Guild {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const MAX_MEMBERS: u16 = 64;
+
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 4 + Self::MAX_NAME_LEN // name
+        + 32 // leader
+        + 32 // treasury
+        + 32 // mint
+        + 2 // member_count
+        + 1 // approval_threshold
+        + 8 // proposal_count
+        + 4 // matches_played
+        + 4 // wins
+        + 8 // created_at
+        + 1; // bump
+}
+
+impl Versioned for Guild {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// A wallet's membership, at `[b"guild_member", wallet]`.
+#[account]
+pub struct GuildMember {
+    pub layout_version: u8,
+    pub guild: Pubkey,
+    pub wallet: Pubkey,
+    pub role: Role,
+    pub joined_at: i64,
+    pub bump: u8,
+}
+
+impl GuildMember {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // guild
+        + 32 // wallet
+        + 1 // role
+        + 8 // joined_at
+        + 1; // bump
+}
+
+impl Versioned for GuildMember {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// Open invite, at `[b"guild_invite", guild, invitee]`.
+#[account]
+pub struct GuildInvite {
+    pub layout_version: u8,
+    pub guild: Pubkey,
+    pub invitee: Pubkey,
+    pub inviter: Pubkey,
+    pub bump: u8,
+}
+
+impl GuildInvite {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // guild
+        + 32 // invitee
+        + 32 // inviter
+        + 1; // bump
+}
+
+impl Versioned for GuildInvite {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// Treasury payment waiting on approvals, at `[b"guild_proposal", guild,
+/// proposal_id]`.
+#[account]
+pub struct SpendProposal {
+    pub layout_version: u8,
+    pub guild: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub approvals: Vec<Pubkey>,
+    pub expires_at: i64,
+    pub executed: bool,
+    pub bump: u8,
+}
+
+impl SpendProposal {
+    pub const MAX_APPROVALS: usize = 16;
+
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // guild
+        + 8 // proposal_id
+        + 32 // proposer
+        + 32 // recipient
+        + 8 // amount
+        + 4 + 32 * Self::MAX_APPROVALS // approvals
+        + 8 // expires_at
+        + 1 // executed
+        + 1; // bump
+}
+
+impl Versioned for SpendProposal {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// Marks one side of a match as counted for `guild`, at `[b"guild_match",
+/// match_id, side]`.
+#[account]
+pub struct GuildMatchTag {
+    pub layout_version: u8,
+    pub match_id: u64,
+    pub side: u8,
+    pub guild: Pubkey,
+    pub bump: u8,
+}
+
+impl GuildMatchTag {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 8 // match_id
+        + 1 // side
+        + 32 // guild
+        + 1; // bump
+}
+
+impl Versioned for GuildMatchTag {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GuildStanding {
+    pub guild: Pubkey,
+    pub wins: u32,
+    pub matches_played: u32,
+}
+
+impl GuildStanding {
+    pub const SIZE: usize = 32 + 4 + 4;
+}
+
+/// Guilds with the most tagged wins, at `[b"guild_leaderboard"]`, most
+/// first.
+#[account]
+pub struct GuildLeaderboard {
+    pub layout_version: u8,
+    pub standings: Vec<GuildStanding>,
+    pub bump: u8,
+}
+
+impl GuildLeaderboard {
+    pub const MAX_STANDINGS: usize = 16;
+
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 4 + GuildStanding::SIZE * Self::MAX_STANDINGS // standings
+        + 1; // bump
+
+    /// Places `standing` by wins, replacing the guild's earlier standing,
+    /// and returns its rank from 0, or `None` if it didn't make the board.
+    pub fn insert(&mut self, standing: GuildStanding) -> Option<u8> {
+        self.standings.retain(|s| s.guild != standing.guild);
+        let rank = self.standings.iter().take_while(|s| s.wins >= standing.wins).count();
+        if rank >= Self::MAX_STANDINGS {
+            return None;
+        }
+        self.standings.insert(rank, standing);
+        self.standings.truncate(Self::MAX_STANDINGS);
+        Some(rank as u8)
+    }
+}
+
+impl Versioned for GuildLeaderboard {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[event]
+pub struct GuildCreated {
+    pub guild: Pubkey,
+    pub name: String,
+    pub leader: Pubkey,
+    pub treasury: Pubkey,
+}
+
+#[event]
+pub struct MemberInvited {
+    pub guild: Pubkey,
+    pub invitee: Pubkey,
+    pub inviter: Pubkey,
+}
+
+#[event]
+pub struct MemberJoined {
+    pub guild: Pubkey,
+    pub wallet: Pubkey,
+}
+
+#[event]
+pub struct MemberLeft {
+    pub guild: Pubkey,
+    pub wallet: Pubkey,
+    pub kicked: bool,
+}
+
+#[event]
+pub struct RoleChanged {
+    pub guild: Pubkey,
+    pub wallet: Pubkey,
+    pub role: Role,
+}
+
+#[event]
+pub struct SpendProposed {
+    pub guild: Pubkey,
+    pub proposal_id: u64,
+    pub proposer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct SpendApproved {
+    pub guild: Pubkey,
+    pub proposal_id: u64,
+    pub approver: Pubkey,
+    pub approvals: u8,
+}
+
+#[event]
+pub struct SpendExecuted {
+    pub guild: Pubkey,
+    pub proposal_id: u64,
+    pub recipient: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct GuildMatchTagged {
+    pub guild: Pubkey,
+    pub match_id: u64,
+    pub player: Pubkey,
+    pub won: bool,
+    /// `None` when the guild isn't on the leaderboard.
+    pub rank: Option<u8>,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum GuildError {
+    #[msg("Guild name must be 1 to 32 bytes")]
+    InvalidName,
+    #[msg("Approval threshold must be between 1 and 16")]
+    InvalidThreshold,
+    #[msg("Mint has a token extension the treasury can't support")]
+    UnsupportedMint,
+    #[msg("Wallet is not a member of this guild")]
+    NotAMember,
+    #[msg("Member's role is too low")]
+    InsufficientRole,
+    #[msg("Role cannot be given or changed this way")]
+    InvalidRole,
+    #[msg("Guild is full")]
+    GuildFull,
+    #[msg("Leader must hand over leadership before leaving")]
+    LeaderCannotLeave,
+    #[msg("Amount must be positive")]
+    InvalidAmount,
+    #[msg("Officer has already approved")]
+    AlreadyApproved,
+    #[msg("Proposal has no room for more approvals")]
+    TooManyApprovals,
+    #[msg("Proposal does not have enough approvals")]
+    NotEnoughApprovals,
+    #[msg("Proposal was already executed")]
+    AlreadyExecuted,
+    #[msg("Proposal has expired")]
+    ProposalExpired,
+    #[msg("Match is not finalized")]
+    MatchNotFinalized,
+    #[msg("Side must be 0 or 1")]
+    InvalidSide,
+    #[msg("Player joined the guild after the match")]
+    JoinedAfterMatch,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for GuildError {
+    fn from(_: Overflow) -> Self {
+        GuildError::Overflow
+    }
+}
+
+impl From<MigrationError> for GuildError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => GuildError::AlreadyMigrated,
+            _ => GuildError::UnsupportedLayout,
+        }
+    }
+}
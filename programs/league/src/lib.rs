@@ -0,0 +1,560 @@
+// Rust Anchor Program for Round-Robin Leagues
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{Layout, MigrationError, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::Discriminator;
+use checked_math::{add_assign, increment, Overflow};
+use game_core::Rng;
+use match_result::MatchAccount;
+
+declare_id!("LEAGUE_PROGRAM_ID_HERE");
+
+/// Marks the bye seat of an odd roster and an unreported fixture.
+pub const NONE: u8 = u8::MAX;
+pub const WIN_POINTS: u16 = 3;
+
+#[program]
+pub mod league {
+    use super::*;
+
+    /// Opens a league for a fixed roster and schedules every pairing up
+    /// front. Pairings come from a permutation seeded by the league address
+    /// rather than from the roster order, which only breaks ties that
+    /// nothing else does.
+    #[access_control(validate_config(&players, promotion_spots, relegation_spots))]
+    pub fn create_league(
+        ctx: Context<CreateLeague>,
+        league_id: u64,
+        players: Vec<Pubkey>,
+        promotion_spots: u8,
+        relegation_spots: u8,
+    ) -> Result<()> {
+        let league_key = ctx.accounts.league.key();
+        let seed = schedule_seed(&league_key);
+        let league = &mut ctx.accounts.league;
+
+        league.layout_version = League::LAYOUT_VERSION;
+        league.organizer = *ctx.accounts.organizer.key;
+        league.league_id = league_id;
+        league.status = LeagueStatus::InProgress;
+        league.promotion_spots = promotion_spots;
+        league.relegation_spots = relegation_spots;
+        league.seed = seed;
+        league.started_at = Clock::get()?.unix_timestamp;
+        league.fixtures = schedule(players.len(), seed);
+        league.reported = 0;
+        league.players = players;
+        league.bump = ctx.bumps.league;
+
+        let standings = &mut ctx.accounts.standings;
+        standings.layout_version = Standings::LAYOUT_VERSION;
+        standings.league = league_key;
+        standings.rows = league.players.iter().map(|&player| Standing::new(player)).collect();
+        standings.bump = ctx.bumps.standings;
+
+        emit!(LeagueCreated {
+            league: league_key,
+            organizer: league.organizer,
+            players: league.players.len() as u8,
+            fixtures: league.fixtures.len() as u8,
+            seed,
+        });
+
+        Ok(())
+    }
+
+    /// Scores a scheduled fixture from a finalized match between its two
+    /// players. The organizer picks the match, since the pair may have
+    /// played others. Reporting the last fixture ends the league.
+    #[access_control(validate_result(&ctx, fixture_index))]
+    pub fn record_result(ctx: Context<RecordResult>, fixture_index: u8) -> Result<()> {
+        let record = &ctx.accounts.match_account;
+        let league = &mut ctx.accounts.league;
+        let standings = &mut ctx.accounts.standings;
+        let mut fixture = league.fixtures[fixture_index as usize];
+
+        // Match players may be listed in either order.
+        let swapped = record.players[0] != league.players[fixture.players[0] as usize];
+        let score = if swapped {
+            [record.score[1], record.score[0]]
+        } else {
+            record.score
+        };
+        let winner = record.winner ^ swapped as u8;
+        fixture.winner = winner;
+        fixture.score = score;
+        fixture.match_id = record.match_id;
+
+        for (slot, &seed) in fixture.players.iter().enumerate() {
+            let row = &mut standings.rows[seed as usize];
+            increment(&mut row.played).map_err(LeagueError::from)?;
+            if slot == winner as usize {
+                increment(&mut row.wins).map_err(LeagueError::from)?;
+                add_assign(&mut row.points, WIN_POINTS).map_err(LeagueError::from)?;
+            } else {
+                increment(&mut row.losses).map_err(LeagueError::from)?;
+            }
+            add_assign(&mut row.games_won, score[slot] as u16).map_err(LeagueError::from)?;
+            add_assign(&mut row.games_lost, score[1 - slot] as u16).map_err(LeagueError::from)?;
+        }
+        league.fixtures[fixture_index as usize] = fixture;
+        increment(&mut league.reported).map_err(LeagueError::from)?;
+
+        emit!(LeagueResultRecorded {
+            league: league.key(),
+            fixture_index,
+            match_id: record.match_id,
+            winner: league.players[fixture.players[winner as usize] as usize],
+            loser: league.players[fixture.players[1 - winner as usize] as usize],
+            score,
+        });
+
+        if league.reported as usize == league.fixtures.len() {
+            let league_key = league.key();
+            finish(league_key, league, standings);
+        }
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<League>(League::discriminator()),
+            Layout::new::<Standings>(Standings::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<LeagueError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+/// Ranks the table and flags the top `promotion_spots` players for
+/// promotion and the bottom `relegation_spots` for relegation.
+fn finish(league_key: Pubkey, league: &mut League, standings: &mut Standings) {
+    let order = final_order(&standings.rows, &league.fixtures);
+    let count = order.len();
+    for (place, &seed) in order.iter().enumerate() {
+        let row = &mut standings.rows[seed as usize];
+        row.rank = place as u8 + 1;
+        row.promoted = place < league.promotion_spots as usize;
+        row.relegated = place >= count - league.relegation_spots as usize;
+    }
+    league.status = LeagueStatus::Complete;
+
+    let players = |flag: fn(&Standing) -> bool| -> Vec<Pubkey> {
+        order
+            .iter()
+            .map(|&seed| &standings.rows[seed as usize])
+            .filter(|row| flag(row))
+            .map(|row| row.player)
+            .collect()
+    };
+    emit!(LeagueCompleted {
+        league: league_key,
+        order: order.iter().map(|&seed| league.players[seed as usize]).collect(),
+        promoted: players(|row| row.promoted),
+        relegated: players(|row| row.relegated),
+    });
+}
+
+/// Seed of the pairing permutation: the first 8 bytes of
+/// `hashv(["league_seed", league])`.
+pub fn schedule_seed(league: &Pubkey) -> u64 {
+    let hash = hashv(&[b"league_seed", league.as_ref()]).to_bytes();
+    u64::from_le_bytes(hash[..8].try_into().unwrap())
+}
+
+/// Every pairing of a single round robin between `players` roster seeds,
+/// in round order. The seeds are shuffled with `game_core::Rng` and then
+/// paired by the circle method: the first seat stays put and the others
+/// rotate one place per round. An odd roster gets a bye seat, and whoever
+/// draws it sits the round out.
+pub fn schedule(players: usize, seed: u64) -> Vec<Fixture> {
+    let mut seats: Vec<u8> = (0..players as u8).collect();
+    let mut rng = Rng::new(seed);
+    for i in (1..seats.len()).rev() {
+        let j = rng.below(i as u32 + 1) as usize;
+        seats.swap(i, j);
+    }
+    if seats.len() % 2 == 1 {
+        seats.push(NONE);
+    }
+
+    let seat_count = seats.len();
+    let mut fixtures = Vec::with_capacity(players * (players - 1) / 2);
+    for round in 0..seat_count - 1 {
+        for i in 0..seat_count / 2 {
+            let pair = [seats[i], seats[seat_count - 1 - i]];
+            if pair.iter().all(|&seat| seat != NONE) {
+                fixtures.push(Fixture::new(round as u8, pair));
+            }
+        }
+        seats[1..].rotate_right(1);
+    }
+    fixtures
+}
+
+/// Roster seeds from first place to last. Players are ordered by points,
+/// then among players level on points by wins against each other, then
+/// by game differential, then by roster order.
+pub fn final_order(rows: &[Standing], fixtures: &[Fixture]) -> Vec<u8> {
+    let mut order: Vec<u8> = (0..rows.len() as u8).collect();
+    order.sort_by(|&a, &b| rows[b as usize].points.cmp(&rows[a as usize].points));
+
+    let mut start = 0;
+    while start < order.len() {
+        let points = rows[order[start] as usize].points;
+        let len = order[start..]
+            .iter()
+            .take_while(|&&seed| rows[seed as usize].points == points)
+            .count();
+        let tied = order[start..start + len].to_vec();
+        let head_to_head = |seed: u8| {
+            fixtures
+                .iter()
+                .filter(|fixture| {
+                    fixture.winner_seed() == Some(seed)
+                        && fixture.loser_seed().map_or(false, |loser| tied.contains(&loser))
+                })
+                .count()
+        };
+        order[start..start + len].sort_by(|&a, &b| {
+            head_to_head(b)
+                .cmp(&head_to_head(a))
+                .then(
+                    rows[b as usize]
+                        .game_differential()
+                        .cmp(&rows[a as usize].game_differential()),
+                )
+                .then(a.cmp(&b))
+        });
+        start += len;
+    }
+    order
+}
+
+fn validate_config(players: &[Pubkey], promotion_spots: u8, relegation_spots: u8) -> Result<()> {
+    require!(
+        players.len() >= 2 && players.len() <= League::MAX_PLAYERS,
+        LeagueError::InvalidPlayerCount
+    );
+    for (i, player) in players.iter().enumerate() {
+        require!(!players[..i].contains(player), LeagueError::DuplicatePlayer);
+    }
+    require_gte!(
+        players.len(),
+        promotion_spots as usize + relegation_spots as usize,
+        LeagueError::InvalidSpots
+    );
+    Ok(())
+}
+
+fn validate_result(ctx: &Context<RecordResult>, fixture_index: u8) -> Result<()> {
+    let league = &ctx.accounts.league;
+    let record = &ctx.accounts.match_account;
+    require!(league.status == LeagueStatus::InProgress, LeagueError::NotInProgress);
+    let fixture = league
+        .fixtures
+        .get(fixture_index as usize)
+        .ok_or(LeagueError::InvalidFixture)?;
+    require_eq!(fixture.winner, NONE, LeagueError::FixtureReported);
+    require!(record.finalized, LeagueError::MatchNotFinalized);
+    require_gte!(record.recorded_at, league.started_at, LeagueError::MatchBeforeLeague);
+    let [home, away] = fixture.players.map(|seed| league.players[seed as usize]);
+    require!(
+        record.players == [home, away] || record.players == [away, home],
+        LeagueError::PlayerMismatch
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(league_id: u64, players: Vec<Pubkey>)]
+pub struct CreateLeague<'info> {
+    #[account(mut)]
+    pub organizer: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"league", organizer.key.as_ref(), &league_id.to_le_bytes()],
+        bump,
+        space = League::SPACE,
+        payer = organizer,
+    )]
+    pub league: Account<'info, League>,
+    #[account(
+        init,
+        seeds = [b"standings", league.key().as_ref()],
+        bump,
+        space = Standings::SPACE,
+        payer = organizer,
+    )]
+    pub standings: Account<'info, Standings>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordResult<'info> {
+    pub organizer: Signer<'info>,
+    #[account(mut, has_one = organizer)]
+    pub league: Account<'info, League>,
+    #[account(
+        mut,
+        seeds = [b"standings", league.key().as_ref()],
+        bump = standings.bump,
+    )]
+    pub standings: Account<'info, Standings>,
+    #[account(
+        seeds = [b"match", &match_account.match_id.to_le_bytes()],
+        bump = match_account.bump,
+        seeds::program = match_result::ID,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct League {
+    pub layout_version: u8,
+    pub organizer: Pubkey,
+    pub league_id: u64,
+    pub status: LeagueStatus,
+    /// Fixed at creation; fixtures and standings refer to players by their
+    /// index here.
+    pub players: Vec<Pubkey>,
+    pub promotion_spots: u8,
+    pub relegation_spots: u8,
+    /// Seed of the pairing permutation, see `schedule_seed`.
+    pub seed: u64,
+    /// Matches recorded before this are not accepted.
+    pub started_at: i64,
+    pub fixtures: Vec<Fixture>,
+    /// Fixtures reported so far.
+    pub reported: u8,
+    pub bump: u8,
+}
+
+impl League {
+    pub const MAX_PLAYERS: usize = 16;
+    pub const MAX_FIXTURES: usize = Self::MAX_PLAYERS * (Self::MAX_PLAYERS - 1) / 2;
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // organizer
+        + 8 // league_id
+        + 1 // status
+        + 4 + 32 * Self::MAX_PLAYERS // players
+        + 1 // promotion_spots
+        + 1 // relegation_spots
+        + 8 // seed
+        + 8 // started_at
+        + 4 + Fixture::SIZE * Self::MAX_FIXTURES // fixtures
+        + 1 // reported
+        + 1; // bump
+}
+
+impl Versioned for League {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Fixture {
+    pub round: u8,
+    /// Roster seeds.
+    pub players: [u8; 2],
+    /// Winning slot, `NONE` until reported.
+    pub winner: u8,
+    /// Games won per slot.
+    pub score: [u8; 2],
+    /// The `match_result` match the fixture was scored from.
+    pub match_id: u64,
+}
+
+impl Fixture {
+    pub const SIZE: usize = 1 + 2 + 1 + 2 + 8;
+
+    pub fn new(round: u8, players: [u8; 2]) -> Self {
+        Fixture {
+            round,
+            players,
+            winner: NONE,
+            score: [0; 2],
+            match_id: 0,
+        }
+    }
+
+    pub fn winner_seed(&self) -> Option<u8> {
+        (self.winner != NONE).then(|| self.players[self.winner as usize])
+    }
+
+    pub fn loser_seed(&self) -> Option<u8> {
+        (self.winner != NONE).then(|| self.players[1 - self.winner as usize])
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeagueStatus {
+    InProgress,
+    Complete,
+}
+
+/// League table, one row per roster seed.
+#[account]
+pub struct Standings {
+    pub layout_version: u8,
+    pub league: Pubkey,
+    pub rows: Vec<Standing>,
+    pub bump: u8,
+}
+
+impl Standings {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // league
+        + 4 + Standing::SIZE * League::MAX_PLAYERS // rows
+        + 1; // bump
+}
+
+impl Versioned for Standings {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Standing {
+    pub player: Pubkey,
+    pub points: u16,
+    pub played: u8,
+    pub wins: u8,
+    pub losses: u8,
+    pub games_won: u16,
+    pub games_lost: u16,
+    /// Final place from 1, 0 while the league runs.
+    pub rank: u8,
+    pub promoted: bool,
+    pub relegated: bool,
+}
+
+impl Standing {
+    pub const SIZE: usize = 32 + 2 + 1 + 1 + 1 + 2 + 2 + 1 + 1 + 1;
+
+    pub fn new(player: Pubkey) -> Self {
+        Standing {
+            player,
+            points: 0,
+            played: 0,
+            wins: 0,
+            losses: 0,
+            games_won: 0,
+            games_lost: 0,
+            rank: 0,
+            promoted: false,
+            relegated: false,
+        }
+    }
+
+    pub fn game_differential(&self) -> i32 {
+        self.games_won as i32 - self.games_lost as i32
+    }
+}
+
+#[event]
+pub struct LeagueCreated {
+    pub league: Pubkey,
+    pub organizer: Pubkey,
+    pub players: u8,
+    pub fixtures: u8,
+    pub seed: u64,
+}
+
+#[event]
+pub struct LeagueResultRecorded {
+    pub league: Pubkey,
+    pub fixture_index: u8,
+    pub match_id: u64,
+    pub winner: Pubkey,
+    pub loser: Pubkey,
+    /// Games won per fixture slot.
+    pub score: [u8; 2],
+}
+
+#[event]
+pub struct LeagueCompleted {
+    pub league: Pubkey,
+    /// Players from first place to last.
+    pub order: Vec<Pubkey>,
+    pub promoted: Vec<Pubkey>,
+    pub relegated: Vec<Pubkey>,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum LeagueError {
+    #[msg("A league needs between 2 and 16 players")]
+    InvalidPlayerCount,
+    #[msg("Player is listed twice")]
+    DuplicatePlayer,
+    #[msg("Promotion and relegation spots exceed the roster")]
+    InvalidSpots,
+    #[msg("League is not in progress")]
+    NotInProgress,
+    #[msg("No such fixture")]
+    InvalidFixture,
+    #[msg("Fixture is already reported")]
+    FixtureReported,
+    #[msg("Match result is not finalized")]
+    MatchNotFinalized,
+    #[msg("Match was recorded before the league started")]
+    MatchBeforeLeague,
+    #[msg("Match players do not match the fixture")]
+    PlayerMismatch,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for LeagueError {
+    fn from(_: Overflow) -> Self {
+        LeagueError::Overflow
+    }
+}
+
+impl From<MigrationError> for LeagueError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => LeagueError::AlreadyMigrated,
+            _ => LeagueError::UnsupportedLayout,
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Attestation messages game and relay servers sign for `record_match` and
+//! `report_disconnect`, checked by `ed25519_attestation`.
+//!
+//! Messages start with a domain tag so a signature for one instruction can
+//! never be replayed as another.
+
+use anchor_lang::prelude::*;
+use ed25519_attestation::AttestationError;
+
+use crate::MatchError;
+
+pub const RECORD_MATCH_DOMAIN: &[u8] = b"match-result:record_match:v1";
+pub const REPORT_DISCONNECT_DOMAIN: &[u8] = b"match-result:report_disconnect:v1";
+
+/// Message a server signs to attest the result of `match_id`.
+pub fn record_match_message(
+    match_id: u64,
+    players: &[Pubkey; 2],
+    winner: u8,
+    score: &[u8; 2],
+    replay_hash: &[u8; 32],
+    frame_count: u32,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(RECORD_MATCH_DOMAIN.len() + 8 + 64 + 1 + 2 + 32 + 4);
+    message.extend_from_slice(RECORD_MATCH_DOMAIN);
+    message.extend_from_slice(&match_id.to_le_bytes());
+    message.extend_from_slice(players[0].as_ref());
+    message.extend_from_slice(players[1].as_ref());
+    message.push(winner);
+    message.extend_from_slice(score);
+    message.extend_from_slice(replay_hash);
+    message.extend_from_slice(&frame_count.to_le_bytes());
+    message
+}
+
+/// Message a relay server signs to attest that `players[quitter]` stopped
+/// sending inputs: the last frame both peers confirmed, its checksum, and
+/// how long the relay waited for the next input.
+pub fn report_disconnect_message(
+    match_id: u64,
+    players: &[Pubkey; 2],
+    quitter: u8,
+    last_frame: u32,
+    last_checksum: u64,
+    waited_ms: u64,
+) -> Vec<u8> {
+    let mut message = Vec::with_capacity(REPORT_DISCONNECT_DOMAIN.len() + 8 + 64 + 1 + 4 + 8 + 8);
+    message.extend_from_slice(REPORT_DISCONNECT_DOMAIN);
+    message.extend_from_slice(&match_id.to_le_bytes());
+    message.extend_from_slice(players[0].as_ref());
+    message.extend_from_slice(players[1].as_ref());
+    message.push(quitter);
+    message.extend_from_slice(&last_frame.to_le_bytes());
+    message.extend_from_slice(&last_checksum.to_le_bytes());
+    message.extend_from_slice(&waited_ms.to_le_bytes());
+    message
+}
+
+/// Returns the key that signed `message` in the ed25519 instruction right
+/// before the current one.
+pub fn verify_attestation(instructions: &AccountInfo, message: &[u8]) -> Result<Pubkey> {
+    ed25519_attestation::verify_attestation(instructions, message).map_err(|err| match err {
+        AttestationError::Sysvar(err) => err.into(),
+        AttestationError::Missing => MatchError::MissingAttestation.into(),
+        AttestationError::Invalid => MatchError::InvalidAttestation.into(),
+    })
+}
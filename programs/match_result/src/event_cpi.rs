@@ -0,0 +1,84 @@
+//! Events carried in self-CPI instruction data as well as in the log.
+//!
+//! Log events are truncated once a transaction's logs fill up, and
+//! indexers then miss them. With the `event-cpi` feature, `EventSink::emit`
+//! also invokes this program with the event as instruction data, signed by
+//! the `event_authority` PDA. Inner instructions are kept in full in the
+//! transaction metadata, so indexers can read every event from there. The
+//! layout matches Anchor's `emit_cpi!`: `EVENT_IX_TAG_LE` followed by the
+//! event's discriminator and data.
+//!
+//! The program's `fallback` accepts the self-CPI through `handle_event_ix`,
+//! which only lets it through when the event authority signed, so the
+//! instruction cannot be used to forge events from outside.
+
+use anchor_lang::prelude::*;
+use anchor_lang::Event;
+#[cfg(feature = "event-cpi")]
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+#[cfg(feature = "event-cpi")]
+use anchor_lang::solana_program::program::invoke_signed;
+
+pub const EVENT_AUTHORITY_SEED: &[u8] = b"__event_authority";
+/// First 8 bytes of `sha256("anchor:event")`, as used by `emit_cpi!`.
+pub const EVENT_IX_TAG: u64 = 0x1d9a_cb51_2ea5_45e4;
+pub const EVENT_IX_TAG_LE: [u8; 8] = EVENT_IX_TAG.to_le_bytes();
+
+/// The self-CPI accounts of one instruction's context.
+pub struct EventSink<'a, 'info> {
+    authority: &'a AccountInfo<'info>,
+    program: &'a AccountInfo<'info>,
+    bump: u8,
+}
+
+impl<'a, 'info> EventSink<'a, 'info> {
+    pub fn new(authority: &'a AccountInfo<'info>, program: &'a AccountInfo<'info>, bump: u8) -> Self {
+        EventSink {
+            authority,
+            program,
+            bump,
+        }
+    }
+
+    /// Logs `event` and, with the `event-cpi` feature, sends it through a
+    /// self-CPI.
+    pub fn emit<T: Event>(&self, event: &T) -> Result<()> {
+        let data = event.data();
+        anchor_lang::solana_program::log::sol_log_data(&[&data]);
+
+        #[cfg(feature = "event-cpi")]
+        {
+            let mut ix_data = Vec::with_capacity(EVENT_IX_TAG_LE.len() + data.len());
+            ix_data.extend_from_slice(&EVENT_IX_TAG_LE);
+            ix_data.extend_from_slice(&data);
+            let ix = Instruction {
+                program_id: crate::ID,
+                accounts: vec![AccountMeta::new_readonly(*self.authority.key, true)],
+                data: ix_data,
+            };
+            let signer_seeds: &[&[u8]] = &[EVENT_AUTHORITY_SEED, &[self.bump]];
+            invoke_signed(
+                &ix,
+                &[self.authority.clone(), self.program.clone()],
+                &[signer_seeds],
+            )?;
+        }
+        #[cfg(not(feature = "event-cpi"))]
+        let _ = (self.authority, self.program, self.bump);
+
+        Ok(())
+    }
+}
+
+/// Accepts an event self-CPI and rejects any other unknown instruction.
+pub fn handle_event_ix(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> Result<()> {
+    if !data.starts_with(&EVENT_IX_TAG_LE) {
+        return err!(ErrorCode::InstructionFallbackNotFound);
+    }
+    let (event_authority, _) = Pubkey::find_program_address(&[EVENT_AUTHORITY_SEED], program_id);
+    let signed = accounts
+        .first()
+        .map_or(false, |info| info.is_signer && *info.key == event_authority);
+    require!(signed, ErrorCode::ConstraintSigner);
+    Ok(())
+}
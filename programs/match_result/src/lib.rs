@@ -0,0 +1,1402 @@
+// Rust Anchor Program for Match Results
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::Discriminator;
+use anchor_lang::system_program;
+
+pub mod attestation;
+mod event_cpi;
+
+use attestation::{record_match_message, report_disconnect_message, verify_attestation};
+use event_cpi::{EventSink, EVENT_AUTHORITY_SEED};
+
+declare_id!("MATCH_RESULT_PROGRAM_ID_HERE");
+
+/// `MatchAccount.format` of a one-on-one match.
+pub const FORMAT_SINGLES: u8 = 0;
+/// `MatchAccount.format` of a king-of-the-hill team battle, played out by
+/// `game_core::team`.
+pub const FORMAT_TEAM: u8 = 1;
+pub const TEAM_SIZE: usize = game_core::team::TEAM_SIZE;
+/// Longest set `open_set` accepts, best of five.
+pub const MAX_SET_GAMES: usize = 5;
+/// Most players a crew battle team can field.
+pub const MAX_CREW_SIZE: usize = 5;
+/// Most stocks each crew member can bring.
+pub const MAX_CREW_STOCKS: u8 = 4;
+/// Every game eliminates one player, so the last game is played once all
+/// but one of all the players are out.
+pub const MAX_CREW_GAMES: usize = 2 * MAX_CREW_SIZE - 1;
+/// Seconds without a new game before an unfinished set or crew battle can
+/// be voided by `finalize_set_if_expired` or
+/// `finalize_crew_battle_if_expired`.
+pub const STALE_TIMEOUT: i64 = 24 * 60 * 60;
+/// Lamports deposited when a set or crew battle opens, returned when it
+/// finishes and paid to whoever finalizes it once it goes stale.
+pub const CRANK_REWARD: u64 = 5_000;
+/// Shortest input gap a relay can attest as a disconnect, matching
+/// `game_core::lockstep`'s default timeout.
+pub const MIN_DISCONNECT_WAIT_MS: u64 = 5_000;
+/// Seconds without a disconnect after which a player's count starts over.
+pub const DISCONNECT_DECAY: i64 = 30 * 24 * 60 * 60;
+
+/// The ranking program, whose `rater` PDA is the only signer allowed to mark a
+/// match as rated.
+pub mod ranking_program {
+    anchor_lang::declare_id!("RANKING_PROGRAM_ID_HERE");
+}
+
+/// The `EventSink` for a context carrying the self-CPI accounts.
+macro_rules! event_sink {
+    ($ctx:expr) => {
+        EventSink::new(
+            &$ctx.accounts.event_authority,
+            &$ctx.accounts.program,
+            $ctx.bumps.event_authority,
+        )
+    };
+}
+
+#[program]
+pub mod match_result {
+    use super::*;
+
+    /// Both players sign the same result; either one missing fails the
+    /// transaction, so a recorded match is agreed on by construction.
+    /// `checksum_root` is the replay's `replay::Replay::checksum_root`,
+    /// which `dispute` checks key-frame windows against; zero for replays
+    /// without checksums.
+    #[access_control(validate_result(&ctx, winner, &score, frame_count))]
+    pub fn record_match(
+        ctx: Context<RecordMatch>,
+        match_id: u64,
+        winner: u8,
+        score: [u8; 2],
+        replay_hash: [u8; 32],
+        frame_count: u32,
+        checksum_root: [u8; 32],
+    ) -> Result<()> {
+        let players = [*ctx.accounts.player_one.key, *ctx.accounts.player_two.key];
+        write_result(
+            &event_sink!(ctx),
+            &mut ctx.accounts.match_account,
+            ctx.bumps.match_account,
+            match_id,
+            players,
+            FORMAT_SINGLES,
+            [[0; TEAM_SIZE]; 2],
+            winner,
+            score,
+            replay_hash,
+            frame_count,
+            checksum_root,
+        )?;
+        Ok(())
+    }
+
+    /// Records a team battle co-signed like `record_match`. `rosters` lists
+    /// each player's characters in fighting order and `score` the opposing
+    /// characters each player knocked out, so the winner's is `TEAM_SIZE`.
+    #[access_control(validate_team_result(&ctx, winner, &score, frame_count))]
+    pub fn record_team_match(
+        ctx: Context<RecordMatch>,
+        match_id: u64,
+        winner: u8,
+        rosters: [[u8; TEAM_SIZE]; 2],
+        score: [u8; 2],
+        replay_hash: [u8; 32],
+        frame_count: u32,
+        checksum_root: [u8; 32],
+    ) -> Result<()> {
+        let players = [*ctx.accounts.player_one.key, *ctx.accounts.player_two.key];
+        write_result(
+            &event_sink!(ctx),
+            &mut ctx.accounts.match_account,
+            ctx.bumps.match_account,
+            match_id,
+            players,
+            FORMAT_TEAM,
+            rosters,
+            winner,
+            score,
+            replay_hash,
+            frame_count,
+            checksum_root,
+        )?;
+        Ok(())
+    }
+
+    /// Opens a best-of-`best_of` set between the two signers. Games are
+    /// added one at a time by `record_set_game`, and the set is only
+    /// finalized, and so only reaches the ranking program, once a player
+    /// has won a majority of `best_of`. Player one deposits `CRANK_REWARD`
+    /// alongside the rent.
+    #[access_control(validate_set(&ctx, best_of))]
+    pub fn open_set(ctx: Context<RecordMatch>, match_id: u64, best_of: u8) -> Result<()> {
+        deposit_bounty(
+            &ctx.accounts.system_program,
+            &ctx.accounts.player_one,
+            &ctx.accounts.match_account.to_account_info(),
+        )?;
+        let players = [*ctx.accounts.player_one.key, *ctx.accounts.player_two.key];
+        let record = &mut ctx.accounts.match_account;
+        record.layout_version = MatchAccount::LAYOUT_VERSION;
+        record.match_id = match_id;
+        record.players = players;
+        record.winner = 0;
+        record.score = [0; 2];
+        record.replay_hash = [0; 32];
+        record.frame_count = 0;
+        record.checksum_root = [0; 32];
+        record.recorded_at = Clock::get()?.unix_timestamp;
+        record.finalized = false;
+        record.rating_applied = false;
+        record.bump = ctx.bumps.match_account;
+        record.format = FORMAT_SINGLES;
+        record.rosters = [[0; TEAM_SIZE]; 2];
+        record.best_of = best_of;
+        record.game_count = 0;
+        record.games = [SetGame::default(); MAX_SET_GAMES];
+
+        event_sink!(ctx).emit(&SetOpened {
+            match_account: record.key(),
+            match_id,
+            players,
+            best_of,
+        })
+    }
+
+    /// Adds the next game of an open set, co-signed by both players.
+    /// `characters` are what each player picked for this game, so switches
+    /// between games are on record. The game that wins the set finalizes
+    /// it with `replay_hash` committing to every game's hash in order.
+    pub fn record_set_game(
+        ctx: Context<RecordSetGame>,
+        winner: u8,
+        characters: [u8; 2],
+        replay_hash: [u8; 32],
+        frame_count: u32,
+    ) -> Result<()> {
+        require_gt!(2, winner, MatchError::InvalidWinner);
+        require_gt!(frame_count, 0, MatchError::EmptyMatch);
+        let events = event_sink!(ctx);
+        let record = &mut ctx.accounts.match_account;
+        require!(!record.finalized, MatchError::SetFinished);
+        require_gt!(record.best_of, record.game_count, MatchError::SetFinished);
+
+        let game = record.game_count;
+        record.games[game as usize] = SetGame {
+            winner,
+            characters,
+            replay_hash,
+            frame_count,
+        };
+        record.game_count += 1;
+        record.score[winner as usize] += 1;
+        record.frame_count = record.frame_count.saturating_add(frame_count);
+        record.recorded_at = Clock::get()?.unix_timestamp;
+        events.emit(&SetGameRecorded {
+            match_account: record.key(),
+            game,
+            winner: record.players[winner as usize],
+            characters,
+            replay_hash,
+        })?;
+
+        if record.score[winner as usize] < record.best_of / 2 + 1 {
+            return Ok(());
+        }
+        pay_bounty(
+            &ctx.accounts.match_account.to_account_info(),
+            &ctx.accounts.player_one,
+        )?;
+        finish_set(&events, &mut ctx.accounts.match_account, winner)
+    }
+
+    /// Voids a set nobody has added a game to for `STALE_TIMEOUT`, so a
+    /// player going offline can't hold it open. Nobody wins it, not even
+    /// the player ahead: a player could leave as soon as they lead and the
+    /// set would be theirs. Its account is closed, returning the rent to
+    /// player one, so the players can open it again. Takes no signature, so it can run from any
+    /// crank or a Clockwork thread triggered at the timeout; `caller`
+    /// collects the `CRANK_REWARD` deposit.
+    #[access_control(validate_stale(
+        ctx.accounts.match_account.finalized,
+        ctx.accounts.match_account.recorded_at,
+    ))]
+    pub fn finalize_set_if_expired(ctx: Context<FinalizeExpiredSet>) -> Result<()> {
+        let events = event_sink!(ctx);
+        let info = ctx.accounts.match_account.to_account_info();
+        pay_bounty(&info, &ctx.accounts.caller)?;
+
+        events.emit(&StaleMatchClosed {
+            account: info.key(),
+            match_id: ctx.accounts.match_account.match_id,
+        })?;
+        ctx.accounts.match_account.close(ctx.accounts.player_one.to_account_info())
+    }
+
+    /// Opens a crew battle between two teams of `crew_size` players, each
+    /// bringing `stocks` stocks to its team's pool. Captains co-sign the
+    /// opening and every game, like the players of a set, and captain one
+    /// deposits `CRANK_REWARD` the same way.
+    #[access_control(validate_crew_battle(&ctx, &crews, crew_size, stocks))]
+    pub fn open_crew_battle(
+        ctx: Context<OpenCrewBattle>,
+        match_id: u64,
+        crews: [[Pubkey; MAX_CREW_SIZE]; 2],
+        crew_size: u8,
+        stocks: u8,
+    ) -> Result<()> {
+        deposit_bounty(
+            &ctx.accounts.system_program,
+            &ctx.accounts.captain_one,
+            &ctx.accounts.crew_battle.to_account_info(),
+        )?;
+        let captains = [*ctx.accounts.captain_one.key, *ctx.accounts.captain_two.key];
+        let battle = &mut ctx.accounts.crew_battle;
+        battle.layout_version = CrewBattle::LAYOUT_VERSION;
+        battle.match_id = match_id;
+        battle.captains = captains;
+        battle.crews = crews;
+        battle.crew_size = crew_size;
+        battle.stocks_per_player = stocks;
+        battle.pool = [crew_size as u16 * stocks as u16; 2];
+        battle.active = [0; 2];
+        battle.active_stocks = [stocks; 2];
+        battle.game_count = 0;
+        battle.games = [CrewGame::default(); MAX_CREW_GAMES];
+        battle.winner = 0;
+        battle.recorded_at = Clock::get()?.unix_timestamp;
+        battle.finalized = false;
+        battle.bump = ctx.bumps.crew_battle;
+
+        event_sink!(ctx).emit(&CrewBattleOpened {
+            crew_battle: battle.key(),
+            match_id,
+            captains,
+            crew_size,
+            stocks,
+        })
+    }
+
+    /// Records the next game between the two crews' active players. The
+    /// loser is out with all of their stocks; the winner stays in with
+    /// `stocks_left`, and every stock either side lost comes out of its
+    /// crew's pool. The crew battle is finalized once a pool runs out.
+    pub fn record_crew_game(
+        ctx: Context<RecordCrewGame>,
+        winner: u8,
+        stocks_left: u8,
+        replay_hash: [u8; 32],
+        frame_count: u32,
+    ) -> Result<()> {
+        require_gt!(2, winner, MatchError::InvalidWinner);
+        require_gt!(frame_count, 0, MatchError::EmptyMatch);
+        let events = event_sink!(ctx);
+        let battle = &mut ctx.accounts.crew_battle;
+        require!(!battle.finalized, MatchError::CrewBattleFinished);
+        let (side, other) = (winner as usize, 1 - winner as usize);
+        require!(
+            stocks_left > 0 && stocks_left <= battle.active_stocks[side],
+            MatchError::InvalidStocks
+        );
+
+        let game = battle.game_count;
+        battle.games[game as usize] = CrewGame {
+            players: battle.active,
+            winner,
+            stocks_left,
+            replay_hash,
+            frame_count,
+        };
+        battle.game_count += 1;
+        battle.pool[side] -= (battle.active_stocks[side] - stocks_left) as u16;
+        battle.pool[other] -= battle.active_stocks[other] as u16;
+        battle.active_stocks[side] = stocks_left;
+        battle.active_stocks[other] = battle.stocks_per_player;
+        battle.active[other] += 1;
+        battle.recorded_at = Clock::get()?.unix_timestamp;
+        events.emit(&CrewGameRecorded {
+            crew_battle: battle.key(),
+            game,
+            winner: battle.crews[side][battle.games[game as usize].players[side] as usize],
+            loser: battle.crews[other][battle.games[game as usize].players[other] as usize],
+            stocks_left,
+            pool: battle.pool,
+            replay_hash,
+        })?;
+
+        if battle.pool[other] > 0 {
+            return Ok(());
+        }
+        pay_bounty(
+            &ctx.accounts.crew_battle.to_account_info(),
+            &ctx.accounts.captain_one,
+        )?;
+        finish_crew_battle(&events, &mut ctx.accounts.crew_battle, winner)
+    }
+
+    /// Voids a crew battle gone stale like `finalize_set_if_expired`,
+    /// whichever crew has more stocks left, closing it and returning the
+    /// rent to captain one.
+    #[access_control(validate_stale(
+        ctx.accounts.crew_battle.finalized,
+        ctx.accounts.crew_battle.recorded_at,
+    ))]
+    pub fn finalize_crew_battle_if_expired(ctx: Context<FinalizeExpiredCrewBattle>) -> Result<()> {
+        let events = event_sink!(ctx);
+        let info = ctx.accounts.crew_battle.to_account_info();
+        pay_bounty(&info, &ctx.accounts.caller)?;
+
+        events.emit(&StaleMatchClosed {
+            account: info.key(),
+            match_id: ctx.accounts.crew_battle.match_id,
+        })?;
+        ctx.accounts.crew_battle.close(ctx.accounts.captain_one.to_account_info())
+    }
+
+    pub fn initialize_attestation_config(
+        ctx: Context<InitializeAttestationConfig>,
+        servers: Vec<Pubkey>,
+    ) -> Result<()> {
+        require_gte!(AttestationConfig::MAX_SERVERS, servers.len(), MatchError::TooManyServers);
+        let config = &mut ctx.accounts.attestation_config;
+        config.layout_version = AttestationConfig::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.servers = servers;
+        config.bump = ctx.bumps.attestation_config;
+        Ok(())
+    }
+
+    pub fn set_attestation_servers(
+        ctx: Context<SetAttestationServers>,
+        servers: Vec<Pubkey>,
+    ) -> Result<()> {
+        require_gte!(AttestationConfig::MAX_SERVERS, servers.len(), MatchError::TooManyServers);
+        ctx.accounts.attestation_config.servers = servers;
+        Ok(())
+    }
+
+    /// Records a result signed off-chain by an approved game server instead
+    /// of by both players. The signature is checked by an ed25519 program
+    /// instruction placed right before this one, so anyone can submit the
+    /// result and pay for the account. Only singles results are attested.
+    #[access_control(check_outcome(&players, winner, &score, frame_count))]
+    pub fn record_match_attested(
+        ctx: Context<RecordMatchAttested>,
+        match_id: u64,
+        players: [Pubkey; 2],
+        winner: u8,
+        score: [u8; 2],
+        replay_hash: [u8; 32],
+        frame_count: u32,
+    ) -> Result<()> {
+        let message =
+            record_match_message(match_id, &players, winner, &score, &replay_hash, frame_count);
+        let server = verify_attestation(&ctx.accounts.instructions, &message)?;
+        require!(
+            ctx.accounts.attestation_config.servers.contains(&server),
+            MatchError::UnauthorizedAttester
+        );
+        write_result(
+            &event_sink!(ctx),
+            &mut ctx.accounts.match_account,
+            ctx.bumps.match_account,
+            match_id,
+            players,
+            FORMAT_SINGLES,
+            [[0; TEAM_SIZE]; 2],
+            winner,
+            score,
+            replay_hash,
+            frame_count,
+            [0; 32],
+        )?;
+        Ok(())
+    }
+
+    /// Records a loss for a player who quit mid-match. The remaining player
+    /// submits the relay server's attestation of the disconnect, checked
+    /// like `record_match_attested`, and the quitter's disconnect count
+    /// goes up, keeping them out of ranked for longer each time; see
+    /// `disconnect_restriction`. The match has no replay, so its
+    /// `replay_hash` is zero.
+    #[access_control(validate_disconnect(&ctx, &players, quitter, last_frame, waited_ms))]
+    pub fn report_disconnect(
+        ctx: Context<ReportDisconnect>,
+        match_id: u64,
+        players: [Pubkey; 2],
+        quitter: u8,
+        last_frame: u32,
+        last_checksum: u64,
+        waited_ms: u64,
+    ) -> Result<()> {
+        let message = report_disconnect_message(
+            match_id,
+            &players,
+            quitter,
+            last_frame,
+            last_checksum,
+            waited_ms,
+        );
+        let server = verify_attestation(&ctx.accounts.instructions, &message)?;
+        require!(
+            ctx.accounts.attestation_config.servers.contains(&server),
+            MatchError::UnauthorizedAttester
+        );
+        let winner = 1 - quitter;
+        let mut score = [0; 2];
+        score[winner as usize] = 1;
+        write_result(
+            &event_sink!(ctx),
+            &mut ctx.accounts.match_account,
+            ctx.bumps.match_account,
+            match_id,
+            players,
+            FORMAT_SINGLES,
+            [[0; TEAM_SIZE]; 2],
+            winner,
+            score,
+            [0; 32],
+            last_frame,
+            [0; 32],
+        )?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let record = &mut ctx.accounts.disconnects;
+        if record.layout_version == 0 {
+            record.layout_version = DisconnectRecord::LAYOUT_VERSION;
+            record.player = players[quitter as usize];
+            record.bump = ctx.bumps.disconnects;
+        }
+        if now - record.last_at > DISCONNECT_DECAY {
+            record.count = 0;
+        }
+        record.count = record.count.saturating_add(1);
+        record.last_at = now;
+
+        event_sink!(ctx).emit(&DisconnectReported {
+            match_id,
+            quitter: record.player,
+            reporter: *ctx.accounts.reporter.key,
+            last_frame,
+            last_checksum,
+            disconnect_count: record.count,
+            restricted_until: record.restricted_until(),
+        })
+    }
+
+    /// Called by the ranking program once both ratings have been updated, so
+    /// the same result can never be applied twice.
+    pub fn mark_rated(ctx: Context<MarkRated>) -> Result<()> {
+        let record = &mut ctx.accounts.match_account;
+        require!(record.finalized, MatchError::NotFinalized);
+        require!(!record.rating_applied, MatchError::AlreadyRated);
+        record.rating_applied = true;
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<MatchAccount>(MatchAccount::discriminator()),
+            Layout::new::<AttestationConfig>(AttestationConfig::discriminator()),
+            Layout::new::<CrewBattle>(CrewBattle::discriminator()),
+            Layout::new::<DisconnectRecord>(DisconnectRecord::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<MatchError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+
+    /// Receives the event self-CPIs sent by `EventSink::emit`.
+    pub fn fallback<'info>(
+        program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> Result<()> {
+        event_cpi::handle_event_ix(program_id, accounts, data)
+    }
+}
+
+fn finish_set(events: &EventSink, record: &mut Account<MatchAccount>, winner: u8) -> Result<()> {
+    record.winner = winner;
+    record.replay_hash = record.set_replay_hash();
+    record.recorded_at = Clock::get()?.unix_timestamp;
+    record.finalized = true;
+    events.emit(&MatchRecorded {
+        match_account: record.key(),
+        match_id: record.match_id,
+        players: record.players,
+        format: record.format,
+        rosters: record.rosters,
+        winner: record.winner_key(),
+        score: record.score,
+        replay_hash: record.replay_hash,
+        frame_count: record.frame_count,
+        timestamp: record.recorded_at,
+    })
+}
+
+fn finish_crew_battle(
+    events: &EventSink,
+    battle: &mut Account<CrewBattle>,
+    winner: u8,
+) -> Result<()> {
+    battle.winner = winner;
+    battle.recorded_at = Clock::get()?.unix_timestamp;
+    battle.finalized = true;
+    events.emit(&CrewBattleFinished {
+        crew_battle: battle.key(),
+        match_id: battle.match_id,
+        winner: battle.captains[winner as usize],
+        stocks_left: battle.pool[winner as usize],
+        games: battle.game_count,
+        timestamp: battle.recorded_at,
+    })
+}
+
+fn deposit_bounty<'info>(
+    system_program: &Program<'info, System>,
+    from: &Signer<'info>,
+    to: &AccountInfo<'info>,
+) -> Result<()> {
+    system_program::transfer(
+        CpiContext::new(
+            system_program.to_account_info(),
+            system_program::Transfer {
+                from: from.to_account_info(),
+                to: to.clone(),
+            },
+        ),
+        CRANK_REWARD,
+    )
+}
+
+/// Moves whatever `from` holds beyond its rent-exempt minimum, the
+/// `CRANK_REWARD` deposit, to `to`. Accounts opened before the deposit
+/// existed have nothing to move.
+fn pay_bounty(from: &AccountInfo, to: &AccountInfo) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(from.data_len());
+    let bounty = from.lamports().saturating_sub(rent);
+    **from.try_borrow_mut_lamports()? -= bounty;
+    **to.try_borrow_mut_lamports()? += bounty;
+    Ok(())
+}
+
+fn validate_stale(finalized: bool, last_game_at: i64) -> Result<()> {
+    require!(!finalized, MatchError::AlreadyFinalized);
+    require_gte!(
+        Clock::get()?.unix_timestamp,
+        last_game_at + STALE_TIMEOUT,
+        MatchError::NotStale
+    );
+    Ok(())
+}
+
+fn write_result(
+    events: &EventSink,
+    record: &mut Account<MatchAccount>,
+    bump: u8,
+    match_id: u64,
+    players: [Pubkey; 2],
+    format: u8,
+    rosters: [[u8; TEAM_SIZE]; 2],
+    winner: u8,
+    score: [u8; 2],
+    replay_hash: [u8; 32],
+    frame_count: u32,
+    checksum_root: [u8; 32],
+) -> Result<()> {
+    record.layout_version = MatchAccount::LAYOUT_VERSION;
+    record.match_id = match_id;
+    record.players = players;
+    record.format = format;
+    record.rosters = rosters;
+    record.best_of = 1;
+    record.game_count = 0;
+    record.games = [SetGame::default(); MAX_SET_GAMES];
+    record.winner = winner;
+    record.score = score;
+    record.replay_hash = replay_hash;
+    record.frame_count = frame_count;
+    record.checksum_root = checksum_root;
+    record.recorded_at = Clock::get()?.unix_timestamp;
+    record.finalized = true;
+    record.rating_applied = false;
+    record.bump = bump;
+
+    events.emit(&MatchRecorded {
+        match_account: record.key(),
+        match_id,
+        players,
+        format,
+        rosters,
+        winner: players[winner as usize],
+        score,
+        replay_hash,
+        frame_count,
+        timestamp: record.recorded_at,
+    })
+}
+
+fn validate_result(
+    ctx: &Context<RecordMatch>,
+    winner: u8,
+    score: &[u8; 2],
+    frame_count: u32,
+) -> Result<()> {
+    let players = [*ctx.accounts.player_one.key, *ctx.accounts.player_two.key];
+    check_outcome(&players, winner, score, frame_count)
+}
+
+fn validate_team_result(
+    ctx: &Context<RecordMatch>,
+    winner: u8,
+    score: &[u8; 2],
+    frame_count: u32,
+) -> Result<()> {
+    validate_result(ctx, winner, score, frame_count)?;
+    require_eq!(score[winner as usize] as usize, TEAM_SIZE, MatchError::ScoreMismatch);
+    Ok(())
+}
+
+/// Sets are an odd number of games, so one player always wins a majority.
+fn validate_set(ctx: &Context<RecordMatch>, best_of: u8) -> Result<()> {
+    require_keys_neq!(
+        *ctx.accounts.player_one.key,
+        *ctx.accounts.player_two.key,
+        MatchError::InvalidPlayers
+    );
+    require!(
+        best_of % 2 == 1 && best_of > 1 && best_of as usize <= MAX_SET_GAMES,
+        MatchError::InvalidSetLength
+    );
+    Ok(())
+}
+
+/// Crew members must be distinct across both crews, and the slots past
+/// `crew_size` empty.
+fn validate_crew_battle(
+    ctx: &Context<OpenCrewBattle>,
+    crews: &[[Pubkey; MAX_CREW_SIZE]; 2],
+    crew_size: u8,
+    stocks: u8,
+) -> Result<()> {
+    require_keys_neq!(
+        *ctx.accounts.captain_one.key,
+        *ctx.accounts.captain_two.key,
+        MatchError::InvalidPlayers
+    );
+    require!(
+        crew_size > 0 && crew_size as usize <= MAX_CREW_SIZE,
+        MatchError::InvalidCrew
+    );
+    require!(stocks > 0 && stocks <= MAX_CREW_STOCKS, MatchError::InvalidStocks);
+    let size = crew_size as usize;
+    let members: Vec<&Pubkey> = crews.iter().flat_map(|crew| &crew[..size]).collect();
+    for (i, member) in members.iter().enumerate() {
+        require!(
+            **member != Pubkey::default() && !members[..i].contains(member),
+            MatchError::InvalidCrew
+        );
+    }
+    require!(
+        crews.iter().all(|crew| crew[size..].iter().all(|slot| *slot == Pubkey::default())),
+        MatchError::InvalidCrew
+    );
+    Ok(())
+}
+
+/// The reporter is the player who stayed, and the relay waited at least
+/// `MIN_DISCONNECT_WAIT_MS` for the quitter.
+fn validate_disconnect(
+    ctx: &Context<ReportDisconnect>,
+    players: &[Pubkey; 2],
+    quitter: u8,
+    last_frame: u32,
+    waited_ms: u64,
+) -> Result<()> {
+    require_keys_neq!(players[0], players[1], MatchError::InvalidPlayers);
+    require_gt!(2, quitter, MatchError::InvalidWinner);
+    require_keys_eq!(
+        *ctx.accounts.reporter.key,
+        players[1 - quitter as usize],
+        MatchError::NotRemainingPlayer
+    );
+    require_gt!(last_frame, 0, MatchError::EmptyMatch);
+    require_gte!(waited_ms, MIN_DISCONNECT_WAIT_MS, MatchError::DisconnectTooShort);
+    Ok(())
+}
+
+/// Seconds after their latest disconnect that a player with `count` recent
+/// disconnects is kept out of ranked matchmaking. A first disconnect is
+/// let go.
+pub fn disconnect_restriction(count: u32) -> i64 {
+    match count {
+        0 | 1 => 0,
+        2 => 10 * 60,
+        3 => 60 * 60,
+        4 => 24 * 60 * 60,
+        _ => 7 * 24 * 60 * 60,
+    }
+}
+
+fn check_outcome(
+    players: &[Pubkey; 2],
+    winner: u8,
+    score: &[u8; 2],
+    frame_count: u32,
+) -> Result<()> {
+    require_keys_neq!(players[0], players[1], MatchError::InvalidPlayers);
+    require_gt!(2, winner, MatchError::InvalidWinner);
+    require_gt!(score[winner as usize], score[1 - winner as usize], MatchError::ScoreMismatch);
+    require_gt!(frame_count, 0, MatchError::EmptyMatch);
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(match_id: u64)]
+pub struct RecordMatch<'info> {
+    #[account(mut)]
+    pub player_one: Signer<'info>,
+    pub player_two: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"match".as_ref(), &match_id.to_le_bytes()],
+        bump,
+        space = MatchAccount::SPACE,
+        payer = player_one,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::MatchResult>,
+}
+
+#[derive(Accounts)]
+pub struct RecordSetGame<'info> {
+    /// Gets the `CRANK_REWARD` deposit back when the set finishes
+    #[account(mut)]
+    pub player_one: Signer<'info>,
+    pub player_two: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"match", &match_account.match_id.to_le_bytes()],
+        bump = match_account.bump,
+        constraint = match_account.players == [player_one.key(), player_two.key()]
+            @ MatchError::InvalidPlayers,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::MatchResult>,
+}
+
+#[derive(Accounts)]
+#[instruction(match_id: u64)]
+pub struct OpenCrewBattle<'info> {
+    #[account(mut)]
+    pub captain_one: Signer<'info>,
+    pub captain_two: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"crew_battle".as_ref(), &match_id.to_le_bytes()],
+        bump,
+        space = CrewBattle::SPACE,
+        payer = captain_one,
+    )]
+    pub crew_battle: Account<'info, CrewBattle>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::MatchResult>,
+}
+
+#[derive(Accounts)]
+pub struct RecordCrewGame<'info> {
+    /// Gets the `CRANK_REWARD` deposit back when the crew battle finishes
+    #[account(mut)]
+    pub captain_one: Signer<'info>,
+    pub captain_two: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"crew_battle", &crew_battle.match_id.to_le_bytes()],
+        bump = crew_battle.bump,
+        constraint = crew_battle.captains == [captain_one.key(), captain_two.key()]
+            @ MatchError::InvalidPlayers,
+    )]
+    pub crew_battle: Account<'info, CrewBattle>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::MatchResult>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeExpiredSet<'info> {
+    /// Collects the `CRANK_REWARD` deposit
+    #[account(mut)]
+    pub caller: SystemAccount<'info>,
+    /// Receives the rent of a void set
+    #[account(mut, address = match_account.players[0])]
+    pub player_one: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"match", &match_account.match_id.to_le_bytes()],
+        bump = match_account.bump,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::MatchResult>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeExpiredCrewBattle<'info> {
+    /// Collects the `CRANK_REWARD` deposit
+    #[account(mut)]
+    pub caller: SystemAccount<'info>,
+    /// Receives the rent of a void crew battle
+    #[account(mut, address = crew_battle.captains[0])]
+    pub captain_one: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"crew_battle", &crew_battle.match_id.to_le_bytes()],
+        bump = crew_battle.bump,
+    )]
+    pub crew_battle: Account<'info, CrewBattle>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::MatchResult>,
+}
+
+#[derive(Accounts)]
+#[instruction(match_id: u64)]
+pub struct RecordMatchAttested<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"attestation_config"], bump = attestation_config.bump)]
+    pub attestation_config: Account<'info, AttestationConfig>,
+    #[account(
+        init,
+        seeds = [b"match".as_ref(), &match_id.to_le_bytes()],
+        bump,
+        space = MatchAccount::SPACE,
+        payer = payer,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    /// CHECK: the instructions sysvar, verified by address
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::MatchResult>,
+}
+
+#[derive(Accounts)]
+#[instruction(match_id: u64, players: [Pubkey; 2], quitter: u8)]
+pub struct ReportDisconnect<'info> {
+    #[account(mut)]
+    pub reporter: Signer<'info>,
+    #[account(seeds = [b"attestation_config"], bump = attestation_config.bump)]
+    pub attestation_config: Account<'info, AttestationConfig>,
+    #[account(
+        init,
+        seeds = [b"match".as_ref(), &match_id.to_le_bytes()],
+        bump,
+        space = MatchAccount::SPACE,
+        payer = reporter,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    #[account(
+        init_if_needed,
+        seeds = [b"disconnects", players[quitter as usize % 2].as_ref()],
+        bump,
+        space = DisconnectRecord::SPACE,
+        payer = reporter,
+    )]
+    pub disconnects: Account<'info, DisconnectRecord>,
+    /// CHECK: the instructions sysvar, verified by address
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    /// CHECK: signs the self-CPI that carries events, verified by seeds
+    #[account(seeds = [EVENT_AUTHORITY_SEED], bump)]
+    pub event_authority: UncheckedAccount<'info>,
+    pub program: Program<'info, crate::program::MatchResult>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeAttestationConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"attestation_config"],
+        bump,
+        space = AttestationConfig::SPACE,
+        payer = admin,
+    )]
+    pub attestation_config: Account<'info, AttestationConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetAttestationServers<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"attestation_config"],
+        bump = attestation_config.bump,
+        has_one = admin,
+    )]
+    pub attestation_config: Account<'info, AttestationConfig>,
+}
+
+#[derive(Accounts)]
+pub struct MarkRated<'info> {
+    /// PDA of the ranking program, verified by seeds
+    #[account(
+        seeds = [b"rater"],
+        bump,
+        seeds::program = ranking_program::ID,
+    )]
+    pub rater: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"match", &match_account.match_id.to_le_bytes()],
+        bump = match_account.bump,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct MatchAccount {
+    pub layout_version: u8,
+    pub match_id: u64,
+    pub players: [Pubkey; 2],
+    /// Index into `players`.
+    pub winner: u8,
+    pub score: [u8; 2],
+    pub replay_hash: [u8; 32],
+    pub frame_count: u32,
+    /// When the result was recorded; while a set runs, when it opened or
+    /// last had a game added.
+    pub recorded_at: i64,
+    pub finalized: bool,
+    /// Set once the ranking program has applied this result.
+    pub rating_applied: bool,
+    pub bump: u8,
+    /// `FORMAT_SINGLES` or `FORMAT_TEAM`.
+    pub format: u8,
+    /// Each player's characters in fighting order; zero for singles.
+    pub rosters: [[u8; TEAM_SIZE]; 2],
+    /// Games in the set; 1 for a match recorded in one go.
+    pub best_of: u8,
+    /// Games of a set recorded so far; only the first `game_count` entries
+    /// of `games` are set.
+    pub game_count: u8,
+    pub games: [SetGame; MAX_SET_GAMES],
+    /// Merkle root of the replay's key-frame checksums, committed by
+    /// `record_match` and `record_team_match`. Zero for sets, crew battles,
+    /// attested results and disconnects, whose disputes go to the
+    /// committee.
+    pub checksum_root: [u8; 32],
+}
+
+impl MatchAccount {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 8 // match_id
+        + 32 * 2 // players
+        + 1 // winner
+        + 2 // score
+        + 32 // replay_hash
+        + 4 // frame_count
+        + 8 // recorded_at
+        + 1 // finalized
+        + 1 // rating_applied
+        + 1 // bump
+        + 1 // format
+        + TEAM_SIZE * 2 // rosters
+        + 1 // best_of
+        + 1 // game_count
+        + SetGame::SIZE * MAX_SET_GAMES // games
+        + 32; // checksum_root
+
+    pub fn winner_key(&self) -> Pubkey {
+        self.players[self.winner as usize]
+    }
+
+    pub fn loser_key(&self) -> Pubkey {
+        self.players[1 - self.winner as usize]
+    }
+
+    /// Hash of the recorded games' replay hashes, in order.
+    pub fn set_replay_hash(&self) -> [u8; 32] {
+        let hashes: Vec<&[u8]> = self.games[..self.game_count as usize]
+            .iter()
+            .map(|game| game.replay_hash.as_ref())
+            .collect();
+        hashv(&hashes).to_bytes()
+    }
+}
+
+/// One game of a set.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SetGame {
+    /// Index into `MatchAccount::players`.
+    pub winner: u8,
+    /// Character each player picked for the game.
+    pub characters: [u8; 2],
+    pub replay_hash: [u8; 32],
+    pub frame_count: u32,
+}
+
+impl SetGame {
+    pub const SIZE: usize = 1 + 2 + 32 + 4;
+}
+
+impl Versioned for MatchAccount {
+    const LAYOUT_VERSION: u8 = 4;
+    const MIGRATIONS: &'static [Step] = &[
+        ADD_LAYOUT_VERSION,
+        Step {
+            from: 1,
+            grow: 1 + TEAM_SIZE * 2,
+            upgrade: append_format,
+        },
+        Step {
+            from: 2,
+            grow: 1 + 1 + SetGame::SIZE * MAX_SET_GAMES,
+            upgrade: append_set,
+        },
+        Step {
+            from: 3,
+            grow: 32,
+            upgrade: append_checksum_root,
+        },
+    ];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+/// Layout 2 appends `format` and `rosters`; zeroed, every earlier match
+/// reads as singles.
+fn append_format(_: &mut [u8]) {}
+
+/// Layout 3 appends the set fields. Earlier matches were recorded in one
+/// go, so they are sets of one game.
+fn append_set(data: &mut [u8]) {
+    let best_of = data.len() - SetGame::SIZE * MAX_SET_GAMES - 2;
+    data[best_of] = 1;
+}
+
+/// Layout 4 appends `checksum_root`. Earlier matches committed no key
+/// frames, so disputes over them go to the committee.
+fn append_checksum_root(_: &mut [u8]) {}
+
+/// Two crews sharing stock pools, one player from each fighting at a time.
+#[account]
+pub struct CrewBattle {
+    pub layout_version: u8,
+    pub match_id: u64,
+    /// Sign for their crews; index 0 owns `crews[0]`.
+    pub captains: [Pubkey; 2],
+    /// Members in playing order; slots past `crew_size` are empty.
+    pub crews: [[Pubkey; MAX_CREW_SIZE]; 2],
+    pub crew_size: u8,
+    pub stocks_per_player: u8,
+    /// Stocks each crew has left, the active player's included.
+    pub pool: [u16; 2],
+    /// Index into each crew of the player currently in.
+    pub active: [u8; 2],
+    /// Stocks the player currently in has left.
+    pub active_stocks: [u8; 2],
+    /// Only the first `game_count` entries of `games` are set.
+    pub game_count: u8,
+    pub games: [CrewGame; MAX_CREW_GAMES],
+    /// Index of the winning crew, set once finalized.
+    pub winner: u8,
+    /// When the crew battle was decided; until then, when it opened or
+    /// last had a game added.
+    pub recorded_at: i64,
+    pub finalized: bool,
+    pub bump: u8,
+}
+
+impl CrewBattle {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 8 // match_id
+        + 32 * 2 // captains
+        + 32 * MAX_CREW_SIZE * 2 // crews
+        + 1 // crew_size
+        + 1 // stocks_per_player
+        + 2 * 2 // pool
+        + 2 // active
+        + 2 // active_stocks
+        + 1 // game_count
+        + CrewGame::SIZE * MAX_CREW_GAMES // games
+        + 1 // winner
+        + 8 // recorded_at
+        + 1 // finalized
+        + 1; // bump
+}
+
+impl Versioned for CrewBattle {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// One game of a crew battle.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CrewGame {
+    /// Index into each crew of the player who fought.
+    pub players: [u8; 2],
+    /// Index of the winning crew.
+    pub winner: u8,
+    /// Stocks the winner had left.
+    pub stocks_left: u8,
+    pub replay_hash: [u8; 32],
+    pub frame_count: u32,
+}
+
+impl CrewGame {
+    pub const SIZE: usize = 2 + 1 + 1 + 32 + 4;
+}
+
+/// Game servers whose signed results can be recorded without the players.
+#[account]
+pub struct AttestationConfig {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    pub servers: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl AttestationConfig {
+    pub const MAX_SERVERS: usize = 8;
+
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 4 + 32 * Self::MAX_SERVERS // servers
+        + 1; // bump
+}
+
+impl Versioned for AttestationConfig {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+/// A player's attested disconnects, at `[b"disconnects", player]`.
+#[account]
+pub struct DisconnectRecord {
+    pub layout_version: u8,
+    pub player: Pubkey,
+    /// Disconnects since the count last decayed.
+    pub count: u32,
+    pub last_at: i64,
+    pub bump: u8,
+}
+
+impl DisconnectRecord {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // player
+        + 4 // count
+        + 8 // last_at
+        + 1; // bump
+
+    /// When the player may queue for ranked again.
+    pub fn restricted_until(&self) -> i64 {
+        self.last_at + disconnect_restriction(self.count)
+    }
+}
+
+impl Versioned for DisconnectRecord {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[event]
+pub struct MatchRecorded {
+    pub match_account: Pubkey,
+    pub match_id: u64,
+    pub players: [Pubkey; 2],
+    pub format: u8,
+    pub rosters: [[u8; TEAM_SIZE]; 2],
+    pub winner: Pubkey,
+    pub score: [u8; 2],
+    pub replay_hash: [u8; 32],
+    pub frame_count: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct SetOpened {
+    pub match_account: Pubkey,
+    pub match_id: u64,
+    pub players: [Pubkey; 2],
+    pub best_of: u8,
+}
+
+#[event]
+pub struct SetGameRecorded {
+    pub match_account: Pubkey,
+    /// Zero-based index of the game in the set.
+    pub game: u8,
+    pub winner: Pubkey,
+    pub characters: [u8; 2],
+    pub replay_hash: [u8; 32],
+}
+
+#[event]
+pub struct CrewBattleOpened {
+    pub crew_battle: Pubkey,
+    pub match_id: u64,
+    pub captains: [Pubkey; 2],
+    pub crew_size: u8,
+    pub stocks: u8,
+}
+
+#[event]
+pub struct CrewGameRecorded {
+    pub crew_battle: Pubkey,
+    /// Zero-based index of the game in the crew battle.
+    pub game: u8,
+    pub winner: Pubkey,
+    pub loser: Pubkey,
+    pub stocks_left: u8,
+    /// Stocks each crew has left after the game.
+    pub pool: [u16; 2],
+    pub replay_hash: [u8; 32],
+}
+
+#[event]
+pub struct CrewBattleFinished {
+    pub crew_battle: Pubkey,
+    pub match_id: u64,
+    /// Captain of the winning crew.
+    pub winner: Pubkey,
+    /// Stocks left in the winning crew's pool.
+    pub stocks_left: u16,
+    pub games: u8,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct DisconnectReported {
+    pub match_id: u64,
+    pub quitter: Pubkey,
+    pub reporter: Pubkey,
+    pub last_frame: u32,
+    pub last_checksum: u64,
+    pub disconnect_count: u32,
+    pub restricted_until: i64,
+}
+
+/// A stale set or crew battle, closed without a result.
+#[event]
+pub struct StaleMatchClosed {
+    pub account: Pubkey,
+    pub match_id: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum MatchError {
+    #[msg("Players must be distinct")]
+    InvalidPlayers,
+    #[msg("Winner must be 0 or 1")]
+    InvalidWinner,
+    #[msg("Score does not agree with the winner")]
+    ScoreMismatch,
+    #[msg("Match has no frames")]
+    EmptyMatch,
+    #[msg("Match result is not finalized")]
+    NotFinalized,
+    #[msg("Match result has already been rated")]
+    AlreadyRated,
+    #[msg("Too many attestation servers")]
+    TooManyServers,
+    #[msg("No ed25519 attestation precedes this instruction")]
+    MissingAttestation,
+    #[msg("Attestation does not sign the expected payload")]
+    InvalidAttestation,
+    #[msg("Attestation is not signed by an approved server")]
+    UnauthorizedAttester,
+    #[msg("Sets are best of an odd number of games, at most MAX_SET_GAMES")]
+    InvalidSetLength,
+    #[msg("Set is already decided")]
+    SetFinished,
+    #[msg("Crews must be 1 to MAX_CREW_SIZE distinct players")]
+    InvalidCrew,
+    #[msg("Stock count is out of range")]
+    InvalidStocks,
+    #[msg("Crew battle is already decided")]
+    CrewBattleFinished,
+    #[msg("Match is already finalized")]
+    AlreadyFinalized,
+    #[msg("Match has had a game within the stale timeout")]
+    NotStale,
+    #[msg("Only the player who stayed can report a disconnect")]
+    NotRemainingPlayer,
+    #[msg("Input gap is shorter than MIN_DISCONNECT_WAIT_MS")]
+    DisconnectTooShort,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<MigrationError> for MatchError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => MatchError::AlreadyMigrated,
+            _ => MatchError::UnsupportedLayout,
+        }
+    }
+}
@@ -0,0 +1,1018 @@
+// Rust Anchor Program for Matchmaking
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use checked_math::{self as checked, add_assign, increment, Overflow};
+use orao_solana_vrf::program::OraoVrf;
+use orao_solana_vrf::state::{NetworkState, Randomness};
+use orao_solana_vrf::{CONFIG_ACCOUNT_SEED, RANDOMNESS_ACCOUNT_SEED};
+use match_result::{DisconnectRecord, MatchAccount};
+use ranking::PlayerRating;
+
+declare_id!("MATCHMAKING_PROGRAM_ID_HERE");
+
+/// Most players waiting in the queue at once.
+pub const MAX_QUEUE: usize = 64;
+/// Rating difference accepted for a player who just joined.
+pub const BASE_MMR_WINDOW: u32 = 50;
+/// Extra rating difference accepted per `WINDOW_STEP_SECONDS` waited.
+pub const MMR_WINDOW_GROWTH: u32 = 25;
+pub const WINDOW_STEP_SECONDS: i64 = 10;
+pub const MAX_MMR_WINDOW: u32 = 400;
+/// Lamports each player locks with their loadout commitment.
+pub const LOADOUT_DEPOSIT: u64 = 10_000_000;
+/// Seconds both players have to reveal once both have committed.
+pub const REVEAL_TIMEOUT: i64 = 120;
+/// Seconds after a player last joined the queue before their ranked stake
+/// can be withdrawn.
+pub const STAKE_LOCK: i64 = 24 * 60 * 60;
+
+#[program]
+pub mod matchmaking {
+    use super::*;
+
+    pub fn initialize_queue(ctx: Context<InitializeQueue>) -> Result<()> {
+        let queue = &mut ctx.accounts.queue;
+        queue.layout_version = MatchQueue::LAYOUT_VERSION;
+        queue.entries = Vec::new();
+        queue.next_match_id = 0;
+        queue.bump = ctx.bumps.queue;
+        Ok(())
+    }
+
+    /// Creates the ranked entry requirements, with `admin` able to change
+    /// them. All zero lets anyone registered into the queue.
+    pub fn initialize_ranked_config(
+        ctx: Context<InitializeRankedConfig>,
+        requirements: RankedRequirements,
+    ) -> Result<()> {
+        require_gte!(requirements.min_account_age, 0, MatchmakingError::InvalidRequirements);
+        let config = &mut ctx.accounts.ranked_config;
+        config.layout_version = RankedConfig::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.requirements = requirements;
+        config.bump = ctx.bumps.ranked_config;
+        Ok(())
+    }
+
+    /// Changes the ranked entry requirements. Stakes already held keep
+    /// counting up to the new `stake`.
+    pub fn set_ranked_requirements(
+        ctx: Context<SetRankedRequirements>,
+        requirements: RankedRequirements,
+    ) -> Result<()> {
+        require_gte!(requirements.min_account_age, 0, MatchmakingError::InvalidRequirements);
+        ctx.accounts.ranked_config.requirements = requirements;
+
+        emit!(RankedRequirementsChanged { requirements });
+
+        Ok(())
+    }
+
+    /// Starts the player's account age for ranked entry.
+    pub fn register_player(ctx: Context<RegisterPlayer>) -> Result<()> {
+        let registration = &mut ctx.accounts.registration;
+        registration.layout_version = PlayerRegistration::LAYOUT_VERSION;
+        registration.player = *ctx.accounts.player.key;
+        registration.registered_at = Clock::get()?.unix_timestamp;
+        registration.unranked_matches = 0;
+        registration.last_counted_match = 0;
+        registration.stake = 0;
+        registration.last_queued_at = 0;
+        registration.bump = ctx.bumps.registration;
+        Ok(())
+    }
+
+    /// Counts a finalized unrated match the player took part in after
+    /// registering. Matches are counted in increasing id order so none is
+    /// counted twice. Anyone may crank this.
+    #[access_control(validate_unranked_match(&ctx.accounts.registration, &ctx.accounts.match_account))]
+    pub fn record_unranked_match(ctx: Context<RecordUnrankedMatch>) -> Result<()> {
+        let registration = &mut ctx.accounts.registration;
+        registration.last_counted_match = ctx.accounts.match_account.match_id;
+        increment(&mut registration.unranked_matches).map_err(MatchmakingError::from)?;
+        Ok(())
+    }
+
+    /// Queues the player at their current ranking rating, once their account
+    /// meets the ranked requirements. Any stake the requirements ask for
+    /// and the player doesn't hold yet is taken here. Players who keep
+    /// disconnecting from matches sit out for a while; see
+    /// `match_result::disconnect_restriction`.
+    #[access_control(
+        validate_ranked_entry(&ctx.accounts.ranked_config, &ctx.accounts.registration)
+        validate_not_restricted(&ctx.accounts.disconnects)
+    )]
+    pub fn join_queue(ctx: Context<JoinQueue>) -> Result<()> {
+        let player = *ctx.accounts.player.key;
+        let now = Clock::get()?.unix_timestamp;
+        let queue = &mut ctx.accounts.queue;
+        require!(
+            queue.entries.iter().all(|e| e.player != player),
+            MatchmakingError::AlreadyQueued
+        );
+        require_gt!(MAX_QUEUE, queue.entries.len(), MatchmakingError::QueueFull);
+        queue.entries.push(QueueEntry {
+            player,
+            mmr: ctx.accounts.rating.rating,
+            joined_at: now,
+        });
+
+        let top_up = ctx
+            .accounts
+            .ranked_config
+            .requirements
+            .stake
+            .saturating_sub(ctx.accounts.registration.stake);
+        if top_up > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.player.to_account_info(),
+                        to: ctx.accounts.registration.to_account_info(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        let registration = &mut ctx.accounts.registration;
+        add_assign(&mut registration.stake, top_up).map_err(MatchmakingError::from)?;
+        registration.last_queued_at = now;
+        Ok(())
+    }
+
+    /// Refunds the player's ranked stake `STAKE_LOCK` after they last
+    /// joined the queue. They stake again on their next `join_queue`.
+    pub fn withdraw_stake(ctx: Context<WithdrawStake>) -> Result<()> {
+        let registration = &ctx.accounts.registration;
+        require_gt!(registration.stake, 0, MatchmakingError::NoStake);
+        require_gte!(
+            Clock::get()?.unix_timestamp,
+            registration.last_queued_at + STAKE_LOCK,
+            MatchmakingError::StakeLocked
+        );
+        move_lamports(
+            &ctx.accounts.registration.to_account_info(),
+            &ctx.accounts.player,
+            registration.stake,
+        )?;
+        ctx.accounts.registration.stake = 0;
+        Ok(())
+    }
+
+    pub fn leave_queue(ctx: Context<LeaveQueue>) -> Result<()> {
+        let player = *ctx.accounts.player.key;
+        let queue = &mut ctx.accounts.queue;
+        let before = queue.entries.len();
+        queue.entries.retain(|e| e.player != player);
+        require_gt!(before, queue.entries.len(), MatchmakingError::NotQueued);
+        Ok(())
+    }
+
+    /// Pairs the longest-waiting player with the closest-rated compatible
+    /// opponent and opens a `PendingMatch` for them, requesting the VRF
+    /// randomness that will seed it. Anyone may crank this.
+    pub fn pair_players(ctx: Context<PairPlayers>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let queue = &mut ctx.accounts.queue;
+        let (first, second) =
+            find_pair(&queue.entries, now).ok_or(MatchmakingError::NoCompatiblePair)?;
+        let a = queue.entries[first];
+        let b = queue.entries[second];
+        queue.entries.remove(second);
+        queue.entries.remove(first);
+        let match_id = queue.next_match_id;
+        increment(&mut queue.next_match_id).map_err(MatchmakingError::from)?;
+
+        let force = ctx.accounts.pending_match.key().to_bytes();
+        orao_solana_vrf::cpi::request(
+            CpiContext::new(
+                ctx.accounts.vrf.to_account_info(),
+                orao_solana_vrf::cpi::accounts::Request {
+                    payer: ctx.accounts.payer.to_account_info(),
+                    network_state: ctx.accounts.network_state.to_account_info(),
+                    treasury: ctx.accounts.treasury.to_account_info(),
+                    request: ctx.accounts.random.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                },
+            ),
+            force,
+        )?;
+
+        let pending = &mut ctx.accounts.pending_match;
+        pending.layout_version = PendingMatch::LAYOUT_VERSION;
+        pending.match_id = match_id;
+        pending.players = [a.player, b.player];
+        pending.mmr = [a.mmr, b.mmr];
+        pending.vrf_request = ctx.accounts.random.key();
+        pending.seed = 0;
+        pending.seeded = false;
+        pending.sides_swapped = false;
+        pending.stage_pick = 0;
+        pending.created_at = now;
+        pending.status = PendingStatus::Committing;
+        pending.commitments = [[0; 32]; 2];
+        pending.committed = [false; 2];
+        pending.revealed = [false; 2];
+        pending.loadouts = [Loadout::default(); 2];
+        pending.stage = 0;
+        pending.reveal_deadline = 0;
+        pending.bump = ctx.bumps.pending_match;
+
+        emit!(MatchFound {
+            pending_match: pending.key(),
+            match_id,
+            players: pending.players,
+        });
+
+        Ok(())
+    }
+
+    /// Locks in `hashv([character, stage, salt, player])` along with the
+    /// loadout deposit. Nobody sees a pick until both have committed.
+    pub fn commit_loadout(ctx: Context<CommitLoadout>, hash: [u8; 32]) -> Result<()> {
+        let side = player_side(&ctx.accounts.pending_match, ctx.accounts.player.key)?;
+        let pending = &ctx.accounts.pending_match;
+        require!(
+            pending.status == PendingStatus::Committing,
+            MatchmakingError::WrongPhase
+        );
+        require!(!pending.committed[side], MatchmakingError::AlreadyCommitted);
+
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.player.to_account_info(),
+                    to: ctx.accounts.pending_match.to_account_info(),
+                },
+            ),
+            LOADOUT_DEPOSIT,
+        )?;
+
+        let pending = &mut ctx.accounts.pending_match;
+        pending.commitments[side] = hash;
+        pending.committed[side] = true;
+        if pending.committed == [true; 2] {
+            pending.status = PendingStatus::Revealing;
+            pending.reveal_deadline = Clock::get()?.unix_timestamp + REVEAL_TIMEOUT;
+        }
+
+        emit!(LoadoutCommitted {
+            match_id: pending.match_id,
+            player: *ctx.accounts.player.key,
+        });
+
+        Ok(())
+    }
+
+    /// Opens the player's commitment. A matching reveal refunds their
+    /// deposit; a mismatched one forfeits the match and both deposits go to
+    /// the opponent. The stage is taken from the two revealed picks.
+    pub fn reveal_loadout(
+        ctx: Context<RevealLoadout>,
+        character: u8,
+        stage: u8,
+        salt: [u8; 32],
+    ) -> Result<()> {
+        let side = player_side(&ctx.accounts.pending_match, ctx.accounts.player.key)?;
+        let pending = &ctx.accounts.pending_match;
+        require!(
+            pending.status == PendingStatus::Revealing,
+            MatchmakingError::WrongPhase
+        );
+        require!(!pending.revealed[side], MatchmakingError::AlreadyRevealed);
+        require_keys_eq!(
+            ctx.accounts.opponent.key(),
+            pending.players[1 - side],
+            MatchmakingError::NotInMatch
+        );
+
+        let hash = loadout_hash(character, stage, &salt, ctx.accounts.player.key);
+        let pending_info = ctx.accounts.pending_match.to_account_info();
+        if hash != pending.commitments[side] {
+            move_lamports(
+                &pending_info,
+                &ctx.accounts.opponent,
+                deposits_held(pending),
+            )?;
+            let pending = &mut ctx.accounts.pending_match;
+            pending.status = PendingStatus::Forfeited;
+            emit!(MatchForfeited {
+                match_id: pending.match_id,
+                forfeited_by: *ctx.accounts.player.key,
+            });
+            return Ok(());
+        }
+
+        move_lamports(&pending_info, &ctx.accounts.player, LOADOUT_DEPOSIT)?;
+        let pending = &mut ctx.accounts.pending_match;
+        pending.revealed[side] = true;
+        pending.loadouts[side] = Loadout { character, stage };
+        try_make_ready(pending);
+
+        Ok(())
+    }
+
+    /// Copies the fulfilled VRF output into the match: the simulation seed,
+    /// which player takes the left side, and whose stage pick is played.
+    /// Anyone may crank this.
+    pub fn fulfill_randomness(ctx: Context<FulfillRandomness>) -> Result<()> {
+        let randomness = ctx
+            .accounts
+            .random
+            .fulfilled()
+            .ok_or(MatchmakingError::RandomnessNotReady)?;
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&randomness[..8]);
+
+        let pending = &mut ctx.accounts.pending_match;
+        require!(!pending.seeded, MatchmakingError::AlreadySeeded);
+        pending.seed = u64::from_le_bytes(seed);
+        pending.sides_swapped = randomness[8] & 1 == 1;
+        pending.stage_pick = randomness[9] & 1;
+        pending.seeded = true;
+
+        emit!(MatchSeeded {
+            match_id: pending.match_id,
+            seed: pending.seed,
+            sides_swapped: pending.sides_swapped,
+        });
+
+        try_make_ready(pending);
+
+        Ok(())
+    }
+
+    /// Lets a player who revealed take the deposits once the opponent has
+    /// let the reveal deadline pass.
+    pub fn claim_reveal_timeout(ctx: Context<RevealLoadout>) -> Result<()> {
+        let side = player_side(&ctx.accounts.pending_match, ctx.accounts.player.key)?;
+        let pending = &ctx.accounts.pending_match;
+        require!(
+            pending.status == PendingStatus::Revealing,
+            MatchmakingError::WrongPhase
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= pending.reveal_deadline,
+            MatchmakingError::RevealStillOpen
+        );
+        require!(
+            pending.revealed[side] && !pending.revealed[1 - side],
+            MatchmakingError::AlreadyRevealed
+        );
+
+        move_lamports(
+            &ctx.accounts.pending_match.to_account_info(),
+            &ctx.accounts.player,
+            deposits_held(pending),
+        )?;
+        let pending = &mut ctx.accounts.pending_match;
+        pending.status = PendingStatus::Forfeited;
+        emit!(MatchForfeited {
+            match_id: pending.match_id,
+            forfeited_by: pending.players[1 - side],
+        });
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<MatchQueue>(MatchQueue::discriminator()),
+            Layout::new::<PendingMatch>(PendingMatch::discriminator()),
+            Layout::new::<RankedConfig>(RankedConfig::discriminator()),
+            Layout::new::<PlayerRegistration>(PlayerRegistration::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<MatchmakingError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+/// Starts the match once both loadouts are revealed and the VRF output is
+/// in; the stage is the pick of the player chosen by the randomness.
+fn try_make_ready(pending: &mut PendingMatch) {
+    if pending.status != PendingStatus::Revealing
+        || pending.revealed != [true; 2]
+        || !pending.seeded
+    {
+        return;
+    }
+    pending.status = PendingStatus::Ready;
+    pending.stage = pending.loadouts[pending.stage_pick as usize].stage;
+
+    emit!(LoadoutsRevealed {
+        match_id: pending.match_id,
+        loadouts: pending.loadouts,
+        stage: pending.stage,
+    });
+}
+
+fn validate_unranked_match(registration: &PlayerRegistration, record: &MatchAccount) -> Result<()> {
+    require!(
+        record.finalized && !record.rating_applied,
+        MatchmakingError::NotUnrankedMatch
+    );
+    require!(
+        record.players.contains(&registration.player),
+        MatchmakingError::NotInMatch
+    );
+    require_gte!(
+        record.recorded_at,
+        registration.registered_at,
+        MatchmakingError::NotUnrankedMatch
+    );
+    require!(
+        registration.unranked_matches == 0 || record.match_id > registration.last_counted_match,
+        MatchmakingError::MatchAlreadyCounted
+    );
+    Ok(())
+}
+
+/// Keeps new and thinly played accounts, the usual smurfs and boosters,
+/// out of the ranked queue.
+fn validate_ranked_entry(config: &RankedConfig, registration: &PlayerRegistration) -> Result<()> {
+    let requirements = &config.requirements;
+    require_gte!(
+        Clock::get()?.unix_timestamp - registration.registered_at,
+        requirements.min_account_age,
+        MatchmakingError::AccountTooNew
+    );
+    require_gte!(
+        registration.unranked_matches,
+        requirements.min_unranked_matches,
+        MatchmakingError::TooFewUnrankedMatches
+    );
+    Ok(())
+}
+
+/// A player with no disconnect record has never been reported.
+fn validate_not_restricted(disconnects: &AccountInfo) -> Result<()> {
+    if disconnects.owner == &match_result::ID {
+        let record = DisconnectRecord::try_deserialize(&mut &disconnects.data.borrow()[..])?;
+        require_gte!(
+            Clock::get()?.unix_timestamp,
+            record.restricted_until(),
+            MatchmakingError::DisconnectRestricted
+        );
+    }
+    Ok(())
+}
+
+pub fn loadout_hash(character: u8, stage: u8, salt: &[u8; 32], player: &Pubkey) -> [u8; 32] {
+    hashv(&[&[character, stage], salt, player.as_ref()]).to_bytes()
+}
+
+fn player_side(pending: &PendingMatch, player: &Pubkey) -> Result<usize> {
+    pending
+        .players
+        .iter()
+        .position(|p| p == player)
+        .ok_or_else(|| error!(MatchmakingError::NotInMatch))
+}
+
+/// Deposits still escrowed in the pending match.
+fn deposits_held(pending: &PendingMatch) -> u64 {
+    let committed = pending.committed.iter().filter(|&&c| c).count() as u64;
+    let refunded = pending.revealed.iter().filter(|&&r| r).count() as u64;
+    (committed - refunded) * LOADOUT_DEPOSIT
+}
+
+/// Moves lamports out of an account this program owns.
+fn move_lamports(from: &AccountInfo, to: &AccountInfo, amount: u64) -> Result<()> {
+    let mut from_lamports = from.try_borrow_mut_lamports()?;
+    let mut to_lamports = to.try_borrow_mut_lamports()?;
+    checked::move_lamports(&mut from_lamports, &mut to_lamports, amount)
+        .map_err(MatchmakingError::from)?;
+    Ok(())
+}
+
+/// Rating difference `entry` accepts after waiting until `now`.
+pub fn mmr_window(entry: &QueueEntry, now: i64) -> u32 {
+    let steps = (now - entry.joined_at).max(0) / WINDOW_STEP_SECONDS;
+    let growth = (steps as u64 * MMR_WINDOW_GROWTH as u64).min(MAX_MMR_WINDOW as u64) as u32;
+    (BASE_MMR_WINDOW + growth).min(MAX_MMR_WINDOW)
+}
+
+/// Indices of the pair to match, the first being the longest waiting
+/// player that has an opponent within both players' windows.
+pub fn find_pair(entries: &[QueueEntry], now: i64) -> Option<(usize, usize)> {
+    let mut order: Vec<usize> = (0..entries.len()).collect();
+    order.sort_by_key(|&i| entries[i].joined_at);
+    for &i in &order {
+        let best = (0..entries.len())
+            .filter(|&j| j != i)
+            .filter(|&j| {
+                let diff = entries[i].mmr.abs_diff(entries[j].mmr);
+                diff <= mmr_window(&entries[i], now) && diff <= mmr_window(&entries[j], now)
+            })
+            .min_by_key(|&j| entries[i].mmr.abs_diff(entries[j].mmr));
+        if let Some(j) = best {
+            return Some((i.min(j), i.max(j)));
+        }
+    }
+    None
+}
+
+#[derive(Accounts)]
+pub struct InitializeQueue<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"queue"],
+        bump,
+        space = MatchQueue::SPACE,
+        payer = payer,
+    )]
+    pub queue: Account<'info, MatchQueue>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRankedConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"ranked_config"],
+        bump,
+        space = RankedConfig::SPACE,
+        payer = admin,
+    )]
+    pub ranked_config: Account<'info, RankedConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRankedRequirements<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"ranked_config"], bump = ranked_config.bump, has_one = admin)]
+    pub ranked_config: Account<'info, RankedConfig>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterPlayer<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"registration", player.key.as_ref()],
+        bump,
+        space = PlayerRegistration::SPACE,
+        payer = player,
+    )]
+    pub registration: Account<'info, PlayerRegistration>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordUnrankedMatch<'info> {
+    #[account(
+        mut,
+        seeds = [b"registration", registration.player.as_ref()],
+        bump = registration.bump,
+    )]
+    pub registration: Account<'info, PlayerRegistration>,
+    #[account(
+        seeds = [b"match", &match_account.match_id.to_le_bytes()],
+        bump = match_account.bump,
+        seeds::program = match_result::ID,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+}
+
+#[derive(Accounts)]
+pub struct JoinQueue<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(
+        seeds = [b"rating", player.key.as_ref()],
+        bump = rating.bump,
+        seeds::program = ranking::ID,
+    )]
+    pub rating: Account<'info, PlayerRating>,
+    #[account(mut, seeds = [b"queue"], bump = queue.bump)]
+    pub queue: Account<'info, MatchQueue>,
+    #[account(seeds = [b"ranked_config"], bump = ranked_config.bump)]
+    pub ranked_config: Account<'info, RankedConfig>,
+    #[account(
+        mut,
+        seeds = [b"registration", player.key.as_ref()],
+        bump = registration.bump,
+    )]
+    pub registration: Account<'info, PlayerRegistration>,
+    /// CHECK: the player's disconnect record, verified by seeds and only
+    /// read when match_result owns it
+    #[account(
+        seeds = [b"disconnects", player.key.as_ref()],
+        bump,
+        seeds::program = match_result::ID,
+    )]
+    pub disconnects: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawStake<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"registration", player.key.as_ref()],
+        bump = registration.bump,
+    )]
+    pub registration: Account<'info, PlayerRegistration>,
+}
+
+#[derive(Accounts)]
+pub struct LeaveQueue<'info> {
+    pub player: Signer<'info>,
+    #[account(mut, seeds = [b"queue"], bump = queue.bump)]
+    pub queue: Account<'info, MatchQueue>,
+}
+
+#[derive(Accounts)]
+pub struct PairPlayers<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"queue"], bump = queue.bump)]
+    pub queue: Account<'info, MatchQueue>,
+    #[account(
+        init,
+        seeds = [b"pending_match".as_ref(), &queue.next_match_id.to_le_bytes()],
+        bump,
+        space = PendingMatch::SPACE,
+        payer = payer,
+    )]
+    pub pending_match: Account<'info, PendingMatch>,
+    #[account(
+        mut,
+        seeds = [CONFIG_ACCOUNT_SEED.as_ref()],
+        bump,
+        seeds::program = orao_solana_vrf::ID,
+    )]
+    pub network_state: Account<'info, NetworkState>,
+    /// CHECK: checked against the network state by the VRF program
+    #[account(mut)]
+    pub treasury: AccountInfo<'info>,
+    /// CHECK: randomness request, created by the VRF program
+    #[account(
+        mut,
+        seeds = [RANDOMNESS_ACCOUNT_SEED.as_ref(), pending_match.key().as_ref()],
+        bump,
+        seeds::program = orao_solana_vrf::ID,
+    )]
+    pub random: AccountInfo<'info>,
+    pub vrf: Program<'info, OraoVrf>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FulfillRandomness<'info> {
+    #[account(
+        mut,
+        seeds = [b"pending_match", &pending_match.match_id.to_le_bytes()],
+        bump = pending_match.bump,
+    )]
+    pub pending_match: Account<'info, PendingMatch>,
+    #[account(address = pending_match.vrf_request)]
+    pub random: Account<'info, Randomness>,
+}
+
+#[derive(Accounts)]
+pub struct CommitLoadout<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"pending_match", &pending_match.match_id.to_le_bytes()],
+        bump = pending_match.bump,
+    )]
+    pub pending_match: Account<'info, PendingMatch>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevealLoadout<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    /// The other player, checked against the pending match
+    #[account(mut)]
+    pub opponent: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"pending_match", &pending_match.match_id.to_le_bytes()],
+        bump = pending_match.bump,
+    )]
+    pub pending_match: Account<'info, PendingMatch>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PendingStatus {
+    Committing,
+    Revealing,
+    Ready,
+    Forfeited,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Loadout {
+    pub character: u8,
+    pub stage: u8,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueEntry {
+    pub player: Pubkey,
+    pub mmr: u32,
+    pub joined_at: i64,
+}
+
+impl QueueEntry {
+    pub const SIZE: usize = 32 + 4 + 8;
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct MatchQueue {
+    pub layout_version: u8,
+    pub entries: Vec<QueueEntry>,
+    /// Id given to the next `PendingMatch`.
+    pub next_match_id: u64,
+    pub bump: u8,
+}
+
+impl MatchQueue {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 4 + QueueEntry::SIZE * MAX_QUEUE // entries
+        + 8 // next_match_id
+        + 1; // bump
+}
+
+impl Versioned for MatchQueue {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RankedRequirements {
+    /// Seconds since `register_player`.
+    pub min_account_age: i64,
+    pub min_unranked_matches: u32,
+    /// Refundable lamports held while queueing; zero for none.
+    pub stake: u64,
+}
+
+impl RankedRequirements {
+    pub const SIZE: usize = 8 + 4 + 8;
+}
+
+#[account]
+pub struct RankedConfig {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    pub requirements: RankedRequirements,
+    pub bump: u8,
+}
+
+impl RankedConfig {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + RankedRequirements::SIZE // requirements
+        + 1; // bump
+}
+
+impl Versioned for RankedConfig {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// A player's ranked eligibility, at `[b"registration", player]`. Holds
+/// their ranked stake in its lamports.
+#[account]
+pub struct PlayerRegistration {
+    pub layout_version: u8,
+    pub player: Pubkey,
+    pub registered_at: i64,
+    pub unranked_matches: u32,
+    /// Id of the newest match counted in `unranked_matches`.
+    pub last_counted_match: u64,
+    /// Lamports staked above rent.
+    pub stake: u64,
+    pub last_queued_at: i64,
+    pub bump: u8,
+}
+
+impl PlayerRegistration {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // player
+        + 8 // registered_at
+        + 4 // unranked_matches
+        + 8 // last_counted_match
+        + 8 // stake
+        + 8 // last_queued_at
+        + 1; // bump
+}
+
+impl Versioned for PlayerRegistration {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// Two paired players. Both clients derive their simulation from `seed`,
+/// taken from VRF output along with the side and stage assignment.
+/// Loadouts are chosen by commit-reveal before the match starts.
+#[account]
+pub struct PendingMatch {
+    pub layout_version: u8,
+    pub match_id: u64,
+    pub players: [Pubkey; 2],
+    pub mmr: [u32; 2],
+    /// VRF randomness account requested when the match was paired.
+    pub vrf_request: Pubkey,
+    pub seed: u64,
+    /// Set once `fulfill_randomness` has copied the VRF output.
+    pub seeded: bool,
+    /// Whether `players[1]` takes the left side.
+    pub sides_swapped: bool,
+    /// Index of the player whose stage pick is played.
+    pub stage_pick: u8,
+    pub created_at: i64,
+    pub status: PendingStatus,
+    pub commitments: [[u8; 32]; 2],
+    pub committed: [bool; 2],
+    pub revealed: [bool; 2],
+    pub loadouts: [Loadout; 2],
+    /// Stage the match is played on, set once both loadouts are revealed.
+    pub stage: u8,
+    pub reveal_deadline: i64,
+    pub bump: u8,
+}
+
+impl PendingMatch {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 8 // match_id
+        + 32 * 2 // players
+        + 4 * 2 // mmr
+        + 32 // vrf_request
+        + 8 // seed
+        + 1 // seeded
+        + 1 // sides_swapped
+        + 1 // stage_pick
+        + 8 // created_at
+        + 1 // status
+        + 32 * 2 // commitments
+        + 2 // committed
+        + 2 // revealed
+        + 2 * 2 // loadouts
+        + 1 // stage
+        + 8 // reveal_deadline
+        + 1; // bump
+}
+
+impl Versioned for PendingMatch {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[event]
+pub struct MatchFound {
+    pub pending_match: Pubkey,
+    pub match_id: u64,
+    pub players: [Pubkey; 2],
+}
+
+#[event]
+pub struct MatchSeeded {
+    pub match_id: u64,
+    pub seed: u64,
+    pub sides_swapped: bool,
+}
+
+#[event]
+pub struct LoadoutCommitted {
+    pub match_id: u64,
+    pub player: Pubkey,
+}
+
+#[event]
+pub struct LoadoutsRevealed {
+    pub match_id: u64,
+    pub loadouts: [Loadout; 2],
+    pub stage: u8,
+}
+
+#[event]
+pub struct MatchForfeited {
+    pub match_id: u64,
+    pub forfeited_by: Pubkey,
+}
+
+#[event]
+pub struct RankedRequirementsChanged {
+    pub requirements: RankedRequirements,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum MatchmakingError {
+    #[msg("Player is already queued")]
+    AlreadyQueued,
+    #[msg("Player is not queued")]
+    NotQueued,
+    #[msg("Queue is full")]
+    QueueFull,
+    #[msg("No two queued players are within each other's window")]
+    NoCompatiblePair,
+    #[msg("Signer is not a player in this match")]
+    NotInMatch,
+    #[msg("Match is not in this phase")]
+    WrongPhase,
+    #[msg("Loadout already committed")]
+    AlreadyCommitted,
+    #[msg("Loadout already revealed")]
+    AlreadyRevealed,
+    #[msg("Reveal deadline has not passed")]
+    RevealStillOpen,
+    #[msg("VRF randomness is not fulfilled yet")]
+    RandomnessNotReady,
+    #[msg("Match is already seeded")]
+    AlreadySeeded,
+    #[msg("Minimum account age cannot be negative")]
+    InvalidRequirements,
+    #[msg("Account is too new for ranked")]
+    AccountTooNew,
+    #[msg("Not enough unranked matches played for ranked")]
+    TooFewUnrankedMatches,
+    #[msg("Match is not a finalized unrated match since registering")]
+    NotUnrankedMatch,
+    #[msg("Match was already counted")]
+    MatchAlreadyCounted,
+    #[msg("No ranked stake is held")]
+    NoStake,
+    #[msg("Ranked stake is still locked")]
+    StakeLocked,
+    #[msg("Player is barred from ranked after repeated disconnects")]
+    DisconnectRestricted,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for MatchmakingError {
+    fn from(_: Overflow) -> Self {
+        MatchmakingError::Overflow
+    }
+}
+
+impl From<MigrationError> for MatchmakingError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => MatchmakingError::AlreadyMigrated,
+            _ => MatchmakingError::UnsupportedLayout,
+        }
+    }
+}
@@ -0,0 +1,378 @@
+// Rust Anchor Program for Character Move Registry
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use checked_math::{increment, Overflow};
+use game_core::Motion;
+
+declare_id!("MOVE_REGISTRY_PROGRAM_ID_HERE");
+
+#[program]
+pub mod move_registry {
+    use super::*;
+
+    pub fn initialize_registry(ctx: Context<InitializeRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        registry.layout_version = Registry::LAYOUT_VERSION;
+        registry.admin = *ctx.accounts.admin.key;
+        registry.character_count = 0;
+        registry.bump = ctx.bumps.registry;
+        Ok(())
+    }
+
+    #[access_control(only_admin(&ctx.accounts.registry, &ctx.accounts.admin))]
+    pub fn register_character(ctx: Context<RegisterCharacter>, character_id: u8) -> Result<()> {
+        let frame_data = &mut ctx.accounts.frame_data;
+        frame_data.layout_version = CharacterFrameData::LAYOUT_VERSION;
+        frame_data.character_id = character_id;
+        frame_data.moves = Vec::new();
+        frame_data.definition_revision = 0;
+        frame_data.definition_hash = [0; 32];
+        frame_data.bump = ctx.bumps.frame_data;
+
+        let registry = &mut ctx.accounts.registry;
+        increment(&mut registry.character_count).map_err(RegistryError::from)?;
+
+        emit!(CharacterRegistered {
+            frame_data: frame_data.key(),
+            character_id,
+        });
+
+        Ok(())
+    }
+
+    #[access_control(
+        only_admin(&ctx.accounts.registry, &ctx.accounts.admin)
+        validate_move(&move_data)
+    )]
+    pub fn set_move(ctx: Context<UpdateMoves>, move_data: MoveFrameData) -> Result<()> {
+        let frame_data = &mut ctx.accounts.frame_data;
+
+        match frame_data.moves.iter_mut().find(|m| m.move_id == move_data.move_id) {
+            Some(existing) => *existing = move_data.clone(),
+            None => {
+                require_gt!(
+                    CharacterFrameData::MAX_MOVES,
+                    frame_data.moves.len(),
+                    RegistryError::TooManyMoves
+                );
+                frame_data.moves.push(move_data.clone());
+            }
+        }
+
+        emit!(MoveUpdated {
+            frame_data: frame_data.key(),
+            character_id: frame_data.character_id,
+            move_id: move_data.move_id,
+        });
+
+        Ok(())
+    }
+
+    #[access_control(only_admin(&ctx.accounts.registry, &ctx.accounts.admin))]
+    pub fn remove_move(ctx: Context<UpdateMoves>, move_id: u8) -> Result<()> {
+        let frame_data = &mut ctx.accounts.frame_data;
+        let before = frame_data.moves.len();
+        frame_data.moves.retain(|m| m.move_id != move_id);
+        require_gt!(before, frame_data.moves.len(), RegistryError::UnknownMove);
+        Ok(())
+    }
+
+    /// Anchors the SHA-256 of the character's canonical definition file so
+    /// clients and verifiers can check they run the same revision.
+    #[access_control(only_admin(&ctx.accounts.registry, &ctx.accounts.admin))]
+    pub fn set_definition_hash(
+        ctx: Context<UpdateMoves>,
+        revision: u16,
+        definition_hash: [u8; 32],
+    ) -> Result<()> {
+        let frame_data = &mut ctx.accounts.frame_data;
+        require_gt!(revision, frame_data.definition_revision, RegistryError::StaleRevision);
+        frame_data.definition_revision = revision;
+        frame_data.definition_hash = definition_hash;
+
+        emit!(DefinitionHashUpdated {
+            frame_data: frame_data.key(),
+            character_id: frame_data.character_id,
+            revision,
+            definition_hash,
+        });
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<Registry>(Registry::discriminator()),
+            Layout::new::<CharacterFrameData>(CharacterFrameData::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<RegistryError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+fn only_admin(registry: &Registry, admin: &AccountInfo) -> Result<()> {
+    require_keys_eq!(registry.admin, *admin.key, RegistryError::Unauthorized);
+    Ok(())
+}
+
+fn validate_move(move_data: &MoveFrameData) -> Result<()> {
+    require_gt!(move_data.active, 0, RegistryError::InvalidFrameData);
+    require_gt!(move_data.base_damage, 0, RegistryError::InvalidFrameData);
+    require_gte!(move_data.launch, 0, RegistryError::InvalidFrameData);
+    require!(
+        move_data.motion == 0 || Motion::from_code(move_data.motion).is_some(),
+        RegistryError::InvalidFrameData
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeRegistry<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"registry"],
+        bump,
+        space = Registry::SPACE,
+        payer = admin,
+    )]
+    pub registry: Account<'info, Registry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(character_id: u8)]
+pub struct RegisterCharacter<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"registry"], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        init,
+        seeds = [b"character".as_ref(), &[character_id]],
+        bump,
+        space = CharacterFrameData::SPACE,
+        payer = admin,
+    )]
+    pub frame_data: Account<'info, CharacterFrameData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMoves<'info> {
+    pub admin: Signer<'info>,
+    #[account(seeds = [b"registry"], bump = registry.bump)]
+    pub registry: Account<'info, Registry>,
+    #[account(
+        mut,
+        seeds = [b"character", &[frame_data.character_id]],
+        bump = frame_data.bump,
+    )]
+    pub frame_data: Account<'info, CharacterFrameData>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct Registry {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    pub character_count: u16,
+    pub bump: u8,
+}
+
+impl Registry {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 2 // character_count
+        + 1; // bump
+}
+
+impl Versioned for Registry {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[account]
+pub struct CharacterFrameData {
+    pub layout_version: u8,
+    pub character_id: u8,
+    pub moves: Vec<MoveFrameData>,
+    pub definition_revision: u16,
+    pub definition_hash: [u8; 32],
+    pub bump: u8,
+}
+
+impl CharacterFrameData {
+    pub const MAX_MOVES: usize = 32;
+
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 1 // character_id
+        + 4 + Self::MAX_MOVES * MoveFrameData::SIZE // moves
+        + 2 // definition_revision
+        + 32 // definition_hash
+        + 1; // bump
+
+    pub fn find_move(&self, move_id: u8) -> Option<&MoveFrameData> {
+        self.moves.iter().find(|m| m.move_id == move_id)
+    }
+}
+
+impl Versioned for CharacterFrameData {
+    const LAYOUT_VERSION: u8 = 2;
+    const MIGRATIONS: &'static [Step] = &[
+        ADD_LAYOUT_VERSION,
+        Step {
+            from: 1,
+            grow: CharacterFrameData::MAX_MOVES * MoveFrameData::ADDED_IN_V2,
+            upgrade: widen_moves,
+        },
+    ];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+/// Layout 2 appends `hitstun` and `motion` to every move. Moves carried
+/// over from layout 1 get 0 for both until the admin sets them again.
+fn widen_moves(data: &mut [u8]) {
+    const OLD_SIZE: usize = MoveFrameData::SIZE - MoveFrameData::ADDED_IN_V2;
+    // Discriminator, layout_version and character_id.
+    let count_at = 8 + 1 + 1;
+    let mut count = [0; 4];
+    count.copy_from_slice(&data[count_at..count_at + 4]);
+    let count = u32::from_le_bytes(count) as usize;
+    let start = count_at + 4;
+    // definition_revision, definition_hash and bump.
+    let tail = 2 + 32 + 1;
+
+    let old_end = start + count * OLD_SIZE;
+    data.copy_within(old_end..old_end + tail, start + count * MoveFrameData::SIZE);
+    for i in (0..count).rev() {
+        let from = start + i * OLD_SIZE;
+        let to = start + i * MoveFrameData::SIZE;
+        data.copy_within(from..from + OLD_SIZE, to);
+        data[to + OLD_SIZE..to + MoveFrameData::SIZE].fill(0);
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MoveFrameData {
+    pub move_id: u8,
+    pub startup: u8,
+    pub active: u8,
+    pub recovery: u8,
+    pub base_damage: u16,
+    pub meter_gain: u16,
+    /// Upward velocity given to the opponent on hit, raw Q16.16 pixels per
+    /// frame as in the game core. Non-zero makes the move a launcher.
+    pub launch: i32,
+    /// Juggle points the hit costs against an airborne opponent.
+    pub juggle_cost: u8,
+    /// Frames the opponent stays in hitstun, which sets how tight links
+    /// out of the move are.
+    pub hitstun: u16,
+    /// `game_core::Motion::code` of the move's input, 0 for a button press.
+    pub motion: u8,
+}
+
+impl MoveFrameData {
+    pub const SIZE: usize = 1 + 1 + 1 + 1 + 2 + 2 + 4 + 1 + 2 + 1;
+    /// Bytes `hitstun` and `motion` added to each move in layout 2.
+    pub const ADDED_IN_V2: usize = 3;
+
+    pub fn total_frames(&self) -> u16 {
+        self.startup as u16 + self.active as u16 + self.recovery as u16
+    }
+}
+
+#[event]
+pub struct CharacterRegistered {
+    pub frame_data: Pubkey,
+    pub character_id: u8,
+}
+
+#[event]
+pub struct MoveUpdated {
+    pub frame_data: Pubkey,
+    pub character_id: u8,
+    pub move_id: u8,
+}
+
+#[event]
+pub struct DefinitionHashUpdated {
+    pub frame_data: Pubkey,
+    pub character_id: u8,
+    pub revision: u16,
+    pub definition_hash: [u8; 32],
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum RegistryError {
+    #[msg("Unauthorized")]
+    Unauthorized,
+    #[msg("Character has too many moves")]
+    TooManyMoves,
+    #[msg("Unknown move id")]
+    UnknownMove,
+    #[msg("Invalid frame data")]
+    InvalidFrameData,
+    #[msg("Definition revision must increase")]
+    StaleRevision,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for RegistryError {
+    fn from(_: Overflow) -> Self {
+        RegistryError::Overflow
+    }
+}
+
+impl From<MigrationError> for RegistryError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => RegistryError::AlreadyMigrated,
+            _ => RegistryError::UnsupportedLayout,
+        }
+    }
+}
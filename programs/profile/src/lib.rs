@@ -0,0 +1,423 @@
+// Rust Anchor Program for Player Profiles
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{Layout, MigrationError, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use checked_math::{increment, Overflow};
+
+declare_id!("PROFILE_PROGRAM_ID_HERE");
+
+/// Seed of the PDA a writer program signs profile updates with.
+pub const PROFILE_WRITER_SEED: &[u8] = b"profile_writer";
+
+#[program]
+pub mod profile {
+    use super::*;
+
+    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.layout_version = ProfileConfig::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.writers = Vec::new();
+        config.bump = ctx.bumps.config;
+        Ok(())
+    }
+
+    /// Lets `program` update match stats and badges by signing with its
+    /// `profile_writer` PDA.
+    pub fn add_writer(ctx: Context<UpdateWriters>, program: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require!(!config.writers.contains(&program), ProfileError::WriterAlreadyAdded);
+        require_gt!(
+            ProfileConfig::MAX_WRITERS,
+            config.writers.len(),
+            ProfileError::TooManyWriters
+        );
+        config.writers.push(program);
+
+        emit!(WriterChanged {
+            program,
+            allowed: true,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove_writer(ctx: Context<UpdateWriters>, program: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        let before = config.writers.len();
+        config.writers.retain(|w| *w != program);
+        require_gt!(before, config.writers.len(), ProfileError::UnknownWriter);
+
+        emit!(WriterChanged {
+            program,
+            allowed: false,
+        });
+
+        Ok(())
+    }
+
+    #[access_control(validate_display_name(&display_name))]
+    pub fn create_profile(
+        ctx: Context<CreateProfile>,
+        display_name: String,
+        main_character: u8,
+    ) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+        profile.layout_version = Profile::LAYOUT_VERSION;
+        profile.owner = *ctx.accounts.owner.key;
+        profile.display_name = display_name;
+        profile.main_character = main_character;
+        profile.matches_played = 0;
+        profile.wins = 0;
+        profile.win_rate_bps = 0;
+        profile.favorite_combo = Pubkey::default();
+        profile.badges = Vec::new();
+        profile.created_at = Clock::get()?.unix_timestamp;
+        profile.bump = ctx.bumps.profile;
+
+        emit!(ProfileUpdated {
+            profile: profile.key(),
+            owner: profile.owner,
+            display_name: profile.display_name.clone(),
+            main_character,
+            favorite_combo: profile.favorite_combo,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the fields the owner controls. `favorite_combo` is not checked
+    /// against the combo program; clients resolve it and skip keys that
+    /// don't.
+    #[access_control(validate_display_name(&display_name))]
+    pub fn update_profile(
+        ctx: Context<UpdateProfile>,
+        display_name: String,
+        main_character: u8,
+        favorite_combo: Pubkey,
+    ) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+        profile.display_name = display_name;
+        profile.main_character = main_character;
+        profile.favorite_combo = favorite_combo;
+
+        emit!(ProfileUpdated {
+            profile: profile.key(),
+            owner: profile.owner,
+            display_name: profile.display_name.clone(),
+            main_character,
+            favorite_combo,
+        });
+
+        Ok(())
+    }
+
+    /// Counts one finished match for the profile. Only writer programs can
+    /// call it, so the counters move in the same transaction as the result
+    /// they come from.
+    #[access_control(is_writer(&ctx.accounts.config, &writer_program))]
+    pub fn record_match(ctx: Context<WriteProfile>, writer_program: Pubkey, won: bool) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+        increment(&mut profile.matches_played).map_err(ProfileError::from)?;
+        if won {
+            increment(&mut profile.wins).map_err(ProfileError::from)?;
+        }
+        profile.win_rate_bps = win_rate_bps(profile.wins, profile.matches_played);
+
+        emit!(MatchRecorded {
+            profile: profile.key(),
+            writer_program,
+            won,
+            matches_played: profile.matches_played,
+            wins: profile.wins,
+        });
+
+        Ok(())
+    }
+
+    /// Shows an achievement on the profile. Recording one twice is a no-op,
+    /// so writers don't need to check first.
+    #[access_control(is_writer(&ctx.accounts.config, &writer_program))]
+    pub fn record_badge(
+        ctx: Context<WriteProfile>,
+        writer_program: Pubkey,
+        achievement_id: u16,
+    ) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+        if profile.badges.contains(&achievement_id) {
+            return Ok(());
+        }
+        require_gt!(Profile::MAX_BADGES, profile.badges.len(), ProfileError::TooManyBadges);
+        profile.badges.push(achievement_id);
+
+        emit!(BadgeRecorded {
+            profile: profile.key(),
+            writer_program,
+            achievement_id,
+        });
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<ProfileConfig>(ProfileConfig::discriminator()),
+            Layout::new::<Profile>(Profile::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<ProfileError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+/// Wins per match played, in basis points.
+pub fn win_rate_bps(wins: u32, matches_played: u32) -> u16 {
+    if matches_played == 0 {
+        return 0;
+    }
+    (wins as u64 * 10_000 / matches_played as u64) as u16
+}
+
+fn validate_display_name(display_name: &str) -> Result<()> {
+    require!(!display_name.is_empty(), ProfileError::InvalidDisplayName);
+    require_gte!(
+        Profile::MAX_DISPLAY_NAME_LEN,
+        display_name.len(),
+        ProfileError::InvalidDisplayName
+    );
+    Ok(())
+}
+
+fn is_writer(config: &ProfileConfig, program: &Pubkey) -> Result<()> {
+    require!(config.writers.contains(program), ProfileError::UnknownWriter);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"profile_config"],
+        bump,
+        space = ProfileConfig::SPACE,
+        payer = admin,
+    )]
+    pub config: Account<'info, ProfileConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateWriters<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"profile_config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ProfileConfig>,
+}
+
+#[derive(Accounts)]
+pub struct CreateProfile<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"profile", owner.key.as_ref()],
+        bump,
+        space = Profile::SPACE,
+        payer = owner,
+    )]
+    pub profile: Account<'info, Profile>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateProfile<'info> {
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"profile", owner.key.as_ref()],
+        bump = profile.bump,
+        has_one = owner,
+    )]
+    pub profile: Account<'info, Profile>,
+}
+
+#[derive(Accounts)]
+#[instruction(writer_program: Pubkey)]
+pub struct WriteProfile<'info> {
+    /// PDA of the writer program, verified by seeds
+    #[account(seeds = [PROFILE_WRITER_SEED], bump, seeds::program = writer_program)]
+    pub writer: Signer<'info>,
+    #[account(seeds = [b"profile_config"], bump = config.bump)]
+    pub config: Account<'info, ProfileConfig>,
+    #[account(
+        mut,
+        seeds = [b"profile", profile.owner.as_ref()],
+        bump = profile.bump,
+    )]
+    pub profile: Account<'info, Profile>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct ProfileConfig {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    /// Programs whose `profile_writer` PDA may record matches and badges.
+    pub writers: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl ProfileConfig {
+    pub const MAX_WRITERS: usize = 8;
+
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 4 + 32 * Self::MAX_WRITERS // writers
+        + 1; // bump
+}
+
+impl Versioned for ProfileConfig {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// One wallet's public profile, at `[b"profile", owner]`. The stats are
+/// kept by writer programs, so a profile page reads one account.
+#[account]
+pub struct Profile {
+    pub layout_version: u8,
+    pub owner: Pubkey,
+    pub display_name: String,
+    pub main_character: u8,
+    pub matches_played: u32,
+    pub wins: u32,
+    /// `win_rate_bps(wins, matches_played)`, kept up to date.
+    pub win_rate_bps: u16,
+    /// `Pubkey::default()` until the owner picks one.
+    pub favorite_combo: Pubkey,
+    /// Achievement ids, in the order they were recorded.
+    pub badges: Vec<u16>,
+    pub created_at: i64,
+    pub bump: u8,
+}
+
+impl Profile {
+    pub const MAX_DISPLAY_NAME_LEN: usize = 32;
+    pub const MAX_BADGES: usize = 32;
+
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // owner
+        + 4 + Self::MAX_DISPLAY_NAME_LEN // display_name
+        + 1 // main_character
+        + 4 // matches_played
+        + 4 // wins
+        + 2 // win_rate_bps
+        + 32 // favorite_combo
+        + 4 + 2 * Self::MAX_BADGES // badges
+        + 8 // created_at
+        + 1; // bump
+}
+
+impl Versioned for Profile {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[event]
+pub struct WriterChanged {
+    pub program: Pubkey,
+    pub allowed: bool,
+}
+
+#[event]
+pub struct ProfileUpdated {
+    pub profile: Pubkey,
+    pub owner: Pubkey,
+    pub display_name: String,
+    pub main_character: u8,
+    pub favorite_combo: Pubkey,
+}
+
+#[event]
+pub struct MatchRecorded {
+    pub profile: Pubkey,
+    pub writer_program: Pubkey,
+    pub won: bool,
+    pub matches_played: u32,
+    pub wins: u32,
+}
+
+#[event]
+pub struct BadgeRecorded {
+    pub profile: Pubkey,
+    pub writer_program: Pubkey,
+    pub achievement_id: u16,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum ProfileError {
+    #[msg("Display name must be 1 to 32 bytes")]
+    InvalidDisplayName,
+    #[msg("Program is already a writer")]
+    WriterAlreadyAdded,
+    #[msg("Writer list is full")]
+    TooManyWriters,
+    #[msg("Program is not a writer")]
+    UnknownWriter,
+    #[msg("Profile has no room for more badges")]
+    TooManyBadges,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for ProfileError {
+    fn from(_: Overflow) -> Self {
+        ProfileError::Overflow
+    }
+}
+
+impl From<MigrationError> for ProfileError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => ProfileError::AlreadyMigrated,
+            _ => ProfileError::UnsupportedLayout,
+        }
+    }
+}
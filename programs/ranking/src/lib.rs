@@ -0,0 +1,1020 @@
+// Rust Anchor Program for Player Ratings
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use achievements::program::Achievements;
+use achievements::Achievement;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::Discriminator;
+use checked_math::{add, increment, sub, Overflow};
+use match_result::program::MatchResult;
+use match_result::{MatchAccount, FORMAT_TEAM};
+use profile::program::Profile;
+use profile::{ProfileConfig, PROFILE_WRITER_SEED};
+use season::SeasonState;
+
+declare_id!("RANKING_PROGRAM_ID_HERE");
+
+pub const INITIAL_RATING: u32 = 1200;
+/// Elo K-factor.
+pub const K_FACTOR: u32 = 32;
+/// Largest K-factor `RankingSettings` may give placement matches.
+pub const MAX_K_FACTOR: u32 = 128;
+/// Number of `RankTier`s a rating can fall in once placed.
+pub const PLACED_TIERS: usize = 6;
+
+/// Winner's expected score in permille for rating differences of 0, 25, ...
+/// 800 in the winner's favour, i.e. `1000 / (1 + 10^(-d / 400))` rounded.
+const EXPECTED_PERMILLE: [u32; 33] = [
+    500, 536, 571, 606, 640, 673, 703, 733, 760, 785, 808, 830, 849, 867, 882, 896, 909, 920, 930,
+    939, 947, 954, 960, 965, 969, 973, 977, 980, 983, 985, 987, 989, 990,
+];
+const EXPECTED_STEP: u32 = 25;
+/// Consecutive wins that earn the win streak badge.
+pub const STREAK_BADGE_LENGTH: u32 = 10;
+
+#[program]
+pub mod ranking {
+    use super::*;
+
+    /// Creates the ladder settings, with `admin` able to change them.
+    #[access_control(validate_settings(&settings))]
+    pub fn initialize_config(ctx: Context<InitializeConfig>, settings: RankingSettings) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.layout_version = RankingConfig::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.settings = settings;
+        config.bump = ctx.bumps.config;
+        config.oracle = Pubkey::default();
+        config.latest_epoch = 0;
+        Ok(())
+    }
+
+    /// Changes the ladder settings. Ratings pick up new decay and tier
+    /// bands the next time they're touched.
+    #[access_control(validate_settings(&settings))]
+    pub fn set_settings(ctx: Context<SetSettings>, settings: RankingSettings) -> Result<()> {
+        ctx.accounts.config.settings = settings;
+
+        emit!(SettingsChanged { settings });
+
+        Ok(())
+    }
+
+    /// Sets the key allowed to publish leaderboard snapshots.
+    pub fn set_oracle(ctx: Context<SetSettings>, oracle: Pubkey) -> Result<()> {
+        ctx.accounts.config.oracle = oracle;
+
+        emit!(OracleChanged { oracle });
+
+        Ok(())
+    }
+
+    /// Publishes the full leaderboard for `epoch`, computed off-chain from
+    /// the ratings, as a Merkle root over `snapshot_leaf(epoch, rank,
+    /// wallet, rating)` for ranks 1 to `player_count`. Epochs only go up.
+    pub fn publish_snapshot(
+        ctx: Context<PublishSnapshot>,
+        epoch: u64,
+        merkle_root: [u8; 32],
+        player_count: u32,
+    ) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        require_gt!(epoch, config.latest_epoch, RankingError::StaleEpoch);
+        require_gt!(player_count, 0, RankingError::EmptySnapshot);
+        config.latest_epoch = epoch;
+
+        let snapshot = &mut ctx.accounts.snapshot;
+        snapshot.layout_version = LeaderboardSnapshot::LAYOUT_VERSION;
+        snapshot.epoch = epoch;
+        snapshot.merkle_root = merkle_root;
+        snapshot.player_count = player_count;
+        snapshot.published_at = Clock::get()?.unix_timestamp;
+        snapshot.bump = ctx.bumps.snapshot;
+
+        emit!(SnapshotPublished {
+            epoch,
+            merkle_root,
+            player_count,
+        });
+
+        Ok(())
+    }
+
+    /// Proves the signer held `rank` at `rating` in the snapshot and records
+    /// it in a `RankClaim`, which reward programs read to check
+    /// eligibility. Each player claims an epoch once.
+    pub fn claim_rank(
+        ctx: Context<ClaimRank>,
+        epoch: u64,
+        rank: u32,
+        rating: u32,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let snapshot = &ctx.accounts.snapshot;
+        let player = *ctx.accounts.player.key;
+        require!(
+            rank > 0 && rank <= snapshot.player_count,
+            RankingError::InvalidRank
+        );
+        require!(
+            verify_rank_proof(snapshot, rank, &player, rating, &proof),
+            RankingError::InvalidMerkleProof
+        );
+
+        let claim = &mut ctx.accounts.claim;
+        claim.layout_version = RankClaim::LAYOUT_VERSION;
+        claim.epoch = epoch;
+        claim.player = player;
+        claim.rank = rank;
+        claim.rating = rating;
+        claim.bump = ctx.bumps.claim;
+
+        emit!(RankClaimed {
+            epoch,
+            player,
+            rank,
+            rating,
+        });
+
+        Ok(())
+    }
+
+    pub fn initialize_rating(ctx: Context<InitializeRating>) -> Result<()> {
+        let rating = &mut ctx.accounts.rating;
+
+        rating.layout_version = PlayerRating::LAYOUT_VERSION;
+        rating.player = *ctx.accounts.player.key;
+        rating.rating = INITIAL_RATING;
+        rating.wins = 0;
+        rating.losses = 0;
+        rating.streak = 0;
+        rating.best_streak = 0;
+        rating.last_match_id = 0;
+        rating.bump = ctx.bumps.rating;
+        rating.team_wins = 0;
+        rating.team_losses = 0;
+        rating.knockouts = 0;
+        rating.last_played_at = 0;
+        rating.decay_periods = 0;
+        rating.season = 0;
+        rating.season_games = 0;
+        rating.tier = RankTier::Unranked;
+
+        Ok(())
+    }
+
+    /// Applies a finalized match result to both players. Anyone may crank
+    /// this; the match account's `rating_applied` flag makes it one-shot.
+    /// Team battles move ratings like singles and also count towards each
+    /// player's team record. Players with a profile get the match counted
+    /// on it too.
+    ///
+    /// Both ratings first take any inactivity decay they're owed. A player's
+    /// first `placement_games` matches of a season move their own rating by
+    /// `placement_k_factor` instead of `K_FACTOR`, so the winner's gain and
+    /// the loser's loss can differ.
+    #[access_control(validate_match(&ctx.accounts.match_account))]
+    pub fn apply_match_result(ctx: Context<ApplyMatchResult>) -> Result<()> {
+        let match_id = ctx.accounts.match_account.match_id;
+        let now = Clock::get()?.unix_timestamp;
+        let season = ctx.accounts.season_state.current_season;
+        let settings = &ctx.accounts.config.settings;
+        let winner = &mut ctx.accounts.winner_rating;
+        let loser = &mut ctx.accounts.loser_rating;
+        winner.apply_decay(settings, now);
+        loser.apply_decay(settings, now);
+        winner.enter_season(season);
+        loser.enter_season(season);
+
+        let delta = rating_delta_with_k(
+            winner.k_factor(settings),
+            winner.rating,
+            loser.rating,
+        );
+        let loser_delta = rating_delta_with_k(
+            loser.k_factor(settings),
+            winner.rating,
+            loser.rating,
+        );
+        winner.rating = winner.rating.saturating_add(delta);
+        loser.rating = loser.rating.saturating_sub(loser_delta);
+        winner.record_win(match_id, now)?;
+        loser.record_loss(match_id, now)?;
+        let winner_tier = winner.update_tier(settings);
+        let loser_tier = loser.update_tier(settings);
+        let record = &ctx.accounts.match_account;
+        if record.format == FORMAT_TEAM {
+            let winner_side = record.winner as usize;
+            winner.record_team(true, record.score[winner_side])?;
+            loser.record_team(false, record.score[1 - winner_side])?;
+        }
+
+        let seeds = &[b"rater".as_ref(), &[ctx.bumps.rater]];
+        match_result::cpi::mark_rated(CpiContext::new_with_signer(
+            ctx.accounts.match_result_program.to_account_info(),
+            match_result::cpi::accounts::MarkRated {
+                rater: ctx.accounts.rater.to_account_info(),
+                match_account: ctx.accounts.match_account.to_account_info(),
+            },
+            &[&seeds[..]],
+        ))?;
+
+        emit!(RatingsUpdated {
+            match_id,
+            winner: winner.player,
+            loser: loser.player,
+            winner_rating: winner.rating,
+            loser_rating: loser.rating,
+            delta,
+            loser_delta,
+        });
+        for (rating, changed) in [(winner, winner_tier), (loser, loser_tier)] {
+            if changed {
+                emit!(TierChanged {
+                    player: rating.player,
+                    rating: rating.rating,
+                    tier: rating.tier,
+                });
+            }
+        }
+
+        let bump = ctx.bumps.profile_writer;
+        record_profile_match(&ctx.accounts, &ctx.accounts.winner_profile, bump, true)?;
+        record_profile_match(&ctx.accounts, &ctx.accounts.loser_profile, bump, false)
+    }
+
+    /// Takes the inactivity decay a rating is owed and refreshes its tier,
+    /// for players who haven't played. Anyone may crank this; decay already
+    /// taken is never taken twice.
+    pub fn apply_decay(ctx: Context<ApplyDecay>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let settings = &ctx.accounts.config.settings;
+        let rating = &mut ctx.accounts.rating;
+        let decayed = rating.apply_decay(settings, now);
+        let tier_changed = rating.update_tier(settings);
+        require!(decayed > 0 || tier_changed, RankingError::NothingToDecay);
+
+        emit!(RatingDecayed {
+            player: rating.player,
+            rating: rating.rating,
+            decayed,
+        });
+        if tier_changed {
+            emit!(TierChanged {
+                player: rating.player,
+                rating: rating.rating,
+                tier: rating.tier,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Awards the win streak badge to a player whose best streak has reached
+    /// `STREAK_BADGE_LENGTH`.
+    pub fn award_streak_badge(ctx: Context<AwardStreakBadge>) -> Result<()> {
+        require_gte!(
+            ctx.accounts.rating.best_streak,
+            STREAK_BADGE_LENGTH,
+            RankingError::StreakTooShort
+        );
+        let seeds = &[b"achievement_issuer".as_ref(), &[ctx.bumps.issuer]];
+        achievements::cpi::award_badge(CpiContext::new_with_signer(
+            ctx.accounts.achievements_program.to_account_info(),
+            achievements::cpi::accounts::AwardBadge {
+                issuer: ctx.accounts.issuer.to_account_info(),
+                achievement: ctx.accounts.achievement.to_account_info(),
+                player: ctx.accounts.player.to_account_info(),
+                badge: ctx.accounts.badge.to_account_info(),
+                profile: ctx.accounts.profile.to_account_info(),
+                profile_config: ctx.accounts.profile_config.to_account_info(),
+                profile_writer: ctx.accounts.profile_writer.to_account_info(),
+                profile_program: ctx.accounts.profile_program.to_account_info(),
+                payer: ctx.accounts.payer.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[&seeds[..]],
+        ))
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<PlayerRating>(PlayerRating::discriminator()),
+            Layout::new::<RankingConfig>(RankingConfig::discriminator()),
+            Layout::new::<LeaderboardSnapshot>(LeaderboardSnapshot::discriminator()),
+            Layout::new::<RankClaim>(RankClaim::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<RankingError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+/// Points moved from loser to winner, rounded to the nearest integer.
+pub fn rating_delta(winner_rating: u32, loser_rating: u32) -> u32 {
+    rating_delta_with_k(K_FACTOR, winner_rating, loser_rating)
+}
+
+/// `rating_delta` for a K-factor of `k`.
+pub fn rating_delta_with_k(k: u32, winner_rating: u32, loser_rating: u32) -> u32 {
+    let expected = expected_permille(winner_rating as i64 - loser_rating as i64);
+    (k * (1000 - expected) + 500) / 1000
+}
+
+/// Placed tier for `rating`: the highest tier whose floor it reaches.
+pub fn tier_for(rating: u32, tier_floors: &[u32; PLACED_TIERS - 1]) -> RankTier {
+    let reached = tier_floors.iter().filter(|floor| rating >= **floor).count();
+    RankTier::PLACED[reached]
+}
+
+/// Expected score in permille for a player rated `diff` above the opponent,
+/// interpolated linearly between table entries and clamped at +/-800.
+pub fn expected_permille(diff: i64) -> u32 {
+    let magnitude = diff.unsigned_abs().min(800) as u32;
+    let index = (magnitude / EXPECTED_STEP) as usize;
+    let remainder = magnitude % EXPECTED_STEP;
+    let low = EXPECTED_PERMILLE[index];
+    let high = EXPECTED_PERMILLE[(index + 1).min(EXPECTED_PERMILLE.len() - 1)];
+    let favoured = low + (high - low) * remainder / EXPECTED_STEP;
+    if diff >= 0 {
+        favoured
+    } else {
+        1000 - favoured
+    }
+}
+
+/// Leaf of a leaderboard snapshot. The epoch is hashed in so a proof from
+/// one snapshot can't be replayed against another.
+pub fn snapshot_leaf(epoch: u64, rank: u32, wallet: &Pubkey, rating: u32) -> [u8; 32] {
+    hashv(&[
+        &epoch.to_le_bytes(),
+        &rank.to_le_bytes(),
+        wallet.as_ref(),
+        &rating.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Whether `proof` shows `wallet` at `rank` and `rating` in `snapshot`.
+pub fn verify_rank_proof(
+    snapshot: &LeaderboardSnapshot,
+    rank: u32,
+    wallet: &Pubkey,
+    rating: u32,
+    proof: &[[u8; 32]],
+) -> bool {
+    let leaf = snapshot_leaf(snapshot.epoch, rank, wallet, rating);
+    verify_merkle_proof(proof, snapshot.merkle_root, leaf)
+}
+
+/// Checks `leaf` against `root`, hashing each pair in sorted order so proofs
+/// carry no left/right flags.
+fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            hashv(&[&node, sibling]).to_bytes()
+        } else {
+            hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+    computed == root
+}
+
+/// Counts the match on `profile`, signed by this program's
+/// `profile_writer` PDA, if the player made one.
+fn record_profile_match<'info>(
+    accounts: &ApplyMatchResult<'info>,
+    profile: &UncheckedAccount<'info>,
+    bump: u8,
+    won: bool,
+) -> Result<()> {
+    if profile.owner != &profile::ID {
+        return Ok(());
+    }
+    let seeds = &[PROFILE_WRITER_SEED, &[bump]];
+    profile::cpi::record_match(
+        CpiContext::new_with_signer(
+            accounts.profile_program.to_account_info(),
+            profile::cpi::accounts::WriteProfile {
+                writer: accounts.profile_writer.to_account_info(),
+                config: accounts.profile_config.to_account_info(),
+                profile: profile.to_account_info(),
+            },
+            &[&seeds[..]],
+        ),
+        crate::ID,
+        won,
+    )
+}
+
+fn validate_settings(settings: &RankingSettings) -> Result<()> {
+    require_gte!(settings.decay_after, 0, RankingError::InvalidSettings);
+    require_gt!(settings.decay_period, 0, RankingError::InvalidSettings);
+    require_gte!(settings.placement_k_factor, K_FACTOR, RankingError::InvalidSettings);
+    require_gte!(MAX_K_FACTOR, settings.placement_k_factor, RankingError::InvalidSettings);
+    require!(
+        settings.tier_floors.windows(2).all(|pair| pair[0] < pair[1]),
+        RankingError::InvalidSettings
+    );
+    Ok(())
+}
+
+fn validate_match(match_account: &MatchAccount) -> Result<()> {
+    require!(match_account.finalized, RankingError::MatchNotFinalized);
+    require!(!match_account.rating_applied, RankingError::AlreadyApplied);
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"ranking_config"],
+        bump,
+        space = RankingConfig::SPACE,
+        payer = admin,
+    )]
+    pub config: Account<'info, RankingConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetSettings<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"ranking_config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, RankingConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct PublishSnapshot<'info> {
+    #[account(mut)]
+    pub oracle: Signer<'info>,
+    #[account(mut, seeds = [b"ranking_config"], bump = config.bump, has_one = oracle)]
+    pub config: Account<'info, RankingConfig>,
+    #[account(
+        init,
+        seeds = [b"snapshot", &epoch.to_le_bytes()],
+        bump,
+        space = LeaderboardSnapshot::SPACE,
+        payer = oracle,
+    )]
+    pub snapshot: Account<'info, LeaderboardSnapshot>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct ClaimRank<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(seeds = [b"snapshot", &epoch.to_le_bytes()], bump = snapshot.bump)]
+    pub snapshot: Account<'info, LeaderboardSnapshot>,
+    #[account(
+        init,
+        seeds = [b"rank_claim".as_ref(), &epoch.to_le_bytes(), player.key.as_ref()],
+        bump,
+        space = RankClaim::SPACE,
+        payer = player,
+    )]
+    pub claim: Account<'info, RankClaim>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeRating<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"rating", player.key.as_ref()],
+        bump,
+        space = PlayerRating::SPACE,
+        payer = player,
+    )]
+    pub rating: Account<'info, PlayerRating>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyMatchResult<'info> {
+    #[account(
+        mut,
+        seeds = [b"match", &match_account.match_id.to_le_bytes()],
+        bump = match_account.bump,
+        seeds::program = match_result::ID,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    #[account(
+        mut,
+        seeds = [b"rating", match_account.winner_key().as_ref()],
+        bump = winner_rating.bump,
+    )]
+    pub winner_rating: Account<'info, PlayerRating>,
+    #[account(
+        mut,
+        seeds = [b"rating", match_account.loser_key().as_ref()],
+        bump = loser_rating.bump,
+    )]
+    pub loser_rating: Account<'info, PlayerRating>,
+    #[account(seeds = [b"ranking_config"], bump = config.bump)]
+    pub config: Account<'info, RankingConfig>,
+    #[account(seeds = [b"season_state"], bump = season_state.bump, seeds::program = season::ID)]
+    pub season_state: Account<'info, SeasonState>,
+    /// CHECK: signing PDA for `match_result::mark_rated`
+    #[account(seeds = [b"rater"], bump)]
+    pub rater: UncheckedAccount<'info>,
+    pub match_result_program: Program<'info, MatchResult>,
+    /// CHECK: the winner's profile PDA, verified by seeds; only written
+    /// when the profile program owns it, i.e. the winner made a profile
+    #[account(
+        mut,
+        seeds = [b"profile", match_account.winner_key().as_ref()],
+        bump,
+        seeds::program = profile::ID,
+    )]
+    pub winner_profile: UncheckedAccount<'info>,
+    /// CHECK: the loser's profile PDA, like `winner_profile`
+    #[account(
+        mut,
+        seeds = [b"profile", match_account.loser_key().as_ref()],
+        bump,
+        seeds::program = profile::ID,
+    )]
+    pub loser_profile: UncheckedAccount<'info>,
+    #[account(seeds = [b"profile_config"], bump = profile_config.bump, seeds::program = profile::ID)]
+    pub profile_config: Account<'info, ProfileConfig>,
+    /// CHECK: signing PDA for `profile::record_match`
+    #[account(seeds = [PROFILE_WRITER_SEED], bump)]
+    pub profile_writer: UncheckedAccount<'info>,
+    pub profile_program: Program<'info, Profile>,
+}
+
+#[derive(Accounts)]
+pub struct ApplyDecay<'info> {
+    #[account(mut, seeds = [b"rating", rating.player.as_ref()], bump = rating.bump)]
+    pub rating: Account<'info, PlayerRating>,
+    #[account(seeds = [b"ranking_config"], bump = config.bump)]
+    pub config: Account<'info, RankingConfig>,
+}
+
+#[derive(Accounts)]
+pub struct AwardStreakBadge<'info> {
+    #[account(seeds = [b"rating", player.key.as_ref()], bump = rating.bump)]
+    pub rating: Account<'info, PlayerRating>,
+    /// Owner of `rating`, verified by seeds
+    pub player: SystemAccount<'info>,
+    /// CHECK: signing PDA for `achievements::award_badge`
+    #[account(seeds = [b"achievement_issuer"], bump)]
+    pub issuer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"achievement", &achievements::WIN_STREAK.to_le_bytes()],
+        bump = achievement.bump,
+        seeds::program = achievements::ID,
+    )]
+    pub achievement: Account<'info, Achievement>,
+    /// CHECK: created by the achievements program
+    #[account(mut)]
+    pub badge: UncheckedAccount<'info>,
+    /// CHECK: the player's profile, checked by `achievements::award_badge`
+    #[account(mut)]
+    pub profile: UncheckedAccount<'info>,
+    /// CHECK: checked by `achievements::award_badge`
+    pub profile_config: UncheckedAccount<'info>,
+    /// CHECK: the achievements program's signing PDA for
+    /// `profile::record_badge`, checked by `achievements::award_badge`
+    pub profile_writer: UncheckedAccount<'info>,
+    pub profile_program: Program<'info, Profile>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub achievements_program: Program<'info, Achievements>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RankingSettings {
+    /// Seconds without a match before a rating starts to decay.
+    pub decay_after: i64,
+    /// Seconds per decay step after that.
+    pub decay_period: i64,
+    /// Rating lost per decay step; zero turns decay off.
+    pub decay_per_period: u32,
+    /// Decay never takes a rating below this.
+    pub decay_floor: u32,
+    /// Matches per season played at `placement_k_factor`; zero for none.
+    pub placement_games: u32,
+    pub placement_k_factor: u32,
+    /// Lowest rating of Silver, Gold, Platinum, Diamond and Master, in
+    /// increasing order. Anything below is Bronze.
+    pub tier_floors: [u32; PLACED_TIERS - 1],
+}
+
+impl RankingSettings {
+    pub const SIZE: usize = 8 + 8 + 4 + 4 + 4 + 4 + 4 * (PLACED_TIERS - 1);
+}
+
+#[account]
+pub struct RankingConfig {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    pub settings: RankingSettings,
+    pub bump: u8,
+    /// Publishes leaderboard snapshots; the default key until `set_oracle`.
+    pub oracle: Pubkey,
+    /// Epoch of the newest snapshot, 0 before the first.
+    pub latest_epoch: u64,
+}
+
+impl RankingConfig {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + RankingSettings::SIZE // settings
+        + 1 // bump
+        + 32 // oracle
+        + 8; // latest_epoch
+}
+
+impl Versioned for RankingConfig {
+    const LAYOUT_VERSION: u8 = 2;
+    const MIGRATIONS: &'static [Step] = &[Step {
+        from: 1,
+        grow: 32 + 8,
+        upgrade: append_snapshot_oracle,
+    }];
+}
+
+/// Layout 2 appends the snapshot oracle, unset, and the latest epoch.
+fn append_snapshot_oracle(_: &mut [u8]) {}
+
+/// Merkle root of the leaderboard at an epoch, at `[b"snapshot", epoch]`.
+#[account]
+pub struct LeaderboardSnapshot {
+    pub layout_version: u8,
+    pub epoch: u64,
+    pub merkle_root: [u8; 32],
+    pub player_count: u32,
+    pub published_at: i64,
+    pub bump: u8,
+}
+
+impl LeaderboardSnapshot {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 8 // epoch
+        + 32 // merkle_root
+        + 4 // player_count
+        + 8 // published_at
+        + 1; // bump
+}
+
+impl Versioned for LeaderboardSnapshot {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// A proven snapshot rank, at `[b"rank_claim", epoch, player]`.
+#[account]
+pub struct RankClaim {
+    pub layout_version: u8,
+    pub epoch: u64,
+    pub player: Pubkey,
+    /// 1 is the top of the leaderboard.
+    pub rank: u32,
+    pub rating: u32,
+    pub bump: u8,
+}
+
+impl RankClaim {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 8 // epoch
+        + 32 // player
+        + 4 // rank
+        + 4 // rating
+        + 1; // bump
+}
+
+impl Versioned for RankClaim {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// Rank shown for a rating. Players are `Unranked` until they finish the
+/// season's placement matches.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RankTier {
+    Unranked,
+    Bronze,
+    Silver,
+    Gold,
+    Platinum,
+    Diamond,
+    Master,
+}
+
+impl RankTier {
+    pub const PLACED: [RankTier; PLACED_TIERS] = [
+        RankTier::Bronze,
+        RankTier::Silver,
+        RankTier::Gold,
+        RankTier::Platinum,
+        RankTier::Diamond,
+        RankTier::Master,
+    ];
+}
+
+#[account]
+pub struct PlayerRating {
+    pub layout_version: u8,
+    pub player: Pubkey,
+    pub rating: u32,
+    pub wins: u32,
+    pub losses: u32,
+    /// Positive for consecutive wins, negative for consecutive losses.
+    pub streak: i32,
+    pub best_streak: u32,
+    pub last_match_id: u64,
+    pub bump: u8,
+    /// Team battles won and lost; also counted in `wins` and `losses`.
+    pub team_wins: u32,
+    pub team_losses: u32,
+    /// Opposing characters knocked out across team battles.
+    pub knockouts: u32,
+    /// Zero until the first rated match.
+    pub last_played_at: i64,
+    /// Decay steps already taken since `last_played_at`.
+    pub decay_periods: u32,
+    /// Season `season_games` counts towards.
+    pub season: u32,
+    pub season_games: u32,
+    pub tier: RankTier,
+}
+
+impl PlayerRating {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // player
+        + 4 // rating
+        + 4 // wins
+        + 4 // losses
+        + 4 // streak
+        + 4 // best_streak
+        + 8 // last_match_id
+        + 1 // bump
+        + 4 // team_wins
+        + 4 // team_losses
+        + 4 // knockouts
+        + 8 // last_played_at
+        + 4 // decay_periods
+        + 4 // season
+        + 4 // season_games
+        + 1; // tier
+
+    /// Lowers the rating by the decay steps it has become owed since the
+    /// last time, down to `decay_floor`. Returns the rating lost.
+    fn apply_decay(&mut self, settings: &RankingSettings, now: i64) -> u32 {
+        if self.last_played_at == 0 {
+            return 0;
+        }
+        let idle = now - self.last_played_at - settings.decay_after;
+        if idle < 0 {
+            return 0;
+        }
+        let owed = (idle / settings.decay_period + 1).min(u32::MAX as i64) as u32;
+        let steps = owed.saturating_sub(self.decay_periods);
+        self.decay_periods = owed;
+        let floor = settings.decay_floor.min(self.rating);
+        let decayed = settings
+            .decay_per_period
+            .saturating_mul(steps)
+            .min(self.rating - floor);
+        self.rating -= decayed;
+        decayed
+    }
+
+    /// Starts the player's placement matches when `season` is new to them.
+    fn enter_season(&mut self, season: u32) {
+        if self.season != season {
+            self.season = season;
+            self.season_games = 0;
+        }
+    }
+
+    fn placed(&self, settings: &RankingSettings) -> bool {
+        self.season_games >= settings.placement_games
+    }
+
+    fn k_factor(&self, settings: &RankingSettings) -> u32 {
+        if self.placed(settings) {
+            K_FACTOR
+        } else {
+            settings.placement_k_factor
+        }
+    }
+
+    /// Sets `tier` from the rating, returning whether it changed.
+    fn update_tier(&mut self, settings: &RankingSettings) -> bool {
+        let tier = if self.placed(settings) {
+            tier_for(self.rating, &settings.tier_floors)
+        } else {
+            RankTier::Unranked
+        };
+        let changed = tier != self.tier;
+        self.tier = tier;
+        changed
+    }
+
+    fn record_played(&mut self, match_id: u64, now: i64) -> Result<()> {
+        increment(&mut self.season_games).map_err(RankingError::from)?;
+        self.last_match_id = match_id;
+        self.last_played_at = now;
+        self.decay_periods = 0;
+        Ok(())
+    }
+
+    fn record_win(&mut self, match_id: u64, now: i64) -> Result<()> {
+        increment(&mut self.wins).map_err(RankingError::from)?;
+        self.streak = if self.streak > 0 {
+            add(self.streak, 1).map_err(RankingError::from)?
+        } else {
+            1
+        };
+        self.best_streak = self.best_streak.max(self.streak as u32);
+        self.record_played(match_id, now)
+    }
+
+    fn record_loss(&mut self, match_id: u64, now: i64) -> Result<()> {
+        increment(&mut self.losses).map_err(RankingError::from)?;
+        self.streak = if self.streak < 0 {
+            sub(self.streak, 1).map_err(RankingError::from)?
+        } else {
+            -1
+        };
+        self.record_played(match_id, now)
+    }
+
+    fn record_team(&mut self, won: bool, knockouts: u8) -> Result<()> {
+        if won {
+            increment(&mut self.team_wins).map_err(RankingError::from)?;
+        } else {
+            increment(&mut self.team_losses).map_err(RankingError::from)?;
+        }
+        self.knockouts = add(self.knockouts, knockouts as u32).map_err(RankingError::from)?;
+        Ok(())
+    }
+}
+
+impl Versioned for PlayerRating {
+    const LAYOUT_VERSION: u8 = 3;
+    const MIGRATIONS: &'static [Step] = &[
+        ADD_LAYOUT_VERSION,
+        Step {
+            from: 1,
+            grow: 4 * 3,
+            upgrade: append_team_record,
+        },
+        Step {
+            from: 2,
+            grow: 8 + 4 + 4 + 4 + 1,
+            upgrade: append_season_progress,
+        },
+    ];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+/// Layout 2 appends the team record, which starts at zero.
+fn append_team_record(_: &mut [u8]) {}
+
+/// Layout 3 appends decay and placement progress. Zeroed, the rating has no
+/// decay clock until its next match and is `Unranked` in season 0.
+fn append_season_progress(_: &mut [u8]) {}
+
+#[event]
+pub struct RatingsUpdated {
+    pub match_id: u64,
+    pub winner: Pubkey,
+    pub loser: Pubkey,
+    pub winner_rating: u32,
+    pub loser_rating: u32,
+    /// Rating the winner gained.
+    pub delta: u32,
+    /// Rating the loser lost; differs from `delta` during placements.
+    pub loser_delta: u32,
+}
+
+#[event]
+pub struct RatingDecayed {
+    pub player: Pubkey,
+    pub rating: u32,
+    pub decayed: u32,
+}
+
+#[event]
+pub struct TierChanged {
+    pub player: Pubkey,
+    pub rating: u32,
+    pub tier: RankTier,
+}
+
+#[event]
+pub struct OracleChanged {
+    pub oracle: Pubkey,
+}
+
+#[event]
+pub struct SnapshotPublished {
+    pub epoch: u64,
+    pub merkle_root: [u8; 32],
+    pub player_count: u32,
+}
+
+#[event]
+pub struct RankClaimed {
+    pub epoch: u64,
+    pub player: Pubkey,
+    pub rank: u32,
+    pub rating: u32,
+}
+
+#[event]
+pub struct SettingsChanged {
+    pub settings: RankingSettings,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum RankingError {
+    #[msg("Match result is not finalized")]
+    MatchNotFinalized,
+    #[msg("Match result has already been applied")]
+    AlreadyApplied,
+    #[msg("Best streak is too short for the badge")]
+    StreakTooShort,
+    #[msg("Ranking settings are out of range")]
+    InvalidSettings,
+    #[msg("Rating has no decay owed and its tier is current")]
+    NothingToDecay,
+    #[msg("Snapshot epoch is not newer than the latest")]
+    StaleEpoch,
+    #[msg("Snapshot has no players")]
+    EmptySnapshot,
+    #[msg("Rank is outside the snapshot")]
+    InvalidRank,
+    #[msg("Merkle proof does not match the snapshot")]
+    InvalidMerkleProof,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for RankingError {
+    fn from(_: Overflow) -> Self {
+        RankingError::Overflow
+    }
+}
+
+impl From<MigrationError> for RankingError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => RankingError::AlreadyMigrated,
+            _ => RankingError::UnsupportedLayout,
+        }
+    }
+}
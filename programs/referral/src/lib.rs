@@ -0,0 +1,470 @@
+// Rust Anchor Program for Referrals
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{Layout, MigrationError, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_lang::Discriminator;
+use checked_math::{self as checked, add_assign, increment, Overflow};
+
+declare_id!("REFERRAL_PROGRAM_ID_HERE");
+
+/// Basis points in 100%.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+/// Highest share of a fee the admin can send to referrers.
+pub const MAX_REFERRAL_BPS: u16 = 2_000;
+
+#[program]
+pub mod referral {
+    use super::*;
+
+    #[access_control(validate_rates(entry_fee_bps))]
+    pub fn initialize(ctx: Context<Initialize>, entry_fee_bps: u16) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.layout_version = ReferralConfig::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.entry_fee_bps = entry_fee_bps;
+        config.bump = ctx.bumps.config;
+        Ok(())
+    }
+
+    /// Sets the share of each kind of fee paid to referrers from now on.
+    #[access_control(validate_rates(entry_fee_bps))]
+    pub fn set_rates(ctx: Context<SetRates>, entry_fee_bps: u16) -> Result<()> {
+        ctx.accounts.config.entry_fee_bps = entry_fee_bps;
+
+        emit!(RatesChanged { entry_fee_bps });
+
+        Ok(())
+    }
+
+    /// Claims `code` for the signer. Codes are unique regardless of case.
+    #[access_control(validate_code(&code))]
+    pub fn create_code(ctx: Context<CreateCode>, code: String) -> Result<()> {
+        let referral_code = &mut ctx.accounts.referral_code;
+        referral_code.layout_version = ReferralCode::LAYOUT_VERSION;
+        referral_code.owner = *ctx.accounts.owner.key;
+        referral_code.code = code;
+        referral_code.referred_count = 0;
+        referral_code.claimable = 0;
+        referral_code.total_earned = 0;
+        referral_code.bump = ctx.bumps.referral_code;
+
+        emit!(CodeCreated {
+            referral_code: referral_code.key(),
+            owner: referral_code.owner,
+            code: referral_code.code.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Records that the signer was referred by the owner of `referral_code`.
+    /// A wallet registers once, can't use its own code, and can't use the
+    /// code of a wallet it referred itself.
+    #[access_control(validate_referral(&ctx))]
+    pub fn register_referral(ctx: Context<RegisterReferral>) -> Result<()> {
+        let referral_code = &mut ctx.accounts.referral_code;
+        increment(&mut referral_code.referred_count).map_err(ReferralError::from)?;
+
+        let referral = &mut ctx.accounts.referral;
+        referral.layout_version = Referral::LAYOUT_VERSION;
+        referral.player = *ctx.accounts.player.key;
+        referral.referrer = referral_code.owner;
+        referral.code = referral_code.key();
+        referral.registered_at = Clock::get()?.unix_timestamp;
+        referral.bump = ctx.bumps.referral;
+
+        emit!(ReferralRegistered {
+            player: referral.player,
+            referrer: referral.referrer,
+            referral_code: referral.code,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the referrer's share of a `fee` the signer is being charged,
+    /// and returns it so the calling program can take the rest. Fee-taking
+    /// programs CPI this for referred players.
+    pub fn pay_referral(ctx: Context<PayReferral>, kind: FeeKind, fee: u64) -> Result<u64> {
+        let share = ctx.accounts.config.share(kind, fee);
+        if share == 0 {
+            return Ok(0);
+        }
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.player.to_account_info(),
+                    to: ctx.accounts.referral_code.to_account_info(),
+                },
+            ),
+            share,
+        )?;
+        let referral_code = &mut ctx.accounts.referral_code;
+        add_assign(&mut referral_code.claimable, share).map_err(ReferralError::from)?;
+        add_assign(&mut referral_code.total_earned, share).map_err(ReferralError::from)?;
+
+        emit!(ReferralPaid {
+            referral_code: referral_code.key(),
+            player: *ctx.accounts.player.key,
+            kind,
+            fee,
+            share,
+        });
+
+        Ok(share)
+    }
+
+    /// Pays out everything the code has earned since the last claim.
+    pub fn claim(ctx: Context<Claim>) -> Result<()> {
+        let amount = ctx.accounts.referral_code.claimable;
+        require_gt!(amount, 0, ReferralError::NothingToClaim);
+        let info = ctx.accounts.referral_code.to_account_info();
+        {
+            let mut from = info.try_borrow_mut_lamports()?;
+            let mut to = ctx.accounts.owner.try_borrow_mut_lamports()?;
+            checked::move_lamports(&mut from, &mut to, amount).map_err(ReferralError::from)?;
+        }
+        ctx.accounts.referral_code.claimable = 0;
+
+        emit!(ReferralClaimed {
+            referral_code: info.key(),
+            owner: *ctx.accounts.owner.key,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<ReferralConfig>(ReferralConfig::discriminator()),
+            Layout::new::<ReferralCode>(ReferralCode::discriminator()),
+            Layout::new::<Referral>(Referral::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<ReferralError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+/// Seed for a `ReferralCode`: the hash of the lowercased code.
+pub fn code_seed(code: &str) -> [u8; 32] {
+    hash(code.to_ascii_lowercase().as_bytes()).to_bytes()
+}
+
+fn validate_rates(entry_fee_bps: u16) -> Result<()> {
+    require_gte!(MAX_REFERRAL_BPS, entry_fee_bps, ReferralError::RateTooHigh);
+    Ok(())
+}
+
+fn validate_code(code: &str) -> Result<()> {
+    require!(
+        (ReferralCode::MIN_CODE_LEN..=ReferralCode::MAX_CODE_LEN).contains(&code.len())
+            && code.bytes().all(|b| b.is_ascii_alphanumeric()),
+        ReferralError::InvalidCode
+    );
+    Ok(())
+}
+
+/// `referrer_referral` is the referrer's own `Referral`, which only exists
+/// if someone referred them.
+fn validate_referral(ctx: &Context<RegisterReferral>) -> Result<()> {
+    let player = ctx.accounts.player.key;
+    require_keys_neq!(ctx.accounts.referral_code.owner, *player, ReferralError::SelfReferral);
+    let info = &ctx.accounts.referrer_referral;
+    if info.owner == &crate::ID {
+        let referrer_referral = Referral::try_deserialize(&mut &info.data.borrow()[..])?;
+        require_keys_neq!(referrer_referral.referrer, *player, ReferralError::SelfReferral);
+    }
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"referral_config"],
+        bump,
+        space = ReferralConfig::SPACE,
+        payer = admin,
+    )]
+    pub config: Account<'info, ReferralConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRates<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"referral_config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, ReferralConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(code: String)]
+pub struct CreateCode<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"referral_code".as_ref(), &code_seed(&code)],
+        bump,
+        space = ReferralCode::SPACE,
+        payer = owner,
+    )]
+    pub referral_code: Account<'info, ReferralCode>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferral<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"referral_code", &code_seed(&referral_code.code)],
+        bump = referral_code.bump,
+    )]
+    pub referral_code: Account<'info, ReferralCode>,
+    #[account(
+        init,
+        seeds = [b"referral", player.key.as_ref()],
+        bump,
+        space = Referral::SPACE,
+        payer = player,
+    )]
+    pub referral: Account<'info, Referral>,
+    /// CHECK: the referrer's `Referral` PDA, verified by seeds; read only
+    /// if it exists
+    #[account(seeds = [b"referral", referral_code.owner.as_ref()], bump)]
+    pub referrer_referral: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PayReferral<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(
+        seeds = [b"referral", player.key.as_ref()],
+        bump = referral.bump,
+        has_one = player,
+    )]
+    pub referral: Account<'info, Referral>,
+    #[account(seeds = [b"referral_config"], bump = config.bump)]
+    pub config: Account<'info, ReferralConfig>,
+    #[account(mut, address = referral.code)]
+    pub referral_code: Account<'info, ReferralCode>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"referral_code", &code_seed(&referral_code.code)],
+        bump = referral_code.bump,
+        has_one = owner,
+    )]
+    pub referral_code: Account<'info, ReferralCode>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+/// What a fee passed to `pay_referral` was charged for. Entry fees are the
+/// only fees the programs charge players; a kind, with its rate in
+/// `ReferralConfig`, comes with the first program to charge another.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeeKind {
+    /// Tournament and other competition entry fees.
+    Entry,
+}
+
+#[account]
+pub struct ReferralConfig {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    pub entry_fee_bps: u16,
+    pub bump: u8,
+}
+
+impl ReferralConfig {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 2 // entry_fee_bps
+        + 1; // bump
+
+    /// The referrer's part of a `kind` fee of `fee` lamports.
+    pub fn share(&self, kind: FeeKind, fee: u64) -> u64 {
+        let bps = match kind {
+            FeeKind::Entry => self.entry_fee_bps,
+        };
+        (fee as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64
+    }
+}
+
+impl Versioned for ReferralConfig {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// A referrer's code, at `[b"referral_code", code_seed(code)]`. Shares
+/// paid to it are held in its own lamports until `claim`.
+#[account]
+pub struct ReferralCode {
+    pub layout_version: u8,
+    pub owner: Pubkey,
+    pub code: String,
+    pub referred_count: u32,
+    /// Lamports held above rent, owed to `owner`.
+    pub claimable: u64,
+    pub total_earned: u64,
+    pub bump: u8,
+}
+
+impl ReferralCode {
+    pub const MIN_CODE_LEN: usize = 3;
+    pub const MAX_CODE_LEN: usize = 16;
+
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // owner
+        + 4 + Self::MAX_CODE_LEN // code
+        + 4 // referred_count
+        + 8 // claimable
+        + 8 // total_earned
+        + 1; // bump
+}
+
+impl Versioned for ReferralCode {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// Who referred `player`, at `[b"referral", player]`.
+#[account]
+pub struct Referral {
+    pub layout_version: u8,
+    pub player: Pubkey,
+    pub referrer: Pubkey,
+    pub code: Pubkey,
+    pub registered_at: i64,
+    pub bump: u8,
+}
+
+impl Referral {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // player
+        + 32 // referrer
+        + 32 // code
+        + 8 // registered_at
+        + 1; // bump
+}
+
+impl Versioned for Referral {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[event]
+pub struct RatesChanged {
+    pub entry_fee_bps: u16,
+}
+
+#[event]
+pub struct CodeCreated {
+    pub referral_code: Pubkey,
+    pub owner: Pubkey,
+    pub code: String,
+}
+
+#[event]
+pub struct ReferralRegistered {
+    pub player: Pubkey,
+    pub referrer: Pubkey,
+    pub referral_code: Pubkey,
+}
+
+#[event]
+pub struct ReferralPaid {
+    pub referral_code: Pubkey,
+    pub player: Pubkey,
+    pub kind: FeeKind,
+    pub fee: u64,
+    pub share: u64,
+}
+
+#[event]
+pub struct ReferralClaimed {
+    pub referral_code: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum ReferralError {
+    #[msg("Referral rate is above the maximum")]
+    RateTooHigh,
+    #[msg("Code must be 3 to 16 letters or digits")]
+    InvalidCode,
+    #[msg("Wallet cannot refer itself")]
+    SelfReferral,
+    #[msg("Nothing to claim")]
+    NothingToClaim,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for ReferralError {
+    fn from(_: Overflow) -> Self {
+        ReferralError::Overflow
+    }
+}
+
+impl From<MigrationError> for ReferralError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => ReferralError::AlreadyMigrated,
+            _ => ReferralError::UnsupportedLayout,
+        }
+    }
+}
@@ -0,0 +1,203 @@
+// Rust Anchor Program for Replay Anchoring
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+declare_id!("REPLAY_REGISTRY_PROGRAM_ID_HERE");
+
+/// Replay format versions accepted by `finalize_replay` (see the `replay` crate).
+pub const MIN_REPLAY_FORMAT_VERSION: u16 = 1;
+pub const REPLAY_FORMAT_VERSION: u16 = 2;
+
+#[program]
+pub mod replay_registry {
+    use super::*;
+
+    #[access_control(validate_replay(&ctx, &players, frame_count, format_version))]
+    pub fn finalize_replay(
+        ctx: Context<FinalizeReplay>,
+        match_id: u64,
+        players: [Pubkey; 2],
+        character_ids: [u8; 2],
+        seed: u64,
+        game_version: u32,
+        format_version: u16,
+        frame_count: u32,
+        replay_hash: [u8; 32],
+    ) -> Result<()> {
+        let record = &mut ctx.accounts.replay;
+
+        record.layout_version = ReplayRecord::LAYOUT_VERSION;
+        record.match_id = match_id;
+        record.submitter = *ctx.accounts.submitter.key;
+        record.players = players;
+        record.character_ids = character_ids;
+        record.seed = seed;
+        record.game_version = game_version;
+        record.format_version = format_version;
+        record.frame_count = frame_count;
+        record.replay_hash = replay_hash;
+        record.finalized_at = Clock::get()?.unix_timestamp;
+        record.bump = ctx.bumps.replay;
+
+        emit!(ReplayFinalized {
+            replay: record.key(),
+            match_id,
+            submitter: record.submitter,
+            replay_hash,
+            frame_count,
+            timestamp: record.finalized_at,
+        });
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [Layout::new::<ReplayRecord>(ReplayRecord::discriminator())];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<ReplayError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+fn validate_replay(
+    ctx: &Context<FinalizeReplay>,
+    players: &[Pubkey; 2],
+    frame_count: u32,
+    format_version: u16,
+) -> Result<()> {
+    let submitter = ctx.accounts.submitter.key;
+    require!(
+        players[0] == *submitter || players[1] == *submitter,
+        ReplayError::NotAParticipant
+    );
+    require_neq!(players[0], players[1], ReplayError::InvalidPlayers);
+    require_gt!(frame_count, 0, ReplayError::EmptyReplay);
+    require!(
+        (MIN_REPLAY_FORMAT_VERSION..=REPLAY_FORMAT_VERSION).contains(&format_version),
+        ReplayError::UnsupportedFormat
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(match_id: u64)]
+pub struct FinalizeReplay<'info> {
+    #[account(mut)]
+    pub submitter: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"replay".as_ref(), &match_id.to_le_bytes()],
+        bump,
+        space = ReplayRecord::SPACE,
+        payer = submitter,
+    )]
+    pub replay: Account<'info, ReplayRecord>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct ReplayRecord {
+    pub layout_version: u8,
+    pub match_id: u64,
+    pub submitter: Pubkey,
+    pub players: [Pubkey; 2],
+    pub character_ids: [u8; 2],
+    pub seed: u64,
+    pub game_version: u32,
+    pub format_version: u16,
+    pub frame_count: u32,
+    pub replay_hash: [u8; 32],
+    pub finalized_at: i64,
+    pub bump: u8,
+}
+
+impl ReplayRecord {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 8 // match_id
+        + 32 // submitter
+        + 32 * 2 // players
+        + 2 // character_ids
+        + 8 // seed
+        + 4 // game_version
+        + 2 // format_version
+        + 4 // frame_count
+        + 32 // replay_hash
+        + 8 // finalized_at
+        + 1; // bump
+}
+
+impl Versioned for ReplayRecord {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[event]
+pub struct ReplayFinalized {
+    pub replay: Pubkey,
+    pub match_id: u64,
+    pub submitter: Pubkey,
+    pub replay_hash: [u8; 32],
+    pub frame_count: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum ReplayError {
+    #[msg("Submitter is not a player in this match")]
+    NotAParticipant,
+    #[msg("Players must be distinct")]
+    InvalidPlayers,
+    #[msg("Replay has no frames")]
+    EmptyReplay,
+    #[msg("Unsupported replay format version")]
+    UnsupportedFormat,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<MigrationError> for ReplayError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => ReplayError::AlreadyMigrated,
+            _ => ReplayError::UnsupportedLayout,
+        }
+    }
+}
@@ -0,0 +1,376 @@
+// Rust Anchor Program for Merkle Reward Distribution
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use checked_math::{add, add_assign, Overflow};
+
+
+declare_id!("REWARDS_PROGRAM_ID_HERE");
+
+/// Claims tracked by one bitmap account.
+pub const CLAIMS_PER_BITMAP: u32 = 8 * ClaimBitmap::BYTES as u32;
+
+#[program]
+pub mod rewards {
+    use super::*;
+
+    /// Publishes a distribution. `merkle_root` commits to leaves
+    /// `hashv([index as u32 le, wallet, amount as u64 le])`; the vault is
+    /// funded with plain token transfers. `mint` may be a Token-2022 mint,
+    /// in which case any transfer fee comes out of what claimants receive.
+    #[access_control(validate_mint(&ctx))]
+    pub fn create_distributor(
+        ctx: Context<CreateDistributor>,
+        distributor_id: u64,
+        merkle_root: [u8; 32],
+        claim_count: u32,
+        total_amount: u64,
+    ) -> Result<()> {
+        require_gt!(claim_count, 0, RewardsError::EmptyDistribution);
+        let distributor = &mut ctx.accounts.distributor;
+        distributor.layout_version = Distributor::LAYOUT_VERSION;
+        distributor.admin = *ctx.accounts.admin.key;
+        distributor.distributor_id = distributor_id;
+        distributor.mint = ctx.accounts.mint.key();
+        distributor.merkle_root = merkle_root;
+        distributor.claim_count = claim_count;
+        distributor.total_amount = total_amount;
+        distributor.claimed_amount = 0;
+        distributor.bump = ctx.bumps.distributor;
+        distributor.vault_bump = ctx.bumps.vault;
+
+        emit!(DistributorCreated {
+            distributor: distributor.key(),
+            mint: distributor.mint,
+            merkle_root,
+            claim_count,
+            total_amount,
+        });
+
+        Ok(())
+    }
+
+    /// Pays `amount` to the signer if `(index, signer, amount)` is a leaf of
+    /// the distribution and `index` has not been claimed.
+    #[access_control(validate_claim(&ctx, index, amount, &proof))]
+    pub fn claim(
+        ctx: Context<Claim>,
+        index: u32,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let bitmap = &mut ctx.accounts.bitmap;
+        bitmap.layout_version = ClaimBitmap::LAYOUT_VERSION;
+        bitmap.distributor = ctx.accounts.distributor.key();
+        bitmap.chunk = index / CLAIMS_PER_BITMAP;
+        bitmap.bump = ctx.bumps.bitmap;
+        bitmap.set(index % CLAIMS_PER_BITMAP);
+
+        let distributor = &ctx.accounts.distributor;
+        let distributor_id = distributor.distributor_id.to_le_bytes();
+        let signer_seeds: &[&[u8]] = &[b"distributor", &distributor_id, &[distributor.bump]];
+        token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.vault,
+            &ctx.accounts.claimant_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.distributor.to_account_info(),
+            &[signer_seeds],
+            amount,
+        )?;
+
+        let distributor = &mut ctx.accounts.distributor;
+        add_assign(&mut distributor.claimed_amount, amount).map_err(RewardsError::from)?;
+
+        emit!(RewardClaimed {
+            distributor: distributor.key(),
+            claimant: *ctx.accounts.claimant.key,
+            index,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<Distributor>(Distributor::discriminator()),
+            Layout::new::<ClaimBitmap>(ClaimBitmap::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<RewardsError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+fn validate_mint(ctx: &Context<CreateDistributor>) -> Result<()> {
+    require!(
+        token_compat::supported_mint(&ctx.accounts.mint)?,
+        RewardsError::UnsupportedMint
+    );
+    Ok(())
+}
+
+fn validate_claim(ctx: &Context<Claim>, index: u32, amount: u64, proof: &[[u8; 32]]) -> Result<()> {
+    let distributor = &ctx.accounts.distributor;
+    require_gt!(distributor.claim_count, index, RewardsError::InvalidIndex);
+    require!(
+        !ctx.accounts.bitmap.is_set(index % CLAIMS_PER_BITMAP),
+        RewardsError::AlreadyClaimed
+    );
+    require!(
+        add(distributor.claimed_amount, amount).map_err(RewardsError::from)?
+            <= distributor.total_amount,
+        RewardsError::ExceedsTotal
+    );
+    let leaf = hashv(&[
+        &index.to_le_bytes(),
+        ctx.accounts.claimant.key.as_ref(),
+        &amount.to_le_bytes(),
+    ])
+    .to_bytes();
+    require!(
+        verify_proof(proof, distributor.merkle_root, leaf),
+        RewardsError::InvalidProof
+    );
+    Ok(())
+}
+
+/// Checks `leaf` against `root`, hashing each pair in sorted order so proofs
+/// carry no left/right flags.
+pub fn verify_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            hashv(&[&node, sibling]).to_bytes()
+        } else {
+            hashv(&[sibling, &node]).to_bytes()
+        }
+    });
+    computed == root
+}
+
+#[derive(Accounts)]
+#[instruction(distributor_id: u64)]
+pub struct CreateDistributor<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"distributor".as_ref(), &distributor_id.to_le_bytes()],
+        bump,
+        space = Distributor::SPACE,
+        payer = admin,
+    )]
+    pub distributor: Account<'info, Distributor>,
+    #[account(
+        init,
+        seeds = [b"vault", distributor.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = distributor,
+        token::token_program = token_program,
+        payer = admin,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+#[instruction(index: u32)]
+pub struct Claim<'info> {
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"distributor", &distributor.distributor_id.to_le_bytes()],
+        bump = distributor.bump,
+    )]
+    pub distributor: Account<'info, Distributor>,
+    #[account(
+        init_if_needed,
+        seeds = [
+            b"claims",
+            distributor.key().as_ref(),
+            &(index / CLAIMS_PER_BITMAP).to_le_bytes(),
+        ],
+        bump,
+        space = ClaimBitmap::SPACE,
+        payer = claimant,
+    )]
+    pub bitmap: Account<'info, ClaimBitmap>,
+    #[account(
+        mut,
+        seeds = [b"vault", distributor.key().as_ref()],
+        bump = distributor.vault_bump,
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = distributor.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = distributor.mint)]
+    pub claimant_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct Distributor {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    pub distributor_id: u64,
+    pub mint: Pubkey,
+    pub merkle_root: [u8; 32],
+    /// Leaves in the tree; claim indices run from 0 to this.
+    pub claim_count: u32,
+    pub total_amount: u64,
+    pub claimed_amount: u64,
+    pub bump: u8,
+    pub vault_bump: u8,
+}
+
+impl Distributor {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 8 // distributor_id
+        + 32 // mint
+        + 32 // merkle_root
+        + 4 // claim_count
+        + 8 // total_amount
+        + 8 // claimed_amount
+        + 1 // bump
+        + 1; // vault_bump
+}
+
+impl Versioned for Distributor {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+/// Claimed flags for indices `chunk * CLAIMS_PER_BITMAP` onwards. Created by
+/// the first claim in its range.
+#[account]
+pub struct ClaimBitmap {
+    pub layout_version: u8,
+    pub distributor: Pubkey,
+    pub chunk: u32,
+    pub bits: [u8; ClaimBitmap::BYTES],
+    pub bump: u8,
+}
+
+impl ClaimBitmap {
+    pub const BYTES: usize = 1024;
+
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // distributor
+        + 4 // chunk
+        + Self::BYTES // bits
+        + 1; // bump
+
+    pub fn is_set(&self, bit: u32) -> bool {
+        self.bits[bit as usize / 8] & (1 << (bit % 8)) != 0
+    }
+
+    pub fn set(&mut self, bit: u32) {
+        self.bits[bit as usize / 8] |= 1 << (bit % 8);
+    }
+}
+
+impl Versioned for ClaimBitmap {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[event]
+pub struct DistributorCreated {
+    pub distributor: Pubkey,
+    pub mint: Pubkey,
+    pub merkle_root: [u8; 32],
+    pub claim_count: u32,
+    pub total_amount: u64,
+}
+
+#[event]
+pub struct RewardClaimed {
+    pub distributor: Pubkey,
+    pub claimant: Pubkey,
+    pub index: u32,
+    pub amount: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum RewardsError {
+    #[msg("Distribution has no claims")]
+    EmptyDistribution,
+    #[msg("Claim index is out of range")]
+    InvalidIndex,
+    #[msg("Reward already claimed")]
+    AlreadyClaimed,
+    #[msg("Claim exceeds the distribution total")]
+    ExceedsTotal,
+    #[msg("Merkle proof does not match the root")]
+    InvalidProof,
+    #[msg("Mint has a token extension distributions can't support")]
+    UnsupportedMint,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for RewardsError {
+    fn from(_: Overflow) -> Self {
+        RewardsError::Overflow
+    }
+}
+
+impl From<MigrationError> for RewardsError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => RewardsError::AlreadyMigrated,
+            _ => RewardsError::UnsupportedLayout,
+        }
+    }
+}
@@ -0,0 +1,582 @@
+// Rust Anchor Program for Ranked Seasons
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use checked_math::{add_assign, increment, Overflow};
+use combo_mint::{ComboAccount, MIN_VERIFICATIONS_FOR_NFT};
+use match_result::MatchAccount;
+
+declare_id!("SEASON_PROGRAM_ID_HERE");
+
+/// Standings kept in a season's archive.
+pub const ARCHIVE_SIZE: usize = 10;
+pub const WIN_POINTS: u32 = 3;
+pub const LOSS_POINTS: u32 = 1;
+/// Points for a combo verified enough to be minted.
+pub const COMBO_POINTS: u32 = 2;
+
+#[program]
+pub mod season {
+    use super::*;
+
+    pub fn initialize(ctx: Context<Initialize>) -> Result<()> {
+        let state = &mut ctx.accounts.state;
+        state.layout_version = SeasonState::LAYOUT_VERSION;
+        state.admin = *ctx.accounts.admin.key;
+        state.current_season = 0;
+        state.active = false;
+        state.bump = ctx.bumps.state;
+        Ok(())
+    }
+
+    /// Opens the next season. Only matches recorded and combos created
+    /// while it runs count towards it.
+    pub fn start_season(ctx: Context<StartSeason>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let state = &mut ctx.accounts.state;
+        require!(!state.active, SeasonError::SeasonActive);
+        increment(&mut state.current_season).map_err(SeasonError::from)?;
+        state.active = true;
+
+        let season = &mut ctx.accounts.season;
+        season.layout_version = Season::LAYOUT_VERSION;
+        season.id = state.current_season;
+        season.started_at = now;
+        season.ended_at = 0;
+        season.player_count = 0;
+        season.bump = ctx.bumps.season;
+
+        emit!(SeasonStarted {
+            season: season.id,
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    pub fn join_season(ctx: Context<JoinSeason>) -> Result<()> {
+        let season = &mut ctx.accounts.season;
+        increment(&mut season.player_count).map_err(SeasonError::from)?;
+
+        let standing = &mut ctx.accounts.standing;
+        standing.layout_version = SeasonStanding::LAYOUT_VERSION;
+        standing.season = season.id;
+        standing.player = *ctx.accounts.player.key;
+        standing.points = 0;
+        standing.wins = 0;
+        standing.losses = 0;
+        standing.combos = 0;
+        standing.bump = ctx.bumps.standing;
+
+        Ok(())
+    }
+
+    /// Scores a recorded match for both players. Anyone may crank this; the
+    /// `SeasonEntry` receipt makes it one-shot.
+    #[access_control(validate_season_match(&ctx))]
+    pub fn record_match(ctx: Context<RecordMatch>) -> Result<()> {
+        let winner = &mut ctx.accounts.winner_standing;
+        increment(&mut winner.wins).map_err(SeasonError::from)?;
+        add_assign(&mut winner.points, WIN_POINTS).map_err(SeasonError::from)?;
+        let loser = &mut ctx.accounts.loser_standing;
+        increment(&mut loser.losses).map_err(SeasonError::from)?;
+        add_assign(&mut loser.points, LOSS_POINTS).map_err(SeasonError::from)?;
+        ctx.accounts.entry.layout_version = SeasonEntry::LAYOUT_VERSION;
+        ctx.accounts.entry.bump = ctx.bumps.entry;
+
+        emit!(StandingsUpdated {
+            season: ctx.accounts.season.id,
+            winner: winner.player,
+            loser: loser.player,
+            winner_points: winner.points,
+            loser_points: loser.points,
+        });
+
+        Ok(())
+    }
+
+    /// Scores a combo created this season once it has enough verifications
+    /// to be minted.
+    #[access_control(validate_season_combo(&ctx))]
+    pub fn record_combo(ctx: Context<RecordCombo>) -> Result<()> {
+        let standing = &mut ctx.accounts.standing;
+        increment(&mut standing.combos).map_err(SeasonError::from)?;
+        add_assign(&mut standing.points, COMBO_POINTS).map_err(SeasonError::from)?;
+        ctx.accounts.entry.layout_version = SeasonEntry::LAYOUT_VERSION;
+        ctx.accounts.entry.bump = ctx.bumps.entry;
+
+        emit!(ComboScored {
+            season: ctx.accounts.season.id,
+            player: standing.player,
+            combo: ctx.accounts.combo.key(),
+            points: standing.points,
+        });
+
+        Ok(())
+    }
+
+    /// Closes the season and writes its top standings into the archive,
+    /// which nothing modifies afterwards. The candidate standings are passed
+    /// as remaining accounts; those not from this season are rejected.
+    pub fn end_season<'info>(ctx: Context<'_, '_, 'info, 'info, EndSeason<'info>>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let season_id = ctx.accounts.season.id;
+
+        let mut standings = Vec::with_capacity(ctx.remaining_accounts.len());
+        for info in ctx.remaining_accounts.iter() {
+            let standing: Account<SeasonStanding> = Account::try_from(info)?;
+            require_eq!(standing.season, season_id, SeasonError::WrongSeason);
+            require!(
+                standings.iter().all(|s: &ArchivedStanding| s.player != standing.player),
+                SeasonError::DuplicateStanding
+            );
+            standings.push(ArchivedStanding {
+                player: standing.player,
+                points: standing.points,
+                wins: standing.wins,
+                losses: standing.losses,
+            });
+        }
+        standings.sort_by(|a, b| b.points.cmp(&a.points).then(b.wins.cmp(&a.wins)));
+        standings.truncate(ARCHIVE_SIZE);
+
+        ctx.accounts.state.active = false;
+        ctx.accounts.season.ended_at = now;
+
+        let archive = &mut ctx.accounts.archive;
+        archive.layout_version = SeasonArchive::LAYOUT_VERSION;
+        archive.season = season_id;
+        archive.ended_at = now;
+        archive.top = standings;
+        archive.bump = ctx.bumps.archive;
+
+        emit!(SeasonEnded {
+            season: season_id,
+            top: archive.top.iter().map(|s| s.player).collect(),
+            timestamp: now,
+        });
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<SeasonState>(SeasonState::discriminator()),
+            Layout::new::<Season>(Season::discriminator()),
+            Layout::new::<SeasonStanding>(SeasonStanding::discriminator()),
+            Layout::new::<SeasonEntry>(SeasonEntry::discriminator()),
+            Layout::new::<SeasonArchive>(SeasonArchive::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<SeasonError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+fn season_is_open(season: &Season, timestamp: i64) -> bool {
+    season.ended_at == 0 && timestamp >= season.started_at
+}
+
+fn validate_season_match(ctx: &Context<RecordMatch>) -> Result<()> {
+    let record = &ctx.accounts.match_account;
+    require!(record.finalized, SeasonError::MatchNotFinalized);
+    require!(
+        season_is_open(&ctx.accounts.season, record.recorded_at),
+        SeasonError::OutsideSeason
+    );
+    Ok(())
+}
+
+fn validate_season_combo(ctx: &Context<RecordCombo>) -> Result<()> {
+    let combo = &ctx.accounts.combo;
+    require!(
+        season_is_open(&ctx.accounts.season, combo.timestamp),
+        SeasonError::OutsideSeason
+    );
+    require!(!combo.stale, SeasonError::StaleCombo);
+    require_gte!(
+        combo.verification_count,
+        MIN_VERIFICATIONS_FOR_NFT,
+        SeasonError::NotEnoughVerifications
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"season_state"],
+        bump,
+        space = SeasonState::SPACE,
+        payer = admin,
+    )]
+    pub state: Account<'info, SeasonState>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StartSeason<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"season_state"], bump = state.bump, has_one = admin)]
+    pub state: Account<'info, SeasonState>,
+    #[account(
+        init,
+        seeds = [b"season".as_ref(), &(state.current_season + 1).to_le_bytes()],
+        bump,
+        space = Season::SPACE,
+        payer = admin,
+    )]
+    pub season: Account<'info, Season>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct JoinSeason<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"season", &season.id.to_le_bytes()],
+        bump = season.bump,
+        constraint = season.ended_at == 0 @ SeasonError::SeasonEnded,
+    )]
+    pub season: Account<'info, Season>,
+    #[account(
+        init,
+        seeds = [b"standing".as_ref(), &season.id.to_le_bytes(), player.key.as_ref()],
+        bump,
+        space = SeasonStanding::SPACE,
+        payer = player,
+    )]
+    pub standing: Account<'info, SeasonStanding>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordMatch<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"season", &season.id.to_le_bytes()], bump = season.bump)]
+    pub season: Account<'info, Season>,
+    #[account(
+        seeds = [b"match", &match_account.match_id.to_le_bytes()],
+        bump = match_account.bump,
+        seeds::program = match_result::ID,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    #[account(
+        mut,
+        seeds = [b"standing", &season.id.to_le_bytes(), match_account.winner_key().as_ref()],
+        bump = winner_standing.bump,
+    )]
+    pub winner_standing: Account<'info, SeasonStanding>,
+    #[account(
+        mut,
+        seeds = [b"standing", &season.id.to_le_bytes(), match_account.loser_key().as_ref()],
+        bump = loser_standing.bump,
+    )]
+    pub loser_standing: Account<'info, SeasonStanding>,
+    #[account(
+        init,
+        seeds = [b"season_entry".as_ref(), &season.id.to_le_bytes(), match_account.key().as_ref()],
+        bump,
+        space = SeasonEntry::SPACE,
+        payer = payer,
+    )]
+    pub entry: Account<'info, SeasonEntry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RecordCombo<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"season", &season.id.to_le_bytes()], bump = season.bump)]
+    pub season: Account<'info, Season>,
+    pub combo: Account<'info, ComboAccount>,
+    #[account(
+        mut,
+        seeds = [b"standing", &season.id.to_le_bytes(), combo.creator.as_ref()],
+        bump = standing.bump,
+    )]
+    pub standing: Account<'info, SeasonStanding>,
+    #[account(
+        init,
+        seeds = [b"season_entry".as_ref(), &season.id.to_le_bytes(), combo.key().as_ref()],
+        bump,
+        space = SeasonEntry::SPACE,
+        payer = payer,
+    )]
+    pub entry: Account<'info, SeasonEntry>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EndSeason<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"season_state"], bump = state.bump, has_one = admin)]
+    pub state: Account<'info, SeasonState>,
+    #[account(
+        mut,
+        seeds = [b"season", &state.current_season.to_le_bytes()],
+        bump = season.bump,
+        constraint = season.ended_at == 0 @ SeasonError::SeasonEnded,
+    )]
+    pub season: Account<'info, Season>,
+    #[account(
+        init,
+        seeds = [b"archive".as_ref(), &season.id.to_le_bytes()],
+        bump,
+        space = SeasonArchive::SPACE,
+        payer = admin,
+    )]
+    pub archive: Account<'info, SeasonArchive>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct SeasonState {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    /// Id of the newest season, 0 before the first one.
+    pub current_season: u32,
+    pub active: bool,
+    pub bump: u8,
+}
+
+impl SeasonState {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 4 // current_season
+        + 1 // active
+        + 1; // bump
+}
+
+impl Versioned for SeasonState {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[account]
+pub struct Season {
+    pub layout_version: u8,
+    pub id: u32,
+    pub started_at: i64,
+    /// 0 while the season runs.
+    pub ended_at: i64,
+    pub player_count: u32,
+    pub bump: u8,
+}
+
+impl Season {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 4 // id
+        + 8 // started_at
+        + 8 // ended_at
+        + 4 // player_count
+        + 1; // bump
+}
+
+impl Versioned for Season {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[account]
+pub struct SeasonStanding {
+    pub layout_version: u8,
+    pub season: u32,
+    pub player: Pubkey,
+    pub points: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub combos: u32,
+    pub bump: u8,
+}
+
+impl SeasonStanding {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 4 // season
+        + 32 // player
+        + 4 // points
+        + 4 // wins
+        + 4 // losses
+        + 4 // combos
+        + 1; // bump
+}
+
+impl Versioned for SeasonStanding {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+/// Receipt for a match or combo already scored this season.
+#[account]
+pub struct SeasonEntry {
+    pub layout_version: u8,
+    pub bump: u8,
+}
+
+impl SeasonEntry {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 1; // bump
+}
+
+impl Versioned for SeasonEntry {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ArchivedStanding {
+    pub player: Pubkey,
+    pub points: u32,
+    pub wins: u32,
+    pub losses: u32,
+}
+
+impl ArchivedStanding {
+    pub const SIZE: usize = 32 + 4 + 4 + 4;
+}
+
+/// Final top standings of an ended season, highest points first.
+#[account]
+pub struct SeasonArchive {
+    pub layout_version: u8,
+    pub season: u32,
+    pub ended_at: i64,
+    pub top: Vec<ArchivedStanding>,
+    pub bump: u8,
+}
+
+impl SeasonArchive {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 4 // season
+        + 8 // ended_at
+        + 4 + ArchivedStanding::SIZE * ARCHIVE_SIZE // top
+        + 1; // bump
+}
+
+impl Versioned for SeasonArchive {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[event]
+pub struct SeasonStarted {
+    pub season: u32,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct StandingsUpdated {
+    pub season: u32,
+    pub winner: Pubkey,
+    pub loser: Pubkey,
+    pub winner_points: u32,
+    pub loser_points: u32,
+}
+
+#[event]
+pub struct ComboScored {
+    pub season: u32,
+    pub player: Pubkey,
+    pub combo: Pubkey,
+    pub points: u32,
+}
+
+#[event]
+pub struct SeasonEnded {
+    pub season: u32,
+    pub top: Vec<Pubkey>,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum SeasonError {
+    #[msg("A season is already running")]
+    SeasonActive,
+    #[msg("Season has ended")]
+    SeasonEnded,
+    #[msg("Match or combo is outside the season")]
+    OutsideSeason,
+    #[msg("Match result is not finalized")]
+    MatchNotFinalized,
+    #[msg("Combo is from an older game version")]
+    StaleCombo,
+    #[msg("Combo does not have enough verifications")]
+    NotEnoughVerifications,
+    #[msg("Standing belongs to another season")]
+    WrongSeason,
+    #[msg("Standing passed more than once")]
+    DuplicateStanding,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for SeasonError {
+    fn from(_: Overflow) -> Self {
+        SeasonError::Overflow
+    }
+}
+
+impl From<MigrationError> for SeasonError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => SeasonError::AlreadyMigrated,
+            _ => SeasonError::UnsupportedLayout,
+        }
+    }
+}
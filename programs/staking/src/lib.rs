@@ -0,0 +1,939 @@
+// Rust Anchor Program for Combo Staking
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{Layout, MigrationError, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_spl::token::{self, CloseAccount, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token_interface::{self, TokenInterface};
+use checked_math::{add_assign, sub_assign, Overflow};
+use combo_mint::program::ComboMint;
+use combo_mint::{combo_name_seed, ComboAccount};
+
+
+declare_id!("STAKING_PROGRAM_ID_HERE");
+
+/// Most segments an emission curve can have.
+pub const MAX_SEGMENTS: usize = 16;
+/// Weight every staked combo starts from, so an easy combo still earns.
+pub const BASE_WEIGHT: u64 = 100;
+/// Verifications past this add no weight.
+pub const MAX_WEIGHTED_VERIFICATIONS: u32 = 10;
+/// Fixed-point scale of `StakingPool::reward_per_weight`.
+pub const REWARD_SCALE: u128 = 1_000_000_000_000;
+
+#[program]
+pub mod staking {
+    use super::*;
+
+    /// Creates the pool paying `reward_mint` tokens, with an empty emission
+    /// curve. The vault is funded with plain token transfers.
+    #[access_control(validate_mint(&ctx))]
+    pub fn initialize(ctx: Context<Initialize>, unstake_cooldown: i64) -> Result<()> {
+        require_gte!(unstake_cooldown, 0, StakingError::InvalidCooldown);
+        let pool = &mut ctx.accounts.pool;
+        pool.layout_version = StakingPool::LAYOUT_VERSION;
+        pool.admin = *ctx.accounts.admin.key;
+        pool.reward_mint = ctx.accounts.reward_mint.key();
+        pool.vault = ctx.accounts.vault.key();
+        pool.unstake_cooldown = unstake_cooldown;
+        pool.total_weight = 0;
+        pool.reward_per_weight = 0;
+        pool.last_update = Clock::get()?.unix_timestamp;
+        pool.bump = ctx.bumps.pool;
+
+        let curve = &mut ctx.accounts.curve;
+        curve.layout_version = EmissionCurve::LAYOUT_VERSION;
+        curve.segments = Vec::new();
+        curve.bump = ctx.bumps.curve;
+        Ok(())
+    }
+
+    /// Replaces the emission curve. Rewards up to now are accrued on the
+    /// old curve first, so the new one only applies from here on.
+    #[access_control(validate_curve(&segments))]
+    pub fn set_emission_curve(
+        ctx: Context<SetEmissionCurve>,
+        segments: Vec<EmissionSegment>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        accrue(&mut ctx.accounts.pool, &ctx.accounts.curve, now).map_err(StakingError::from)?;
+        ctx.accounts.curve.segments = segments;
+
+        emit!(EmissionCurveSet {
+            segments: ctx.accounts.curve.segments.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Locks the combo by handing its authority to the position, which
+    /// then earns rewards by the combo's weight.
+    #[access_control(validate_stake(&ctx.accounts.combo_pda))]
+    pub fn stake_combo(ctx: Context<StakeCombo>) -> Result<()> {
+        combo_mint::cpi::transfer_combo(
+            CpiContext::new(
+                ctx.accounts.combo_mint_program.to_account_info(),
+                combo_mint::cpi::accounts::TransferCombo {
+                    authority: ctx.accounts.owner.to_account_info(),
+                    combo_pda: ctx.accounts.combo_pda.to_account_info(),
+                },
+            ),
+            ctx.accounts.position.key(),
+            false,
+        )?;
+
+        open_position(
+            &mut ctx.accounts.pool,
+            &ctx.accounts.curve,
+            &mut ctx.accounts.position,
+            &ctx.accounts.combo_pda,
+            *ctx.accounts.owner.key,
+            StakeKind::Authority,
+            ctx.bumps.position,
+        )
+    }
+
+    /// Locks the combo's NFT in a vault owned by the position, which then
+    /// earns rewards like `stake_combo`.
+    #[access_control(validate_stake(&ctx.accounts.combo_pda))]
+    pub fn stake_combo_nft(ctx: Context<StakeComboNft>) -> Result<()> {
+        token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.owner_nft_account.to_account_info(),
+                    to: ctx.accounts.nft_vault.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            1,
+        )?;
+
+        open_position(
+            &mut ctx.accounts.pool,
+            &ctx.accounts.curve,
+            &mut ctx.accounts.position,
+            &ctx.accounts.combo_pda,
+            *ctx.accounts.owner.key,
+            StakeKind::Nft,
+            ctx.bumps.position,
+        )
+    }
+
+    /// Picks up verifications the combo gained since it was staked. Anyone
+    /// can call it.
+    pub fn refresh_weight(ctx: Context<RefreshWeight>) -> Result<()> {
+        let position = &ctx.accounts.position;
+        require!(!position.slashed, StakingError::Slashed);
+        require!(position.unlock_at == 0, StakingError::Unstaking);
+        require!(!ctx.accounts.combo_pda.frozen, StakingError::ComboFrozen);
+
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        accrue(pool, &ctx.accounts.curve, now).map_err(StakingError::from)?;
+        settle(position, pool).map_err(StakingError::from)?;
+        let weight = stake_weight(&ctx.accounts.combo_pda);
+        set_weight(position, pool, weight).map_err(StakingError::from)?;
+
+        emit!(WeightRefreshed {
+            position: position.key(),
+            combo: position.combo,
+            weight,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the position's pending rewards, as far as the vault covers them.
+    /// A frozen combo's rewards are held for `slash` to share out, or for
+    /// the owner once it is unfrozen.
+    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+        require!(!ctx.accounts.combo_pda.frozen, StakingError::ComboFrozen);
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        accrue(pool, &ctx.accounts.curve, now).map_err(StakingError::from)?;
+        settle(position, pool).map_err(StakingError::from)?;
+
+        let amount = position.pending.min(ctx.accounts.vault.amount);
+        if amount > 0 {
+            let signer_seeds: &[&[u8]] = &[b"staking_pool", &[pool.bump]];
+            token_compat::transfer(
+                &ctx.accounts.token_program,
+                &ctx.accounts.vault,
+                &ctx.accounts.owner_reward_account,
+                &ctx.accounts.reward_mint,
+                &ctx.accounts.pool.to_account_info(),
+                &[signer_seeds],
+                amount,
+            )?;
+        }
+        let position = &mut ctx.accounts.position;
+        position.pending -= amount;
+
+        emit!(RewardsClaimed {
+            position: position.key(),
+            owner: position.owner,
+            amount,
+        });
+
+        Ok(())
+    }
+
+    /// Stops the position earning and starts the unstake cooldown.
+    pub fn request_unstake(ctx: Context<RequestUnstake>) -> Result<()> {
+        require!(ctx.accounts.position.unlock_at == 0, StakingError::Unstaking);
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        accrue(pool, &ctx.accounts.curve, now).map_err(StakingError::from)?;
+        settle(position, pool).map_err(StakingError::from)?;
+        set_weight(position, pool, 0).map_err(StakingError::from)?;
+        position.unlock_at = now + pool.unstake_cooldown;
+
+        emit!(UnstakeRequested {
+            position: position.key(),
+            combo: position.combo,
+            unlock_at: position.unlock_at,
+        });
+
+        Ok(())
+    }
+
+    /// Hands the combo's authority back to the owner once the cooldown is
+    /// over and closes the position. A frozen combo can't change hands, so
+    /// it stays locked until it is unfrozen. Pending rewards must be
+    /// claimed first, unless the vault has run dry; whatever it couldn't
+    /// cover is then forfeited.
+    #[access_control(validate_unstake(&ctx.accounts.position, &ctx.accounts.vault))]
+    pub fn unstake_combo(ctx: Context<UnstakeCombo>) -> Result<()> {
+        let position = &ctx.accounts.position;
+        let combo = position.combo;
+        let signer_seeds: &[&[u8]] = &[b"stake", combo.as_ref(), &[position.bump]];
+        combo_mint::cpi::transfer_combo(
+            CpiContext::new_with_signer(
+                ctx.accounts.combo_mint_program.to_account_info(),
+                combo_mint::cpi::accounts::TransferCombo {
+                    authority: ctx.accounts.position.to_account_info(),
+                    combo_pda: ctx.accounts.combo_pda.to_account_info(),
+                },
+                &[signer_seeds],
+            ),
+            position.owner,
+            false,
+        )?;
+
+        emit!(ComboUnstaked {
+            position: position.key(),
+            combo,
+            owner: position.owner,
+            forfeited: position.pending,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the combo's NFT to the owner once the cooldown is over and
+    /// closes the position and its vault, forfeiting rewards like
+    /// `unstake_combo`.
+    #[access_control(validate_unstake(&ctx.accounts.position, &ctx.accounts.vault))]
+    pub fn unstake_combo_nft(ctx: Context<UnstakeComboNft>) -> Result<()> {
+        let position = &ctx.accounts.position;
+        let combo = position.combo;
+        let signer_seeds: &[&[u8]] = &[b"stake", combo.as_ref(), &[position.bump]];
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.nft_vault.to_account_info(),
+                    to: ctx.accounts.owner_nft_account.to_account_info(),
+                    authority: ctx.accounts.position.to_account_info(),
+                },
+                &[signer_seeds],
+            ),
+            1,
+        )?;
+        token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.nft_vault.to_account_info(),
+                destination: ctx.accounts.owner.to_account_info(),
+                authority: ctx.accounts.position.to_account_info(),
+            },
+            &[signer_seeds],
+        ))?;
+
+        emit!(ComboUnstaked {
+            position: position.key(),
+            combo,
+            owner: position.owner,
+            forfeited: position.pending,
+        });
+
+        Ok(())
+    }
+
+    /// Slashes a position whose combo has been frozen, e.g. for relying on
+    /// an exploit: it stops earning, and its unclaimed rewards are shared
+    /// out to the other stakers by weight. Anyone can call it. The owner
+    /// can still unstake the combo once it is unfrozen.
+    pub fn slash(ctx: Context<Slash>) -> Result<()> {
+        require!(ctx.accounts.combo_pda.frozen, StakingError::NotFrozen);
+        require!(!ctx.accounts.position.slashed, StakingError::Slashed);
+
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        let position = &mut ctx.accounts.position;
+        accrue(pool, &ctx.accounts.curve, now).map_err(StakingError::from)?;
+        settle(position, pool).map_err(StakingError::from)?;
+        set_weight(position, pool, 0).map_err(StakingError::from)?;
+        let forfeited = position.pending;
+        position.pending = 0;
+        position.slashed = true;
+        if pool.total_weight > 0 {
+            let per_weight = forfeited as u128 * REWARD_SCALE / pool.total_weight as u128;
+            add_assign(&mut pool.reward_per_weight, per_weight).map_err(StakingError::from)?;
+        }
+
+        emit!(PositionSlashed {
+            position: position.key(),
+            combo: position.combo,
+            forfeited,
+        });
+
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<StakingPool>(StakingPool::discriminator()),
+            Layout::new::<EmissionCurve>(EmissionCurve::discriminator()),
+            Layout::new::<StakePosition>(StakePosition::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<StakingError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+/// A staked combo's reward weight: harder combos and better-verified ones
+/// earn more, up to `MAX_WEIGHTED_VERIFICATIONS`.
+pub fn stake_weight(combo: &ComboAccount) -> u64 {
+    let verifications = combo.verification_count.min(MAX_WEIGHTED_VERIFICATIONS) as u64;
+    (BASE_WEIGHT + combo.difficulty as u64) * (1 + verifications)
+}
+
+/// Brings `reward_per_weight` up to `now` along the emission curve. What is
+/// emitted while nothing is staked is not owed to anyone.
+fn accrue(pool: &mut StakingPool, curve: &EmissionCurve, now: i64) -> std::result::Result<(), Overflow> {
+    if now <= pool.last_update {
+        return Ok(());
+    }
+    if pool.total_weight > 0 {
+        let emitted = curve.emitted(pool.last_update, now);
+        add_assign(
+            &mut pool.reward_per_weight,
+            emitted * REWARD_SCALE / pool.total_weight as u128,
+        )?;
+    }
+    pool.last_update = now;
+    Ok(())
+}
+
+/// Moves what the position earned since it last settled into `pending`.
+fn settle(position: &mut StakePosition, pool: &StakingPool) -> std::result::Result<(), Overflow> {
+    let earned = position.weight as u128 * pool.reward_per_weight / REWARD_SCALE;
+    let reward = u64::try_from(earned - position.reward_debt).map_err(|_| Overflow)?;
+    add_assign(&mut position.pending, reward)?;
+    position.reward_debt = earned;
+    Ok(())
+}
+
+/// Changes a settled position's weight and the pool total with it.
+fn set_weight(
+    position: &mut StakePosition,
+    pool: &mut StakingPool,
+    weight: u64,
+) -> std::result::Result<(), Overflow> {
+    sub_assign(&mut pool.total_weight, position.weight)?;
+    add_assign(&mut pool.total_weight, weight)?;
+    position.weight = weight;
+    position.reward_debt = weight as u128 * pool.reward_per_weight / REWARD_SCALE;
+    Ok(())
+}
+
+fn open_position(
+    pool: &mut StakingPool,
+    curve: &EmissionCurve,
+    position: &mut Account<StakePosition>,
+    combo: &Account<ComboAccount>,
+    owner: Pubkey,
+    kind: StakeKind,
+    bump: u8,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    accrue(pool, curve, now).map_err(StakingError::from)?;
+
+    position.layout_version = StakePosition::LAYOUT_VERSION;
+    position.owner = owner;
+    position.combo = combo.key();
+    position.kind = kind;
+    position.weight = 0;
+    position.reward_debt = 0;
+    position.pending = 0;
+    position.staked_at = now;
+    position.unlock_at = 0;
+    position.slashed = false;
+    position.bump = bump;
+    let weight = stake_weight(combo);
+    set_weight(position, pool, weight).map_err(StakingError::from)?;
+
+    emit!(ComboStaked {
+        position: position.key(),
+        combo: position.combo,
+        owner,
+        kind,
+        weight,
+    });
+
+    Ok(())
+}
+
+fn validate_mint(ctx: &Context<Initialize>) -> Result<()> {
+    require!(
+        token_compat::supported_mint(&ctx.accounts.reward_mint)?,
+        StakingError::UnsupportedMint
+    );
+    Ok(())
+}
+
+fn validate_curve(segments: &[EmissionSegment]) -> Result<()> {
+    require_gte!(MAX_SEGMENTS, segments.len(), StakingError::InvalidCurve);
+    require!(
+        segments.windows(2).all(|pair| pair[0].start < pair[1].start),
+        StakingError::InvalidCurve
+    );
+    Ok(())
+}
+
+fn validate_stake(combo: &ComboAccount) -> Result<()> {
+    require!(!combo.frozen, StakingError::ComboFrozen);
+    Ok(())
+}
+
+/// `claim_rewards` leaves rewards pending only once it has emptied the
+/// vault, so a position still owed from a dry vault can leave without them
+/// rather than wait on a top-up.
+fn validate_unstake(position: &StakePosition, vault: &token_interface::TokenAccount) -> Result<()> {
+    require!(position.unlock_at != 0, StakingError::NotUnstaking);
+    require_gte!(
+        Clock::get()?.unix_timestamp,
+        position.unlock_at,
+        StakingError::CooldownActive
+    );
+    require!(
+        position.pending == 0 || vault.amount == 0,
+        StakingError::UnclaimedRewards
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"staking_pool"],
+        bump,
+        space = StakingPool::SPACE,
+        payer = admin,
+    )]
+    pub pool: Account<'info, StakingPool>,
+    #[account(
+        init,
+        seeds = [b"emission_curve"],
+        bump,
+        space = EmissionCurve::SPACE,
+        payer = admin,
+    )]
+    pub curve: Account<'info, EmissionCurve>,
+    pub reward_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(
+        init,
+        seeds = [b"staking_vault"],
+        bump,
+        token::mint = reward_mint,
+        token::authority = pool,
+        token::token_program = token_program,
+        payer = admin,
+    )]
+    pub vault: InterfaceAccount<'info, token_interface::TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetEmissionCurve<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"staking_pool"], bump = pool.bump, has_one = admin)]
+    pub pool: Account<'info, StakingPool>,
+    #[account(mut, seeds = [b"emission_curve"], bump = curve.bump)]
+    pub curve: Account<'info, EmissionCurve>,
+}
+
+#[derive(Accounts)]
+pub struct StakeCombo<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"staking_pool"], bump = pool.bump)]
+    pub pool: Account<'info, StakingPool>,
+    #[account(seeds = [b"emission_curve"], bump = curve.bump)]
+    pub curve: Account<'info, EmissionCurve>,
+    #[account(
+        mut,
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        seeds::program = combo_mint::ID,
+        constraint = combo_pda.authority == owner.key() @ StakingError::NotComboOwner,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(
+        init,
+        seeds = [b"stake", combo_pda.key().as_ref()],
+        bump,
+        space = StakePosition::SPACE,
+        payer = owner,
+    )]
+    pub position: Account<'info, StakePosition>,
+    pub combo_mint_program: Program<'info, ComboMint>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct StakeComboNft<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"staking_pool"], bump = pool.bump)]
+    pub pool: Account<'info, StakingPool>,
+    #[account(seeds = [b"emission_curve"], bump = curve.bump)]
+    pub curve: Account<'info, EmissionCurve>,
+    #[account(
+        seeds = [b"combo", combo_pda.creator.as_ref(), &combo_name_seed(&combo_pda.name)],
+        bump = combo_pda.bump,
+        seeds::program = combo_mint::ID,
+        constraint = combo_pda.nft_mint != Pubkey::default() @ StakingError::NoNft,
+    )]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(address = combo_pda.nft_mint)]
+    pub nft_mint: Account<'info, Mint>,
+    #[account(mut, token::mint = nft_mint, token::authority = owner)]
+    pub owner_nft_account: Account<'info, TokenAccount>,
+    #[account(
+        init,
+        seeds = [b"stake", combo_pda.key().as_ref()],
+        bump,
+        space = StakePosition::SPACE,
+        payer = owner,
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(
+        init,
+        seeds = [b"nft_vault", position.key().as_ref()],
+        bump,
+        token::mint = nft_mint,
+        token::authority = position,
+        payer = owner,
+    )]
+    pub nft_vault: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RefreshWeight<'info> {
+    #[account(mut, seeds = [b"staking_pool"], bump = pool.bump)]
+    pub pool: Account<'info, StakingPool>,
+    #[account(seeds = [b"emission_curve"], bump = curve.bump)]
+    pub curve: Account<'info, EmissionCurve>,
+    #[account(
+        mut,
+        seeds = [b"stake", position.combo.as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(address = position.combo)]
+    pub combo_pda: Account<'info, ComboAccount>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimRewards<'info> {
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"staking_pool"], bump = pool.bump)]
+    pub pool: Account<'info, StakingPool>,
+    #[account(seeds = [b"emission_curve"], bump = curve.bump)]
+    pub curve: Account<'info, EmissionCurve>,
+    #[account(
+        mut,
+        seeds = [b"stake", position.combo.as_ref()],
+        bump = position.bump,
+        has_one = owner,
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(address = position.combo)]
+    pub combo_pda: Account<'info, ComboAccount>,
+    #[account(mut, address = pool.vault)]
+    pub vault: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(address = pool.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, token_interface::Mint>,
+    #[account(mut, token::mint = pool.reward_mint)]
+    pub owner_reward_account: InterfaceAccount<'info, token_interface::TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RequestUnstake<'info> {
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"staking_pool"], bump = pool.bump)]
+    pub pool: Account<'info, StakingPool>,
+    #[account(seeds = [b"emission_curve"], bump = curve.bump)]
+    pub curve: Account<'info, EmissionCurve>,
+    #[account(
+        mut,
+        seeds = [b"stake", position.combo.as_ref()],
+        bump = position.bump,
+        has_one = owner,
+    )]
+    pub position: Account<'info, StakePosition>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakeCombo<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"stake", position.combo.as_ref()],
+        bump = position.bump,
+        has_one = owner,
+        constraint = position.kind == StakeKind::Authority @ StakingError::WrongStakeKind,
+        close = owner,
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(seeds = [b"staking_pool"], bump = pool.bump)]
+    pub pool: Account<'info, StakingPool>,
+    /// Read for whether pending rewards can still be claimed
+    #[account(address = pool.vault)]
+    pub vault: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(mut, address = position.combo)]
+    pub combo_pda: Account<'info, ComboAccount>,
+    pub combo_mint_program: Program<'info, ComboMint>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakeComboNft<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"stake", position.combo.as_ref()],
+        bump = position.bump,
+        has_one = owner,
+        constraint = position.kind == StakeKind::Nft @ StakingError::WrongStakeKind,
+        close = owner,
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(seeds = [b"staking_pool"], bump = pool.bump)]
+    pub pool: Account<'info, StakingPool>,
+    /// Read for whether pending rewards can still be claimed
+    #[account(address = pool.vault)]
+    pub vault: InterfaceAccount<'info, token_interface::TokenAccount>,
+    #[account(
+        mut,
+        seeds = [b"nft_vault", position.key().as_ref()],
+        bump,
+    )]
+    pub nft_vault: Account<'info, TokenAccount>,
+    #[account(mut, token::mint = nft_vault.mint, token::authority = owner)]
+    pub owner_nft_account: Account<'info, TokenAccount>,
+    pub token_program: Program<'info, Token>,
+}
+
+#[derive(Accounts)]
+pub struct Slash<'info> {
+    #[account(mut, seeds = [b"staking_pool"], bump = pool.bump)]
+    pub pool: Account<'info, StakingPool>,
+    #[account(seeds = [b"emission_curve"], bump = curve.bump)]
+    pub curve: Account<'info, EmissionCurve>,
+    #[account(
+        mut,
+        seeds = [b"stake", position.combo.as_ref()],
+        bump = position.bump,
+    )]
+    pub position: Account<'info, StakePosition>,
+    #[account(address = position.combo)]
+    pub combo_pda: Account<'info, ComboAccount>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct StakingPool {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    pub reward_mint: Pubkey,
+    pub vault: Pubkey,
+    /// Seconds between `request_unstake` and getting the combo back.
+    pub unstake_cooldown: i64,
+    /// Sum of the weights of positions that are earning.
+    pub total_weight: u64,
+    /// Rewards emitted per unit of weight since the pool opened, scaled by
+    /// `REWARD_SCALE`.
+    pub reward_per_weight: u128,
+    pub last_update: i64,
+    pub bump: u8,
+}
+
+impl StakingPool {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 32 // reward_mint
+        + 32 // vault
+        + 8 // unstake_cooldown
+        + 8 // total_weight
+        + 16 // reward_per_weight
+        + 8 // last_update
+        + 1; // bump
+}
+
+impl Versioned for StakingPool {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+/// One step of the emission curve: `rate` tokens per second from `start`
+/// until the next segment starts.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EmissionSegment {
+    pub start: i64,
+    pub rate: u64,
+}
+
+impl EmissionSegment {
+    pub const SIZE: usize = 8 + 8;
+}
+
+/// Reward tokens emitted to the pool over time, as a step function. Nothing
+/// is emitted before the first segment; the last one runs on until the
+/// curve is replaced, so a curve ends with a zero-rate segment.
+#[account]
+pub struct EmissionCurve {
+    pub layout_version: u8,
+    /// Sorted by `start`.
+    pub segments: Vec<EmissionSegment>,
+    pub bump: u8,
+}
+
+impl EmissionCurve {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 4 + EmissionSegment::SIZE * MAX_SEGMENTS // segments
+        + 1; // bump
+
+    /// Tokens emitted between `from` and `to`.
+    pub fn emitted(&self, from: i64, to: i64) -> u128 {
+        let mut total = 0;
+        for (i, segment) in self.segments.iter().enumerate() {
+            let end = self.segments.get(i + 1).map_or(i64::MAX, |next| next.start);
+            let (start, end) = (segment.start.max(from), end.min(to));
+            if end > start {
+                total += (end - start) as u128 * segment.rate as u128;
+            }
+        }
+        total
+    }
+}
+
+impl Versioned for EmissionCurve {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StakeKind {
+    /// The position holds the combo's authority.
+    Authority,
+    /// The position's vault holds the combo's NFT.
+    Nft,
+}
+
+#[account]
+pub struct StakePosition {
+    pub layout_version: u8,
+    pub owner: Pubkey,
+    pub combo: Pubkey,
+    pub kind: StakeKind,
+    /// Zero once unstaking or slashed.
+    pub weight: u64,
+    /// `weight * reward_per_weight` the position has already been credited
+    /// with, scaled down by `REWARD_SCALE`.
+    pub reward_debt: u128,
+    /// Settled rewards not yet claimed.
+    pub pending: u64,
+    pub staked_at: i64,
+    /// When the combo can be unstaked; zero until `request_unstake`.
+    pub unlock_at: i64,
+    pub slashed: bool,
+    pub bump: u8,
+}
+
+impl StakePosition {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // owner
+        + 32 // combo
+        + 1 // kind
+        + 8 // weight
+        + 16 // reward_debt
+        + 8 // pending
+        + 8 // staked_at
+        + 8 // unlock_at
+        + 1 // slashed
+        + 1; // bump
+}
+
+impl Versioned for StakePosition {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[event]
+pub struct EmissionCurveSet {
+    pub segments: Vec<EmissionSegment>,
+}
+
+#[event]
+pub struct ComboStaked {
+    pub position: Pubkey,
+    pub combo: Pubkey,
+    pub owner: Pubkey,
+    pub kind: StakeKind,
+    pub weight: u64,
+}
+
+#[event]
+pub struct WeightRefreshed {
+    pub position: Pubkey,
+    pub combo: Pubkey,
+    pub weight: u64,
+}
+
+#[event]
+pub struct RewardsClaimed {
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct UnstakeRequested {
+    pub position: Pubkey,
+    pub combo: Pubkey,
+    pub unlock_at: i64,
+}
+
+#[event]
+pub struct ComboUnstaked {
+    pub position: Pubkey,
+    pub combo: Pubkey,
+    pub owner: Pubkey,
+    /// Rewards still pending that the vault couldn't pay.
+    pub forfeited: u64,
+}
+
+#[event]
+pub struct PositionSlashed {
+    pub position: Pubkey,
+    pub combo: Pubkey,
+    pub forfeited: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum StakingError {
+    #[msg("Mint has a token extension staking rewards can't support")]
+    UnsupportedMint,
+    #[msg("Unstake cooldown cannot be negative")]
+    InvalidCooldown,
+    #[msg("Emission curve has too many segments or unsorted start times")]
+    InvalidCurve,
+    #[msg("Signer is not the combo's authority")]
+    NotComboOwner,
+    #[msg("Combo has no NFT")]
+    NoNft,
+    #[msg("Combo is frozen")]
+    ComboFrozen,
+    #[msg("Combo is not frozen")]
+    NotFrozen,
+    #[msg("Position has been slashed")]
+    Slashed,
+    #[msg("Position is already unstaking")]
+    Unstaking,
+    #[msg("Position has not requested to unstake")]
+    NotUnstaking,
+    #[msg("Unstake cooldown has not passed")]
+    CooldownActive,
+    #[msg("Claim pending rewards before unstaking")]
+    UnclaimedRewards,
+    #[msg("Position was staked the other way")]
+    WrongStakeKind,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for StakingError {
+    fn from(_: Overflow) -> Self {
+        StakingError::Overflow
+    }
+}
+
+impl From<MigrationError> for StakingError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => StakingError::AlreadyMigrated,
+            _ => StakingError::UnsupportedLayout,
+        }
+    }
+}
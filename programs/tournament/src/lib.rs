@@ -0,0 +1,1160 @@
+// Rust Anchor Program for Tournament Brackets
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use achievements::program::Achievements;
+use achievements::Achievement;
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{self, Transfer};
+use anchor_lang::Discriminator;
+use checked_math::{add_assign, increment, Overflow};
+use profile::program::Profile;
+use referral::program::Referral;
+use referral::{FeeKind, MAX_REFERRAL_BPS};
+
+declare_id!("TOURNAMENT_PROGRAM_ID_HERE");
+
+/// Marks an empty bracket slot or a missing destination.
+pub const NONE: u8 = u8::MAX;
+/// Basis points in 100%.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+/// Most finishing places a prize split can pay.
+pub const MAX_PRIZE_PLACES: usize = 3;
+
+#[program]
+pub mod tournament {
+    use super::*;
+
+    pub fn initialize_config(ctx: Context<InitializeConfig>, max_organizer_fee_bps: u16) -> Result<()> {
+        require_gte!(BPS_DENOMINATOR, max_organizer_fee_bps as u64, TournamentError::InvalidFee);
+        let config = &mut ctx.accounts.config;
+        config.layout_version = TournamentConfig::LAYOUT_VERSION;
+        config.admin = *ctx.accounts.admin.key;
+        config.max_organizer_fee_bps = max_organizer_fee_bps;
+        config.bump = ctx.bumps.config;
+        Ok(())
+    }
+
+    /// Caps the fee organizers can take from tournaments created from now
+    /// on; existing tournaments keep theirs.
+    pub fn set_max_organizer_fee(ctx: Context<UpdateConfig>, max_organizer_fee_bps: u16) -> Result<()> {
+        require_gte!(BPS_DENOMINATOR, max_organizer_fee_bps as u64, TournamentError::InvalidFee);
+        ctx.accounts.config.max_organizer_fee_bps = max_organizer_fee_bps;
+        Ok(())
+    }
+
+    /// `organizer_fee_bps` of the pool goes to the organizer and the rest is
+    /// shared by place, `prize_split[0]` to the champion and so on, in basis
+    /// points summing to `BPS_DENOMINATOR`.
+    #[access_control(validate_config(
+        &ctx.accounts.config,
+        format,
+        max_entrants,
+        entry_fee,
+        &prize_split,
+        organizer_fee_bps,
+    ))]
+    pub fn create_tournament(
+        ctx: Context<CreateTournament>,
+        tournament_id: u64,
+        format: BracketFormat,
+        entry_fee: u64,
+        max_entrants: u8,
+        prize_split: Vec<u16>,
+        organizer_fee_bps: u16,
+    ) -> Result<()> {
+        let tournament = &mut ctx.accounts.tournament;
+
+        tournament.layout_version = Tournament::LAYOUT_VERSION;
+        tournament.organizer = *ctx.accounts.organizer.key;
+        tournament.tournament_id = tournament_id;
+        tournament.format = format;
+        tournament.status = TournamentStatus::Registration;
+        tournament.entry_fee = entry_fee;
+        tournament.max_entrants = max_entrants;
+        tournament.runner_up_bps = prize_split.get(1).copied().unwrap_or(0);
+        tournament.prize_pool = 0;
+        tournament.entrants = Vec::new();
+        tournament.matches = Vec::new();
+        tournament.champion = Pubkey::default();
+        tournament.bump = ctx.bumps.tournament;
+        tournament.vault_bump = ctx.bumps.vault;
+        tournament.swiss_round = 0;
+        tournament.swiss_rounds = swiss_rounds(max_entrants);
+        tournament.swiss = Vec::new();
+        tournament.organizer_fee_bps = organizer_fee_bps;
+        tournament.prize_split = prize_split;
+
+        emit!(TournamentCreated {
+            tournament: tournament.key(),
+            organizer: tournament.organizer,
+            format,
+            entry_fee,
+            max_entrants,
+        });
+
+        Ok(())
+    }
+
+    /// Pays the entry fee into the vault, less the referrer's share for a
+    /// referred player. The bracket is built as soon as the last seat is
+    /// taken, seeded in registration order. Swiss tournaments start without
+    /// pairings; `pair_swiss_round` makes them.
+    #[access_control(validate_registration(&ctx))]
+    pub fn register<'info>(ctx: Context<'_, '_, '_, 'info, Register<'info>>) -> Result<()> {
+        let mut entry_fee = ctx.accounts.tournament.entry_fee;
+        if entry_fee > 0 && ctx.accounts.referral.owner == &referral::ID {
+            require_eq!(
+                ctx.remaining_accounts.len(),
+                2,
+                TournamentError::MissingReferralAccounts
+            );
+            let share = referral::cpi::pay_referral(
+                CpiContext::new(
+                    ctx.accounts.referral_program.to_account_info(),
+                    referral::cpi::accounts::PayReferral {
+                        player: ctx.accounts.player.to_account_info(),
+                        referral: ctx.accounts.referral.to_account_info(),
+                        config: ctx.remaining_accounts[0].clone(),
+                        referral_code: ctx.remaining_accounts[1].clone(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                    },
+                ),
+                FeeKind::Entry,
+                entry_fee,
+            )?
+            .get();
+            entry_fee -= share;
+        }
+        if entry_fee > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: ctx.accounts.player.to_account_info(),
+                        to: ctx.accounts.vault.to_account_info(),
+                    },
+                ),
+                entry_fee,
+            )?;
+        }
+
+        let tournament = &mut ctx.accounts.tournament;
+        tournament.entrants.push(*ctx.accounts.player.key);
+        add_assign(&mut tournament.prize_pool, entry_fee).map_err(TournamentError::from)?;
+
+        emit!(PlayerRegistered {
+            tournament: tournament.key(),
+            player: *ctx.accounts.player.key,
+            seed: tournament.entrants.len() as u8 - 1,
+        });
+
+        if tournament.entrants.len() == tournament.max_entrants as usize {
+            if tournament.format == BracketFormat::Swiss {
+                tournament.swiss = vec![SwissRecord::NEW; tournament.entrants.len()];
+            } else {
+                tournament.matches = build_bracket(tournament.format, tournament.entrants.len());
+            }
+            tournament.status = TournamentStatus::InProgress;
+        }
+
+        Ok(())
+    }
+
+    /// Records the winner of a ready match and moves both players to their
+    /// next slots. Reporting the final pays out the vault to the finishers
+    /// `placings` lists, passed as remaining accounts in that order. A Swiss
+    /// match only scores a point for its winner.
+    #[access_control(validate_report(&ctx, match_index, winner))]
+    pub fn report_result<'info>(
+        ctx: Context<'_, '_, '_, 'info, ReportResult<'info>>,
+        match_index: u8,
+        winner: u8,
+    ) -> Result<()> {
+        let tournament = &mut ctx.accounts.tournament;
+        let index = match_index as usize;
+        let reported = tournament.matches[index];
+        let winner_seed = reported.players[winner as usize];
+        let loser_seed = reported.players[1 - winner as usize];
+
+        tournament.matches[index].winner = winner;
+        tournament.place(reported.winner_to, reported.winner_slot, winner_seed);
+        tournament.place(reported.loser_to, reported.loser_slot, loser_seed);
+
+        emit!(BracketMatchReported {
+            tournament: tournament.key(),
+            match_index,
+            winner: tournament.entrants[winner_seed as usize],
+            loser: tournament.entrants[loser_seed as usize],
+        });
+
+        if tournament.format == BracketFormat::Swiss {
+            increment(&mut tournament.swiss[winner_seed as usize].points)
+                .map_err(TournamentError::from)?;
+            return Ok(());
+        }
+        if index + 1 < tournament.matches.len() {
+            return Ok(());
+        }
+
+        pay_out(
+            tournament,
+            &ctx.accounts.vault,
+            &ctx.accounts.organizer.to_account_info(),
+            ctx.remaining_accounts,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    /// Pairs the next Swiss round once every match of the current one is
+    /// reported. Anyone may crank this.
+    ///
+    /// Players are ranked by points, then registration order, and each is
+    /// paired with the highest ranked player left that it has not met. With
+    /// an odd field the lowest ranked player without a bye sits the round
+    /// out and scores a point. Rematches are only allowed when no pairing
+    /// avoids them.
+    #[access_control(validate_swiss_round(&ctx.accounts.tournament))]
+    pub fn pair_swiss_round(ctx: Context<CrankSwiss>) -> Result<()> {
+        let tournament = &mut ctx.accounts.tournament;
+        let round = tournament.swiss_round as usize;
+        let (pairs, bye) = pair_swiss(&tournament.swiss, round);
+
+        for &[a, b] in &pairs {
+            tournament.swiss[a as usize].opponents[round] = b;
+            tournament.swiss[b as usize].opponents[round] = a;
+        }
+        if let Some(seed) = bye {
+            let record = &mut tournament.swiss[seed as usize];
+            record.had_bye = true;
+            increment(&mut record.points).map_err(TournamentError::from)?;
+        }
+        tournament.matches = pairs
+            .iter()
+            .map(|&players| BracketMatch {
+                players,
+                ..BracketMatch::PENDING
+            })
+            .collect();
+        tournament.swiss_round += 1;
+
+        emit!(SwissRoundPaired {
+            tournament: tournament.key(),
+            round: tournament.swiss_round,
+            pairs: pairs
+                .iter()
+                .map(|pair| pair.map(|seed| tournament.entrants[seed as usize]))
+                .collect(),
+            bye: bye.map(|seed| tournament.entrants[seed as usize]),
+        });
+
+        Ok(())
+    }
+
+    /// Ends a Swiss tournament after its last round and pays out the vault
+    /// by `swiss_standings`. Anyone may crank this, passing the paid
+    /// finishers as remaining accounts like `report_result`.
+    #[access_control(validate_swiss_finish(&ctx))]
+    pub fn finish_swiss<'info>(ctx: Context<'_, '_, '_, 'info, FinishSwiss<'info>>) -> Result<()> {
+        pay_out(
+            &mut ctx.accounts.tournament,
+            &ctx.accounts.vault,
+            &ctx.accounts.organizer.to_account_info(),
+            ctx.remaining_accounts,
+            &ctx.accounts.system_program,
+        )
+    }
+
+    /// Awards the champion of a completed tournament the first tournament
+    /// win badge. Anyone may crank this; a player only gets the badge once.
+    pub fn award_champion_badge(ctx: Context<AwardChampionBadge>) -> Result<()> {
+        require!(
+            ctx.accounts.tournament.status == TournamentStatus::Complete,
+            TournamentError::NotComplete
+        );
+        let seeds = &[b"achievement_issuer".as_ref(), &[ctx.bumps.issuer]];
+        achievements::cpi::award_badge(CpiContext::new_with_signer(
+            ctx.accounts.achievements_program.to_account_info(),
+            achievements::cpi::accounts::AwardBadge {
+                issuer: ctx.accounts.issuer.to_account_info(),
+                achievement: ctx.accounts.achievement.to_account_info(),
+                player: ctx.accounts.champion.to_account_info(),
+                badge: ctx.accounts.badge.to_account_info(),
+                profile: ctx.accounts.profile.to_account_info(),
+                profile_config: ctx.accounts.profile_config.to_account_info(),
+                profile_writer: ctx.accounts.profile_writer.to_account_info(),
+                profile_program: ctx.accounts.profile_program.to_account_info(),
+                payer: ctx.accounts.payer.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            &[&seeds[..]],
+        ))
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [
+            Layout::new::<Tournament>(Tournament::discriminator()),
+            Layout::new::<TournamentConfig>(TournamentConfig::discriminator()),
+        ];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<TournamentError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+/// Takes the organizer fee off the pool and shares the rest between the
+/// paid places, split evenly between players tied for a place. Rounding
+/// dust goes to the champion. `payees` must be the paid finishers in
+/// `placings` order. Completes the tournament.
+fn pay_out<'info>(
+    tournament: &mut Account<'info, Tournament>,
+    vault: &SystemAccount<'info>,
+    organizer: &AccountInfo<'info>,
+    payees: &[AccountInfo<'info>],
+    system_program: &Program<'info, System>,
+) -> Result<()> {
+    let places = placings(tournament);
+    let paid = &places[..tournament.prize_split.len()];
+    let finishers: Vec<Pubkey> = paid
+        .iter()
+        .flatten()
+        .map(|&seed| tournament.entrants[seed as usize])
+        .collect();
+    require!(
+        payees.len() == finishers.len()
+            && payees.iter().zip(&finishers).all(|(payee, finisher)| payee.key == finisher),
+        TournamentError::PlayerMismatch
+    );
+
+    let pool = tournament.prize_pool;
+    let organizer_fee = pool * tournament.organizer_fee_bps as u64 / BPS_DENOMINATOR;
+    let prizes = pool - organizer_fee;
+    let mut amounts = Vec::with_capacity(finishers.len());
+    for (place, &share) in paid.iter().zip(&tournament.prize_split) {
+        let amount = prizes * share as u64 / BPS_DENOMINATOR / place.len() as u64;
+        amounts.extend(std::iter::repeat(amount).take(place.len()));
+    }
+    amounts[0] += prizes - amounts.iter().sum::<u64>();
+    tournament.champion = finishers[0];
+    tournament.status = TournamentStatus::Complete;
+    tournament.prize_pool = 0;
+
+    let tournament_key = tournament.key();
+    let seeds = &[b"vault".as_ref(), tournament_key.as_ref(), &[tournament.vault_bump]];
+    let recipients = payees.iter().zip(amounts.iter().copied());
+    for (recipient, amount) in recipients.chain([(organizer, organizer_fee)]) {
+        if amount == 0 {
+            continue;
+        }
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                system_program.to_account_info(),
+                Transfer {
+                    from: vault.to_account_info(),
+                    to: recipient.clone(),
+                },
+                &[&seeds[..]],
+            ),
+            amount,
+        )?;
+    }
+
+    emit!(TournamentCompleted {
+        tournament: tournament_key,
+        champion: tournament.champion,
+        payouts: finishers
+            .iter()
+            .zip(&amounts)
+            .map(|(&player, &amount)| Payout { player, amount })
+            .collect(),
+        organizer_fee,
+    });
+
+    Ok(())
+}
+
+/// Finishers of a completed tournament by place, first place first. Swiss
+/// places come from `swiss_standings`. Brackets only decide the first two
+/// places and third: the losers of both semifinals share third in single
+/// elimination, and the loser of the losers final takes it in double
+/// elimination.
+pub fn placings(tournament: &Tournament) -> Vec<Vec<u8>> {
+    if tournament.format == BracketFormat::Swiss {
+        return swiss_standings(&tournament.swiss)
+            .into_iter()
+            .map(|seed| vec![seed])
+            .collect();
+    }
+
+    let matches = &tournament.matches;
+    let last = matches.len() - 1;
+    let mut places = vec![vec![matches[last].winner_seed()], vec![matches[last].loser_seed()]];
+    let third: Vec<u8> = matches
+        .iter()
+        .filter(|m| m.winner_to as usize == last)
+        .filter(|m| tournament.format == BracketFormat::SingleElimination || m.winner_slot == 1)
+        .map(|m| m.loser_seed())
+        .collect();
+    if !third.is_empty() {
+        places.push(third);
+    }
+    places
+}
+
+/// Places `placings` will decide for a tournament of `entrants`.
+fn decided_places(format: BracketFormat, entrants: u8) -> usize {
+    match format {
+        BracketFormat::Swiss => entrants as usize,
+        _ if entrants >= 4 => 3,
+        _ => 2,
+    }
+}
+
+/// Lays out every match of a bracket for `entrants` players (a power of
+/// two). Matches are ordered so each one only feeds later ones, and the
+/// last match is always the final.
+///
+/// Double elimination adds a losers bracket of `entrants - 2` matches and a
+/// single grand final between the two bracket winners, with no reset.
+pub fn build_bracket(format: BracketFormat, entrants: usize) -> Vec<BracketMatch> {
+    let rounds = entrants.trailing_zeros() as usize;
+    let double = format == BracketFormat::DoubleElimination;
+    let mut matches = Vec::with_capacity(2 * entrants);
+
+    let mut winners_base = Vec::with_capacity(rounds);
+    for round in 0..rounds {
+        winners_base.push(matches.len());
+        matches.resize(matches.len() + (entrants >> (round + 1)), BracketMatch::PENDING);
+    }
+
+    let losers_rounds = if double { 2 * (rounds - 1) } else { 0 };
+    let mut losers_base = Vec::with_capacity(losers_rounds);
+    for round in 0..losers_rounds {
+        losers_base.push(matches.len());
+        matches.resize(matches.len() + losers_round_len(entrants, round), BracketMatch::PENDING);
+    }
+
+    let grand_final = if double {
+        matches.push(BracketMatch::PENDING);
+        Some(matches.len() - 1)
+    } else {
+        None
+    };
+
+    for round in 0..rounds {
+        for i in 0..entrants >> (round + 1) {
+            let winner_dest = if round + 1 < rounds {
+                Some((winners_base[round + 1] + i / 2, i % 2))
+            } else {
+                grand_final.map(|gf| (gf, 0))
+            };
+            let loser_dest = match (double, round) {
+                (false, _) => None,
+                (true, 0) if losers_rounds == 0 => grand_final.map(|gf| (gf, 1)),
+                (true, 0) => Some((losers_base[0] + i / 2, i % 2)),
+                // Drop into the losers round that pairs survivors with
+                // fresh dropouts, always in the second slot.
+                (true, _) => Some((losers_base[2 * round - 1] + i, 1)),
+            };
+            matches[winners_base[round] + i].wire(winner_dest, loser_dest);
+        }
+    }
+
+    for round in 0..losers_rounds {
+        for i in 0..losers_round_len(entrants, round) {
+            let winner_dest = if round % 2 == 0 {
+                (losers_base[round + 1] + i, 0)
+            } else if round + 1 == losers_rounds {
+                (grand_final.unwrap(), 1)
+            } else {
+                (losers_base[round + 1] + i / 2, i % 2)
+            };
+            matches[losers_base[round] + i].wire(Some(winner_dest), None);
+        }
+    }
+
+    for (i, bracket_match) in matches.iter_mut().take(entrants / 2).enumerate() {
+        bracket_match.players = [2 * i as u8, 2 * i as u8 + 1];
+    }
+
+    matches
+}
+
+/// Rounds a Swiss tournament of `entrants` plays: enough for one unbeaten
+/// player to remain.
+pub fn swiss_rounds(entrants: u8) -> u8 {
+    entrants.next_power_of_two().trailing_zeros() as u8
+}
+
+/// Pairings and bye for Swiss round `round` (from 0), as entrant indices.
+/// See `pair_swiss_round` for the rules.
+pub fn pair_swiss(records: &[SwissRecord], round: usize) -> (Vec<[u8; 2]>, Option<u8>) {
+    let mut ranked: Vec<u8> = (0..records.len() as u8).collect();
+    ranked.sort_by(|&a, &b| records[b as usize].points.cmp(&records[a as usize].points));
+
+    let mut bye = None;
+    if ranked.len() % 2 == 1 {
+        let slot = ranked
+            .iter()
+            .rposition(|&seed| !records[seed as usize].had_bye)
+            .unwrap_or(ranked.len() - 1);
+        bye = Some(ranked.remove(slot));
+    }
+
+    let met = |a: u8, b: u8| records[a as usize].opponents[..round].contains(&b);
+    let mut pairs = Vec::with_capacity(ranked.len() / 2);
+    if !pair_unmet(&ranked, &met, &mut pairs) {
+        pairs = ranked.chunks(2).map(|pair| [pair[0], pair[1]]).collect();
+    }
+    (pairs, bye)
+}
+
+/// Pairs off `left` in rank order without rematches, backtracking when a
+/// choice leaves the rest unpairable. Returns false if no such pairing
+/// exists, leaving `pairs` as it found it.
+fn pair_unmet(left: &[u8], met: &dyn Fn(u8, u8) -> bool, pairs: &mut Vec<[u8; 2]>) -> bool {
+    let (&first, rest) = match left.split_first() {
+        Some(split) => split,
+        None => return true,
+    };
+    for (i, &opponent) in rest.iter().enumerate() {
+        if met(first, opponent) {
+            continue;
+        }
+        let remaining: Vec<u8> = rest[..i].iter().chain(&rest[i + 1..]).copied().collect();
+        pairs.push([first, opponent]);
+        if pair_unmet(&remaining, met, pairs) {
+            return true;
+        }
+        pairs.pop();
+    }
+    false
+}
+
+/// Sum of the points of the entrant's opponents; byes count for nothing.
+pub fn buchholz(records: &[SwissRecord], seed: u8) -> u16 {
+    records[seed as usize]
+        .opponents
+        .iter()
+        .filter(|&&opponent| opponent != NONE)
+        .map(|&opponent| records[opponent as usize].points as u16)
+        .sum()
+}
+
+/// Entrant indices from first to last: by points, then Buchholz, then
+/// registration order.
+pub fn swiss_standings(records: &[SwissRecord]) -> Vec<u8> {
+    let mut order: Vec<u8> = (0..records.len() as u8).collect();
+    order.sort_by(|&a, &b| {
+        records[b as usize]
+            .points
+            .cmp(&records[a as usize].points)
+            .then(buchholz(records, b).cmp(&buchholz(records, a)))
+    });
+    order
+}
+
+/// Losers bracket rounds alternate between survivors playing each other
+/// (even rounds) and survivors meeting winners bracket dropouts (odd rounds).
+fn losers_round_len(entrants: usize, round: usize) -> usize {
+    if round % 2 == 0 {
+        entrants >> (round / 2 + 2)
+    } else {
+        entrants >> ((round + 1) / 2 + 1)
+    }
+}
+
+fn validate_config(
+    config: &TournamentConfig,
+    format: BracketFormat,
+    max_entrants: u8,
+    entry_fee: u64,
+    prize_split: &[u16],
+    organizer_fee_bps: u16,
+) -> Result<()> {
+    require!(
+        max_entrants >= 2
+            && max_entrants as usize <= Tournament::MAX_ENTRANTS
+            && (format == BracketFormat::Swiss || max_entrants.is_power_of_two()),
+        TournamentError::InvalidEntrantCount
+    );
+    // The vault is a plain system account, so its first deposit must make it
+    // rent exempt, even after the largest referral share comes out.
+    let least_deposit = entry_fee - entry_fee * MAX_REFERRAL_BPS as u64 / BPS_DENOMINATOR;
+    require!(
+        entry_fee == 0 || least_deposit >= Rent::get()?.minimum_balance(0),
+        TournamentError::EntryFeeTooLow
+    );
+    require!(
+        !prize_split.is_empty()
+            && prize_split.len() <= MAX_PRIZE_PLACES.min(decided_places(format, max_entrants))
+            && prize_split.iter().map(|&share| share as u64).sum::<u64>() == BPS_DENOMINATOR,
+        TournamentError::InvalidPrizeSplit
+    );
+    require_gte!(
+        config.max_organizer_fee_bps,
+        organizer_fee_bps,
+        TournamentError::OrganizerFeeTooHigh
+    );
+    Ok(())
+}
+
+fn validate_registration(ctx: &Context<Register>) -> Result<()> {
+    let tournament = &ctx.accounts.tournament;
+    require!(
+        tournament.status == TournamentStatus::Registration,
+        TournamentError::RegistrationClosed
+    );
+    require!(
+        !tournament.entrants.contains(ctx.accounts.player.key),
+        TournamentError::AlreadyRegistered
+    );
+    Ok(())
+}
+
+fn validate_swiss_round(tournament: &Tournament) -> Result<()> {
+    require!(tournament.format == BracketFormat::Swiss, TournamentError::NotSwiss);
+    require!(
+        tournament.status == TournamentStatus::InProgress,
+        TournamentError::NotInProgress
+    );
+    require!(
+        tournament.matches.iter().all(|m| m.winner != NONE),
+        TournamentError::RoundInProgress
+    );
+    require_gt!(
+        tournament.swiss_rounds,
+        tournament.swiss_round,
+        TournamentError::SwissRoundsPlayed
+    );
+    Ok(())
+}
+
+fn validate_swiss_finish(ctx: &Context<FinishSwiss>) -> Result<()> {
+    let tournament = &ctx.accounts.tournament;
+    require!(tournament.format == BracketFormat::Swiss, TournamentError::NotSwiss);
+    require!(
+        tournament.status == TournamentStatus::InProgress,
+        TournamentError::NotInProgress
+    );
+    require!(
+        tournament.swiss_round == tournament.swiss_rounds
+            && tournament.matches.iter().all(|m| m.winner != NONE),
+        TournamentError::RoundInProgress
+    );
+    Ok(())
+}
+
+fn validate_report(ctx: &Context<ReportResult>, match_index: u8, winner: u8) -> Result<()> {
+    let tournament = &ctx.accounts.tournament;
+    require!(
+        tournament.status == TournamentStatus::InProgress,
+        TournamentError::NotInProgress
+    );
+    let reported = tournament
+        .matches
+        .get(match_index as usize)
+        .ok_or(TournamentError::InvalidMatch)?;
+    require!(
+        reported.winner == NONE && reported.players.iter().all(|&p| p != NONE),
+        TournamentError::MatchNotReady
+    );
+    require_gt!(2, winner, TournamentError::InvalidWinner);
+    require!(
+        *ctx.accounts.player_one.key == tournament.entrants[reported.players[0] as usize]
+            && *ctx.accounts.player_two.key == tournament.entrants[reported.players[1] as usize],
+        TournamentError::PlayerMismatch
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        init,
+        seeds = [b"config"],
+        bump,
+        space = TournamentConfig::SPACE,
+        payer = admin,
+    )]
+    pub config: Account<'info, TournamentConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, TournamentConfig>,
+}
+
+#[derive(Accounts)]
+#[instruction(tournament_id: u64)]
+pub struct CreateTournament<'info> {
+    #[account(mut)]
+    pub organizer: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, TournamentConfig>,
+    #[account(
+        init,
+        seeds = [b"tournament", organizer.key.as_ref(), &tournament_id.to_le_bytes()],
+        bump,
+        space = Tournament::SPACE,
+        payer = organizer,
+    )]
+    pub tournament: Account<'info, Tournament>,
+    /// Lamport escrow owned by the system program, verified by seeds
+    #[account(seeds = [b"vault", tournament.key().as_ref()], bump)]
+    pub vault: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Register<'info> {
+    #[account(mut)]
+    pub player: Signer<'info>,
+    #[account(mut)]
+    pub tournament: Account<'info, Tournament>,
+    /// Lamport escrow owned by the system program, verified by seeds
+    #[account(
+        mut,
+        seeds = [b"vault", tournament.key().as_ref()],
+        bump = tournament.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    /// CHECK: the player's `Referral` PDA, verified by seeds. When it
+    /// exists, the `ReferralConfig` and the referrer's `ReferralCode`
+    /// follow as remaining accounts
+    #[account(seeds = [b"referral", player.key.as_ref()], bump, seeds::program = referral::ID)]
+    pub referral: UncheckedAccount<'info>,
+    pub referral_program: Program<'info, Referral>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReportResult<'info> {
+    /// Receives the organizer fee on the final
+    #[account(mut)]
+    pub organizer: Signer<'info>,
+    #[account(mut, has_one = organizer)]
+    pub tournament: Account<'info, Tournament>,
+    /// Lamport escrow owned by the system program, verified by seeds
+    #[account(
+        mut,
+        seeds = [b"vault", tournament.key().as_ref()],
+        bump = tournament.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    /// Checked against the bracket
+    pub player_one: SystemAccount<'info>,
+    /// Checked against the bracket
+    pub player_two: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CrankSwiss<'info> {
+    #[account(mut)]
+    pub tournament: Account<'info, Tournament>,
+}
+
+#[derive(Accounts)]
+pub struct FinishSwiss<'info> {
+    #[account(mut)]
+    pub tournament: Account<'info, Tournament>,
+    /// Lamport escrow owned by the system program, verified by seeds
+    #[account(
+        mut,
+        seeds = [b"vault", tournament.key().as_ref()],
+        bump = tournament.vault_bump,
+    )]
+    pub vault: SystemAccount<'info>,
+    /// Receives the organizer fee
+    #[account(mut, address = tournament.organizer)]
+    pub organizer: SystemAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AwardChampionBadge<'info> {
+    pub tournament: Account<'info, Tournament>,
+    /// The recorded champion
+    #[account(address = tournament.champion)]
+    pub champion: SystemAccount<'info>,
+    /// CHECK: signing PDA for `achievements::award_badge`
+    #[account(seeds = [b"achievement_issuer"], bump)]
+    pub issuer: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [b"achievement", &achievements::FIRST_TOURNAMENT_WIN.to_le_bytes()],
+        bump = achievement.bump,
+        seeds::program = achievements::ID,
+    )]
+    pub achievement: Account<'info, Achievement>,
+    /// CHECK: created by the achievements program
+    #[account(mut)]
+    pub badge: UncheckedAccount<'info>,
+    /// CHECK: the player's profile, checked by `achievements::award_badge`
+    #[account(mut)]
+    pub profile: UncheckedAccount<'info>,
+    /// CHECK: checked by `achievements::award_badge`
+    pub profile_config: UncheckedAccount<'info>,
+    /// CHECK: the achievements program's signing PDA for
+    /// `profile::record_badge`, checked by `achievements::award_badge`
+    pub profile_writer: UncheckedAccount<'info>,
+    pub profile_program: Program<'info, Profile>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub achievements_program: Program<'info, Achievements>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct Tournament {
+    pub layout_version: u8,
+    pub organizer: Pubkey,
+    pub tournament_id: u64,
+    pub format: BracketFormat,
+    pub status: TournamentStatus,
+    pub entry_fee: u64,
+    pub max_entrants: u8,
+    /// Second place's share, as in `prize_split`; kept from the first
+    /// layout.
+    pub runner_up_bps: u16,
+    pub prize_pool: u64,
+    pub entrants: Vec<Pubkey>,
+    pub matches: Vec<BracketMatch>,
+    pub champion: Pubkey,
+    pub bump: u8,
+    pub vault_bump: u8,
+    /// Swiss rounds paired so far.
+    pub swiss_round: u8,
+    /// Swiss rounds the tournament plays; unused by brackets.
+    pub swiss_rounds: u8,
+    /// Swiss scores by entrant index, filled once registration closes.
+    pub swiss: Vec<SwissRecord>,
+    /// Organizer's cut of the pool, taken before the prizes.
+    pub organizer_fee_bps: u16,
+    /// Share of the prizes by place, first place first.
+    pub prize_split: Vec<u16>,
+}
+
+impl Tournament {
+    pub const MAX_ENTRANTS: usize = 16;
+    pub const MAX_MATCHES: usize = 2 * Self::MAX_ENTRANTS - 2;
+    pub const MAX_SWISS_ROUNDS: usize = 4;
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // organizer
+        + 8 // tournament_id
+        + 1 // format
+        + 1 // status
+        + 8 // entry_fee
+        + 1 // max_entrants
+        + 2 // runner_up_bps
+        + 8 // prize_pool
+        + 4 + 32 * Self::MAX_ENTRANTS // entrants
+        + 4 + BracketMatch::SIZE * Self::MAX_MATCHES // matches
+        + 32 // champion
+        + 1 // bump
+        + 1 // vault_bump
+        + 1 // swiss_round
+        + 1 // swiss_rounds
+        + 4 + SwissRecord::SIZE * Self::MAX_ENTRANTS // swiss
+        + 2 // organizer_fee_bps
+        + 4 + 2 * MAX_PRIZE_PLACES; // prize_split
+
+    fn place(&mut self, to: u8, slot: u8, seed: u8) {
+        if to != NONE {
+            self.matches[to as usize].players[slot as usize] = seed;
+        }
+    }
+}
+
+impl Versioned for Tournament {
+    const LAYOUT_VERSION: u8 = 3;
+    const MIGRATIONS: &'static [Step] = &[
+        ADD_LAYOUT_VERSION,
+        Step {
+            from: 1,
+            grow: 1 + 1 + 4 + SwissRecord::SIZE * Tournament::MAX_ENTRANTS,
+            upgrade: append_swiss,
+        },
+        Step {
+            from: 2,
+            grow: 2 + 4 + 2 * MAX_PRIZE_PLACES,
+            upgrade: append_prize_split,
+        },
+    ];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+/// v1 -> v2: appends `swiss_round`, `swiss_rounds` and an empty `swiss`,
+/// which zeroed space already is.
+fn append_swiss(_data: &mut [u8]) {}
+
+/// v2 -> v3: appends `organizer_fee_bps`, left at zero, and a
+/// `prize_split` carrying over the champion and runner-up shares of
+/// `runner_up_bps`. The vectors before it are walked to find where the
+/// serialized data ends.
+fn append_prize_split(data: &mut [u8]) {
+    let len_at = |data: &[u8], at: usize| {
+        u32::from_le_bytes(data[at..at + 4].try_into().unwrap()) as usize
+    };
+    let runner_up_at = 8 + 1 + 32 + 8 + 1 + 1 + 8 + 1;
+    let runner_up_bps = u16::from_le_bytes([data[runner_up_at], data[runner_up_at + 1]]);
+    let mut at = runner_up_at + 2 + 8;
+    at += 4 + 32 * len_at(data, at); // entrants
+    at += 4 + BracketMatch::SIZE * len_at(data, at); // matches
+    at += 32 + 1 + 1 + 1 + 1; // champion to swiss_rounds
+    at += 4 + SwissRecord::SIZE * len_at(data, at); // swiss
+    at += 2; // organizer_fee_bps
+
+    let split = [BPS_DENOMINATOR as u16 - runner_up_bps, runner_up_bps];
+    let places = if runner_up_bps == 0 { 1 } else { 2 };
+    data[at..at + 4].copy_from_slice(&(places as u32).to_le_bytes());
+    for (i, share) in split[..places].iter().enumerate() {
+        let share_at = at + 4 + 2 * i;
+        data[share_at..share_at + 2].copy_from_slice(&share.to_le_bytes());
+    }
+}
+
+/// Program-wide limits set by the admin.
+#[account]
+pub struct TournamentConfig {
+    pub layout_version: u8,
+    pub admin: Pubkey,
+    /// Highest `organizer_fee_bps` a new tournament may set.
+    pub max_organizer_fee_bps: u16,
+    pub bump: u8,
+}
+
+impl TournamentConfig {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 32 // admin
+        + 2 // max_organizer_fee_bps
+        + 1; // bump
+}
+
+impl Versioned for TournamentConfig {
+    const LAYOUT_VERSION: u8 = 1;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwissRecord {
+    /// Wins plus the bye.
+    pub points: u8,
+    /// Entrant index met each round; `NONE` for a bye or a round not yet
+    /// paired.
+    pub opponents: [u8; Tournament::MAX_SWISS_ROUNDS],
+    pub had_bye: bool,
+}
+
+impl SwissRecord {
+    pub const SIZE: usize = 1 + Tournament::MAX_SWISS_ROUNDS + 1;
+    pub const NEW: SwissRecord = SwissRecord {
+        points: 0,
+        opponents: [NONE; Tournament::MAX_SWISS_ROUNDS],
+        had_bye: false,
+    };
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BracketMatch {
+    /// Entrant indices, `NONE` until decided by an earlier match.
+    pub players: [u8; 2],
+    /// Winning slot, `NONE` until reported.
+    pub winner: u8,
+    pub winner_to: u8,
+    pub winner_slot: u8,
+    /// `NONE` when losing eliminates.
+    pub loser_to: u8,
+    pub loser_slot: u8,
+}
+
+impl BracketMatch {
+    pub const SIZE: usize = 7;
+    pub const PENDING: BracketMatch = BracketMatch {
+        players: [NONE; 2],
+        winner: NONE,
+        winner_to: NONE,
+        winner_slot: 0,
+        loser_to: NONE,
+        loser_slot: 0,
+    };
+
+    fn winner_seed(&self) -> u8 {
+        self.players[self.winner as usize]
+    }
+
+    fn loser_seed(&self) -> u8 {
+        self.players[1 - self.winner as usize]
+    }
+
+    fn wire(&mut self, winner: Option<(usize, usize)>, loser: Option<(usize, usize)>) {
+        if let Some((to, slot)) = winner {
+            self.winner_to = to as u8;
+            self.winner_slot = slot as u8;
+        }
+        if let Some((to, slot)) = loser {
+            self.loser_to = to as u8;
+            self.loser_slot = slot as u8;
+        }
+    }
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BracketFormat {
+    SingleElimination,
+    DoubleElimination,
+    /// Rounds of score-based pairings instead of a bracket.
+    Swiss,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TournamentStatus {
+    Registration,
+    InProgress,
+    Complete,
+}
+
+#[event]
+pub struct TournamentCreated {
+    pub tournament: Pubkey,
+    pub organizer: Pubkey,
+    pub format: BracketFormat,
+    pub entry_fee: u64,
+    pub max_entrants: u8,
+}
+
+#[event]
+pub struct PlayerRegistered {
+    pub tournament: Pubkey,
+    pub player: Pubkey,
+    pub seed: u8,
+}
+
+#[event]
+pub struct BracketMatchReported {
+    pub tournament: Pubkey,
+    pub match_index: u8,
+    pub winner: Pubkey,
+    pub loser: Pubkey,
+}
+
+#[event]
+pub struct SwissRoundPaired {
+    pub tournament: Pubkey,
+    /// From 1.
+    pub round: u8,
+    pub pairs: Vec<[Pubkey; 2]>,
+    pub bye: Option<Pubkey>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Payout {
+    pub player: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TournamentCompleted {
+    pub tournament: Pubkey,
+    pub champion: Pubkey,
+    /// Prizes in place order.
+    pub payouts: Vec<Payout>,
+    pub organizer_fee: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum TournamentError {
+    #[msg("Entrant count must be between 2 and 16, and a power of two for brackets")]
+    InvalidEntrantCount,
+    #[msg("Entry fee is below the vault's rent-exempt minimum")]
+    EntryFeeTooLow,
+    #[msg("Referred player must pass the referral config and code")]
+    MissingReferralAccounts,
+    #[msg("Prize split must sum to 100% over places the format decides, at most MAX_PRIZE_PLACES")]
+    InvalidPrizeSplit,
+    #[msg("Fee must be at most 100%")]
+    InvalidFee,
+    #[msg("Organizer fee exceeds the configured cap")]
+    OrganizerFeeTooHigh,
+    #[msg("Registration is closed")]
+    RegistrationClosed,
+    #[msg("Player is already registered")]
+    AlreadyRegistered,
+    #[msg("Tournament is not in progress")]
+    NotInProgress,
+    #[msg("No such bracket match")]
+    InvalidMatch,
+    #[msg("Bracket match is not ready to be reported")]
+    MatchNotReady,
+    #[msg("Winner must be 0 or 1")]
+    InvalidWinner,
+    #[msg("Player accounts do not match the bracket")]
+    PlayerMismatch,
+    #[msg("Tournament is not complete")]
+    NotComplete,
+    #[msg("Tournament is not Swiss")]
+    NotSwiss,
+    #[msg("Current Swiss round still has unreported matches")]
+    RoundInProgress,
+    #[msg("Every Swiss round has been paired")]
+    SwissRoundsPlayed,
+    #[msg("Arithmetic overflow")]
+    Overflow,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<Overflow> for TournamentError {
+    fn from(_: Overflow) -> Self {
+        TournamentError::Overflow
+    }
+}
+
+impl From<MigrationError> for TournamentError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => TournamentError::AlreadyMigrated,
+            _ => TournamentError::UnsupportedLayout,
+        }
+    }
+}
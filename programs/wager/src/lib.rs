@@ -0,0 +1,685 @@
+// Rust Anchor Program for Match Wagers
+// Deploy with: anchor deploy --provider.cluster devnet
+
+use account_migration::{ADD_LAYOUT_VERSION, Layout, MigrationError, Step, Versioned};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use match_result::MatchAccount;
+
+
+declare_id!("WAGER_PROGRAM_ID_HERE");
+
+/// Seconds the opponent has to match the stake before it can be refunded.
+pub const DEPOSIT_TIMEOUT: i64 = 10 * 60;
+/// Seconds after creation before a matched wager without a finalized result
+/// can be refunded. Longer than the match result program's stale timeout,
+/// so an abandoned set is finalized, and its wager settled, first.
+pub const RESULT_TIMEOUT: i64 = 2 * 24 * 60 * 60;
+/// Lamports of the escrow's rent paid to whoever cranks `finalize_if_expired`.
+pub const CRANK_REWARD: u64 = 5_000;
+
+#[program]
+pub mod wager {
+    use super::*;
+
+    /// Opens a wager on `match_id` against `opponent` and locks the
+    /// creator's stake in the escrow. The stake is what the escrow received,
+    /// after any transfer fee.
+    #[access_control(validate_wager(&ctx, &opponent, amount))]
+    pub fn create_wager(
+        ctx: Context<CreateWager>,
+        match_id: u64,
+        opponent: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        let stake = token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.creator_token_account,
+            &ctx.accounts.escrow,
+            &ctx.accounts.mint,
+            &ctx.accounts.creator,
+            &[],
+            amount,
+        )?;
+        require_gt!(stake, 0, WagerError::InvalidAmount);
+
+        let wager = &mut ctx.accounts.wager;
+        wager.layout_version = Wager::LAYOUT_VERSION;
+        wager.match_id = match_id;
+        wager.players = [*ctx.accounts.creator.key, opponent];
+        wager.mint = ctx.accounts.mint.key();
+        wager.amount = stake;
+        wager.status = WagerStatus::Open;
+        wager.created_at = Clock::get()?.unix_timestamp;
+        wager.bump = ctx.bumps.wager;
+        wager.escrow_bump = ctx.bumps.escrow;
+
+        emit!(WagerCreated {
+            wager: wager.key(),
+            match_id,
+            creator: wager.players[0],
+            opponent,
+            mint: wager.mint,
+            amount: stake,
+        });
+
+        Ok(())
+    }
+
+    /// Opponent matches the stake before the deposit timeout, sending enough
+    /// that the same amount arrives after any transfer fee.
+    #[access_control(validate_accept(&ctx))]
+    pub fn accept_wager(ctx: Context<AcceptWager>) -> Result<()> {
+        let amount = token_compat::gross_amount(&ctx.accounts.mint, ctx.accounts.wager.amount)?;
+        token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.opponent_token_account,
+            &ctx.accounts.escrow,
+            &ctx.accounts.mint,
+            &ctx.accounts.opponent,
+            &[],
+            amount,
+        )?;
+
+        let wager = &mut ctx.accounts.wager;
+        wager.status = WagerStatus::Matched;
+
+        emit!(WagerAccepted {
+            wager: wager.key(),
+            match_id: wager.match_id,
+            opponent: wager.players[1],
+        });
+
+        Ok(())
+    }
+
+    /// Pays the whole pot to the winner of the finalized match.
+    #[access_control(validate_claim(&ctx))]
+    pub fn claim_pot(ctx: Context<ClaimPot>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        let pot = ctx.accounts.escrow.amount;
+        let match_id = wager.match_id.to_le_bytes();
+        let signer_seeds: &[&[u8]] = &[
+            b"wager",
+            &match_id,
+            wager.players[0].as_ref(),
+            wager.players[1].as_ref(),
+            &[wager.bump],
+        ];
+
+        token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.winner_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.wager.to_account_info(),
+            &[signer_seeds],
+            pot,
+        )?;
+        token_compat::close(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.mint,
+            &ctx.accounts.creator,
+            &ctx.accounts.wager.to_account_info(),
+            &[signer_seeds],
+        )?;
+
+        let wager = &mut ctx.accounts.wager;
+        wager.status = WagerStatus::Settled;
+
+        emit!(WagerSettled {
+            wager: wager.key(),
+            match_id: wager.match_id,
+            winner: *ctx.accounts.winner.key,
+            pot,
+        });
+
+        Ok(())
+    }
+
+    /// Refunds the creator when the opponent never deposited. Either player
+    /// may call it once the deposit timeout has passed.
+    #[access_control(validate_cancel(&ctx))]
+    pub fn cancel_wager(ctx: Context<CancelWager>) -> Result<()> {
+        let wager = &ctx.accounts.wager;
+        let refund = ctx.accounts.escrow.amount;
+        let match_id = wager.match_id.to_le_bytes();
+        let signer_seeds: &[&[u8]] = &[
+            b"wager",
+            &match_id,
+            wager.players[0].as_ref(),
+            wager.players[1].as_ref(),
+            &[wager.bump],
+        ];
+
+        token_compat::transfer(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.creator_token_account,
+            &ctx.accounts.mint,
+            &ctx.accounts.wager.to_account_info(),
+            &[signer_seeds],
+            refund,
+        )?;
+        token_compat::close(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.mint,
+            &ctx.accounts.creator,
+            &ctx.accounts.wager.to_account_info(),
+            &[signer_seeds],
+        )?;
+
+        let wager = &mut ctx.accounts.wager;
+        wager.status = WagerStatus::Cancelled;
+
+        emit!(WagerCancelled {
+            wager: wager.key(),
+            match_id: wager.match_id,
+            refund,
+        });
+
+        Ok(())
+    }
+
+    /// Settles a wager neither player is around to settle. A finalized
+    /// result between the wager's players pays the pot to the winner, an
+    /// unaccepted wager past the deposit timeout refunds the creator, and a
+    /// matched wager still without such a result after `RESULT_TIMEOUT`
+    /// refunds both stakes. A match ID recorded for other players counts as
+    /// no result, so it can't hold the stakes forever. Takes
+    /// no signature, so it can run from any crank or a Clockwork thread
+    /// triggered at the timeout; `caller` gets `CRANK_REWARD` out of the
+    /// escrow's rent and the creator the rest.
+    pub fn finalize_if_expired(ctx: Context<FinalizeIfExpired>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let wager = &ctx.accounts.wager;
+        let pot = ctx.accounts.escrow.amount;
+        let match_id = wager.match_id.to_le_bytes();
+        let signer_seeds: &[&[u8]] = &[
+            b"wager",
+            &match_id,
+            wager.players[0].as_ref(),
+            wager.players[1].as_ref(),
+            &[wager.bump],
+        ];
+        let accounts = &ctx.accounts;
+
+        let status = match wager.status {
+            WagerStatus::Open => {
+                require_gte!(now, wager.created_at + DEPOSIT_TIMEOUT, WagerError::TimeoutNotReached);
+                release_escrow(accounts, &accounts.creator_token_account, signer_seeds, pot)?;
+                emit!(WagerCancelled {
+                    wager: wager.key(),
+                    match_id: wager.match_id,
+                    refund: pot,
+                });
+                WagerStatus::Cancelled
+            }
+            WagerStatus::Matched => match finalized_result(&accounts.match_account)?
+                .filter(|result| played_by(result, wager))
+            {
+                Some(result) => {
+                    let winner = result.winner_key();
+                    if winner == wager.players[0] {
+                        release_escrow(accounts, &accounts.creator_token_account, signer_seeds, pot)?;
+                    } else {
+                        release_escrow(accounts, &accounts.opponent_token_account, signer_seeds, pot)?;
+                    }
+                    emit!(WagerSettled {
+                        wager: wager.key(),
+                        match_id: wager.match_id,
+                        winner,
+                        pot,
+                    });
+                    WagerStatus::Settled
+                }
+                None => {
+                    require_gte!(now, wager.created_at + RESULT_TIMEOUT, WagerError::TimeoutNotReached);
+                    let refund = pot / 2;
+                    release_escrow(accounts, &accounts.creator_token_account, signer_seeds, pot - refund)?;
+                    release_escrow(accounts, &accounts.opponent_token_account, signer_seeds, refund)?;
+                    emit!(WagerCancelled {
+                        wager: wager.key(),
+                        match_id: wager.match_id,
+                        refund: pot,
+                    });
+                    WagerStatus::Cancelled
+                }
+            },
+            _ => return err!(WagerError::AlreadySettled),
+        };
+
+        let wager_info = ctx.accounts.wager.to_account_info();
+        token_compat::close(
+            &ctx.accounts.token_program,
+            &ctx.accounts.escrow,
+            &ctx.accounts.mint,
+            &wager_info,
+            &wager_info,
+            &[signer_seeds],
+        )?;
+        let reclaimed = wager_info
+            .lamports()
+            .saturating_sub(Rent::get()?.minimum_balance(wager_info.data_len()));
+        let reward = reclaimed.min(CRANK_REWARD);
+        **wager_info.try_borrow_mut_lamports()? -= reclaimed;
+        **ctx.accounts.caller.try_borrow_mut_lamports()? += reward;
+        **ctx.accounts.creator.try_borrow_mut_lamports()? += reclaimed - reward;
+
+        ctx.accounts.wager.status = status;
+        Ok(())
+    }
+
+    /// Brings one of this program's accounts up to the current layout of its
+    /// type. Anyone can call it; `payer` covers the rent for any space the
+    /// new layout adds.
+    pub fn migrate_account(ctx: Context<MigrateAccount>) -> Result<()> {
+        let layouts = [Layout::new::<Wager>(Wager::discriminator())];
+        let accounts = &ctx.accounts;
+        let (from, to) = account_migration::migrate::<WagerError>(
+            &accounts.account,
+            &accounts.payer,
+            &accounts.system_program,
+            &layouts,
+        )?;
+        emit!(AccountMigrated {
+            account: accounts.account.key(),
+            from,
+            to,
+        });
+        Ok(())
+    }
+}
+
+fn release_escrow<'info>(
+    accounts: &FinalizeIfExpired<'info>,
+    to: &InterfaceAccount<'info, TokenAccount>,
+    signer_seeds: &[&[u8]],
+    amount: u64,
+) -> Result<u64> {
+    token_compat::transfer(
+        &accounts.token_program,
+        &accounts.escrow,
+        to,
+        &accounts.mint,
+        &accounts.wager.to_account_info(),
+        &[signer_seeds],
+        amount,
+    )
+}
+
+/// The wagered match's record, if its result is final. A match account that
+/// never got created, or holds a set still being played, has none.
+fn finalized_result(info: &UncheckedAccount) -> Result<Option<MatchAccount>> {
+    if info.owner != &match_result::ID {
+        return Ok(None);
+    }
+    let record = MatchAccount::try_deserialize(&mut &info.data.borrow()[..])?;
+    Ok(if record.finalized { Some(record) } else { None })
+}
+
+/// Whether the match was between the wager's two players.
+fn played_by(result: &MatchAccount, wager: &Wager) -> bool {
+    result.players.contains(&wager.players[0]) && result.players.contains(&wager.players[1])
+}
+
+fn validate_wager(ctx: &Context<CreateWager>, opponent: &Pubkey, amount: u64) -> Result<()> {
+    require!(ctx.accounts.creator.key != opponent, WagerError::InvalidOpponent);
+    require_gt!(amount, 0, WagerError::InvalidAmount);
+    require!(
+        token_compat::supported_mint(&ctx.accounts.mint)?,
+        WagerError::UnsupportedMint
+    );
+    Ok(())
+}
+
+fn validate_accept(ctx: &Context<AcceptWager>) -> Result<()> {
+    let wager = &ctx.accounts.wager;
+    require!(wager.status == WagerStatus::Open, WagerError::NotOpen);
+    require_keys_eq!(*ctx.accounts.opponent.key, wager.players[1], WagerError::Unauthorized);
+    require!(
+        Clock::get()?.unix_timestamp < wager.created_at + DEPOSIT_TIMEOUT,
+        WagerError::DepositExpired
+    );
+    Ok(())
+}
+
+fn validate_claim(ctx: &Context<ClaimPot>) -> Result<()> {
+    let wager = &ctx.accounts.wager;
+    let result = &ctx.accounts.match_account;
+    require!(wager.status == WagerStatus::Matched, WagerError::NotMatched);
+    require!(result.finalized, WagerError::ResultNotFinal);
+    require!(played_by(result, wager), WagerError::PlayerMismatch);
+    require_keys_eq!(result.winner_key(), *ctx.accounts.winner.key, WagerError::NotWinner);
+    Ok(())
+}
+
+fn validate_cancel(ctx: &Context<CancelWager>) -> Result<()> {
+    let wager = &ctx.accounts.wager;
+    require!(wager.status == WagerStatus::Open, WagerError::NotOpen);
+    require!(
+        wager.players.contains(ctx.accounts.player.key),
+        WagerError::Unauthorized
+    );
+    require!(
+        Clock::get()?.unix_timestamp >= wager.created_at + DEPOSIT_TIMEOUT,
+        WagerError::TimeoutNotReached
+    );
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(match_id: u64, opponent: Pubkey)]
+pub struct CreateWager<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    /// Keyed by both players as well as the match, so nobody can take a
+    /// match's wager address before the players open theirs.
+    #[account(
+        init,
+        seeds = [
+            b"wager".as_ref(),
+            &match_id.to_le_bytes(),
+            creator.key().as_ref(),
+            opponent.as_ref(),
+        ],
+        bump,
+        space = Wager::SPACE,
+        payer = creator,
+    )]
+    pub wager: Account<'info, Wager>,
+    #[account(
+        init,
+        seeds = [b"escrow", wager.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = wager,
+        token::token_program = token_program,
+        payer = creator,
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint, token::authority = creator)]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptWager<'info> {
+    pub opponent: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"wager",
+            &wager.match_id.to_le_bytes(),
+            wager.players[0].as_ref(),
+            wager.players[1].as_ref(),
+        ],
+        bump = wager.bump,
+    )]
+    pub wager: Account<'info, Wager>,
+    #[account(
+        mut,
+        seeds = [b"escrow", wager.key().as_ref()],
+        bump = wager.escrow_bump,
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    #[account(address = wager.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = wager.mint, token::authority = opponent)]
+    pub opponent_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimPot<'info> {
+    pub winner: Signer<'info>,
+    /// Receives the escrow's rent, checked against the wager
+    #[account(mut, address = wager.players[0])]
+    pub creator: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"wager",
+            &wager.match_id.to_le_bytes(),
+            wager.players[0].as_ref(),
+            wager.players[1].as_ref(),
+        ],
+        bump = wager.bump,
+    )]
+    pub wager: Account<'info, Wager>,
+    #[account(
+        mut,
+        seeds = [b"escrow", wager.key().as_ref()],
+        bump = wager.escrow_bump,
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        seeds = [b"match", &wager.match_id.to_le_bytes()],
+        bump = match_account.bump,
+        seeds::program = match_result::ID,
+    )]
+    pub match_account: Account<'info, MatchAccount>,
+    /// Writable so fees withheld in the escrow can be harvested to it
+    #[account(mut, address = wager.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = wager.mint, token::authority = winner)]
+    pub winner_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CancelWager<'info> {
+    pub player: Signer<'info>,
+    /// Receives the refund and the escrow's rent, checked against the wager
+    #[account(mut, address = wager.players[0])]
+    pub creator: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"wager",
+            &wager.match_id.to_le_bytes(),
+            wager.players[0].as_ref(),
+            wager.players[1].as_ref(),
+        ],
+        bump = wager.bump,
+    )]
+    pub wager: Account<'info, Wager>,
+    #[account(
+        mut,
+        seeds = [b"escrow", wager.key().as_ref()],
+        bump = wager.escrow_bump,
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    /// Writable so fees withheld in the escrow can be harvested to it
+    #[account(mut, address = wager.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = wager.mint, token::authority = creator)]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeIfExpired<'info> {
+    /// Collects `CRANK_REWARD`
+    #[account(mut)]
+    pub caller: SystemAccount<'info>,
+    /// Receives the rest of the escrow's rent, checked against the wager
+    #[account(mut, address = wager.players[0])]
+    pub creator: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            b"wager",
+            &wager.match_id.to_le_bytes(),
+            wager.players[0].as_ref(),
+            wager.players[1].as_ref(),
+        ],
+        bump = wager.bump,
+    )]
+    pub wager: Account<'info, Wager>,
+    #[account(
+        mut,
+        seeds = [b"escrow", wager.key().as_ref()],
+        bump = wager.escrow_bump,
+    )]
+    pub escrow: InterfaceAccount<'info, TokenAccount>,
+    /// CHECK: the match PDA, verified by seeds; only read when the match
+    /// result program owns it, i.e. the match was recorded
+    #[account(
+        seeds = [b"match", &wager.match_id.to_le_bytes()],
+        bump,
+        seeds::program = match_result::ID,
+    )]
+    pub match_account: UncheckedAccount<'info>,
+    /// Writable so fees withheld in the escrow can be harvested to it
+    #[account(mut, address = wager.mint)]
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = wager.mint, token::authority = wager.players[0])]
+    pub creator_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, token::mint = wager.mint, token::authority = wager.players[1])]
+    pub opponent_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: any account owned by this program; `migrate_account` checks its
+    /// discriminator and layout version
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[account]
+pub struct Wager {
+    pub layout_version: u8,
+    pub match_id: u64,
+    /// Creator first, opponent second.
+    pub players: [Pubkey; 2],
+    pub mint: Pubkey,
+    /// Stake per player, as the escrow received it. The opponent's deposit
+    /// is grossed up so it arrives at the same amount; rounding in the fee
+    /// can leave the pot a token or so over twice this.
+    pub amount: u64,
+    pub status: WagerStatus,
+    pub created_at: i64,
+    pub bump: u8,
+    pub escrow_bump: u8,
+}
+
+impl Wager {
+    pub const SPACE: usize = 8 // discriminator
+        + 1 // layout_version
+        + 8 // match_id
+        + 32 * 2 // players
+        + 32 // mint
+        + 8 // amount
+        + 1 // status
+        + 8 // created_at
+        + 1 // bump
+        + 1; // escrow_bump
+}
+
+impl Versioned for Wager {
+    const LAYOUT_VERSION: u8 = 1;
+    const MIGRATIONS: &'static [Step] = &[ADD_LAYOUT_VERSION];
+    const FIXED_SPACE: Option<usize> = Some(Self::SPACE);
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WagerStatus {
+    /// Only the creator has deposited.
+    Open,
+    /// Both stakes are in escrow.
+    Matched,
+    Settled,
+    Cancelled,
+}
+
+#[event]
+pub struct WagerCreated {
+    pub wager: Pubkey,
+    pub match_id: u64,
+    pub creator: Pubkey,
+    pub opponent: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct WagerAccepted {
+    pub wager: Pubkey,
+    pub match_id: u64,
+    pub opponent: Pubkey,
+}
+
+#[event]
+pub struct WagerSettled {
+    pub wager: Pubkey,
+    pub match_id: u64,
+    pub winner: Pubkey,
+    pub pot: u64,
+}
+
+#[event]
+pub struct WagerCancelled {
+    pub wager: Pubkey,
+    pub match_id: u64,
+    pub refund: u64,
+}
+
+#[event]
+pub struct AccountMigrated {
+    pub account: Pubkey,
+    pub from: u8,
+    pub to: u8,
+}
+
+#[error_code]
+pub enum WagerError {
+    #[msg("Cannot wager against yourself")]
+    InvalidOpponent,
+    #[msg("Wager amount must be positive")]
+    InvalidAmount,
+    #[msg("Wager is not open")]
+    NotOpen,
+    #[msg("Wager has not been matched")]
+    NotMatched,
+    #[msg("Signer is not part of this wager")]
+    Unauthorized,
+    #[msg("Deposit window has expired")]
+    DepositExpired,
+    #[msg("Deposit window has not expired yet")]
+    TimeoutNotReached,
+    #[msg("Match result is not finalized")]
+    ResultNotFinal,
+    #[msg("Match players do not match the wager")]
+    PlayerMismatch,
+    #[msg("Only the match winner can claim the pot")]
+    NotWinner,
+    #[msg("Mint has a token extension wagers can't support")]
+    UnsupportedMint,
+    #[msg("Wager is already settled or cancelled")]
+    AlreadySettled,
+    #[msg("Account is already on the current layout")]
+    AlreadyMigrated,
+    #[msg("Account layout cannot be migrated")]
+    UnsupportedLayout,
+}
+
+impl From<MigrationError> for WagerError {
+    fn from(error: MigrationError) -> Self {
+        match error {
+            MigrationError::UpToDate => WagerError::AlreadyMigrated,
+            _ => WagerError::UnsupportedLayout,
+        }
+    }
+}